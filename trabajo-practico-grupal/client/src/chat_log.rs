@@ -0,0 +1,142 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use model::client_errors::ClientError;
+use model::consts::is_channel_name;
+use model::ctcp::{current_timestamp, format_timestamp};
+
+/// The directory where the logs of the conversations are saved.
+pub const LOG_DIRECTORY: &str = "client/logs";
+/// The lines of each conversation reloaded when the client starts.
+pub const RELOADED_LINES: usize = 50;
+/// The extension of the log files.
+const LOG_EXTENSION: &str = "log";
+
+/// Struct that represents the logs of the conversations of the client, with a file for each
+/// channel or user in the log directory. Each line of a log has the time, the sender and the text
+/// of a message, like `[2023-06-01 18:30:00] bob: hello`.
+/// # Fields
+/// * `directory` - The directory of the log files.
+#[derive(Debug, Clone)]
+pub struct ChatLog {
+    directory: PathBuf,
+}
+
+impl ChatLog {
+    /// Creates the logs saved in the directory, which is created with the first message.
+    pub fn new<P: AsRef<Path>>(directory: P) -> Self {
+        Self {
+            directory: directory.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Appends a message to the log of the conversation with the target.
+    /// # Arguments
+    /// * `target` - The channel or user of the conversation.
+    /// * `sender` - The sender of the message.
+    /// * `text` - The text of the message.
+    /// # Errors
+    /// ClientError::FileError if the log can't be written.
+    pub fn append(&self, target: &str, sender: &str, text: &str) -> Result<(), ClientError> {
+        fs::create_dir_all(&self.directory).map_err(|_| ClientError::FileError)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path(target))
+            .map_err(|_| ClientError::FileError)?;
+        let line = format!(
+            "[{}] {}: {}",
+            format_timestamp(current_timestamp()),
+            sender,
+            text.replace('\n', " ")
+        );
+        writeln!(file, "{}", line).map_err(|_| ClientError::FileError)
+    }
+
+    /// Returns the last lines of the log of the conversation with the target, oldest first.
+    /// A conversation without a log has no lines.
+    /// # Arguments
+    /// * `target` - The channel or user of the conversation.
+    /// * `count` - The maximum number of lines returned.
+    pub fn last_lines(&self, target: &str, count: usize) -> Vec<String> {
+        let content = fs::read_to_string(self.path(target)).unwrap_or_default();
+        let lines = content.lines().collect::<Vec<_>>();
+        lines[lines.len().saturating_sub(count)..]
+            .iter()
+            .map(|line| line.to_string())
+            .collect()
+    }
+
+    /// Returns the channels and users with a log, sorted by name.
+    pub fn targets(&self) -> Vec<String> {
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(_) => return vec![],
+        };
+        let mut targets = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == LOG_EXTENSION))
+            .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+            .collect::<Vec<_>>();
+        targets.sort();
+        targets
+    }
+
+    /// Returns the path of the log of the target. The separators of paths are replaced, so a
+    /// target can't write outside the log directory.
+    fn path(&self, target: &str) -> PathBuf {
+        let name = target.replace(['/', '\\'], "_");
+        self.directory.join(format!("{}.{}", name, LOG_EXTENSION))
+    }
+}
+
+/// Splits a line of a log into its time, between brackets, its sender and its text, like
+/// `[2023-06-01 18:30:00]`, `bob` and `hello`, or None if the line doesn't have them.
+pub fn split_line(line: &str) -> Option<(&str, &str, &str)> {
+    let (time, message) = line.split_once("] ")?;
+    let (sender, text) = message.split_once(": ")?;
+    Some((&line[..time.len() + 1], sender, text))
+}
+
+/// Returns true if the target of a conversation is a channel.
+pub fn is_channel(target: &str) -> bool {
+    is_channel_name(target)
+}
+
+#[cfg(test)]
+mod chat_log_tests {
+    use std::fs;
+
+    use super::{is_channel, split_line, ChatLog};
+
+    #[test]
+    fn test_chat_log_appends_messages_and_returns_the_last_lines() {
+        let directory = std::env::temp_dir().join(format!("chat_log_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        let log = ChatLog::new(&directory);
+        assert!(log.targets().is_empty());
+
+        for text in ["one", "two", "three"] {
+            log.append("#channel", "bob", text).unwrap();
+        }
+        log.append("alice", "You", "hi\nthere").unwrap();
+        log.append("../escape", "eve", "hello").unwrap();
+
+        let lines = log.last_lines("#channel", 2);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with('[') && lines[0].ends_with("] bob: two"));
+        assert!(lines[1].ends_with("] bob: three"));
+        let (time, sender, text) = split_line(&lines[1]).unwrap();
+        assert!(time.starts_with('[') && time.ends_with(']'));
+        assert_eq!((sender, text), ("bob", "three"));
+        assert_eq!(split_line("not a line"), None);
+        assert!(log.last_lines("alice", 10)[0].ends_with("] You: hi there"));
+        assert!(log.last_lines("nobody", 10).is_empty());
+        assert_eq!(log.targets(), vec!["#channel", ".._escape", "alice"]);
+        assert!(is_channel("#channel") && is_channel("&local") && !is_channel("alice"));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
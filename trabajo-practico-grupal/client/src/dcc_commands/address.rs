@@ -0,0 +1,50 @@
+use std::net::{IpAddr, Ipv4Addr, TcpListener, UdpSocket};
+
+/// The address used to find the network interface that reaches other hosts. No packet is sent to it,
+/// connecting an UDP socket only chooses the route.
+const ROUTE_PROBE_ADDRESS: &str = "8.8.8.8:80";
+
+/// Returns the ip address of the network interface that reaches other hosts,
+/// or the loopback address if there is no network
+pub fn local_ip() -> IpAddr {
+    UdpSocket::bind("0.0.0.0:0")
+        .and_then(|socket| {
+            socket.connect(ROUTE_PROBE_ADDRESS)?;
+            socket.local_addr()
+        })
+        .map(|address| address.ip())
+        .ok()
+        .filter(|ip| !ip.is_unspecified())
+        .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST))
+}
+
+/// Returns a free port of the ip, chosen by the system when binding to the port 0,
+/// or None if the ip can't be bound
+pub fn free_port(ip: IpAddr) -> Option<u16> {
+    let listener = TcpListener::bind((ip, 0)).ok()?;
+    Some(listener.local_addr().ok()?.port())
+}
+
+/// Returns the ip and the port suggested to open a DCC connection or transfer,
+/// the port is empty if no port is free
+pub fn default_dcc_address() -> (String, String) {
+    let ip = local_ip();
+    let port = free_port(ip).map_or_else(String::new, |port| port.to_string());
+    (ip.to_string(), port)
+}
+
+#[cfg(test)]
+mod dcc_address_test {
+    use std::net::TcpListener;
+
+    use super::{default_dcc_address, free_port, local_ip};
+
+    #[test]
+    fn test_default_address_can_be_bound() {
+        let port = free_port(local_ip()).unwrap();
+        assert_ne!(port, 0);
+
+        let (ip, port) = default_dcc_address();
+        assert!(TcpListener::bind(format!("{ip}:{port}")).is_ok());
+    }
+}
@@ -0,0 +1,156 @@
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+/// Struct that represents a file of a batch sent through a dcc connection
+/// # Fields
+/// * `path`: The path of the file in the current client
+/// * `name`: The path of the file relative to the folder chosen, which the requested client recreates
+/// * `size`: The size of the file in bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchFile {
+    pub path: String,
+    pub name: String,
+    pub size: u64,
+}
+
+impl BatchFile {
+    /// Returns if the file can be sent in a dcc send request, whose parameters are separated by spaces
+    pub fn can_be_sent(&self) -> bool {
+        !self.path.contains(char::is_whitespace) && !self.name.contains(char::is_whitespace)
+    }
+}
+
+/// Returns the files of the paths chosen, in order, to send them one after the other
+/// The files chosen are sent with their names, and the files of the folders chosen and their subfolders
+/// with their paths relative to the parent of the folder, so the requested client recreates the folder
+/// Links and the files that can't be read are left out
+pub fn batch_files(paths: &[PathBuf]) -> Vec<BatchFile> {
+    let mut files = vec![];
+    for path in paths {
+        if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+            add_files(path, name.to_owned(), &mut files);
+        }
+    }
+    files
+}
+
+/// Adds the file of the path, or the files of the folder and its subfolders sorted by name, to the batch
+fn add_files(path: &Path, name: String, files: &mut Vec<BatchFile>) {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return,
+    };
+
+    if metadata.is_file() {
+        if let Some(file_path) = path.to_str() {
+            files.push(BatchFile {
+                path: file_path.to_owned(),
+                name,
+                size: metadata.len(),
+            });
+        }
+    } else if metadata.is_dir() {
+        let mut entries = match fs::read_dir(path) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .collect::<Vec<_>>(),
+            Err(_) => return,
+        };
+        entries.sort();
+        for entry in entries {
+            if let Some(entry_name) = entry.file_name().and_then(|name| name.to_str()) {
+                add_files(&entry, format!("{name}/{entry_name}"), files);
+            }
+        }
+    }
+}
+
+/// Returns the path of a file received with the name sent by the requested client, inside the folder,
+/// or None if the name is not a relative path that stays inside the folder
+pub fn path_inside(folder: &str, file_name: &str) -> Option<PathBuf> {
+    let relative = Path::new(file_name);
+    let inside = relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if file_name.is_empty() || !inside {
+        return None;
+    }
+    Some(Path::new(folder).join(relative))
+}
+
+#[cfg(test)]
+mod dcc_batch_test {
+    use std::{fs, path::Path};
+
+    use super::{batch_files, path_inside, BatchFile};
+
+    #[test]
+    fn test_files_of_a_folder_keep_their_paths_relative_to_it() {
+        let root = std::env::temp_dir().join(format!("dcc_batch_{}", std::process::id()));
+        let folder = root.join("photos");
+        fs::create_dir_all(folder.join("trip")).unwrap();
+        fs::write(folder.join("b.jpg"), "bb").unwrap();
+        fs::write(folder.join("a.jpg"), "a").unwrap();
+        fs::write(folder.join("trip").join("c.jpg"), "ccc").unwrap();
+        fs::write(root.join("notes.txt"), "notes").unwrap();
+
+        let files = batch_files(&[
+            folder.clone(),
+            root.join("notes.txt"),
+            root.join("missing.txt"),
+        ]);
+        let names = files
+            .iter()
+            .map(|file| (file.name.as_str(), file.size))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                ("photos/a.jpg", 1),
+                ("photos/b.jpg", 2),
+                ("photos/trip/c.jpg", 3),
+                ("notes.txt", 5),
+            ]
+        );
+        assert_eq!(
+            Path::new(&files[2].path),
+            folder.join("trip").join("c.jpg").as_path()
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_files_with_spaces_can_not_be_sent() {
+        let file = BatchFile {
+            path: "/home/alice/photos/a.jpg".to_owned(),
+            name: "photos/a.jpg".to_owned(),
+            size: 1,
+        };
+        assert!(file.can_be_sent());
+        let file = BatchFile {
+            name: "photos/my trip.jpg".to_owned(),
+            ..file
+        };
+        assert!(!file.can_be_sent());
+    }
+
+    #[test]
+    fn test_received_files_stay_inside_the_folder() {
+        assert_eq!(
+            path_inside("./received", "photos/trip/c.jpg").unwrap(),
+            Path::new("./received/photos/trip/c.jpg")
+        );
+        assert_eq!(
+            path_inside("./received", "a.jpg").unwrap(),
+            Path::new("./received/a.jpg")
+        );
+        assert!(path_inside("./received", "../a.jpg").is_none());
+        assert!(path_inside("./received", "photos/../../a.jpg").is_none());
+        assert!(path_inside("./received", "/etc/passwd").is_none());
+        assert!(path_inside("./received", "./a.jpg").is_none());
+        assert!(path_inside("./received", "").is_none());
+    }
+}
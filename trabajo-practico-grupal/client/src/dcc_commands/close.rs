@@ -102,28 +102,43 @@ mod dcc_close_test {
     //use client::dcc_commands::{chat::incoming_chat_request, transfer::{receive_file, transfer_file, remove_transfer_communication}, close::{incoming_close_request, outgoing_close_request}};
     use gtk::glib;
     use model::{
+        channel_registry::ChannelRegistry,
+        client_registry::ClientRegistry,
+        command_metrics::CommandMetrics,
+        config::ServerConfig,
         dcc::{DccMessage, DccMessageType},
+        events::EventBus,
+        gc_stats::GcStats,
+        link_queues::LinkQueues,
         message::Message,
         network::Network,
         persistence::PersistenceType,
+        plugins::Plugins,
+        responses::numeric::WireFormat,
         server::Server,
         session::Session,
+        socket_registry::SocketRegistry,
     };
-    use server::{client_handler::register_client, database::handle_database};
+    use server::{client_handler::register_client, database::{handle_database, DatabasePaths}};
 
     use crate::dcc_commands::close::{incoming_close_request, outgoing_close_request};
 
     use super::close_all_dcc_connections;
 
     fn create_session_for_test(tx: Sender<(PersistenceType, String)>) -> Session {
-        let clients = Arc::new(std::sync::RwLock::new(std::collections::HashMap::new()));
-        let sockets = Arc::new(Mutex::new(HashMap::new()));
-        let channels = Arc::new(std::sync::RwLock::new(HashMap::new()));
         Session {
-            clients,
-            sockets,
-            channels,
+            clients: ClientRegistry::default(),
+            sockets: SocketRegistry::default(),
+            connections: Arc::new(Mutex::new(HashMap::new())),
+            rejected_connections: Arc::new(Mutex::new(HashMap::new())),
+            channels: ChannelRegistry::default(),
             database_sender: tx,
+            config: Arc::new(RwLock::new(ServerConfig::default())),
+            events: EventBus::default(),
+            plugins: Plugins::default(),
+            who_queries: Arc::new(Mutex::new(HashMap::new())),
+            command_metrics: CommandMetrics::default(),
+            gc_stats: GcStats::default(),
         }
     }
 
@@ -143,7 +158,7 @@ mod dcc_close_test {
             message_user,
             (&mut nickname, &mut user_parameters),
             &mut password,
-            stream.clone(),
+            (stream.clone(), WireFormat::Custom),
             &session,
             &network,
             &"test".to_string(),
@@ -154,7 +169,7 @@ mod dcc_close_test {
             message_nick,
             (&mut nickname, &mut user_parameters),
             &mut password,
-            stream.clone(),
+            (stream.clone(), WireFormat::Custom),
             &session,
             &network,
             &"test".to_string(),
@@ -168,7 +183,7 @@ mod dcc_close_test {
         let port = listener.local_addr().unwrap().port();
         let address_port = format!("127.0.0.1:{}", port.to_string());
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "127.0.0.1".to_string(),
             port: port.to_string(),
@@ -178,13 +193,14 @@ mod dcc_close_test {
             children: HashMap::new(),
         }));
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let client_stream_sender = Arc::new(TcpStream::connect(address_port.clone()).unwrap());
@@ -248,7 +264,7 @@ mod dcc_close_test {
         let port = listener.local_addr().unwrap().port();
         let address_port = format!("127.0.0.1:{}", port.to_string());
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "127.0.0.1".to_string(),
             port: port.to_string(),
@@ -258,13 +274,14 @@ mod dcc_close_test {
             children: HashMap::new(),
         }));
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let client_stream_sender = Arc::new(TcpStream::connect(address_port.clone()).unwrap());
@@ -0,0 +1,304 @@
+use std::sync::{Arc, Mutex};
+
+/// The states of a dcc connection, from the request until it is closed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DccState {
+    /// The connection was requested, and the requested client didn't answer yet
+    Offered,
+    /// The clients are connected, but the keys are not exchanged yet if the connection is secure
+    Connecting,
+    /// The clients can chat and send files through the connection
+    Established,
+    /// The connection is established and files of its queue are being sent
+    Transferring,
+    /// The connection was closed, rejected or it failed, and it can't be used again
+    Closed,
+}
+
+/// The events that change the state of a dcc connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DccEvent {
+    /// The requested client answered, and the clients are connected
+    Connect,
+    /// The keys were exchanged, or the connection is not secure
+    Establish,
+    /// A file starts being sent
+    StartTransfer,
+    /// A file is no longer sent
+    EndTransfer,
+    /// The connection is closed
+    Close,
+}
+
+/// Error returned when an event can't happen in the state of the connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidTransition {
+    pub state: DccState,
+    pub event: DccEvent,
+}
+
+/// Struct that represents the state of a dcc connection with the requested client
+/// # Fields
+/// * `requested_client`: The client at the other side of the connection
+/// * `state`: The state of the connection
+/// * `transfers`: The files being sent through the connection
+#[derive(Debug)]
+pub struct DccConnection {
+    requested_client: String,
+    state: DccState,
+    transfers: usize,
+}
+
+/// A dcc connection shared by the threads that read and write its socket
+pub type SharedDccConnection = Arc<Mutex<DccConnection>>;
+
+impl DccConnection {
+    /// Creates the connection with the requested client once it is requested, offered to the requested client
+    pub fn new(requested_client: String) -> Self {
+        DccConnection {
+            requested_client,
+            state: DccState::Offered,
+            transfers: 0,
+        }
+    }
+
+    /// Creates the connection with the requested client, shared by the threads that drive it
+    pub fn shared(requested_client: String) -> SharedDccConnection {
+        Arc::new(Mutex::new(DccConnection::new(requested_client)))
+    }
+
+    pub fn requested_client(&self) -> &str {
+        &self.requested_client
+    }
+
+    pub fn state(&self) -> DccState {
+        self.state
+    }
+
+    pub fn transfers(&self) -> usize {
+        self.transfers
+    }
+
+    /// Returns if the clients can chat and send files through the connection
+    pub fn is_established(&self) -> bool {
+        matches!(self.state, DccState::Established | DccState::Transferring)
+    }
+
+    /// The requested client answered the request
+    /// Offered -> Connecting
+    pub fn connect(&mut self) -> Result<DccState, InvalidTransition> {
+        self.handle(DccEvent::Connect)
+    }
+
+    /// The connection can be used, after the keys are exchanged if it is secure
+    /// Connecting -> Established
+    pub fn establish(&mut self) -> Result<DccState, InvalidTransition> {
+        self.handle(DccEvent::Establish)
+    }
+
+    /// A file starts being sent
+    /// Established | Transferring -> Transferring
+    pub fn start_transfer(&mut self) -> Result<DccState, InvalidTransition> {
+        self.handle(DccEvent::StartTransfer)
+    }
+
+    /// A file is no longer sent, and the connection is established again when no file is being sent
+    /// Transferring -> Transferring | Established
+    pub fn end_transfer(&mut self) -> Result<DccState, InvalidTransition> {
+        self.handle(DccEvent::EndTransfer)
+    }
+
+    /// The connection is closed, from any state but Closed
+    /// Offered | Connecting | Established | Transferring -> Closed
+    pub fn close(&mut self) -> Result<DccState, InvalidTransition> {
+        self.handle(DccEvent::Close)
+    }
+
+    /// Changes the state of the connection with the event, and returns the new state
+    /// Returns an InvalidTransition, without changing the state, if the event can't happen in the state
+    pub fn handle(&mut self, event: DccEvent) -> Result<DccState, InvalidTransition> {
+        let state = match (self.state, event) {
+            (DccState::Offered, DccEvent::Connect) => DccState::Connecting,
+            (DccState::Connecting, DccEvent::Establish) => DccState::Established,
+            (DccState::Established | DccState::Transferring, DccEvent::StartTransfer) => {
+                self.transfers += 1;
+                DccState::Transferring
+            }
+            (DccState::Transferring, DccEvent::EndTransfer) => {
+                self.transfers -= 1;
+                if self.transfers == 0 {
+                    DccState::Established
+                } else {
+                    DccState::Transferring
+                }
+            }
+            (state, DccEvent::Close) if state != DccState::Closed => {
+                self.transfers = 0;
+                DccState::Closed
+            }
+            (state, event) => return Err(InvalidTransition { state, event }),
+        };
+        self.state = state;
+        Ok(state)
+    }
+}
+
+/// Changes the state of the shared connection with the event, and returns the new state
+/// Returns None, and logs it, if the event can't happen in the state or the connection can't be locked
+pub fn drive(connection: &SharedDccConnection, event: DccEvent) -> Option<DccState> {
+    let mut connection = match connection.lock() {
+        Ok(connection) => connection,
+        Err(_) => {
+            println!("[ERROR] Error locking the dcc connection");
+            return None;
+        }
+    };
+    match connection.handle(event) {
+        Ok(state) => Some(state),
+        Err(e) => {
+            println!(
+                "[ERROR] Invalid dcc connection event with {}: {e:?}",
+                connection.requested_client()
+            );
+            None
+        }
+    }
+}
+
+/// Returns the state of the shared connection, or Closed if it can't be locked
+pub fn state(connection: &SharedDccConnection) -> DccState {
+    connection
+        .lock()
+        .map_or(DccState::Closed, |connection| connection.state())
+}
+
+/// Closes the shared connection if it is not closed yet, since both of its threads close it when they end
+pub fn close(connection: &SharedDccConnection) {
+    if state(connection) != DccState::Closed {
+        drive(connection, DccEvent::Close);
+    }
+}
+
+#[cfg(test)]
+mod dcc_connection_test {
+    use super::{close, drive, state, DccConnection, DccEvent, DccState, InvalidTransition};
+
+    fn connection_in(target: DccState) -> DccConnection {
+        let mut connection = DccConnection::new("bob".to_owned());
+        let events: &[DccEvent] = match target {
+            DccState::Offered => &[],
+            DccState::Connecting => &[DccEvent::Connect],
+            DccState::Established => &[DccEvent::Connect, DccEvent::Establish],
+            DccState::Transferring => &[
+                DccEvent::Connect,
+                DccEvent::Establish,
+                DccEvent::StartTransfer,
+            ],
+            DccState::Closed => &[DccEvent::Close],
+        };
+        for event in events {
+            connection.handle(*event).unwrap();
+        }
+        assert_eq!(connection.state(), target);
+        connection
+    }
+
+    #[test]
+    fn test_a_connection_is_offered_connected_and_established() {
+        let mut connection = DccConnection::new("bob".to_owned());
+        assert_eq!(connection.requested_client(), "bob");
+        assert_eq!(connection.state(), DccState::Offered);
+        assert!(!connection.is_established());
+
+        assert_eq!(connection.connect(), Ok(DccState::Connecting));
+        assert!(!connection.is_established());
+        assert_eq!(connection.establish(), Ok(DccState::Established));
+        assert!(connection.is_established());
+    }
+
+    #[test]
+    fn test_a_connection_is_transferring_until_every_file_ends() {
+        let mut connection = connection_in(DccState::Established);
+        assert_eq!(connection.start_transfer(), Ok(DccState::Transferring));
+        assert_eq!(connection.start_transfer(), Ok(DccState::Transferring));
+        assert_eq!(connection.transfers(), 2);
+        assert!(connection.is_established());
+
+        assert_eq!(connection.end_transfer(), Ok(DccState::Transferring));
+        assert_eq!(connection.end_transfer(), Ok(DccState::Established));
+        assert_eq!(connection.transfers(), 0);
+    }
+
+    #[test]
+    fn test_a_connection_is_closed_from_every_state_but_closed() {
+        for target in [
+            DccState::Offered,
+            DccState::Connecting,
+            DccState::Established,
+            DccState::Transferring,
+        ] {
+            let mut connection = connection_in(target);
+            assert_eq!(connection.close(), Ok(DccState::Closed));
+            assert_eq!(connection.transfers(), 0);
+            assert!(!connection.is_established());
+        }
+
+        let mut connection = connection_in(DccState::Closed);
+        assert_eq!(
+            connection.close(),
+            Err(InvalidTransition {
+                state: DccState::Closed,
+                event: DccEvent::Close,
+            })
+        );
+    }
+
+    #[test]
+    fn test_events_out_of_order_do_not_change_the_state() {
+        let invalid = [
+            (DccState::Offered, DccEvent::Establish),
+            (DccState::Offered, DccEvent::StartTransfer),
+            (DccState::Offered, DccEvent::EndTransfer),
+            (DccState::Connecting, DccEvent::Connect),
+            (DccState::Connecting, DccEvent::StartTransfer),
+            (DccState::Connecting, DccEvent::EndTransfer),
+            (DccState::Established, DccEvent::Connect),
+            (DccState::Established, DccEvent::Establish),
+            (DccState::Established, DccEvent::EndTransfer),
+            (DccState::Transferring, DccEvent::Connect),
+            (DccState::Transferring, DccEvent::Establish),
+            (DccState::Closed, DccEvent::Connect),
+            (DccState::Closed, DccEvent::Establish),
+            (DccState::Closed, DccEvent::StartTransfer),
+            (DccState::Closed, DccEvent::EndTransfer),
+        ];
+        for (target, event) in invalid {
+            let mut connection = connection_in(target);
+            let transfers = connection.transfers();
+            assert_eq!(
+                connection.handle(event),
+                Err(InvalidTransition {
+                    state: target,
+                    event,
+                })
+            );
+            assert_eq!(connection.state(), target);
+            assert_eq!(connection.transfers(), transfers);
+        }
+    }
+
+    #[test]
+    fn test_shared_connections_are_driven_and_closed_once() {
+        let connection = DccConnection::shared("bob".to_owned());
+        assert_eq!(
+            drive(&connection, DccEvent::Connect),
+            Some(DccState::Connecting)
+        );
+        assert_eq!(drive(&connection, DccEvent::StartTransfer), None);
+        assert_eq!(state(&connection), DccState::Connecting);
+
+        close(&connection);
+        close(&connection);
+        assert_eq!(state(&connection), DccState::Closed);
+    }
+}
@@ -1,18 +1,20 @@
 use std::{
     collections::HashMap,
+    io::ErrorKind,
     net::{Shutdown, TcpListener, TcpStream},
     sync::{
-        mpsc::{sync_channel, Receiver, SyncSender},
+        mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender, TryRecvError},
         Arc, RwLock,
     },
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use crate::dcc_commands::close::remove_connection;
 use gtk::glib;
 use model::{
     client_errors::ClientError,
+    crypto::SecureSession,
     dcc::{DccMessage, DccMessageType},
     responses::{
         dcc::DccResponse, errors::ErrorResponse, ongoing_transfer::OngoingTransfer,
@@ -24,28 +26,64 @@ use model::{
 use crate::dcc_commands::{
     chat::incoming_chat_request,
     close::{incoming_close_request, outgoing_close_request},
+    connection::{close, drive, DccConnection, DccEvent, SharedDccConnection},
+    queue::TransferScheduler,
+    relay::{incoming_relay_request, DccRelays},
     resume::{incoming_resume_request, outgoing_resume_request},
-    send::{incoming_send_request, outgoing_send_request},
+    room::{incoming_room_request, leave_rooms, outgoing_room_request, DccRooms},
+    saved_transfers::restore_saved_transfers,
+    secure::{
+        exchange_keys, open_chat_message, open_room_message, seal_chat_message, seal_room_message,
+    },
+    send::incoming_send_request,
     stop::{incoming_stop_request, outgoing_stop_request},
+    typing::incoming_typing_request,
 };
 
+/// The time the requested client has to answer a dcc connection request before it expires
+const NEGOTIATION_TIMEOUT: Duration = Duration::from_secs(60);
+/// The time between two checks of a dcc connection request that is not answered yet
+const NEGOTIATION_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The reasons a dcc connection request is left without an answer
+#[derive(Debug, PartialEq, Eq)]
+enum Unanswered {
+    /// The current client cancelled the request
+    Cancelled,
+    /// The requested client didn't answer before the timeout
+    TimedOut,
+    /// The connection with the requested client failed
+    Failed,
+}
+
 /// This function manages the DCC request from the current client
 /// Sends the message to the thread that manages the connection
 /// If a dcc connection doesn't exist, it creates a new one
 /// If a dcc connection is already active, it sends the message to the active thread
+/// A SCHAT request creates a connection like a CHAT request, whose messages and files are encrypted
+/// A ROOM request is managed by the rooms, which send it to the connections of their members
 /// Returns a ClientError in case of error
 pub fn manage_dcc_request_from_current_client(
     dcc_msg: DccMessage,
     dcc_connections: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
     tx_chats: gtk::glib::Sender<Response>, // comunication with interface
     arc_dcc_interface_communication: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
+    dcc_rooms: DccRooms,
 ) -> Result<(), ClientError> {
+    if dcc_msg.command == DccMessageType::Room {
+        return outgoing_room_request(dcc_msg, dcc_rooms, dcc_connections, tx_chats);
+    }
+
     let mut dcc_hash_lock = dcc_connections.as_ref().write()?;
     let requested_client = dcc_msg.parameters[0].to_owned();
 
     match dcc_msg.command {
-        DccMessageType::Chat => {
+        DccMessageType::Chat | DccMessageType::SChat => {
             if let Some(dcc_connection) = dcc_hash_lock.get(&requested_client) {
+                if dcc_msg.command == DccMessageType::SChat {
+                    println!("[ERROR] A p2p connection with {requested_client} is already active");
+                    return Ok(());
+                }
                 // send information to the active thread that manages the connection
                 if dcc_connection
                     .send(DccMessage::serialize(dcc_msg)?)
@@ -68,6 +106,7 @@ pub fn manage_dcc_request_from_current_client(
                     dcc_connections,
                     tx_chats,
                     arc_dcc_interface_communication,
+                    dcc_rooms,
                 )?;
             }
         }
@@ -98,19 +137,49 @@ pub fn manage_dcc_request_from_current_client(
 /// Sends the message to the thread that manages the connection
 /// If a dcc connection doesn't exist, it connects to the requested connection from the other client
 /// If a dcc connection is already active, it sends the message to the active thread
+/// The socket of the server is used to answer passive offers
+/// ROOM, TYPING and ERROR messages are dropped, since they are only sent through the dcc connections
+/// RELAY messages are files sent through the server, since the clients can't connect
 /// Returns a ClientError in case of error
 pub fn manage_dcc_request_from_client(
     dcc_msg: DccMessage,
     dcc_connections: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
     tx_chats: gtk::glib::Sender<Response>,
     arc_dcc_interface_communication: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
+    arc_server_socket: Arc<TcpStream>,
+    dcc_rooms: DccRooms,
+    dcc_relays: DccRelays,
 ) -> Result<(), ClientError> {
+    if dcc_msg.command == DccMessageType::Relay {
+        return incoming_relay_request(
+            dcc_msg,
+            arc_server_socket,
+            dcc_relays,
+            tx_chats,
+            arc_dcc_interface_communication,
+        );
+    }
+    if matches!(
+        dcc_msg.command,
+        DccMessageType::Room | DccMessageType::Typing | DccMessageType::Error
+    ) {
+        println!(
+            "[ERROR] DCC {:?} message received through the server",
+            dcc_msg.command
+        );
+        return Ok(());
+    }
+
     let mut dcc_hash_lock = dcc_connections.as_ref().write()?;
     let requested_client = dcc_msg.prefix.clone().unwrap_or_else(|| "".to_owned()); // just in the "CHAT" case
 
     match dcc_msg.command {
-        DccMessageType::Chat => {
+        DccMessageType::Chat | DccMessageType::SChat => {
             if let Some(dcc_connection) = dcc_hash_lock.get(&requested_client) {
+                if dcc_msg.command == DccMessageType::SChat {
+                    println!("[DEBUG] A p2p connection with {requested_client} is already active");
+                    return Ok(());
+                }
                 // send information to the active thread that manages the connection
                 if dcc_connection.send(DccMessage::serialize(dcc_msg)?).is_ok() {};
                 drop(dcc_hash_lock);
@@ -126,6 +195,8 @@ pub fn manage_dcc_request_from_client(
                     dcc_connections,
                     tx_chats,
                     arc_dcc_interface_communication,
+                    arc_server_socket,
+                    dcc_rooms,
                 )?;
             }
         }
@@ -147,6 +218,15 @@ pub fn manage_dcc_request_from_client(
 /// Creates a new dcc connection between the current client and the requested_client.
 /// Sends a dcc connection request to the requested_client, who must accept the request to establish the connection.
 /// It also handles the incoming and outgoing dcc messages for the current client who created the connection.
+/// If the request is a SCHAT, the clients exchange their keys after the connection is accepted,
+/// and the messages and files are encrypted with them.
+/// If the request is a passive offer, the requested_client opens the listening socket instead, and sends
+/// its address back through the server with the token of the offer.
+/// The files sent through the connection wait their turn in its transfer queue.
+/// The rooms the requested_client is part of send their messages through the connection, and it leaves
+/// them when the connection is closed.
+/// If the requested_client doesn't answer before NEGOTIATION_TIMEOUT, or the current client closes the
+/// connection before the answer, the request is dropped and the interface stops waiting.
 /// Returns a ClientError in case of error
 pub fn create_new_dcc_connection(
     dcc_receiver: Receiver<String>,
@@ -155,16 +235,15 @@ pub fn create_new_dcc_connection(
     dcc_connections: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
     tx_chats: gtk::glib::Sender<Response>,
     arc_dcc_interface_communication: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
+    dcc_rooms: DccRooms,
 ) -> Result<(), ClientError> {
     // to communicate with the transfers' thread in progress
     let transfers_communication = HashMap::<String, SyncSender<String>>::new();
     let arc_transfers_communication = Arc::new(RwLock::new(transfers_communication));
-    let arc_transfers_communication_clone = arc_transfers_communication.clone();
 
     // to keep track of unfinished transfers
     let ongoing_transfers = HashMap::<String, OngoingTransfer>::new();
     let arc_ongoing_transfers = Arc::new(RwLock::new(ongoing_transfers));
-    let arc_ongoing_transfers_clone = arc_ongoing_transfers.clone();
 
     if dcc_msg.parameters.len() < 3 {
         println!("[ERROR] first DCC CHAT command needs 3 parameters: client, ip, port.");
@@ -173,24 +252,27 @@ pub fn create_new_dcc_connection(
     }
     let ip = dcc_msg.parameters[1].to_owned();
     let port = dcc_msg.parameters[2].to_owned();
-
-    let requested_client_clone = requested_client.clone();
-    let tx_chats_clone = tx_chats.clone();
+    let secure = dcc_msg.command == DccMessageType::SChat;
+    let passive_token = dcc_msg.passive_token().map(|token| token.to_owned());
+    let connection = DccConnection::shared(requested_client.clone());
 
     thread::spawn(move || {
-        let listener = match TcpListener::bind(format!("{ip}:{port}")) {
-            Ok(listener) => listener,
-            Err(_) => {
-                println!("[ERROR] Error creating the listener on {ip}:{port}");
-                remove_connection(dcc_connections.clone(), requested_client.clone());
-                let response = Response::DccResponse {
-                    response: DccResponse::ErrorResponse {
-                        description: "Invalid address.".to_owned(),
-                    },
-                };
-                if tx_chats.send(response).is_ok() {};
-                return;
-            }
+        let listener = match passive_token {
+            Some(_) => None,
+            None => match TcpListener::bind(format!("{ip}:{port}")) {
+                Ok(listener) => Some(listener),
+                Err(_) => {
+                    println!("[ERROR] Error creating the listener on {ip}:{port}");
+                    remove_connection(dcc_connections.clone(), requested_client.clone());
+                    let response = Response::DccResponse {
+                        response: DccResponse::ErrorResponse {
+                            description: "Invalid address.".to_owned(),
+                        },
+                    };
+                    if tx_chats.send(response).is_ok() {};
+                    return;
+                }
+            },
         };
 
         println!("[DEBUG] Waiting for connection with {requested_client}");
@@ -201,12 +283,39 @@ pub fn create_new_dcc_connection(
         };
         if tx_chats.send(response).is_ok() {};
 
-        if let Ok((dcc_socket, _)) = listener.accept() {
+        let deadline = Instant::now() + NEGOTIATION_TIMEOUT;
+        let dcc_socket = match listener {
+            Some(listener) => accept_before(&listener, &dcc_receiver, deadline),
+            None => wait_for_passive_connection(
+                &dcc_receiver,
+                passive_token.as_deref().unwrap_or_default(),
+                &requested_client,
+                &tx_chats,
+                deadline,
+            ),
+        }
+        .and_then(|socket| {
+            wait_for_first_message(&socket, &dcc_receiver, deadline).map(|_| socket)
+        });
+        match &dcc_socket {
+            Err(Unanswered::TimedOut) => {
+                println!("[INFO] Client {requested_client} didn't answer the connection.");
+                let response = Response::DccResponse {
+                    response: DccResponse::RequestExpired {
+                        sender: requested_client.clone(),
+                    },
+                };
+                if tx_chats.send(response).is_ok() {};
+            }
+            Err(Unanswered::Cancelled) => {
+                println!("[INFO] Connection with {requested_client} cancelled.");
+            }
+            _ => {}
+        }
+        if let Ok(dcc_socket) = dcc_socket {
             let arc_socket = Arc::new(dcc_socket);
-            let arc_socket_clone = arc_socket.clone();
-            let arc_socket_clone_1 = arc_socket.clone();
 
-            if let Ok(msg) = read_socket(arc_socket_clone.clone()) {
+            if let Ok(msg) = read_socket(arc_socket.clone()) {
                 if let Ok(first_dcc_msg) = DccMessage::deserialize(msg) {
                     match first_dcc_msg.command {
                         DccMessageType::Accept => {
@@ -255,171 +364,134 @@ pub fn create_new_dcc_connection(
                     }
                 }
             }
+            drive(&connection, DccEvent::Connect);
 
-            let dcc_connections_clone = dcc_connections.clone();
-            let requested_client_clone_1 = requested_client.clone();
-            let tx_chats_clone_1 = tx_chats.clone();
-
-            thread::spawn(move || {
-                while let Ok(message_from_client) = read_socket(arc_socket_clone.clone()) {
-                    let dcc_msg = match DccMessage::deserialize(message_from_client.clone()) {
-                        Ok(m) => m,
-                        Err(e) => {
-                            println!("[ERROR] Invalid DCC message received: {e:?}");
-                            continue;
-                        }
-                    };
-                    match dcc_msg.command {
-                        DccMessageType::Chat => {
-                            incoming_chat_request(
-                                requested_client.clone(),
-                                dcc_msg.clone(),
-                                tx_chats.clone(),
-                            );
-                        }
-                        DccMessageType::Close => {
-                            incoming_close_request(
-                                arc_socket_clone.clone(),
-                                dcc_connections.clone(),
-                                requested_client.clone(),
-                                tx_chats_clone,
-                            );
-                            return;
-                        }
-                        DccMessageType::Send => {
-                            match incoming_send_request(
-                                requested_client_clone.clone(),
-                                dcc_msg.clone(),
-                                tx_chats_clone.clone(),
-                                arc_dcc_interface_communication.clone(),
-                                arc_ongoing_transfers.clone(),
-                            ) {
-                                Ok(_) => {}
-                                Err(e) => println!("[ERROR] Transfer failed: {e:?}"),
-                            };
-                        }
-                        DccMessageType::Resume => {
-                            match incoming_resume_request(
-                                dcc_msg.clone(),
-                                requested_client_clone.clone(),
-                                arc_socket.clone(),
-                                tx_chats_clone.clone(),
-                                arc_transfers_communication.clone(),
-                                arc_ongoing_transfers.clone(),
-                            ) {
-                                Ok(_) => {}
-                                Err(e) => println!("[ERROR] Transfer resume failed: {e:?}"),
-                            }
-                        }
-                        DccMessageType::Stop => {
-                            match incoming_stop_request(
-                                dcc_msg.clone(),
-                                arc_transfers_communication.clone(),
-                                arc_ongoing_transfers.clone(),
-                                requested_client_clone.clone(),
-                            ) {
-                                Ok(_) => {}
-                                Err(e) => println!("[ERROR] Failed to stop transfer: {e:?}"),
-                            };
-                        }
-                        _ => {
-                            todo!();
-                        }
-                    }
-                }
-            });
-
-            while let Ok(message_for_client) = dcc_receiver.recv() {
-                let dcc_msg = match DccMessage::deserialize(message_for_client.clone()) {
-                    Ok(m) => m,
-                    Err(e) => {
-                        println!("[ERROR] Invalid command: {e:?}");
-                        continue;
+            let session =
+                match secure_connection(secure, arc_socket.clone(), &requested_client, &tx_chats) {
+                    Ok(session) => session,
+                    Err(_) => {
+                        if arc_socket.as_ref().shutdown(Shutdown::Both).is_ok() {};
+                        remove_connection(dcc_connections.clone(), requested_client);
+                        return;
                     }
                 };
+            run_dcc_connection(
+                DccLink {
+                    requested_client,
+                    arc_socket,
+                    session,
+                    connection,
+                    dcc_connections,
+                    tx_chats,
+                    arc_dcc_interface_communication,
+                    arc_transfers_communication,
+                    arc_ongoing_transfers,
+                    dcc_rooms,
+                },
+                dcc_receiver,
+            );
+        } else {
+            // dropped first, so a message sent to the connection while it is removed doesn't wait forever
+            drop(dcc_receiver);
+            remove_connection(dcc_connections, requested_client);
+        }
+    });
 
-                match dcc_msg.command {
-                    DccMessageType::Send => {
-                        if dcc_msg.parameters.len() < 5 {
-                            println!("[ERROR] DCC SEND command needs 5 parameters");
-                            continue;
-                        }
+    Ok(())
+}
 
-                        match outgoing_send_request(
-                            requested_client_clone_1.clone(),
-                            arc_socket_clone_1.clone(),
-                            dcc_msg.clone(),
-                            tx_chats_clone_1.clone(),
-                            arc_transfers_communication_clone.clone(),
-                            arc_ongoing_transfers_clone.clone(),
-                        ) {
-                            Ok(_) => {}
-                            Err(e) => {
-                                println!("[ERROR] Error sending file: {e:?}");
-                                continue;
-                            }
-                        }
-                    }
-                    DccMessageType::Close => {
-                        outgoing_close_request(
-                            arc_socket_clone_1,
-                            dcc_connections_clone,
-                            requested_client_clone_1,
-                            message_for_client,
-                        );
-                        return;
-                    }
-                    DccMessageType::Chat => {
-                        if write_socket(
-                            arc_socket_clone_1.clone(),
-                            &DccMessage::serialize(dcc_msg).unwrap(),
-                        )
-                        .is_ok()
-                        {}
-                    }
-                    DccMessageType::Stop => {
-                        match outgoing_stop_request(
-                            dcc_msg.clone(),
-                            arc_socket_clone_1.clone(),
-                            arc_transfers_communication_clone.clone(),
-                            arc_ongoing_transfers_clone.clone(),
-                        ) {
-                            Ok(_) => {}
-                            Err(e) => {
-                                println!("[ERROR] Error stopping file transfer: {e:?}");
-                                continue;
-                            }
-                        };
-                    }
-                    DccMessageType::Resume => {
-                        match outgoing_resume_request(
-                            dcc_msg.clone(),
-                            arc_socket_clone_1.clone(),
-                            tx_chats_clone_1.clone(),
-                            arc_transfers_communication_clone.clone(),
-                            arc_ongoing_transfers_clone.clone(),
-                        ) {
-                            Ok(_) => {}
-                            Err(e) => {
-                                println!("[ERROR] Error resuming file transfer: {e:?}");
-                                continue;
-                            }
-                        };
-                    }
-                    _ => {
-                        todo!();
-                    }
-                }
+/// Waits for the requested client to connect to the listener until the deadline
+/// The current client can cancel the request meanwhile by closing the connection
+/// Returns why the request was left without an answer if the requested client doesn't connect
+fn accept_before(
+    listener: &TcpListener,
+    dcc_receiver: &Receiver<String>,
+    deadline: Instant,
+) -> Result<TcpStream, Unanswered> {
+    listener
+        .set_nonblocking(true)
+        .map_err(|_| Unanswered::Failed)?;
+    loop {
+        match listener.accept() {
+            Ok((socket, _)) => {
+                socket
+                    .set_nonblocking(false)
+                    .map_err(|_| Unanswered::Failed)?;
+                return Ok(socket);
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+            Err(e) => {
+                println!("[ERROR] Error accepting the connection: {e:?}");
+                return Err(Unanswered::Failed);
             }
         }
-    });
+        check_unanswered(dcc_receiver, deadline)?;
+        thread::sleep(NEGOTIATION_POLL_INTERVAL);
+    }
+}
+
+/// Waits for the first message of the requested client, who connected but answers once its user accepts or
+/// rejects the request, until the deadline
+/// The message is left in the socket to be read afterwards
+/// The current client can cancel the request meanwhile by closing the connection
+fn wait_for_first_message(
+    socket: &TcpStream,
+    dcc_receiver: &Receiver<String>,
+    deadline: Instant,
+) -> Result<(), Unanswered> {
+    socket
+        .set_read_timeout(Some(NEGOTIATION_POLL_INTERVAL))
+        .map_err(|_| Unanswered::Failed)?;
+    let answered = loop {
+        match socket.peek(&mut [0; 1]) {
+            Ok(0) => break Err(Unanswered::Failed),
+            Ok(_) => break Ok(()),
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(_) => break Err(Unanswered::Failed),
+        }
+        if let Err(unanswered) = check_unanswered(dcc_receiver, deadline) {
+            break Err(unanswered);
+        }
+    };
+    socket
+        .set_read_timeout(None)
+        .map_err(|_| Unanswered::Failed)?;
+    answered
+}
 
+/// Checks a dcc connection request that is not answered yet
+/// Returns Cancelled if the current client closed the connection, or TimedOut if the deadline passed
+/// Other messages for the connection are dropped, since it is not established yet
+fn check_unanswered(dcc_receiver: &Receiver<String>, deadline: Instant) -> Result<(), Unanswered> {
+    loop {
+        match dcc_receiver.try_recv() {
+            Ok(message) => match DccMessage::deserialize(message) {
+                Ok(dcc_msg) if dcc_msg.command == DccMessageType::Close => {
+                    return Err(Unanswered::Cancelled)
+                }
+                _ => println!("[ERROR] The connection is not established yet"),
+            },
+            Err(TryRecvError::Empty) => break,
+            Err(TryRecvError::Disconnected) => return Err(Unanswered::Failed),
+        }
+    }
+    if Instant::now() >= deadline {
+        return Err(Unanswered::TimedOut);
+    }
     Ok(())
 }
 
 /// Receives a dcc connection request from the interface, from the client who requested the connection.
 /// If the connection is accepted, the response is sent back to the client who requested it, and the connection establishes.
 /// It also handles the incoming and outgoing dcc messages for the current client who received the connection request.
+/// If the request is a SCHAT, the clients exchange their keys after the connection is accepted,
+/// and the messages and files are encrypted with them.
+/// If the request is a passive offer, the current client opens the listening socket and sends its address
+/// to the client who requested the connection through the server.
+/// The files sent through the connection wait their turn in its transfer queue.
+/// The rooms the requested_client is part of send their messages through the connection, and it leaves
+/// them when the connection is closed.
+#[allow(clippy::too_many_arguments)]
 pub fn connect_to_new_dcc_connection(
     dcc_receiver: Receiver<String>,
     dcc_msg: DccMessage,
@@ -427,6 +499,8 @@ pub fn connect_to_new_dcc_connection(
     dcc_connections: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
     tx_chats: glib::Sender<Response>,
     arc_dcc_interface_communication: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
+    arc_server_socket: Arc<TcpStream>,
+    dcc_rooms: DccRooms,
 ) -> Result<(), ClientError> {
     if dcc_msg.parameters.len() < 3 {
         println!("[ERROR] DCC needs more parameters");
@@ -436,19 +510,18 @@ pub fn connect_to_new_dcc_connection(
 
     let transfers_communication = HashMap::<String, SyncSender<String>>::new();
     let arc_transfers_communication = Arc::new(RwLock::new(transfers_communication));
-    let arc_transfers_communication_clone = arc_transfers_communication.clone();
 
     // to keep track of unfinished transfers
     // (file_name, (offset, size, path))
     let ongoing_transfers = HashMap::<String, OngoingTransfer>::new();
     let arc_ongoing_transfers = Arc::new(RwLock::new(ongoing_transfers));
-    let arc_ongoing_transfers_clone = arc_ongoing_transfers.clone();
 
     let ip = dcc_msg.parameters[1].to_owned();
     let port = dcc_msg.parameters[2].to_owned();
-
-    let tx_chats_clone = tx_chats.clone();
-    let tx_chats_clone_1 = tx_chats.clone();
+    let secure = dcc_msg.command == DccMessageType::SChat;
+    let passive_token = dcc_msg.passive_token().map(|token| token.to_owned());
+    let nickname = dcc_msg.parameters[0].to_owned();
+    let connection = DccConnection::shared(requested_client.clone());
 
     thread::spawn(move || {
         let response = Response::DccResponse {
@@ -458,19 +531,29 @@ pub fn connect_to_new_dcc_connection(
         };
         if tx_chats.send(response).is_ok() {};
 
-        // wait for the other client to bind
-        thread::sleep(Duration::from_millis(500));
-        let socket = match TcpStream::connect(format!("{ip}:{port}")) {
+        let socket = match &passive_token {
+            Some(token) => listen_for_passive_connection(
+                arc_server_socket,
+                &nickname,
+                &requested_client,
+                token,
+            ),
+            None => {
+                // wait for the other client to bind
+                thread::sleep(Duration::from_millis(500));
+                TcpStream::connect(format!("{ip}:{port}")).map_err(ClientError::from)
+            }
+        };
+        let socket = match socket {
             Ok(socket) => socket,
             Err(e) => {
-                println!("[ERROR] Error connecting to {ip}:{port}. {e:?}");
+                println!("[ERROR] Error connecting with {requested_client}. {e:?}");
                 remove_connection(dcc_connections.clone(), requested_client);
                 return;
             }
         };
 
         let arc_socket = Arc::new(socket);
-        let arc_socket_clone = arc_socket.clone();
 
         if let Ok(answer) = dcc_receiver.recv() {
             let msg = match DccMessage::deserialize(answer) {
@@ -484,20 +567,17 @@ pub fn connect_to_new_dcc_connection(
                 DccMessageType::Accept => {
                     println!("[INFO] DCC connection with {requested_client} accepted");
                     if write_socket(
-                        arc_socket_clone.clone(),
+                        arc_socket.clone(),
                         &format!("DCC ACCEPT {requested_client}"),
                     )
                     .is_ok()
                     {};
                 }
                 DccMessageType::Close => {
-                    if write_socket(
-                        arc_socket_clone.clone(),
-                        &format!("DCC CLOSE {requested_client}"),
-                    )
-                    .is_ok()
+                    if write_socket(arc_socket.clone(), &format!("DCC CLOSE {requested_client}"))
+                        .is_ok()
                     {};
-                    if arc_socket_clone.as_ref().shutdown(Shutdown::Both).is_ok() {};
+                    if arc_socket.as_ref().shutdown(Shutdown::Both).is_ok() {};
                     remove_connection(dcc_connections.clone(), requested_client);
                     return;
                 }
@@ -507,155 +587,610 @@ pub fn connect_to_new_dcc_connection(
                 }
             }
         }
+        drive(&connection, DccEvent::Connect);
 
-        let dcc_connections_clone = dcc_connections.clone();
-        let requested_client_clone_1 = requested_client.clone();
-        let arc_socket_clone_2 = arc_socket.clone();
-        let tx_chats_clone_2 = tx_chats_clone.clone();
-        thread::spawn(move || {
-            let arc_socket_clone_1 = arc_socket_clone.clone();
+        let session =
+            match secure_connection(secure, arc_socket.clone(), &requested_client, &tx_chats) {
+                Ok(session) => session,
+                Err(_) => {
+                    if arc_socket.as_ref().shutdown(Shutdown::Both).is_ok() {};
+                    remove_connection(dcc_connections.clone(), requested_client);
+                    return;
+                }
+            };
+        run_dcc_connection(
+            DccLink {
+                requested_client,
+                arc_socket,
+                session,
+                connection,
+                dcc_connections,
+                tx_chats,
+                arc_dcc_interface_communication,
+                arc_transfers_communication,
+                arc_ongoing_transfers,
+                dcc_rooms,
+            },
+            dcc_receiver,
+        );
+    });
 
-            while let Ok(message_from_client) = read_socket(arc_socket_clone.clone()) {
-                let requested_client_clone = requested_client.clone();
+    Ok(())
+}
 
-                let dcc_msg = match DccMessage::deserialize(message_from_client.clone()) {
-                    Ok(m) => m,
-                    Err(e) => {
-                        println!("[ERROR] Error parsing DCC message: {e:?}");
+/// Struct that has what the threads of an established dcc connection share
+/// # Fields
+/// * `requested_client`: The client at the other side of the connection
+/// * `arc_socket`: The socket of the connection
+/// * `session`: The keys shared with the requested client, if the connection is secure
+/// * `connection`: The state of the connection, driven by its threads
+/// * `dcc_connections`: The channels to communicate with the threads of the dcc connections
+/// * `tx_chats`: The channel to send the responses to the interface
+/// * `arc_dcc_interface_communication`: The channels to communicate the answers of the interface to the transfers
+/// * `arc_transfers_communication`: The channels to communicate with the transfers in progress
+/// * `arc_ongoing_transfers`: The unfinished transfers
+/// * `dcc_rooms`: The rooms the current client is part of
+#[derive(Clone)]
+struct DccLink {
+    requested_client: String,
+    arc_socket: Arc<TcpStream>,
+    session: Option<SecureSession>,
+    connection: SharedDccConnection,
+    dcc_connections: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
+    tx_chats: glib::Sender<Response>,
+    arc_dcc_interface_communication: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
+    arc_transfers_communication: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
+    arc_ongoing_transfers: Arc<RwLock<HashMap<String, OngoingTransfer>>>,
+    dcc_rooms: DccRooms,
+}
+
+/// Runs a dcc connection once the clients are connected and the keys are exchanged if it is secure, in the
+/// same way for both clients
+/// The messages of the requested client are read in a new thread, and the messages of the current client
+/// are written in this one, until the connection is closed
+fn run_dcc_connection(link: DccLink, dcc_receiver: Receiver<String>) {
+    drive(&link.connection, DccEvent::Establish);
+    restore_saved_transfers(
+        &link.requested_client,
+        link.arc_ongoing_transfers.clone(),
+        link.tx_chats.clone(),
+    );
+    let reader = link.clone();
+    thread::spawn(move || read_dcc_messages(reader));
+    write_dcc_messages(link, dcc_receiver);
+}
+
+/// Reads the messages the requested client sends through the dcc connection and manages them, until the
+/// connection is closed
+fn read_dcc_messages(link: DccLink) {
+    while let Ok(message_from_client) = read_socket(link.arc_socket.clone()) {
+        let dcc_msg = match DccMessage::deserialize(message_from_client) {
+            Ok(m) => m,
+            Err(e) => {
+                println!("[ERROR] Invalid DCC message received: {e:?}");
+                continue;
+            }
+        };
+        match dcc_msg.command {
+            DccMessageType::Chat => {
+                let dcc_msg = match read_chat_message(dcc_msg, &link.session) {
+                    Some(dcc_msg) => dcc_msg,
+                    None => {
+                        println!("[ERROR] Invalid encrypted message received");
                         continue;
                     }
                 };
+                incoming_chat_request(
+                    link.requested_client.clone(),
+                    dcc_msg,
+                    link.tx_chats.clone(),
+                );
+            }
+            DccMessageType::Room => {
+                manage_room_message(
+                    dcc_msg,
+                    &link.session,
+                    &link.requested_client,
+                    &link.dcc_rooms,
+                    &link.dcc_connections,
+                    &link.tx_chats,
+                );
+            }
+            DccMessageType::Typing => {
+                incoming_typing_request(
+                    link.requested_client.clone(),
+                    dcc_msg,
+                    link.tx_chats.clone(),
+                );
+            }
+            DccMessageType::Close => {
+                leave_rooms(
+                    &link.requested_client,
+                    link.dcc_rooms.clone(),
+                    link.dcc_connections.clone(),
+                    link.tx_chats.clone(),
+                );
+                incoming_close_request(
+                    link.arc_socket.clone(),
+                    link.dcc_connections.clone(),
+                    link.requested_client.clone(),
+                    link.tx_chats.clone(),
+                );
+                break;
+            }
+            DccMessageType::Send | DccMessageType::SSend => {
+                if dcc_msg.parameters.len() < 4 {
+                    println!("[ERROR] Invalid DCC SEND command. Not enough parameters.");
+                    continue;
+                }
+                match incoming_send_request(
+                    link.requested_client.clone(),
+                    dcc_msg,
+                    link.tx_chats.clone(),
+                    link.arc_dcc_interface_communication.clone(),
+                    link.arc_ongoing_transfers.clone(),
+                    link.session.clone(),
+                ) {
+                    Ok(_) => {}
+                    Err(e) => println!("[ERROR] Transfer failed: {e:?}"),
+                };
+            }
+            DccMessageType::Resume => {
+                match incoming_resume_request(
+                    dcc_msg,
+                    link.requested_client.clone(),
+                    link.arc_socket.clone(),
+                    link.tx_chats.clone(),
+                    link.arc_transfers_communication.clone(),
+                    link.arc_ongoing_transfers.clone(),
+                ) {
+                    Ok(_) => {}
+                    Err(e) => println!("[ERROR] Transfer resume failed: {e:?}"),
+                }
+            }
+            DccMessageType::Stop => {
+                match incoming_stop_request(
+                    dcc_msg,
+                    link.arc_transfers_communication.clone(),
+                    link.arc_ongoing_transfers.clone(),
+                    link.requested_client.clone(),
+                ) {
+                    Ok(_) => {}
+                    Err(e) => println!("[ERROR] Failed to stop transfer: {e:?}"),
+                };
+            }
+            DccMessageType::Error => incoming_error_message(&link, dcc_msg),
+            _ => reject_message_from_client(&link, dcc_msg.command),
+        }
+    }
+    close(&link.connection);
+}
 
-                match dcc_msg.command {
-                    DccMessageType::Chat => {
-                        incoming_chat_request(
-                            requested_client.clone(),
-                            dcc_msg.clone(),
-                            tx_chats.clone(),
-                        );
-                    }
-                    DccMessageType::Send => {
-                        if dcc_msg.parameters.len() < 4 {
-                            println!("[ERROR] Invalid DCC SEND command. Not enough parameters.");
-                            continue;
-                        }
-                        match incoming_send_request(
-                            requested_client_clone.clone(),
-                            dcc_msg.clone(),
-                            tx_chats_clone.clone(),
-                            arc_dcc_interface_communication.clone(),
-                            arc_ongoing_transfers.clone(),
-                        ) {
-                            Ok(_) => {}
-                            Err(e) => println!("[ERROR] Error sending file: {e:?}"),
-                        };
-                    }
-                    DccMessageType::Close => {
-                        incoming_close_request(
-                            arc_socket.clone(),
-                            dcc_connections.clone(),
-                            requested_client.clone(),
-                            tx_chats,
-                        );
-                        return;
-                    }
-                    DccMessageType::Stop => {
-                        match incoming_stop_request(
-                            dcc_msg.clone(),
-                            arc_transfers_communication.clone(),
-                            arc_ongoing_transfers.clone(),
-                            requested_client_clone.clone(),
-                        ) {
-                            Ok(_) => {}
-                            Err(e) => println!("[ERROR] Error stopping transfer: {e:?}"),
-                        };
-                    }
-                    DccMessageType::Resume => {
-                        match incoming_resume_request(
-                            dcc_msg.clone(),
-                            requested_client_clone.clone(),
-                            arc_socket_clone_1.clone(),
-                            tx_chats_clone_1.clone(),
-                            arc_transfers_communication.clone(),
-                            arc_ongoing_transfers.clone(),
-                        ) {
-                            Ok(_) => {}
-                            Err(e) => println!("[ERROR] Error resuming transfer: {e:?}"),
-                        };
-                    }
-                    _ => {
-                        todo!();
-                    }
+/// Writes the messages of the current client, sent by the interface, through the dcc connection, until the
+/// connection is closed
+/// The files sent wait their turn in the transfer queue of the connection
+fn write_dcc_messages(link: DccLink, dcc_receiver: Receiver<String>) {
+    let transfer_scheduler = TransferScheduler::new(
+        link.requested_client.clone(),
+        link.arc_socket.clone(),
+        link.tx_chats.clone(),
+        link.arc_transfers_communication.clone(),
+        link.arc_ongoing_transfers.clone(),
+        link.session.clone(),
+        link.connection.clone(),
+    );
+    while let Ok(message_for_client) = dcc_receiver.recv() {
+        let dcc_msg = match DccMessage::deserialize(message_for_client.clone()) {
+            Ok(m) => m,
+            Err(e) => {
+                println!("[ERROR] Invalid command: {e:?}");
+                continue;
+            }
+        };
+
+        match dcc_msg.command {
+            DccMessageType::Send | DccMessageType::SSend => {
+                if dcc_msg.parameters.len() < 5 {
+                    println!("[ERROR] DCC SEND command needs 5 parameters");
+                    continue;
                 }
+                transfer_scheduler.schedule(dcc_msg);
             }
-        });
+            DccMessageType::Close => {
+                leave_rooms(
+                    &link.requested_client,
+                    link.dcc_rooms.clone(),
+                    link.dcc_connections.clone(),
+                    link.tx_chats.clone(),
+                );
+                outgoing_close_request(
+                    link.arc_socket.clone(),
+                    link.dcc_connections.clone(),
+                    link.requested_client.clone(),
+                    message_for_client,
+                );
+                break;
+            }
+            DccMessageType::Chat => {
+                for message in write_chat_message(dcc_msg, message_for_client, &link.session) {
+                    if write_socket(link.arc_socket.clone(), &message).is_ok() {}
+                }
+            }
+            DccMessageType::Room => {
+                for message in write_room_message(dcc_msg, message_for_client, &link.session) {
+                    if write_socket(link.arc_socket.clone(), &message).is_ok() {}
+                }
+            }
+            DccMessageType::Typing => {
+                if write_socket(link.arc_socket.clone(), &message_for_client).is_ok() {}
+            }
+            DccMessageType::Stop => {
+                match outgoing_stop_request(
+                    dcc_msg,
+                    link.arc_socket.clone(),
+                    link.arc_transfers_communication.clone(),
+                    link.arc_ongoing_transfers.clone(),
+                ) {
+                    Ok(_) => {}
+                    Err(e) => println!("[ERROR] Error stopping file transfer: {e:?}"),
+                };
+            }
+            DccMessageType::Resume => {
+                match outgoing_resume_request(
+                    dcc_msg,
+                    link.arc_socket.clone(),
+                    link.tx_chats.clone(),
+                    link.arc_transfers_communication.clone(),
+                    link.arc_ongoing_transfers.clone(),
+                ) {
+                    Ok(_) => {}
+                    Err(e) => println!("[ERROR] Error resuming file transfer: {e:?}"),
+                };
+            }
+            _ => reject_message_for_client(&link, dcc_msg.command),
+        }
+    }
+    close(&link.connection);
+}
 
-        while let Ok(message_for_client) = dcc_receiver.recv() {
-            let arc_socket_clone_3 = arc_socket_clone_2.clone();
-            if let Ok(dcc_msg_for_client) = DccMessage::deserialize(message_for_client.clone()) {
-                match dcc_msg_for_client.command {
-                    DccMessageType::Chat => {
-                        if write_socket(arc_socket_clone_2.clone(), &message_for_client).is_ok() {};
-                    }
-                    DccMessageType::Send => {
-                        if dcc_msg_for_client.parameters.len() < 4 {
-                            println!("[ERROR] Invalid DCC SEND command. Not enough parameters.");
-                            continue;
-                        }
-                        match outgoing_send_request(
-                            requested_client_clone_1.clone(),
-                            arc_socket_clone_2.clone(),
-                            dcc_msg_for_client.clone(),
-                            tx_chats_clone_2.clone(),
-                            arc_transfers_communication_clone.clone(),
-                            arc_ongoing_transfers_clone.clone(),
-                        ) {
-                            Ok(_) => {}
-                            Err(e) => println!("[ERROR] Transfer failed: {e:?}"),
-                        };
-                    }
-                    DccMessageType::Close => {
-                        outgoing_close_request(
-                            arc_socket_clone_2,
-                            dcc_connections_clone,
-                            requested_client_clone_1,
-                            message_for_client,
-                        );
-                        return;
-                    }
-                    DccMessageType::Stop => {
-                        match outgoing_stop_request(
-                            dcc_msg_for_client.clone(),
-                            arc_socket_clone_2.clone(),
-                            arc_transfers_communication_clone.clone(),
-                            arc_ongoing_transfers_clone.clone(),
-                        ) {
-                            Ok(_) => {}
-                            Err(e) => println!("[ERROR] Error stopping transfer: {e:?}"),
-                        };
-                    }
-                    DccMessageType::Resume => {
-                        match outgoing_resume_request(
-                            dcc_msg_for_client.clone(),
-                            arc_socket_clone_3.clone(),
-                            tx_chats_clone_2.clone(),
-                            arc_transfers_communication_clone.clone(),
-                            arc_ongoing_transfers_clone.clone(),
-                        ) {
-                            Ok(_) => {}
-                            Err(e) => {
-                                println!("[ERROR] Error resuming file transfer: {e:?}");
-                                continue;
-                            }
+/// Answers a message of the requested client that can't be managed once the connection is established with a
+/// DCC ERROR message, so the requested client knows it was ignored, and the connection goes on
+fn reject_message_from_client(link: &DccLink, command: DccMessageType) {
+    println!(
+        "[ERROR] Unexpected DCC {} message received from {}",
+        command_name(command.clone()),
+        link.requested_client
+    );
+    let message = error_message(&link.requested_client, command);
+    if write_socket(link.arc_socket.clone(), &message).is_err() {
+        println!("[ERROR] Error answering {}", link.requested_client);
+    }
+}
+
+/// Notifies the interface that a message of the current client can't be sent once the connection is
+/// established, and the connection goes on
+fn reject_message_for_client(link: &DccLink, command: DccMessageType) {
+    let description = format!(
+        "DCC {} can't be sent to {} once the connection is established.",
+        command_name(command),
+        link.requested_client
+    );
+    println!("[ERROR] {description}");
+    let response = Response::DccResponse {
+        response: DccResponse::ErrorResponse { description },
+    };
+    if link.tx_chats.send(response).is_ok() {};
+}
+
+/// Notifies the interface that the requested client couldn't manage a message of the current client
+/// `DCC ERROR <client> <description>`
+fn incoming_error_message(link: &DccLink, dcc_msg: DccMessage) {
+    let description = format!("{}: {}", link.requested_client, error_description(&dcc_msg));
+    println!("[ERROR] {description}");
+    let response = Response::DccResponse {
+        response: DccResponse::ErrorResponse { description },
+    };
+    if link.tx_chats.send(response).is_ok() {};
+}
+
+/// Returns the DCC ERROR message that tells the requested client its message with the command was ignored
+fn error_message(requested_client: &str, command: DccMessageType) -> String {
+    format!(
+        "DCC ERROR {requested_client} Unexpected {} message",
+        command_name(command)
+    )
+}
+
+/// Returns the description of a DCC ERROR message
+fn error_description(dcc_msg: &DccMessage) -> String {
+    match dcc_msg.parameters.get(1..) {
+        Some(description) if !description.is_empty() => description.join(" "),
+        _ => "Unknown error".to_owned(),
+    }
+}
+
+/// Returns the name of the command that is sent in the dcc messages
+fn command_name(command: DccMessageType) -> String {
+    DccMessageType::dcc_message_type_to_string(command).unwrap_or_else(|_| "INVALID".to_owned())
+}
+
+/// Waits for the answer of the requested client to a passive offer, with the address it is listening on
+/// and the token of the offer, and connects to it, until the deadline
+/// Returns why the offer was left without an answer if it is closed, it expires or the address can't be reached
+fn wait_for_passive_connection(
+    dcc_receiver: &Receiver<String>,
+    token: &str,
+    requested_client: &str,
+    tx_chats: &glib::Sender<Response>,
+    deadline: Instant,
+) -> Result<TcpStream, Unanswered> {
+    loop {
+        let message =
+            match dcc_receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(message) => message,
+                Err(RecvTimeoutError::Timeout) => return Err(Unanswered::TimedOut),
+                Err(RecvTimeoutError::Disconnected) => return Err(Unanswered::Failed),
+            };
+        let answer = match DccMessage::deserialize(message) {
+            Ok(answer) => answer,
+            Err(e) => {
+                println!("[ERROR] Invalid DCC message received: {e:?}");
+                continue;
+            }
+        };
+        match answer.command {
+            DccMessageType::Chat if answer.parameters.get(3).map(|t| t.as_str()) == Some(token) => {
+                let ip = answer.parameters[1].to_owned();
+                let port = answer.parameters[2].to_owned();
+                match TcpStream::connect(format!("{ip}:{port}")) {
+                    Ok(socket) => return Ok(socket),
+                    Err(e) => {
+                        println!("[ERROR] Error connecting to {ip}:{port}. {e:?}");
+                        let response = Response::DccResponse {
+                            response: DccResponse::ChatAddressErrorResponse {
+                                sender: requested_client.to_owned(),
+                            },
                         };
-                    }
-                    _ => {
-                        todo!();
+                        if tx_chats.send(response).is_ok() {};
+                        return Err(Unanswered::Failed);
                     }
                 }
             }
+            DccMessageType::Close => return Err(Unanswered::Cancelled),
+            _ => println!(
+                "[ERROR] Unexpected DCC command while waiting for {requested_client}: {:?}",
+                answer.command
+            ),
         }
-    });
+    }
+}
 
-    Ok(())
+/// Opens a listening socket for a passive offer of the requested client, on the address the current
+/// client uses to reach the server and a port chosen by the system, and sends the address to the
+/// requested client through the server with the token of the offer
+/// Returns a ClientError if the socket can't be opened or the requested client doesn't connect
+fn listen_for_passive_connection(
+    arc_server_socket: Arc<TcpStream>,
+    nickname: &str,
+    requested_client: &str,
+    token: &str,
+) -> Result<TcpStream, ClientError> {
+    let ip = arc_server_socket.local_addr()?.ip();
+    let listener = TcpListener::bind((ip, 0))?;
+    let port = listener.local_addr()?.port();
+    write_socket(
+        arc_server_socket,
+        &format!(":{nickname} DCC CHAT {requested_client} {ip} {port} {token}"),
+    )?;
+    let (socket, _) = listener.accept()?;
+    Ok(socket)
+}
+
+/// Exchanges the keys with the requested client if the connection is secure, and notifies the
+/// interface that the connection is encrypted
+/// Returns the keys shared with the requested client, or None if the connection is not secure
+/// Returns a ClientError if the keys could not be exchanged
+fn secure_connection(
+    secure: bool,
+    arc_socket: Arc<TcpStream>,
+    requested_client: &str,
+    tx_chats: &glib::Sender<Response>,
+) -> Result<Option<SecureSession>, ClientError> {
+    if !secure {
+        return Ok(None);
+    }
+    match exchange_keys(arc_socket, requested_client) {
+        Ok(session) => {
+            let response = Response::DccResponse {
+                response: DccResponse::Secured {
+                    sender: requested_client.to_owned(),
+                },
+            };
+            if tx_chats.send(response).is_ok() {};
+            Ok(Some(session))
+        }
+        Err(e) => {
+            println!("[ERROR] Key exchange with {requested_client} failed: {e:?}");
+            let response = Response::DccResponse {
+                response: DccResponse::ErrorResponse {
+                    description: format!(
+                        "Could not secure the connection with {requested_client}."
+                    ),
+                },
+            };
+            if tx_chats.send(response).is_ok() {};
+            Err(e)
+        }
+    }
+}
+
+/// Returns the chat message received, opening it with the keys of the session if the connection is
+/// secure, or None if it was not sealed with them
+fn read_chat_message(dcc_msg: DccMessage, session: &Option<SecureSession>) -> Option<DccMessage> {
+    match session {
+        Some(session) => open_chat_message(&dcc_msg, session),
+        None => Some(dcc_msg),
+    }
+}
+
+/// Returns the messages written to the socket for a chat message of the current client, sealing it
+/// with the keys of the session if the connection is secure
+fn write_chat_message(
+    dcc_msg: DccMessage,
+    message_for_client: String,
+    session: &Option<SecureSession>,
+) -> Vec<String> {
+    match session {
+        Some(session) => seal_chat_message(&dcc_msg, session),
+        None => vec![message_for_client],
+    }
+}
+
+/// Returns if the room message is a chat line, whose text is sealed if the connection is secure
+fn is_room_line(dcc_msg: &DccMessage) -> bool {
+    dcc_msg.parameters.len() > 2 && dcc_msg.parameters[1].eq_ignore_ascii_case("MSG")
+}
+
+/// Manages a room message received from the requested client, opening its text with the keys of the
+/// session if the connection is secure
+fn manage_room_message(
+    dcc_msg: DccMessage,
+    session: &Option<SecureSession>,
+    requested_client: &str,
+    dcc_rooms: &DccRooms,
+    dcc_connections: &Arc<RwLock<HashMap<String, SyncSender<String>>>>,
+    tx_chats: &glib::Sender<Response>,
+) {
+    let dcc_msg = match session {
+        Some(session) if is_room_line(&dcc_msg) => match open_room_message(&dcc_msg, session) {
+            Some(dcc_msg) => dcc_msg,
+            None => {
+                println!("[ERROR] Invalid encrypted message received");
+                return;
+            }
+        },
+        _ => dcc_msg,
+    };
+    if let Err(e) = incoming_room_request(
+        requested_client.to_owned(),
+        dcc_msg,
+        dcc_rooms.clone(),
+        dcc_connections.clone(),
+        tx_chats.clone(),
+    ) {
+        println!("[ERROR] Error managing room message: {e:?}");
+    }
+}
+
+/// Returns the messages written to the socket for a room message, sealing the text of the chat lines
+/// with the keys of the session if the connection is secure
+fn write_room_message(
+    dcc_msg: DccMessage,
+    message_for_client: String,
+    session: &Option<SecureSession>,
+) -> Vec<String> {
+    match session {
+        Some(session) if is_room_line(&dcc_msg) => seal_room_message(&dcc_msg, session),
+        _ => vec![message_for_client],
+    }
+}
+
+#[cfg(test)]
+mod dcc_management_test {
+    use std::{
+        net::{TcpListener, TcpStream},
+        sync::{mpsc::sync_channel, Arc},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use model::{
+        dcc::{DccMessage, DccMessageType},
+        socket::{read_socket, write_socket},
+    };
+
+    use super::{
+        accept_before, error_description, error_message, listen_for_passive_connection,
+        wait_for_first_message, Unanswered,
+    };
+
+    #[test]
+    fn test_passive_offer_is_answered_through_the_server_with_the_token() {
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let server_address = server.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let server_socket = Arc::new(TcpStream::connect(server_address).unwrap());
+            listen_for_passive_connection(server_socket, "bob", "alice", "1234").unwrap()
+        });
+        let (server_side, _) = server.accept().unwrap();
+
+        let answer = DccMessage::deserialize(read_socket(Arc::new(server_side)).unwrap()).unwrap();
+        assert_eq!(answer.prefix, Some("bob".to_string()));
+        assert_eq!(answer.parameters[0], "alice");
+        assert_eq!(answer.parameters[1], "127.0.0.1");
+        assert_eq!(answer.parameters[3], "1234");
+        assert_eq!(answer.passive_token(), None);
+
+        let alice_socket =
+            TcpStream::connect(format!("{}:{}", answer.parameters[1], answer.parameters[2]))
+                .unwrap();
+        let bob_socket = handle.join().unwrap();
+        assert_eq!(
+            bob_socket.peer_addr().unwrap(),
+            alice_socket.local_addr().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_unanswered_request_expires_or_is_cancelled() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (dcc_sender, dcc_receiver) = sync_channel::<String>(0);
+        let deadline = Instant::now() + Duration::from_millis(300);
+        assert_eq!(
+            accept_before(&listener, &dcc_receiver, deadline).unwrap_err(),
+            Unanswered::TimedOut
+        );
+        assert!(Instant::now() >= deadline);
+
+        let handle = thread::spawn(move || dcc_sender.send("DCC CLOSE bob".to_owned()));
+        let deadline = Instant::now() + Duration::from_secs(10);
+        assert_eq!(
+            accept_before(&listener, &dcc_receiver, deadline).unwrap_err(),
+            Unanswered::Cancelled
+        );
+        assert!(handle.join().unwrap().is_ok());
+    }
+
+    #[test]
+    fn test_request_waits_for_the_answer_of_the_connected_client() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let (_dcc_sender, dcc_receiver) = sync_channel::<String>(0);
+        let bob_socket = TcpStream::connect(address).unwrap();
+        let deadline = Instant::now() + Duration::from_millis(300);
+        let alice_socket = accept_before(&listener, &dcc_receiver, deadline).unwrap();
+
+        assert_eq!(
+            wait_for_first_message(&alice_socket, &dcc_receiver, deadline).unwrap_err(),
+            Unanswered::TimedOut
+        );
+
+        write_socket(Arc::new(bob_socket), "DCC ACCEPT alice").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        assert!(wait_for_first_message(&alice_socket, &dcc_receiver, deadline).is_ok());
+        let answer = DccMessage::deserialize(read_socket(Arc::new(alice_socket)).unwrap()).unwrap();
+        assert_eq!(answer.parameters[0], "alice");
+    }
+
+    #[test]
+    fn test_unexpected_messages_are_answered_with_an_error() {
+        let answer = error_message("bob", DccMessageType::Accept);
+        assert_eq!(answer, "DCC ERROR bob Unexpected ACCEPT message");
+        let answer = DccMessage::deserialize(answer).unwrap();
+        assert_eq!(answer.command, DccMessageType::Error);
+        assert_eq!(error_description(&answer), "Unexpected ACCEPT message");
+
+        let answer = DccMessage::deserialize("DCC ERROR bob".to_owned()).unwrap();
+        assert_eq!(error_description(&answer), "Unknown error");
+    }
 }
@@ -0,0 +1,145 @@
+use std::path::{Path, PathBuf};
+
+use super::batch::path_inside;
+use crate::gui::preferences::{CollisionPolicy, Preferences, PREFERENCES_PATH};
+
+/// Returns the folder where the received files are saved, chosen in the preferences
+/// It is read every time a file is requested, so a new folder applies to the next files
+pub fn download_directory() -> String {
+    Preferences::load(PREFERENCES_PATH).download_directory
+}
+
+/// Returns if the folder already has a file with the name sent by the requested client
+pub fn is_already_received(folder: &str, file_name: &str) -> bool {
+    path_inside(folder, file_name).is_some_and(|path| path.exists())
+}
+
+/// Returns why a file requested by the requested client is declined, or None if it can be received
+/// The file is declined if its size is invalid or bigger than the maximum size of the preferences, or if
+/// its extension is blocked in the preferences
+pub fn declined_reason(
+    file_name: &str,
+    file_size: f64,
+    preferences: &Preferences,
+) -> Option<String> {
+    if !file_size.is_finite() || file_size < 0.0 {
+        return Some(format!("The size of {file_name} is invalid."));
+    }
+    if let Some(max_file_size) = preferences.dcc_max_file_size_bytes() {
+        if file_size > max_file_size as f64 {
+            return Some(format!(
+                "{file_name} is bigger than the maximum of {} MB.",
+                preferences.dcc_max_file_size
+            ));
+        }
+    }
+    let extension = Path::new(file_name)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    match extension {
+        Some(extension) if preferences.dcc_blocked_extensions().contains(&extension) => Some(
+            format!("The extension .{extension} of {file_name} is blocked."),
+        ),
+        _ => None,
+    }
+}
+
+/// Returns the path where a file received with the name sent by the requested client is saved, inside the
+/// folder, or None if the name is not a relative path that stays inside the folder
+/// If the folder already has a file with the name, it is overwritten or the file is saved with the first
+/// number after its name that is free, like `photo (1).jpg`, depending on the policy. The policy that asks
+/// the user renames the file, since the interface sends the answer of the user when the file is accepted
+pub fn save_path(folder: &str, file_name: &str, policy: CollisionPolicy) -> Option<PathBuf> {
+    let path = path_inside(folder, file_name)?;
+    if policy == CollisionPolicy::Overwrite || !path.exists() {
+        return Some(path);
+    }
+    (1..)
+        .map(|number| numbered_path(&path, number))
+        .find(|path| !path.exists())
+}
+
+/// Returns the path with the number after the name of the file and before its extension
+fn numbered_path(path: &Path, number: u32) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let name = match path.extension() {
+        Some(extension) => format!("{stem} ({number}).{}", extension.to_string_lossy()),
+        None => format!("{stem} ({number})"),
+    };
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod dcc_downloads_test {
+    use std::fs;
+
+    use super::{declined_reason, is_already_received, save_path};
+    use crate::gui::preferences::{CollisionPolicy, Preferences};
+
+    #[test]
+    fn test_received_files_are_renamed_or_overwritten_when_they_already_exist() {
+        let folder = std::env::temp_dir().join(format!("dcc_downloads_{}", std::process::id()));
+        fs::create_dir_all(folder.join("photos")).unwrap();
+        let folder_name = folder.to_str().unwrap();
+        fs::write(folder.join("a.txt"), "a").unwrap();
+        fs::write(folder.join("a (1).txt"), "a").unwrap();
+        fs::write(folder.join("photos").join("b"), "b").unwrap();
+
+        assert!(is_already_received(folder_name, "a.txt"));
+        assert!(!is_already_received(folder_name, "c.txt"));
+        assert_eq!(
+            save_path(folder_name, "c.txt", CollisionPolicy::Rename).unwrap(),
+            folder.join("c.txt")
+        );
+        assert_eq!(
+            save_path(folder_name, "a.txt", CollisionPolicy::Rename).unwrap(),
+            folder.join("a (2).txt")
+        );
+        assert_eq!(
+            save_path(folder_name, "a.txt", CollisionPolicy::Ask).unwrap(),
+            folder.join("a (2).txt")
+        );
+        assert_eq!(
+            save_path(folder_name, "a.txt", CollisionPolicy::Overwrite).unwrap(),
+            folder.join("a.txt")
+        );
+        assert_eq!(
+            save_path(folder_name, "photos/b", CollisionPolicy::Rename).unwrap(),
+            folder.join("photos").join("b (1)")
+        );
+        assert!(save_path(folder_name, "../a.txt", CollisionPolicy::Overwrite).is_none());
+
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn test_files_too_big_or_with_blocked_extensions_are_declined() {
+        let preferences = Preferences {
+            dcc_max_file_size: 1,
+            dcc_blocked_extensions: "exe,sh".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(declined_reason("a.txt", 1048576.0, &preferences), None);
+        assert_eq!(declined_reason("photos/README", 10.0, &preferences), None);
+        assert_eq!(
+            declined_reason("a.txt", 1048577.0, &preferences).unwrap(),
+            "a.txt is bigger than the maximum of 1 MB."
+        );
+        assert_eq!(
+            declined_reason("tools/setup.EXE", 10.0, &preferences).unwrap(),
+            "The extension .exe of tools/setup.EXE is blocked."
+        );
+        assert!(declined_reason("run.sh", 10.0, &preferences).is_some());
+        assert!(declined_reason("a.txt", -1.0, &preferences).is_some());
+        assert!(declined_reason("a.txt", f64::NAN, &preferences).is_some());
+
+        let preferences = Preferences {
+            dcc_blocked_extensions: "".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(declined_reason("setup.exe", 1e12, &preferences), None);
+    }
+}
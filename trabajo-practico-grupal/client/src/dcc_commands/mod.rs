@@ -1,7 +1,18 @@
+pub mod address;
+pub mod batch;
 pub mod chat;
 pub mod close;
+pub mod connection;
 pub mod dcc_management;
+pub mod downloads;
+pub mod queue;
+pub mod relay;
 pub mod resume;
+pub mod room;
+pub mod saved_transfers;
+pub mod secure;
 pub mod send;
+pub mod speed;
 pub mod stop;
 pub mod transfer;
+pub mod typing;
@@ -0,0 +1,271 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    net::TcpStream,
+    sync::{mpsc::SyncSender, Arc, Mutex, RwLock},
+    thread,
+};
+
+use super::{
+    connection::{drive, state, DccEvent, DccState, SharedDccConnection},
+    send::{outgoing_send_request, sent_file_name},
+};
+use crate::gui::preferences::{Preferences, PREFERENCES_PATH};
+use gtk::glib;
+use model::{
+    crypto::SecureSession,
+    dcc::DccMessage,
+    responses::{
+        dcc::{DccResponse, TransferState},
+        ongoing_transfer::OngoingTransfer,
+        response::Response,
+    },
+};
+
+/// Returns the most files sent at the same time through a dcc connection, chosen in the preferences
+/// It is read every time a file of the queue can start, so a new limit applies to the next files
+pub fn max_transfers() -> usize {
+    Preferences::load(PREFERENCES_PATH).dcc_max_transfers as usize
+}
+
+/// Struct that represents the files sent through a dcc connection, in the order they were sent
+/// # Fields
+/// * `pending`: The files waiting to be sent, with their send requests
+/// * `active`: The files being sent
+/// * `done`: The files that are no longer sent, because they were sent, declined, paused or failed
+#[derive(Debug, Default)]
+pub struct TransferQueue {
+    pending: VecDeque<(String, DccMessage)>,
+    active: Vec<String>,
+    done: Vec<String>,
+}
+
+impl TransferQueue {
+    /// Adds a file at the end of the queue
+    /// Returns false if a file with the same name is already waiting or being sent
+    pub fn push(&mut self, file_name: String, dcc_msg: DccMessage) -> bool {
+        if self
+            .state(&file_name)
+            .is_some_and(|state| state != TransferState::Done)
+        {
+            return false;
+        }
+        self.done.retain(|done| *done != file_name);
+        self.pending.push_back((file_name, dcc_msg));
+        true
+    }
+
+    /// Returns the next file to send with its send request, if there is one waiting and less than
+    /// `limit` files are being sent, and marks it as active
+    pub fn next(&mut self, limit: usize) -> Option<(String, DccMessage)> {
+        if self.active.len() >= limit {
+            return None;
+        }
+        let (file_name, dcc_msg) = self.pending.pop_front()?;
+        self.active.push(file_name.clone());
+        Some((file_name, dcc_msg))
+    }
+
+    /// Marks the file as done, so the next file of the queue can be sent
+    pub fn finish(&mut self, file_name: &str) {
+        self.active.retain(|active| active != file_name);
+        self.done.push(file_name.to_owned());
+    }
+
+    /// Returns the state of the file in the queue, or None if it was never added
+    pub fn state(&self, file_name: &str) -> Option<TransferState> {
+        if self.pending.iter().any(|(pending, _)| pending == file_name) {
+            Some(TransferState::Pending)
+        } else if self.active.iter().any(|active| active == file_name) {
+            Some(TransferState::Active)
+        } else if self.done.iter().any(|done| done == file_name) {
+            Some(TransferState::Done)
+        } else {
+            None
+        }
+    }
+}
+
+/// Struct that sends the files of a dcc connection in the order they are requested, with at most
+/// `max_transfers()` of them at the same time. Each file is sent in its own thread, which starts the
+/// next files waiting when it ends. Every change of state of a file is sent to the interface
+/// # Fields
+/// * `queue`: The files sent through the connection
+/// * `requested_client`: The client the files are sent to
+/// * `arc_socket`: The socket of the dcc connection
+/// * `tx_chats`: The channel to send the responses to the interface
+/// * `arc_transfers_communication`: The channels to communicate with the transfers in progress
+/// * `arc_ongoing_transfers`: The unfinished transfers
+/// * `session`: The keys shared with the requested client, if the connection is secure
+/// * `connection`: The state of the dcc connection, which is transferring while files are being sent
+#[derive(Clone)]
+pub struct TransferScheduler {
+    queue: Arc<Mutex<TransferQueue>>,
+    requested_client: String,
+    arc_socket: Arc<TcpStream>,
+    tx_chats: glib::Sender<Response>,
+    arc_transfers_communication: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
+    arc_ongoing_transfers: Arc<RwLock<HashMap<String, OngoingTransfer>>>,
+    session: Option<SecureSession>,
+    connection: SharedDccConnection,
+}
+
+impl TransferScheduler {
+    /// Creates a new scheduler, with an empty queue, for the files sent through a dcc connection
+    pub fn new(
+        requested_client: String,
+        arc_socket: Arc<TcpStream>,
+        tx_chats: glib::Sender<Response>,
+        arc_transfers_communication: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
+        arc_ongoing_transfers: Arc<RwLock<HashMap<String, OngoingTransfer>>>,
+        session: Option<SecureSession>,
+        connection: SharedDccConnection,
+    ) -> Self {
+        TransferScheduler {
+            queue: Arc::new(Mutex::new(TransferQueue::default())),
+            requested_client,
+            arc_socket,
+            tx_chats,
+            arc_transfers_communication,
+            arc_ongoing_transfers,
+            session,
+            connection,
+        }
+    }
+
+    /// Adds the file of a send request to the queue, and starts sending it if less than `max_transfers()`
+    /// files are being sent
+    /// If a file with the same name is already waiting or being sent, the interface is notified instead
+    pub fn schedule(&self, dcc_msg: DccMessage) {
+        let file_name = sent_file_name(&dcc_msg);
+
+        let queued = match self.queue.lock() {
+            Ok(mut queue) => queue.push(file_name.clone(), dcc_msg),
+            Err(_) => {
+                println!("[ERROR] Error locking the transfer queue");
+                return;
+            }
+        };
+        if !queued {
+            println!("[ERROR] {file_name} is already in the transfer queue");
+            let response = Response::DccResponse {
+                response: DccResponse::OngoingTransfer {
+                    sender: self.requested_client.clone(),
+                    file_name,
+                },
+            };
+            if self.tx_chats.send(response).is_ok() {};
+            return;
+        }
+
+        self.send_state(&file_name, TransferState::Pending);
+        self.start_next();
+    }
+
+    /// Starts sending the files waiting in the queue, until `max_transfers()` of them are being sent
+    fn start_next(&self) {
+        loop {
+            let next = match self.queue.lock() {
+                Ok(mut queue) => queue.next(max_transfers()),
+                Err(_) => {
+                    println!("[ERROR] Error locking the transfer queue");
+                    return;
+                }
+            };
+            let (file_name, dcc_msg) = match next {
+                Some(next) => next,
+                None => return,
+            };
+
+            self.send_state(&file_name, TransferState::Active);
+            self.drive_connection(DccEvent::StartTransfer);
+            let scheduler = self.clone();
+            thread::spawn(move || {
+                if let Err(e) = outgoing_send_request(
+                    scheduler.requested_client.clone(),
+                    scheduler.arc_socket.clone(),
+                    dcc_msg,
+                    scheduler.tx_chats.clone(),
+                    scheduler.arc_transfers_communication.clone(),
+                    scheduler.arc_ongoing_transfers.clone(),
+                    scheduler.session.clone(),
+                ) {
+                    println!("[ERROR] Error sending file: {e:?}");
+                }
+                match scheduler.queue.lock() {
+                    Ok(mut queue) => queue.finish(&file_name),
+                    Err(_) => println!("[ERROR] Error locking the transfer queue"),
+                }
+                scheduler.drive_connection(DccEvent::EndTransfer);
+                scheduler.send_state(&file_name, TransferState::Done);
+                scheduler.start_next();
+            });
+        }
+    }
+
+    /// Changes the state of the dcc connection when a file starts or ends, unless the connection was closed and
+    /// the file was cut off
+    fn drive_connection(&self, event: DccEvent) {
+        if state(&self.connection) != DccState::Closed {
+            drive(&self.connection, event);
+        }
+    }
+
+    /// Sends the state of the file in the queue to the interface
+    fn send_state(&self, file_name: &str, state: TransferState) {
+        let response = Response::DccResponse {
+            response: DccResponse::TransferQueue {
+                sender: self.requested_client.clone(),
+                file_name: file_name.to_owned(),
+                state,
+            },
+        };
+        if self.tx_chats.send(response).is_ok() {};
+    }
+}
+
+#[cfg(test)]
+mod dcc_queue_test {
+    use model::{dcc::DccMessage, responses::dcc::TransferState};
+
+    use super::TransferQueue;
+
+    fn send_request(file_name: &str) -> DccMessage {
+        DccMessage::deserialize(format!("DCC SEND bob ./files/{file_name} 127.0.0.1 0 10")).unwrap()
+    }
+
+    #[test]
+    fn test_files_are_sent_in_order_with_at_most_the_limit_at_the_same_time() {
+        let mut queue = TransferQueue::default();
+        for file_name in ["a.txt", "b.txt", "c.txt"] {
+            assert!(queue.push(file_name.to_owned(), send_request(file_name)));
+        }
+        assert_eq!(queue.state("a.txt"), Some(TransferState::Pending));
+
+        let (first, dcc_msg) = queue.next(2).unwrap();
+        assert_eq!(first, "a.txt");
+        assert_eq!(dcc_msg.parameters[1], "./files/a.txt");
+        assert_eq!(queue.next(2).unwrap().0, "b.txt");
+        assert!(queue.next(2).is_none());
+        assert_eq!(queue.state("b.txt"), Some(TransferState::Active));
+        assert_eq!(queue.state("c.txt"), Some(TransferState::Pending));
+
+        queue.finish("a.txt");
+        assert_eq!(queue.state("a.txt"), Some(TransferState::Done));
+        assert_eq!(queue.next(2).unwrap().0, "c.txt");
+        assert!(queue.next(5).is_none());
+        assert_eq!(queue.state("d.txt"), None);
+    }
+
+    #[test]
+    fn test_a_file_can_only_be_queued_again_when_it_is_done() {
+        let mut queue = TransferQueue::default();
+        assert!(queue.push("a.txt".to_owned(), send_request("a.txt")));
+        assert!(!queue.push("a.txt".to_owned(), send_request("a.txt")));
+        queue.next(1);
+        assert!(!queue.push("a.txt".to_owned(), send_request("a.txt")));
+
+        queue.finish("a.txt");
+        assert!(queue.push("a.txt".to_owned(), send_request("a.txt")));
+        assert_eq!(queue.state("a.txt"), Some(TransferState::Pending));
+    }
+}
@@ -0,0 +1,738 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{Read, Write},
+    net::TcpStream,
+    path::Path,
+    sync::{
+        mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender},
+        Arc, Mutex, RwLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::{
+    batch::path_inside,
+    downloads::{declined_reason, save_path},
+    speed::TransferMeter,
+    transfer::{checksum_matches, file_checksum, remove_interface_communication, send_progress},
+};
+use crate::gui::preferences::{CollisionPolicy, Preferences, PREFERENCES_PATH};
+use gtk::glib;
+use model::{
+    client_errors::ClientError,
+    crypto::{from_hex, to_hex},
+    dcc::{DccMessage, DccMessageType},
+    responses::{dcc::DccResponse, response::Response},
+    socket::write_socket,
+};
+
+/// The bytes of a file sent in each chunk. They are sent in hexadecimal, so the chunk fits in a line of the
+/// server with the names of the clients and the file
+pub const RELAY_CHUNK_SIZE: u64 = 160;
+/// The most chunks sent and not acknowledged yet by the requested client, so the server is not flooded
+pub const RELAY_WINDOW: u64 = 8;
+/// The time the requested client has to accept a relayed file
+pub const RELAY_ANSWER_TIMEOUT: Duration = Duration::from_secs(60);
+/// The time the requested client has to acknowledge the chunks sent before the transfer is cancelled
+pub const RELAY_ACK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Struct that keeps the flow of a relayed file, which is sent in chunks that the requested client
+/// acknowledges, without more than `window` chunks waiting for their acknowledgement
+/// # Fields
+/// * `file_size`: The size of the file
+/// * `chunk_size`: The bytes of each chunk
+/// * `window`: The most chunks not acknowledged
+/// * `sent`: The bytes sent
+/// * `acknowledged`: The bytes the requested client received
+#[derive(Debug)]
+pub struct RelayFlow {
+    file_size: u64,
+    chunk_size: u64,
+    window: u64,
+    sent: u64,
+    acknowledged: u64,
+}
+
+impl RelayFlow {
+    pub fn new(file_size: u64, chunk_size: u64, window: u64) -> Self {
+        RelayFlow {
+            file_size,
+            chunk_size,
+            window,
+            sent: 0,
+            acknowledged: 0,
+        }
+    }
+
+    /// Returns the offset and the length of the next chunk, and marks it as sent, or None if the file was
+    /// sent or the window is full
+    pub fn next_chunk(&mut self) -> Option<(u64, u64)> {
+        if self.sent >= self.file_size
+            || self.sent - self.acknowledged >= self.window * self.chunk_size
+        {
+            return None;
+        }
+        let offset = self.sent;
+        let length = self.chunk_size.min(self.file_size - self.sent);
+        self.sent += length;
+        Some((offset, length))
+    }
+
+    /// Records the bytes the requested client received
+    /// Returns false if it received more bytes than the sent ones, or less than the ones acknowledged before
+    pub fn acknowledge(&mut self, received: u64) -> bool {
+        if received > self.sent || received < self.acknowledged {
+            return false;
+        }
+        self.acknowledged = received;
+        true
+    }
+
+    pub fn acknowledged(&self) -> u64 {
+        self.acknowledged
+    }
+
+    /// Returns if the requested client received the whole file
+    pub fn is_done(&self) -> bool {
+        self.acknowledged >= self.file_size
+    }
+}
+
+/// Struct that represents a relayed file being received
+/// # Fields
+/// * `nickname`: The nickname of the current client, who answers the chunks
+/// * `file`: The file where the chunks are written
+/// * `path`: The path of the file
+/// * `file_size`: The size of the file
+/// * `received`: The bytes received
+/// * `checksum`: The checksum sent with the offer
+/// * `meter`: The meter of the throughput of the transfer
+pub struct IncomingRelay {
+    nickname: String,
+    file: File,
+    path: String,
+    file_size: u64,
+    received: u64,
+    checksum: Option<String>,
+    meter: TransferMeter,
+}
+
+impl IncomingRelay {
+    /// Writes the chunk at the end of the file, and returns the bytes received
+    /// Returns None if the chunk is not the next one of the file, it goes beyond the size of the file or it
+    /// can't be written
+    pub fn write_chunk(&mut self, offset: u64, data: &[u8]) -> Option<u64> {
+        let received = self.received + data.len() as u64;
+        if offset != self.received || received > self.file_size {
+            return None;
+        }
+        self.file.write_all(data).ok()?;
+        self.received = received;
+        Some(received)
+    }
+
+    /// Returns if the whole file was received and it matches the checksum sent with the offer
+    pub fn is_complete(&self) -> bool {
+        self.received == self.file_size && checksum_matches(&self.path, self.checksum.as_deref())
+    }
+}
+
+/// Struct that has the relayed files being sent and received
+/// # Fields
+/// * `outgoing`: The channels to send the answers of the requested clients to the files being sent
+/// * `incoming`: The files being received
+#[derive(Default)]
+pub struct RelayState {
+    outgoing: HashMap<String, SyncSender<String>>,
+    incoming: HashMap<String, IncomingRelay>,
+}
+
+/// The relayed files of the current client, shared by the threads that send and receive them
+pub type DccRelays = Arc<Mutex<RelayState>>;
+
+/// Struct that represents a relayed file being sent
+/// # Fields
+/// * `nickname`: The nickname of the current client
+/// * `requested_client`: The client the file is sent to
+/// * `file_path`: The path of the file
+/// * `file_name`: The name of the file received by the requested client
+/// * `file_size`: The size of the file
+struct OutgoingRelay {
+    nickname: String,
+    requested_client: String,
+    file_path: String,
+    file_name: String,
+    file_size: u64,
+}
+
+/// Returns the key of a relayed file, which is sent or received once at a time with each client
+fn relay_key(client: &str, file_name: &str) -> String {
+    format!("{client} {file_name}")
+}
+
+/// Sends a file to the requested client through the server, since a dcc connection with it can't be
+/// established. It is used when the clients can't reach each other, like behind a NAT
+/// `:<nickname> DCC RELAY <client> OFFER <file_path> <file_size> [file_name]`
+/// The file is offered to the requested client, and once it is accepted it is sent in chunks that the
+/// requested client acknowledges, with at most RELAY_WINDOW chunks waiting, in its own thread
+/// Returns a ClientError if the message is invalid or the file can't be read
+pub fn outgoing_relay_request(
+    dcc_msg: DccMessage,
+    arc_server_socket: Arc<TcpStream>,
+    dcc_relays: DccRelays,
+    tx_chats: glib::Sender<Response>,
+) -> Result<(), ClientError> {
+    let nickname = dcc_msg.prefix.clone().unwrap_or_default();
+    if nickname.is_empty()
+        || dcc_msg.parameters.len() < 4
+        || !dcc_msg.parameters[1].eq_ignore_ascii_case("OFFER")
+    {
+        println!("[ERROR] Invalid DCC RELAY offer: {dcc_msg:?}");
+        return Err(ClientError::InvalidArgs);
+    }
+    let file_path = dcc_msg.parameters[2].to_owned();
+    let file_name = match dcc_msg.parameters.get(4) {
+        Some(file_name) => file_name.to_owned(),
+        None => Path::new(&file_path)
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy().to_string())
+            .ok_or(ClientError::FileError)?,
+    };
+    let relay = OutgoingRelay {
+        nickname,
+        requested_client: dcc_msg.parameters[0].to_owned(),
+        file_path,
+        file_name,
+        file_size: dcc_msg.parameters[3].parse::<u64>().unwrap_or(0),
+    };
+
+    let key = relay_key(&relay.requested_client, &relay.file_name);
+    let (tx_answers, rx_answers) = sync_channel::<String>(RELAY_WINDOW as usize);
+    let mut relays_lock = dcc_relays.lock().map_err(|_| ClientError::LockError)?;
+    if relays_lock.outgoing.contains_key(&key) {
+        drop(relays_lock);
+        let response = Response::DccResponse {
+            response: DccResponse::OngoingTransfer {
+                sender: relay.requested_client,
+                file_name: relay.file_name,
+            },
+        };
+        if tx_chats.send(response).is_ok() {};
+        return Ok(());
+    }
+    relays_lock.outgoing.insert(key.clone(), tx_answers);
+    drop(relays_lock);
+
+    thread::spawn(move || {
+        if let Err(e) = send_relayed_file(&relay, &rx_answers, &arc_server_socket, &tx_chats) {
+            println!(
+                "[ERROR] Relayed transfer of {} failed: {e:?}",
+                relay.file_name
+            );
+            let cancel = format!(
+                ":{} DCC RELAY {} CANCEL {}",
+                relay.nickname, relay.requested_client, relay.file_name
+            );
+            if write_socket(arc_server_socket.clone(), &cancel).is_ok() {};
+            let response = Response::DccResponse {
+                response: DccResponse::TransferFailed {
+                    sender: relay.requested_client.clone(),
+                    file_name: relay.file_name.clone(),
+                },
+            };
+            if tx_chats.send(response).is_ok() {};
+        }
+        if let Ok(mut relays_lock) = dcc_relays.lock() {
+            relays_lock.outgoing.remove(&key);
+        }
+    });
+    Ok(())
+}
+
+/// Offers the file to the requested client and sends it in chunks once it is accepted, waiting for the
+/// acknowledgements of the requested client when the window is full
+/// Returns a ClientError if the file can't be read, the requested client cancels it or doesn't answer
+fn send_relayed_file(
+    relay: &OutgoingRelay,
+    rx_answers: &Receiver<String>,
+    arc_server_socket: &Arc<TcpStream>,
+    tx_chats: &glib::Sender<Response>,
+) -> Result<(), ClientError> {
+    let prefix = format!(":{} DCC RELAY {}", relay.nickname, relay.requested_client);
+    let checksum = file_checksum(&relay.file_path)?;
+    let mut file = File::open(&relay.file_path).map_err(|_| ClientError::FileError)?;
+    write_socket(
+        arc_server_socket.clone(),
+        &format!(
+            "{prefix} OFFER {} {} {checksum}",
+            relay.file_name, relay.file_size
+        ),
+    )?;
+
+    let answer = next_answer(rx_answers, RELAY_ANSWER_TIMEOUT)?;
+    match answer.as_str() {
+        "ACCEPT" => println!(
+            "[INFO] {} accepted {}",
+            relay.requested_client, relay.file_name
+        ),
+        "DECLINE" => {
+            let response = Response::DccResponse {
+                response: DccResponse::TransferDeclined {
+                    sender: relay.requested_client.clone(),
+                    file_name: relay.file_name.clone(),
+                },
+            };
+            if tx_chats.send(response).is_ok() {};
+            return Ok(());
+        }
+        _ => return Err(ClientError::InvalidArgs),
+    }
+
+    let mut flow = RelayFlow::new(relay.file_size, RELAY_CHUNK_SIZE, RELAY_WINDOW);
+    let mut meter = TransferMeter::new(None, 0, Instant::now());
+    let mut buffer = vec![0; RELAY_CHUNK_SIZE as usize];
+    loop {
+        while let Some((offset, length)) = flow.next_chunk() {
+            let chunk = &mut buffer[..length as usize];
+            file.read_exact(chunk).map_err(|_| ClientError::FileError)?;
+            write_socket(
+                arc_server_socket.clone(),
+                &format!(
+                    "{prefix} CHUNK {} {offset} {}",
+                    relay.file_name,
+                    to_hex(chunk)
+                ),
+            )?;
+        }
+        if flow.is_done() {
+            break;
+        }
+
+        let answer = next_answer(rx_answers, RELAY_ACK_TIMEOUT)?;
+        let received = match answer.split_once(' ') {
+            Some(("ACK", received)) => received.parse::<u64>().unwrap_or(u64::MAX),
+            _ => return Err(ClientError::InvalidArgs),
+        };
+        if !flow.acknowledge(received) {
+            return Err(ClientError::InvalidArgs);
+        }
+        let now = Instant::now();
+        meter.record(flow.acknowledged(), now);
+        if meter.should_report(flow.is_done(), now) {
+            send_progress(
+                flow.acknowledged() as f64,
+                relay.file_size as f64,
+                &meter,
+                &relay.requested_client,
+                &relay.file_name,
+                tx_chats.clone(),
+            )?;
+        }
+    }
+
+    write_socket(
+        arc_server_socket.clone(),
+        &format!("{prefix} DONE {}", relay.file_name),
+    )?;
+    if relay.file_size == 0 {
+        send_progress(
+            1.0,
+            1.0,
+            &meter,
+            &relay.requested_client,
+            &relay.file_name,
+            tx_chats.clone(),
+        )?;
+    }
+    Ok(())
+}
+
+/// Waits for the next answer of the requested client to a relayed file, which is its subcommand followed by
+/// its arguments after the file name, like `ACK 320`
+/// Returns a ClientError if the requested client cancels the file or doesn't answer before the timeout
+fn next_answer(rx_answers: &Receiver<String>, timeout: Duration) -> Result<String, ClientError> {
+    let answer = match rx_answers.recv_timeout(timeout) {
+        Ok(answer) => answer,
+        Err(RecvTimeoutError::Timeout) => {
+            println!("[ERROR] The relayed transfer was not answered");
+            return Err(ClientError::SocketError);
+        }
+        Err(RecvTimeoutError::Disconnected) => return Err(ClientError::SocketError),
+    };
+    let dcc_msg = DccMessage::deserialize(answer).map_err(|_| ClientError::InvalidArgs)?;
+    let subcommand = dcc_msg
+        .parameters
+        .get(1)
+        .map(|subcommand| subcommand.to_uppercase());
+    match subcommand.as_deref() {
+        Some("CANCEL") | None => Err(ClientError::InvalidArgs),
+        Some(subcommand) => Ok(std::iter::once(subcommand.to_owned())
+            .chain(dcc_msg.parameters.into_iter().skip(3))
+            .collect::<Vec<_>>()
+            .join(" ")),
+    }
+}
+
+/// Manages a relay message sent by another client through the server
+/// `:<client> DCC RELAY <nickname> OFFER|ACCEPT|DECLINE|CHUNK|ACK|DONE|CANCEL <file_name> ...`
+/// The offers are sent to the interface, which accepts or declines them, and the chunks of the files
+/// accepted are saved in the download folder of the preferences and acknowledged. The answers to the files
+/// sent by the current client are sent to the threads that send them
+/// Returns a ClientError if the message is invalid
+pub fn incoming_relay_request(
+    dcc_msg: DccMessage,
+    arc_server_socket: Arc<TcpStream>,
+    dcc_relays: DccRelays,
+    tx_chats: glib::Sender<Response>,
+    arc_dcc_interface_communication: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
+) -> Result<(), ClientError> {
+    let requested_client = match &dcc_msg.prefix {
+        Some(requested_client) if dcc_msg.parameters.len() >= 3 => requested_client.to_owned(),
+        _ => {
+            println!("[ERROR] Invalid DCC RELAY message: {dcc_msg:?}");
+            return Err(ClientError::InvalidArgs);
+        }
+    };
+    let key = relay_key(&requested_client, &dcc_msg.parameters[2]);
+
+    match dcc_msg.parameters[1].to_uppercase().as_str() {
+        "OFFER" => incoming_offer(
+            dcc_msg,
+            requested_client,
+            arc_server_socket,
+            dcc_relays,
+            tx_chats,
+            arc_dcc_interface_communication,
+        ),
+        "CHUNK" => incoming_chunk(
+            dcc_msg,
+            requested_client,
+            arc_server_socket,
+            dcc_relays,
+            tx_chats,
+        ),
+        "DONE" => {
+            let incoming = dcc_relays
+                .lock()
+                .map_err(|_| ClientError::LockError)?
+                .incoming
+                .remove(&key);
+            if let Some(incoming) = incoming {
+                finish_incoming(
+                    incoming,
+                    requested_client,
+                    dcc_msg.parameters[2].to_owned(),
+                    &tx_chats,
+                );
+            }
+            Ok(())
+        }
+        _ => {
+            let relays_lock = dcc_relays.lock().map_err(|_| ClientError::LockError)?;
+            let tx_answers = relays_lock.outgoing.get(&key).cloned();
+            drop(relays_lock);
+            if let Some(tx_answers) = tx_answers {
+                let answer = DccMessage::serialize(dcc_msg)?;
+                if tx_answers.try_send(answer).is_err() {
+                    println!("[ERROR] Too many answers to the relayed file {key}");
+                }
+            } else if dcc_msg.parameters[1].eq_ignore_ascii_case("CANCEL") {
+                cancel_incoming(
+                    &dcc_relays,
+                    &key,
+                    requested_client,
+                    dcc_msg.parameters[2].to_owned(),
+                    &tx_chats,
+                );
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Manages a file offered by the requested client through the server, which is declined if it is not a path
+/// inside the download folder, or if it is too big or has a blocked extension
+/// Otherwise the interface opens a conversation to receive files from the requested client through the
+/// server, and the user accepts or declines the file
+fn incoming_offer(
+    dcc_msg: DccMessage,
+    requested_client: String,
+    arc_server_socket: Arc<TcpStream>,
+    dcc_relays: DccRelays,
+    tx_chats: glib::Sender<Response>,
+    arc_dcc_interface_communication: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
+) -> Result<(), ClientError> {
+    let nickname = dcc_msg.parameters[0].to_owned();
+    let file_name = dcc_msg.parameters[2].to_owned();
+    let file_size = dcc_msg
+        .parameters
+        .get(3)
+        .and_then(|file_size| file_size.parse::<f64>().ok())
+        .unwrap_or(f64::NAN);
+    let checksum = dcc_msg.parameters.get(4).cloned();
+    let prefix = format!(":{nickname} DCC RELAY {requested_client}");
+
+    let preferences = Preferences::load(PREFERENCES_PATH);
+    let declined = match path_inside(&preferences.download_directory, &file_name) {
+        Some(_) => declined_reason(&file_name, file_size, &preferences)
+            .map(|reason| format!("{requested_client} sent a file that was declined. {reason}")),
+        None => Some(format!(
+            "{requested_client} sent a file with an invalid path."
+        )),
+    };
+    if let Some(description) = declined {
+        println!("[ERROR] {description} File: {file_name}");
+        write_socket(arc_server_socket, &format!("{prefix} DECLINE {file_name}"))?;
+        let response = Response::DccResponse {
+            response: DccResponse::ErrorResponse { description },
+        };
+        if tx_chats.send(response).is_ok() {};
+        return Ok(());
+    }
+
+    let (tx_interface, rx_interface) = sync_channel(0);
+    arc_dcc_interface_communication
+        .write()
+        .map_err(|_| ClientError::LockError)?
+        .insert(requested_client.clone(), tx_interface);
+    let response = Response::DccResponse {
+        response: DccResponse::Relayed {
+            sender: requested_client.clone(),
+        },
+    };
+    if tx_chats.send(response).is_ok() {};
+    let response = Response::DccResponse {
+        response: DccResponse::TransferRequest {
+            sender: requested_client.clone(),
+            file_name: file_name.clone(),
+            file_size,
+        },
+    };
+    if tx_chats.send(response).is_ok() {};
+
+    thread::spawn(move || {
+        let answer = rx_interface
+            .recv_timeout(RELAY_ANSWER_TIMEOUT)
+            .ok()
+            .and_then(|answer| DccMessage::deserialize(answer).ok())
+            .filter(|answer| answer.command == DccMessageType::Accept);
+        remove_interface_communication(arc_dcc_interface_communication, requested_client.clone());
+
+        let incoming = answer.and_then(|answer| {
+            let policy = answer
+                .parameters
+                .get(2)
+                .and_then(|policy| CollisionPolicy::from_name(policy))
+                .unwrap_or(preferences.collision_policy);
+            let path = save_path(&preferences.download_directory, &file_name, policy)?;
+            if let Some(folder) = path.parent() {
+                fs::create_dir_all(folder).ok()?;
+            }
+            Some(IncomingRelay {
+                nickname,
+                file: File::create(&path).ok()?,
+                path: path.to_string_lossy().to_string(),
+                file_size: file_size as u64,
+                received: 0,
+                checksum,
+                meter: TransferMeter::new(None, 0, Instant::now()),
+            })
+        });
+        let answer = match incoming {
+            Some(incoming) => {
+                match dcc_relays.lock() {
+                    Ok(mut relays_lock) => {
+                        relays_lock
+                            .incoming
+                            .insert(relay_key(&requested_client, &file_name), incoming);
+                    }
+                    Err(_) => println!("[ERROR] Error locking the relayed files"),
+                }
+                "ACCEPT"
+            }
+            None => "DECLINE",
+        };
+        if write_socket(arc_server_socket, &format!("{prefix} {answer} {file_name}")).is_err() {
+            println!("[ERROR] Error answering the relayed file {file_name}");
+        }
+    });
+    Ok(())
+}
+
+/// Saves a chunk of a relayed file and acknowledges it to the requested client, or cancels the file if the
+/// chunk is invalid
+/// `:<client> DCC RELAY <nickname> CHUNK <file_name> <offset> <data>`
+fn incoming_chunk(
+    dcc_msg: DccMessage,
+    requested_client: String,
+    arc_server_socket: Arc<TcpStream>,
+    dcc_relays: DccRelays,
+    tx_chats: glib::Sender<Response>,
+) -> Result<(), ClientError> {
+    let file_name = dcc_msg.parameters[2].to_owned();
+    let key = relay_key(&requested_client, &file_name);
+    let offset = dcc_msg
+        .parameters
+        .get(3)
+        .and_then(|offset| offset.parse::<u64>().ok());
+    let data = dcc_msg.parameters.get(4).and_then(|data| from_hex(data));
+
+    let mut relays_lock = dcc_relays.lock().map_err(|_| ClientError::LockError)?;
+    let incoming = match relays_lock.incoming.get_mut(&key) {
+        Some(incoming) => incoming,
+        None => {
+            println!("[ERROR] Chunk of a relayed file not accepted: {key}");
+            return Ok(());
+        }
+    };
+    let prefix = format!(":{} DCC RELAY {requested_client}", incoming.nickname);
+    let received = offset
+        .zip(data)
+        .and_then(|(offset, data)| incoming.write_chunk(offset, &data));
+    let received = match received {
+        Some(received) => received,
+        None => {
+            drop(relays_lock);
+            println!("[ERROR] Invalid chunk of the relayed file {key}");
+            write_socket(arc_server_socket, &format!("{prefix} CANCEL {file_name}"))?;
+            cancel_incoming(&dcc_relays, &key, requested_client, file_name, &tx_chats);
+            return Ok(());
+        }
+    };
+    let now = Instant::now();
+    incoming.meter.record(received, now);
+    if incoming
+        .meter
+        .should_report(received == incoming.file_size, now)
+    {
+        send_progress(
+            received as f64,
+            incoming.file_size as f64,
+            &incoming.meter,
+            &requested_client,
+            &file_name,
+            tx_chats,
+        )?;
+    }
+    drop(relays_lock);
+
+    write_socket(
+        arc_server_socket,
+        &format!("{prefix} ACK {file_name} {received}"),
+    )?;
+    Ok(())
+}
+
+/// Notifies the interface that a relayed file was received, or that it failed if it is not complete or it
+/// doesn't match its checksum
+fn finish_incoming(
+    mut incoming: IncomingRelay,
+    requested_client: String,
+    file_name: String,
+    tx_chats: &glib::Sender<Response>,
+) {
+    if incoming.file.flush().is_err() {
+        println!("[ERROR] Error saving the relayed file {file_name}");
+    }
+    let response = if incoming.is_complete() {
+        DccResponse::FileReceived {
+            sender: requested_client,
+            file_name,
+            path: incoming.path,
+        }
+    } else {
+        println!("[ERROR] The relayed file {file_name} is incomplete or corrupted");
+        DccResponse::TransferFailed {
+            sender: requested_client,
+            file_name,
+        }
+    };
+    if tx_chats.send(Response::DccResponse { response }).is_ok() {};
+}
+
+/// Drops a relayed file being received, removing what was saved of it, and notifies the interface
+fn cancel_incoming(
+    dcc_relays: &DccRelays,
+    key: &str,
+    requested_client: String,
+    file_name: String,
+    tx_chats: &glib::Sender<Response>,
+) {
+    let incoming = match dcc_relays.lock() {
+        Ok(mut relays_lock) => relays_lock.incoming.remove(key),
+        Err(_) => None,
+    };
+    if let Some(incoming) = incoming {
+        if fs::remove_file(&incoming.path).is_err() {
+            println!("[ERROR] Error removing the relayed file {}", incoming.path);
+        }
+        let response = Response::DccResponse {
+            response: DccResponse::TransferFailed {
+                sender: requested_client,
+                file_name,
+            },
+        };
+        if tx_chats.send(response).is_ok() {};
+    }
+}
+
+#[cfg(test)]
+mod dcc_relay_test {
+    use std::{
+        fs::{self, File},
+        time::Instant,
+    };
+
+    use super::{IncomingRelay, RelayFlow};
+    use crate::dcc_commands::{speed::TransferMeter, transfer::file_checksum};
+
+    #[test]
+    fn test_chunks_wait_for_acknowledgements_when_the_window_is_full() {
+        let mut flow = RelayFlow::new(25, 10, 2);
+        assert_eq!(flow.next_chunk(), Some((0, 10)));
+        assert_eq!(flow.next_chunk(), Some((10, 10)));
+        assert_eq!(flow.next_chunk(), None);
+
+        assert!(!flow.acknowledge(21));
+        assert!(flow.acknowledge(10));
+        assert!(!flow.acknowledge(5));
+        assert_eq!(flow.next_chunk(), Some((20, 5)));
+        assert_eq!(flow.next_chunk(), None);
+        assert!(!flow.is_done());
+
+        assert!(flow.acknowledge(25));
+        assert!(flow.is_done());
+        assert_eq!(flow.acknowledged(), 25);
+
+        let empty = RelayFlow::new(0, 10, 2);
+        assert!(empty.is_done());
+    }
+
+    #[test]
+    fn test_relayed_chunks_are_saved_in_order() {
+        let path = std::env::temp_dir().join(format!("dcc_relay_{}.txt", std::process::id()));
+        fs::write(&path, "hello world").unwrap();
+        let checksum = file_checksum(path.to_str().unwrap()).unwrap();
+        let mut incoming = IncomingRelay {
+            nickname: "bob".to_owned(),
+            file: File::create(&path).unwrap(),
+            path: path.to_string_lossy().to_string(),
+            file_size: 11,
+            received: 0,
+            checksum: Some(checksum),
+            meter: TransferMeter::new(None, 0, Instant::now()),
+        };
+
+        assert_eq!(incoming.write_chunk(0, b"hello"), Some(5));
+        assert_eq!(incoming.write_chunk(0, b"hello"), None);
+        assert_eq!(incoming.write_chunk(5, b" world!"), None);
+        assert!(!incoming.is_complete());
+        assert_eq!(incoming.write_chunk(5, b" world"), Some(11));
+        assert!(incoming.is_complete());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello world");
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -15,6 +15,7 @@ use crate::{dcc_commands::transfer::remove_transfer_communication, run_interface
 use gtk::glib;
 use model::{
     client_errors::ClientError,
+    crypto::FileCipher,
     dcc::DccMessage,
     responses::{dcc::DccResponse, ongoing_transfer::OngoingTransfer, response::Response},
     socket::write_socket,
@@ -58,13 +59,17 @@ pub fn outgoing_resume_request(
 
     let mut client_has_the_file = false;
     let mut file_path = String::new();
-    let file_offset = match arc_ongoing_transfers_lock.get(&filename) {
+    let (file_offset, cipher, checksum) = match arc_ongoing_transfers_lock.get(&filename) {
         Some(ongoing_transfer) => {
             if !ongoing_transfer.file_path.is_empty() {
                 client_has_the_file = true;
                 file_path = ongoing_transfer.file_path.to_owned();
             }
-            ongoing_transfer.file_offset
+            (
+                ongoing_transfer.file_offset,
+                ongoing_transfer.cipher.clone(),
+                ongoing_transfer.checksum.clone(),
+            )
         }
         None => {
             println!("[ERROR] No ongoing transfer found with key: {filename}");
@@ -96,7 +101,7 @@ pub fn outgoing_resume_request(
     thread::spawn(move || {
         if client_has_the_file {
             if outgoing_resume_transfer_file(
-                (filename, file_path, file_offset),
+                (filename, file_path, file_offset, cipher),
                 dcc_msg,
                 arc_socket,
                 rx_transfer,
@@ -107,7 +112,7 @@ pub fn outgoing_resume_request(
             .is_ok()
             {};
         } else if outgoing_resume_receive_file(
-            (filename, file_offset),
+            (filename, file_offset, cipher, checksum),
             dcc_msg,
             tx_chats,
             arc_ongoing_transfers,
@@ -124,7 +129,7 @@ pub fn outgoing_resume_request(
 /// It creates a thread to resume the transfer and sends a message to the interface to notify that the transfer is being resumed
 /// It returns a ClientError if there is an error creating the socket
 fn outgoing_resume_transfer_file(
-    file_data: (String, String, u64),
+    file_data: (String, String, u64, Option<FileCipher>),
     dcc_msg: DccMessage,
     arc_socket: Arc<TcpStream>,
     rx_transfer: Receiver<String>,
@@ -132,7 +137,7 @@ fn outgoing_resume_transfer_file(
     arc_ongoing_transfers: Arc<RwLock<HashMap<String, OngoingTransfer>>>,
     arc_transfers_communication: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
 ) -> Result<(), ClientError> {
-    let (filename, filepath, file_offset) = file_data;
+    let (filename, filepath, file_offset, cipher) = file_data;
     let requested_client = dcc_msg.parameters[0].to_owned();
     let ip = dcc_msg.parameters[2].to_owned();
     let port = dcc_msg.parameters[3].to_owned();
@@ -166,7 +171,7 @@ fn outgoing_resume_transfer_file(
             if tx_chats.send(response).is_ok() {};
 
             match transfer_file(
-                (filename_clone, filepath, -1.0, file_offset),
+                (filename_clone, filepath, -1.0, file_offset, cipher),
                 requested_client,
                 arc_transfer_socket,
                 rx_transfer,
@@ -190,12 +195,12 @@ fn outgoing_resume_transfer_file(
 /// It creates a thread to resume the receipt and sends a message to the interface to notify that the receipt is being resumed
 /// It returns a ClientError if there is an error creating the socket
 fn outgoing_resume_receive_file(
-    file_data: (String, u64),
+    file_data: (String, u64, Option<FileCipher>, Option<String>),
     dcc_msg: DccMessage,
     tx_chats: glib::Sender<Response>,
     arc_ongoing_transfers: Arc<RwLock<HashMap<String, OngoingTransfer>>>,
 ) -> Result<(), ClientError> {
-    let (filename, file_offset) = file_data;
+    let (filename, file_offset, cipher, checksum) = file_data;
     let requested_client = dcc_msg.parameters[0].to_owned();
     let ip = dcc_msg.parameters[2].to_owned();
     let port = dcc_msg.parameters[3].to_owned();
@@ -228,7 +233,7 @@ fn outgoing_resume_receive_file(
             if tx_chats.send(response).is_ok() {};
 
             match receive_file(
-                (filename, -1.0, file_offset),
+                (filename, -1.0, file_offset, cipher, checksum),
                 arc_transfer_socket,
                 requested_client,
                 tx_chats,
@@ -269,13 +274,17 @@ pub fn incoming_resume_request(
 
     let mut client_has_the_file = false;
     let mut file_path = String::new();
-    let file_offset = match arc_ongoing_transfers_lock.get(&filename) {
+    let (file_offset, cipher, checksum) = match arc_ongoing_transfers_lock.get(&filename) {
         Some(ongoing_transfer) => {
             if !ongoing_transfer.file_path.is_empty() {
                 client_has_the_file = true;
                 file_path = ongoing_transfer.file_path.to_owned();
             }
-            ongoing_transfer.file_offset
+            (
+                ongoing_transfer.file_offset,
+                ongoing_transfer.cipher.clone(),
+                ongoing_transfer.checksum.clone(),
+            )
         }
         None => {
             println!("[ERROR] No ongoing transfer found with key: {filename}");
@@ -284,6 +293,17 @@ pub fn incoming_resume_request(
     };
     drop(arc_ongoing_transfers_lock);
 
+    // the file is sent from the bytes the other client has, so a file received with a wrong
+    // checksum is sent again from the start
+    let file_offset = match client_has_the_file {
+        true => dcc_msg
+            .parameters
+            .get(4)
+            .and_then(|offset| offset.parse::<u64>().ok())
+            .unwrap_or(file_offset),
+        false => file_offset,
+    };
+
     let (tx_transfer, rx_transfer) = sync_channel(0);
     let mut arc_transfers_communication_lock = match arc_transfers_communication.as_ref().write() {
         Ok(lock) => lock,
@@ -301,7 +321,7 @@ pub fn incoming_resume_request(
     thread::spawn(move || {
         if client_has_the_file {
             if incoming_resume_transfer_file(
-                (filename, file_path, file_offset),
+                (filename, file_path, file_offset, cipher),
                 dcc_msg_aux,
                 arc_socket,
                 rx_transfer,
@@ -312,7 +332,7 @@ pub fn incoming_resume_request(
             .is_ok()
             {};
         } else if incoming_resume_receive_file(
-            (filename, file_offset),
+            (filename, file_offset, cipher, checksum),
             dcc_msg_aux,
             tx_chats,
             arc_ongoing_transfers,
@@ -329,7 +349,7 @@ pub fn incoming_resume_request(
 /// It creates a thread to resume the transfer and sends a message to the interface to notify that the transfer is being resumed
 /// It returns a ClientError if there is an error creating the socket
 fn incoming_resume_transfer_file(
-    file_data: (String, String, u64), // filename, filepath, file_offset
+    file_data: (String, String, u64, Option<FileCipher>), // filename, filepath, file_offset, cipher
     dcc_msg: DccMessage,
     arc_socket: Arc<TcpStream>,
     rx_transfer: Receiver<String>,
@@ -337,7 +357,7 @@ fn incoming_resume_transfer_file(
     arc_ongoing_transfers: Arc<RwLock<HashMap<String, OngoingTransfer>>>,
     arc_transfers_communication: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
 ) -> Result<(), ClientError> {
-    let (filename, filepath, file_offset) = file_data;
+    let (filename, filepath, file_offset, cipher) = file_data;
     let requested_client = dcc_msg.parameters[0].to_owned();
     let ip = dcc_msg.parameters[2].to_owned();
     let port = dcc_msg.parameters[3].to_owned();
@@ -366,7 +386,7 @@ fn incoming_resume_transfer_file(
         if tx_chats.send(response).is_ok() {};
 
         match transfer_file(
-            (filename_clone, filepath, -1.0, file_offset),
+            (filename_clone, filepath, -1.0, file_offset, cipher),
             requested_client,
             arc_transfer_socket,
             rx_transfer,
@@ -389,12 +409,12 @@ fn incoming_resume_transfer_file(
 /// It creates a thread to resume the receipt and sends a message to the interface to notify that the receipt is being resumed
 /// It returns a ClientError if there is an error creating the socket
 fn incoming_resume_receive_file(
-    file_data: (String, u64), // filename, file_offset
+    file_data: (String, u64, Option<FileCipher>, Option<String>), // filename, file_offset, cipher, checksum
     dcc_msg: DccMessage,
     tx_chats: glib::Sender<Response>,
     arc_ongoing_transfers: Arc<RwLock<HashMap<String, OngoingTransfer>>>,
 ) -> Result<(), ClientError> {
-    let (filename, file_offset) = file_data;
+    let (filename, file_offset, cipher, checksum) = file_data;
     let requested_client = dcc_msg.parameters[0].to_owned();
     let ip = dcc_msg.parameters[2].to_owned();
     let port = dcc_msg.parameters[3].to_owned();
@@ -422,7 +442,7 @@ fn incoming_resume_receive_file(
         if tx_chats.send(response).is_ok() {};
 
         match receive_file(
-            (filename_clone, -1.0, file_offset),
+            (filename_clone, -1.0, file_offset, cipher, checksum),
             arc_transfer_socket,
             requested_client,
             tx_chats,
@@ -0,0 +1,450 @@
+use std::{
+    collections::HashMap,
+    sync::{mpsc::SyncSender, Arc, RwLock},
+};
+
+use gtk::glib;
+use model::{
+    client_errors::ClientError,
+    dcc::DccMessage,
+    responses::{dcc::DccResponse, response::Response},
+};
+
+/// The rooms the current client hosts or joined, by their names
+pub type DccRooms = Arc<RwLock<HashMap<String, DccRoom>>>;
+
+/// Struct that represents a group dcc chat, whose host has a dcc connection with each member and sends
+/// the chat lines of every member to the others
+/// # Fields
+/// * `name`: The name of the room, chosen by the host
+/// * `host`: The client who hosts the room
+/// * `members`: The clients who joined the room, without the host, in the order they joined
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DccRoom {
+    pub name: String,
+    pub host: String,
+    pub members: Vec<String>,
+}
+
+impl DccRoom {
+    /// Creates a new room without members
+    pub fn new(name: String, host: String) -> Self {
+        DccRoom {
+            name,
+            host,
+            members: vec![],
+        }
+    }
+
+    /// Adds the client to the members of the room
+    /// Returns false if the client is the host or already a member
+    pub fn join(&mut self, client: &str) -> bool {
+        if client == self.host || self.is_member(client) {
+            return false;
+        }
+        self.members.push(client.to_owned());
+        true
+    }
+
+    /// Removes the client from the members of the room
+    /// Returns false if the client was not a member
+    pub fn leave(&mut self, client: &str) -> bool {
+        let len = self.members.len();
+        self.members.retain(|member| member != client);
+        self.members.len() != len
+    }
+
+    /// Returns if the client is a member of the room, without counting the host
+    pub fn is_member(&self, client: &str) -> bool {
+        self.members.iter().any(|member| member == client)
+    }
+
+    /// Returns the members a chat line of the sender is sent to, which are all of them but the sender
+    pub fn recipients(&self, sender: &str) -> Vec<String> {
+        self.members
+            .iter()
+            .filter(|member| *member != sender)
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the message that tells the members who is in the room
+    pub fn members_message(&self) -> String {
+        let mut message = format!("DCC ROOM {} MEMBERS {}", self.name, self.host);
+        for member in &self.members {
+            message.push(' ');
+            message.push_str(member);
+        }
+        message
+    }
+
+    /// Returns the response that tells the interface who is in the room
+    fn members_response(&self) -> Response {
+        Response::DccResponse {
+            response: DccResponse::RoomMembers {
+                room: self.name.clone(),
+                host: self.host.clone(),
+                members: self.members.clone(),
+            },
+        }
+    }
+}
+
+/// Returns the name of a room written by the user, which is its first word starting with `&`, so it
+/// can't be the nickname of a client, or None if there is no name
+pub fn room_name(name: &str) -> Option<String> {
+    let name = name.split_whitespace().next()?;
+    match name.strip_prefix('&') {
+        Some("") => None,
+        Some(_) => Some(name.to_owned()),
+        None => Some(format!("&{name}")),
+    }
+}
+
+/// Manages a room request from the interface of the current client, whose nickname is its prefix
+/// * `DCC ROOM <room> INVITE <client>`: adds a client with a dcc connection to a room hosted by the current
+///   client, creating the room if it doesn't exist
+/// * `DCC ROOM <room> MSG <text>`: sends a chat line to the room, to every member if the current client
+///   hosts it or to the host otherwise
+/// * `DCC ROOM <room> LEAVE`: leaves the room, or closes it if the current client hosts it
+///
+/// Returns a ClientError if the rooms or the connections can't be locked
+pub fn outgoing_room_request(
+    dcc_msg: DccMessage,
+    dcc_rooms: DccRooms,
+    dcc_connections: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
+    tx_chats: glib::Sender<Response>,
+) -> Result<(), ClientError> {
+    let nickname = dcc_msg.prefix.clone().unwrap_or_default();
+    let (room_name, action) = match (dcc_msg.parameters.first(), dcc_msg.parameters.get(1)) {
+        (Some(room_name), Some(action)) => (room_name.to_owned(), action.to_uppercase()),
+        _ => {
+            println!("[ERROR] DCC ROOM command needs a room and an action");
+            return Ok(());
+        }
+    };
+
+    match action.as_str() {
+        "INVITE" => {
+            let client = match dcc_msg.parameters.get(2) {
+                Some(client) => client.to_owned(),
+                None => {
+                    println!("[ERROR] DCC ROOM INVITE command needs a client");
+                    return Ok(());
+                }
+            };
+            if !dcc_connections.read()?.contains_key(&client) {
+                send_error(
+                    &tx_chats,
+                    format!("There is no DCC connection with {client}."),
+                );
+                return Ok(());
+            }
+
+            let mut rooms = dcc_rooms.write()?;
+            let room = rooms
+                .entry(room_name.clone())
+                .or_insert_with(|| DccRoom::new(room_name.clone(), nickname.clone()));
+            if room.host != nickname {
+                let description = format!("Only {} can invite clients to {room_name}.", room.host);
+                drop(rooms);
+                send_error(&tx_chats, description);
+                return Ok(());
+            }
+            room.join(&client);
+            let room = room.clone();
+            drop(rooms);
+            send_members(&room, &dcc_connections, &tx_chats);
+        }
+        "MSG" => {
+            let room = match dcc_rooms.read()?.get(&room_name) {
+                Some(room) => room.clone(),
+                None => {
+                    send_error(&tx_chats, format!("You are not in {room_name}."));
+                    return Ok(());
+                }
+            };
+            let text = dcc_msg.parameters[2..].join(" ");
+            let message = format!("DCC ROOM {room_name} MSG {nickname} {text}");
+            if room.host == nickname {
+                send_to_clients(&dcc_connections, &room.members, &message);
+            } else {
+                send_to_clients(&dcc_connections, &[room.host], &message);
+            }
+        }
+        "LEAVE" => {
+            let room = match dcc_rooms.write()?.remove(&room_name) {
+                Some(room) => room,
+                None => return Ok(()),
+            };
+            let message = format!("DCC ROOM {room_name} LEAVE");
+            if room.host == nickname {
+                send_to_clients(&dcc_connections, &room.members, &message);
+            } else {
+                send_to_clients(&dcc_connections, &[room.host], &message);
+            }
+            send_closed(&tx_chats, room_name);
+        }
+        _ => println!("[ERROR] Invalid DCC ROOM action: {action}"),
+    }
+    Ok(())
+}
+
+/// Manages a room message received from the requested client through its dcc connection
+/// * `DCC ROOM <room> MEMBERS <host> <members>`: the host tells who is in the room
+/// * `DCC ROOM <room> MSG <sender> <text>`: a chat line, which the host sends to the other members
+/// * `DCC ROOM <room> LEAVE`: the member left the room, or the host closed it
+///
+/// The messages of rooms the requested client is not part of are dropped
+/// Returns a ClientError if the rooms can't be locked
+pub fn incoming_room_request(
+    requested_client: String,
+    dcc_msg: DccMessage,
+    dcc_rooms: DccRooms,
+    dcc_connections: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
+    tx_chats: glib::Sender<Response>,
+) -> Result<(), ClientError> {
+    let (room_name, action) = match (dcc_msg.parameters.first(), dcc_msg.parameters.get(1)) {
+        (Some(room_name), Some(action)) => (room_name.to_owned(), action.to_uppercase()),
+        _ => {
+            println!("[ERROR] Invalid DCC ROOM message received from {requested_client}");
+            return Ok(());
+        }
+    };
+
+    match action.as_str() {
+        "MEMBERS" => {
+            if dcc_msg.parameters.get(2) != Some(&requested_client) {
+                println!("[ERROR] {requested_client} is not the host of {room_name}");
+                return Ok(());
+            }
+            let room = DccRoom {
+                name: room_name.clone(),
+                host: requested_client.clone(),
+                members: dcc_msg.parameters[3..].to_vec(),
+            };
+            let mut rooms = dcc_rooms.write()?;
+            if rooms
+                .get(&room_name)
+                .is_some_and(|joined| joined.host != requested_client)
+            {
+                println!("[ERROR] There is already a room named {room_name}");
+                return Ok(());
+            }
+            rooms.insert(room_name, room.clone());
+            drop(rooms);
+            if tx_chats.send(room.members_response()).is_ok() {};
+        }
+        "MSG" => {
+            let room = match dcc_rooms.read()?.get(&room_name) {
+                Some(room) => room.clone(),
+                None => return Ok(()),
+            };
+            if room.host == requested_client {
+                let sender = dcc_msg.parameters.get(2).cloned().unwrap_or_default();
+                let text = dcc_msg.parameters.get(3..).unwrap_or_default().join(" ");
+                send_line(&tx_chats, room_name, sender, text);
+            } else if room.is_member(&requested_client) {
+                // the host names the sender, so a member can't send lines in the name of another one
+                let text = dcc_msg.parameters.get(3..).unwrap_or_default().join(" ");
+                let message = format!("DCC ROOM {room_name} MSG {requested_client} {text}");
+                send_to_clients(
+                    &dcc_connections,
+                    &room.recipients(&requested_client),
+                    &message,
+                );
+                send_line(&tx_chats, room_name, requested_client, text);
+            }
+        }
+        "LEAVE" => {
+            remove_from_room(
+                &room_name,
+                &requested_client,
+                &dcc_rooms,
+                &dcc_connections,
+                &tx_chats,
+            )?;
+        }
+        _ => println!("[ERROR] Invalid DCC ROOM action received from {requested_client}: {action}"),
+    }
+    Ok(())
+}
+
+/// Removes the requested client from every room, once its dcc connection is closed
+/// The rooms it hosts are closed, and the other members are told who is still in the rooms it joined
+pub fn leave_rooms(
+    requested_client: &str,
+    dcc_rooms: DccRooms,
+    dcc_connections: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
+    tx_chats: glib::Sender<Response>,
+) {
+    let room_names = match dcc_rooms.read() {
+        Ok(rooms) => rooms
+            .values()
+            .filter(|room| room.host == requested_client || room.is_member(requested_client))
+            .map(|room| room.name.clone())
+            .collect::<Vec<_>>(),
+        Err(e) => {
+            println!("[ERROR] Error locking the rooms: {e}");
+            return;
+        }
+    };
+    for room_name in room_names {
+        if let Err(e) = remove_from_room(
+            &room_name,
+            requested_client,
+            &dcc_rooms,
+            &dcc_connections,
+            &tx_chats,
+        ) {
+            println!("[ERROR] Error leaving {room_name}: {e:?}");
+        }
+    }
+}
+
+/// Removes the client from the room, closing it if the client is its host
+fn remove_from_room(
+    room_name: &str,
+    client: &str,
+    dcc_rooms: &DccRooms,
+    dcc_connections: &Arc<RwLock<HashMap<String, SyncSender<String>>>>,
+    tx_chats: &glib::Sender<Response>,
+) -> Result<(), ClientError> {
+    let mut rooms = dcc_rooms.write()?;
+    let room = match rooms.get_mut(room_name) {
+        Some(room) => room,
+        None => return Ok(()),
+    };
+    if room.host == client {
+        rooms.remove(room_name);
+        drop(rooms);
+        send_closed(tx_chats, room_name.to_owned());
+    } else if room.leave(client) {
+        let room = room.clone();
+        drop(rooms);
+        send_members(&room, dcc_connections, tx_chats);
+    }
+    Ok(())
+}
+
+/// Tells every member of the room hosted by the current client, and its interface, who is in the room
+fn send_members(
+    room: &DccRoom,
+    dcc_connections: &Arc<RwLock<HashMap<String, SyncSender<String>>>>,
+    tx_chats: &glib::Sender<Response>,
+) {
+    send_to_clients(dcc_connections, &room.members, &room.members_message());
+    if tx_chats.send(room.members_response()).is_ok() {};
+}
+
+/// Sends the message to the threads of the dcc connections with the clients, which write it to their sockets
+/// The channels are copied before sending, so the connections are not locked while a thread is busy
+fn send_to_clients(
+    dcc_connections: &Arc<RwLock<HashMap<String, SyncSender<String>>>>,
+    clients: &[String],
+    message: &str,
+) {
+    let senders = match dcc_connections.read() {
+        Ok(connections) => clients
+            .iter()
+            .filter_map(|client| Some((client, connections.get(client)?.clone())))
+            .collect::<Vec<_>>(),
+        Err(e) => {
+            println!("[ERROR] Error locking the dcc connections: {e}");
+            return;
+        }
+    };
+    for (client, sender) in senders {
+        if sender.send(message.to_owned()).is_err() {
+            println!("[ERROR] Communication with dcc thread failed. Client: {client}");
+        }
+    }
+}
+
+/// Sends a chat line of the room to the interface
+fn send_line(tx_chats: &glib::Sender<Response>, room: String, sender: String, message: String) {
+    let response = Response::DccResponse {
+        response: DccResponse::RoomMessage {
+            room,
+            sender,
+            message,
+        },
+    };
+    if tx_chats.send(response).is_ok() {};
+}
+
+/// Tells the interface the current client is no longer in the room
+fn send_closed(tx_chats: &glib::Sender<Response>, room: String) {
+    let response = Response::DccResponse {
+        response: DccResponse::RoomClosed { room },
+    };
+    if tx_chats.send(response).is_ok() {};
+}
+
+/// Sends an error to the interface
+fn send_error(tx_chats: &glib::Sender<Response>, description: String) {
+    let response = Response::DccResponse {
+        response: DccResponse::ErrorResponse { description },
+    };
+    if tx_chats.send(response).is_ok() {};
+}
+
+#[cfg(test)]
+mod dcc_room_test {
+    use std::{
+        collections::HashMap,
+        sync::{mpsc::sync_channel, Arc, RwLock},
+    };
+
+    use super::{room_name, send_to_clients, DccRoom};
+
+    #[test]
+    fn test_members_join_and_leave_the_room_of_the_host() {
+        let mut room = DccRoom::new("&team".to_owned(), "alice".to_owned());
+        assert!(room.join("bob"));
+        assert!(room.join("carol"));
+        assert!(!room.join("bob"));
+        assert!(!room.join("alice"));
+        assert_eq!(
+            room.members_message(),
+            "DCC ROOM &team MEMBERS alice bob carol"
+        );
+        assert_eq!(room.recipients("bob"), vec!["carol".to_owned()]);
+        assert_eq!(room.recipients("alice").len(), 2);
+
+        assert!(room.leave("bob"));
+        assert!(!room.leave("bob"));
+        assert!(!room.is_member("bob"));
+        assert_eq!(room.members_message(), "DCC ROOM &team MEMBERS alice carol");
+    }
+
+    #[test]
+    fn test_room_names_start_with_an_ampersand() {
+        assert_eq!(room_name("team"), Some("&team".to_owned()));
+        assert_eq!(room_name("&team  now"), Some("&team".to_owned()));
+        assert_eq!(room_name("&"), None);
+        assert_eq!(room_name("  "), None);
+    }
+
+    #[test]
+    fn test_lines_are_sent_to_the_connections_of_the_clients() {
+        let (bob_sender, bob_receiver) = sync_channel::<String>(1);
+        let (carol_sender, carol_receiver) = sync_channel::<String>(1);
+        let connections = HashMap::from([
+            ("bob".to_owned(), bob_sender),
+            ("carol".to_owned(), carol_sender),
+        ]);
+        let dcc_connections = Arc::new(RwLock::new(connections));
+
+        send_to_clients(
+            &dcc_connections,
+            &["carol".to_owned(), "dave".to_owned()],
+            "DCC ROOM &team MSG bob hi",
+        );
+        assert_eq!(
+            carol_receiver.try_recv().unwrap(),
+            "DCC ROOM &team MSG bob hi"
+        );
+        assert!(bob_receiver.try_recv().is_err());
+    }
+}
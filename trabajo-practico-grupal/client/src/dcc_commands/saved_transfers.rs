@@ -0,0 +1,409 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::Read,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use gtk::glib;
+use model::{
+    client_errors::ClientError,
+    crypto::{to_hex, Sha256},
+    responses::{dcc::DccResponse, ongoing_transfer::OngoingTransfer, response::Response},
+};
+
+/// The file where the unfinished transfers are saved, to resume them after the client is restarted
+pub const SAVED_TRANSFERS_PATH: &str = "client/transfers.conf";
+/// The least time between two saves of a transfer in progress
+const SAVE_INTERVAL: Duration = Duration::from_secs(2);
+/// The separator of the fields of a transfer in the file
+const FIELD_SEPARATOR: char = '\t';
+
+/// Keeps the threads of the transfers from writing the file at the same time
+static SAVED_TRANSFERS_LOCK: Mutex<()> = Mutex::new(());
+
+/// Struct that represents an unfinished transfer saved in the file
+/// # Fields
+/// * `peer`: The client the file is sent to or received from
+/// * `file_name`: The name of the file in the transfer
+/// * `file_offset`: The bytes of the file transferred
+/// * `file_size`: The size of the file
+/// * `file_path`: The path of the file sent, or empty if the file is received
+/// * `checksum`: The checksum of the whole file sent with the request, or empty if it is unknown
+/// * `prefix_checksum`: The checksum of the bytes of the file transferred, to check the file wasn't changed
+/// * `save_path`: The path where the file received is saved, or empty if the file is sent
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SavedTransfer {
+    pub peer: String,
+    pub file_name: String,
+    pub file_offset: u64,
+    pub file_size: f64,
+    pub file_path: String,
+    pub checksum: String,
+    pub prefix_checksum: String,
+    pub save_path: String,
+}
+
+impl SavedTransfer {
+    /// Returns the path of the file of the transfer in the current client, or None if it is unknown
+    pub fn local_path(&self) -> Option<PathBuf> {
+        match self.file_path.is_empty() {
+            true => Some(PathBuf::from(&self.save_path)).filter(|_| !self.save_path.is_empty()),
+            false => Some(PathBuf::from(&self.file_path)),
+        }
+    }
+
+    /// Returns the transfer kept in the ongoing transfers of the dcc connection
+    pub fn ongoing_transfer(&self) -> OngoingTransfer {
+        OngoingTransfer {
+            file_offset: self.file_offset,
+            file_size: self.file_size,
+            file_path: self.file_path.clone(),
+            cipher: None,
+            checksum: Some(self.checksum.clone()).filter(|checksum| !checksum.is_empty()),
+            save_path: self.save_path.clone(),
+        }
+    }
+
+    /// Parses the transfer from a line of the file, or None if the line is invalid
+    fn from_line(line: &str) -> Option<Self> {
+        let fields = line.split(FIELD_SEPARATOR).collect::<Vec<_>>();
+        if fields.len() != 8 || fields[0].is_empty() || fields[1].is_empty() {
+            return None;
+        }
+        Some(Self {
+            peer: fields[0].to_string(),
+            file_name: fields[1].to_string(),
+            file_offset: fields[2].parse().ok()?,
+            file_size: fields[3].parse().ok()?,
+            file_path: fields[4].to_string(),
+            checksum: fields[5].to_string(),
+            prefix_checksum: fields[6].to_string(),
+            save_path: fields[7].to_string(),
+        })
+    }
+
+    /// Returns the line of the file with the transfer
+    fn to_line(&self) -> String {
+        [
+            self.peer.clone(),
+            self.file_name.clone(),
+            self.file_offset.to_string(),
+            self.file_size.to_string(),
+            self.file_path.clone(),
+            self.checksum.clone(),
+            self.prefix_checksum.clone(),
+            self.save_path.clone(),
+        ]
+        .join(&FIELD_SEPARATOR.to_string())
+    }
+}
+
+/// Struct that represents the unfinished transfers saved in the file, with a line for each one
+/// The encrypted transfers are not saved, since the keys of the secure connections are never written to disk
+/// # Fields
+/// * `transfers`: The saved transfers
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SavedTransfers {
+    transfers: Vec<SavedTransfer>,
+}
+
+impl SavedTransfers {
+    /// Loads the transfers saved in the file, or none if it can't be read
+    /// The invalid lines are skipped
+    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+        let content = fs::read_to_string(path).unwrap_or_default();
+        let transfers = content
+            .lines()
+            .filter_map(SavedTransfer::from_line)
+            .collect();
+        Self { transfers }
+    }
+
+    /// Saves the transfers to the file
+    /// It returns a ClientError if the file can't be written
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ClientError> {
+        let content = self
+            .transfers
+            .iter()
+            .map(|transfer| transfer.to_line() + "\n")
+            .collect::<String>();
+        fs::write(path, content).map_err(|_| ClientError::FileError)
+    }
+
+    /// Adds the transfer, replacing the one of the same file with the same client
+    pub fn set(&mut self, transfer: SavedTransfer) {
+        self.remove(&transfer.peer, &transfer.file_name);
+        self.transfers.push(transfer);
+    }
+
+    /// Removes the transfer of the file with the client
+    pub fn remove(&mut self, peer: &str, file_name: &str) {
+        self.transfers
+            .retain(|transfer| transfer.peer != peer || transfer.file_name != file_name);
+    }
+
+    /// Returns the transfers with the client
+    pub fn of_peer(&self, peer: &str) -> Vec<SavedTransfer> {
+        self.transfers
+            .iter()
+            .filter(|transfer| transfer.peer == peer)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Changes the transfers saved in the file, without other transfers changing them at the same time
+fn change_saved_transfers(path: &Path, change: impl FnOnce(&mut SavedTransfers)) {
+    let _lock = match SAVED_TRANSFERS_LOCK.lock() {
+        Ok(lock) => lock,
+        Err(_) => {
+            println!("[ERROR] Error locking the saved transfers");
+            return;
+        }
+    };
+    let mut saved_transfers = SavedTransfers::load(path);
+    change(&mut saved_transfers);
+    if saved_transfers.save(path).is_err() {
+        println!("[ERROR] Error saving the transfers to {}", path.display());
+    }
+}
+
+/// Removes the transfer of the file with the client from the file, when it is complete
+pub fn forget_transfer<P: AsRef<Path>>(path: P, peer: &str, file_name: &str) {
+    change_saved_transfers(path.as_ref(), |saved_transfers| {
+        saved_transfers.remove(peer, file_name)
+    });
+}
+
+/// Struct that saves a transfer in progress to the file, with the checksum of the bytes transferred,
+/// which is updated with each part of the file sent or received
+/// # Fields
+/// * `path`: The file where the transfer is saved
+/// * `transfer`: The transfer saved
+/// * `hasher`: The checksum of the bytes transferred
+/// * `last_save`: The moment the transfer was saved for the last time
+pub struct TransferRecord {
+    path: PathBuf,
+    transfer: SavedTransfer,
+    hasher: Sha256,
+    last_save: Instant,
+}
+
+impl TransferRecord {
+    /// Creates the record of a transfer that starts at the offset of the transfer, hashing the bytes of the
+    /// file already transferred, and saves it
+    pub fn new<P: AsRef<Path>>(path: P, transfer: SavedTransfer) -> Self {
+        let hasher = transfer
+            .local_path()
+            .and_then(|local_path| prefix_hasher(&local_path, transfer.file_offset))
+            .unwrap_or_default();
+        let mut record = TransferRecord {
+            path: path.as_ref().to_path_buf(),
+            transfer,
+            hasher,
+            last_save: Instant::now(),
+        };
+        record.save();
+        record
+    }
+
+    /// Records the bytes of the file transferred after the last ones, and saves the transfer if it wasn't
+    /// saved in the last SAVE_INTERVAL
+    pub fn update(&mut self, data: &[u8], now: Instant) {
+        self.hasher.update(data);
+        self.transfer.file_offset += data.len() as u64;
+        if now.saturating_duration_since(self.last_save) >= SAVE_INTERVAL {
+            self.last_save = now;
+            self.save();
+        }
+    }
+
+    /// Starts the transfer again from the first byte and saves it, when the file received is corrupt
+    pub fn restart(&mut self) {
+        self.hasher = Sha256::new();
+        self.transfer.file_offset = 0;
+        self.save();
+    }
+
+    /// Saves the transfer to the file, with the checksum of the bytes transferred
+    pub fn save(&mut self) {
+        self.transfer.prefix_checksum = to_hex(&self.hasher.clone().finish());
+        let transfer = self.transfer.clone();
+        change_saved_transfers(&self.path, |saved_transfers| saved_transfers.set(transfer));
+    }
+
+    /// Removes the transfer from the file, when it is complete
+    pub fn forget(&self) {
+        forget_transfer(&self.path, &self.transfer.peer, &self.transfer.file_name);
+    }
+}
+
+/// Returns the hasher of the first bytes of the file, or None if the file doesn't have them
+fn prefix_hasher(local_path: &Path, bytes: u64) -> Option<Sha256> {
+    let mut file = File::open(local_path).ok()?.take(bytes);
+    let mut hasher = Sha256::new();
+    let mut buffer = [0; 1024];
+    let mut hashed = 0;
+    loop {
+        match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => {
+                hasher.update(&buffer[..read]);
+                hashed += read as u64;
+            }
+            Err(_) => return None,
+        }
+    }
+    (hashed == bytes).then_some(hasher)
+}
+
+/// Returns the transfers with the client saved in the file, whose files still have the bytes transferred,
+/// so they are resumed with a DCC RESUME
+/// The received files are cut at the bytes saved, since more bytes can be written after the last save
+/// The transfers whose files were changed or removed are forgotten
+pub fn restore_transfers<P: AsRef<Path>>(path: P, peer: &str) -> Vec<SavedTransfer> {
+    let mut restored = vec![];
+    change_saved_transfers(path.as_ref(), |saved_transfers| {
+        for transfer in saved_transfers.of_peer(peer) {
+            let intact = transfer.local_path().is_some_and(|local_path| {
+                prefix_hasher(&local_path, transfer.file_offset).is_some_and(|hasher| {
+                    to_hex(&hasher.finish()).eq_ignore_ascii_case(&transfer.prefix_checksum)
+                }) && (!transfer.file_path.is_empty()
+                    || OpenOptions::new()
+                        .write(true)
+                        .open(&local_path)
+                        .and_then(|file| file.set_len(transfer.file_offset))
+                        .is_ok())
+            });
+            match intact {
+                true => restored.push(transfer),
+                false => {
+                    println!(
+                        "[INFO] The file of the saved transfer {} changed",
+                        transfer.file_name
+                    );
+                    saved_transfers.remove(peer, &transfer.file_name);
+                }
+            }
+        }
+    });
+    restored
+}
+
+/// Adds the transfers with the client saved before the client was restarted to the ongoing transfers of
+/// the dcc connection, and shows them paused in the interface, so they can be resumed
+pub fn restore_saved_transfers(
+    peer: &str,
+    arc_ongoing_transfers: Arc<RwLock<HashMap<String, OngoingTransfer>>>,
+    tx_chats: glib::Sender<Response>,
+) {
+    let restored = restore_transfers(SAVED_TRANSFERS_PATH, peer);
+    let mut ongoing_transfers_lock = match arc_ongoing_transfers.write() {
+        Ok(lock) => lock,
+        Err(_) => {
+            println!("[ERROR] Error locking ongoing transfers");
+            return;
+        }
+    };
+    for transfer in restored {
+        if ongoing_transfers_lock.contains_key(&transfer.file_name) {
+            continue;
+        }
+        ongoing_transfers_lock.insert(transfer.file_name.clone(), transfer.ongoing_transfer());
+        let response = Response::DccResponse {
+            response: DccResponse::TransferRestored {
+                sender: peer.to_owned(),
+                file_name: transfer.file_name,
+                file_size: transfer.file_size,
+                progress: transfer.file_offset as f64 / transfer.file_size,
+                received: transfer.file_path.is_empty(),
+            },
+        };
+        if tx_chats.send(response).is_ok() {};
+    }
+    drop(ongoing_transfers_lock);
+}
+
+#[cfg(test)]
+mod dcc_saved_transfers_test {
+    use std::{fs, time::Instant};
+
+    use super::{restore_transfers, SavedTransfer, SavedTransfers, TransferRecord};
+
+    fn transfer(peer: &str, file_name: &str, file_path: &str) -> SavedTransfer {
+        SavedTransfer {
+            peer: peer.to_owned(),
+            file_name: file_name.to_owned(),
+            file_size: 10.0,
+            file_path: file_path.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_transfers_are_saved_and_loaded_by_client() {
+        let path = std::env::temp_dir().join(format!("saved_transfers_{}", std::process::id()));
+        let mut saved_transfers = SavedTransfers::default();
+        saved_transfers.set(transfer("bob", "a.txt", "/home/alice/a.txt"));
+        let received = SavedTransfer {
+            save_path: "/home/alice/files/a (1).txt".to_owned(),
+            ..transfer("carol", "a.txt", "")
+        };
+        saved_transfers.set(received);
+        let mut updated = transfer("bob", "a.txt", "/home/alice/a.txt");
+        updated.file_offset = 4;
+        updated.checksum = "abc".to_owned();
+        saved_transfers.set(updated.clone());
+        saved_transfers.save(&path).unwrap();
+        fs::write(&path, fs::read_to_string(&path).unwrap() + "invalid line\n").unwrap();
+
+        let loaded = SavedTransfers::load(&path);
+        assert_eq!(loaded.of_peer("bob"), vec![updated.clone()]);
+        assert_eq!(loaded.of_peer("carol").len(), 1);
+        assert_eq!(updated.ongoing_transfer().checksum.as_deref(), Some("abc"));
+        assert_eq!(loaded.of_peer("carol")[0].ongoing_transfer().checksum, None);
+        assert_eq!(
+            loaded.of_peer("carol")[0].ongoing_transfer().save_path,
+            "/home/alice/files/a (1).txt"
+        );
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(SavedTransfers::load(&path), SavedTransfers::default());
+    }
+
+    #[test]
+    fn test_transfers_are_restored_only_if_their_files_did_not_change() {
+        let directory = std::env::temp_dir().join(format!("restore_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("transfers.conf");
+        let sent = directory.join("sent.txt");
+        let changed = directory.join("changed.txt");
+        fs::write(&sent, "0123456789").unwrap();
+        fs::write(&changed, "0123456789").unwrap();
+
+        let sent_path = sent.to_str().unwrap();
+        let mut record = TransferRecord::new(&path, transfer("bob", "sent.txt", sent_path));
+        record.update(b"0123", Instant::now());
+        record.save();
+        let mut record = TransferRecord::new(
+            &path,
+            transfer("bob", "changed.txt", changed.to_str().unwrap()),
+        );
+        record.update(b"01", Instant::now());
+        record.save();
+        TransferRecord::new(&path, transfer("carol", "sent.txt", sent_path)).forget();
+        fs::write(&changed, "ab23456789").unwrap();
+
+        let restored = restore_transfers(&path, "bob");
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].file_name, "sent.txt");
+        assert_eq!(restored[0].file_offset, 4);
+        assert_eq!(SavedTransfers::load(&path).of_peer("bob").len(), 1);
+        assert!(SavedTransfers::load(&path).of_peer("carol").is_empty());
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,178 @@
+use std::{net::TcpStream, sync::Arc};
+
+use model::{
+    client_errors::ClientError,
+    crypto::{from_hex, to_hex, KeyPair, SecureSession},
+    dcc::{DccMessage, DccMessageType},
+    socket::{read_socket, write_socket},
+};
+
+/// The most bytes of text sealed in a single message, so the sealed message fits in a socket frame
+const MAX_SEALED_TEXT_LEN: usize = 200;
+
+/// Exchanges the public keys of a new key pair with the requested client through the dcc connection,
+/// right after the connection is accepted, and returns the keys shared with it
+/// Both clients send their key before reading the other one, so neither waits for the other
+/// It returns a ClientError if the key of the other client can't be read or is not valid
+pub fn exchange_keys(
+    arc_socket: Arc<TcpStream>,
+    requested_client: &str,
+) -> Result<SecureSession, ClientError> {
+    let key_pair = KeyPair::generate();
+    write_socket(
+        arc_socket.clone(),
+        &format!(
+            "DCC KEY {requested_client} {}",
+            to_hex(&key_pair.public_key)
+        ),
+    )?;
+
+    let answer = DccMessage::deserialize(read_socket(arc_socket)?)?;
+    if answer.command != DccMessageType::Key || answer.parameters.len() < 2 {
+        println!("[ERROR] Expected the key of {requested_client}");
+        return Err(ClientError::KeyExchangeError);
+    }
+    let peer_public_key: [u8; 32] = from_hex(&answer.parameters[1])
+        .and_then(|key| key.try_into().ok())
+        .ok_or(ClientError::KeyExchangeError)?;
+
+    SecureSession::new(&key_pair, &peer_public_key).ok_or(ClientError::KeyExchangeError)
+}
+
+/// Returns the dcc chat messages that carry the text of a chat message sealed with the keys of the session
+/// Long texts are split in several messages, so each one fits in a socket frame
+pub fn seal_chat_message(dcc_msg: &DccMessage, session: &SecureSession) -> Vec<String> {
+    let requested_client = dcc_msg.parameters[0].to_owned();
+    let text = dcc_msg.parameters[1..].join(" ");
+
+    split_text(&text)
+        .into_iter()
+        .map(|part| format!("DCC CHAT {requested_client} {}", session.seal(part)))
+        .collect()
+}
+
+/// Returns the dcc chat message with the text of a sealed chat message, or None if it was not
+/// sealed with the keys of the session
+pub fn open_chat_message(dcc_msg: &DccMessage, session: &SecureSession) -> Option<DccMessage> {
+    let text = session.open(dcc_msg.parameters.get(1)?)?;
+    Some(DccMessage {
+        prefix: dcc_msg.prefix.clone(),
+        command: DccMessageType::Chat,
+        parameters: vec![dcc_msg.parameters[0].to_owned(), text],
+    })
+}
+
+/// Returns the dcc room messages that carry the text of a chat line of a room sealed with the keys of the
+/// session, split like the chat messages
+/// The room and the sender are left in the clear, since the host needs them to send the line to the members
+pub fn seal_room_message(dcc_msg: &DccMessage, session: &SecureSession) -> Vec<String> {
+    let room = &dcc_msg.parameters[0];
+    let sender = dcc_msg.parameters.get(2).cloned().unwrap_or_default();
+    let text = dcc_msg.parameters.get(3..).unwrap_or_default().join(" ");
+
+    split_text(&text)
+        .into_iter()
+        .map(|part| format!("DCC ROOM {room} MSG {sender} {}", session.seal(part)))
+        .collect()
+}
+
+/// Returns the dcc room message with the text of a sealed chat line of a room, or None if it was not
+/// sealed with the keys of the session
+pub fn open_room_message(dcc_msg: &DccMessage, session: &SecureSession) -> Option<DccMessage> {
+    let text = session.open(dcc_msg.parameters.get(3)?)?;
+    let mut parameters = dcc_msg.parameters[..3].to_vec();
+    parameters.push(text);
+    Some(DccMessage {
+        prefix: dcc_msg.prefix.clone(),
+        command: DccMessageType::Room,
+        parameters,
+    })
+}
+
+/// Splits the text in parts of at most MAX_SEALED_TEXT_LEN bytes, without splitting a character
+fn split_text(text: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut rest = text;
+    while rest.len() > MAX_SEALED_TEXT_LEN {
+        let mut end = MAX_SEALED_TEXT_LEN;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (part, next) = rest.split_at(end);
+        parts.push(part);
+        rest = next;
+    }
+    parts.push(rest);
+    parts
+}
+
+#[cfg(test)]
+mod dcc_secure_test {
+    use std::{
+        net::{TcpListener, TcpStream},
+        sync::Arc,
+        thread,
+    };
+
+    use model::{
+        consts::MAX_MSG_SIZE,
+        dcc::{DccMessage, DccMessageType},
+    };
+
+    use super::{
+        exchange_keys, open_chat_message, open_room_message, seal_chat_message, seal_room_message,
+        MAX_SEALED_TEXT_LEN,
+    };
+
+    #[test]
+    fn test_chat_messages_sealed_by_a_client_are_opened_by_the_other_one() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let socket = Arc::new(TcpStream::connect(address).unwrap());
+            exchange_keys(socket, "alice").unwrap()
+        });
+        let (socket, _) = listener.accept().unwrap();
+        let bob_session = exchange_keys(Arc::new(socket), "bob").unwrap();
+        let alice_session = handle.join().unwrap();
+
+        let text = "ñ".repeat(MAX_SEALED_TEXT_LEN);
+        let dcc_msg = DccMessage::deserialize(format!("DCC CHAT bob hola  {text}")).unwrap();
+        let sealed = seal_chat_message(&dcc_msg, &alice_session);
+        assert_eq!(sealed.len(), 3);
+        assert!(sealed.iter().all(|message| message.len() <= MAX_MSG_SIZE));
+
+        let opened = sealed
+            .iter()
+            .map(|message| {
+                let dcc_msg = DccMessage::deserialize(message.to_owned()).unwrap();
+                assert_eq!(dcc_msg.command, DccMessageType::Chat);
+                let mut changed = dcc_msg.clone();
+                let first = if changed.parameters[1].starts_with('0') {
+                    "1"
+                } else {
+                    "0"
+                };
+                changed.parameters[1].replace_range(..1, first);
+                assert!(open_chat_message(&changed, &bob_session).is_none());
+                open_chat_message(&dcc_msg, &bob_session)
+                    .unwrap()
+                    .parameters[1]
+                    .to_owned()
+            })
+            .collect::<String>();
+        assert_eq!(opened, format!("hola {text}"));
+
+        let dcc_msg =
+            DccMessage::deserialize("DCC ROOM &team MSG alice hi all".to_owned()).unwrap();
+        let sealed = seal_room_message(&dcc_msg, &alice_session);
+        assert_eq!(sealed.len(), 1);
+        assert!(!sealed[0].contains("hi all"));
+        let opened = DccMessage::deserialize(sealed[0].to_owned()).unwrap();
+        assert_eq!(opened.parameters[..3], ["&team", "MSG", "alice"]);
+        let opened = open_room_message(&opened, &bob_session).unwrap();
+        assert_eq!(opened.command, DccMessageType::Room);
+        assert_eq!(opened.parameters[3], "hi all");
+        assert!(open_room_message(&dcc_msg, &bob_session).is_none());
+    }
+}
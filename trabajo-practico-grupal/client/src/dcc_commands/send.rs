@@ -1,11 +1,17 @@
-use super::transfer::{receive_file, transfer_file};
+use super::{
+    batch::path_inside,
+    downloads::{declined_reason, save_path},
+    transfer::{file_checksum, receive_file, transfer_file},
+};
 use crate::{
     dcc_commands::transfer::{remove_interface_communication, remove_transfer_communication},
+    gui::preferences::{CollisionPolicy, Preferences, PREFERENCES_PATH},
     run_interface::check_address,
 };
 use gtk::glib;
 use model::{
     client_errors::ClientError,
+    crypto::SecureSession,
     dcc::{DccMessage, DccMessageType},
     responses::{dcc::DccResponse, ongoing_transfer::OngoingTransfer, response::Response},
     socket::{read_socket, write_socket},
@@ -25,6 +31,14 @@ use std::{
 /// It creates a new thread to receive the file and then sends a message to the current client's interface
 /// to notify that the file is being received and show the progress
 /// It also creates a new thread to communicate with the interface, so the user can cancel the transfer
+/// If the request is a SSEND, the file is decrypted with the nonce sent by the requested client and
+/// the keys of the secure session
+/// The checksum sent with the request is checked when the file is complete
+/// The file name can be a path relative to a folder sent in a batch. If it is not a path inside the download
+/// folder of the preferences, the file is declined, as well as the files with an invalid size, bigger than
+/// the maximum size of the preferences or with a blocked extension
+/// If the download folder already has a file with the name, the accepted file is renamed or overwrites it,
+/// as the user answered when accepting it or as the preferences say
 /// It returns a ClientError if there is an error creating the socket or the thread
 pub fn incoming_send_request(
     requested_client: String,
@@ -32,7 +46,49 @@ pub fn incoming_send_request(
     tx_chats: glib::Sender<Response>,
     arc_interface_communication: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
     arc_ongoing_transfers: Arc<RwLock<HashMap<String, OngoingTransfer>>>,
+    session: Option<SecureSession>,
 ) -> Result<(), ClientError> {
+    let cipher = match dcc_msg.command {
+        DccMessageType::SSend => {
+            let cipher = session
+                .zip(dcc_msg.parameters.get(6))
+                .and_then(|(session, nonce)| session.file_cipher(nonce));
+            if cipher.is_none() {
+                println!("[ERROR] Encrypted file received without a secure connection");
+                return Err(ClientError::KeyExchangeError);
+            }
+            cipher
+        }
+        _ => None,
+    };
+
+    let file_name = dcc_msg.parameters[1].to_owned();
+    let ip = dcc_msg.parameters[2].to_owned();
+    let port = dcc_msg.parameters[3].to_owned();
+    let file_size = dcc_msg.parameters[4].parse::<f64>().unwrap_or(f64::NAN);
+    let preferences = Preferences::load(PREFERENCES_PATH);
+    let declined = match path_inside(&preferences.download_directory, &file_name) {
+        Some(_) => declined_reason(&file_name, file_size, &preferences)
+            .map(|reason| format!("{requested_client} sent a file that was declined. {reason}")),
+        None => Some(format!(
+            "{requested_client} sent a file with an invalid path."
+        )),
+    };
+    if let Some(description) = declined {
+        println!("[ERROR] {description} File: {file_name}");
+        if let Ok(transfer_socket) = TcpStream::connect(format!("{ip}:{port}")) {
+            let arc_transfer_socket = Arc::new(transfer_socket);
+            let decline = format!("DCC CLOSE {requested_client} {file_name}");
+            if write_socket(arc_transfer_socket.clone(), &decline).is_ok() {};
+            if arc_transfer_socket.shutdown(Shutdown::Both).is_ok() {};
+        }
+        let response = Response::DccResponse {
+            response: DccResponse::ErrorResponse { description },
+        };
+        if tx_chats.send(response).is_ok() {};
+        return Err(ClientError::FileError);
+    }
+
     // set channel to communicate with the interface
     let (tx_interface, rx_interface) = sync_channel(0);
     let mut arc_interface_communication_lock = match arc_interface_communication.as_ref().write() {
@@ -46,10 +102,7 @@ pub fn incoming_send_request(
     drop(arc_interface_communication_lock);
 
     let tx_chats_clone_1 = tx_chats.clone();
-    let file_name = dcc_msg.parameters[1].to_owned();
-    let ip = dcc_msg.parameters[2].to_owned();
-    let port = dcc_msg.parameters[3].to_owned();
-    let file_size = dcc_msg.parameters[4].parse::<f64>().unwrap_or(0.0);
+    let checksum = dcc_msg.parameters.get(5).cloned();
 
     let response = Response::DccResponse {
         response: DccResponse::TransferRequest {
@@ -73,9 +126,15 @@ pub fn incoming_send_request(
     };
 
     let arc_transfer_socket = Arc::new(transfer_socket);
+    let mut policy = preferences.collision_policy;
     if let Ok(gui_answer) = rx_interface.recv() {
-        if let Ok(dcc_answer) = DccMessage::deserialize(gui_answer) {
+        if let Ok(mut dcc_answer) = DccMessage::deserialize(gui_answer) {
             if dcc_answer.command == DccMessageType::Accept {
+                // the answer of the user to a file already received is not sent to the requested client
+                if let Some(answer) = dcc_answer.parameters.get(2) {
+                    policy = CollisionPolicy::from_name(answer).unwrap_or(policy);
+                    dcc_answer.parameters.truncate(2);
+                }
                 if write_socket(
                     arc_transfer_socket.clone(),
                     &DccMessage::serialize(dcc_answer).unwrap_or_else(|_| "".to_string()),
@@ -101,9 +160,28 @@ pub fn incoming_send_request(
 
     remove_interface_communication(arc_interface_communication, requested_client.clone());
 
+    // the path is kept with the ongoing transfer, so the file is saved in the same path when it is resumed
+    let save_path = save_path(&preferences.download_directory, &file_name, policy)
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_default();
+    match arc_ongoing_transfers.as_ref().write() {
+        Ok(mut arc_ongoing_transfers_lock) => {
+            let ongoing_transfer = OngoingTransfer {
+                file_offset: 0,
+                file_size,
+                file_path: "".to_string(),
+                cipher: cipher.clone(),
+                checksum: checksum.clone(),
+                save_path,
+            };
+            arc_ongoing_transfers_lock.insert(file_name.clone(), ongoing_transfer);
+        }
+        Err(_) => println!("[ERROR] Error accesing the hash for ongoing transfers"),
+    }
+
     thread::spawn(move || {
         match receive_file(
-            (file_name, file_size, 0),
+            (file_name, file_size, 0, cipher, checksum),
             arc_transfer_socket,
             requested_client,
             tx_chats_clone_1,
@@ -118,10 +196,16 @@ pub fn incoming_send_request(
 }
 
 /// Sends a file to the requested client through the dcc connection
-/// It creates a new thread to send the file and then sends a message to the current client's interface
-/// to notify that the file is being sent and show the progress
-/// It also creates a new thread to communicate with the interface, so the user can cancel the transfer
-/// It returns a ClientError if there is an error creating the socket or the thread
+/// It returns when the file is sent, declined or paused, so it is called by the transfer queue of
+/// the connection in its own thread, and it sends to the current client's interface the progress
+/// It also sets a channel to communicate with the transfer, so the user can pause it
+/// The SHA-256 checksum of the file is sent with the request, so the requested client can check
+/// the file it receives
+/// If the request is a SSEND, the file is encrypted with a new nonce, which is sent to the requested
+/// client with the request, after the checksum
+/// The listener of the transfer is opened before the request is sent, and the request carries its port,
+/// so the files of a batch can be sent on the port 0 and each one gets a free port
+/// It returns a ClientError if there is an error creating the socket or sending the file
 pub fn outgoing_send_request(
     requested_client: String,
     arc_socket: Arc<TcpStream>,
@@ -129,13 +213,10 @@ pub fn outgoing_send_request(
     tx_chats: glib::Sender<Response>,
     arc_transfers_communication: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
     arc_ongoing_transfers: Arc<RwLock<HashMap<String, OngoingTransfer>>>,
+    session: Option<SecureSession>,
 ) -> Result<(), ClientError> {
     let filepath = dcc_msg.parameters[1].to_owned();
-    let filename = dcc_msg.parameters[1]
-        .split('/')
-        .last()
-        .unwrap_or("")
-        .to_owned();
+    let filename = sent_file_name(&dcc_msg);
     let ip = dcc_msg.parameters[2].to_owned();
     let port = dcc_msg.parameters[3].to_owned();
     let file_size = dcc_msg.parameters[4].parse::<f64>().unwrap_or(0.0);
@@ -161,11 +242,34 @@ pub fn outgoing_send_request(
         return Err(ClientError::SocketError);
     }
 
-    let mut dcc_msg_aux = dcc_msg;
-    dcc_msg_aux.parameters[1] = filename.clone();
-    let dcc_msg_str = DccMessage::serialize(dcc_msg_aux).unwrap_or_else(|_| "".to_string());
+    let cipher = match (&dcc_msg.command, session) {
+        (DccMessageType::SSend, Some(session)) => Some(session.new_file_cipher()),
+        (DccMessageType::SSend, None) => {
+            let response = Response::DccResponse {
+                response: DccResponse::ErrorResponse {
+                    description: "Encrypted files can only be sent through a secure chat."
+                        .to_owned(),
+                },
+            };
+            if tx_chats.send(response).is_ok() {};
+            return Err(ClientError::KeyExchangeError);
+        }
+        _ => None,
+    };
 
-    if write_socket(arc_socket.clone(), &dcc_msg_str).is_ok() {}
+    let checksum = match file_checksum(&filepath) {
+        Ok(checksum) => checksum,
+        Err(e) => {
+            println!("[ERROR] Error reading the file {filepath}");
+            let response = Response::DccResponse {
+                response: DccResponse::ErrorResponse {
+                    description: "The file can't be read.".to_owned(),
+                },
+            };
+            if tx_chats.send(response).is_ok() {};
+            return Err(e);
+        }
+    };
 
     let listener = match TcpListener::bind(format!("{ip}:{port}")) {
         Ok(listener) => listener,
@@ -180,6 +284,31 @@ pub fn outgoing_send_request(
             return Err(ClientError::SocketError);
         }
     };
+    let listener_port = match listener.local_addr() {
+        Ok(address) => address.port().to_string(),
+        Err(_) => port,
+    };
+
+    let mut dcc_msg_aux = dcc_msg;
+    dcc_msg_aux.parameters[1] = filename.clone();
+    dcc_msg_aux.parameters[3] = listener_port;
+    dcc_msg_aux.parameters.truncate(5);
+    dcc_msg_aux.parameters.push(checksum);
+    if let Some(cipher) = &cipher {
+        dcc_msg_aux.parameters.push(cipher.nonce());
+    }
+    let dcc_msg_str = DccMessage::serialize(dcc_msg_aux).unwrap_or_else(|_| "".to_string());
+
+    if let Err(e) = write_socket(arc_socket.clone(), &dcc_msg_str) {
+        println!("[ERROR] Error sending the DCC SEND offer: {:?}", e);
+        let response = Response::DccResponse {
+            response: DccResponse::ErrorResponse {
+                description: "The file offer can't be sent.".to_owned(),
+            },
+        };
+        if tx_chats.send(response).is_ok() {};
+        return Err(e);
+    }
 
     if let Ok((transfer_socket, _)) = listener.accept() {
         let arc_transfer_socket = Arc::new(transfer_socket);
@@ -229,29 +358,35 @@ pub fn outgoing_send_request(
         transfers_communication_lock.insert(filename.clone(), tx_transfer);
         drop(transfers_communication_lock);
 
-        let filename_clone = filename.clone();
-        thread::spawn(move || {
-            match transfer_file(
-                (filename_clone, filepath, file_size, 0),
-                requested_client,
-                arc_transfer_socket,
-                rx_transfer,
-                tx_chats,
-                arc_socket.clone(),
-                arc_ongoing_transfers,
-            ) {
-                Ok(_) => {}
-                Err(e) => {
-                    println!("[ERROR] Transfer failed: {e:?}");
-                }
-            }
-            remove_transfer_communication(arc_transfers_communication, filename);
-        });
+        let result = transfer_file(
+            (filename.clone(), filepath, file_size, 0, cipher),
+            requested_client,
+            arc_transfer_socket,
+            rx_transfer,
+            tx_chats,
+            arc_socket,
+            arc_ongoing_transfers,
+        );
+        remove_transfer_communication(arc_transfers_communication, filename);
+        result?;
     }
 
     Ok(())
 }
 
+/// Returns the name the file of a send request of the interface is sent with: the path relative to the folder
+/// chosen, if the file is sent in a batch, or else the name of the file
+pub fn sent_file_name(dcc_msg: &DccMessage) -> String {
+    match dcc_msg.parameters.get(5) {
+        Some(name) => name.to_owned(),
+        None => dcc_msg.parameters[1]
+            .split('/')
+            .next_back()
+            .unwrap_or("")
+            .to_owned(),
+    }
+}
+
 fn check_ongoing_transfer(
     arc_ongoing_transfers: Arc<RwLock<HashMap<String, OngoingTransfer>>>,
     requested_client: String,
@@ -265,7 +400,11 @@ fn check_ongoing_transfer(
             return false;
         }
     };
-    if ongoing_transfers_lock.get(&filename).is_some() {
+    // a finished transfer is only kept in case the file has to be sent again
+    let ongoing = ongoing_transfers_lock
+        .get(&filename)
+        .is_some_and(|transfer| (transfer.file_offset as f64) < transfer.file_size);
+    if ongoing {
         let response = Response::DccResponse {
             response: DccResponse::OngoingTransfer {
                 sender: requested_client,
@@ -0,0 +1,180 @@
+use std::time::{Duration, Instant};
+
+use crate::gui::preferences::{Preferences, PREFERENCES_PATH};
+
+/// The time over which the throughput of a transfer is measured
+const MEASURE_WINDOW: Duration = Duration::from_secs(1);
+/// The least time between two progress reports sent to the interface
+const REPORT_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Returns the speed limit of the transfers chosen in the preferences, in bytes per second, or None if
+/// they have no limit
+/// It is read when each transfer starts, so a new limit applies to the next transfers
+pub fn speed_limit() -> Option<u64> {
+    Preferences::load(PREFERENCES_PATH).dcc_speed_limit_bytes()
+}
+
+/// Struct that measures the throughput of a transfer and keeps it under the speed limit
+/// # Fields
+/// * `limit`: The most bytes per second transferred, or None if there is no limit
+/// * `started`: The moment the transfer started or was resumed
+/// * `start_bytes`: The bytes of the file that were already transferred when it started
+/// * `window_start`: The moment the current measure started
+/// * `window_bytes`: The bytes transferred when the current measure started
+/// * `bytes_per_second`: The throughput of the last measure
+/// * `last_report`: The moment the last progress was reported
+#[derive(Debug, Clone)]
+pub struct TransferMeter {
+    limit: Option<u64>,
+    started: Instant,
+    start_bytes: u64,
+    window_start: Instant,
+    window_bytes: u64,
+    bytes_per_second: u64,
+    last_report: Option<Instant>,
+}
+
+impl TransferMeter {
+    /// Creates a new meter for a transfer that starts with the bytes already transferred
+    pub fn new(limit: Option<u64>, start_bytes: u64, now: Instant) -> Self {
+        TransferMeter {
+            limit,
+            started: now,
+            start_bytes,
+            window_start: now,
+            window_bytes: start_bytes,
+            bytes_per_second: 0,
+            last_report: None,
+        }
+    }
+
+    /// Records the bytes transferred so far and returns how long the transfer has to wait to stay under
+    /// the speed limit
+    pub fn record(&mut self, bytes: u64, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.window_start);
+        if elapsed >= MEASURE_WINDOW {
+            let transferred = bytes.saturating_sub(self.window_bytes);
+            self.bytes_per_second = (transferred as f64 / elapsed.as_secs_f64()) as u64;
+            self.window_start = now;
+            self.window_bytes = bytes;
+        }
+
+        match self.limit {
+            Some(limit) if limit > 0 => {
+                let transferred = bytes.saturating_sub(self.start_bytes);
+                let expected = Duration::from_secs_f64(transferred as f64 / limit as f64);
+                expected.saturating_sub(now.saturating_duration_since(self.started))
+            }
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// Returns if the progress has to be reported to the interface, which happens every REPORT_INTERVAL
+    /// and when the transfer is complete, so the interface is not flooded with progress messages
+    pub fn should_report(&mut self, complete: bool, now: Instant) -> bool {
+        let report = complete
+            || self
+                .last_report
+                .is_none_or(|last| now.saturating_duration_since(last) >= REPORT_INTERVAL);
+        if report {
+            self.last_report = Some(now);
+        }
+        report
+    }
+
+    /// Returns the throughput of the transfer, in bytes per second, or 0 if it was not measured yet
+    pub fn bytes_per_second(&self) -> u64 {
+        self.bytes_per_second
+    }
+
+    /// Returns the seconds left to transfer the rest of the file at the current throughput, or 0 if it
+    /// was not measured yet
+    pub fn seconds_left(&self, bytes: u64, file_size: u64) -> u64 {
+        match self.bytes_per_second {
+            0 => 0,
+            speed => file_size.saturating_sub(bytes).div_ceil(speed),
+        }
+    }
+}
+
+/// Returns the text that shows the throughput and the time left of a transfer, like `1.2 MB/s, 30s left`,
+/// or an empty text if the throughput was not measured yet
+pub fn throughput_text(bytes_per_second: u64, seconds_left: u64) -> String {
+    if bytes_per_second == 0 {
+        return String::new();
+    }
+    let speed = bytes_per_second as f64;
+    let speed = if speed >= 1024.0 * 1024.0 {
+        format!("{:.1} MB/s", speed / (1024.0 * 1024.0))
+    } else if speed >= 1024.0 {
+        format!("{:.1} KB/s", speed / 1024.0)
+    } else {
+        format!("{bytes_per_second} B/s")
+    };
+    let time_left = if seconds_left >= 3600 {
+        format!("{}h {}m", seconds_left / 3600, seconds_left % 3600 / 60)
+    } else if seconds_left >= 60 {
+        format!("{}m {}s", seconds_left / 60, seconds_left % 60)
+    } else {
+        format!("{seconds_left}s")
+    };
+    format!("{speed}, {time_left} left")
+}
+
+#[cfg(test)]
+mod dcc_speed_test {
+    use std::time::{Duration, Instant};
+
+    use super::{throughput_text, TransferMeter};
+
+    #[test]
+    fn test_transfer_waits_to_stay_under_the_speed_limit() {
+        let start = Instant::now();
+        let mut meter = TransferMeter::new(Some(1024), 2048, start);
+        assert_eq!(meter.record(2048 + 512, start), Duration::from_millis(500));
+        assert_eq!(
+            meter.record(2048 + 1024, start + Duration::from_millis(250)),
+            Duration::from_millis(750)
+        );
+        assert_eq!(
+            meter.record(2048 + 1024, start + Duration::from_secs(2)),
+            Duration::ZERO
+        );
+
+        let mut meter = TransferMeter::new(None, 0, start);
+        assert_eq!(meter.record(1 << 30, start), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_throughput_and_time_left_are_measured_every_second() {
+        let start = Instant::now();
+        let mut meter = TransferMeter::new(None, 1000, start);
+        meter.record(3000, start + Duration::from_millis(500));
+        assert_eq!(meter.bytes_per_second(), 0);
+        assert_eq!(meter.seconds_left(3000, 10000), 0);
+
+        meter.record(5000, start + Duration::from_secs(2));
+        assert_eq!(meter.bytes_per_second(), 2000);
+        assert_eq!(meter.seconds_left(5000, 10001), 3);
+        assert_eq!(meter.seconds_left(10001, 10001), 0);
+    }
+
+    #[test]
+    fn test_progress_is_reported_at_most_every_interval_and_when_complete() {
+        let start = Instant::now();
+        let mut meter = TransferMeter::new(None, 0, start);
+        assert!(meter.should_report(false, start));
+        assert!(!meter.should_report(false, start + Duration::from_millis(100)));
+        assert!(meter.should_report(true, start + Duration::from_millis(100)));
+        assert!(meter.should_report(false, start + Duration::from_millis(300)));
+    }
+
+    #[test]
+    fn test_throughput_is_shown_with_the_time_left() {
+        assert_eq!(throughput_text(0, 10), "");
+        assert_eq!(throughput_text(512, 5), "512 B/s, 5s left");
+        assert_eq!(throughput_text(1258291, 30), "1.2 MB/s, 30s left");
+        assert_eq!(throughput_text(2048, 125), "2.0 KB/s, 2m 5s left");
+        assert_eq!(throughput_text(2048, 3720), "2.0 KB/s, 1h 2m left");
+    }
+}
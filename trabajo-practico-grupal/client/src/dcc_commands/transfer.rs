@@ -3,30 +3,49 @@ use std::{
     fs::{File, OpenOptions},
     io::{Read, Seek, SeekFrom, Write},
     net::{Shutdown, TcpStream},
-    path::Path,
+    path::{Path, PathBuf},
     sync::{
         mpsc::{Receiver, SyncSender},
         Arc, RwLock,
     },
     thread,
-    time::Duration,
+    time::Instant,
 };
 
+use super::{
+    batch::path_inside,
+    downloads::download_directory,
+    saved_transfers::{SavedTransfer, TransferRecord, SAVED_TRANSFERS_PATH},
+    speed::{speed_limit, TransferMeter},
+};
 use gtk::glib;
 use model::{
     client_errors::ClientError,
+    crypto::{to_hex, FileCipher, Sha256},
     dcc::{DccMessage, DccMessageType},
     responses::{dcc::DccResponse, ongoing_transfer::OngoingTransfer, response::Response},
     socket::write_socket,
 };
 
-/// Receives data from the socket and writes it to the file
-/// It also sends the progress of the transfer to the interface
-/// The file_data contains the file name, the file size and the file offset
+/// The folder where the received files are saved if the preferences don't have one.
+pub const RECEIVED_FILES_DIRECTORY: &str = "./client/files_to_receive";
+
+/// Receives data from the socket and writes it to the file, keeping it under the speed limit of the preferences
+/// It also sends the progress of the transfer to the interface, with its throughput and the time left
+/// The file_data contains the file name, the file size, the file offset, the cipher of the file,
+/// if it is sent through a secure connection, and the checksum sent with the request
+/// The file is saved in the path kept in its ongoing transfer when it was accepted, or in the download folder
+/// of the preferences. The file name can be a path relative to the folder, whose folders are created
+/// The bytes sent after the size of the file are not written, and the transfer ends with an error in the
+/// interface, so the file is never bigger than the size accepted
+/// When the file is complete, its checksum is checked. If it doesn't match, the transfer is marked
+/// as failed in the interface and kept from the start, so the file can be requested again with a resume
+/// If the file is not encrypted, the transfer is saved to disk while it is received, so it can be resumed
+/// after the client is restarted
 /// It returns a ClientError if there is an error reading from the socket or writing to the file
 pub fn receive_file(
-    // file_data.0 = file_name, file_data.1 = file_size, file_data.2 = file_offset
-    file_data: (String, f64, u64),
+    // file_data.0 = file_name, file_data.1 = file_size, file_data.2 = file_offset, file_data.3 = cipher, file_data.4 = checksum
+    file_data: (String, f64, u64, Option<FileCipher>, Option<String>),
     arc_transfer_socket: Arc<TcpStream>,
     requested_client: String,
     tx_chats: glib::Sender<Response>,
@@ -35,17 +54,29 @@ pub fn receive_file(
     let file_name = file_data.0;
     let mut file_size = file_data.1;
     let file_offset = file_data.2;
-
-    let transfer_folder = Path::new("./client/files_to_receive");
-    if !transfer_folder.exists() && std::fs::create_dir(transfer_folder).is_err() {
+    let cipher = file_data.3;
+    let checksum = file_data.4;
+
+    let path_to_save = match get_save_path(arc_ongoing_transfers.clone(), &file_name)
+        .or_else(|| path_inside(&download_directory(), &file_name))
+    {
+        Some(path) => path,
+        None => {
+            println!("[ERROR] The file name {file_name} is not a path inside the transfer folder");
+            return Ok(());
+        }
+    };
+    let transfer_folder = path_to_save
+        .parent()
+        .unwrap_or_else(|| Path::new(RECEIVED_FILES_DIRECTORY));
+    if !transfer_folder.exists() && std::fs::create_dir_all(transfer_folder).is_err() {
         println!("[ERROR] Error creating transfer folder");
         return Ok(());
     }
 
     let mut file;
-    let path_to_save = format!("./client/files_to_receive/{file_name}");
     if file_offset == 0 {
-        file = match File::create(path_to_save) {
+        file = match File::create(&path_to_save) {
             Ok(file) => file,
             Err(e) => {
                 println!("[ERROR] Error opening file: {e:?}");
@@ -57,7 +88,7 @@ pub fn receive_file(
             .read(false)
             .create(false)
             .append(true)
-            .open(path_to_save)
+            .open(&path_to_save)
         {
             Ok(file) => file,
             Err(e) => {
@@ -77,6 +108,21 @@ pub fn receive_file(
     }
 
     let mut file_bytes_read = file_offset;
+    let mut meter = TransferMeter::new(speed_limit(), file_offset, Instant::now());
+    let mut record = cipher.is_none().then(|| {
+        TransferRecord::new(
+            SAVED_TRANSFERS_PATH,
+            SavedTransfer {
+                peer: requested_client.clone(),
+                file_name: file_name.clone(),
+                file_offset,
+                file_size,
+                checksum: checksum.clone().unwrap_or_default(),
+                save_path: path_to_save.to_string_lossy().to_string(),
+                ..Default::default()
+            },
+        )
+    });
     loop {
         let mut buffer = [0; 1024];
 
@@ -85,16 +131,21 @@ pub fn receive_file(
                 if file_bytes_read < file_size as u64 {
                     println!("[DEBUG] Socket returned 0 so transfer stopped.");
                     println!("[DEBUG] Stopped on {file_bytes_read} bytes");
+                    if let Some(record) = &mut record {
+                        record.save();
+                    }
                     update_ongoing_transfer(
                         arc_ongoing_transfers.clone(),
                         file_bytes_read,
                         file_size,
                         file_name.clone(),
                         "".to_string(),
+                        cipher.clone(),
+                        checksum.clone(),
                     );
                     let response = Response::DccResponse {
                         response: DccResponse::TransferPaused {
-                            sender: requested_client,
+                            sender: requested_client.clone(),
                             file_name: file_name.clone(),
                         },
                     };
@@ -102,28 +153,64 @@ pub fn receive_file(
                 }
                 break;
             }
-            Ok(bytes_read) => match file.write(&buffer[0..bytes_read]) {
-                Ok(bytes_written) => {
-                    if bytes_written != bytes_read {
-                        println!("[ERROR] Error writing to file");
-                        break;
-                    } else {
-                        file_bytes_read += bytes_read as u64;
-                        send_progress(
-                            file_bytes_read as f64,
-                            file_size,
-                            &requested_client,
-                            &file_name,
-                            tx_chats.clone(),
-                        )?;
+            Ok(bytes_read) => {
+                let bytes_left = (file_size as u64).saturating_sub(file_bytes_read) as usize;
+                let oversized = bytes_read > bytes_left;
+                let bytes_read = bytes_read.min(bytes_left);
+                if let Some(cipher) = &cipher {
+                    cipher.apply(file_bytes_read, &mut buffer[0..bytes_read]);
+                }
+                match file.write(&buffer[0..bytes_read]) {
+                    Ok(bytes_written) => {
+                        if bytes_written != bytes_read {
+                            println!("[ERROR] Error writing to file");
+                            break;
+                        } else {
+                            file_bytes_read += bytes_read as u64;
+                            let now = Instant::now();
+                            if let Some(record) = &mut record {
+                                record.update(&buffer[0..bytes_read], now);
+                            }
+                            let wait = meter.record(file_bytes_read, now);
+                            if meter.should_report(file_bytes_read >= file_size as u64, now) {
+                                send_progress(
+                                    file_bytes_read as f64,
+                                    file_size,
+                                    &meter,
+                                    &requested_client,
+                                    &file_name,
+                                    tx_chats.clone(),
+                                )?;
+                            }
+                            thread::sleep(wait);
+                        }
+                    }
+                    Err(e) => {
+                        println!("[ERROR] Error writing to file: {e:?}");
                     }
                 }
-                Err(e) => {
-                    println!("[ERROR] Error writing to file: {e:?}");
+                if oversized {
+                    println!(
+                        "[ERROR] {requested_client} sent more bytes than the size of {file_name}"
+                    );
+                    let response = Response::DccResponse {
+                        response: DccResponse::ErrorResponse {
+                            description: format!(
+                                "{requested_client} sent more bytes than the size of {file_name}, so the file was cut at {} bytes.",
+                                file_size as u64
+                            ),
+                        },
+                    };
+                    if tx_chats.send(response).is_ok() {};
+                    if arc_transfer_socket.shutdown(Shutdown::Both).is_ok() {};
+                    break;
                 }
-            },
+            }
             Err(e) => {
                 println!("[ERROR] Error reading from socket: {e:?}");
+                if let Some(record) = &mut record {
+                    record.save();
+                }
                 return Ok(());
             }
         }
@@ -134,24 +221,66 @@ pub fn receive_file(
             file_size,
             file_name.clone(),
             "".to_string(),
+            cipher.clone(),
+            checksum.clone(),
         );
     }
 
     if file_bytes_read == file_size as u64 {
-        println!("[INFO] Transfer complete, deleting ongoing transfer");
-        remove_ongoing_transfer(arc_ongoing_transfers, file_name);
+        if checksum_matches(&path_to_save.to_string_lossy(), checksum.as_deref()) {
+            println!("[INFO] Transfer complete, deleting ongoing transfer");
+            remove_ongoing_transfer(arc_ongoing_transfers, file_name.clone());
+            if let Some(record) = &record {
+                record.forget();
+            }
+            let response = Response::DccResponse {
+                response: DccResponse::FileReceived {
+                    sender: requested_client,
+                    file_name,
+                    path: path_to_save.to_string_lossy().to_string(),
+                },
+            };
+            if tx_chats.send(response).is_ok() {};
+        } else {
+            println!(
+                "[ERROR] The checksum of {file_name} doesn't match, it has to be received again"
+            );
+            if let Some(record) = &mut record {
+                record.restart();
+            }
+            update_ongoing_transfer(
+                arc_ongoing_transfers,
+                0,
+                file_size,
+                file_name.clone(),
+                "".to_string(),
+                cipher,
+                checksum,
+            );
+            let response = Response::DccResponse {
+                response: DccResponse::TransferFailed {
+                    sender: requested_client,
+                    file_name,
+                },
+            };
+            if tx_chats.send(response).is_ok() {};
+        }
     }
 
     Ok(())
 }
 
-/// Sends the file to the requested client through the transfer socket
-/// It also sends the progress of the transfer to the interface
-/// The file_data contains the file name, the file path, the file size and the file offset
+/// Sends the file to the requested client through the transfer socket, keeping it under the speed limit of
+/// the preferences
+/// It also sends the progress of the transfer to the interface, with its throughput and the time left
+/// The file_data contains the file name, the file path, the file size, the file offset and the cipher
+/// of the file, if it is sent through a secure connection
+/// If the file is not encrypted, the transfer is saved to disk while it is sent, so it can be resumed
+/// after the client is restarted
 /// It returns a ClientError if there is an error reading from the file or writing to the socket
 pub fn transfer_file(
-    // (filedata.0 = filename, filedata.1 = filepath, filedata.2 = filesize, filedata.3 = offset)
-    file_data: (String, String, f64, u64),
+    // (filedata.0 = filename, filedata.1 = filepath, filedata.2 = filesize, filedata.3 = offset, filedata.4 = cipher)
+    file_data: (String, String, f64, u64, Option<FileCipher>),
     requested_client: String,
     arc_transfer_socket: Arc<TcpStream>,
     rx_transfer: Receiver<String>,
@@ -163,6 +292,7 @@ pub fn transfer_file(
     let file_path = file_data.1;
     let mut file_size = file_data.2;
     let file_offset = file_data.3;
+    let cipher = file_data.4;
 
     let mut file = match File::open(file_path.clone()) {
         Ok(file) => file,
@@ -183,6 +313,20 @@ pub fn transfer_file(
     }
 
     let mut bytes_read = file_offset;
+    let mut meter = TransferMeter::new(speed_limit(), file_offset, Instant::now());
+    let mut record = cipher.is_none().then(|| {
+        TransferRecord::new(
+            SAVED_TRANSFERS_PATH,
+            SavedTransfer {
+                peer: requested_client.clone(),
+                file_name: file_name.clone(),
+                file_offset,
+                file_size,
+                file_path: file_path.clone(),
+                ..Default::default()
+            },
+        )
+    });
     loop {
         let mut buffer = [0; 1024];
 
@@ -191,6 +335,9 @@ pub fn transfer_file(
                 match dcc_msg.command {
                     DccMessageType::Stop => {
                         println!("[INFO] Stopping transfer");
+                        if let Some(record) = &mut record {
+                            record.save();
+                        }
                         if dcc_msg.parameters.len() < 3 {
                             let message_for_client =
                                 format!("DCC STOP {requested_client} {file_name} {bytes_read}");
@@ -201,6 +348,8 @@ pub fn transfer_file(
                                 file_size,
                                 file_name.clone(),
                                 file_path,
+                                cipher,
+                                None,
                             );
                         } else {
                             update_ongoing_transfer(
@@ -209,6 +358,8 @@ pub fn transfer_file(
                                 file_size,
                                 file_name.clone(),
                                 file_path,
+                                cipher,
+                                None,
                             );
                         }
                         let response = Response::DccResponse {
@@ -240,8 +391,9 @@ pub fn transfer_file(
             }
         };
 
-        // just to test progress bar in GUI
-        thread::sleep(Duration::from_millis(1000));
+        if let Some(cipher) = &cipher {
+            cipher.apply(bytes_read, &mut buffer[0..read]);
+        }
 
         match arc_transfer_socket.as_ref().write(&buffer[0..read]) {
             Ok(0) => {
@@ -255,13 +407,22 @@ pub fn transfer_file(
                 } else {
                     bytes_read += read as u64;
 
-                    send_progress(
-                        bytes_read as f64,
-                        file_size,
-                        &requested_client,
-                        &file_name,
-                        tx_chats.clone(),
-                    )?;
+                    let now = Instant::now();
+                    if let Some(record) = &mut record {
+                        record.update(&buffer[0..read], now);
+                    }
+                    let wait = meter.record(bytes_read, now);
+                    if meter.should_report(bytes_read >= file_size as u64, now) {
+                        send_progress(
+                            bytes_read as f64,
+                            file_size,
+                            &meter,
+                            &requested_client,
+                            &file_name,
+                            tx_chats.clone(),
+                        )?;
+                    }
+                    thread::sleep(wait);
                 }
             }
             Err(e) => {
@@ -276,21 +437,30 @@ pub fn transfer_file(
             file_size,
             file_name.clone(),
             file_path.clone(),
+            cipher.clone(),
+            None,
         );
     }
 
     if bytes_read == file_size as u64 {
-        println!("[INFO] Transfer complete, deleting ongoing transfer..");
-        remove_ongoing_transfer(arc_ongoing_transfers, file_name);
+        // the transfer is kept, so the file can be sent again if its checksum doesn't match
+        println!("[INFO] Transfer complete");
+        if let Some(record) = &record {
+            record.forget();
+        }
+    } else if let Some(record) = &mut record {
+        record.save();
     }
 
     Ok(())
 }
 
-/// Sends the progress of the transfer to the interface
+/// Sends the progress of the transfer to the interface, with the throughput and the time left measured
+/// by the meter
 pub fn send_progress(
     bytes_transfered: f64,
     file_size: f64,
+    meter: &TransferMeter,
     requested_client: &str,
     filename: &str,
     tx_chats: glib::Sender<Response>,
@@ -302,19 +472,51 @@ pub fn send_progress(
             sender: requested_client.to_owned(),
             file_name: filename.to_owned(),
             progress,
+            bytes_per_second: meter.bytes_per_second(),
+            seconds_left: meter.seconds_left(bytes_transfered as u64, file_size as u64),
         },
     };
     if tx_chats.send(response).is_ok() {};
     Ok(())
 }
 
+/// Returns the SHA-256 checksum of the file, in hexadecimal
+/// It returns a ClientError if the file can't be read
+pub fn file_checksum(file_path: &str) -> Result<String, ClientError> {
+    let mut file = File::open(file_path).map_err(|_| ClientError::FileError)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0; 1024];
+    loop {
+        match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => hasher.update(&buffer[..read]),
+            Err(_) => return Err(ClientError::FileError),
+        }
+    }
+    Ok(to_hex(&hasher.finish()))
+}
+
+/// Checks the checksum of the received file against the one sent with the request
+/// If no checksum was sent, the file can't be checked, so it matches
+pub fn checksum_matches(file_path: &str, checksum: Option<&str>) -> bool {
+    match checksum {
+        Some(checksum) => file_checksum(file_path)
+            .map(|file_checksum| file_checksum.eq_ignore_ascii_case(checksum))
+            .unwrap_or(false),
+        None => true,
+    }
+}
+
 /// Updates the ongoing transfers hash with the the file and its file data
+/// The path where a received file is saved is kept from the ongoing transfer it replaces
 pub fn update_ongoing_transfer(
     arc_ongoing_transfers: Arc<RwLock<HashMap<String, OngoingTransfer>>>,
     bytes_read: u64,
     file_size: f64,
     file_name: String,
     file_path: String,
+    cipher: Option<FileCipher>,
+    checksum: Option<String>,
 ) {
     let mut arc_ongoing_transfers_lock = match arc_ongoing_transfers.as_ref().write() {
         Ok(arc_ongoing_transfers) => arc_ongoing_transfers,
@@ -324,10 +526,17 @@ pub fn update_ongoing_transfer(
         }
     };
 
+    let save_path = arc_ongoing_transfers_lock
+        .get(&file_name)
+        .map(|ongoing_transfer| ongoing_transfer.save_path.clone())
+        .unwrap_or_default();
     let ongoing_transfer = OngoingTransfer {
         file_offset: bytes_read,
         file_size,
         file_path,
+        cipher,
+        checksum,
+        save_path,
     };
 
     arc_ongoing_transfers_lock.insert(file_name, ongoing_transfer);
@@ -406,6 +615,50 @@ fn get_file_size(
     }
 }
 
+/// Gets the path where the file being received is saved from the ongoing transfers hash
+/// In case the file is not being received or the hash can't be accessed, it returns None
+fn get_save_path(
+    arc_ongoing_transfers: Arc<RwLock<HashMap<String, OngoingTransfer>>>,
+    file_name: &str,
+) -> Option<PathBuf> {
+    let arc_ongoing_transfers_lock = match arc_ongoing_transfers.as_ref().read() {
+        Ok(lock) => lock,
+        Err(_) => {
+            println!("[ERROR] Error accesing the hash for ongoing transfers");
+            return None;
+        }
+    };
+
+    arc_ongoing_transfers_lock
+        .get(file_name)
+        .filter(|ongoing_transfer| !ongoing_transfer.save_path.is_empty())
+        .map(|ongoing_transfer| PathBuf::from(&ongoing_transfer.save_path))
+}
+
+#[cfg(test)]
+mod dcc_transfer_test {
+    use super::{checksum_matches, file_checksum};
+
+    #[test]
+    fn test_received_file_matches_only_the_checksum_of_the_sent_file() {
+        let path = std::env::temp_dir().join(format!("dcc_checksum_{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "abc").unwrap();
+        let checksum = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        assert_eq!(file_checksum(path).unwrap(), checksum);
+
+        assert!(checksum_matches(path, Some(checksum)));
+        assert!(checksum_matches(path, Some(&checksum.to_uppercase())));
+        assert!(checksum_matches(path, None));
+        std::fs::write(path, "abd").unwrap();
+        assert!(!checksum_matches(path, Some(checksum)));
+
+        std::fs::remove_file(path).unwrap();
+        assert!(file_checksum(path).is_err());
+        assert!(!checksum_matches(path, Some(checksum)));
+    }
+}
+
 /*
 #[cfg(test)]
 mod dcc_tests {
@@ -413,21 +666,26 @@ mod dcc_tests {
 
     //use crate::{chat::incoming_chat_request, transfer::{receive_file, transfer_file, remove_transfer_communication}, close::{incoming_close_request, outgoing_close_request}};
     use gtk::glib;
-    use model::{persistence::PersistenceType, session::Session, network::Network, message::Message, server::Server, dcc::{DccMessage, DccMessageType}, responses::{dcc::DccResponse, response::Response}};
-    use server::{client_handler::register_client, database::handle_database};
+    use model::{channel_registry::ChannelRegistry, client_registry::ClientRegistry, command_metrics::CommandMetrics, config::ServerConfig, events::EventBus, gc_stats::GcStats, persistence::PersistenceType, plugins::Plugins, session::Session, socket_registry::SocketRegistry, link_queues::LinkQueues, network::Network, message::Message, server::Server, dcc::{DccMessage, DccMessageType}, responses::{dcc::DccResponse, numeric::WireFormat, response::Response}};
+    use server::{client_handler::register_client, database::{handle_database, DatabasePaths}};
 
     use crate::dcc_commands::transfer::{receive_file, transfer_file, remove_transfer_communication};
 
 
     fn create_session_for_test(tx: Sender<(PersistenceType, String)>) -> Session {
-        let clients = Arc::new(std::sync::RwLock::new(std::collections::HashMap::new()));
-        let sockets = Arc::new(Mutex::new(HashMap::new()));
-        let channels = Arc::new(std::sync::RwLock::new(HashMap::new()));
         Session {
-            clients,
-            sockets,
-            channels,
+            clients: ClientRegistry::default(),
+            sockets: SocketRegistry::default(),
+            connections: Arc::new(Mutex::new(HashMap::new())),
+            rejected_connections: Arc::new(Mutex::new(HashMap::new())),
+            channels: ChannelRegistry::default(),
             database_sender: tx,
+            config: Arc::new(RwLock::new(ServerConfig::default())),
+            events: EventBus::default(),
+            plugins: Plugins::default(),
+            who_queries: Arc::new(Mutex::new(HashMap::new())),
+            command_metrics: CommandMetrics::default(),
+            gc_stats: GcStats::default(),
         }
     }
 
@@ -444,7 +702,7 @@ mod dcc_tests {
             message_user,
             (&mut nickname, &mut user_parameters),
             &mut password,
-            stream.clone(),
+            (stream.clone(), WireFormat::Custom),
             &session,
             &network,
             &"test".to_string(),
@@ -454,7 +712,7 @@ mod dcc_tests {
             message_nick,
             (&mut nickname, &mut user_parameters),
             &mut password,
-            stream.clone(),
+            (stream.clone(), WireFormat::Custom),
             &session,
             &network,
             &"test".to_string(),
@@ -469,7 +727,7 @@ mod dcc_tests {
         let port = listener.local_addr().unwrap().port();
         let address_port = format!("127.0.0.1:{}", port.to_string());
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "127.0.0.1".to_string(),
             port: port.to_string(),
@@ -479,13 +737,14 @@ mod dcc_tests {
             children: HashMap::new(),
         }));
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let client_stream_sender = Arc::new(TcpStream::connect(address_port.clone()).unwrap());
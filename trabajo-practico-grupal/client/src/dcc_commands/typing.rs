@@ -0,0 +1,104 @@
+use std::time::{Duration, Instant};
+
+use gtk::glib;
+use model::{
+    dcc::DccMessage,
+    responses::{dcc::DccResponse, response::Response},
+};
+
+/// The least time between two typing messages sent while the user keeps typing
+pub const TYPING_REFRESH: Duration = Duration::from_secs(3);
+/// The time the interface shows that the requested client is typing after its last typing message, in
+/// case its message that it stopped is lost
+pub const TYPING_TIMEOUT: Duration = Duration::from_secs(6);
+
+/// Struct that decides when the current client tells the requested client it is typing, so a message is
+/// not sent for every key pressed
+/// # Fields
+/// * `last_sent`: The moment the last message that the user is typing was sent, or None if the user is
+///   not typing
+#[derive(Debug, Default)]
+pub struct TypingNotifier {
+    last_sent: Option<Instant>,
+}
+
+impl TypingNotifier {
+    /// Records if the user is typing, which is when the message written is not empty, and returns the
+    /// state sent to the requested client, or None if nothing is sent
+    /// While the user keeps typing, the message is sent again every TYPING_REFRESH, so the requested
+    /// client keeps showing it
+    pub fn update(&mut self, typing: bool, now: Instant) -> Option<bool> {
+        match (typing, self.last_sent) {
+            (true, Some(last)) if now.saturating_duration_since(last) < TYPING_REFRESH => None,
+            (true, _) => {
+                self.last_sent = Some(now);
+                Some(true)
+            }
+            (false, Some(_)) => {
+                self.last_sent = None;
+                Some(false)
+            }
+            (false, None) => None,
+        }
+    }
+}
+
+/// Returns the dcc message that tells the requested client if the current client is typing
+pub fn typing_message(requested_client: &str, typing: bool) -> String {
+    let state = if typing { "ON" } else { "OFF" };
+    format!("DCC TYPING {requested_client} {state}")
+}
+
+/// Manages an incoming dcc typing message from another client, sending if it is typing to the interface
+/// `DCC TYPING <client> ON|OFF`
+pub fn incoming_typing_request(
+    requested_client: String,
+    dcc_msg: DccMessage,
+    tx_chats: glib::Sender<Response>,
+) {
+    let typing = match dcc_msg.parameters.get(1).map(|state| state.to_uppercase()) {
+        Some(state) if state == "ON" => true,
+        Some(state) if state == "OFF" => false,
+        _ => {
+            println!("[ERROR] Invalid DCC TYPING message received from {requested_client}");
+            return;
+        }
+    };
+    let response = Response::DccResponse {
+        response: DccResponse::Typing {
+            sender: requested_client,
+            typing,
+        },
+    };
+    if tx_chats.send(response).is_ok() {};
+}
+
+#[cfg(test)]
+mod dcc_typing_test {
+    use std::time::{Duration, Instant};
+
+    use super::{typing_message, TypingNotifier, TYPING_REFRESH};
+
+    #[test]
+    fn test_typing_is_sent_again_only_after_the_refresh_time() {
+        let start = Instant::now();
+        let mut notifier = TypingNotifier::default();
+        assert_eq!(notifier.update(false, start), None);
+        assert_eq!(notifier.update(true, start), Some(true));
+        assert_eq!(
+            notifier.update(true, start + Duration::from_millis(500)),
+            None
+        );
+        assert_eq!(notifier.update(true, start + TYPING_REFRESH), Some(true));
+
+        assert_eq!(notifier.update(false, start + TYPING_REFRESH), Some(false));
+        assert_eq!(notifier.update(false, start + TYPING_REFRESH), None);
+        assert_eq!(notifier.update(true, start + TYPING_REFRESH), Some(true));
+    }
+
+    #[test]
+    fn test_typing_messages_have_the_state() {
+        assert_eq!(typing_message("bob", true), "DCC TYPING bob ON");
+        assert_eq!(typing_message("bob", false), "DCC TYPING bob OFF");
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+/// Struct that represents the activity of a conversation shown in the sidebar.
+/// # Fields
+/// * `unread`: The messages received since the conversation was last opened.
+/// * `mentioned`: True if one of the unread messages mentions the user's nickname.
+/// * `last_activity`: The order of the last message received, higher is more recent.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Activity {
+    pub unread: usize,
+    pub mentioned: bool,
+    pub last_activity: u64,
+}
+
+/// Struct that keeps the activity of every conversation, to show the unread counters and
+/// sort the conversations by the most recent activity.
+/// # Fields
+/// * `conversations`: The activity of each channel or user.
+/// * `counter`: The order of the last message received.
+#[derive(Debug, Default)]
+pub struct ActivityTracker {
+    conversations: HashMap<String, Activity>,
+    counter: u64,
+}
+
+impl ActivityTracker {
+    /// Creates a tracker without activity.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a message received in a conversation. If the conversation is open the message
+    /// is already read, so only its activity is updated.
+    /// # Arguments
+    /// * `target`: The channel or user of the conversation.
+    /// * `text`: The text of the message.
+    /// * `nickname`: The nickname of the user, to know if the message mentions them.
+    /// * `is_open`: True if the conversation is the one shown.
+    pub fn record(&mut self, target: &str, text: &str, nickname: &str, is_open: bool) {
+        self.counter += 1;
+        let activity = self.conversations.entry(target.to_string()).or_default();
+        activity.last_activity = self.counter;
+        if !is_open {
+            activity.unread += 1;
+            activity.mentioned |= mentions(text, nickname);
+        }
+    }
+
+    /// Marks the messages of a conversation as read, when it is opened.
+    pub fn open(&mut self, target: &str) {
+        if let Some(activity) = self.conversations.get_mut(target) {
+            activity.unread = 0;
+            activity.mentioned = false;
+        }
+    }
+
+    /// Returns the activity of a conversation.
+    pub fn get(&self, target: &str) -> Activity {
+        self.conversations.get(target).cloned().unwrap_or_default()
+    }
+
+    /// Returns the label of a conversation in the sidebar, with its unread counter if it has one.
+    pub fn label(&self, target: &str) -> String {
+        match self.get(target).unread {
+            0 => target.to_string(),
+            unread => format!("{} ({})", target, unread),
+        }
+    }
+
+    /// Sorts the conversations by the most recent activity. The ones without activity keep
+    /// their order, after the others.
+    pub fn sort_by_activity(&self, targets: &mut [String]) {
+        targets.sort_by_key(|target| std::cmp::Reverse(self.get(target).last_activity));
+    }
+}
+
+/// Returns true if the text mentions the nickname, as a word of the text, ignoring the case.
+pub fn mentions(text: &str, nickname: &str) -> bool {
+    !nickname.is_empty()
+        && text
+            .split_whitespace()
+            .map(|word| word.trim_matches(|c: char| ":,.!?;'\"()".contains(c)))
+            .any(|word| word.eq_ignore_ascii_case(nickname))
+}
+
+#[cfg(test)]
+mod activity_tests {
+    use super::{mentions, ActivityTracker};
+
+    #[test]
+    fn test_messages_are_counted_until_the_conversation_is_opened() {
+        let mut tracker = ActivityTracker::new();
+        tracker.record("#a", "hello", "alice", false);
+        tracker.record("#b", "hi alice!", "alice", false);
+        tracker.record("#a", "bye", "alice", false);
+        tracker.record("bob", "seen", "alice", true);
+
+        assert_eq!(tracker.label("#a"), "#a (2)");
+        assert!(!tracker.get("#a").mentioned);
+        assert!(tracker.get("#b").mentioned);
+        assert_eq!(tracker.label("bob"), "bob");
+
+        let mut targets = ["#b", "#c", "#a", "bob"].map(|t| t.to_string()).to_vec();
+        tracker.sort_by_activity(&mut targets);
+        assert_eq!(targets, vec!["bob", "#a", "#b", "#c"]);
+
+        tracker.open("#b");
+        assert_eq!(tracker.label("#b"), "#b");
+        assert!(!tracker.get("#b").mentioned);
+    }
+
+    #[test]
+    fn test_mentions_match_the_nickname_as_a_word() {
+        assert!(mentions("Alice: are you there?", "alice"));
+        assert!(mentions("thanks, alice.", "alice"));
+        assert!(!mentions("malice", "alice"));
+        assert!(!mentions("hello", ""));
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+
+/// The flags of a channel that are switched on or off, with the key of each one in the modes of the channel.
+pub const CHANNEL_FLAGS: [(char, &str); 6] = [
+    ('i', "InviteOnly"),
+    ('m', "ModeratedChannel"),
+    ('s', "Secret"),
+    ('p', "Private"),
+    ('t', "TopicSettableOnlyOperators"),
+    ('n', "NoMessageFromOutside"),
+];
+
+/// Struct that represents the settings of a channel shown in the channel settings dialog.
+/// # Fields
+/// * `topic`: The topic of the channel.
+/// * `flags`: The flags of the channel that are on.
+/// * `key`: The key of the channel, empty if it has none.
+/// * `limit`: The user limit of the channel, if it has one.
+/// * `bans`: The nicknames of the banned users.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChannelSettings {
+    pub topic: String,
+    pub flags: Vec<char>,
+    pub key: String,
+    pub limit: Option<u32>,
+    pub bans: Vec<String>,
+}
+
+impl ChannelSettings {
+    /// Creates the settings of a channel from its topic and the reply to a MODE query.
+    /// # Arguments
+    /// * `topic`: The topic of the channel.
+    /// * `mode_string`: The modes of the channel with their parameters, like `+tkl secret 10`.
+    /// * `modes`: The modes of the channel, with a `+` for the flags that are on and the bans
+    ///   separated by commas, like `bob:alice:1700000000:`.
+    pub fn from_modes(topic: &str, mode_string: &str, modes: &HashMap<String, String>) -> Self {
+        let flags = CHANNEL_FLAGS
+            .iter()
+            .filter(|(_, key)| modes.get(*key).is_some_and(|value| value == "+"))
+            .map(|(flag, _)| *flag)
+            .collect();
+        let bans = modes
+            .get("Ban")
+            .map(|bans| {
+                bans.split(',')
+                    .filter_map(|ban| ban.split(':').next())
+                    .filter(|nickname| !nickname.is_empty())
+                    .map(|nickname| nickname.to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let limit = modes.get("UserLimit").and_then(|limit| limit.parse().ok());
+
+        Self {
+            topic: topic.to_string(),
+            flags,
+            key: key_of_mode_string(mode_string).unwrap_or_default(),
+            limit,
+            bans,
+        }
+    }
+
+    /// Returns the commands that change the settings of the channel to the edited ones,
+    /// a TOPIC if the topic changed and a MODE for each mode that changed.
+    /// The bans are added and removed from the dialog, so they are not compared.
+    pub fn commands(&self, channel: &str, edited: &ChannelSettings) -> Vec<String> {
+        let mut commands = vec![];
+        if edited.topic != self.topic {
+            commands.push(format!("TOPIC {} :{}", channel, edited.topic));
+        }
+        for (flag, _) in CHANNEL_FLAGS {
+            let (before, after) = (self.flags.contains(&flag), edited.flags.contains(&flag));
+            if before != after {
+                let action = if after { '+' } else { '-' };
+                commands.push(format!("MODE {} {}{}", channel, action, flag));
+            }
+        }
+        if edited.key != self.key {
+            if !self.key.is_empty() {
+                commands.push(format!("MODE {} -k {}", channel, self.key));
+            }
+            if !edited.key.is_empty() {
+                commands.push(format!("MODE {} +k {}", channel, edited.key));
+            }
+        }
+        if edited.limit != self.limit {
+            match edited.limit {
+                Some(limit) => commands.push(format!("MODE {} +l {}", channel, limit)),
+                None => commands.push(format!("MODE {} -l", channel)),
+            }
+        }
+        commands
+    }
+}
+
+/// Returns the key in the modes of a channel, like `+tkl secret 10`, or None if it has no key.
+fn key_of_mode_string(mode_string: &str) -> Option<String> {
+    let mut words = mode_string.split_whitespace();
+    let flags = words.next()?;
+    for flag in flags.chars() {
+        match flag {
+            'k' => return words.next().map(|key| key.to_string()),
+            'l' | 'f' => {
+                words.next();
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod channel_settings_tests {
+    use std::collections::HashMap;
+
+    use super::ChannelSettings;
+
+    fn modes() -> HashMap<String, String> {
+        [
+            ("InviteOnly", "+"),
+            ("Secret", "-"),
+            ("TopicSettableOnlyOperators", "+"),
+            ("UserLimit", "10"),
+            (
+                "Ban",
+                "bob:alice:1700000000:,carol:alice:1700000000:1700003600",
+            ),
+        ]
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+    }
+
+    #[test]
+    fn test_settings_are_read_from_the_modes_of_the_channel() {
+        let settings = ChannelSettings::from_modes("Rust", "+tikl secret 10", &modes());
+
+        assert_eq!(settings.topic, "Rust");
+        assert_eq!(settings.flags, vec!['i', 't']);
+        assert_eq!(settings.key, "secret");
+        assert_eq!(settings.limit, Some(10));
+        assert_eq!(settings.bans, vec!["bob", "carol"]);
+        assert!(ChannelSettings::from_modes("", "+", &HashMap::new())
+            .bans
+            .is_empty());
+    }
+
+    #[test]
+    fn test_only_the_edited_settings_are_sent() {
+        let settings = ChannelSettings::from_modes("Rust", "+tikl secret 10", &modes());
+        assert!(settings.commands("#rust", &settings).is_empty());
+
+        let edited = ChannelSettings {
+            topic: "Rust!".to_string(),
+            flags: vec!['t', 'm'],
+            key: "other".to_string(),
+            limit: None,
+            ..settings.clone()
+        };
+        assert_eq!(
+            settings.commands("#rust", &edited),
+            vec![
+                "TOPIC #rust :Rust!",
+                "MODE #rust -i",
+                "MODE #rust +m",
+                "MODE #rust -k secret",
+                "MODE #rust +k other",
+                "MODE #rust -l",
+            ]
+        );
+    }
+}
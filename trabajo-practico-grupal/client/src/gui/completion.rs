@@ -0,0 +1,119 @@
+use model::consts::{OPERATOR_FLAG, VOICE_FLAG};
+
+/// Struct that completes the nicknames typed in the message entry, cycling through the
+/// members of the channel that start with the word typed each time Tab is pressed.
+/// # Fields
+/// * `cycle`: The completion in progress, while the text is the last one completed.
+#[derive(Debug, Default)]
+pub struct NickCompleter {
+    cycle: Option<Cycle>,
+}
+
+/// The state of a completion in progress.
+/// # Fields
+/// * `before`: The text before the word completed.
+/// * `candidates`: The nicknames that start with the word, sorted.
+/// * `index`: The candidate of the last completion.
+/// * `completed`: The text after the last completion.
+#[derive(Debug)]
+struct Cycle {
+    before: String,
+    candidates: Vec<String>,
+    index: usize,
+    completed: String,
+}
+
+impl NickCompleter {
+    /// Creates a completer without a completion in progress.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Completes the last word of the text with the next member of the channel that starts with it,
+    /// ignoring the case. A nickname at the start of the line is followed by `: ` and one after
+    /// other words by a space. If the text is the last one completed, the next member is used.
+    /// Returns None if no member starts with the word.
+    /// # Arguments
+    /// * `text`: The text of the message entry.
+    /// * `members`: The nicknames of the members of the channel, which may have the `@` or `+` prefixes.
+    pub fn complete(&mut self, text: &str, members: &[String]) -> Option<String> {
+        match self.cycle.as_mut() {
+            Some(cycle) if cycle.completed == text => {
+                cycle.index = (cycle.index + 1) % cycle.candidates.len();
+            }
+            _ => self.cycle = Some(Self::start(text, members)?),
+        }
+        let cycle = self.cycle.as_mut()?;
+        let nickname = &cycle.candidates[cycle.index];
+        cycle.completed = if cycle.before.is_empty() {
+            format!("{}: ", nickname)
+        } else {
+            format!("{}{} ", cycle.before, nickname)
+        };
+        Some(cycle.completed.to_owned())
+    }
+
+    /// Starts a completion of the last word of the text.
+    fn start(text: &str, members: &[String]) -> Option<Cycle> {
+        let (before, word) = match text.rfind(' ') {
+            Some(position) => text.split_at(position + 1),
+            None => ("", text),
+        };
+        let mut candidates = members
+            .iter()
+            .map(|member| {
+                member
+                    .trim_start_matches([OPERATOR_FLAG, VOICE_FLAG])
+                    .to_string()
+            })
+            .filter(|member| member.to_lowercase().starts_with(&word.to_lowercase()))
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|member| member.to_lowercase());
+        candidates.dedup();
+        if candidates.is_empty() {
+            return None;
+        }
+        Some(Cycle {
+            before: before.to_string(),
+            candidates,
+            index: 0,
+            completed: String::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod completion_tests {
+    use super::NickCompleter;
+
+    fn members() -> Vec<String> {
+        ["@bob", "+Bart", "alice", "bill"]
+            .map(|member| member.to_string())
+            .to_vec()
+    }
+
+    #[test]
+    fn test_completion_cycles_through_the_members_at_the_start_of_the_line() {
+        let mut completer = NickCompleter::new();
+
+        let first = completer.complete("b", &members()).unwrap();
+        assert_eq!(first, "Bart: ");
+        let second = completer.complete(&first, &members()).unwrap();
+        assert_eq!(second, "bill: ");
+        let third = completer.complete(&second, &members()).unwrap();
+        assert_eq!(third, "bob: ");
+        assert_eq!(completer.complete(&third, &members()).unwrap(), "Bart: ");
+    }
+
+    #[test]
+    fn test_completion_after_other_words_adds_a_space() {
+        let mut completer = NickCompleter::new();
+
+        assert_eq!(
+            completer.complete("thanks AL", &members()).unwrap(),
+            "thanks alice "
+        );
+        assert_eq!(completer.complete("thanks zed", &members()), None);
+        assert_eq!(completer.complete("", &members()).unwrap(), "alice: ");
+    }
+}
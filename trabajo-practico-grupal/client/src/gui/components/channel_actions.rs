@@ -2,6 +2,7 @@ use gtk::{
     glib::{self, clone},
     prelude::*,
 };
+use model::consts::is_channel_name;
 use std::sync::mpsc::Sender;
 
 use super::actions::fetch_information;
@@ -66,7 +67,7 @@ impl ChannelActions {
             clone!(@weak join_modal, @weak join_channel_entry, @weak pass_channel_entry, @weak error_join, @weak self.error_modal as error_modal=> move |_| {
                 let channel = join_channel_entry.text();
                 let pass = pass_channel_entry.text();
-                if !channel.starts_with('#') && !channel.starts_with('&') {
+                if !is_channel_name(&channel) {
                     error_join.set_text("Channel name must start with # or &");
                     join_channel_entry.set_text("");
                     pass_channel_entry.set_text("");
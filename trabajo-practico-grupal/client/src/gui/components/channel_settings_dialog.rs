@@ -0,0 +1,238 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc, sync::mpsc::Sender};
+
+use gtk::prelude::*;
+
+use crate::gui::channel_settings::ChannelSettings;
+
+/// The channel settings dialog, where the operators of a channel edit its topic, switch its flags,
+/// set its key and user limit, and add or remove bans. The edits are translated into the TOPIC and
+/// MODE commands that change them.
+/// # Fields
+/// * `tx`: The sender of the channel that sends the messages to the server.
+/// * `settings_modal`: The window of the dialog.
+/// * `title`: The label with the name of the channel.
+/// * `current_chat`: The label containing the name of the current chat.
+/// * `topic_entry`: The entry of the topic.
+/// * `flag_switches`: The switch of each flag of the channel.
+/// * `key_entry`: The entry of the key.
+/// * `limit_entry`: The entry of the user limit.
+/// * `bans_box`: The box with the banned users.
+/// * `ban_entry`: The entry of the nickname to ban.
+/// * `error_label`: The label that shows the errors of the edits.
+/// * `topics`: The topic of each channel.
+/// * `settings`: The channel shown and its settings before the edits.
+#[derive(Clone)]
+pub struct ChannelSettingsDialog {
+    tx: Sender<String>,
+    settings_modal: gtk::Window,
+    title: gtk::Label,
+    current_chat: gtk::Label,
+    topic_entry: gtk::Entry,
+    flag_switches: Vec<(char, gtk::Switch)>,
+    key_entry: gtk::Entry,
+    limit_entry: gtk::Entry,
+    bans_box: gtk::Box,
+    ban_entry: gtk::Entry,
+    error_label: gtk::Label,
+    topics: Rc<RefCell<HashMap<String, String>>>,
+    settings: Rc<RefCell<Option<(String, ChannelSettings)>>>,
+}
+
+impl ChannelSettingsDialog {
+    /// Creates a new `ChannelSettingsDialog` struct.
+    /// # Arguments
+    /// * `builder`: The builder of the glade file that builds the application.
+    /// * `tx`: The sender of the channel that sends the messages to the server.
+    pub fn new(builder: &gtk::Builder, tx: Sender<String>) -> Self {
+        let switch = |id: &str| builder.object::<gtk::Switch>(id).unwrap();
+        let flag_switches = vec![
+            ('i', switch("settings_invite_switch")),
+            ('m', switch("settings_moderated_switch")),
+            ('s', switch("settings_secret_switch")),
+            ('p', switch("settings_private_switch")),
+            ('t', switch("settings_topic_switch")),
+            ('n', switch("settings_no_outside_switch")),
+        ];
+
+        ChannelSettingsDialog {
+            tx,
+            settings_modal: builder.object("channel_settings_modal").unwrap(),
+            title: builder.object("channel_settings_title").unwrap(),
+            current_chat: builder.object("current_chat").unwrap(),
+            topic_entry: builder.object("settings_topic_entry").unwrap(),
+            flag_switches,
+            key_entry: builder.object("settings_key_entry").unwrap(),
+            limit_entry: builder.object("settings_limit_entry").unwrap(),
+            bans_box: builder.object("settings_bans_box").unwrap(),
+            ban_entry: builder.object("settings_ban_entry").unwrap(),
+            error_label: builder.object("settings_error_label").unwrap(),
+            topics: Rc::new(RefCell::new(HashMap::new())),
+            settings: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    /// Builds the SETTINGS button, which asks the server for the modes of the current channel and
+    /// opens the dialog, and the buttons of the dialog that add a ban and save the edits.
+    /// # Arguments
+    /// * `builder`: The builder of the glade file that builds the application.
+    pub fn build(&self, builder: &gtk::Builder) {
+        let settings_button = builder
+            .object::<gtk::Button>("channel_settings_button")
+            .unwrap();
+        let add_ban_button = builder
+            .object::<gtk::Button>("settings_add_ban_button")
+            .unwrap();
+        let save_button = builder
+            .object::<gtk::Button>("settings_save_button")
+            .unwrap();
+
+        self.settings_modal
+            .connect_delete_event(move |_win, _| _win.hide_on_delete());
+
+        let dialog = self.clone();
+        settings_button.connect_clicked(move |_| {
+            let channel = dialog.current_chat.text().to_string();
+            dialog.error_label.set_text("");
+            dialog.title.set_text(&format!("SETTINGS OF {}", channel));
+            if dialog.tx.send(format!("MODE {}", channel)).is_ok() {
+                dialog.settings_modal.show();
+            }
+        });
+
+        let dialog = self.clone();
+        add_ban_button.connect_clicked(move |_| {
+            let nickname = dialog.ban_entry.text().to_string();
+            if nickname.is_empty() {
+                dialog.error_label.set_text("Enter the nick to ban");
+                return;
+            }
+            dialog.ban_entry.set_text("");
+            dialog.send_to_shown_channel(&format!("+b {}", nickname));
+        });
+
+        let dialog = self.clone();
+        save_button.connect_clicked(move |_| dialog.save());
+    }
+
+    /// Updates the topic of a channel.
+    pub fn update_topic(&self, channel: &str, topic: &str) {
+        self.topics
+            .borrow_mut()
+            .insert(channel.to_string(), topic.to_string());
+    }
+
+    /// Updates the settings of a channel with the reply to a MODE query, showing them in the
+    /// dialog if the channel is the current chat.
+    /// # Arguments
+    /// * `channel`: The name of the channel.
+    /// * `mode_string`: The modes of the channel with their parameters.
+    /// * `modes`: The modes of the channel.
+    pub fn update_channel_modes(
+        &self,
+        channel: &str,
+        mode_string: &str,
+        modes: &HashMap<String, String>,
+    ) {
+        if self.current_chat.text().as_str() != channel {
+            return;
+        }
+        let topic = self
+            .topics
+            .borrow()
+            .get(channel)
+            .cloned()
+            .unwrap_or_default();
+        let settings = ChannelSettings::from_modes(&topic, mode_string, modes);
+        self.show_settings(&settings);
+        *self.settings.borrow_mut() = Some((channel.to_string(), settings));
+    }
+
+    /// Shows the settings in the widgets of the dialog.
+    fn show_settings(&self, settings: &ChannelSettings) {
+        self.topic_entry.set_text(&settings.topic);
+        for (flag, switch) in self.flag_switches.iter() {
+            switch.set_active(settings.flags.contains(flag));
+        }
+        self.key_entry.set_text(&settings.key);
+        let limit = settings.limit.map(|l| l.to_string()).unwrap_or_default();
+        self.limit_entry.set_text(&limit);
+
+        for widget in self.bans_box.children() {
+            self.bans_box.remove(&widget);
+        }
+        if settings.bans.is_empty() {
+            self.bans_box.add(&gtk::Label::new(Some("No banned users")));
+        }
+        for nickname in settings.bans.iter() {
+            let ban_box = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+            ban_box.add(&gtk::Label::new(Some(nickname)));
+            let remove_button = gtk::Button::with_label("REMOVE");
+            let dialog = self.clone();
+            let nickname = nickname.to_string();
+            remove_button.connect_clicked(move |_| {
+                dialog.send_to_shown_channel(&format!("-b {}", nickname));
+            });
+            ban_box.add(&remove_button);
+            self.bans_box.add(&ban_box);
+        }
+        self.bans_box.show_all();
+    }
+
+    /// Reads the edited settings and sends the commands that change them, then asks the server
+    /// for the modes of the channel to show them again.
+    fn save(&self) {
+        let (channel, settings) = match self.settings.borrow().clone() {
+            Some(shown) => shown,
+            None => return,
+        };
+        let limit_text = self.limit_entry.text().to_string();
+        let limit = match limit_text.trim() {
+            "" => None,
+            limit => match limit.parse::<u32>() {
+                Ok(limit) => Some(limit),
+                Err(_) => {
+                    self.error_label.set_text("Limit must be a positive number");
+                    return;
+                }
+            },
+        };
+        let edited = ChannelSettings {
+            topic: self.topic_entry.text().to_string(),
+            flags: self
+                .flag_switches
+                .iter()
+                .filter(|(_, switch)| switch.is_active())
+                .map(|(flag, _)| *flag)
+                .collect(),
+            key: self.key_entry.text().to_string(),
+            limit,
+            bans: settings.bans.clone(),
+        };
+        for command in settings.commands(&channel, &edited) {
+            if self.tx.send(command).is_err() {
+                self.error_label.set_text("Error sending the settings");
+                return;
+            }
+        }
+        if edited.topic != settings.topic {
+            self.update_topic(&channel, &edited.topic);
+        }
+        self.error_label.set_text("");
+        if self.tx.send(format!("MODE {}", channel)).is_ok() {
+            self.settings_modal.hide();
+        }
+    }
+
+    /// Sends a mode change to the channel shown, and asks for its modes to show the change.
+    fn send_to_shown_channel(&self, mode: &str) {
+        let channel = match self.settings.borrow().as_ref() {
+            Some((channel, _)) => channel.to_string(),
+            None => return,
+        };
+        if self.tx.send(format!("MODE {} {}", channel, mode)).is_err()
+            || self.tx.send(format!("MODE {}", channel)).is_err()
+        {
+            self.error_label.set_text("Error sending the settings");
+        }
+    }
+}
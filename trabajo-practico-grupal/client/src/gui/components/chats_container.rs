@@ -1,12 +1,27 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
 use std::sync::mpsc::Sender;
 
 use gtk::{
     glib::{self, clone},
     prelude::*,
 };
+use model::{
+    consts::{OPERATOR_FLAG, VOICE_FLAG},
+    ctcp::CtcpMessage,
+    formatting::strip_formatting,
+    responses::message::MessageResponse,
+};
 
+use crate::chat_log::{is_channel, split_line, ChatLog, LOG_DIRECTORY, RELOADED_LINES};
 use crate::gui::{
-    messages_box::{message_received_box, message_sent_box},
+    activity::mentions,
+    completion::NickCompleter,
+    components::dcc_feature::dcc_chat_command,
+    input_history::InputHistory,
+    messages_box::{message_received_box, message_sent_box, nick_message_box, system_message_box},
+    nick_colors::NickColors,
     utils::{adjust_scroll_to_bottom, new_conversation},
 };
 
@@ -15,6 +30,11 @@ use crate::gui::{
 /// # Fields
 /// * `scrolled_window`: The scrolled window that contains the messages.
 /// * `stack_conversations`: The stack of conversations.
+/// * `chat_log`: The logs where the messages of the conversations are saved.
+/// * `members`: The members of each channel, from the NAMES replies, to complete their nicknames.
+/// * `nick_label`: The label that contains the nick of the user, to highlight the messages that mention it.
+/// * `nick_colors`: The colors of the nicknames of the senders.
+/// * `input_history`: The messages sent in each conversation, recalled in the message entry.
 ///
 pub struct ChatsContainer {
     scrolled_window: gtk::ScrolledWindow,
@@ -26,13 +46,19 @@ pub struct ChatsContainer {
     close_dcc_button: gtk::Button,
     chat_button: gtk::Button,
     file_chooser_button: gtk::FileChooserButton,
+    chat_log: ChatLog,
+    members: Rc<RefCell<HashMap<String, Vec<String>>>>,
+    nick_label: gtk::Label,
+    nick_colors: Rc<RefCell<NickColors>>,
+    input_history: Rc<RefCell<InputHistory>>,
 }
 
 impl ChatsContainer {
     /// Creates a new `ChatsContainer` struct.
     /// # Arguments
     /// * `builder`: The builder of the glade file that builds the application.
-    pub fn new(builder: &gtk::Builder) -> Self {
+    /// * `nick_colors`: The colors of the nicknames of the senders.
+    pub fn new(builder: &gtk::Builder, nick_colors: Rc<RefCell<NickColors>>) -> Self {
         let scrolled_window = builder
             .object::<gtk::ScrolledWindow>("scrolled_window")
             .unwrap();
@@ -47,6 +73,7 @@ impl ChatsContainer {
         let send_button = builder.object::<gtk::Button>("send_message").unwrap();
         let message_entry = builder.object::<gtk::Entry>("chat_input").unwrap();
         let close_dcc_button = builder.object::<gtk::Button>("close_dcc_button").unwrap();
+        let nick_label = builder.object::<gtk::Label>("user_nick").unwrap();
 
         ChatsContainer {
             scrolled_window,
@@ -58,38 +85,231 @@ impl ChatsContainer {
             close_dcc_button,
             chat_button,
             file_chooser_button,
+            chat_log: ChatLog::new(LOG_DIRECTORY),
+            members: Rc::new(RefCell::new(HashMap::new())),
+            nick_label,
+            nick_colors,
+            input_history: Rc::new(RefCell::new(InputHistory::new())),
         }
     }
 
-    /// Builds the send button and the message entry.
+    /// Builds the send button and the message entry, and reloads the last messages of the logged conversations.
     /// # Arguments
     /// * `tx`: The sender of the channel that sends the messages to the server.
     /// * `builder`: The builder of the glade file that builds the application.
     pub fn build(&self, builder: &gtk::Builder, tx: Sender<String>) {
         self.active_send_button(builder, tx);
+        self.active_nick_completion();
+        self.active_input_history();
+        self.load_history();
+    }
+
+    /// Updates the members of a channel, used to complete their nicknames.
+    /// # Arguments
+    /// * `channel`: The name of the channel.
+    /// * `names`: The nicknames of the members of the channel.
+    pub fn update_members(&self, channel: String, names: Vec<String>) {
+        self.members.borrow_mut().insert(channel, names);
+    }
+
+    /// Completes the nickname typed in the message entry when Tab is pressed, cycling through
+    /// the members of the current channel that start with it.
+    pub fn active_nick_completion(&self) {
+        let members = self.members.clone();
+        let completer = RefCell::new(NickCompleter::new());
+        self.message_entry.connect_key_press_event(
+            clone!(@weak self.current_chat as current_chat => @default-return glib::Propagation::Proceed, move |entry, event| {
+                if event.keyval() != gtk::gdk::keys::constants::Tab {
+                    return glib::Propagation::Proceed;
+                }
+                let channel_members = members.borrow().get(current_chat.text().as_str()).cloned().unwrap_or_default();
+                if let Some(text) = completer.borrow_mut().complete(entry.text().as_str(), &channel_members) {
+                    entry.set_text(&text);
+                    entry.set_position(-1);
+                }
+                glib::Propagation::Stop
+            }),
+        );
+    }
+
+    /// Recalls the messages sent in the current conversation when Up and Down are pressed in the
+    /// message entry, showing again the text typed before after the newest one.
+    pub fn active_input_history(&self) {
+        let input_history = self.input_history.clone();
+        self.message_entry.connect_key_press_event(
+            clone!(@weak self.current_chat as current_chat => @default-return glib::Propagation::Proceed, move |entry, event| {
+                let recalled = match event.keyval() {
+                    gtk::gdk::keys::constants::Up => input_history.borrow_mut().previous(current_chat.text().as_str(), entry.text().as_str()),
+                    gtk::gdk::keys::constants::Down => input_history.borrow_mut().next(current_chat.text().as_str(), entry.text().as_str()),
+                    _ => return glib::Propagation::Proceed,
+                };
+                if let Some(text) = recalled {
+                    entry.set_text(&text);
+                    entry.set_position(-1);
+                }
+                glib::Propagation::Stop
+            }),
+        );
+    }
+
+    /// Adds the last lines of the log of each conversation to its conversation stack,
+    /// so the messages of previous sessions are shown when the client starts, with the
+    /// nicknames of the senders in their colors.
+    pub fn load_history(&self) {
+        for target in self.chat_log.targets() {
+            let stack_name = if is_channel(&target) {
+                "Channel conversations"
+            } else {
+                "User conversations"
+            };
+            for line in self.chat_log.last_lines(&target, RELOADED_LINES) {
+                let message_box = match split_line(&line) {
+                    Some((time, sender, text)) => self.nick_box(time, sender, text),
+                    None => message_received_box(line, "message_received"),
+                };
+                Self::add_message_to_screen(self, target.clone(), message_box, stack_name);
+            }
+        }
+    }
+
+    /// Appends a message to the log of its conversation, informing if it can't be saved.
+    /// # Arguments
+    /// * `target`: The channel or user of the conversation.
+    /// * `sender`: The name of the sender.
+    /// * `message`: The text of the message.
+    pub fn log_message(&self, target: &str, sender: &str, message: &str) {
+        if self.chat_log.append(target, sender, message).is_err() {
+            println!("[ERROR] Error saving the message to the log of {target}");
+        }
     }
 
     /// Adds a new message to the corresponding conversation stack, depending on the channel, showing it on the left side of the conversation.
+    /// The nickname of the sender is shown in its color, and the message is highlighted if it mentions the user.
     /// # Arguments
     /// * `sender`: The name of the sender.
     /// * `message`: The message that was received.
     /// * `channel`: The channel that the message was sent to.
     pub fn add_message_channel_received(&self, channel: String, sender: String, message: String) {
-        let message_box =
-            message_received_box(format!("{}: {}", sender, message), "message_received");
+        self.log_message(&channel, &sender, &message);
+        let message_box = self.nick_box("", &sender, &message);
         Self::add_message_to_screen(self, channel, message_box, "Channel conversations");
     }
 
+    /// Creates the box of a message with the nickname of the sender in its color, highlighted if the
+    /// message mentions the user.
+    fn nick_box(&self, prefix: &str, sender: &str, message: &str) -> gtk::Box {
+        let nickname = self.nick_label.text();
+        let mentioned =
+            sender != nickname.as_str() && mentions(&strip_formatting(message), &nickname);
+        let color = self.nick_colors.borrow().color(sender);
+        nick_message_box(prefix, sender, color, message, mentioned)
+    }
+
+    /// Adds a new chat line to the conversation of a room, showing it on the left side of the conversation
+    /// with the nickname of the sender in its color.
+    /// # Arguments
+    /// * `room`: The name of the room.
+    /// * `sender`: The name of the sender.
+    /// * `message`: The message that was received.
+    pub fn add_room_message_received(&self, room: String, sender: String, message: String) {
+        let message_box = self.nick_box("", &sender, &message);
+        Self::add_message_to_screen(self, room, message_box, "DCC conversations");
+    }
+
     /// Adds a new message to the corresponding user stack, showing it on the left side of the conversation.
+    /// The messages of the user conversations are saved to their logs.
     /// # Arguments
     /// * `sender`: The name of the sender.
     /// * `message`: The message that was received.
     pub fn add_message_received(&self, sender: String, message: String, stack_name: &str) {
         println!("El message received es {}", message);
+        if stack_name == "User conversations" {
+            self.log_message(&sender, &sender, &message);
+        }
         let message_box: gtk::Box = message_received_box(message, "message_received");
         Self::add_message_to_screen(self, sender, message_box, stack_name);
     }
 
+    /// Adds the delivery receipt of a private message to the conversation with the receiver,
+    /// showing it on the right side of the conversation, below the message sent.
+    /// # Arguments
+    /// * `receiver`: The nickname of the receiver of the message.
+    /// * `status`: The status of the delivery, `delivered`, `away` or `offline`.
+    pub fn add_delivery_receipt(&self, receiver: String, status: String) {
+        let text = match status.as_str() {
+            "delivered" => "Delivered".to_string(),
+            "away" => format!("Delivered, {} is away", receiver),
+            "offline" => format!("Not delivered, {} is offline", receiver),
+            _ => status,
+        };
+        let message_box = message_sent_box(text, "delivery_receipt");
+        Self::add_message_to_screen(self, receiver, message_box, "User conversations");
+    }
+
+    /// Adds a presence or mode event as a system line to the conversations it belongs to: the
+    /// conversation of the channel for the joins, parts and modes set, and the conversations shared
+    /// with the user for the quits.
+    /// # Arguments
+    /// * `event`: The message of the event received from the server.
+    pub fn add_event(&self, event: MessageResponse) {
+        let text = match event.event_text() {
+            Some(text) => text,
+            None => return,
+        };
+        match event {
+            MessageResponse::JoinMsg { channel, .. }
+            | MessageResponse::PartMsg { channel, .. }
+            | MessageResponse::ChannelModeMsg { channel, .. } => {
+                self.add_system_message(channel, text)
+            }
+            MessageResponse::QuitMsg { nickname, .. } => self.add_quit_message(nickname, text),
+            _ => (),
+        }
+    }
+
+    /// Adds a system line, like a user that joined the channel or a mode set, to the conversation of a channel.
+    /// The system lines are not saved to the logs.
+    /// # Arguments
+    /// * `channel`: The channel of the conversation.
+    /// * `text`: The text of the system line.
+    fn add_system_message(&self, channel: String, text: String) {
+        let message_box = system_message_box(text);
+        Self::add_message_to_screen(self, channel, message_box, "Channel conversations");
+    }
+
+    /// Adds the system line of a user that quit to the conversations of the channels the user was a
+    /// member of and to the conversation with the user, if there is one.
+    /// # Arguments
+    /// * `nickname`: The nickname of the user that quit.
+    /// * `text`: The text of the system line.
+    fn add_quit_message(&self, nickname: String, text: String) {
+        let channels = self
+            .members
+            .borrow()
+            .iter()
+            .filter(|(_, names)| {
+                names
+                    .iter()
+                    .any(|name| name.trim_start_matches([OPERATOR_FLAG, VOICE_FLAG]) == nickname)
+            })
+            .map(|(channel, _)| channel.to_owned())
+            .collect::<Vec<_>>();
+        for channel in channels {
+            self.add_system_message(channel, text.to_owned());
+        }
+
+        let user_conversations = self
+            .stack_conversations
+            .child_by_name("User conversations")
+            .unwrap()
+            .downcast::<gtk::Stack>()
+            .unwrap();
+        if user_conversations.child_by_name(&nickname).is_some() {
+            let message_box = system_message_box(text);
+            Self::add_message_to_screen(self, nickname, message_box, "User conversations");
+        }
+    }
+
     /// Adds a new message to the corresponding conversation stack, depending on the channel or user,
     ///  showing it on the right side of the conversation.
     /// # Arguments
@@ -131,6 +351,8 @@ impl ChatsContainer {
         let message_entry: gtk::Entry = builder.object::<gtk::Entry>("chat_input").unwrap();
         let receiver_label: gtk::Label = builder.object::<gtk::Label>("current_chat").unwrap();
         let stack_conversations = builder.object::<gtk::Stack>("conversation_stack").unwrap();
+        let chat_log = self.chat_log.clone();
+        let input_history = self.input_history.clone();
         let file_chooser_button: gtk::FileChooserButton = builder
             .object::<gtk::FileChooserButton>("file_chooser_button")
             .unwrap();
@@ -144,13 +366,23 @@ impl ChatsContainer {
         }));
 
         send_button.connect_clicked(
-            clone!(@weak message_entry, @weak ip_port_dcc_modal_file, @weak builder, @weak file_chooser_button,  @weak self.scrolled_window as scrolled_window, @weak stack_conversations, @weak receiver_label => move |_| {
+            clone!(@weak message_entry, @weak ip_port_dcc_modal_file, @weak builder, @weak file_chooser_button,  @weak self.scrolled_window as scrolled_window, @weak stack_conversations, @weak receiver_label, @weak self.nick_label as nick_label => move |_| {
                 if stack_conversations.child_by_name("Loadings").unwrap() != stack_conversations.visible_child().unwrap() && stack_conversations.child_by_name("No conversation").unwrap() != stack_conversations.visible_child().unwrap() {
                     adjust_scroll_to_bottom(&scrolled_window);
                     let message = message_entry.text();
                     if !message.is_empty() {
-                        let style = if stack_conversations.child_by_name("DCC conversations").unwrap() == stack_conversations.visible_child().unwrap() {
-                            match tx.send(format!("DCC CHAT {} {}", receiver_label.text(), message)){
+                        let is_dcc = stack_conversations.child_by_name("DCC conversations").unwrap() == stack_conversations.visible_child().unwrap();
+                        let style = if is_dcc {
+                            match tx.send(dcc_chat_command(&stack_conversations, &nick_label.text(), &receiver_label.text(), &message)){
+                                Ok(_) => {
+                                    "command_sent"
+                                },
+                                Err(_) => {
+                                    "error_message"
+                                }
+                            }
+                        } else if let Some(action) = message.strip_prefix("/me ") {
+                            match tx.send(format!("PRIVMSG {} :{}", receiver_label.text(), CtcpMessage::new("ACTION", action))){
                                 Ok(_) => {
                                     "command_sent"
                                 },
@@ -169,6 +401,12 @@ impl ChatsContainer {
                             }
                         };
 
+                        if style == "command_sent" {
+                            input_history.borrow_mut().push(&receiver_label.text(), &message);
+                        }
+                        if !is_dcc && style == "command_sent" && chat_log.append(&receiver_label.text(), "You", &message).is_err() {
+                            println!("[ERROR] Error saving the message to the log of {}", receiver_label.text());
+                        }
                         let message_box: gtk::Box = message_sent_box(message.to_string(), style);
                         message_box.set_widget_name(&message);
                         message_entry.set_text("");
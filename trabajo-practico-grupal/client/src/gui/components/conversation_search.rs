@@ -0,0 +1,201 @@
+use std::{cell::RefCell, rc::Rc};
+
+use gtk::{glib, prelude::*};
+
+use crate::gui::search::SearchMatches;
+
+/// The search bar over the conversation shown, opened with Ctrl+F, which highlights the messages
+/// that contain the text searched and jumps between them. The messages are read from the
+/// conversation box every time, so the live messages and the history loaded from the logs are
+/// both searched.
+/// # Fields
+/// * `search_bar`: The bar that shows the search entry.
+/// * `search_entry`: The entry of the text searched.
+/// * `result_label`: The label that shows which match is selected.
+/// * `scrolled_window`: The scrolled window that contains the messages.
+/// * `stack_conversations`: The stack of conversations.
+/// * `matches`: The messages of the conversation shown that match the text searched.
+/// * `highlighted`: The messages highlighted, to remove their highlight.
+#[derive(Clone)]
+pub struct ConversationSearch {
+    search_bar: gtk::SearchBar,
+    search_entry: gtk::SearchEntry,
+    result_label: gtk::Label,
+    scrolled_window: gtk::ScrolledWindow,
+    stack_conversations: gtk::Stack,
+    matches: Rc<RefCell<SearchMatches>>,
+    highlighted: Rc<RefCell<Vec<gtk::Widget>>>,
+}
+
+impl ConversationSearch {
+    /// Creates a new `ConversationSearch` struct.
+    /// # Arguments
+    /// * `builder`: The builder of the glade file that builds the application.
+    pub fn new(builder: &gtk::Builder) -> Self {
+        ConversationSearch {
+            search_bar: builder.object("conversation_search_bar").unwrap(),
+            search_entry: builder.object("conversation_search_entry").unwrap(),
+            result_label: builder.object("conversation_search_label").unwrap(),
+            scrolled_window: builder.object("scrolled_window").unwrap(),
+            stack_conversations: builder.object("conversation_stack").unwrap(),
+            matches: Rc::new(RefCell::new(SearchMatches::default())),
+            highlighted: Rc::new(RefCell::new(vec![])),
+        }
+    }
+
+    /// Builds the Ctrl+F shortcut of the screens of the connection that opens the bar, and the
+    /// entry and the buttons that search the text and move to the previous and the next match.
+    /// Enter moves to the previous match, since the newest one is selected first, and Escape
+    /// closes the bar.
+    /// # Arguments
+    /// * `builder`: The builder of the glade file that builds the application.
+    pub fn build(&self, builder: &gtk::Builder) {
+        let stack = builder.object::<gtk::Stack>("stack").unwrap();
+        let current_chat = builder.object::<gtk::Label>("current_chat").unwrap();
+        let previous_button = builder
+            .object::<gtk::Button>("search_previous_button")
+            .unwrap();
+        let next_button = builder.object::<gtk::Button>("search_next_button").unwrap();
+
+        self.search_bar.connect_entry(&self.search_entry);
+
+        let search = self.clone();
+        stack.connect_key_press_event(move |_, event| {
+            let control = event.state().contains(gtk::gdk::ModifierType::CONTROL_MASK);
+            if !control || event.keyval().to_lower() != gtk::gdk::keys::constants::f {
+                return glib::Propagation::Proceed;
+            }
+            search.search_bar.set_search_mode(true);
+            search.search_entry.grab_focus();
+            search.search(|_| ());
+            glib::Propagation::Stop
+        });
+
+        let search = self.clone();
+        self.search_entry
+            .connect_search_changed(move |_| search.search(|_| ()));
+        let search = self.clone();
+        self.search_entry
+            .connect_activate(move |_| search.search(SearchMatches::previous));
+        let search = self.clone();
+        self.search_entry
+            .connect_previous_match(move |_| search.search(SearchMatches::previous));
+        let search = self.clone();
+        self.search_entry
+            .connect_next_match(move |_| search.search(SearchMatches::next));
+        let search = self.clone();
+        previous_button.connect_clicked(move |_| search.search(SearchMatches::previous));
+        let search = self.clone();
+        next_button.connect_clicked(move |_| search.search(SearchMatches::next));
+
+        let search = self.clone();
+        self.search_bar
+            .connect_search_mode_enabled_notify(move |bar| {
+                if !bar.is_search_mode() {
+                    search.clear();
+                }
+            });
+
+        let search = self.clone();
+        current_chat.connect_label_notify(move |_| {
+            *search.matches.borrow_mut() = SearchMatches::default();
+            if search.search_bar.is_search_mode() {
+                search.search(|_| ());
+            }
+        });
+    }
+
+    /// Searches the text of the entry in the conversation shown, then moves the selected match
+    /// and highlights the matches, scrolling to the selected one.
+    fn search(&self, move_match: impl Fn(&mut SearchMatches)) {
+        self.remove_highlights();
+        let messages = self.conversation_messages();
+        let texts = messages.iter().map(message_text).collect::<Vec<_>>();
+        let query = self.search_entry.text().to_string();
+        let mut matches = self.matches.borrow_mut();
+        matches.update(&texts, &query);
+        move_match(&mut matches);
+
+        let mut highlighted = self.highlighted.borrow_mut();
+        for position in matches.matches() {
+            let message = &messages[*position];
+            message.style_context().add_class("search_match");
+            highlighted.push(message.clone());
+        }
+        if let Some(current) = matches.current_message() {
+            messages[current].style_context().add_class("current_match");
+            self.scroll_to(&messages[current]);
+        }
+        match query.trim().is_empty() {
+            true => self.result_label.set_text(""),
+            false => self.result_label.set_text(&matches.summary()),
+        }
+    }
+
+    /// Removes the highlights and forgets the matches, when the bar is closed.
+    fn clear(&self) {
+        self.remove_highlights();
+        *self.matches.borrow_mut() = SearchMatches::default();
+        self.result_label.set_text("");
+    }
+
+    /// Removes the highlight of the messages that matched the last search.
+    fn remove_highlights(&self) {
+        for message in self.highlighted.borrow_mut().drain(..) {
+            message.style_context().remove_class("search_match");
+            message.style_context().remove_class("current_match");
+        }
+    }
+
+    /// Returns the message boxes of the conversation shown, in order, or none if no conversation
+    /// is shown.
+    fn conversation_messages(&self) -> Vec<gtk::Widget> {
+        let conversation = self
+            .stack_conversations
+            .visible_child()
+            .and_then(|stack| stack.downcast::<gtk::Stack>().ok())
+            .and_then(|stack| stack.visible_child())
+            .and_then(|conversation| conversation.downcast::<gtk::Box>().ok());
+        match conversation {
+            Some(conversation) => conversation
+                .children()
+                .into_iter()
+                .filter(|message| message.is::<gtk::Box>())
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Scrolls the conversation so the message is in the middle of the scrolled window.
+    fn scroll_to(&self, message: &gtk::Widget) {
+        let (_, y) = match message.translate_coordinates(&self.stack_conversations, 0, 0) {
+            Some(coordinates) => coordinates,
+            None => return,
+        };
+        let adjustment = self.scrolled_window.vadjustment();
+        let centered =
+            y as f64 - (adjustment.page_size() - message.allocated_height() as f64) / 2.0;
+        adjustment.set_value(
+            centered
+                .min(adjustment.upper() - adjustment.page_size())
+                .max(adjustment.lower()),
+        );
+    }
+}
+
+/// Returns the text shown by a message box, joining the texts of its labels, without the markup.
+fn message_text(message: &gtk::Widget) -> String {
+    if let Some(label) = message.downcast_ref::<gtk::Label>() {
+        return label.text().to_string();
+    }
+    match message.downcast_ref::<gtk::Container>() {
+        Some(container) => container
+            .children()
+            .iter()
+            .map(message_text)
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join(" "),
+        None => String::new(),
+    }
+}
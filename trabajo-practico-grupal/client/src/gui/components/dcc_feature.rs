@@ -3,14 +3,28 @@ use gtk::{
     glib::{self, clone},
     prelude::*,
 };
+use model::{crypto::random_bytes, responses::dcc::TransferState};
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::rc::Rc;
 use std::sync::mpsc::Sender;
 use std::sync::mpsc::SyncSender;
 use std::sync::Arc;
 use std::sync::RwLock;
+use std::time::Instant;
 
+use crate::dcc_commands::{
+    address::default_dcc_address,
+    batch::{batch_files, BatchFile},
+    downloads::is_already_received,
+    room::room_name,
+    typing::{typing_message, TypingNotifier, TYPING_TIMEOUT},
+};
 use crate::gui::{
-    components::file_message::add_file_message_box,
+    components::file_message::{
+        add_file_message_box, create_progress_bar_box, create_received_file_box,
+    },
+    preferences::{CollisionPolicy, Preferences, PREFERENCES_PATH},
     utils::{adjust_scroll_to_bottom, new_conversation},
 };
 
@@ -26,6 +40,9 @@ use crate::gui::{
 /// * `message_entry` - The entry to write the message to send to the DCC or normal chat.
 /// * `file_chooser_button` - The button to choose the file to send to the DCC chat.
 /// * `send_button` - The button to send the message or the file to the DCC or normal chat.
+/// * `communication_hash` - The channels to communicate with the DCC connections.
+/// * `batch` - The files chosen to send together to the DCC chat, with their paths relative to the folders chosen.
+/// * `typing_since` - When each client with a DCC conversation last said it is typing, while it is shown.
 pub struct DCCFeature {
     builder: gtk::Builder,
     tx: Sender<String>,
@@ -38,6 +55,8 @@ pub struct DCCFeature {
     file_chooser_button: gtk::FileChooserButton,
     send_button: gtk::Button,
     communication_hash: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
+    batch: Rc<RefCell<Vec<BatchFile>>>,
+    typing_since: Rc<RefCell<HashMap<String, Instant>>>,
 }
 
 impl DCCFeature {
@@ -74,6 +93,8 @@ impl DCCFeature {
             send_button,
             file_chooser_button,
             communication_hash,
+            batch: Rc::new(RefCell::new(vec![])),
+            typing_since: Rc::new(RefCell::new(HashMap::new())),
         }
     }
 
@@ -87,8 +108,42 @@ impl DCCFeature {
         self.active_chat_button(builder);
         self.build_dcc_confirmation_modal(builder, tx.clone());
         self.build_dcc_file_send_modal(builder, tx.clone());
+        self.build_dcc_batch_chooser(builder);
         self.build_dcc_confirmation_file_modal(builder, tx.clone());
-        self.build_dcc_ip_port_resume_modal(builder, tx);
+        self.build_dcc_ip_port_resume_modal(builder, tx.clone());
+        self.active_typing_notifier(tx);
+        self.active_dcc_lock_icon(builder);
+        self.fill_default_addresses(builder);
+    }
+
+    /// Fills the ip and port entries of the DCC modals with the address of the current client and a free
+    /// port every time they are shown, so the user only changes them when the other user can't reach it.
+    /// # Arguments
+    /// * `builder` - The builder that contains the widgets.
+    fn fill_default_addresses(&self, builder: &gtk::Builder) {
+        let modals = [
+            ("ip_port_dcc_modal", "ip_dcc_entry", "port_dcc_entry"),
+            (
+                "ip_port_dcc_modal_file",
+                "ip_dcc_entry_file",
+                "port_dcc_entry_file",
+            ),
+            (
+                "ip_port_dcc_modal_resume_file",
+                "ip_dcc_entry_file_resume",
+                "port_dcc_entry_file_resume",
+            ),
+        ];
+        for (modal, ip_entry, port_entry) in modals {
+            let modal = builder.object::<gtk::Window>(modal).unwrap();
+            let ip_entry = builder.object::<gtk::Entry>(ip_entry).unwrap();
+            let port_entry = builder.object::<gtk::Entry>(port_entry).unwrap();
+            modal.connect_show(clone!(@weak ip_entry, @weak port_entry => move |_| {
+                let (ip, port) = default_dcc_address();
+                ip_entry.set_text(&ip);
+                port_entry.set_text(&port);
+            }));
+        }
     }
 
     /// Actives the DCC button, giving it the functionality to open the DCC modal.
@@ -109,12 +164,25 @@ impl DCCFeature {
         let user_nick = builder.object::<gtk::Label>("user_nick").unwrap();
         let error_modal = builder.object::<gtk::Window>("error_modal").unwrap();
         let error_label = builder.object::<gtk::Label>("error_label").unwrap();
+        let secure_dcc_check = builder
+            .object::<gtk::CheckButton>("secure_dcc_check")
+            .unwrap();
+        let passive_dcc_check = builder
+            .object::<gtk::CheckButton>("passive_dcc_check")
+            .unwrap();
         let file_chooser_button = builder
             .object::<gtk::FileChooserButton>("file_chooser_button")
             .unwrap();
 
         ip_port_dcc_modal.connect_delete_event(move |_win, _| _win.hide_on_delete());
 
+        passive_dcc_check.connect_toggled(
+            clone!(@weak ip_dcc_entry, @weak port_dcc_entry => move |check| {
+                ip_dcc_entry.set_sensitive(!check.is_active());
+                port_dcc_entry.set_sensitive(!check.is_active());
+            }),
+        );
+
         dcc_button.connect_clicked(
             clone!(@weak builder, @weak error_modal, @weak error_label, @weak self.current_chat as current_chat, @weak user_nick, @weak self.close_dcc_button as dcc_close_button, @weak self.chat_button as chat_button, @weak self.message_entry as message_entry, @weak self.dcc_button as dcc_button, @weak loading_screens, @weak file_chooser_button, @weak receiver_label, @weak ip_port_dcc_modal, @weak dcc_stack, @weak self.stack_conversations as stack_conversations => move |_| {
                 let current_chat_text = current_chat.text();
@@ -150,42 +218,25 @@ impl DCCFeature {
         );
 
         connect_dcc_button.connect_clicked(
-            clone!(@weak builder, @weak port_dcc_entry, @weak self.stack_conversations as stack_conversations, @weak self.close_dcc_button as dcc_close_button, @weak ip_dcc_entry, @weak dcc_stack, @weak user_nick, @weak receiver_label => move |_| {
+            clone!(@weak builder, @weak port_dcc_entry, @weak self.stack_conversations as stack_conversations, @weak self.close_dcc_button as dcc_close_button, @weak ip_dcc_entry, @weak secure_dcc_check, @weak passive_dcc_check, @weak dcc_stack, @weak user_nick, @weak receiver_label => move |_| {
                 let ip = ip_dcc_entry.text();
                 let port = port_dcc_entry.text();
-                if ip.is_empty() || port.is_empty() {
+                let passive = passive_dcc_check.is_active();
+                if !passive && (ip.is_empty() || port.is_empty()) {
                     error_ip_port_dcc.set_text("Please fill out all fields")
                 }
                 else{
-                    let loading_stack = stack_conversations
-                        .child_by_name("Loadings")
-                        .unwrap()
-                        .downcast::<gtk::Stack>()
-                        .unwrap();
-                    let sender = receiver_label.text();
-                    if loading_stack.child_by_name(&sender).is_none() {
-                        println!("[DEBUG] create_loading_screen");
-                        println!("[DEBUG] {}", sender);
-                        let loading_screen = gtk::Box::new(gtk::Orientation::Vertical, 0);
-                        let label = gtk::Label::new(
-                            Some(&format!("Waiting for {} to accept connection", sender))
-                        );
-                        let loading_icon = gtk::Spinner::new();
-                        loading_icon.set_size_request(50, 50);
-                        loading_icon.start();
-                        loading_screen.add(&label);
-                        loading_screen.add(&loading_icon);
-                        loading_screen.show_all();
-                        loading_stack.add_named(&loading_screen, &sender);
-                    }
-                    loading_stack.set_visible_child_name(&sender);
-                    loading_stack.show_all();
-                    stack_conversations.set_visible_child_name("Loadings");
-                                let message = format!(":{} DCC CHAT {} {} {}", user_nick.text(), receiver_label.text(), ip, port);
+                    show_loading_screen(&stack_conversations, &receiver_label.text(), tx.clone());
+                                let command = if secure_dcc_check.is_active() { "SCHAT" } else { "CHAT" };
+                                // a passive offer has the port 0 and a token to match the answer of the user
+                                let address = if passive { format!("0 0 {}", u32::from_le_bytes(random_bytes::<4>())) } else { format!("{} {}", ip, port) };
+                                let message = format!(":{} DCC {} {} {}", user_nick.text(), command, receiver_label.text(), address);
                                 println!("{}", message);
                                     if tx.send(message).is_ok(){
                                             ip_dcc_entry.set_text("");
                                             port_dcc_entry.set_text("");
+                                            secure_dcc_check.set_active(false);
+                                            passive_dcc_check.set_active(false);
                                             ip_port_dcc_modal.hide();
                                     }
                             }
@@ -210,11 +261,22 @@ impl DCCFeature {
     pub fn active_dcc_close_button(&self, builder: &gtk::Builder, tx: Sender<String>) {
         let current_chat = builder.object::<gtk::Label>("current_chat").unwrap();
         let close_dcc_chat_button = builder.object::<gtk::Button>("close_dcc_button").unwrap();
+        let user_nick = builder.object::<gtk::Label>("user_nick").unwrap();
 
         let tx_clone = tx;
         close_dcc_chat_button.connect_clicked(
-            clone!(@weak self.stack_conversations as stack_conversations, @weak current_chat, @weak self.send_button as send_button, @weak self.message_entry as message_entry, @weak self.file_chooser_button as file_chooser_button, @weak self.chat_button as chat_button, @weak close_dcc_chat_button => move |_| {
-                if tx_clone.send(format!("DCC CLOSE {}", current_chat.text())).is_ok() && stack_conversations.visible_child_name().unwrap() == "DCC conversations" {
+            clone!(@weak self.stack_conversations as stack_conversations, @weak current_chat, @weak user_nick, @weak self.send_button as send_button, @weak self.message_entry as message_entry, @weak self.file_chooser_button as file_chooser_button, @weak self.chat_button as chat_button, @weak close_dcc_chat_button => move |_| {
+                // closing a room leaves it, without closing the connections with its members
+                let message = if is_room_conversation(&stack_conversations, &current_chat.text()) {
+                    Some(format!(":{} DCC ROOM {} LEAVE", user_nick.text(), current_chat.text()))
+                } else if is_relayed_conversation(&stack_conversations, &current_chat.text()) {
+                    // a relayed conversation has no connection to close
+                    None
+                } else {
+                    Some(format!("DCC CLOSE {}", current_chat.text()))
+                };
+                let sent = message.is_none_or(|message| tx_clone.send(message).is_ok());
+                if sent && stack_conversations.visible_child_name().unwrap() == "DCC conversations" {
                     let stack = stack_conversations
                     .child_by_name("DCC conversations")
                     .unwrap()
@@ -349,16 +411,43 @@ impl DCCFeature {
             .unwrap();
         let error_label = builder.object::<gtk::Label>("error_ip_port_dcc1").unwrap();
 
-        ip_port_dcc_modal_file.connect_delete_event(move |_win, _| _win.hide_on_delete());
+        let batch = self.batch.clone();
+        ip_port_dcc_modal_file.connect_delete_event(move |_win, _| {
+            batch.borrow_mut().clear();
+            _win.hide_on_delete()
+        });
 
+        let batch = self.batch.clone();
         send_dcc_file_button.connect_clicked(
             clone!(@weak builder, @weak ip_port_dcc_modal_file, @weak error_label, @weak self.file_chooser_button as file_chooser_button, @weak self.stack_conversations as stack_conversations, @weak self.current_chat as current_chat, @weak ip_dcc_entry_file, @weak port_dcc_entry_file => move |_| {
                 let ip = ip_dcc_entry_file.text().to_string();
                 let port = port_dcc_entry_file.text().to_string();
+                let receiver = current_chat.text().to_string();
+                let command = file_send_command(&stack_conversations, &receiver);
+                let user_nick = builder.object::<gtk::Label>("user_nick").unwrap().text();
 
-                if ip.is_empty() || port.is_empty() {
+                // the relayed files are sent through the server, so they don't need an address
+                if command != "RELAY" && (ip.is_empty() || port.is_empty()) {
                     error_label.set_text("Please fill all the fields");
                 }
+                else if !batch.borrow().is_empty() {
+                    let files = batch.take();
+                    for (position, file) in files.into_iter().enumerate() {
+                        // the files after the first one get a free port, since some of them are sent at the same time
+                        let file_port = if position == 0 { port.as_str() } else { "0" };
+                        let message = if command == "RELAY" {
+                            format!(":{} DCC RELAY {} OFFER {} {} {}", user_nick, receiver, file.path, file.size, file.name)
+                        } else {
+                            format!("DCC {} {} {} {} {} {} {}", command, receiver, file.path, ip, file_port, file.size, file.name)
+                        };
+                        if tx.send(message).is_err() {
+                            error_label.set_text("Error sending DCC SEND command");
+                            return;
+                        }
+                        add_sent_file_box(&builder, tx.clone(), &stack_conversations, &receiver, file.name, file.size);
+                    }
+                    ip_port_dcc_modal_file.close();
+                }
                 else if let Some(file) = file_chooser_button.file() {
                     let file_info = match file.query_info("standard::*", gio::FileQueryInfoFlags::NONE, gio::Cancellable::NONE) {
                         Ok(file_info) => file_info,
@@ -371,16 +460,16 @@ impl DCCFeature {
                         },
                         None => return,
                     };
-                    let receiver = current_chat.text();
                     let file_size = file_info.size();
                     let file_name = file_info.name().to_string_lossy().to_string();
-                    match tx.send(format!("DCC SEND {} {} {} {} {}", receiver, file_path, ip, port, file_size)) {
+                    let message = if command == "RELAY" {
+                        format!(":{} DCC RELAY {} OFFER {} {}", user_nick, receiver, file_path, file_size)
+                    } else {
+                        format!("DCC {} {} {} {} {} {}", command, receiver, file_path, ip, port, file_size)
+                    };
+                    match tx.send(message) {
                         Ok(_) => {
-                            let message_box: gtk::Box = add_file_message_box(&builder, tx.clone(), receiver.to_string(), file_name.clone(), file_size.to_string(), "command_sent", format!("{}-{}", file_name, receiver));
-                            let stack_visible = stack_conversations.visible_child().unwrap().downcast::<gtk::Stack>().unwrap();
-                            let chats_view = stack_visible.visible_child().unwrap().downcast::<gtk::Box>().unwrap();
-                            chats_view.add(&message_box);
-                            chats_view.show_all();
+                            add_sent_file_box(&builder, tx.clone(), &stack_conversations, &receiver, file_name, file_size as u64);
                             file_chooser_button.unselect_all();
                             ip_port_dcc_modal_file.close();
                         },
@@ -393,6 +482,67 @@ impl DCCFeature {
         );
     }
 
+    /// Builds the button that chooses several files, or folders, to send to the DCC chat. It is shown with the
+    /// button that chooses a single file. The files chosen and the files inside the folders chosen are kept in the
+    /// batch, and the modal that asks the ip and port is opened to send them, so the other user receives them
+    /// inside the same folders.
+    /// # Arguments
+    /// * `builder` - The builder that contains the widgets.
+    fn build_dcc_batch_chooser(&self, builder: &gtk::Builder) {
+        let choose_files_button = builder
+            .object::<gtk::Button>("choose_files_button")
+            .unwrap();
+        let main_window = builder.object::<gtk::Window>("main_window").unwrap();
+        let ip_port_dcc_modal_file = builder
+            .object::<gtk::Window>("ip_port_dcc_modal_file")
+            .unwrap();
+        let error_label = builder.object::<gtk::Label>("error_ip_port_dcc1").unwrap();
+
+        self.file_chooser_button
+            .bind_property("visible", &choose_files_button, "visible")
+            .sync_create()
+            .build();
+
+        let batch = self.batch.clone();
+        choose_files_button.connect_clicked(
+            clone!(@weak main_window, @weak ip_port_dcc_modal_file, @weak error_label => move |_| {
+                let dialog = gtk::FileChooserDialog::with_buttons(
+                    Some("Choose the files to send"),
+                    Some(&main_window),
+                    gtk::FileChooserAction::Open,
+                    &[("Cancel", gtk::ResponseType::Cancel), ("Choose", gtk::ResponseType::Accept)],
+                );
+                dialog.set_select_multiple(true);
+                let folders_check = gtk::CheckButton::with_label("Choose folders");
+                folders_check.connect_toggled(clone!(@weak dialog => move |check| {
+                    match check.is_active() {
+                        true => dialog.set_action(gtk::FileChooserAction::SelectFolder),
+                        false => dialog.set_action(gtk::FileChooserAction::Open),
+                    }
+                }));
+                dialog.set_extra_widget(&folders_check);
+
+                let batch = batch.clone();
+                dialog.connect_response(clone!(@weak ip_port_dcc_modal_file, @weak error_label => move |dialog, response| {
+                    if response == gtk::ResponseType::Accept {
+                        let (files, left_out): (Vec<BatchFile>, Vec<BatchFile>) = batch_files(&dialog.filenames())
+                            .into_iter()
+                            .partition(BatchFile::can_be_sent);
+                        if !files.is_empty() {
+                            *batch.borrow_mut() = files;
+                            ip_port_dcc_modal_file.show();
+                            if !left_out.is_empty() {
+                                error_label.set_text(&format!("{} files with spaces in their names are left out", left_out.len()));
+                            }
+                        }
+                    }
+                    dialog.close();
+                }));
+                dialog.show_all();
+            })
+        );
+    }
+
     /// Builds the dcc file transfer confirmation modal, giving it the functionality to accept or decline the file
     /// trfrom another client.
     fn build_dcc_confirmation_file_modal(&self, builder: &gtk::Builder, tx: Sender<String>) {
@@ -415,42 +565,69 @@ impl DCCFeature {
                 let sender = file_sender_label.text().to_string();
                 let file_name = file_name_label.text().to_string();
                 let file_size = file_size_label.text().to_string();
-                let message = format!("DCC ACCEPT {} {}", sender, file_name_label.text());
-                println!("Message to send to thread: {:?}", message);
-                let lock_communication_hash = match communication_hash.as_ref().read(){
-                    Ok(lock_communication_hash) => lock_communication_hash,
-                    Err(_) => return,
-                };
-                println!("[DEBUG] communication hash locked from gui");
-                let tx_sender = match lock_communication_hash.get(&sender) {
-                    Some(tx) => tx,
-                    None => return,
-                };
-                match tx_sender.send(message){
-                    Ok(_) => {
-                        println!("[DEBUG] accept message sent to thread");
-                        let message_box: gtk::Box = add_file_message_box(
-                            &builder,
-                            tx.clone(),
-                            sender.to_string(),
-                            file_name.clone(),
-                            file_size,
-                            "message_received",
-                            format!("{}-{}", file_name, sender)
-                        );
-                        let dcc_stack = stack_conversations
-                            .child_by_name("DCC conversations")
-                            .unwrap()
-                            .downcast::<gtk::Stack>()
-                            .unwrap();
-                        let chats_view = dcc_stack.child_by_name(&sender).unwrap().downcast::<gtk::Box>().unwrap();
-                        chats_view.add(&message_box);
-                        chats_view.show_all();
-                        dcc_confirmation_file_modal.close();
-                    },
-                    Err(e) => println!("Error sending accept: {e}"),
+                let tx = tx.clone();
+                let accept = clone!( @weak dcc_confirmation_file_modal => move |policy: CollisionPolicy| {
+                    let message = format!("DCC ACCEPT {} {} {}", sender, file_name, policy.as_str());
+                    println!("Message to send to thread: {:?}", message);
+                    let lock_communication_hash = match communication_hash.as_ref().read(){
+                        Ok(lock_communication_hash) => lock_communication_hash,
+                        Err(_) => return,
+                    };
+                    println!("[DEBUG] communication hash locked from gui");
+                    let tx_sender = match lock_communication_hash.get(&sender) {
+                        Some(tx) => tx,
+                        None => return,
+                    };
+                    match tx_sender.send(message){
+                        Ok(_) => {
+                            println!("[DEBUG] accept message sent to thread");
+                            let message_box: gtk::Box = add_file_message_box(
+                                &builder,
+                                tx.clone(),
+                                sender.to_string(),
+                                file_name.clone(),
+                                file_size.clone(),
+                                "message_received",
+                                format!("{}-{}", file_name, sender)
+                            );
+                            let dcc_stack = stack_conversations
+                                .child_by_name("DCC conversations")
+                                .unwrap()
+                                .downcast::<gtk::Stack>()
+                                .unwrap();
+                            let chats_view = dcc_stack.child_by_name(&sender).unwrap().downcast::<gtk::Box>().unwrap();
+                            chats_view.add(&message_box);
+                            chats_view.show_all();
+                            dcc_confirmation_file_modal.close();
+                        },
+                        Err(e) => println!("Error sending accept: {e}"),
+                    }
+                    drop(lock_communication_hash);
+                });
+
+                // the user chooses what is done with a file already received before accepting it
+                let preferences = Preferences::load(PREFERENCES_PATH);
+                if preferences.collision_policy == CollisionPolicy::Ask && is_already_received(&preferences.download_directory, &file_name_label.text()) {
+                    let dialog = gtk::MessageDialog::new(
+                        Some(&dcc_confirmation_file_modal),
+                        gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT,
+                        gtk::MessageType::Question,
+                        gtk::ButtonsType::None,
+                        &format!("{} was already received. Do you want to save it with another name or overwrite it?", file_name_label.text()),
+                    );
+                    dialog.add_buttons(&[("RENAME", gtk::ResponseType::Other(0)), ("OVERWRITE", gtk::ResponseType::Other(1))]);
+                    dialog.connect_response(move |dialog, response| {
+                        match response {
+                            gtk::ResponseType::Other(0) => accept(CollisionPolicy::Rename),
+                            gtk::ResponseType::Other(1) => accept(CollisionPolicy::Overwrite),
+                            _ => (),
+                        }
+                        dialog.close();
+                    });
+                    dialog.show_all();
+                } else {
+                    accept(preferences.collision_policy);
                 }
-                drop(lock_communication_hash);
             })
         );
         decline_file_transfer_button.connect_clicked(
@@ -487,12 +664,10 @@ impl DCCFeature {
             .downcast::<gtk::Stack>()
             .unwrap();
         println!("Removing {sender} from {stack_name}");
-        let deleted_box = stack
-            .child_by_name(&sender)
-            .unwrap()
-            .downcast::<gtk::Box>()
-            .unwrap();
-        stack.remove(&deleted_box);
+        // the box may be gone already, like a loading screen cancelled by the user
+        if let Some(deleted_box) = stack.child_by_name(&sender) {
+            stack.remove(&deleted_box);
+        }
     }
 
     /// Builds the resume dcc file transfer modal, giving it the functionality to resume the file send from another client.
@@ -566,13 +741,7 @@ impl DCCFeature {
             println!("el nombre del widget es {}", message.widget_name());
             if message.widget_name() == format!("{}-{}-box", file_name, sender) {
                 let box_message = message.downcast::<gtk::Box>().unwrap();
-                let css_provider = gtk::CssProvider::new();
-                css_provider
-                    .load_from_path("client/src/gui/style.scss")
-                    .unwrap();
-                let style_context = box_message.style_context();
-                style_context.add_provider(&css_provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
-                style_context.add_class("error_message");
+                box_message.style_context().add_class("error_message");
                 let message_children = box_message.children();
                 for message_child in message_children {
                     if message_child.widget_name()
@@ -586,6 +755,70 @@ impl DCCFeature {
         }
     }
 
+    /// Set the corresponding message box of a received file to an error message when its checksum doesn't
+    /// match the one sent by the sender, removing the link to the file. Adds a REQUEST AGAIN button that
+    /// shows the progress bar again and opens the resume modal, to receive the file again from the start.
+    /// # Arguments
+    /// * `sender` - The name of the client that sent the file.
+    /// * `file_name` - The name of the file that is corrupt.
+    pub fn set_transfer_failed_message(&self, sender: String, file_name: String) {
+        let dcc_stack_conversations = self
+            .stack_conversations
+            .child_by_name("DCC conversations")
+            .unwrap()
+            .downcast::<gtk::Stack>()
+            .unwrap();
+        let conversation_dcc = match dcc_stack_conversations.child_by_name(&sender) {
+            Some(conversation) => conversation.downcast::<gtk::Box>().unwrap(),
+            None => return,
+        };
+        let id = format!("{file_name}-{sender}");
+        for message in conversation_dcc.children() {
+            if message.widget_name() != format!("{id}-box-done") {
+                continue;
+            }
+            let box_message = message.downcast::<gtk::Box>().unwrap();
+            // only the name and the size of the file are kept
+            for message_child in box_message.children().into_iter().skip(1) {
+                box_message.remove(&message_child);
+            }
+            box_message.style_context().add_class("error_message");
+
+            let failed_box = gtk::Box::new(gtk::Orientation::Horizontal, 10);
+            failed_box.set_halign(gtk::Align::Center);
+            let failed_label = gtk::Label::new(Some("The file is corrupt"));
+            let request_again_button = gtk::Button::with_label("REQUEST AGAIN");
+            failed_box.pack_start(&failed_label, true, true, 0);
+            failed_box.pack_start(&request_again_button, false, false, 0);
+            box_message.pack_start(&failed_box, true, true, 5);
+            box_message.show_all();
+
+            let builder = self.builder.clone();
+            let tx = self.tx.clone();
+            let sender = sender.clone();
+            let file_name = file_name.clone();
+            let id = id.clone();
+            request_again_button.connect_clicked(
+                clone!(@weak box_message, @weak failed_box => move |_| {
+                    box_message.remove(&failed_box);
+                    box_message.style_context().remove_class("error_message");
+                    box_message.set_widget_name(&format!("{id}-box"));
+                    let progress_bar_box = create_progress_bar_box(id.clone(), tx.clone(), file_name.clone(), sender.clone(), &builder);
+                    box_message.pack_start(&progress_bar_box, true, true, 5);
+                    box_message.show_all();
+                    let resume_button = progress_bar_box
+                        .children()
+                        .into_iter()
+                        .find(|button| button.widget_name() == format!("{id}-resume-button"))
+                        .and_then(|button| button.downcast::<gtk::Button>().ok());
+                    if let Some(resume_button) = resume_button {
+                        resume_button.clicked();
+                    }
+                }),
+            );
+        }
+    }
+
     /// Creates the new DCC conversation box to start chatting to the user and then saves
     ///  the box in the DCC conversations stack. Sets the file chooser button, the close dcc
     /// button and the chat button to visible and sets the dcc button to not visible.
@@ -614,12 +847,237 @@ impl DCCFeature {
         //adjust_scroll_to_bottom(&self.scrolled_window);
     }
 
+    /// Marks the DCC conversation with the sender as encrypted, once the keys were exchanged, so the lock
+    /// icon is shown while it is open and the files are sent encrypted.
+    /// # Arguments
+    /// * `sender`: The name of the sender.
+    pub fn set_secure_conversation(&self, sender: String) {
+        let dcc_stack = self
+            .stack_conversations
+            .child_by_name("DCC conversations")
+            .unwrap()
+            .downcast::<gtk::Stack>()
+            .unwrap();
+        if let Some(conversation_dcc) = dcc_stack.child_by_name(&sender) {
+            conversation_dcc
+                .style_context()
+                .add_class("secure_conversation");
+        }
+        let lock_icon = self.builder.object::<gtk::Image>("dcc_lock_icon").unwrap();
+        update_lock_icon(&self.stack_conversations, &dcc_stack, &lock_icon);
+    }
+
+    /// Creates the conversation to send files to the sender through the server, when a DCC connection with the
+    /// sender can't be established. It is opened with the DCC button, and the messages written in it are sent as
+    /// private messages. Nothing is done if there is already a DCC conversation with the sender.
+    /// # Arguments
+    /// * `sender`: The name of the sender. It is used as key to save the conversation box in the stack.
+    pub fn create_relayed_box(&self, sender: String) {
+        let dcc_stack = self
+            .stack_conversations
+            .child_by_name("DCC conversations")
+            .unwrap()
+            .downcast::<gtk::Stack>()
+            .unwrap();
+        if dcc_stack.child_by_name(&sender).is_some() {
+            return;
+        }
+        let box_conversation = new_conversation(&sender);
+        box_conversation
+            .style_context()
+            .add_class("relayed_conversation");
+        let relayed_label = gtk::Label::new(Some("Files are sent through the server."));
+        box_conversation.add(&relayed_label);
+        box_conversation.show_all();
+        dcc_stack.add_named(&box_conversation, &sender);
+    }
+
+    /// Shows who is in a room, creating its conversation and opening it if the user was not in the room yet.
+    /// The room conversation has no files, since they are sent through the connection with each client.
+    /// # Arguments
+    /// * `room`: The name of the room. It is used as key to save the room conversation box in the stack.
+    /// * `host`: The name of the client who hosts the room.
+    /// * `members`: The names of the other clients in the room.
+    pub fn set_room_members(&self, room: String, host: String, members: Vec<String>) {
+        let dcc_stack = self
+            .stack_conversations
+            .child_by_name("DCC conversations")
+            .unwrap()
+            .downcast::<gtk::Stack>()
+            .unwrap();
+        let conversation_room = match dcc_stack.child_by_name(&room) {
+            Some(conversation_room) => conversation_room.downcast::<gtk::Box>().unwrap(),
+            None => {
+                let conversation_room = new_conversation(&room);
+                conversation_room.style_context().add_class("dcc_room");
+                let members_label = gtk::Label::new(None);
+                members_label.set_widget_name("room-members");
+                members_label.set_line_wrap(true);
+                conversation_room.add(&members_label);
+                dcc_stack.add_named(&conversation_room, &room);
+                self.create_room_screen(&dcc_stack, &room);
+                conversation_room
+            }
+        };
+
+        let names = std::iter::once(format!("{host} (host)"))
+            .chain(members)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let members_label = conversation_room
+            .children()
+            .into_iter()
+            .find(|child| child.widget_name().as_str() == "room-members")
+            .and_then(|child| child.downcast::<gtk::Label>().ok());
+        if let Some(members_label) = members_label {
+            members_label.set_text(&format!("Members: {names}"));
+        }
+        conversation_room.show_all();
+    }
+
+    /// Opens the conversation of a room the user just joined.
+    fn create_room_screen(&self, dcc_stack: &gtk::Stack, room: &str) {
+        self.send_button.set_sensitive(true);
+        self.message_entry.set_sensitive(true);
+        self.current_chat.set_text(room);
+        dcc_stack.set_visible_child_name(room);
+        self.chat_button.set_visible(false);
+        self.file_chooser_button.set_visible(false);
+        self.dcc_button.set_visible(false);
+        self.close_dcc_button.set_visible(true);
+        self.stack_conversations
+            .set_visible_child_name("DCC conversations");
+    }
+
+    /// Removes the conversation of a room the user is no longer in.
+    /// Returns true if the room was open.
+    /// # Arguments
+    /// * `room`: The name of the room.
+    pub fn remove_room(&self, room: String) -> bool {
+        let open = self.current_chat.text() == room
+            && self.stack_conversations.visible_child_name().as_deref()
+                == Some("DCC conversations");
+        self.remove_stack_box(room, "DCC conversations");
+        open
+    }
+
+    /// Tells the client of the DCC conversation shown when the user starts and stops writing a message, so it
+    /// shows that the user is typing. Nothing is sent in rooms, relayed or normal conversations.
+    /// # Arguments
+    /// * `tx` - The channel to send messages to the client.
+    fn active_typing_notifier(&self, tx: Sender<String>) {
+        let notifiers: Rc<RefCell<HashMap<String, TypingNotifier>>> =
+            Rc::new(RefCell::new(HashMap::new()));
+        self.message_entry.connect_changed(
+            clone!(@weak self.stack_conversations as stack_conversations, @weak self.current_chat as current_chat => move |message_entry| {
+                let receiver = current_chat.text().to_string();
+                let dcc_conversation = stack_conversations.visible_child_name().as_deref()
+                    == Some("DCC conversations")
+                    && stack_conversations
+                        .child_by_name("DCC conversations")
+                        .and_then(|dcc_stack| dcc_stack.downcast::<gtk::Stack>().ok())
+                        .is_some_and(|dcc_stack| dcc_stack.child_by_name(&receiver).is_some());
+                if !dcc_conversation
+                    || is_room_conversation(&stack_conversations, &receiver)
+                    || is_relayed_conversation(&stack_conversations, &receiver)
+                {
+                    return;
+                }
+                let typing = !message_entry.text().is_empty();
+                let state = notifiers
+                    .borrow_mut()
+                    .entry(receiver.clone())
+                    .or_default()
+                    .update(typing, Instant::now());
+                if let Some(typing) = state {
+                    if tx.send(typing_message(&receiver, typing)).is_ok() {}
+                }
+            }),
+        );
+    }
+
+    /// Shows or hides that the sender is typing at the end of its DCC conversation. If the sender doesn't say it
+    /// stopped, it is hidden when it doesn't say it is still typing for some seconds.
+    /// # Arguments
+    /// * `sender`: The name of the sender.
+    /// * `typing`: If the sender is typing.
+    pub fn set_typing(&self, sender: String, typing: bool) {
+        let dcc_stack = self
+            .stack_conversations
+            .child_by_name("DCC conversations")
+            .unwrap()
+            .downcast::<gtk::Stack>()
+            .unwrap();
+        let conversation_dcc = match dcc_stack.child_by_name(&sender) {
+            Some(conversation) => conversation.downcast::<gtk::Box>().unwrap(),
+            None => return,
+        };
+        remove_typing_label(&conversation_dcc);
+        if !typing {
+            self.typing_since.borrow_mut().remove(&sender);
+            return;
+        }
+
+        let typing_label = gtk::Label::new(Some(&format!("{sender} is typing...")));
+        typing_label.set_widget_name("typing-indicator");
+        typing_label.set_halign(gtk::Align::Start);
+        typing_label.style_context().add_class("typing_indicator");
+        conversation_dcc.add(&typing_label);
+        conversation_dcc.show_all();
+        self.typing_since
+            .borrow_mut()
+            .insert(sender.clone(), Instant::now());
+
+        let typing_since = self.typing_since.clone();
+        glib::timeout_add_local(
+            TYPING_TIMEOUT,
+            clone!(@weak conversation_dcc => @default-return glib::Continue(false), move || {
+                let expired = typing_since
+                    .borrow()
+                    .get(&sender)
+                    .is_some_and(|since| since.elapsed() >= TYPING_TIMEOUT);
+                if expired {
+                    typing_since.borrow_mut().remove(&sender);
+                    remove_typing_label(&conversation_dcc);
+                }
+                glib::Continue(false)
+            }),
+        );
+    }
+
+    /// Shows the lock icon in the message bar while an encrypted DCC conversation is open.
+    /// # Arguments
+    /// * `builder` - The builder that contains the widgets.
+    fn active_dcc_lock_icon(&self, builder: &gtk::Builder) {
+        let lock_icon = builder.object::<gtk::Image>("dcc_lock_icon").unwrap();
+        let dcc_stack = builder.object::<gtk::Stack>("dcc_conversations").unwrap();
+
+        self.stack_conversations.connect_visible_child_notify(
+            clone!(@weak dcc_stack, @weak lock_icon => move |stack_conversations| {
+                update_lock_icon(stack_conversations, &dcc_stack, &lock_icon);
+            }),
+        );
+        dcc_stack.connect_visible_child_notify(
+            clone!(@weak self.stack_conversations as stack_conversations, @weak lock_icon => move |dcc_stack| {
+                update_lock_icon(&stack_conversations, dcc_stack, &lock_icon);
+            }),
+        );
+    }
+
     /// Updates the progress bar in the file message of the file that is being sent.
+    /// When a received file is completed, its path and the button that opens its folder are shown.
     /// # Arguments
     /// * `sender`: The name of the sender.
     /// * `file_name`: The name of the file.
     /// * `progress`: The progress of the file.
-    pub fn update_progress_bar(&self, sender: String, file_name: String, progress: f64) {
+    /// * `throughput`: The throughput and the time left of the transfer, shown after the percentage.
+    pub fn update_progress_bar(
+        &self,
+        sender: String,
+        file_name: String,
+        progress: f64,
+        throughput: &str,
+    ) {
         let dcc_stack_conversations = self
             .stack_conversations
             .child_by_name("DCC conversations")
@@ -648,8 +1106,12 @@ impl DCCFeature {
                                 let progress_bar = p.downcast::<gtk::ProgressBar>().unwrap();
 
                                 progress_bar.set_fraction(progress);
-                                progress_bar
-                                    .set_text(Some(&format!("{}%", (progress * 100.0) as i32)));
+                                let percentage = format!("{}%", (progress * 100.0) as i32);
+                                match throughput.is_empty() {
+                                    true => progress_bar.set_text(Some(&percentage)),
+                                    false => progress_bar
+                                        .set_text(Some(&format!("{percentage} - {throughput}"))),
+                                }
 
                                 if progress == 1.0 {
                                     box_message.remove(&progress_bar_box);
@@ -664,33 +1126,56 @@ impl DCCFeature {
         }
     }
 
-    /// Creates the loading screen that is shown when waiting to the user to accept the DCC connection.
-    /// Then saves the screen in the Loadings stack
+    /// Shows the state of a file sent in the queue of the DCC connection in its progress bar. A file
+    /// waiting its turn shows it is queued, and it shows its progress when it starts to be sent.
     /// # Arguments
-    /// * `sender`: The name of the sender. It is used as key to save the new loading box in the stack.
-    pub fn create_loading_screen(&self, sender: String) {
-        let loading_stack = self
+    /// * `sender`: The name of the client the file is sent to.
+    /// * `file_name`: The name of the file.
+    /// * `state`: The state of the file in the queue.
+    pub fn set_transfer_queue_state(
+        &self,
+        sender: String,
+        file_name: String,
+        state: TransferState,
+    ) {
+        let text = match state {
+            TransferState::Pending => "Queued",
+            TransferState::Active => "0%",
+            TransferState::Done => return,
+        };
+        let dcc_stack_conversations = self
             .stack_conversations
-            .child_by_name("Loadings")
+            .child_by_name("DCC conversations")
             .unwrap()
             .downcast::<gtk::Stack>()
             .unwrap();
-        if loading_stack.child_by_name(&sender).is_none() {
-            println!("[DEBUG] create_loading_screen");
-            let loading_screen = gtk::Box::new(gtk::Orientation::Vertical, 0);
-            let label =
-                gtk::Label::new(Some(&format!("Waiting for {sender} to accept connection")));
-            let loading_icon = gtk::Spinner::new();
-            loading_icon.set_size_request(50, 50);
-            loading_icon.start();
-            loading_screen.add(&label);
-            loading_screen.add(&loading_icon);
-            loading_screen.show_all();
-            loading_stack.add_named(&loading_screen, &sender);
+        let conversation_dcc = match dcc_stack_conversations.child_by_name(&sender) {
+            Some(conversation) => conversation.downcast::<gtk::Box>().unwrap(),
+            None => return,
+        };
+        let progress_bar = conversation_dcc
+            .children()
+            .into_iter()
+            .filter(|message| message.widget_name() == format!("{file_name}-{sender}-box"))
+            .filter_map(|message| message.downcast::<gtk::Box>().ok())
+            .flat_map(|message| message.children())
+            .filter_map(|child| child.downcast::<gtk::Box>().ok())
+            .flat_map(|progress_bar_box| progress_bar_box.children())
+            .find(|child| child.widget_name() == format!("{file_name}-{sender}"))
+            .and_then(|progress_bar| progress_bar.downcast::<gtk::ProgressBar>().ok());
+        if let Some(progress_bar) = progress_bar {
+            if progress_bar.fraction() == 0.0 {
+                progress_bar.set_text(Some(text));
+            }
         }
-        loading_stack.set_visible_child_name(&sender);
-        loading_stack.show_all();
-        self.stack_conversations.set_visible_child_name("Loadings");
+    }
+
+    /// Creates the loading screen that is shown when waiting to the user to accept the DCC connection.
+    /// Then saves the screen in the Loadings stack
+    /// # Arguments
+    /// * `sender`: The name of the sender. It is used as key to save the new loading box in the stack.
+    pub fn create_loading_screen(&self, sender: String) {
+        show_loading_screen(&self.stack_conversations, &sender, self.tx.clone());
     }
 
     /// Removes the corresponding button from the file message being sent, according visible id and invisible id.
@@ -759,6 +1244,89 @@ impl DCCFeature {
         );
     }
 
+    /// Adds the message of a transfer saved before the client was restarted to the DCC conversation with the
+    /// sender, with the progress it had and paused, so the user can resume it. Nothing is added if the
+    /// conversation already shows the transfer.
+    /// # Arguments
+    /// * `sender`: The name of the other user of the transfer.
+    /// * `file_name`: The name of the file.
+    /// * `file_size`: The size of the file.
+    /// * `progress`: The part of the file transferred, from 0 to 1.
+    /// * `received`: True if the file is received from the other user.
+    pub fn add_restored_transfer(
+        &self,
+        sender: String,
+        file_name: String,
+        file_size: f64,
+        progress: f64,
+        received: bool,
+    ) {
+        let dcc_stack_conversations = self
+            .stack_conversations
+            .child_by_name("DCC conversations")
+            .unwrap()
+            .downcast::<gtk::Stack>()
+            .unwrap();
+        let conversation_dcc = match dcc_stack_conversations.child_by_name(&sender) {
+            Some(conversation) => conversation.downcast::<gtk::Box>().unwrap(),
+            None => return,
+        };
+        let id = format!("{file_name}-{sender}");
+        if conversation_dcc
+            .children()
+            .iter()
+            .any(|message| message.widget_name() == format!("{id}-box"))
+        {
+            return;
+        }
+
+        let style = if received {
+            "message_received"
+        } else {
+            "command_sent"
+        };
+        let message_box = add_file_message_box(
+            &self.builder,
+            self.tx.clone(),
+            sender.clone(),
+            file_name.clone(),
+            (file_size as u64).to_string(),
+            style,
+            id,
+        );
+        conversation_dcc.add(&message_box);
+        conversation_dcc.show_all();
+        self.update_progress_bar(sender.clone(), file_name.clone(), progress, "");
+        self.set_transfer_paused_message(sender, file_name);
+    }
+
+    /// Adds the link to the file received and the button to open its folder to the message of the file,
+    /// once it is complete and saved.
+    /// # Arguments
+    /// * `sender`: The name of the sender.
+    /// * `file_name`: The name of the file sent by the sender.
+    /// * `path`: The path where the file was saved, which has another name if it was renamed.
+    pub fn set_file_received_message(&self, sender: String, file_name: String, path: String) {
+        let dcc_stack_conversations = self
+            .stack_conversations
+            .child_by_name("DCC conversations")
+            .unwrap()
+            .downcast::<gtk::Stack>()
+            .unwrap();
+        let conversation_dcc = match dcc_stack_conversations.child_by_name(&sender) {
+            Some(conversation) => conversation.downcast::<gtk::Box>().unwrap(),
+            None => return,
+        };
+        for child in conversation_dcc.children() {
+            if child.widget_name() == format!("{file_name}-{sender}-box-done") {
+                let box_message = child.downcast::<gtk::Box>().unwrap();
+                let received_file_box = create_received_file_box(&path);
+                box_message.pack_start(&received_file_box, true, true, 5);
+                box_message.show_all();
+            }
+        }
+    }
+
     /// Sets the message of the file being sent as resumed.
     /// # Arguments
     /// * `sender`: The name of the sender.
@@ -772,3 +1340,176 @@ impl DCCFeature {
         );
     }
 }
+
+/// Shows the loading screen that is shown when waiting for the sender to accept the DCC connection, creating it
+/// in the Loadings stack if there isn't one for the sender.
+/// The screen has a button to cancel the request, which closes the connection and shows no conversation.
+/// # Arguments
+/// * `stack_conversations` - The stack that contains the DCC conversation, loadings and the normal conversations.
+/// * `sender` - The name of the sender. It is used as key to save the new loading box in the stack.
+/// * `tx` - The channel to send messages to the client.
+fn show_loading_screen(stack_conversations: &gtk::Stack, sender: &str, tx: Sender<String>) {
+    let loading_stack = stack_conversations
+        .child_by_name("Loadings")
+        .unwrap()
+        .downcast::<gtk::Stack>()
+        .unwrap();
+    if loading_stack.child_by_name(sender).is_none() {
+        println!("[DEBUG] create_loading_screen");
+        let loading_screen = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        let label = gtk::Label::new(Some(&format!("Waiting for {sender} to accept connection")));
+        let loading_icon = gtk::Spinner::new();
+        loading_icon.set_size_request(50, 50);
+        loading_icon.start();
+        let cancel_button = gtk::Button::with_label("CANCEL");
+        cancel_button.set_halign(gtk::Align::Center);
+        let requested_client = sender.to_owned();
+        cancel_button.connect_clicked(
+            clone!(@weak stack_conversations, @weak loading_stack, @weak loading_screen => move |_| {
+                if tx.send(format!("DCC CLOSE {}", requested_client)).is_ok() {}
+                loading_stack.remove(&loading_screen);
+                stack_conversations.set_visible_child_name("No conversation");
+            }),
+        );
+        loading_screen.add(&label);
+        loading_screen.add(&loading_icon);
+        loading_screen.add(&cancel_button);
+        loading_screen.show_all();
+        loading_stack.add_named(&loading_screen, sender);
+    }
+    loading_stack.set_visible_child_name(sender);
+    loading_stack.show_all();
+    stack_conversations.set_visible_child_name("Loadings");
+}
+
+/// Shows the lock icon if the conversation shown is an encrypted DCC conversation, and hides it otherwise.
+fn update_lock_icon(
+    stack_conversations: &gtk::Stack,
+    dcc_stack: &gtk::Stack,
+    lock_icon: &gtk::Image,
+) {
+    let secure = stack_conversations.visible_child_name().as_deref() == Some("DCC conversations")
+        && dcc_stack.visible_child().map_or(false, |conversation| {
+            conversation
+                .style_context()
+                .has_class("secure_conversation")
+        });
+    lock_icon.set_visible(secure);
+}
+
+/// Removes the label that shows that the client of the DCC conversation is typing, if it is shown.
+fn remove_typing_label(conversation_dcc: &gtk::Box) {
+    for child in conversation_dcc.children() {
+        if child.widget_name().as_str() == "typing-indicator" {
+            conversation_dcc.remove(&child);
+        }
+    }
+}
+
+/// Returns if the DCC conversation with the receiver is a room.
+fn is_room_conversation(stack_conversations: &gtk::Stack, receiver: &str) -> bool {
+    stack_conversations
+        .child_by_name("DCC conversations")
+        .and_then(|dcc_stack| dcc_stack.downcast::<gtk::Stack>().ok())
+        .and_then(|dcc_stack| dcc_stack.child_by_name(receiver))
+        .is_some_and(|conversation| conversation.style_context().has_class("dcc_room"))
+}
+
+/// Returns if the DCC conversation with the receiver sends its files through the server.
+fn is_relayed_conversation(stack_conversations: &gtk::Stack, receiver: &str) -> bool {
+    stack_conversations
+        .child_by_name("DCC conversations")
+        .and_then(|dcc_stack| dcc_stack.downcast::<gtk::Stack>().ok())
+        .and_then(|dcc_stack| dcc_stack.child_by_name(receiver))
+        .is_some_and(|conversation| {
+            conversation
+                .style_context()
+                .has_class("relayed_conversation")
+        })
+}
+
+/// Returns the DCC command that sends the message written in the DCC conversation with the receiver.
+/// In a room, `/invite <client>` invites a client with a DCC conversation to the room, and the other messages
+/// are chat lines of the room. In a conversation with a client, `/room <name>` invites the client to a room
+/// hosted by the user, which is created if it doesn't exist. In a relayed conversation, the messages are private
+/// messages sent through the server.
+/// # Arguments
+/// * `stack_conversations` - The stack that contains the DCC conversation, loadings and the normal conversations.
+/// * `nickname` - The nickname of the user.
+/// * `receiver` - The client or room of the DCC conversation.
+/// * `message` - The message written by the user.
+pub fn dcc_chat_command(
+    stack_conversations: &gtk::Stack,
+    nickname: &str,
+    receiver: &str,
+    message: &str,
+) -> String {
+    if is_relayed_conversation(stack_conversations, receiver) {
+        format!("PRIVMSG {receiver} {message}")
+    } else if is_room_conversation(stack_conversations, receiver) {
+        match message.strip_prefix("/invite ") {
+            Some(client) => format!(":{nickname} DCC ROOM {receiver} INVITE {}", client.trim()),
+            None => format!(":{nickname} DCC ROOM {receiver} MSG {message}"),
+        }
+    } else {
+        match message.strip_prefix("/room ").and_then(room_name) {
+            Some(room) => format!(":{nickname} DCC ROOM {room} INVITE {receiver}"),
+            None => format!("DCC CHAT {receiver} {message}"),
+        }
+    }
+}
+
+/// Returns the DCC command that sends a file to the receiver, RELAY if the DCC conversation with the receiver sends
+/// its files through the server, SSEND if it is secure or SEND if it is not.
+fn file_send_command(stack_conversations: &gtk::Stack, receiver: &str) -> &'static str {
+    if is_relayed_conversation(stack_conversations, receiver) {
+        return "RELAY";
+    }
+    let secure = stack_conversations
+        .child_by_name("DCC conversations")
+        .and_then(|dcc_stack| dcc_stack.downcast::<gtk::Stack>().ok())
+        .and_then(|dcc_stack| dcc_stack.child_by_name(receiver))
+        .is_some_and(|conversation| {
+            conversation
+                .style_context()
+                .has_class("secure_conversation")
+        });
+    if secure {
+        "SSEND"
+    } else {
+        "SEND"
+    }
+}
+
+/// Adds the message of a file sent to the receiver to the conversation shown.
+fn add_sent_file_box(
+    builder: &gtk::Builder,
+    tx: Sender<String>,
+    stack_conversations: &gtk::Stack,
+    receiver: &str,
+    file_name: String,
+    file_size: u64,
+) {
+    let id = format!("{}-{}", file_name, receiver);
+    let message_box: gtk::Box = add_file_message_box(
+        builder,
+        tx,
+        receiver.to_string(),
+        file_name,
+        file_size.to_string(),
+        "command_sent",
+        id,
+    );
+    let stack_visible = stack_conversations
+        .visible_child()
+        .unwrap()
+        .downcast::<gtk::Stack>()
+        .unwrap();
+    let chats_view = stack_visible
+        .visible_child()
+        .unwrap()
+        .downcast::<gtk::Box>()
+        .unwrap();
+    chats_view.add(&message_box);
+    chats_view.show_all();
+}
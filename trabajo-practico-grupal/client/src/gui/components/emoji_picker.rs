@@ -0,0 +1,86 @@
+use gtk::prelude::*;
+use model::formatting::FormatStyle;
+
+/// The emojis that can be inserted from the emoji picker.
+const EMOJIS: [&str; 24] = [
+    "😀", "😂", "😊", "😍", "😎", "😉", "😢", "😡", "🤔", "😴", "🙈", "🙏", "👍", "👎", "👏", "🎉",
+    "❤️", "🔥", "✨", "💯", "✅", "❌", "☕", "🍕",
+];
+
+/// The emoji picker next to the send button, a popover with the emojis that are inserted in the
+/// message entry and the buttons that format the text of the message.
+/// # Fields
+/// * `emoji_button`: The button that opens the popover.
+/// * `message_entry`: The entry of the message.
+pub struct EmojiPicker {
+    emoji_button: gtk::MenuButton,
+    message_entry: gtk::Entry,
+}
+
+impl EmojiPicker {
+    /// Creates a new `EmojiPicker` struct.
+    /// # Arguments
+    /// * `builder`: The builder of the glade file that builds the application.
+    pub fn new(builder: &gtk::Builder) -> Self {
+        EmojiPicker {
+            emoji_button: builder.object("emoji_button").unwrap(),
+            message_entry: builder.object("chat_input").unwrap(),
+        }
+    }
+
+    /// Fills the popover with the emojis and builds the BOLD, ITALIC and UNDERLINE buttons.
+    /// The picker can only be used while the message entry can be edited.
+    /// # Arguments
+    /// * `builder`: The builder of the glade file that builds the application.
+    pub fn build(&self, builder: &gtk::Builder) {
+        let emoji_flowbox = builder.object::<gtk::FlowBox>("emoji_flowbox").unwrap();
+        for emoji in EMOJIS {
+            let emoji_button = gtk::Button::with_label(emoji);
+            emoji_button.set_relief(gtk::ReliefStyle::None);
+            let message_entry = self.message_entry.clone();
+            emoji_button.connect_clicked(move |_| insert_text(&message_entry, emoji));
+            emoji_flowbox.add(&emoji_button);
+        }
+        emoji_flowbox.show_all();
+
+        for (id, style) in [
+            ("format_bold_button", FormatStyle::Bold),
+            ("format_italic_button", FormatStyle::Italic),
+            ("format_underline_button", FormatStyle::Underline),
+        ] {
+            let format_button = builder.object::<gtk::Button>(id).unwrap();
+            let message_entry = self.message_entry.clone();
+            format_button.connect_clicked(move |_| format_selection(&message_entry, style));
+        }
+
+        let emoji_button = self.emoji_button.clone();
+        emoji_button.set_sensitive(self.message_entry.is_sensitive());
+        self.message_entry.connect_sensitive_notify(move |entry| {
+            emoji_button.set_sensitive(entry.is_sensitive())
+        });
+    }
+}
+
+/// Inserts the text at the cursor of the entry, leaving the cursor after it.
+fn insert_text(entry: &gtk::Entry, text: &str) {
+    let mut position = entry.position();
+    entry.insert_text(text, &mut position);
+    entry.set_position(position);
+    entry.grab_focus_without_selecting();
+}
+
+/// Formats the text selected in the entry. Without a selection, the control code of the format
+/// is inserted at the cursor, so the text typed after it is formatted.
+fn format_selection(entry: &gtk::Entry, style: FormatStyle) {
+    match entry.selection_bounds() {
+        Some((start, end)) => {
+            let selected = entry.chars(start, end);
+            entry.delete_text(start, end);
+            let mut position = start;
+            entry.insert_text(&style.apply(&selected), &mut position);
+            entry.set_position(position);
+            entry.grab_focus_without_selecting();
+        }
+        None => insert_text(entry, &style.code().to_string()),
+    }
+}
@@ -2,6 +2,7 @@ use gtk::{
     glib::{self, clone},
     prelude::*,
 };
+use std::path::Path;
 use std::sync::mpsc::Sender;
 
 /// Adds a new file_message to the corresponding conversation.
@@ -27,13 +28,7 @@ pub fn add_file_message_box(
 
     let message_box_info = create_message_info_box(file_name.clone(), file_size);
 
-    let css_provider = gtk::CssProvider::new();
-    css_provider
-        .load_from_path("client/src/gui/style.scss")
-        .unwrap();
-    let style_context = message_box.style_context();
-    style_context.add_provider(&css_provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
-    style_context.add_class(style);
+    message_box.style_context().add_class(style);
 
     let progress_bar_box = create_progress_bar_box(id, tx, file_name, receiver, builder);
     message_box.pack_start(&message_box_info, true, true, 0);
@@ -75,7 +70,7 @@ fn create_message_info_box(file_name: String, file_size: String) -> gtk::Box {
 /// * `file_name` - The name of the file that is goinge to be resumed or paused
 /// * `receiver` - The name of the user that the message is sent to.
 /// * `builder` - The gtk::Builder object that contains all the widgets of the application.
-fn create_progress_bar_box(
+pub fn create_progress_bar_box(
     id: String,
     tx: Sender<String>,
     file_name: String,
@@ -155,3 +150,46 @@ fn create_progress_bar_box(
 
     progress_bar_box
 }
+
+/// Creates the box shown when a file is received, with a link that opens the file and an
+/// OPEN FOLDER button that opens the folder where it was saved.
+/// # Arguments
+/// * `path` - The path where the file received was saved.
+pub fn create_received_file_box(path: &str) -> gtk::Box {
+    let received_file_box = gtk::Box::new(gtk::Orientation::Horizontal, 10);
+    received_file_box.set_halign(gtk::Align::Center);
+
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| Path::new(path).to_path_buf());
+    let path_label = gtk::Label::new(None);
+    match glib::filename_to_uri(&path, None) {
+        Ok(uri) => path_label.set_markup(&format!(
+            "<a href=\"{}\">{}</a>",
+            glib::markup_escape_text(&uri),
+            glib::markup_escape_text(&path.to_string_lossy())
+        )),
+        Err(_) => path_label.set_text(&path.to_string_lossy()),
+    }
+    received_file_box.pack_start(&path_label, true, true, 0);
+
+    let open_folder_button = gtk::Button::with_label("OPEN FOLDER");
+    let folder_uri = path
+        .parent()
+        .and_then(|folder| glib::filename_to_uri(folder, None).ok());
+    open_folder_button.set_sensitive(folder_uri.is_some());
+    open_folder_button.connect_clicked(move |button| {
+        let uri = match folder_uri.as_ref() {
+            Some(uri) => uri,
+            None => return,
+        };
+        let window = button
+            .toplevel()
+            .and_then(|toplevel| toplevel.downcast::<gtk::Window>().ok());
+        if let Err(error) = gtk::show_uri_on_window(window.as_ref(), uri, gtk::current_event_time())
+        {
+            println!("[ERROR] Error opening the folder {uri}: {error}");
+        }
+    });
+    received_file_box.pack_start(&open_folder_button, false, false, 0);
+
+    received_file_box
+}
@@ -0,0 +1,208 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc, sync::mpsc::Sender};
+
+use gtk::{
+    glib::{self, clone},
+    prelude::*,
+};
+
+use model::formatting::escape_markup;
+
+use crate::gui::{
+    members::{ChannelMembers, Member},
+    nick_colors::{NickColors, NICK_COLORS_PATH},
+};
+
+/// Struct that contains the widgets of the member list, the panel on the right side of the
+/// application with the members of the current channel. The operators have the `@` prefix and
+/// the moderators the `+` prefix, and each nickname has its color. Each member has a context menu to
+/// see its information, talk with it, change the color of its nickname and, if the user is an
+/// operator of the channel, kick or ban it.
+/// # Fields
+/// * `tx`: The sender of the channel that sends the messages to the server.
+/// * `member_list`: The list of members of the current channel.
+/// * `current_chat`: The label containing the name of the current chat.
+/// * `nick_label`: The label that contains the nick of the user.
+/// * `users_list`: The list of users, whose buttons open the private conversations.
+/// * `members`: The members of each channel.
+/// * `nick_colors`: The colors of the nicknames.
+#[derive(Clone)]
+pub struct MemberList {
+    tx: Sender<String>,
+    member_list: gtk::ListBox,
+    current_chat: gtk::Label,
+    nick_label: gtk::Label,
+    users_list: gtk::Box,
+    members: Rc<RefCell<ChannelMembers>>,
+    nick_colors: Rc<RefCell<NickColors>>,
+}
+
+impl MemberList {
+    /// Creates a new `MemberList` struct.
+    /// # Arguments
+    /// * `builder`: The builder of the glade file that builds the application.
+    /// * `tx`: The sender of the channel that sends the messages to the server.
+    /// * `nick_colors`: The colors of the nicknames.
+    pub fn new(
+        builder: &gtk::Builder,
+        tx: Sender<String>,
+        nick_colors: Rc<RefCell<NickColors>>,
+    ) -> Self {
+        let member_list = builder.object::<gtk::ListBox>("member_list").unwrap();
+        let current_chat = builder.object::<gtk::Label>("current_chat").unwrap();
+        let nick_label = builder.object::<gtk::Label>("user_nick").unwrap();
+        let users_list = builder.object::<gtk::Box>("users_list").unwrap();
+
+        MemberList {
+            tx,
+            member_list,
+            current_chat,
+            nick_label,
+            users_list,
+            members: Rc::new(RefCell::new(ChannelMembers::new())),
+            nick_colors,
+        }
+    }
+
+    /// Shows the members of the current chat each time it changes.
+    pub fn build(&self) {
+        let member_list = self.clone();
+        self.current_chat
+            .connect_label_notify(move |_| member_list.show());
+    }
+
+    /// Updates the members of a channel with a NAMES reply, which has the `+` prefix
+    /// on the moderators.
+    pub fn update_names(&self, channel: &str, names: &[String]) {
+        self.members.borrow_mut().set_names(channel, names);
+        self.refresh(channel);
+    }
+
+    /// Updates the operators of a channel with its modes.
+    pub fn update_channel_modes(&self, channel: &str, modes: &HashMap<String, String>) {
+        let default = String::new();
+        let operators = modes.get("ChannelOperator").unwrap_or(&default);
+        self.members.borrow_mut().set_operators(channel, operators);
+        self.refresh(channel);
+    }
+
+    /// Applies a mode change announced to a channel, like `+o bob`.
+    pub fn apply_mode_change(&self, channel: &str, modes: &str) {
+        self.members.borrow_mut().apply_mode(channel, modes);
+        self.refresh(channel);
+    }
+
+    /// Shows the members again if the channel is the current chat.
+    fn refresh(&self, channel: &str) {
+        if self.current_chat.text().as_str() == channel {
+            self.show();
+        }
+    }
+
+    /// Shows the members of the current chat, or none if it is not a channel.
+    fn show(&self) {
+        for row in self.member_list.children() {
+            self.member_list.remove(&row);
+        }
+        let channel = self.current_chat.text().to_string();
+        let nick = self.nick_label.text().to_string();
+        let members = self.members.borrow();
+        let is_operator = members.is_operator(&channel, &nick);
+        for member in members.members(&channel) {
+            let row =
+                self.create_member_row(&channel, &member, is_operator && member.nickname != nick);
+            self.member_list.add(&row);
+        }
+        self.member_list.show_all();
+    }
+
+    /// Creates the row of a member, which opens its context menu with a right click.
+    /// # Arguments
+    /// * `channel`: The channel of the member.
+    /// * `member`: The member shown.
+    /// * `can_remove`: True if the user can kick or ban the member.
+    fn create_member_row(&self, channel: &str, member: &Member, can_remove: bool) -> gtk::EventBox {
+        let row = gtk::EventBox::new();
+        let label = gtk::Label::new(None);
+        label.set_markup(&format!(
+            "<span foreground=\"{}\">{}</span>",
+            self.nick_colors.borrow().color(&member.nickname),
+            escape_markup(&member.display_name())
+        ));
+        label.set_halign(gtk::Align::Start);
+        row.add(&label);
+
+        let menu = self.create_member_menu(channel, &member.nickname, can_remove);
+        row.connect_button_press_event(move |_, event| {
+            if event.button() == 3 {
+                menu.popup_easy(event.button(), event.time());
+                return glib::Propagation::Stop;
+            }
+            glib::Propagation::Proceed
+        });
+        row
+    }
+
+    /// Creates the context menu of a member, with the options to see its information, to talk
+    /// with it and to change the color of its nickname, and the options to kick or ban it if the
+    /// user can remove it. The colors changed are saved, so they are kept in the next sessions.
+    fn create_member_menu(&self, channel: &str, nickname: &str, can_remove: bool) -> gtk::Menu {
+        let menu = gtk::Menu::new();
+
+        let whois_item = gtk::MenuItem::with_label("WHOIS");
+        let tx = self.tx.clone();
+        let name = nickname.to_string();
+        whois_item.connect_activate(move |_| if tx.send(format!("WHOIS {}", name)).is_ok() {});
+        menu.append(&whois_item);
+
+        let message_item = gtk::MenuItem::with_label("PRIVATE MESSAGE");
+        let name = nickname.to_string();
+        message_item.connect_activate(clone!(@weak self.users_list as users_list => move |_| {
+            for widget in users_list.children() {
+                if widget.widget_name().as_str() == name {
+                    if let Ok(button) = widget.downcast::<gtk::Button>() {
+                        button.clicked();
+                    }
+                }
+            }
+        }));
+        menu.append(&message_item);
+
+        let color_item = gtk::MenuItem::with_label("CHANGE COLOR");
+        let member_list = self.clone();
+        let name = nickname.to_string();
+        color_item.connect_activate(move |_| {
+            let mut nick_colors = member_list.nick_colors.borrow_mut();
+            nick_colors.next_color(&name);
+            if nick_colors.save(NICK_COLORS_PATH).is_err() {
+                println!("[ERROR] Error saving the color of {name}");
+            }
+            drop(nick_colors);
+            member_list.show();
+        });
+        menu.append(&color_item);
+
+        if can_remove {
+            let kick_item = gtk::MenuItem::with_label("KICK");
+            let tx = self.tx.clone();
+            let (chan, name) = (channel.to_string(), nickname.to_string());
+            kick_item.connect_activate(
+                move |_| {
+                    if tx.send(format!("KICK {} {}", chan, name)).is_ok() {}
+                },
+            );
+            menu.append(&kick_item);
+
+            let ban_item = gtk::MenuItem::with_label("BAN");
+            let tx = self.tx.clone();
+            let (chan, name) = (channel.to_string(), nickname.to_string());
+            ban_item.connect_activate(move |_| {
+                if tx.send(format!("MODE {} +b {}", chan, name)).is_ok()
+                    && tx.send(format!("KICK {} {}", chan, name)).is_ok()
+                {}
+            });
+            menu.append(&ban_item);
+        }
+        menu.show_all();
+        menu
+    }
+}
@@ -2,10 +2,16 @@ pub mod actions;
 pub mod channel_actions;
 pub mod channel_info;
 pub mod channel_mode;
+pub mod channel_settings_dialog;
 pub mod chats_container;
+pub mod conversation_search;
 pub mod dcc_feature;
+pub mod emoji_picker;
 pub mod file_message;
+pub mod member_list;
 pub mod names_list;
+pub mod preferences_dialog;
+pub mod server_switcher;
 pub mod user_actions;
 pub mod user_mode;
 pub mod who_action;
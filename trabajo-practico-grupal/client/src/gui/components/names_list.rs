@@ -1,11 +1,11 @@
-use std::{collections::HashMap, sync::mpsc::Sender};
+use std::{cell::RefCell, collections::HashMap, rc::Rc, sync::mpsc::Sender};
 
 use gtk::{
     glib::{self, clone},
     prelude::*,
 };
 
-use crate::gui::utils::new_conversation;
+use crate::gui::{activity::ActivityTracker, utils::new_conversation};
 
 /// Struct that contains the widgets of the names list, which is the list of
 /// channels and users.
@@ -20,6 +20,7 @@ use crate::gui::utils::new_conversation;
 /// * `send_button`: The button that sends the chat message.
 /// * `channel_actions`: The box that containes the channel actions menu.
 /// * `stack_channels_info`: The stack that contains the channels info.
+/// * `activity`: The unread messages and the last activity of each conversation.
 pub struct NamesList {
     tx: Sender<String>,
     channels_list: gtk::Box,
@@ -35,6 +36,7 @@ pub struct NamesList {
     file_send_button: gtk::FileChooserButton,
     dcc_close_button: gtk::Button,
     message_entry: gtk::Entry,
+    activity: Rc<RefCell<ActivityTracker>>,
 }
 
 impl NamesList {
@@ -73,6 +75,7 @@ impl NamesList {
             file_send_button,
             dcc_close_button,
             message_entry,
+            activity: Rc::new(RefCell::new(ActivityTracker::new())),
         }
     }
 
@@ -84,37 +87,82 @@ impl NamesList {
 
     /// Adds all channels and users to the list of channels and users on the left side of the
     /// application, then it connects the signals of the channels and users and clears the client hash map.
+    /// The channels and users are sorted by their most recent activity.
     pub fn add_names_to_list(&mut self) {
         self.clean_list();
         let hash_clone = self.clients_hash.clone();
         let mut clients_shown = vec![];
+        let mut channels = vec![];
         for (channel, names) in hash_clone {
             for name in names {
-                if clients_shown.contains(&name) {
-                    continue;
+                if !clients_shown.contains(&name) {
+                    clients_shown.push(name);
                 }
-                clients_shown.push(name.clone());
-
-                self.create_user_button(name);
             }
-            self.users_list.show_all();
-            if channel == "*" {
-                continue;
+            if channel != "*" {
+                channels.push(channel);
             }
+        }
+        self.activity.borrow().sort_by_activity(&mut clients_shown);
+        self.activity.borrow().sort_by_activity(&mut channels);
+
+        for name in clients_shown {
+            self.create_user_button(name);
+        }
+        self.users_list.show_all();
+        for channel in channels {
             let button_channel = self.create_channel_button(channel);
             self.channels_list.set_child(Some(&button_channel));
-            self.channels_list.show_all();
         }
+        self.channels_list.show_all();
         self.clients_hash.clear();
     }
 
+    /// Records a message received in a conversation, updating the unread counter of its button
+    /// and moving it to the top of its list. The button is highlighted if the message mentions the user.
+    /// # Arguments
+    /// * `target`: The channel or user of the conversation.
+    /// * `text`: The text of the message.
+    /// * `nickname`: The nickname of the user.
+    pub fn record_activity(&self, target: &str, text: &str, nickname: &str) {
+        let is_open = self.current_chat.text().as_str() == target;
+        self.activity
+            .borrow_mut()
+            .record(target, text, nickname, is_open);
+        for list in [&self.channels_list, &self.users_list] {
+            for widget in list.children() {
+                if widget.widget_name().as_str() != target {
+                    continue;
+                }
+                if let Ok(button) = widget.clone().downcast::<gtk::Button>() {
+                    self.update_button(&button, target);
+                }
+                list.reorder_child(&widget, 0);
+            }
+        }
+    }
+
+    /// Updates the label of the button of a conversation with its unread counter, highlighting it
+    /// if the user was mentioned.
+    fn update_button(&self, button: &gtk::Button, target: &str) {
+        let activity = self.activity.borrow();
+        button.set_label(&activity.label(target));
+        set_mention_style(button, activity.get(target).mentioned);
+    }
+
     /// Creates a button for a user, then it connects the signal of the button.
     /// When the user button is clicked it creates a new conversation with that user or it shows the conversation
     /// if it already exists.
     fn create_user_button(&mut self, name: String) {
         let button = gtk::Button::with_label(&name);
+        button.set_widget_name(&name);
+        self.update_button(&button, &name);
+        let activity = self.activity.clone();
         button.connect_clicked(
-            clone!(@weak self.chat_button as chat_button,@weak self.file_send_button as file_send_button, @weak self.stack_channels_info as stack_channels_info, @weak self.dcc_button as dcc_button, @weak self.send_button as send_button,  @weak self.current_chat as current, @weak self.channel_actions as channel_actions, @weak self.stack_conversations as stack_conversations, @weak self.dcc_close_button as dcc_close_button, @weak self.message_entry as message_entry => move |_| {
+            clone!(@weak self.chat_button as chat_button,@weak self.file_send_button as file_send_button, @weak self.stack_channels_info as stack_channels_info, @weak self.dcc_button as dcc_button, @weak self.send_button as send_button,  @weak self.current_chat as current, @weak self.channel_actions as channel_actions, @weak self.stack_conversations as stack_conversations, @weak self.dcc_close_button as dcc_close_button, @weak self.message_entry as message_entry => move |button| {
+                activity.borrow_mut().open(&name);
+                button.set_label(&name);
+                set_mention_style(button, false);
                 current.set_label(&name);
                 dcc_close_button.set_visible(false);
                 dcc_button.set_visible(true);
@@ -151,9 +199,15 @@ impl NamesList {
     /// if it already exists. It also sets the channel actions menu visible.
     fn create_channel_button(&mut self, channel: String) -> gtk::Button {
         let button_channel = gtk::Button::with_label(&channel);
+        button_channel.set_widget_name(&channel);
+        self.update_button(&button_channel, &channel);
+        let activity = self.activity.clone();
         let tx_clone = self.tx.clone();
         button_channel.connect_clicked(
-            clone!(@weak self.chat_button as chat_button, @weak self.file_send_button as file_send_button, @weak self.channel_actions as channel_actions, @weak self.dcc_button as dcc_button, @weak self.send_button as send_button, @weak self.stack_conversations as stack_conversations, @weak self.current_chat as current, @weak self.stack_channels_info as stack_channels_info, @weak self.dcc_close_button as dcc_close_button => move |_| {
+            clone!(@weak self.chat_button as chat_button, @weak self.file_send_button as file_send_button, @weak self.channel_actions as channel_actions, @weak self.dcc_button as dcc_button, @weak self.send_button as send_button, @weak self.stack_conversations as stack_conversations, @weak self.current_chat as current, @weak self.stack_channels_info as stack_channels_info, @weak self.dcc_close_button as dcc_close_button => move |button| {
+                activity.borrow_mut().open(&channel);
+                button.set_label(&channel);
+                set_mention_style(button, false);
                 current.set_label(&channel);
                 dcc_close_button.set_visible(false);
                 dcc_button.set_visible(false);
@@ -196,3 +250,13 @@ impl NamesList {
         });
     }
 }
+
+/// Highlights the button of a conversation that mentioned the user, or removes the highlight.
+fn set_mention_style(button: &gtk::Button, mentioned: bool) {
+    let style_context = button.style_context();
+    if mentioned {
+        style_context.add_class("mention");
+    } else {
+        style_context.remove_class("mention");
+    }
+}
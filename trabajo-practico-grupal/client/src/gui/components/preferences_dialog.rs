@@ -0,0 +1,124 @@
+use std::rc::Rc;
+
+use gtk::{glib::clone, prelude::*};
+
+use crate::gui::{
+    preferences::{CollisionPolicy, Theme},
+    theme::ThemeManager,
+};
+
+/// A struct that contains the preferences dialog, where the user switches the theme and
+/// changes the font size of the interface at runtime, the limits of the DCC transfers, where the
+/// received files are saved and which files are declined.
+/// # Fields
+/// * `theme_manager` - The manager that applies and saves the preferences.
+pub struct PreferencesDialog {
+    theme_manager: Rc<ThemeManager>,
+}
+
+impl PreferencesDialog {
+    /// Creates a new PreferencesDialog struct.
+    /// # Arguments
+    /// * `theme_manager` - The manager that applies and saves the preferences.
+    pub fn new(theme_manager: Rc<ThemeManager>) -> Self {
+        PreferencesDialog { theme_manager }
+    }
+
+    /// Builds the PREFERENCES button, which opens the preferences modal, and the widgets of the
+    /// modal, showing the saved preferences and applying the changes as they are made.
+    /// # Arguments
+    /// * `builder` - The builder to get the widgets from.
+    pub fn build(self, builder: &gtk::Builder) {
+        let preferences_button = builder.object::<gtk::Button>("preferences_button").unwrap();
+        let preferences_modal = builder.object::<gtk::Window>("preferences_modal").unwrap();
+        let dark_mode_switch = builder.object::<gtk::Switch>("dark_mode_switch").unwrap();
+        let font_size_spin = builder.object::<gtk::SpinButton>("font_size_spin").unwrap();
+        let dcc_speed_limit_spin = builder
+            .object::<gtk::SpinButton>("dcc_speed_limit_spin")
+            .unwrap();
+        let dcc_max_transfers_spin = builder
+            .object::<gtk::SpinButton>("dcc_max_transfers_spin")
+            .unwrap();
+        let download_directory_chooser = builder
+            .object::<gtk::FileChooserButton>("download_directory_chooser")
+            .unwrap();
+        let collision_policy_combo = builder
+            .object::<gtk::ComboBoxText>("collision_policy_combo")
+            .unwrap();
+        let dcc_max_file_size_spin = builder
+            .object::<gtk::SpinButton>("dcc_max_file_size_spin")
+            .unwrap();
+        let dcc_blocked_extensions_entry = builder
+            .object::<gtk::Entry>("dcc_blocked_extensions_entry")
+            .unwrap();
+
+        let preferences = self.theme_manager.preferences();
+        dark_mode_switch.set_active(preferences.theme == Theme::Dark);
+        font_size_spin.set_value(preferences.font_size as f64);
+        dcc_speed_limit_spin.set_value(preferences.dcc_speed_limit as f64);
+        dcc_max_transfers_spin.set_value(preferences.dcc_max_transfers as f64);
+        download_directory_chooser.set_filename(&preferences.download_directory);
+        collision_policy_combo.set_active_id(Some(preferences.collision_policy.as_str()));
+        dcc_max_file_size_spin.set_value(preferences.dcc_max_file_size as f64);
+        dcc_blocked_extensions_entry.set_text(&preferences.dcc_blocked_extensions);
+
+        preferences_modal.connect_delete_event(move |_win, _| _win.hide_on_delete());
+        preferences_button.connect_clicked(clone!( @weak preferences_modal => move |_| {
+            preferences_modal.show();
+        }));
+
+        let theme_manager = self.theme_manager.clone();
+        dark_mode_switch.connect_active_notify(move |switch| {
+            let theme = match switch.is_active() {
+                true => Theme::Dark,
+                false => Theme::Light,
+            };
+            theme_manager.set_theme(theme);
+        });
+
+        let theme_manager = self.theme_manager.clone();
+        font_size_spin.connect_value_changed(move |spin| {
+            theme_manager.set_font_size(spin.value_as_int() as u32);
+        });
+
+        let theme_manager = self.theme_manager.clone();
+        dcc_speed_limit_spin.connect_value_changed(move |spin| {
+            theme_manager.set_dcc_speed_limit(spin.value_as_int() as u32);
+        });
+
+        let theme_manager = self.theme_manager.clone();
+        dcc_max_transfers_spin.connect_value_changed(move |spin| {
+            theme_manager.set_dcc_max_transfers(spin.value_as_int() as u32);
+        });
+
+        let theme_manager = self.theme_manager.clone();
+        download_directory_chooser.connect_file_set(move |chooser| {
+            if let Some(folder) = chooser
+                .filename()
+                .and_then(|folder| folder.to_str().map(String::from))
+            {
+                theme_manager.set_download_directory(folder);
+            }
+        });
+
+        let theme_manager = self.theme_manager.clone();
+        collision_policy_combo.connect_changed(move |combo| {
+            if let Some(policy) = combo
+                .active_id()
+                .and_then(|id| CollisionPolicy::from_name(&id))
+            {
+                theme_manager.set_collision_policy(policy);
+            }
+        });
+
+        let theme_manager = self.theme_manager.clone();
+        dcc_max_file_size_spin.connect_value_changed(move |spin| {
+            theme_manager.set_dcc_max_file_size(spin.value_as_int() as u32);
+        });
+
+        let theme_manager = self.theme_manager;
+        dcc_blocked_extensions_entry.connect_changed(move |entry| {
+            theme_manager.set_dcc_blocked_extensions(entry.text().to_string());
+        });
+    }
+}
@@ -0,0 +1,114 @@
+use std::{cell::RefCell, rc::Rc};
+
+use gtk::{glib::clone, prelude::*};
+
+use crate::gui::{recent_servers::ServerSettings, servers::ServerPages};
+
+/// The sidebar of the main window that switches between the connections with the servers. Each
+/// connection has its own builder, whose screens are moved to a page of the stack of the servers,
+/// so the sessions, the conversations and the nicknames of the connections don't mix.
+/// # Fields
+/// * `window`: The main window, which holds the sidebar and the stack of the servers.
+/// * `servers_stack`: The stack with the screens of each connection.
+/// * `add_button`: The button that opens a new connection.
+/// * `pages`: The names and the titles of the pages of the connections.
+#[derive(Clone)]
+pub struct ServerSwitcher {
+    window: gtk::Window,
+    servers_stack: gtk::Stack,
+    add_button: gtk::Button,
+    pages: Rc<RefCell<ServerPages>>,
+}
+
+impl ServerSwitcher {
+    /// Creates a new `ServerSwitcher`, replacing the screens of the main window by the sidebar
+    /// and the stack of the servers, where the screens of the first connection are the first page.
+    /// # Arguments
+    /// * `builder`: The builder of the first connection, which has the main window.
+    pub fn new(builder: &gtk::Builder) -> Self {
+        let window = builder.object::<gtk::Window>("main_window").unwrap();
+        let servers_stack = gtk::Stack::new();
+        servers_stack.set_hexpand(true);
+        servers_stack.set_vexpand(true);
+        let sidebar = gtk::StackSidebar::new();
+        sidebar.set_stack(&servers_stack);
+        let add_button = gtk::Button::with_label("Add server");
+
+        let switcher_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        switcher_box.pack_start(&sidebar, true, true, 0);
+        switcher_box.pack_start(&add_button, false, false, 0);
+        let layout = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        layout.pack_start(&switcher_box, false, false, 0);
+        layout.pack_start(&servers_stack, true, true, 0);
+
+        let stack = builder.object::<gtk::Stack>("stack").unwrap();
+        window.remove(&stack);
+        window.add(&layout);
+
+        let switcher = ServerSwitcher {
+            window,
+            servers_stack,
+            add_button,
+            pages: Rc::new(RefCell::new(ServerPages::default())),
+        };
+        switcher.add(builder);
+        switcher
+    }
+
+    /// Adds the screens of a connection as a page of the stack of the servers and shows it. The
+    /// title of the page changes to the nickname and the address of the server once the user
+    /// registers, and the page is closed when the user quits.
+    /// # Arguments
+    /// * `builder`: The builder of the connection, whose screens were already built.
+    pub fn add(&self, builder: &gtk::Builder) {
+        let stack = builder.object::<gtk::Stack>("stack").unwrap();
+        if let Some(window) = builder.object::<gtk::Window>("main_window") {
+            if window != self.window {
+                window.remove(&stack);
+            }
+        }
+        let (name, title) = self.pages.borrow_mut().open();
+        self.servers_stack.add_titled(&stack, &name, &title);
+        stack.show();
+        self.servers_stack.set_visible_child(&stack);
+
+        let user_nick = builder.object::<gtk::Label>("user_nick").unwrap();
+        let ip_entry = builder.object::<gtk::Entry>("ip_entry").unwrap();
+        let port_entry = builder.object::<gtk::Entry>("port_entry").unwrap();
+        let switcher = self.clone();
+        let page = name.to_owned();
+        user_nick.connect_label_notify(
+            clone!(@weak stack, @weak ip_entry, @weak port_entry => move |user_nick| {
+                let server = ServerSettings {
+                    host: ip_entry.text().to_string(),
+                    port: port_entry.text().to_string(),
+                    nickname: user_nick.text().to_string(),
+                    ..Default::default()
+                };
+                if let Some(title) = switcher.pages.borrow_mut().rename(&page, &server) {
+                    switcher.servers_stack.child_set_property(&stack, "title", &title);
+                }
+            }),
+        );
+
+        let quit_button = builder.object::<gtk::Button>("quit_button").unwrap();
+        let switcher = self.clone();
+        quit_button.connect_clicked(clone!(@weak stack => move |_| {
+            if switcher.pages.borrow_mut().close(&name) {
+                switcher.servers_stack.remove(&stack);
+            } else {
+                switcher.window.close();
+            }
+        }));
+    }
+
+    /// Connects the button that opens a new connection, which is called with the switcher to add
+    /// the screens of the connection to it.
+    /// # Arguments
+    /// * `open_connection`: Builds the screens of a new connection and adds them to the switcher.
+    pub fn connect_add_server<F: Fn(&ServerSwitcher) + 'static>(&self, open_connection: F) {
+        let switcher = self.clone();
+        self.add_button
+            .connect_clicked(move |_| open_connection(&switcher));
+    }
+}
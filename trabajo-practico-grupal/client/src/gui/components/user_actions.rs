@@ -31,15 +31,16 @@ impl UserActions {
     }
 
     /// Builds the QUIT button, giving it the correct functionality. It sends the QUIT message to the
-    /// server once the user clicks on the QUIT button.
+    /// server once the user clicks on the QUIT button. The server switcher closes the page of the
+    /// connection, or the window if it was the last one.
     fn active_quit_button(&self, builder: &gtk::Builder) {
         let quit_button = builder.object::<gtk::Button>("quit_button").unwrap();
         let tx_clone = self.tx.clone();
-        let stack = builder.object::<gtk::Stack>("stack").unwrap();
-        let main_window = builder.object::<gtk::Window>("main_window").unwrap();
-        quit_button.connect_clicked(clone!( @weak stack=> move |_| {
-            if tx_clone.send("QUIT".to_string()).is_ok() { main_window.close() }
-        }));
+        quit_button.connect_clicked(move |_| {
+            if tx_clone.send("QUIT".to_string()).is_err() {
+                println!("[ERROR] Error sending the QUIT to the server");
+            }
+        });
     }
 
     /// Builds the OPER button, giving it the correct functionality. It opens the OPER modal and
@@ -16,6 +16,7 @@ use super::actions::send_mode_message;
 /// * `invisible_switch` - The switch that allows the user to change their invisible mode.
 /// * `wallops_switch` - The switch that allows the user to change their wallops mode.
 /// * `server_messages_switch` - The switch that allows the user to change their server messages mode.
+/// * `delivery_receipts_switch` - The switch that allows the user to receive the delivery receipts of their private messages.
 pub struct UserMode {
     tx: Sender<String>,
     mode_user_modal: gtk::Window,
@@ -23,6 +24,7 @@ pub struct UserMode {
     invisible_switch: gtk::Switch,
     wallops_switch: gtk::Switch,
     server_messages_switch: gtk::Switch,
+    delivery_receipts_switch: gtk::Switch,
 }
 
 impl UserMode {
@@ -33,6 +35,8 @@ impl UserMode {
         let invisible_switch: gtk::Switch = builder.object("invisible_switch").unwrap();
         let wallops_switch: gtk::Switch = builder.object("wallops_switch").unwrap();
         let server_messages_switch: gtk::Switch = builder.object("server_messages_switch").unwrap();
+        let delivery_receipts_switch: gtk::Switch =
+            builder.object("delivery_receipts_switch").unwrap();
 
         UserMode {
             tx,
@@ -41,6 +45,7 @@ impl UserMode {
             invisible_switch,
             wallops_switch,
             server_messages_switch,
+            delivery_receipts_switch,
         }
     }
 
@@ -75,14 +80,17 @@ impl UserMode {
         let tx = self.tx.clone();
         mode_user_modal_button.connect_clicked(clone!(@weak mode_user_modal, @weak self.invisible_switch as invisible_switch,
             @weak self.wallops_switch as wallops_switch, @weak self.server_messages_switch as server_messages_switch,
+            @weak self.delivery_receipts_switch as delivery_receipts_switch,
             @weak error_modal, @weak self.nick_label as nick_label => move |_| {
                 let nick = nick_label.text().to_string();
                 let invisible = if invisible_switch.is_active() {"+i"} else {"-i"};
                 let wallops = if wallops_switch.is_active() {"+w"} else {"-w"};
                 let server_messages = if server_messages_switch.is_active() {"+s"} else {"-s"};
+                let delivery_receipts = if delivery_receipts_switch.is_active() {"+d"} else {"-d"};
                 send_mode_message(tx.clone(), &error_modal, &nick, invisible);
                 send_mode_message(tx.clone(),&error_modal, &nick, wallops);
                 send_mode_message(tx.clone(),&error_modal, &nick, server_messages);
+                send_mode_message(tx.clone(),&error_modal, &nick, delivery_receipts);
                 mode_user_modal.hide();
         }));
     }
@@ -92,6 +100,7 @@ impl UserMode {
         self.set_switch_state(&modes, &self.invisible_switch, "Invisible");
         self.set_switch_state(&modes, &self.wallops_switch, "Wallops");
         self.set_switch_state(&modes, &self.server_messages_switch, "ServerNotice");
+        self.set_switch_state(&modes, &self.delivery_receipts_switch, "DeliveryReceipts");
 
         self.mode_user_modal.show();
     }
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+/// The number of messages sent that are remembered of each conversation.
+pub const MAX_INPUT_HISTORY: usize = 50;
+
+/// Struct that keeps the messages sent in each conversation, recalled in the message entry with
+/// Up and Down. While the user goes through the messages of a conversation, the text typed before
+/// is kept as a draft, which is shown again after the newest message.
+/// # Fields
+/// * `sent`: The messages sent in each conversation, the oldest first.
+/// * `browsing`: The messages being recalled, while the text is the last one recalled.
+#[derive(Debug, Default)]
+pub struct InputHistory {
+    sent: HashMap<String, Vec<String>>,
+    browsing: Option<Browsing>,
+}
+
+/// The state of the messages being recalled.
+/// # Fields
+/// * `conversation`: The conversation of the messages.
+/// * `index`: The message recalled, or the amount of messages if the draft is shown.
+/// * `draft`: The text typed before recalling the messages.
+/// * `shown`: The text of the last message recalled.
+#[derive(Debug)]
+struct Browsing {
+    conversation: String,
+    index: usize,
+    draft: String,
+    shown: String,
+}
+
+impl InputHistory {
+    /// Creates a history without messages.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Saves a message sent in the conversation, unless it is the same as the last one, and stops
+    /// recalling the messages. Only the last `MAX_INPUT_HISTORY` messages are kept.
+    /// # Arguments
+    /// * `conversation`: The name of the conversation.
+    /// * `message`: The message sent.
+    pub fn push(&mut self, conversation: &str, message: &str) {
+        self.browsing = None;
+        let sent = self.sent.entry(conversation.to_string()).or_default();
+        if sent.last().map(String::as_str) == Some(message) {
+            return;
+        }
+        sent.push(message.to_string());
+        if sent.len() > MAX_INPUT_HISTORY {
+            sent.remove(0);
+        }
+    }
+
+    /// Returns the message sent before the one recalled, or the last message sent if none is
+    /// recalled, keeping the text as the draft. Returns None if there is no older message.
+    /// # Arguments
+    /// * `conversation`: The name of the conversation.
+    /// * `text`: The text of the message entry.
+    pub fn previous(&mut self, conversation: &str, text: &str) -> Option<String> {
+        let total = self.sent.get(conversation)?.len();
+        let browsing = match self.browsing.take() {
+            Some(browsing) if browsing.conversation == conversation && browsing.shown == text => {
+                browsing
+            }
+            _ => Browsing {
+                conversation: conversation.to_string(),
+                index: total,
+                draft: text.to_string(),
+                shown: text.to_string(),
+            },
+        };
+        if browsing.index == 0 {
+            self.browsing = Some(browsing);
+            return None;
+        }
+        self.show(
+            total,
+            Browsing {
+                index: browsing.index - 1,
+                ..browsing
+            },
+        )
+    }
+
+    /// Returns the message sent after the one recalled, or the draft after the newest one.
+    /// Returns None if no message is recalled or the text was changed after recalling it.
+    /// # Arguments
+    /// * `conversation`: The name of the conversation.
+    /// * `text`: The text of the message entry.
+    pub fn next(&mut self, conversation: &str, text: &str) -> Option<String> {
+        let browsing = self.browsing.take()?;
+        if browsing.conversation != conversation || browsing.shown != text {
+            return None;
+        }
+        let total = self.sent.get(conversation).map_or(0, Vec::len);
+        if browsing.index >= total {
+            return None;
+        }
+        self.show(
+            total,
+            Browsing {
+                index: browsing.index + 1,
+                ..browsing
+            },
+        )
+    }
+
+    /// Saves the state of the messages recalled and returns the text to show, which is the draft
+    /// once the index passes the newest message.
+    fn show(&mut self, total: usize, mut browsing: Browsing) -> Option<String> {
+        browsing.shown = match browsing.index < total {
+            true => self.sent.get(&browsing.conversation)?[browsing.index].to_owned(),
+            false => browsing.draft.to_owned(),
+        };
+        let shown = browsing.shown.to_owned();
+        self.browsing = Some(browsing);
+        Some(shown)
+    }
+}
+
+#[cfg(test)]
+mod input_history_tests {
+    use super::{InputHistory, MAX_INPUT_HISTORY};
+
+    fn history_for_test() -> InputHistory {
+        let mut history = InputHistory::new();
+        history.push("#rust", "hello");
+        history.push("#rust", "how are you?");
+        history.push("#rust", "how are you?");
+        history.push("bob", "hi bob");
+        history
+    }
+
+    #[test]
+    fn test_up_and_down_recall_the_messages_of_the_conversation_keeping_the_draft() {
+        let mut history = history_for_test();
+
+        let last = history.previous("#rust", "typing").unwrap();
+        assert_eq!(last, "how are you?");
+        let first = history.previous("#rust", &last).unwrap();
+        assert_eq!(first, "hello");
+        assert_eq!(history.previous("#rust", &first), None);
+
+        let second = history.next("#rust", &first).unwrap();
+        assert_eq!(second, "how are you?");
+        assert_eq!(history.next("#rust", &second).unwrap(), "typing");
+        assert_eq!(history.next("#rust", "typing"), None);
+    }
+
+    #[test]
+    fn test_editing_the_message_recalled_makes_it_the_draft() {
+        let mut history = history_for_test();
+
+        history.previous("#rust", "").unwrap();
+        assert_eq!(history.next("#rust", "how are you, bob?"), None);
+        let last = history.previous("#rust", "how are you, bob?").unwrap();
+        assert_eq!(last, "how are you?");
+        assert_eq!(history.next("#rust", &last).unwrap(), "how are you, bob?");
+
+        assert_eq!(history.previous("bob", "hello!").unwrap(), "hi bob");
+        assert_eq!(history.next("bob", "hi bob").unwrap(), "hello!");
+        assert_eq!(history.previous("#nobody", ""), None);
+    }
+
+    #[test]
+    fn test_only_the_last_messages_are_kept() {
+        let mut history = InputHistory::new();
+        for number in 0..=MAX_INPUT_HISTORY {
+            history.push("#rust", &number.to_string());
+        }
+
+        let mut oldest = String::new();
+        while let Some(message) = history.previous("#rust", &oldest) {
+            oldest = message;
+        }
+
+        assert_eq!(oldest, "1");
+    }
+}
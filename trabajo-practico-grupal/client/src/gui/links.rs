@@ -0,0 +1,113 @@
+use std::iter::once;
+
+use model::formatting::{escape_markup, parse_formatting};
+
+/// The beginnings of the links detected in the text of the messages.
+const LINK_PREFIXES: [&str; 4] = ["https://", "http://", "file://", "www."];
+/// The characters that may come before a link without being part of it.
+const LEADING_PUNCTUATION: [char; 4] = ['(', '<', '"', '\''];
+/// The characters that may end a sentence after a link without being part of it.
+const TRAILING_PUNCTUATION: [char; 10] = ['.', ',', ';', ':', '!', '?', ')', '>', '"', '\''];
+
+/// Returns the start and the end, in bytes, of each link in the text, in order.
+/// A link is a word that starts with one of the prefixes of the links, without the punctuation
+/// around it.
+pub fn find_links(text: &str) -> Vec<(usize, usize)> {
+    let mut links = vec![];
+    let mut word_start = None;
+    for (i, c) in text.char_indices().chain(once((text.len(), ' '))) {
+        match (c.is_whitespace(), word_start) {
+            (true, Some(start)) => {
+                if let Some(link) = link_in_word(&text[start..i]) {
+                    links.push((start + link.0, start + link.1));
+                }
+                word_start = None;
+            }
+            (false, None) => word_start = Some(i),
+            _ => (),
+        }
+    }
+    links
+}
+
+/// Returns the start and the end of the link in the word, or None if the word is not a link.
+fn link_in_word(word: &str) -> Option<(usize, usize)> {
+    let link = word.trim_start_matches(LEADING_PUNCTUATION);
+    let start = word.len() - link.len();
+    let link = link.trim_end_matches(TRAILING_PUNCTUATION);
+    let prefix = LINK_PREFIXES
+        .iter()
+        .find(|prefix| link.starts_with(*prefix))?;
+    if link.len() == prefix.len() {
+        return None;
+    }
+    Some((start, start + link.len()))
+}
+
+/// Returns the address opened by a link, adding the scheme to the links that start with `www.`.
+pub fn link_target(link: &str) -> String {
+    match link.starts_with("www.") {
+        true => format!("https://{}", link),
+        false => link.to_string(),
+    }
+}
+
+/// Returns the text of a message as Pango markup with its formats, where the links are
+/// clickable and open their address.
+pub fn to_linked_markup(text: &str) -> String {
+    let mut markup = String::new();
+    for span in parse_formatting(text) {
+        let mut span_markup = String::new();
+        let mut end_of_last_link = 0;
+        for (start, end) in find_links(&span.text) {
+            let link = &span.text[start..end];
+            span_markup.push_str(&escape_markup(&span.text[end_of_last_link..start]));
+            span_markup.push_str(&format!(
+                "<a href=\"{}\">{}</a>",
+                escape_markup(&link_target(link)),
+                escape_markup(link)
+            ));
+            end_of_last_link = end;
+        }
+        span_markup.push_str(&escape_markup(&span.text[end_of_last_link..]));
+        markup.push_str(&span.wrap_markup(span_markup));
+    }
+    markup
+}
+
+#[cfg(test)]
+mod links_tests {
+    use super::{find_links, link_target, to_linked_markup};
+
+    #[test]
+    fn test_links_are_found_without_the_punctuation_around_them() {
+        let text =
+            "see https://example.org/a?b=1, (www.rust-lang.org) and http:// or file:///tmp/x.";
+        let links = find_links(text)
+            .into_iter()
+            .map(|(start, end)| &text[start..end])
+            .collect::<Vec<_>>();
+        assert_eq!(
+            links,
+            vec![
+                "https://example.org/a?b=1",
+                "www.rust-lang.org",
+                "file:///tmp/x"
+            ]
+        );
+        assert_eq!(
+            link_target("www.rust-lang.org"),
+            "https://www.rust-lang.org"
+        );
+        assert!(find_links("no links here").is_empty());
+    }
+
+    #[test]
+    fn test_links_are_clickable_in_the_markup_of_the_message() {
+        assert_eq!(
+            to_linked_markup("\x02look\x02 at https://a.org/?x=1&y=<2> now"),
+            "<b>look</b> at <a href=\"https://a.org/?x=1&amp;y=&lt;2\">https://a.org/?x=1&amp;y=&lt;2</a>&gt; now"
+        );
+        assert_eq!(to_linked_markup("a < b"), "a &lt; b");
+    }
+}
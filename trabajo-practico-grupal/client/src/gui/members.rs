@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use model::consts::{OPERATOR_FLAG, VOICE_FLAG};
+
+/// Struct that represents a member of a channel.
+/// # Fields
+/// * `nickname`: The nickname of the member.
+/// * `operator`: True if the member is an operator of the channel.
+/// * `voiced`: True if the member can talk in a moderated channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Member {
+    pub nickname: String,
+    pub operator: bool,
+    pub voiced: bool,
+}
+
+impl Member {
+    /// Returns the nickname with the `@` prefix of the operators or the `+` of the voiced members.
+    pub fn display_name(&self) -> String {
+        if self.operator {
+            format!("@{}", self.nickname)
+        } else if self.voiced {
+            format!("+{}", self.nickname)
+        } else {
+            self.nickname.to_owned()
+        }
+    }
+}
+
+/// Struct that keeps the members of each channel, updated with the NAMES replies, the modes of
+/// the channels and the mode changes announced to the channel.
+/// # Fields
+/// * `channels`: The members of each channel.
+/// * `operators`: The operators of each channel, from its modes.
+#[derive(Debug, Default)]
+pub struct ChannelMembers {
+    channels: HashMap<String, Vec<Member>>,
+    operators: HashMap<String, Vec<String>>,
+}
+
+impl ChannelMembers {
+    /// Creates the members without channels.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the members of a channel from a NAMES reply, where the voiced members have the `+` prefix.
+    pub fn set_names(&mut self, channel: &str, names: &[String]) {
+        let operators = self.operators.get(channel).cloned().unwrap_or_default();
+        let members = names
+            .iter()
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                let nickname = name
+                    .trim_start_matches([OPERATOR_FLAG, VOICE_FLAG])
+                    .to_string();
+                Member {
+                    operator: name.starts_with(OPERATOR_FLAG) || operators.contains(&nickname),
+                    voiced: name.starts_with(VOICE_FLAG),
+                    nickname,
+                }
+            })
+            .collect();
+        self.channels.insert(channel.to_string(), members);
+    }
+
+    /// Sets the operators of a channel from its modes, separated by commas.
+    pub fn set_operators(&mut self, channel: &str, operators: &str) {
+        let operators = operators
+            .split(',')
+            .filter(|operator| !operator.is_empty())
+            .map(|operator| operator.to_string())
+            .collect::<Vec<_>>();
+        if let Some(members) = self.channels.get_mut(channel) {
+            for member in members.iter_mut() {
+                member.operator = operators.contains(&member.nickname);
+            }
+        }
+        self.operators.insert(channel.to_string(), operators);
+    }
+
+    /// Applies a mode change announced to the channel, like `+o bob` or `-v+o bob alice`,
+    /// updating the operators and voiced members. The parameters of other modes are skipped.
+    pub fn apply_mode(&mut self, channel: &str, modes: &str) {
+        let mut words = modes.split_whitespace();
+        let flags = words.next().unwrap_or_default();
+        let mut adding = true;
+        for flag in flags.chars() {
+            match flag {
+                '+' => adding = true,
+                '-' => adding = false,
+                'o' | 'v' => {
+                    let nickname = match words.next() {
+                        Some(nickname) => nickname,
+                        None => return,
+                    };
+                    if flag == 'o' {
+                        let operators = self.operators.entry(channel.to_string()).or_default();
+                        operators.retain(|operator| operator != nickname);
+                        if adding {
+                            operators.push(nickname.to_string());
+                        }
+                    }
+                    let members = self.channels.entry(channel.to_string()).or_default();
+                    for member in members.iter_mut().filter(|m| m.nickname == nickname) {
+                        if flag == 'o' {
+                            member.operator = adding;
+                        } else {
+                            member.voiced = adding;
+                        }
+                    }
+                }
+                'k' | 'b' => {
+                    words.next();
+                }
+                'l' | 'f' if adding => {
+                    words.next();
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Returns the members of a channel, the operators first, then the voiced members and then
+    /// the rest, each group sorted by nickname.
+    pub fn members(&self, channel: &str) -> Vec<Member> {
+        let mut members = self.channels.get(channel).cloned().unwrap_or_default();
+        members.sort_by_key(|member| {
+            (
+                !member.operator,
+                !member.voiced,
+                member.nickname.to_lowercase(),
+            )
+        });
+        members
+    }
+
+    /// Returns true if the nickname is an operator of the channel.
+    pub fn is_operator(&self, channel: &str, nickname: &str) -> bool {
+        self.members(channel)
+            .iter()
+            .any(|member| member.nickname == nickname && member.operator)
+    }
+}
+
+#[cfg(test)]
+mod members_tests {
+    use super::ChannelMembers;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_members_are_sorted_with_their_prefixes() {
+        let mut members = ChannelMembers::new();
+        members.set_operators("#a", "zed");
+        members.set_names("#a", &names(&["bob", "+carol", "zed", "alice"]));
+
+        let shown = members
+            .members("#a")
+            .iter()
+            .map(|member| member.display_name())
+            .collect::<Vec<_>>();
+        assert_eq!(shown, vec!["@zed", "+carol", "alice", "bob"]);
+        assert!(members.is_operator("#a", "zed"));
+        assert!(!members.is_operator("#a", "bob"));
+    }
+
+    #[test]
+    fn test_mode_changes_update_the_prefixes() {
+        let mut members = ChannelMembers::new();
+        members.set_names("#a", &names(&["bob", "+carol"]));
+
+        members.apply_mode("#a", "+o bob");
+        members.apply_mode("#a", "+k-v secret carol");
+        assert!(members.is_operator("#a", "bob"));
+        let shown = members
+            .members("#a")
+            .iter()
+            .map(|member| member.display_name())
+            .collect::<Vec<_>>();
+        assert_eq!(shown, vec!["@bob", "carol"]);
+
+        members.apply_mode("#a", "-o bob");
+        members.set_names("#a", &names(&["bob", "carol", "dave"]));
+        assert!(!members.is_operator("#a", "bob"));
+    }
+}
@@ -1,4 +1,7 @@
 use gtk::prelude::*;
+use model::formatting::escape_markup;
+
+use crate::gui::links::to_linked_markup;
 
 ///This function is used to create a new message box to display in the conversation screen when a message is sent
 /// by the user.
@@ -7,14 +10,9 @@ pub fn message_sent_box(command: String, style: &str) -> gtk::Box {
     let message_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
     message_box.set_halign(gtk::Align::End);
     message_box.set_size_request(100, 50);
-    let message_label = gtk::Label::new(Some(&command));
-    let css_provider = gtk::CssProvider::new();
-    css_provider
-        .load_from_path("client/src/gui/style.scss")
-        .unwrap();
-    let style_context = message_box.style_context();
-    style_context.add_provider(&css_provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
-    style_context.add_class(style);
+    let message_label = gtk::Label::new(None);
+    message_label.set_markup(&to_linked_markup(&command));
+    message_box.style_context().add_class(style);
     message_box.pack_start(&message_label, true, true, 0);
 
     message_box
@@ -22,19 +20,66 @@ pub fn message_sent_box(command: String, style: &str) -> gtk::Box {
 
 ///This function is used to create a new message box to display in the conversation screen when a message is received
 /// by the user.
-/// The message is displayed in the left side of the screen.
+/// The message is displayed in the left side of the screen, with its bold, italic and underlined text
+/// and its links, which open in the default browser when clicked.
 pub fn message_received_box(response: String, style: &str) -> gtk::Box {
     let message_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
     message_box.set_halign(gtk::Align::Start);
     message_box.set_size_request(100, 50);
-    let message_label = gtk::Label::new(Some(&response));
-    let css_provider = gtk::CssProvider::new();
-    css_provider
-        .load_from_path("client/src/gui/style.scss")
-        .unwrap();
-    let style_context = message_box.style_context();
-    style_context.add_provider(&css_provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
-    style_context.add_class(style);
+    let message_label = gtk::Label::new(None);
+    message_label.set_markup(&to_linked_markup(&response));
+    message_box.style_context().add_class(style);
+    message_box.pack_start(&message_label, true, true, 0);
+    message_box
+}
+
+///This function is used to create a new message box to display a system line in the conversation screen,
+/// like the users that join or leave a channel and the modes set.
+/// The message is displayed in the center of the screen.
+pub fn system_message_box(text: String) -> gtk::Box {
+    let message_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+    message_box.set_halign(gtk::Align::Center);
+    let message_label = gtk::Label::new(Some(&text));
+    message_box.style_context().add_class("system_message");
+    message_box.pack_start(&message_label, true, true, 0);
+    message_box
+}
+
+///This function is used to create a new message box to display in the conversation screen when a message is received
+/// in a channel, with the nickname of the sender in its color.
+/// The messages that mention the user are highlighted.
+/// # Arguments
+/// * `prefix` - The text shown before the nickname, like the time of the messages reloaded from the logs.
+/// * `sender` - The nickname of the sender.
+/// * `color` - The color of the nickname.
+/// * `message` - The text of the message.
+/// * `mentioned` - True if the message mentions the user.
+pub fn nick_message_box(
+    prefix: &str,
+    sender: &str,
+    color: &str,
+    message: &str,
+    mentioned: bool,
+) -> gtk::Box {
+    let message_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+    message_box.set_halign(gtk::Align::Start);
+    message_box.set_size_request(100, 50);
+    let prefix = match prefix.is_empty() {
+        true => String::new(),
+        false => format!("{} ", escape_markup(prefix)),
+    };
+    let message_label = gtk::Label::new(None);
+    message_label.set_markup(&format!(
+        "{}<span foreground=\"{}\"><b>{}</b></span>: {}",
+        prefix,
+        color,
+        escape_markup(sender),
+        to_linked_markup(message)
+    ));
+    message_box.style_context().add_class("message_received");
+    if mentioned {
+        message_box.style_context().add_class("highlighted_message");
+    }
     message_box.pack_start(&message_label, true, true, 0);
     message_box
 }
@@ -1,5 +1,17 @@
+pub mod activity;
+pub mod channel_settings;
+pub mod completion;
 pub mod components;
 pub mod controller;
+pub mod input_history;
+pub mod links;
+pub mod members;
 pub mod messages_box;
+pub mod nick_colors;
+pub mod preferences;
+pub mod recent_servers;
 pub mod screens;
+pub mod search;
+pub mod servers;
+pub mod theme;
 pub mod utils;
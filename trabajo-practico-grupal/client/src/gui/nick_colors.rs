@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use model::client_errors::ClientError;
+
+/// The file where the colors chosen for the nicknames are saved.
+pub const NICK_COLORS_PATH: &str = "client/nick_colors.conf";
+/// The colors of the nicknames, dark enough to be read over the messages of both themes.
+pub const PALETTE: [&str; 12] = [
+    "#B3261E", "#1F6FB2", "#2E7D32", "#8E24AA", "#C25E00", "#00838F", "#AD1457", "#5D4037",
+    "#3949AB", "#6D8B00", "#7B1FA2", "#00695C",
+];
+
+/// Struct that represents the colors of the nicknames shown in the conversations. Each nickname
+/// has a color taken from a hash of its name, which is the same in every session, unless the
+/// user chose another one. The chosen colors are saved in a file with a `nickname=color` line for
+/// each nickname, where the color is its position in the palette.
+/// # Fields
+/// * `chosen`: The position in the palette of the color chosen for each nickname.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NickColors {
+    chosen: HashMap<String, usize>,
+}
+
+impl NickColors {
+    /// Loads the colors saved in the file, or none if it can't be read.
+    /// The invalid lines are skipped.
+    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+        let content = fs::read_to_string(path).unwrap_or_default();
+        let chosen = content
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .filter_map(|(nickname, color)| {
+                let color = color.trim().parse::<usize>().ok()?;
+                (color < PALETTE.len()).then(|| (nickname.trim().to_lowercase(), color))
+            })
+            .collect();
+        Self { chosen }
+    }
+
+    /// Saves the colors chosen to the file.
+    /// # Errors
+    /// ClientError::FileError if the file can't be written.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ClientError> {
+        let mut lines = self
+            .chosen
+            .iter()
+            .map(|(nickname, color)| format!("{}={}\n", nickname, color))
+            .collect::<Vec<_>>();
+        lines.sort();
+        fs::write(path, lines.concat()).map_err(|_| ClientError::FileError)
+    }
+
+    /// Returns the color of a nickname, ignoring the case of the nickname.
+    pub fn color(&self, nickname: &str) -> &'static str {
+        PALETTE[self.position(nickname)]
+    }
+
+    /// Changes the color of a nickname to the next one of the palette.
+    pub fn next_color(&mut self, nickname: &str) {
+        let next = (self.position(nickname) + 1) % PALETTE.len();
+        self.chosen.insert(nickname.to_lowercase(), next);
+    }
+
+    /// Returns the position in the palette of the color of a nickname.
+    fn position(&self, nickname: &str) -> usize {
+        let nickname = nickname.to_lowercase();
+        match self.chosen.get(&nickname) {
+            Some(color) => *color,
+            None => (stable_hash(&nickname) % PALETTE.len() as u64) as usize,
+        }
+    }
+}
+
+/// Returns the FNV-1a hash of the text, which doesn't change between sessions or versions.
+fn stable_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod nick_colors_tests {
+    use super::{NickColors, PALETTE};
+
+    #[test]
+    fn test_nicknames_have_the_same_color_in_every_session() {
+        let colors = NickColors::default();
+        assert_eq!(colors.color("alice"), colors.color("Alice"));
+        assert_eq!(colors.color("alice"), NickColors::default().color("alice"));
+        assert!(PALETTE.contains(&colors.color("bob")));
+        let different = ["alice", "bob", "carol", "dave", "erin"]
+            .iter()
+            .map(|nickname| colors.color(nickname))
+            .collect::<std::collections::HashSet<_>>();
+        assert!(different.len() > 1);
+    }
+
+    #[test]
+    fn test_chosen_colors_are_saved_and_loaded() {
+        let path = std::env::temp_dir().join(format!("nick_colors_{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut colors = NickColors::load(&path);
+        let before = colors.color("bob");
+        colors.next_color("Bob");
+        assert_ne!(colors.color("bob"), before);
+        colors.save(&path).unwrap();
+
+        let loaded = NickColors::load(&path);
+        assert_eq!(loaded, colors);
+        assert_eq!(loaded.color("bob"), colors.color("bob"));
+
+        std::fs::write(&path, "bob=99\ncarol\nerin=x\n").unwrap();
+        assert_eq!(NickColors::load(&path), NickColors::default());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,324 @@
+use std::fs;
+use std::path::Path;
+
+use model::client_errors::ClientError;
+
+use crate::dcc_commands::transfer::RECEIVED_FILES_DIRECTORY;
+
+/// The file where the preferences of the interface are saved.
+pub const PREFERENCES_PATH: &str = "client/preferences.conf";
+/// The font size used if the preferences don't have one.
+pub const DEFAULT_FONT_SIZE: u32 = 12;
+/// The smallest font size that can be chosen.
+pub const MIN_FONT_SIZE: u32 = 8;
+/// The biggest font size that can be chosen.
+pub const MAX_FONT_SIZE: u32 = 24;
+/// The biggest speed limit of the DCC transfers that can be chosen, in KB/s.
+pub const MAX_DCC_SPEED_LIMIT: u32 = 100000;
+/// The files sent at the same time through a DCC connection if the preferences don't have a limit.
+pub const DEFAULT_DCC_MAX_TRANSFERS: u32 = 2;
+/// The most files that can be chosen to be sent at the same time through a DCC connection.
+pub const MAX_DCC_MAX_TRANSFERS: u32 = 10;
+/// The biggest maximum size of the files received through DCC that can be chosen, in MB.
+pub const MAX_DCC_MAX_FILE_SIZE: u32 = 1000000;
+/// The extensions of the files declined when they are sent through DCC if the preferences don't have others.
+pub const DEFAULT_DCC_BLOCKED_EXTENSIONS: &str = "exe,bat,cmd,scr,msi,vbs";
+
+/// The themes of the interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Theme {
+    #[default]
+    Light,
+    Dark,
+}
+
+impl Theme {
+    /// Returns the name of the theme saved in the preferences.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+        }
+    }
+
+    /// Returns the theme with the name, or None if there is no theme with it.
+    pub fn from_name(name: &str) -> Option<Theme> {
+        match name {
+            "light" => Some(Theme::Light),
+            "dark" => Some(Theme::Dark),
+            _ => None,
+        }
+    }
+}
+
+/// What is done with a file received through DCC when the download folder already has a file with its name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CollisionPolicy {
+    /// The file is saved with a number after its name, like `photo (1).jpg`.
+    #[default]
+    Rename,
+    /// The file replaces the one with its name.
+    Overwrite,
+    /// The user chooses to rename or overwrite the file when accepting it.
+    Ask,
+}
+
+impl CollisionPolicy {
+    /// Returns the name of the policy saved in the preferences.
+    pub fn as_str(&self) -> &str {
+        match self {
+            CollisionPolicy::Rename => "rename",
+            CollisionPolicy::Overwrite => "overwrite",
+            CollisionPolicy::Ask => "ask",
+        }
+    }
+
+    /// Returns the policy with the name, or None if there is no policy with it.
+    pub fn from_name(name: &str) -> Option<CollisionPolicy> {
+        match name {
+            "rename" => Some(CollisionPolicy::Rename),
+            "overwrite" => Some(CollisionPolicy::Overwrite),
+            "ask" => Some(CollisionPolicy::Ask),
+            _ => None,
+        }
+    }
+}
+
+/// Struct that represents the preferences of the interface, saved in a file with a
+/// `key=value` line for each preference, like `theme=dark`.
+/// # Fields
+/// * `theme`: The theme of the interface.
+/// * `font_size`: The size of the font, in points.
+/// * `dcc_speed_limit`: The most KB/s each DCC transfer sends or receives, or 0 if they have no limit.
+/// * `dcc_max_transfers`: The most files sent at the same time through a DCC connection, the rest wait
+///   in its queue.
+/// * `download_directory`: The folder where the files received through DCC are saved.
+/// * `collision_policy`: What is done with a received file when the download folder already has one with its name.
+/// * `dcc_max_file_size`: The most MB of the files received through DCC, or 0 if they have no limit.
+/// * `dcc_blocked_extensions`: The extensions of the files declined when they are sent through DCC, separated
+///   by commas.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preferences {
+    pub theme: Theme,
+    pub font_size: u32,
+    pub dcc_speed_limit: u32,
+    pub dcc_max_transfers: u32,
+    pub download_directory: String,
+    pub collision_policy: CollisionPolicy,
+    pub dcc_max_file_size: u32,
+    pub dcc_blocked_extensions: String,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            theme: Theme::default(),
+            font_size: DEFAULT_FONT_SIZE,
+            dcc_speed_limit: 0,
+            dcc_max_transfers: DEFAULT_DCC_MAX_TRANSFERS,
+            download_directory: RECEIVED_FILES_DIRECTORY.to_owned(),
+            collision_policy: CollisionPolicy::default(),
+            dcc_max_file_size: 0,
+            dcc_blocked_extensions: DEFAULT_DCC_BLOCKED_EXTENSIONS.to_owned(),
+        }
+    }
+}
+
+impl Preferences {
+    /// Parses the preferences from the content of the file. The missing or invalid preferences
+    /// have their default values, and the font size and the DCC limits are kept between the smallest
+    /// and the biggest ones.
+    pub fn parse(content: &str) -> Self {
+        let mut preferences = Self::default();
+        for line in content.lines() {
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => continue,
+            };
+            match key {
+                "theme" => {
+                    if let Some(theme) = Theme::from_name(value) {
+                        preferences.theme = theme;
+                    }
+                }
+                "font_size" => {
+                    if let Ok(size) = value.parse::<u32>() {
+                        preferences.font_size = size.clamp(MIN_FONT_SIZE, MAX_FONT_SIZE);
+                    }
+                }
+                "dcc_speed_limit" => {
+                    if let Ok(limit) = value.parse::<u32>() {
+                        preferences.dcc_speed_limit = limit.min(MAX_DCC_SPEED_LIMIT);
+                    }
+                }
+                "dcc_max_transfers" => {
+                    if let Ok(transfers) = value.parse::<u32>() {
+                        preferences.dcc_max_transfers = transfers.clamp(1, MAX_DCC_MAX_TRANSFERS);
+                    }
+                }
+                "download_directory" if !value.is_empty() => {
+                    preferences.download_directory = value.to_owned();
+                }
+                "collision_policy" => {
+                    if let Some(policy) = CollisionPolicy::from_name(value) {
+                        preferences.collision_policy = policy;
+                    }
+                }
+                "dcc_max_file_size" => {
+                    if let Ok(size) = value.parse::<u32>() {
+                        preferences.dcc_max_file_size = size.min(MAX_DCC_MAX_FILE_SIZE);
+                    }
+                }
+                "dcc_blocked_extensions" => {
+                    preferences.dcc_blocked_extensions = value.to_owned();
+                }
+                _ => (),
+            }
+        }
+        preferences
+    }
+
+    /// Loads the preferences saved in the file, or the default ones if it can't be read.
+    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+        Self::parse(&fs::read_to_string(path).unwrap_or_default())
+    }
+
+    /// Saves the preferences to the file.
+    /// # Errors
+    /// ClientError::FileError if the file can't be written.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ClientError> {
+        let content = format!(
+            "theme={}\nfont_size={}\ndcc_speed_limit={}\ndcc_max_transfers={}\ndownload_directory={}\ncollision_policy={}\ndcc_max_file_size={}\ndcc_blocked_extensions={}\n",
+            self.theme.as_str(),
+            self.font_size,
+            self.dcc_speed_limit,
+            self.dcc_max_transfers,
+            self.download_directory,
+            self.collision_policy.as_str(),
+            self.dcc_max_file_size,
+            self.dcc_blocked_extensions
+        );
+        fs::write(path, content).map_err(|_| ClientError::FileError)
+    }
+
+    /// Returns the speed limit of the DCC transfers in bytes per second, or None if they have no limit.
+    pub fn dcc_speed_limit_bytes(&self) -> Option<u64> {
+        match self.dcc_speed_limit {
+            0 => None,
+            limit => Some(limit as u64 * 1024),
+        }
+    }
+
+    /// Returns the maximum size of the files received through DCC in bytes, or None if they have no limit.
+    pub fn dcc_max_file_size_bytes(&self) -> Option<u64> {
+        match self.dcc_max_file_size {
+            0 => None,
+            size => Some(size as u64 * 1024 * 1024),
+        }
+    }
+
+    /// Returns the extensions of the files declined when they are sent through DCC, in lowercase and
+    /// without the dot.
+    pub fn dcc_blocked_extensions(&self) -> Vec<String> {
+        self.dcc_blocked_extensions
+            .split(',')
+            .map(|extension| extension.trim().trim_start_matches('.').to_lowercase())
+            .filter(|extension| !extension.is_empty())
+            .collect()
+    }
+
+    /// Returns the stylesheet that sets the font size of the whole interface.
+    pub fn font_css(&self) -> String {
+        format!("* {{ font-size: {}pt; }}", self.font_size)
+    }
+}
+
+#[cfg(test)]
+mod preferences_tests {
+    use super::{
+        CollisionPolicy, Preferences, Theme, DEFAULT_DCC_MAX_TRANSFERS, DEFAULT_FONT_SIZE,
+        MAX_DCC_MAX_FILE_SIZE, MAX_DCC_MAX_TRANSFERS, MAX_DCC_SPEED_LIMIT, MAX_FONT_SIZE,
+    };
+    use crate::dcc_commands::transfer::RECEIVED_FILES_DIRECTORY;
+
+    #[test]
+    fn test_preferences_are_saved_and_loaded() {
+        let path = std::env::temp_dir().join(format!("preferences_{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(Preferences::load(&path), Preferences::default());
+
+        let preferences = Preferences {
+            theme: Theme::Dark,
+            font_size: 16,
+            dcc_speed_limit: 512,
+            dcc_max_transfers: 4,
+            download_directory: "/home/alice/Downloads".to_owned(),
+            collision_policy: CollisionPolicy::Ask,
+            dcc_max_file_size: 100,
+            dcc_blocked_extensions: "exe, .SH".to_owned(),
+        };
+        preferences.save(&path).unwrap();
+        assert_eq!(Preferences::load(&path), preferences);
+        assert_eq!(preferences.font_css(), "* { font-size: 16pt; }");
+        assert_eq!(preferences.dcc_speed_limit_bytes(), Some(512 * 1024));
+        assert_eq!(Preferences::default().dcc_speed_limit_bytes(), None);
+        assert_eq!(
+            preferences.dcc_max_file_size_bytes(),
+            Some(100 * 1024 * 1024)
+        );
+        assert_eq!(Preferences::default().dcc_max_file_size_bytes(), None);
+        assert_eq!(preferences.dcc_blocked_extensions(), vec!["exe", "sh"]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_preferences_have_the_default_values() {
+        let preferences = Preferences::parse("theme=blue\nfont_size=big\nunknown\n");
+        assert_eq!(preferences, Preferences::default());
+        assert_eq!(preferences.font_size, DEFAULT_FONT_SIZE);
+
+        let preferences = Preferences::parse("font_size = 100\ntheme = dark\ndcc_speed_limit=-5");
+        assert_eq!(preferences.font_size, MAX_FONT_SIZE);
+        assert_eq!(preferences.theme, Theme::Dark);
+        assert_eq!(preferences.dcc_speed_limit, 0);
+
+        let preferences = Preferences::parse("dcc_speed_limit=99999999\ndcc_max_transfers=0");
+        assert_eq!(preferences.dcc_speed_limit, MAX_DCC_SPEED_LIMIT);
+        assert_eq!(preferences.dcc_max_transfers, 1);
+
+        let preferences = Preferences::parse("dcc_max_transfers=50");
+        assert_eq!(preferences.dcc_max_transfers, MAX_DCC_MAX_TRANSFERS);
+        assert_eq!(
+            Preferences::default().dcc_max_transfers,
+            DEFAULT_DCC_MAX_TRANSFERS
+        );
+    }
+
+    #[test]
+    fn test_download_preferences_keep_the_default_folder_and_policy_if_invalid() {
+        let preferences = Preferences::parse("download_directory=\ncollision_policy=delete\n");
+        assert_eq!(preferences.download_directory, RECEIVED_FILES_DIRECTORY);
+        assert_eq!(preferences.collision_policy, CollisionPolicy::Rename);
+
+        let preferences = Preferences::parse(
+            "download_directory = /home/alice/My Downloads\ncollision_policy=overwrite",
+        );
+        assert_eq!(preferences.download_directory, "/home/alice/My Downloads");
+        assert_eq!(preferences.collision_policy, CollisionPolicy::Overwrite);
+    }
+
+    #[test]
+    fn test_blocked_extensions_can_be_left_empty() {
+        let preferences = Preferences::parse("dcc_max_file_size=99999999\n");
+        assert_eq!(preferences.dcc_max_file_size, MAX_DCC_MAX_FILE_SIZE);
+        assert!(preferences
+            .dcc_blocked_extensions()
+            .contains(&"exe".to_owned()));
+
+        let preferences = Preferences::parse("dcc_blocked_extensions=\n");
+        assert!(preferences.dcc_blocked_extensions().is_empty());
+        let preferences = Preferences::parse("dcc_blocked_extensions= , ,");
+        assert!(preferences.dcc_blocked_extensions().is_empty());
+    }
+}
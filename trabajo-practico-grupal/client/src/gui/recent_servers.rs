@@ -0,0 +1,191 @@
+use std::fs;
+use std::path::Path;
+
+use model::client_errors::ClientError;
+
+/// The file where the recent servers are saved.
+pub const RECENT_SERVERS_PATH: &str = "client/servers.conf";
+/// The number of recent servers that are saved.
+pub const MAX_RECENT_SERVERS: usize = 5;
+/// The separator of the fields of a server in the file.
+const FIELD_SEPARATOR: char = '\t';
+
+/// Struct that represents the settings used to connect to a server.
+/// # Fields
+/// * `host`: The host of the server.
+/// * `port`: The port of the server.
+/// * `tls`: True if the connection uses TLS.
+/// * `nickname`: The nickname used to log in, empty if it isn't remembered.
+/// * `password`: The password used to log in, empty if it isn't remembered.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServerSettings {
+    pub host: String,
+    pub port: String,
+    pub tls: bool,
+    pub nickname: String,
+    pub password: String,
+}
+
+impl ServerSettings {
+    /// Returns the `host:port` address of the server.
+    pub fn address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    /// Returns the name of the server shown in the list of recent servers, like `bob@localhost:8080`.
+    pub fn label(&self) -> String {
+        match self.nickname.is_empty() {
+            true => self.address(),
+            false => format!("{}@{}", self.nickname, self.address()),
+        }
+    }
+
+    /// Returns the settings without the nickname and the password, used when the user doesn't want
+    /// them to be remembered.
+    pub fn forget_credentials(&self) -> Self {
+        Self {
+            nickname: String::new(),
+            password: String::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Parses the settings from a line of the file, or None if the line is invalid.
+    fn from_line(line: &str) -> Option<Self> {
+        let fields = line.split(FIELD_SEPARATOR).collect::<Vec<_>>();
+        if fields.len() != 5 || fields[0].is_empty() || fields[1].parse::<u16>().is_err() {
+            return None;
+        }
+        Some(Self {
+            host: fields[0].to_string(),
+            port: fields[1].to_string(),
+            tls: fields[2] == "tls",
+            nickname: fields[3].to_string(),
+            password: fields[4].to_string(),
+        })
+    }
+
+    /// Returns the line of the file with the settings.
+    fn to_line(&self) -> String {
+        let tls = match self.tls {
+            true => "tls",
+            false => "plain",
+        };
+        [
+            self.host.as_str(),
+            self.port.as_str(),
+            tls,
+            self.nickname.as_str(),
+            self.password.as_str(),
+        ]
+        .join(&FIELD_SEPARATOR.to_string())
+    }
+}
+
+/// Struct that represents the servers the client connected to, the most recent first. They are
+/// saved in a file with a line for each server. The passwords are saved as they are typed, so
+/// they are only saved if the user asks to be remembered.
+/// # Fields
+/// * `servers`: The recent servers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecentServers {
+    servers: Vec<ServerSettings>,
+}
+
+impl RecentServers {
+    /// Loads the recent servers saved in the file, or none if it can't be read.
+    /// The invalid lines are skipped.
+    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+        let content = fs::read_to_string(path).unwrap_or_default();
+        let servers = content
+            .lines()
+            .filter_map(ServerSettings::from_line)
+            .take(MAX_RECENT_SERVERS)
+            .collect();
+        Self { servers }
+    }
+
+    /// Saves the recent servers to the file.
+    /// # Errors
+    /// ClientError::FileError if the file can't be written.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ClientError> {
+        let content = self
+            .servers
+            .iter()
+            .map(|server| server.to_line() + "\n")
+            .collect::<String>();
+        fs::write(path, content).map_err(|_| ClientError::FileError)
+    }
+
+    /// Adds a server as the most recent one. If it was already saved, the old settings of
+    /// the server are replaced. Only the most recent servers are kept.
+    pub fn add(&mut self, server: ServerSettings) {
+        self.servers
+            .retain(|saved| saved.address() != server.address());
+        self.servers.insert(0, server);
+        self.servers.truncate(MAX_RECENT_SERVERS);
+    }
+
+    /// Returns the recent servers, the most recent first.
+    pub fn servers(&self) -> &[ServerSettings] {
+        &self.servers
+    }
+}
+
+#[cfg(test)]
+mod recent_servers_tests {
+    use super::{RecentServers, ServerSettings, MAX_RECENT_SERVERS};
+
+    fn server(host: &str, port: &str, nickname: &str) -> ServerSettings {
+        ServerSettings {
+            host: host.to_string(),
+            port: port.to_string(),
+            tls: false,
+            nickname: nickname.to_string(),
+            password: "secret".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_recent_servers_are_saved_and_loaded_most_recent_first() {
+        let path = std::env::temp_dir().join(format!("servers_{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut recent = RecentServers::load(&path);
+        assert!(recent.servers().is_empty());
+
+        recent.add(server("localhost", "8080", "bob"));
+        recent.add(server("irc.example.org", "6667", "").forget_credentials());
+        recent.add(server("localhost", "8080", "alice"));
+        recent.save(&path).unwrap();
+
+        let loaded = RecentServers::load(&path);
+        assert_eq!(loaded, recent);
+        let labels = loaded
+            .servers()
+            .iter()
+            .map(|server| server.label())
+            .collect::<Vec<_>>();
+        assert_eq!(labels, vec!["alice@localhost:8080", "irc.example.org:6667"]);
+        assert_eq!(loaded.servers()[1].password, "");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_only_the_most_recent_valid_servers_are_kept() {
+        let mut recent = RecentServers::default();
+        for port in 1..=MAX_RECENT_SERVERS + 2 {
+            recent.add(server("localhost", &port.to_string(), "bob"));
+        }
+        assert_eq!(recent.servers().len(), MAX_RECENT_SERVERS);
+        assert_eq!(
+            recent.servers()[0].port,
+            (MAX_RECENT_SERVERS + 2).to_string()
+        );
+
+        let path = std::env::temp_dir().join(format!("invalid_servers_{}", std::process::id()));
+        std::fs::write(&path, "localhost\tnot a port\tplain\t\t\nonly a host\n").unwrap();
+        assert!(RecentServers::load(&path).servers().is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,23 +1,34 @@
 use gtk::{glib, prelude::*};
+use model::consts::{OPERATOR_FLAG, VOICE_FLAG};
+use model::ctcp::render_text;
 use model::responses::dcc::DccResponse;
 use model::responses::errors::ErrorResponse;
 use model::responses::message::MessageResponse;
-use model::responses::replies::CommandResponse;
+use model::responses::replies::{CommandResponse, ConnectionState};
 use model::responses::response::Response;
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::rc::Rc;
 use std::sync::mpsc::Sender;
 use std::sync::mpsc::SyncSender;
 use std::sync::Arc;
 use std::sync::RwLock;
 
+use crate::dcc_commands::speed::throughput_text;
 use crate::gui::components::channel_actions::ChannelActions;
 use crate::gui::components::channel_info::ChannelInfo;
+use crate::gui::components::channel_settings_dialog::ChannelSettingsDialog;
 use crate::gui::components::chats_container::ChatsContainer;
+use crate::gui::components::conversation_search::ConversationSearch;
 use crate::gui::components::dcc_feature::DCCFeature;
+use crate::gui::components::emoji_picker::EmojiPicker;
+use crate::gui::components::member_list::MemberList;
 use crate::gui::components::names_list::NamesList;
 use crate::gui::components::user_actions::UserActions;
 use crate::gui::components::user_mode::UserMode;
 use crate::gui::components::who_action::SearchWho;
+use crate::gui::nick_colors::{NickColors, NICK_COLORS_PATH};
+use crate::lag::status_text;
 
 pub struct ChatsScreen {
     tx: Sender<String>,
@@ -34,13 +45,20 @@ impl ChatsScreen {
         arc_dcc_interface_communication: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
     ) {
         let mut names_list = NamesList::new(builder, self.tx.clone());
-        let chats_container = ChatsContainer::new(builder);
+        let nick_colors = Rc::new(RefCell::new(NickColors::load(NICK_COLORS_PATH)));
+        let chats_container = ChatsContainer::new(builder, nick_colors.clone());
         let channel_actions = ChannelActions::new(self.tx.clone(), builder);
         let mut search_who = SearchWho::new(builder, self.tx.clone());
         let user_actions = UserActions::new(self.tx.clone());
         let dcc_feature =
             DCCFeature::new(builder, self.tx.clone(), arc_dcc_interface_communication);
         let mut channel_info = ChannelInfo::new(builder, self.tx.clone());
+        let member_list = MemberList::new(builder, self.tx.clone(), nick_colors);
+        member_list.build();
+        let settings_dialog = ChannelSettingsDialog::new(builder, self.tx.clone());
+        settings_dialog.build(builder);
+        EmojiPicker::new(builder).build(builder);
+        ConversationSearch::new(builder).build(builder);
         let mut user_mode = UserMode::new(builder, self.tx.clone());
         let ip_port_dcc_modal_file = builder
             .object::<gtk::Window>("ip_port_dcc_modal_file")
@@ -49,6 +67,7 @@ impl ChatsScreen {
         notification_modal.connect_delete_event(move |_win, _| _win.hide_on_delete());
         let notification_label = builder.object::<gtk::Label>("notification_label").unwrap();
         let user_nick: gtk::Label = builder.object("user_nick").unwrap();
+        let connection_status: gtk::Label = builder.object("connection_status").unwrap();
         let notification_receiver: gtk::Label = builder.object("notification_receiver").unwrap();
         let error_modal = builder.object::<gtk::Window>("error_modal").unwrap();
         error_modal.connect_delete_event(move |_win, _| _win.hide_on_delete());
@@ -72,6 +91,9 @@ impl ChatsScreen {
         user_mode.build(builder);
         search_who.build();
 
+        // the lists of crowded channels arrive in several replies, until the end of the list
+        let mut pending_names: HashMap<String, Vec<String>> = HashMap::new();
+        let mut pending_bans: HashMap<String, Vec<String>> = HashMap::new();
         rx.attach(None, move |message| {
             match message {
                 Response::ErrorResponse { response } => match response {
@@ -98,6 +120,12 @@ impl ChatsScreen {
                         error_label.set_text(&format!("Channel {channel} is invite only"));
                         error_modal.show();
                     }
+                    ErrorResponse::LinkChannel { channel, target } => {
+                        error_label.set_text(&format!(
+                            "Channel {channel} is not available, you joined {target} instead"
+                        ));
+                        error_modal.show();
+                    }
                     ErrorResponse::NotOnChannel { channel } => {
                         channel_actions.show();
                         error_label.set_text(&format!("You are not on channel {channel}"));
@@ -135,18 +163,30 @@ impl ChatsScreen {
                 },
                 Response::CommandResponse { response } => match response {
                     CommandResponse::Names { channel, names } => {
+                        let pending = pending_names.entry(channel.clone()).or_default();
+                        pending.extend(names);
+                        let names = pending.clone();
+                        member_list.update_names(&channel, &names);
+                        let names = names
+                            .into_iter()
+                            .map(|name| name.trim_start_matches(VOICE_FLAG).to_string())
+                            .collect::<Vec<_>>();
                         channel_info.update_clients(names.clone(), channel.clone());
+                        chats_container.update_members(channel.clone(), names.clone());
                         names_list.update_clients(names, channel);
                     }
                     CommandResponse::EndNames => {
+                        pending_names.clear();
                         names_list.add_names_to_list();
                         channel_info.add_channels_to_stack();
                     }
                     CommandResponse::Topic { channel, topic } => {
+                        settings_dialog.update_topic(&channel, &topic);
                         channel_info.update_topic(channel, topic);
                     }
                     CommandResponse::ListStart => {}
                     CommandResponse::List { channel, topic } => {
+                        settings_dialog.update_topic(&channel, &topic);
                         channel_info.update_topic(channel, topic);
                     }
                     CommandResponse::ListEnd => {
@@ -159,7 +199,13 @@ impl ChatsScreen {
                             "User conversations",
                         );
                     }
-                    CommandResponse::ChannelMode { channel, modes } => {
+                    CommandResponse::ChannelMode {
+                        channel,
+                        mode_string,
+                        modes,
+                    } => {
+                        member_list.update_channel_modes(&channel, &modes);
+                        settings_dialog.update_channel_modes(&channel, &mode_string, &modes);
                         channel_info.update_channel_modes(channel, modes);
                     }
                     CommandResponse::UserMode { user: _, modes } => {
@@ -170,6 +216,21 @@ impl ChatsScreen {
                         notification_modal.show();
                         oper_modal.close();
                     }
+                    CommandResponse::Reconnecting { attempt, delay } => {
+                        connection_status.set_text(&status_text(ConnectionState::Reconnecting, 0));
+                        notification_label.set_text(&format!(
+                            "Connection lost, reconnecting in {delay} seconds (attempt {attempt})"
+                        ));
+                        notification_modal.show();
+                    }
+                    CommandResponse::Reconnected => {
+                        connection_status.set_text(&status_text(ConnectionState::Connected, 0));
+                        notification_label.set_text("Reconnected to the server");
+                        notification_modal.show();
+                    }
+                    CommandResponse::ConnectionStatus { state, lag } => {
+                        connection_status.set_text(&status_text(state, lag));
+                    }
                     CommandResponse::WhoIsUser {
                         nickname,
                         username,
@@ -194,17 +255,25 @@ impl ChatsScreen {
                         search_who.show_search_who_is_results();
                     }
                     CommandResponse::BanList { channel, ban_list } => {
-                        channel_info.update_banned_clients(ban_list, channel);
+                        let banned = pending_bans.entry(channel.clone()).or_default();
+                        banned.extend(ban_list.into_iter().map(|ban| ban.nickname));
+                        channel_info.update_banned_clients(banned.clone(), channel);
                     }
                     CommandResponse::EndBanList => {
+                        pending_bans.clear();
                         channel_info.show_banned_list();
                     }
+                    CommandResponse::DeliveryReceipt { nickname, status } => {
+                        chats_container.add_delivery_receipt(nickname, status);
+                    }
                     _ => (),
                 },
                 Response::MessageResponse { response } => match response {
                     MessageResponse::UserPrivMsg { message, sender } => {
                         println!("UserPrivMsg: {message}");
                         println!("Sender: {sender}");
+                        names_list.record_activity(&sender, &message, &user_nick.text());
+                        let message = render_text(&sender, &message);
                         chats_container.add_message_received(sender, message, "User conversations");
                     }
                     MessageResponse::ChannelPrivMsg {
@@ -212,6 +281,25 @@ impl ChatsScreen {
                         message,
                         sender,
                     } => {
+                        // the messages to the operators of a channel are shown in the channel
+                        let channel = channel.trim_start_matches(OPERATOR_FLAG).to_string();
+                        names_list.record_activity(&channel, &message, &user_nick.text());
+                        let message = render_text(&sender, &message);
+                        chats_container.add_message_channel_received(channel, sender, message);
+                    }
+                    MessageResponse::UserNotice { sender, message } => {
+                        names_list.record_activity(&sender, &message, &user_nick.text());
+                        let message = render_text(&sender, &message);
+                        chats_container.add_message_received(sender, message, "User conversations");
+                    }
+                    MessageResponse::ChannelNotice {
+                        channel,
+                        sender,
+                        message,
+                    } => {
+                        let channel = channel.trim_start_matches(OPERATOR_FLAG).to_string();
+                        names_list.record_activity(&channel, &message, &user_nick.text());
+                        let message = render_text(&sender, &message);
                         chats_container.add_message_channel_received(channel, sender, message);
                     }
                     MessageResponse::KickMsg { message } => {
@@ -224,6 +312,28 @@ impl ChatsScreen {
                         notification_label.set_text(&message);
                         notification_modal.set_visible(true);
                     }
+                    MessageResponse::VoiceMsg { message } => {
+                        notification_receiver.set_text(user_nick.text().as_str());
+                        notification_label.set_text(&message);
+                        notification_modal.set_visible(true);
+                    }
+                    MessageResponse::ChannelModeMsg {
+                        channel,
+                        sender,
+                        modes,
+                    } => {
+                        member_list.apply_mode_change(&channel, &modes);
+                        chats_container.add_event(MessageResponse::ChannelModeMsg {
+                            channel,
+                            sender,
+                            modes,
+                        });
+                    }
+                    event @ (MessageResponse::JoinMsg { .. }
+                    | MessageResponse::PartMsg { .. }
+                    | MessageResponse::QuitMsg { .. }) => {
+                        chats_container.add_event(event);
+                    }
                 },
                 Response::DccResponse { response } => match response {
                     DccResponse::Accepted { sender } => {
@@ -231,6 +341,10 @@ impl ChatsScreen {
                         dcc_feature.create_dcc_box(sender.clone());
                         dcc_feature.remove_stack_box(sender, "Loadings");
                     }
+                    DccResponse::Secured { sender } => {
+                        println!("DCC secured");
+                        dcc_feature.set_secure_conversation(sender);
+                    }
                     DccResponse::Pending { sender: _ } => {
                         println!("DCC pending");
                         //dcc_feature.create_loading_screen(sender);
@@ -242,6 +356,7 @@ impl ChatsScreen {
                     }
                     DccResponse::ChatMessage { sender, message } => {
                         println!("DCC message");
+                        dcc_feature.set_typing(sender.clone(), false);
                         chats_container.add_message_received(sender, message, "DCC conversations");
                     }
                     DccResponse::CloseConnection { sender } => {
@@ -253,9 +368,12 @@ impl ChatsScreen {
                         sender,
                         file_name,
                         progress,
+                        bytes_per_second,
+                        seconds_left,
                     } => {
                         //println!("DCC progress");
-                        dcc_feature.update_progress_bar(sender, file_name, progress);
+                        let throughput = throughput_text(bytes_per_second, seconds_left);
+                        dcc_feature.update_progress_bar(sender, file_name, progress, &throughput);
                     }
                     DccResponse::TransferRequest {
                         sender,
@@ -272,6 +390,35 @@ impl ChatsScreen {
                         println!("DCC transfer declined");
                         dcc_feature.set_transfer_declined_message(sender, file_name);
                     }
+                    DccResponse::TransferFailed { sender, file_name } => {
+                        println!("DCC transfer failed");
+                        dcc_feature.set_transfer_failed_message(sender, file_name);
+                    }
+                    DccResponse::TransferQueue {
+                        sender,
+                        file_name,
+                        state,
+                    } => {
+                        dcc_feature.set_transfer_queue_state(sender, file_name, state);
+                    }
+                    DccResponse::TransferRestored {
+                        sender,
+                        file_name,
+                        file_size,
+                        progress,
+                        received,
+                    } => {
+                        dcc_feature.add_restored_transfer(
+                            sender, file_name, file_size, progress, received,
+                        );
+                    }
+                    DccResponse::FileReceived {
+                        sender,
+                        file_name,
+                        path,
+                    } => {
+                        dcc_feature.set_file_received_message(sender, file_name, path);
+                    }
                     DccResponse::TransferPaused { sender, file_name } => {
                         println!("DCC transfer paused");
                         dcc_feature.set_transfer_paused_message(sender, file_name);
@@ -291,6 +438,47 @@ impl ChatsScreen {
                         notification_modal.set_visible(true);
                         dcc_feature.remove_stack_box(sender, "Loadings");
                     }
+                    DccResponse::RequestExpired { sender } => {
+                        println!("DCC request expired");
+                        send_button.set_sensitive(true);
+                        message_entry.set_sensitive(true);
+                        chats_container.set_no_channel_selected_screen();
+                        notification_receiver.set_text(&sender);
+                        notification_label.set_text(&format!(
+                            "DCC connection with {sender} was not answered. Files can be sent through the server."
+                        ));
+                        notification_modal.set_visible(true);
+                        dcc_feature.remove_stack_box(sender.clone(), "Loadings");
+                        dcc_feature.create_relayed_box(sender);
+                    }
+                    DccResponse::RoomMembers {
+                        room,
+                        host,
+                        members,
+                    } => {
+                        println!("DCC room members");
+                        dcc_feature.set_room_members(room, host, members);
+                    }
+                    DccResponse::RoomMessage {
+                        room,
+                        sender,
+                        message,
+                    } => {
+                        chats_container.add_room_message_received(room, sender, message);
+                    }
+                    DccResponse::RoomClosed { room } => {
+                        println!("DCC room closed");
+                        if dcc_feature.remove_room(room) {
+                            chats_container.set_no_channel_selected_screen();
+                        }
+                    }
+                    DccResponse::Typing { sender, typing } => {
+                        dcc_feature.set_typing(sender, typing);
+                    }
+                    DccResponse::Relayed { sender } => {
+                        println!("DCC relayed");
+                        dcc_feature.create_relayed_box(sender);
+                    }
                     DccResponse::ErrorResponse { description } => {
                         error_label.set_text(&description);
                         error_modal.show();
@@ -307,8 +495,9 @@ impl ChatsScreen {
                         ip_port_dcc_modal_file.hide();
                     }
                     DccResponse::ChatAddressErrorResponse { sender } => {
-                        dcc_feature.remove_stack_box(sender, "Loadings");
-                        error_label.set_text("Error selecting address");
+                        dcc_feature.remove_stack_box(sender.clone(), "Loadings");
+                        dcc_feature.create_relayed_box(sender);
+                        error_label.set_text("Error selecting address. Files can be sent through the server.");
                         error_modal.show();
                     }
                     DccResponse::OngoingTransfer { sender, file_name } => {
@@ -5,9 +5,11 @@ use model::responses::replies::CommandResponse;
 use model::responses::response::Response;
 use std::sync::mpsc::Sender;
 
+use crate::gui::recent_servers::{RecentServers, ServerSettings, RECENT_SERVERS_PATH};
+
 ///Struct that represents the connection screen.
 /// This screen will be displayed when the client is not connected to the server.
-/// It will allow the user to enter the ip and port of the server and connect to it.
+/// It will allow the user to enter the host and port of the server, or pick a recent server, and connect to it.
 /// # Fields
 /// * `tx` - The sender that is used to send messages to the server
 pub struct ConnectionScreen {
@@ -23,6 +25,8 @@ impl ConnectionScreen {
     }
 
     ///Builds the connection screen, it sets the callbacks for the buttons and the receiver for the messages.
+    /// The recent servers are shown in a list that fills the connection settings, and the settings
+    /// are saved when connecting. If a nick is given, the user logs in once the client is connected.
     /// # Arguments
     /// * `builder` - The builder used to build the screen
     /// * `rx` - The receiver used to receive messages from the client that where sent by the server
@@ -30,22 +34,39 @@ impl ConnectionScreen {
         let connect_button = builder.object::<gtk::Button>("connect_button").unwrap();
         let port_entry: gtk::Entry = builder.object("port_entry").unwrap();
         let ip_entry: gtk::Entry = builder.object("ip_entry").unwrap();
+        let tls_switch: gtk::Switch = builder.object("tls_switch").unwrap();
+        let nick_entry: gtk::Entry = builder.object("nick_connection_entry").unwrap();
+        let pass_entry: gtk::Entry = builder.object("pass_connection_entry").unwrap();
+        let remember_check: gtk::CheckButton = builder.object("remember_me_check").unwrap();
         let stack: gtk::Stack = builder.object("stack").unwrap();
         let error_label: gtk::Label = builder.object("error_connection").unwrap();
 
-        let tx_clone = self.tx;
+        Self::active_recent_servers(builder);
+
+        let tx_clone = self.tx.clone();
         connect_button.connect_clicked(
-            clone!(@weak port_entry, @weak ip_entry, @weak stack, @weak error_label=> move |_| {
-                let port = port_entry.text();
-                let ip = ip_entry.text();
+            clone!(@weak port_entry, @weak ip_entry, @weak tls_switch, @weak nick_entry, @weak pass_entry, @weak remember_check, @weak error_label=> move |_| {
+                let settings = ServerSettings {
+                    host: ip_entry.text().to_string(),
+                    port: port_entry.text().to_string(),
+                    tls: tls_switch.is_active(),
+                    nickname: nick_entry.text().to_string(),
+                    password: pass_entry.text().to_string(),
+                };
 
-                if port.is_empty() || ip.is_empty() {
+                if settings.port.is_empty() || settings.host.is_empty() {
                     error_label.set_text("Please fill all the fields");
                     println!("Please fill out all fields");
                 }
+                else if settings.port.parse::<u16>().is_err() {
+                    error_label.set_text("The port must be a number");
+                }
+                else if settings.tls {
+                    error_label.set_text("TLS connections are not supported yet");
+                }
                 else {
-                    let address = format!("{ip}:{port}");
-                    match tx_clone.send(address){
+                    Self::save_recent_server(&settings, remember_check.is_active());
+                    match tx_clone.send(settings.address()){
                         Ok(_) => {},
                         Err(_) => error_label.set_text("Error sending connection info"),
                     }
@@ -53,11 +74,27 @@ impl ConnectionScreen {
             }),
         );
 
+        let nick_login: gtk::Entry = builder.object("nick_entry_login").unwrap();
+        let pass_login: gtk::Entry = builder.object("pass_entry_login").unwrap();
+        let stack_registration: gtk::Stack = builder.object("stack_registration").unwrap();
+        let tx = self.tx;
         rx.attach(None, move |message| {
             match message {
                 Response::CommandResponse {
                     response: CommandResponse::ConnectionSuccees,
-                } => stack.set_visible_child_name("Registration"),
+                } => {
+                    stack.set_visible_child_name("Registration");
+                    let nick = nick_entry.text();
+                    if !nick.is_empty() {
+                        let password = pass_entry.text();
+                        nick_login.set_text(&nick);
+                        pass_login.set_text(&password);
+                        stack_registration.set_visible_child_name("Login");
+                        if tx.send(format!("PASS {password}")).is_ok()
+                            && tx.send(format!("NICK {nick}")).is_ok()
+                        {}
+                    }
+                }
 
                 Response::ErrorResponse {
                     response: ErrorResponse::ErrorWhileConnecting,
@@ -68,4 +105,54 @@ impl ConnectionScreen {
             glib::Continue(true)
         });
     }
+
+    /// Shows the recent servers in their list, filling the connection settings with the most recent one,
+    /// and fills the settings with the server chosen from the list.
+    /// # Arguments
+    /// * `builder` - The builder used to build the screen
+    fn active_recent_servers(builder: &gtk::Builder) {
+        let recent_list: gtk::ComboBoxText = builder.object("recent_servers").unwrap();
+        let port_entry: gtk::Entry = builder.object("port_entry").unwrap();
+        let ip_entry: gtk::Entry = builder.object("ip_entry").unwrap();
+        let tls_switch: gtk::Switch = builder.object("tls_switch").unwrap();
+        let nick_entry: gtk::Entry = builder.object("nick_connection_entry").unwrap();
+        let pass_entry: gtk::Entry = builder.object("pass_connection_entry").unwrap();
+        let remember_check: gtk::CheckButton = builder.object("remember_me_check").unwrap();
+
+        let servers = RecentServers::load(RECENT_SERVERS_PATH).servers().to_vec();
+        for server in servers.iter() {
+            recent_list.append_text(&server.label());
+        }
+        let has_servers = !servers.is_empty();
+        recent_list.set_sensitive(has_servers);
+
+        recent_list.connect_changed(move |list| {
+            let server = match list.active().and_then(|index| servers.get(index as usize)) {
+                Some(server) => server,
+                None => return,
+            };
+            ip_entry.set_text(&server.host);
+            port_entry.set_text(&server.port);
+            tls_switch.set_active(server.tls);
+            nick_entry.set_text(&server.nickname);
+            pass_entry.set_text(&server.password);
+            remember_check.set_active(!server.nickname.is_empty());
+        });
+        if has_servers {
+            recent_list.set_active(Some(0));
+        }
+    }
+
+    /// Saves the server as the most recent one. The nick and the password are only saved if the
+    /// user wants to be remembered.
+    fn save_recent_server(settings: &ServerSettings, remember: bool) {
+        let mut recent = RecentServers::load(RECENT_SERVERS_PATH);
+        match remember {
+            true => recent.add(settings.clone()),
+            false => recent.add(settings.forget_credentials()),
+        }
+        if recent.save(RECENT_SERVERS_PATH).is_err() {
+            println!("Could not save the recent servers to {RECENT_SERVERS_PATH}");
+        }
+    }
 }
@@ -0,0 +1,124 @@
+/// Struct that represents the messages of a conversation that match the text searched, and the
+/// match the user is looking at.
+/// # Fields
+/// * `matches`: The positions of the messages that contain the text, in order.
+/// * `current`: The position in `matches` of the match the user is looking at.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchMatches {
+    matches: Vec<usize>,
+    current: Option<usize>,
+}
+
+impl SearchMatches {
+    /// Finds the messages that contain the text searched, ignoring the case. The match the user
+    /// was looking at stays selected if it is still a match, otherwise the newest match is
+    /// selected.
+    /// # Arguments
+    /// * `messages`: The texts of the messages of the conversation, in order.
+    /// * `query`: The text searched.
+    pub fn update(&mut self, messages: &[String], query: &str) {
+        let looking_at = self.current_message();
+        let query = query.trim().to_lowercase();
+        self.matches = match query.is_empty() {
+            true => vec![],
+            false => messages
+                .iter()
+                .enumerate()
+                .filter(|(_, message)| message.to_lowercase().contains(&query))
+                .map(|(i, _)| i)
+                .collect(),
+        };
+        self.current = looking_at
+            .and_then(|message| self.matches.iter().position(|m| *m == message))
+            .or_else(|| self.matches.len().checked_sub(1));
+    }
+
+    /// Returns the positions of the messages that match, in order.
+    pub fn matches(&self) -> &[usize] {
+        &self.matches
+    }
+
+    /// Returns the position of the message the user is looking at, if there are matches.
+    pub fn current_message(&self) -> Option<usize> {
+        self.current.map(|current| self.matches[current])
+    }
+
+    /// Selects the next match, going back to the first one after the last.
+    pub fn next(&mut self) {
+        if !self.matches.is_empty() {
+            self.current = self
+                .current
+                .map(|current| (current + 1) % self.matches.len());
+        }
+    }
+
+    /// Selects the previous match, going to the last one before the first.
+    pub fn previous(&mut self) {
+        if !self.matches.is_empty() {
+            let len = self.matches.len();
+            self.current = self.current.map(|current| (current + len - 1) % len);
+        }
+    }
+
+    /// Returns the text that tells the user which match is selected, like `2 of 5`.
+    pub fn summary(&self) -> String {
+        match self.current {
+            Some(current) => format!("{} of {}", current + 1, self.matches.len()),
+            None => "No matches".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod search_tests {
+    use super::SearchMatches;
+
+    fn messages() -> Vec<String> {
+        [
+            "hello Bob",
+            "how are you?",
+            "bob: fine",
+            "[12:00] alice: HELLO",
+        ]
+        .iter()
+        .map(|message| message.to_string())
+        .collect()
+    }
+
+    #[test]
+    fn test_matches_are_found_ignoring_the_case_starting_from_the_newest() {
+        let mut search = SearchMatches::default();
+        search.update(&messages(), "hello");
+        assert_eq!(search.matches(), &[0, 3]);
+        assert_eq!(search.current_message(), Some(3));
+        assert_eq!(search.summary(), "2 of 2");
+
+        search.update(&messages(), "nobody");
+        assert!(search.matches().is_empty());
+        assert_eq!(search.current_message(), None);
+        assert_eq!(search.summary(), "No matches");
+
+        search.update(&messages(), "  ");
+        assert!(search.matches().is_empty());
+    }
+
+    #[test]
+    fn test_moving_between_matches_wraps_around_and_survives_new_messages() {
+        let mut search = SearchMatches::default();
+        search.update(&messages(), "BOB");
+        assert_eq!(search.current_message(), Some(2));
+        search.next();
+        assert_eq!(search.current_message(), Some(0));
+        search.previous();
+        assert_eq!(search.current_message(), Some(2));
+        search.previous();
+        assert_eq!(search.current_message(), Some(0));
+
+        let mut more = messages();
+        more.push("bye bob".to_string());
+        search.update(&more, "bob");
+        assert_eq!(search.matches(), &[0, 2, 4]);
+        assert_eq!(search.current_message(), Some(0));
+        assert_eq!(search.summary(), "1 of 3");
+    }
+}
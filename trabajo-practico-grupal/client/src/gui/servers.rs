@@ -0,0 +1,107 @@
+use crate::gui::recent_servers::ServerSettings;
+
+/// The prefix of the names of the pages of the connections, followed by their number.
+const PAGE_PREFIX: &str = "server-";
+
+/// Struct that keeps the pages of the connections with the servers shown in the sidebar, in the
+/// order they were opened.
+/// # Fields
+/// * `opened`: The amount of connections opened, used to name the next page.
+/// * `pages`: The name of the page of each connection still open, with its title.
+#[derive(Debug, Default)]
+pub struct ServerPages {
+    opened: usize,
+    pages: Vec<(String, String)>,
+}
+
+impl ServerPages {
+    /// Adds the page of a new connection, returning its name and its title. The connection has
+    /// no server yet, so the title is its number.
+    pub fn open(&mut self) -> (String, String) {
+        self.opened += 1;
+        let name = format!("{}{}", PAGE_PREFIX, self.opened);
+        let title = format!("Server {}", self.opened);
+        self.pages.push((name.to_owned(), title.to_owned()));
+        (name, title)
+    }
+
+    /// Changes the title of the page to the nickname and the address of its server, like
+    /// `bob@localhost:8080`. Returns the new title, or None if the page was closed.
+    /// # Arguments
+    /// * `name`: The name of the page.
+    /// * `server`: The server the connection registered in.
+    pub fn rename(&mut self, name: &str, server: &ServerSettings) -> Option<String> {
+        let (_, title) = self.pages.iter_mut().find(|(page, _)| page == name)?;
+        *title = server.label();
+        Some(title.to_owned())
+    }
+
+    /// Removes the page of a connection the user quit. Returns true if there are other
+    /// connections open, so only the page is closed and not the window.
+    /// # Arguments
+    /// * `name`: The name of the page.
+    pub fn close(&mut self, name: &str) -> bool {
+        self.pages.retain(|(page, _)| page != name);
+        !self.pages.is_empty()
+    }
+
+    /// Returns the title of the page, or None if it was closed.
+    /// # Arguments
+    /// * `name`: The name of the page.
+    pub fn title(&self, name: &str) -> Option<&str> {
+        self.pages
+            .iter()
+            .find(|(page, _)| page == name)
+            .map(|(_, title)| title.as_str())
+    }
+}
+
+#[cfg(test)]
+mod servers_tests {
+    use crate::gui::recent_servers::ServerSettings;
+
+    use super::ServerPages;
+
+    fn server_for_test(nickname: &str, port: &str) -> ServerSettings {
+        ServerSettings {
+            host: "localhost".to_string(),
+            port: port.to_string(),
+            nickname: nickname.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_pages_are_named_in_order_and_renamed_when_registered() {
+        let mut pages = ServerPages::default();
+
+        assert_eq!(
+            pages.open(),
+            ("server-1".to_string(), "Server 1".to_string())
+        );
+        assert_eq!(
+            pages.open(),
+            ("server-2".to_string(), "Server 2".to_string())
+        );
+        assert_eq!(
+            pages.rename("server-2", &server_for_test("bob", "8081")),
+            Some("bob@localhost:8081".to_string())
+        );
+
+        assert_eq!(pages.title("server-1"), Some("Server 1"));
+        assert_eq!(pages.title("server-2"), Some("bob@localhost:8081"));
+    }
+
+    #[test]
+    fn test_window_is_closed_with_the_last_connection() {
+        let mut pages = ServerPages::default();
+        let (first, _) = pages.open();
+        let (second, _) = pages.open();
+
+        assert!(pages.close(&first));
+        assert_eq!(pages.rename(&first, &server_for_test("bob", "8080")), None);
+        assert_eq!(pages.open().0, "server-3");
+        assert!(pages.close(&second));
+        assert!(!pages.close("server-3"));
+    }
+}
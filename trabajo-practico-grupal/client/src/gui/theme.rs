@@ -0,0 +1,182 @@
+use std::cell::{Cell, RefCell};
+
+use gtk::prelude::*;
+
+use crate::gui::preferences::{CollisionPolicy, Preferences, Theme, PREFERENCES_PATH};
+
+/// The stylesheet with the styles of the messages and widgets of the interface, used by both themes.
+pub const STYLE_PATH: &str = "client/src/gui/style.scss";
+/// The stylesheet of the light theme.
+pub const LIGHT_STYLE_PATH: &str = "client/src/gui/light.scss";
+/// The stylesheet of the dark theme.
+pub const DARK_STYLE_PATH: &str = "client/src/gui/dark.scss";
+
+/// Struct that manages the styles of the interface. The stylesheets are loaded once and applied
+/// to the whole screen, so the widgets only need their style classes.
+/// # Fields
+/// * `style`: The provider of the styles used by both themes.
+/// * `light`: The provider of the light theme.
+/// * `dark`: The provider of the dark theme.
+/// * `font`: The provider of the font size.
+/// * `applied`: The theme applied to the screen, if there is one.
+/// * `preferences`: The theme and the font size chosen.
+pub struct ThemeManager {
+    style: gtk::CssProvider,
+    light: gtk::CssProvider,
+    dark: gtk::CssProvider,
+    font: gtk::CssProvider,
+    applied: Cell<Option<Theme>>,
+    preferences: RefCell<Preferences>,
+}
+
+impl ThemeManager {
+    /// Creates a new `ThemeManager`, loading the stylesheets and the saved preferences.
+    pub fn new() -> Self {
+        let style = gtk::CssProvider::new();
+        let light = gtk::CssProvider::new();
+        let dark = gtk::CssProvider::new();
+        for (provider, path) in [
+            (&style, STYLE_PATH),
+            (&light, LIGHT_STYLE_PATH),
+            (&dark, DARK_STYLE_PATH),
+        ] {
+            if provider.load_from_path(path).is_err() {
+                println!("Could not load the stylesheet {path}");
+            }
+        }
+
+        ThemeManager {
+            style,
+            light,
+            dark,
+            font: gtk::CssProvider::new(),
+            applied: Cell::new(None),
+            preferences: RefCell::new(Preferences::load(PREFERENCES_PATH)),
+        }
+    }
+
+    /// Applies the styles, the theme and the font size of the preferences to the screen.
+    pub fn build(&self) {
+        if let Some(screen) = gtk::gdk::Screen::default() {
+            gtk::StyleContext::add_provider_for_screen(
+                &screen,
+                &self.style,
+                gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
+            );
+            gtk::StyleContext::add_provider_for_screen(
+                &screen,
+                &self.font,
+                gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
+            );
+        }
+        self.apply();
+    }
+
+    /// Returns the preferences chosen.
+    pub fn preferences(&self) -> Preferences {
+        self.preferences.borrow().clone()
+    }
+
+    /// Changes the theme, applying and saving it.
+    pub fn set_theme(&self, theme: Theme) {
+        self.preferences.borrow_mut().theme = theme;
+        self.apply();
+        self.save();
+    }
+
+    /// Changes the font size, applying and saving it.
+    pub fn set_font_size(&self, font_size: u32) {
+        self.preferences.borrow_mut().font_size = font_size;
+        self.apply();
+        self.save();
+    }
+
+    /// Changes the speed limit of the DCC transfers, saving it. The transfers read it when they start.
+    pub fn set_dcc_speed_limit(&self, dcc_speed_limit: u32) {
+        self.preferences.borrow_mut().dcc_speed_limit = dcc_speed_limit;
+        self.save();
+    }
+
+    /// Changes the most files sent at the same time through a DCC connection, saving it. The connections
+    /// read it when they start the next file of their queue.
+    pub fn set_dcc_max_transfers(&self, dcc_max_transfers: u32) {
+        self.preferences.borrow_mut().dcc_max_transfers = dcc_max_transfers;
+        self.save();
+    }
+
+    /// Changes the folder where the files received through DCC are saved, saving it. The transfers read it
+    /// when a file is accepted.
+    pub fn set_download_directory(&self, download_directory: String) {
+        self.preferences.borrow_mut().download_directory = download_directory;
+        self.save();
+    }
+
+    /// Changes what is done with a received file when the download folder already has one with its name,
+    /// saving it.
+    pub fn set_collision_policy(&self, collision_policy: CollisionPolicy) {
+        self.preferences.borrow_mut().collision_policy = collision_policy;
+        self.save();
+    }
+
+    /// Changes the most MB of the files received through DCC, saving it. The files bigger than it are
+    /// declined.
+    pub fn set_dcc_max_file_size(&self, dcc_max_file_size: u32) {
+        self.preferences.borrow_mut().dcc_max_file_size = dcc_max_file_size;
+        self.save();
+    }
+
+    /// Changes the extensions of the files declined when they are sent through DCC, saving them.
+    pub fn set_dcc_blocked_extensions(&self, dcc_blocked_extensions: String) {
+        self.preferences.borrow_mut().dcc_blocked_extensions = dcc_blocked_extensions;
+        self.save();
+    }
+
+    /// Applies the theme and the font size of the preferences to the screen.
+    fn apply(&self) {
+        let preferences = self.preferences();
+        if self
+            .font
+            .load_from_data(preferences.font_css().as_bytes())
+            .is_err()
+        {
+            println!("Could not set the font size {}", preferences.font_size);
+        }
+        let screen = match gtk::gdk::Screen::default() {
+            Some(screen) => screen,
+            None => return,
+        };
+        if self.applied.get() == Some(preferences.theme) {
+            return;
+        }
+        if let Some(applied) = self.applied.get() {
+            gtk::StyleContext::remove_provider_for_screen(&screen, self.provider(applied));
+        }
+        gtk::StyleContext::add_provider_for_screen(
+            &screen,
+            self.provider(preferences.theme),
+            gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
+        );
+        self.applied.set(Some(preferences.theme));
+    }
+
+    /// Saves the preferences, so they are used the next time the client starts.
+    fn save(&self) {
+        if self.preferences().save(PREFERENCES_PATH).is_err() {
+            println!("Could not save the preferences to {PREFERENCES_PATH}");
+        }
+    }
+
+    /// Returns the provider of the theme.
+    fn provider(&self, theme: Theme) -> &gtk::CssProvider {
+        match theme {
+            Theme::Light => &self.light,
+            Theme::Dark => &self.dark,
+        }
+    }
+}
+
+impl Default for ThemeManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
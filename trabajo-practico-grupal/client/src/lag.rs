@@ -0,0 +1,223 @@
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use model::responses::replies::ConnectionState;
+use model::responses::response::Response;
+use model::socket::write_socket;
+
+use crate::protocol::ProtocolSession;
+use crate::reconnect::{current_socket, SharedSocket};
+
+/// The time between two PINGs of the client.
+const PING_INTERVAL: Duration = Duration::from_secs(15);
+/// The lag from which the connection is lagging.
+const LAGGING_THRESHOLD: Duration = Duration::from_secs(5);
+/// The prefix of the tokens of the PINGs, followed by their number.
+static TOKEN_PREFIX: &str = "lag-";
+
+/// Struct that measures the round-trip time of the PINGs the client sends to the server.
+/// Only one PING is waiting for its PONG at a time, so while the server doesn't answer, the lag
+/// is the time since it was sent.
+/// # Fields
+/// * `sent` - The amount of PINGs sent, used to number their tokens.
+/// * `pending` - The token of the PING waiting for its PONG, with the time it was sent.
+/// * `lag` - The round-trip time of the last PING answered.
+/// * `reconnecting` - True while the client connects again to the server.
+/// * `reported` - The state of the last status returned, to know when it changes.
+#[derive(Debug, Default)]
+pub struct LagMeter {
+    sent: u64,
+    pending: Option<(String, Instant)>,
+    lag: Duration,
+    reconnecting: bool,
+    reported: Option<ConnectionState>,
+}
+
+impl LagMeter {
+    /// Returns the PING to send to the server, or None if the last one wasn't answered yet.
+    /// # Arguments
+    /// * `now` - When the PING is sent.
+    pub fn ping(&mut self, now: Instant) -> Option<String> {
+        if self.pending.is_some() || self.reconnecting {
+            return None;
+        }
+        self.sent += 1;
+        let token = format!("{}{}", TOKEN_PREFIX, self.sent);
+        self.pending = Some((token.to_owned(), now));
+        Some(format!("PING {}", token))
+    }
+
+    /// Records the PONG of the server, saving the lag if it answers the PING waiting for it.
+    /// Returns false for the PONGs of other PINGs.
+    /// # Arguments
+    /// * `token` - The token of the PONG.
+    /// * `now` - When the PONG was received.
+    pub fn pong(&mut self, token: &str, now: Instant) -> bool {
+        match &self.pending {
+            Some((pending, sent)) if pending == token => {
+                self.lag = now.duration_since(*sent);
+                self.pending = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the lag of the connection: the round-trip time of the last PING answered, or
+    /// the time since the one waiting was sent if it is longer.
+    /// # Arguments
+    /// * `now` - The current time.
+    pub fn lag(&self, now: Instant) -> Duration {
+        match &self.pending {
+            Some((_, sent)) => self.lag.max(now.duration_since(*sent)),
+            None => self.lag,
+        }
+    }
+
+    /// Returns the state of the connection.
+    /// # Arguments
+    /// * `now` - The current time.
+    pub fn state(&self, now: Instant) -> ConnectionState {
+        if self.reconnecting {
+            ConnectionState::Reconnecting
+        } else if self.lag(now) >= LAGGING_THRESHOLD {
+            ConnectionState::Lagging
+        } else {
+            ConnectionState::Connected
+        }
+    }
+
+    /// Returns the state of the connection with its lag, and true if the state is not the one
+    /// of the last status returned.
+    /// # Arguments
+    /// * `now` - The current time.
+    pub fn status(&mut self, now: Instant) -> (ConnectionState, Duration, bool) {
+        let state = self.state(now);
+        let changed = self.reported != Some(state);
+        self.reported = Some(state);
+        (state, self.lag(now), changed)
+    }
+
+    /// Marks the connection as lost, forgetting the PING that will never be answered.
+    pub fn reconnecting(&mut self) {
+        self.reconnecting = true;
+        self.pending = None;
+    }
+
+    /// Marks the connection as established again, measuring the lag from scratch.
+    pub fn reconnected(&mut self) {
+        self.reconnecting = false;
+        self.lag = Duration::ZERO;
+    }
+}
+
+/// Returns the text of the status of the connection shown to the user.
+/// # Arguments
+/// * `state` - The state of the connection.
+/// * `lag` - The lag of the connection, in milliseconds.
+pub fn status_text(state: ConnectionState, lag: u64) -> String {
+    match state {
+        ConnectionState::Connected => format!("Connected (lag {} ms)", lag),
+        ConnectionState::Lagging => format!("Lagging (lag {} ms)", lag),
+        ConnectionState::Reconnecting => "Reconnecting".to_string(),
+    }
+}
+
+/// Function that periodically sends a PING to the server, measuring the lag of the connection.
+/// After each PING the status of the connection is passed to the frontend, with true if its
+/// state changed. It stops once the client quits.
+/// # Arguments
+/// * `shared_socket` - The connection shared by the threads of the client.
+/// * `protocol` - The protocol session of the client.
+/// * `on_status` - Called with the ConnectionStatus response after each PING.
+pub fn handle_lag_meter<F>(
+    shared_socket: SharedSocket,
+    protocol: Arc<RwLock<ProtocolSession>>,
+    on_status: F,
+) where
+    F: Fn(Response, bool) + Send + 'static,
+{
+    thread::spawn(move || loop {
+        thread::sleep(PING_INTERVAL);
+        let (ping, status, changed) = match protocol.write() {
+            Ok(mut protocol) if !protocol.is_quitting() => {
+                let now = Instant::now();
+                let ping = protocol.ping(now);
+                let (status, changed) = protocol.connection_status(now);
+                (ping, status, changed)
+            }
+            _ => break,
+        };
+        if let (Some(ping), Ok(socket)) = (ping, current_socket(&shared_socket)) {
+            if let Err(e) = write_socket(socket, &ping) {
+                println!("[ERROR] Error sending the PING: {:?}", e);
+            }
+        }
+        on_status(status, changed);
+    });
+}
+
+#[cfg(test)]
+mod lag_tests {
+    use std::time::{Duration, Instant};
+
+    use model::responses::replies::ConnectionState;
+
+    use super::{status_text, LagMeter};
+
+    #[test]
+    fn test_lag_is_the_round_trip_time_of_the_ping() {
+        let mut meter = LagMeter::default();
+        let start = Instant::now();
+
+        let ping = meter.ping(start).unwrap();
+        let token = ping.strip_prefix("PING ").unwrap();
+
+        assert_eq!(meter.ping(start + Duration::from_secs(1)), None);
+        assert!(!meter.pong("other", start + Duration::from_millis(10)));
+        assert!(meter.pong(token, start + Duration::from_millis(40)));
+        assert_eq!(meter.lag(start), Duration::from_millis(40));
+        assert_eq!(
+            meter.status(start + Duration::from_secs(1)),
+            (ConnectionState::Connected, Duration::from_millis(40), true)
+        );
+        assert!(meter.ping(start + Duration::from_secs(2)).is_some());
+    }
+
+    #[test]
+    fn test_unanswered_ping_makes_the_connection_lag() {
+        let mut meter = LagMeter::default();
+        let start = Instant::now();
+        meter.ping(start);
+        assert!(meter.status(start).2);
+
+        let (state, lag, changed) = meter.status(start + Duration::from_secs(6));
+
+        assert_eq!(state, ConnectionState::Lagging);
+        assert_eq!(lag, Duration::from_secs(6));
+        assert!(changed);
+        assert!(!meter.status(start + Duration::from_secs(7)).2);
+    }
+
+    #[test]
+    fn test_reconnection_forgets_the_unanswered_ping() {
+        let mut meter = LagMeter::default();
+        let start = Instant::now();
+        meter.ping(start);
+
+        meter.reconnecting();
+
+        assert_eq!(meter.state(start), ConnectionState::Reconnecting);
+        assert_eq!(meter.ping(start), None);
+        meter.reconnected();
+        assert_eq!(
+            meter.state(start + Duration::from_secs(60)),
+            ConnectionState::Connected
+        );
+        assert_eq!(
+            status_text(ConnectionState::Lagging, 6000),
+            "Lagging (lag 6000 ms)"
+        );
+    }
+}
@@ -1,4 +1,9 @@
+pub mod chat_log;
 pub mod dcc_commands;
 pub mod gui;
+pub mod lag;
+pub mod protocol;
+pub mod reconnect;
+pub mod router;
 pub mod run;
 pub mod run_interface;
@@ -0,0 +1,392 @@
+use std::time::Instant;
+
+use model::ctcp::reply_to_query;
+use model::dcc::DccMessage;
+use model::responses::message::MessageResponse;
+use model::responses::replies::CommandResponse;
+use model::responses::response::Response;
+
+use crate::lag::LagMeter;
+
+/// The phases of the registration of the client with the server.
+/// # Variants
+/// * `Unregistered`: The client didn't send its NICK or USER yet.
+/// * `Registering`: The client sent its NICK or USER and waits for the welcome of the server.
+/// * `Registered`: The server welcomed the client.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RegistrationPhase {
+    #[default]
+    Unregistered,
+    Registering,
+    Registered,
+}
+
+/// What a frontend has to do with a line received from the server.
+/// # Variants
+/// * `Dcc`: A DCC message, handled by the DCC commands.
+/// * `Query`: A CTCP query, with the reply to send back to the server.
+/// * `Show`: A response to show to the user.
+/// * `Status`: The PONG of a PING of the client, with the status of the connection it measured
+///   and true if its state changed.
+/// * `Unparsed`: A line that isn't a response known by the client.
+pub enum Incoming {
+    Dcc(DccMessage),
+    Query { reply: String, response: Response },
+    Show(Response),
+    Status { response: Response, changed: bool },
+    Unparsed(String),
+}
+
+/// Struct that represents the state of the protocol with the server, learned from the messages
+/// the client sends and the responses it receives. It doesn't read or write the connection, so
+/// the terminal and the GUI share it and only move the lines between the socket and the user.
+/// # Fields
+/// * `pass` - The last PASS message sent.
+/// * `nick` - The last NICK message sent.
+/// * `user` - The last USER message sent.
+/// * `phase` - The phase of the registration.
+/// * `nickname` - The nickname the server welcomed the client with.
+/// * `channels` - The channels joined and not left.
+/// * `who_queries` - The masks of the WHO queries sent and not ended yet, oldest first.
+/// * `lag` - The lag meter of the PINGs sent to the server.
+/// * `quitting` - True after the client sends QUIT, so it doesn't reconnect.
+#[derive(Debug, Default)]
+pub struct ProtocolSession {
+    pass: Option<String>,
+    nick: Option<String>,
+    user: Option<String>,
+    phase: RegistrationPhase,
+    nickname: Option<String>,
+    channels: Vec<String>,
+    who_queries: Vec<String>,
+    lag: LagMeter,
+    quitting: bool,
+}
+
+impl ProtocolSession {
+    /// Creates the session of a client that didn't register yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the session with a message sent to the server.
+    /// # Arguments
+    /// * `message` - The message sent, like `JOIN #channel`.
+    pub fn record(&mut self, message: &str) {
+        let mut words = message.split_whitespace();
+        let command = words.next().unwrap_or_default().to_uppercase();
+        let target = words.next().unwrap_or_default();
+        let targets = target
+            .split(',')
+            .filter(|channel| !channel.is_empty())
+            .map(|channel| channel.to_string())
+            .collect::<Vec<_>>();
+        match command.as_str() {
+            "PASS" => self.pass = Some(message.to_string()),
+            "NICK" => {
+                self.nick = Some(message.to_string());
+                self.start_registration();
+            }
+            "USER" => {
+                self.user = Some(message.to_string());
+                self.start_registration();
+            }
+            "JOIN" => self.join(targets),
+            "PART" => self.channels.retain(|channel| !targets.contains(channel)),
+            "WHO" => self.who_queries.push(target.to_string()),
+            "QUIT" => self.quitting = true,
+            _ => (),
+        }
+    }
+
+    /// Updates the session with a line received from the server, and returns what the
+    /// frontend has to do with it.
+    /// The welcome ends the registration, the joins and parts of the client update its
+    /// channels and the end of a WHO list ends the oldest query. The CTCP queries are answered
+    /// with the reply of the client, and the PONGs of its PINGs measure the lag.
+    /// # Arguments
+    /// * `line` - The line received, already sanitized.
+    /// * `now` - When the line was received.
+    pub fn receive(&mut self, line: String, now: Instant) -> Incoming {
+        if let Ok(dcc_msg) = DccMessage::deserialize(line.clone()) {
+            return Incoming::Dcc(dcc_msg);
+        }
+        let response = match Response::serialize(line.clone()) {
+            Some(response) => response,
+            None => return Incoming::Unparsed(line),
+        };
+        match &response {
+            Response::CommandResponse {
+                response: CommandResponse::Welcome { nickname, .. },
+            } => {
+                self.phase = RegistrationPhase::Registered;
+                self.nickname = Some(nickname.to_owned());
+            }
+            Response::CommandResponse {
+                response: CommandResponse::EndOfWho,
+            } if !self.who_queries.is_empty() => {
+                self.who_queries.remove(0);
+            }
+            Response::CommandResponse {
+                response: CommandResponse::Pong { token, .. },
+            } if self.lag.pong(token, now) => {
+                let (response, changed) = self.connection_status(now);
+                return Incoming::Status { response, changed };
+            }
+            Response::MessageResponse {
+                response: MessageResponse::JoinMsg { channel, nickname },
+            } if self.is_own(nickname) => self.join(vec![channel.to_owned()]),
+            Response::MessageResponse {
+                response: MessageResponse::PartMsg { channel, nickname },
+            } if self.is_own(nickname) => self.channels.retain(|joined| joined != channel),
+            Response::MessageResponse {
+                response:
+                    MessageResponse::UserPrivMsg { sender, message }
+                    | MessageResponse::ChannelPrivMsg {
+                        sender, message, ..
+                    },
+            } => {
+                if let Some(reply) = reply_to_query(sender, message) {
+                    return Incoming::Query { reply, response };
+                }
+            }
+            _ => (),
+        }
+        Incoming::Show(response)
+    }
+
+    /// Returns the PING to measure the lag, or None if the client isn't registered or the
+    /// last PING wasn't answered yet.
+    /// # Arguments
+    /// * `now` - When the PING is sent.
+    pub fn ping(&mut self, now: Instant) -> Option<String> {
+        if self.phase != RegistrationPhase::Registered {
+            return None;
+        }
+        self.lag.ping(now)
+    }
+
+    /// Returns the ConnectionStatus response with the state of the connection and its lag, and
+    /// true if the state changed since the last status returned.
+    /// # Arguments
+    /// * `now` - The current time.
+    pub fn connection_status(&mut self, now: Instant) -> (Response, bool) {
+        let (state, lag, changed) = self.lag.status(now);
+        let response = Response::CommandResponse {
+            response: CommandResponse::ConnectionStatus {
+                state,
+                lag: lag.as_millis() as u64,
+            },
+        };
+        (response, changed)
+    }
+
+    /// Marks the connection as lost while the client connects again.
+    pub fn reconnecting(&mut self) {
+        self.lag.reconnecting();
+    }
+
+    /// Goes back to waiting for the welcome after the connection was replaced by a new one,
+    /// where the queries of the previous connection are never answered.
+    pub fn reconnected(&mut self) {
+        if self.phase == RegistrationPhase::Registered {
+            self.phase = RegistrationPhase::Registering;
+        }
+        self.who_queries.clear();
+        self.lag.reconnected();
+    }
+
+    /// Returns the phase of the registration.
+    pub fn phase(&self) -> RegistrationPhase {
+        self.phase
+    }
+
+    /// Returns the nickname the server welcomed the client with, if it did.
+    pub fn nickname(&self) -> Option<&str> {
+        self.nickname.as_deref()
+    }
+
+    /// Returns the channels joined and not left.
+    pub fn channels(&self) -> &[String] {
+        &self.channels
+    }
+
+    /// Returns the masks of the WHO queries waiting for the end of their list, oldest first.
+    pub fn pending_who_queries(&self) -> &[String] {
+        &self.who_queries
+    }
+
+    /// Returns true if the client sent QUIT, so the connection was closed on purpose.
+    pub fn is_quitting(&self) -> bool {
+        self.quitting
+    }
+
+    /// Returns the messages to send to resume the session: the registration, in the order
+    /// PASS, NICK and USER, followed by a JOIN of the channels the client was in.
+    pub fn resume_messages(&self) -> Vec<String> {
+        let mut messages = [&self.pass, &self.nick, &self.user]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>();
+        if !self.channels.is_empty() {
+            messages.push(format!("JOIN {}", self.channels.join(",")));
+        }
+        messages
+    }
+
+    /// Waits for the welcome, unless the server already welcomed the client.
+    fn start_registration(&mut self) {
+        if self.phase == RegistrationPhase::Unregistered {
+            self.phase = RegistrationPhase::Registering;
+        }
+    }
+
+    /// Adds the channels that weren't joined yet.
+    fn join(&mut self, channels: Vec<String>) {
+        for channel in channels {
+            if !self.channels.contains(&channel) {
+                self.channels.push(channel);
+            }
+        }
+    }
+
+    /// Returns true if the nickname is the one the client was welcomed with.
+    fn is_own(&self, nickname: &str) -> bool {
+        self.nickname.as_deref() == Some(nickname)
+    }
+}
+
+#[cfg(test)]
+mod protocol_tests {
+    use std::time::{Duration, Instant};
+
+    use model::responses::replies::{CommandResponse, ConnectionState};
+    use model::responses::response::Response;
+
+    use super::{Incoming, ProtocolSession, RegistrationPhase};
+
+    fn registered_session_for_test() -> ProtocolSession {
+        let mut session = ProtocolSession::new();
+        session.record("NICK alice");
+        session.record("USER alice host server :Alice");
+        session.receive(
+            "001 alice alice host :Welcome to the Internet Rust Network alice".into(),
+            Instant::now(),
+        );
+        session
+    }
+
+    #[test]
+    fn test_session_resumes_the_registration_and_the_channels() {
+        let mut session = ProtocolSession::new();
+        session.record("PASS secret");
+        session.record("USER alice host server :Alice");
+        session.record("NICK alice");
+        session.record("JOIN #a,#b");
+        session.record("JOIN #c");
+        session.record("PART #b");
+
+        assert_eq!(
+            session.resume_messages(),
+            vec![
+                "PASS secret",
+                "NICK alice",
+                "USER alice host server :Alice",
+                "JOIN #a,#c"
+            ]
+        );
+        assert!(!session.is_quitting());
+        session.record("QUIT :bye");
+        assert!(session.is_quitting());
+    }
+
+    #[test]
+    fn test_welcome_ends_the_registration() {
+        let mut session = ProtocolSession::new();
+        assert_eq!(session.phase(), RegistrationPhase::Unregistered);
+        session.record("NICK alice");
+        assert_eq!(session.phase(), RegistrationPhase::Registering);
+
+        let incoming = session.receive(
+            "001 alice alice host :Welcome to the Internet Rust Network".into(),
+            Instant::now(),
+        );
+
+        assert!(matches!(incoming, Incoming::Show(_)));
+        assert_eq!(session.phase(), RegistrationPhase::Registered);
+        assert_eq!(session.nickname(), Some("alice"));
+        session.reconnected();
+        assert_eq!(session.phase(), RegistrationPhase::Registering);
+    }
+
+    #[test]
+    fn test_own_joins_and_parts_received_update_the_channels() {
+        let mut session = registered_session_for_test();
+
+        session.receive("017 #rust alice".into(), Instant::now());
+        session.receive("017 #go alice".into(), Instant::now());
+        session.receive("017 #zig bob".into(), Instant::now());
+        session.receive("018 #go alice".into(), Instant::now());
+
+        assert_eq!(session.channels(), ["#rust"]);
+    }
+
+    #[test]
+    fn test_end_of_who_ends_the_oldest_query() {
+        let mut session = registered_session_for_test();
+        session.record("WHO #rust");
+        session.record("WHO bob");
+
+        session.receive("315 :End of /WHO list".into(), Instant::now());
+
+        assert_eq!(session.pending_who_queries(), ["bob"]);
+        session.reconnected();
+        assert!(session.pending_who_queries().is_empty());
+    }
+
+    #[test]
+    fn test_ctcp_queries_are_answered_and_unknown_lines_reported() {
+        let mut session = registered_session_for_test();
+
+        let query = session.receive("002 bob \x01PING 123\x01".into(), Instant::now());
+        let unparsed = session.receive("PING server".into(), Instant::now());
+
+        match query {
+            Incoming::Query { reply, .. } => assert_eq!(reply, "NOTICE bob :\x01PING 123\x01"),
+            _ => panic!("Expected the reply to the query"),
+        }
+        assert!(matches!(unparsed, Incoming::Unparsed(line) if line == "PING server"));
+    }
+
+    #[test]
+    fn test_pong_of_the_ping_measures_the_lag() {
+        let mut session = ProtocolSession::new();
+        let start = Instant::now();
+        assert_eq!(session.ping(start), None);
+        session.record("NICK alice");
+        session.receive("001 alice alice host :Welcome".into(), start);
+
+        let ping = session.ping(start).unwrap();
+        let token = ping.strip_prefix("PING ").unwrap();
+        let incoming = session.receive(
+            format!("020 server {token}"),
+            start + Duration::from_millis(25),
+        );
+
+        match incoming {
+            Incoming::Status {
+                response:
+                    Response::CommandResponse {
+                        response: CommandResponse::ConnectionStatus { state, lag },
+                    },
+                changed,
+            } => {
+                assert_eq!(state, ConnectionState::Connected);
+                assert_eq!(lag, 25);
+                assert!(changed);
+            }
+            _ => panic!("Expected the status of the connection"),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use std::net::TcpStream;
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use model::client_errors::ClientError;
+use model::socket::write_socket;
+
+use crate::protocol::ProtocolSession;
+
+/// The delay before the first attempt to reconnect.
+const INITIAL_DELAY: Duration = Duration::from_secs(1);
+/// The maximum delay between two attempts to reconnect.
+const MAX_DELAY: Duration = Duration::from_secs(30);
+/// The attempts to reconnect before giving up.
+const MAX_ATTEMPTS: u32 = 8;
+
+/// The connection with the server shared by the thread that reads it and the one that writes it,
+/// which is replaced by a new connection when the client reconnects.
+pub type SharedSocket = Arc<RwLock<Arc<TcpStream>>>;
+
+/// Returns the current connection with the server.
+pub fn current_socket(shared: &SharedSocket) -> Result<Arc<TcpStream>, ClientError> {
+    Ok(shared.read()?.clone())
+}
+
+/// Struct that represents the exponential backoff between the attempts to reconnect.
+/// The delay starts at the initial delay and doubles after each attempt, up to the maximum delay.
+/// # Fields
+/// * `initial` - The delay before the first attempt.
+/// * `max` - The maximum delay between two attempts.
+/// * `next` - The delay before the next attempt.
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    next: Duration,
+}
+
+impl Backoff {
+    /// Creates a new backoff.
+    /// # Arguments
+    /// * `initial` - The delay before the first attempt.
+    /// * `max` - The maximum delay between two attempts.
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            next: initial,
+        }
+    }
+
+    /// Returns the delay before the next attempt, and doubles the one after it.
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = (self.next * 2).min(self.max);
+        delay
+    }
+
+    /// Goes back to the initial delay, after a successful attempt.
+    pub fn reset(&mut self) {
+        self.next = self.initial;
+    }
+}
+
+/// Connects again to the server after the connection was lost, waiting between the attempts
+/// with an exponential backoff. Once connected, the new connection replaces the shared one and
+/// the session is resumed by registering again and joining the channels it was in.
+/// The session is marked as reconnecting until then, so its connection state shows it.
+/// # Arguments
+/// * `address` - The address of the server.
+/// * `shared` - The connection shared by the threads of the client.
+/// * `state` - The protocol session to resume.
+/// * `on_attempt` - Called before each attempt, with the number of the attempt and the delay before it.
+/// # Errors
+/// ClientError::ErrorWhileConnecting if every attempt failed.
+pub fn reconnect<F: Fn(u32, Duration)>(
+    address: &str,
+    shared: &SharedSocket,
+    state: &RwLock<ProtocolSession>,
+    on_attempt: F,
+) -> Result<(), ClientError> {
+    state.write()?.reconnecting();
+    let mut backoff = Backoff::new(INITIAL_DELAY, MAX_DELAY);
+    for attempt in 1..=MAX_ATTEMPTS {
+        let delay = backoff.next_delay();
+        on_attempt(attempt, delay);
+        thread::sleep(delay);
+        let socket = match TcpStream::connect(address) {
+            Ok(socket) => Arc::new(socket),
+            Err(_) => continue,
+        };
+        for message in state.read()?.resume_messages() {
+            write_socket(socket.clone(), &message)?;
+        }
+        state.write()?.reconnected();
+        *shared.write()? = socket;
+        return Ok(());
+    }
+    Err(ClientError::ErrorWhileConnecting)
+}
+
+#[cfg(test)]
+mod reconnect_tests {
+    use std::time::Duration;
+
+    use super::Backoff;
+
+    #[test]
+    fn test_backoff_doubles_the_delay_up_to_the_maximum() {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(5));
+
+        let delays = (0..5).map(|_| backoff.next_delay()).collect::<Vec<_>>();
+        backoff.reset();
+
+        assert_eq!(delays, [1, 2, 4, 5, 5].map(Duration::from_secs).to_vec());
+        assert_eq!(backoff.next_delay(), Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,248 @@
+use model::responses::dcc::DccResponse;
+use model::responses::errors::ErrorResponse;
+use model::responses::message::MessageResponse;
+use model::responses::replies::CommandResponse;
+use model::responses::response::Response;
+
+/// A callback subscribed to the responses of a category.
+type Subscriber = Box<dyn Fn(&Response) + Send>;
+
+/// The categories of the responses the components of the interface subscribe to.
+/// # Variants
+/// * `Connection`: The result of connecting to the server.
+/// * `Registration`: The welcome and the errors of the registration.
+/// * `Reply`: The replies to the commands, shown in the chats.
+/// * `ChannelEvent`: The messages and the notifications of the channels and the users.
+/// * `Dcc`: The responses of the DCC connections.
+/// * `Error`: The errors of the commands, shown in the chats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseCategory {
+    Connection,
+    Registration,
+    Reply,
+    ChannelEvent,
+    Dcc,
+    Error,
+}
+
+impl ResponseCategory {
+    /// Returns the category of the response, or None if the interface doesn't show it.
+    pub fn of(response: &Response) -> Option<ResponseCategory> {
+        match response {
+            Response::CommandResponse { response } => match response {
+                CommandResponse::ConnectionSuccees => Some(ResponseCategory::Connection),
+                CommandResponse::Welcome { .. } => Some(ResponseCategory::Registration),
+                CommandResponse::Topic { .. }
+                | CommandResponse::Names { .. }
+                | CommandResponse::EndNames
+                | CommandResponse::ListStart
+                | CommandResponse::List { .. }
+                | CommandResponse::ListEnd
+                | CommandResponse::NowAway
+                | CommandResponse::UnAway
+                | CommandResponse::Away { .. }
+                | CommandResponse::ChannelMode { .. }
+                | CommandResponse::UserMode { .. }
+                | CommandResponse::EndBanList
+                | CommandResponse::BanList { .. }
+                | CommandResponse::YouAreOperator
+                | CommandResponse::WhoIsUser { .. }
+                | CommandResponse::WhoIsServer { .. }
+                | CommandResponse::WhoIsChannels { .. }
+                | CommandResponse::WhoReply { .. }
+                | CommandResponse::EndOfWho
+                | CommandResponse::EndOfWhoIs
+                | CommandResponse::DeliveryReceipt { .. }
+                | CommandResponse::Reconnecting { .. }
+                | CommandResponse::Reconnected
+                | CommandResponse::ConnectionStatus { .. } => Some(ResponseCategory::Reply),
+                _ => None,
+            },
+            Response::ErrorResponse { response } => match response {
+                ErrorResponse::ErrorWhileConnecting => Some(ResponseCategory::Connection),
+                ErrorResponse::NeedMoreParams { .. }
+                | ErrorResponse::AlreadyRegistered { .. }
+                | ErrorResponse::NickInUse { .. }
+                | ErrorResponse::NotRegistered => Some(ResponseCategory::Registration),
+                ErrorResponse::PasswordMismatch
+                | ErrorResponse::CannotSendToChannel { .. }
+                | ErrorResponse::ChannelIsFull { .. }
+                | ErrorResponse::ChanOPrivsNeeded { .. }
+                | ErrorResponse::BannedFromChannel { .. }
+                | ErrorResponse::InviteOnlyChannel { .. }
+                | ErrorResponse::LinkChannel { .. }
+                | ErrorResponse::BadChannelKey { .. }
+                | ErrorResponse::NoSuchChannel { .. }
+                | ErrorResponse::KeySet { .. }
+                | ErrorResponse::NoPrivileges
+                | ErrorResponse::NotOnChannel { .. }
+                | ErrorResponse::UserOnChannel { .. }
+                | ErrorResponse::ClientDisconnected { .. }
+                | ErrorResponse::InvalidEncoding => Some(ResponseCategory::Error),
+                _ => None,
+            },
+            Response::MessageResponse { .. } => Some(ResponseCategory::ChannelEvent),
+            Response::DccResponse { .. } => Some(ResponseCategory::Dcc),
+        }
+    }
+}
+
+/// Struct that sends each response received to the components of the interface subscribed to
+/// its category, so the thread that reads the server doesn't need to know the components.
+/// # Fields
+/// * `subscribers` - The callbacks subscribed, with the category of each one, in the order
+///   they subscribed.
+#[derive(Default)]
+pub struct ResponseRouter {
+    subscribers: Vec<(ResponseCategory, Subscriber)>,
+}
+
+impl ResponseRouter {
+    /// Creates a router without subscribers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Subscribes the callback to the responses of the category.
+    /// # Arguments
+    /// * `category` - The category of the responses.
+    /// * `callback` - Called with each response of the category.
+    pub fn subscribe<F>(&mut self, category: ResponseCategory, callback: F)
+    where
+        F: Fn(&Response) + Send + 'static,
+    {
+        self.subscribers.push((category, Box::new(callback)));
+    }
+
+    /// Subscribes the callback to the messages and the notifications of the channels and the users.
+    /// # Arguments
+    /// * `callback` - Called with each message or notification.
+    pub fn on_channel_event<F>(&mut self, callback: F)
+    where
+        F: Fn(&MessageResponse) + Send + 'static,
+    {
+        self.subscribe(ResponseCategory::ChannelEvent, move |response| {
+            if let Response::MessageResponse { response } = response {
+                callback(response);
+            }
+        });
+    }
+
+    /// Subscribes the callback to the responses of the DCC connections.
+    /// # Arguments
+    /// * `callback` - Called with each DCC response.
+    pub fn on_dcc_event<F>(&mut self, callback: F)
+    where
+        F: Fn(&DccResponse) + Send + 'static,
+    {
+        self.subscribe(ResponseCategory::Dcc, move |response| {
+            if let Response::DccResponse { response } = response {
+                callback(response);
+            }
+        });
+    }
+
+    /// Subscribes the callback to the errors of the commands shown in the chats.
+    /// # Arguments
+    /// * `callback` - Called with each error.
+    pub fn on_error<F>(&mut self, callback: F)
+    where
+        F: Fn(&ErrorResponse) + Send + 'static,
+    {
+        self.subscribe(ResponseCategory::Error, move |response| {
+            if let Response::ErrorResponse { response } = response {
+                callback(response);
+            }
+        });
+    }
+
+    /// Calls the callbacks subscribed to the category of the response, in the order they
+    /// subscribed. Returns false if the response has no category or nobody subscribed to it.
+    /// # Arguments
+    /// * `response` - The response received.
+    pub fn route(&self, response: &Response) -> bool {
+        let category = match ResponseCategory::of(response) {
+            Some(category) => category,
+            None => return false,
+        };
+        let mut routed = false;
+        for (_, callback) in self
+            .subscribers
+            .iter()
+            .filter(|(subscribed, _)| *subscribed == category)
+        {
+            callback(response);
+            routed = true;
+        }
+        routed
+    }
+}
+
+#[cfg(test)]
+mod router_tests {
+    use std::sync::{Arc, Mutex};
+
+    use model::responses::response::Response;
+
+    use super::{ResponseCategory, ResponseRouter};
+
+    fn response_for_test(line: &str) -> Response {
+        Response::serialize(line.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_responses_are_classified_in_the_categories_of_the_screens() {
+        let categories = [
+            "000",
+            "001 alice alice host :Welcome to the Internet Rust Network alice",
+            "433 alice",
+            "315 :End of /WHO list",
+            "017 #rust alice",
+            "403 #nowhere",
+        ]
+        .map(|line| ResponseCategory::of(&response_for_test(line)));
+
+        assert_eq!(
+            categories,
+            [
+                Some(ResponseCategory::Connection),
+                Some(ResponseCategory::Registration),
+                Some(ResponseCategory::Registration),
+                Some(ResponseCategory::Reply),
+                Some(ResponseCategory::ChannelEvent),
+                Some(ResponseCategory::Error),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_responses_are_routed_to_the_subscribers_of_their_category() {
+        let received = Arc::new(Mutex::new(vec![]));
+        let mut router = ResponseRouter::new();
+        let events = received.clone();
+        router.on_channel_event(move |event| {
+            events.lock().unwrap().push(format!("event {}", event));
+        });
+        let errors = received.clone();
+        router.on_error(move |error| {
+            errors.lock().unwrap().push(format!("error {}", error));
+        });
+        let replies = received.clone();
+        router.subscribe(ResponseCategory::ChannelEvent, move |response| {
+            replies.lock().unwrap().push(format!("any {}", response));
+        });
+
+        assert!(router.route(&response_for_test("017 #rust alice")));
+        assert!(router.route(&response_for_test("403 #nowhere")));
+        assert!(!router.route(&response_for_test("000")));
+
+        assert_eq!(
+            *received.lock().unwrap(),
+            vec![
+                "event 017 #rust alice",
+                "any 017 #rust alice",
+                format!("error {}", response_for_test("403 #nowhere")).as_str(),
+            ]
+        );
+    }
+}
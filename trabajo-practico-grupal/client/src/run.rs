@@ -9,20 +9,35 @@ use std::sync::mpsc::SyncSender;
 use std::sync::Arc;
 use std::sync::RwLock;
 use std::thread;
+use std::time::Instant;
 
 use model::client_errors::ClientError;
+use model::consts::MAX_MSG_SIZE;
+use model::ctcp::{render_text, CtcpMessage};
 use model::dcc::DccMessage;
+use model::formatting::{strip_formatting, to_ansi};
+use model::responses::message::MessageResponse;
+use model::responses::replies::CommandResponse;
+use model::responses::response::Response;
+use model::sanitize::sanitize;
 
-const MAX_MSG_SIZE: usize = 510;
+use crate::lag::{handle_lag_meter, status_text};
+use crate::protocol::{Incoming, ProtocolSession};
+use crate::reconnect::{current_socket, reconnect, SharedSocket};
 
 /// This function is the main loop of the client to run in the terminal.
 /// It creates a thread to listen to the server and the other one is used to to listen to the user input.
+/// If the connection drops without the user quitting, the client reconnects and resumes the session.
+/// The lag of the connection is measured with PINGs, and its status is printed when it changes.
 /// Returns a ClientError in case of error
 pub fn client_run(address: &str) -> Result<(), ClientError> {
     let socket = TcpStream::connect(address)?;
 
-    let arc_socket = Arc::new(socket);
+    let arc_socket: SharedSocket = Arc::new(RwLock::new(Arc::new(socket)));
     let arc_socket_clone = arc_socket.clone();
+    let protocol = Arc::new(RwLock::new(ProtocolSession::new()));
+    let protocol_clone = protocol.clone();
+    let address = address.to_string();
 
     let dcc_connections = HashMap::<String, SyncSender<String>>::new();
     let arc_dcc_connections = Arc::new(RwLock::new(dcc_connections));
@@ -32,18 +47,33 @@ pub fn client_run(address: &str) -> Result<(), ClientError> {
     let arc_dcc_ongoing_transfers = Arc::new(RwLock::new(dcc_ongoing_transfers));
     let arc_dcc_ongoing_transfers_clone = arc_dcc_ongoing_transfers.clone();
 
-    thread::spawn(move || {
-        while read_server_response(
-            arc_socket_clone.clone(),
+    handle_lag_meter(arc_socket.clone(), protocol.clone(), |status, changed| {
+        if changed {
+            print_status(&status);
+        }
+    });
+
+    thread::spawn(move || loop {
+        let socket = match current_socket(&arc_socket_clone) {
+            Ok(socket) => socket,
+            Err(_) => break,
+        };
+        if read_server_response(
+            socket,
+            &protocol_clone,
             arc_dcc_connections.clone(),
             arc_dcc_ongoing_transfers.clone(),
         )
-        .is_ok()
-        {}
+        .is_err()
+            && resume_session(&address, &arc_socket_clone, &protocol_clone).is_err()
+        {
+            break;
+        }
     });
 
     send_client_request(
-        arc_socket,
+        &arc_socket,
+        &protocol,
         arc_dcc_connections_clone,
         arc_dcc_ongoing_transfers_clone,
     )?;
@@ -53,18 +83,40 @@ pub fn client_run(address: &str) -> Result<(), ClientError> {
 /// This function listens from the stdin and sends the request to the server
 /// Returns a ClientError if it can't read from stdin or write to the server
 /// Returns an Ok if it the client sends a QUIT command
+/// The slash commands are translated to protocol messages, and the invalid ones are reported
+/// without sending them. The `/lag` command prints the status of the connection instead.
+/// Every line is recorded in the protocol session, to resume the session after reconnecting.
 fn send_client_request(
-    arc_socket: Arc<TcpStream>,
+    shared_socket: &SharedSocket,
+    protocol: &RwLock<ProtocolSession>,
     _dcc_connections: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
     _dcc_ongoing_transfers: Arc<RwLock<HashMap<String, u64>>>,
 ) -> Result<(), ClientError> {
     let stdin = stdin();
     let reader = BufReader::new(stdin);
+    let mut target = None;
     for line in reader.lines().flatten() {
+        if line.trim().eq_ignore_ascii_case("/lag") {
+            let (status, _) = protocol.write()?.connection_status(Instant::now());
+            print_status(&status);
+            continue;
+        }
+        let line = match parse_slash_command(&line, &mut target) {
+            Ok(line) => line,
+            Err(ClientError::InvalidCommand) => {
+                println!("[ERROR] Unknown command {line}");
+                continue;
+            }
+            Err(_) => {
+                println!("[ERROR] Not enough parameters for {line}");
+                continue;
+            }
+        };
         let mut buff = line.to_owned().into_bytes();
         buff.resize(MAX_MSG_SIZE, 0);
-        arc_socket.as_ref().write_all(&buff)?;
-        if line.starts_with("QUIT ") {
+        protocol.write()?.record(&line);
+        current_socket(shared_socket)?.as_ref().write_all(&buff)?;
+        if line == "QUIT" || line.starts_with("QUIT ") {
             break;
         } else if let Ok(_dcc_msg) = DccMessage::deserialize(line) {
             //manage_dcc_request_from_current_client(line, dcc_connections.clone(), dcc_ongoing_transfers.clone())?;
@@ -73,11 +125,86 @@ fn send_client_request(
     Ok(())
 }
 
-/// This function reads the response from the server and prints it to the stdout
+/// This function translates a slash command typed by the user to the protocol message sent
+/// to the server. The lines not starting with `/` are sent as they are.
+/// * `/join <channel>{,<channel>} [<key>{,<key>}]` -> `JOIN`
+/// * `/msg <target> <text>` -> `PRIVMSG <target> :<text>`
+/// * `/me <action>` -> a CTCP ACTION to the current target
+/// * `/whois <nickname>` -> `WHOIS <nickname>`
+/// * `/quit [<reason>]` -> `QUIT [:<reason>]`
+///
+/// The current target is the last channel joined or the last receiver of `/msg`.
+/// Returns a ClientError::InvalidCommand if the slash command doesn't exist
+/// Returns a ClientError::EmptyMessage if it's missing parameters
+pub fn parse_slash_command(line: &str, target: &mut Option<String>) -> Result<String, ClientError> {
+    let input = match line.strip_prefix('/') {
+        Some(input) => input,
+        None => return Ok(line.to_string()),
+    };
+    let (command, rest) = match input.split_once(' ') {
+        Some((command, rest)) => (command, rest.trim()),
+        None => (input, ""),
+    };
+    let mut words = rest.split_whitespace();
+    match command.to_lowercase().as_str() {
+        "join" => {
+            let channels = words.next().ok_or(ClientError::EmptyMessage)?;
+            *target = channels.split(',').next().map(|c| c.to_string());
+            Ok(format!("JOIN {}", rest))
+        }
+        "msg" => match rest.split_once(' ') {
+            Some((receiver, text)) if !text.trim().is_empty() => {
+                *target = Some(receiver.to_string());
+                Ok(format!("PRIVMSG {} :{}", receiver, text.trim()))
+            }
+            _ => Err(ClientError::EmptyMessage),
+        },
+        "me" => match target {
+            Some(receiver) if !rest.is_empty() => {
+                Ok(format!("PRIVMSG {} :\x01ACTION {}\x01", receiver, rest))
+            }
+            _ => Err(ClientError::EmptyMessage),
+        },
+        "whois" => {
+            let nickname = words.next().ok_or(ClientError::EmptyMessage)?;
+            Ok(format!("WHOIS {}", nickname))
+        }
+        "quit" if rest.is_empty() => Ok("QUIT".to_string()),
+        "quit" => Ok(format!("QUIT :{}", rest)),
+        _ => Err(ClientError::InvalidCommand),
+    }
+}
+
+/// This function reconnects to the server after the connection was lost, unless the user quitted,
+/// printing each attempt to the stdout.
+/// Returns a ClientError::ErrorWhileConnecting if the user quitted or it couldn't reconnect
+fn resume_session(
+    address: &str,
+    shared_socket: &SharedSocket,
+    protocol: &RwLock<ProtocolSession>,
+) -> Result<(), ClientError> {
+    if protocol.read()?.is_quitting() {
+        return Err(ClientError::ErrorWhileConnecting);
+    }
+    reconnect(address, shared_socket, protocol, |attempt, delay| {
+        println!(
+            "[INFO] Reconnecting in {} seconds (attempt {attempt})",
+            delay.as_secs()
+        );
+    })?;
+    println!("[INFO] Reconnected to the server");
+    Ok(())
+}
+
+/// This function reads the response from the server, updates the protocol session with it
+/// and prints it to the stdout
+/// The CTCP queries are answered automatically and the CTCP messages are shown rendered.
+/// The bold, italic and underlined text is shown with the formats of the terminal.
 /// Returns a ClientError if it can't read from the server
 /// Returns an Ok(()) if the server closes the connection
 fn read_server_response(
     arc_socket: Arc<TcpStream>,
+    protocol: &RwLock<ProtocolSession>,
     _dcc_connections: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
     _dcc_ongoing_transfers: Arc<RwLock<HashMap<String, u64>>>,
 ) -> Result<(), ClientError> {
@@ -88,12 +215,31 @@ fn read_server_response(
                 .into_iter()
                 .take_while(|&x| x != 0)
                 .collect::<Vec<_>>();
-            let line = String::from_utf8_lossy(&buffer).to_string();
+            let line = match sanitize(&buffer) {
+                Ok(line) => line,
+                Err(e) => {
+                    println!("[ERROR] Discarding message: {:?}", e);
+                    return Ok(());
+                }
+            };
 
-            if let Ok(_dcc_msg) = DccMessage::deserialize(line.clone()) {
-                //manage_dcc_request_from_client(line, dcc_connections, dcc_ongoing_transfers)?;
-            } else {
-                println!("[DEBUG] {line}");
+            let incoming = protocol.write()?.receive(line.clone(), Instant::now());
+            match incoming {
+                Incoming::Dcc(_dcc_msg) => {
+                    //manage_dcc_request_from_client(line, dcc_connections, dcc_ongoing_transfers)?;
+                }
+                Incoming::Query { reply, .. } => {
+                    let mut buff = reply.into_bytes();
+                    buff.resize(MAX_MSG_SIZE, 0);
+                    arc_socket.as_ref().write_all(&buff)?;
+                    print_line(&line);
+                }
+                Incoming::Status { response, changed } => {
+                    if changed {
+                        print_status(&response);
+                    }
+                }
+                Incoming::Show(_) | Incoming::Unparsed(_) => print_line(&line),
             }
         }
         Err(_) => {
@@ -104,3 +250,139 @@ fn read_server_response(
 
     Ok(())
 }
+
+/// Prints a line received from the server: the styled messages with the formats of the
+/// terminal, the events as text and any other line as it is.
+fn print_line(line: &str) {
+    if let Some((sender, text)) = styled_text(line) {
+        println!("[{sender}] {}", to_ansi(&render_text(&sender, &text)));
+    } else if let Some(text) = event_text(line) {
+        println!("[INFO] {text}");
+    } else {
+        println!("[DEBUG] {line}");
+    }
+}
+
+/// Prints the status of the connection, with its state and its lag.
+fn print_status(status: &Response) {
+    if let Response::CommandResponse {
+        response: CommandResponse::ConnectionStatus { state, lag },
+    } = status
+    {
+        println!("[STATUS] {}", status_text(*state, *lag));
+    }
+}
+
+/// Returns the text of a join, part, quit or mode set received, or None for any other line.
+fn event_text(line: &str) -> Option<String> {
+    match Response::serialize(line.to_string())? {
+        Response::MessageResponse { response } => response.event_text(),
+        _ => None,
+    }
+}
+
+/// Returns the sender and the text of a PRIVMSG or NOTICE received with a CTCP message or
+/// with formatted text, or None for any other line.
+fn styled_text(line: &str) -> Option<(String, String)> {
+    let (sender, text) = match Response::serialize(line.to_string())? {
+        Response::MessageResponse {
+            response:
+                MessageResponse::UserPrivMsg { sender, message }
+                | MessageResponse::UserNotice { sender, message }
+                | MessageResponse::ChannelPrivMsg {
+                    sender, message, ..
+                }
+                | MessageResponse::ChannelNotice {
+                    sender, message, ..
+                },
+        } => (sender, message),
+        _ => return None,
+    };
+    if CtcpMessage::parse(&text).is_none() && strip_formatting(&text) == text {
+        return None;
+    }
+    Some((sender, text))
+}
+
+#[cfg(test)]
+mod run_tests {
+    use model::client_errors::ClientError;
+
+    use super::{event_text, parse_slash_command, styled_text};
+
+    #[test]
+    fn test_slash_commands_are_translated_to_protocol_messages() {
+        let mut target = None;
+        let parsed = [
+            "/join #a,#b key",
+            "/me waves",
+            "/msg bob hello there",
+            "/me waves",
+            "/WHOIS bob",
+            "/quit see you",
+            "/quit",
+            "PING server",
+        ]
+        .map(|line| parse_slash_command(line, &mut target).unwrap());
+
+        assert_eq!(
+            parsed,
+            [
+                "JOIN #a,#b key",
+                "PRIVMSG #a :\x01ACTION waves\x01",
+                "PRIVMSG bob :hello there",
+                "PRIVMSG bob :\x01ACTION waves\x01",
+                "WHOIS bob",
+                "QUIT :see you",
+                "QUIT",
+                "PING server",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_slash_commands_are_not_translated() {
+        let mut target = None;
+
+        assert!(matches!(
+            parse_slash_command("/dance", &mut target),
+            Err(ClientError::InvalidCommand)
+        ));
+        assert!(matches!(
+            parse_slash_command("/me waves", &mut target),
+            Err(ClientError::EmptyMessage)
+        ));
+        assert!(matches!(
+            parse_slash_command("/msg bob", &mut target),
+            Err(ClientError::EmptyMessage)
+        ));
+        assert!(matches!(
+            parse_slash_command("/join", &mut target),
+            Err(ClientError::EmptyMessage)
+        ));
+        assert_eq!(target, None);
+    }
+
+    #[test]
+    fn test_events_received_are_shown_as_text() {
+        assert_eq!(
+            event_text("017 #rust alice"),
+            Some("alice joined #rust".to_string())
+        );
+        assert_eq!(
+            event_text("019 alice see you"),
+            Some("alice quit (see you)".to_string())
+        );
+        assert_eq!(event_text("002 alice hello"), None);
+    }
+
+    #[test]
+    fn test_only_ctcp_and_formatted_messages_are_styled() {
+        assert_eq!(
+            styled_text("002 bob \x02hi\x02"),
+            Some(("bob".to_string(), "\x02hi\x02".to_string()))
+        );
+        assert!(styled_text("003 #rust bob \x01ACTION waves\x01").is_some());
+        assert_eq!(styled_text("002 bob hi"), None);
+    }
+}
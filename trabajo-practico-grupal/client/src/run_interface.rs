@@ -11,66 +11,114 @@ use model::socket::{read_socket, write_socket};
 use std::collections::HashMap;
 use std::net::{TcpListener, TcpStream};
 use std::ops::ControlFlow;
+use std::rc::Rc;
 use std::sync::mpsc::Sender;
 use std::sync::mpsc::{Receiver, SyncSender};
 use std::sync::{Arc, RwLock};
 use std::thread;
+use std::time::Instant;
 
 use crate::dcc_commands::close::close_all_dcc_connections;
 use crate::dcc_commands::dcc_management::{
     manage_dcc_request_from_client, manage_dcc_request_from_current_client,
 };
-use crate::gui::controller::send_to_screen;
+use crate::dcc_commands::relay::{outgoing_relay_request, DccRelays};
+use crate::dcc_commands::room::DccRooms;
+use crate::gui::components::preferences_dialog::PreferencesDialog;
+use crate::gui::components::server_switcher::ServerSwitcher;
+use crate::gui::controller::screens_router;
 use crate::gui::screens::chats_screen::ChatsScreen;
 use crate::gui::screens::connection_screen::ConnectionScreen;
 use crate::gui::screens::registration_screen::RegistrationScreen;
+use crate::gui::theme::ThemeManager;
+use crate::lag::handle_lag_meter;
+use crate::protocol::{Incoming, ProtocolSession};
+use crate::reconnect::{current_socket, reconnect, SharedSocket};
+use crate::router::ResponseRouter;
 
-/// This function creates a new thread that will handle the connection to the server.
-/// The main thread will handle the GUI, creating the main application.
-/// It will create the channels that will be used to communicate between the client thread and the GUI.
-/// It will also create de builder from a glade file that will be used to build the GUI.
-/// Returns a ClientError in case of error
+/// The glade file with the screens of each connection.
+const GLADE_PATH: &str = "client/src/gui/irc.glade";
 
-pub fn client_run_interface() {
-    let (tx_view, rx_cliente): (Sender<String>, Receiver<String>) = std::sync::mpsc::channel();
-    let (tx_connection, rx_connection): (
-        gtk::glib::Sender<Response>,
-        gtk::glib::Receiver<Response>,
-    ) = gtk::glib::MainContext::channel(gtk::glib::PRIORITY_DEFAULT);
+/// The channels of the DCC conversations opened from the screens, by the nickname of the other client.
+type DccInterfaceCommunication = Arc<RwLock<HashMap<String, SyncSender<String>>>>;
 
-    let (tx_registration, rx_registration): (
-        gtk::glib::Sender<Response>,
-        gtk::glib::Receiver<Response>,
-    ) = gtk::glib::MainContext::channel(gtk::glib::PRIORITY_DEFAULT);
+/// The channels between the screens of a connection and the thread that talks with its server.
+/// Each connection of the interface has its own, so their sessions don't mix.
+/// # Fields
+/// * `tx_view` - Sends the messages of the screens to the thread of the connection.
+/// * `rx_connection` - Receives the result of connecting to the server.
+/// * `rx_registration` - Receives the responses of the registration.
+/// * `rx_chats` - Receives the responses shown in the chats.
+/// * `dcc_interface_communication` - The channels of the DCC conversations opened from the screens.
+pub struct ConnectionChannels {
+    pub tx_view: Sender<String>,
+    pub rx_connection: gtk::glib::Receiver<Response>,
+    pub rx_registration: gtk::glib::Receiver<Response>,
+    pub rx_chats: gtk::glib::Receiver<Response>,
+    pub dcc_interface_communication: DccInterfaceCommunication,
+}
 
-    let (tx_chats, rx_chats): (gtk::glib::Sender<Response>, gtk::glib::Receiver<Response>) =
-        gtk::glib::MainContext::channel(gtk::glib::PRIORITY_DEFAULT);
+impl ConnectionChannels {
+    /// Creates the channels of a new connection and the thread that will handle it, which waits
+    /// for the address of the server sent by the connection screen.
+    pub fn spawn() -> Self {
+        let (tx_view, rx_cliente): (Sender<String>, Receiver<String>) = std::sync::mpsc::channel();
+        let (tx_connection, rx_connection): (
+            gtk::glib::Sender<Response>,
+            gtk::glib::Receiver<Response>,
+        ) = gtk::glib::MainContext::channel(gtk::glib::PRIORITY_DEFAULT);
 
-    let dcc_interface_communication = HashMap::<String, SyncSender<String>>::new();
-    let arc_dcc_interface_communication = Arc::new(RwLock::new(dcc_interface_communication));
-    let arc_dcc_interface_communication_clone = arc_dcc_interface_communication.clone();
+        let (tx_registration, rx_registration): (
+            gtk::glib::Sender<Response>,
+            gtk::glib::Receiver<Response>,
+        ) = gtk::glib::MainContext::channel(gtk::glib::PRIORITY_DEFAULT);
 
-    thread::spawn(move || {
-        run_client(
-            rx_cliente,
-            tx_connection,
-            tx_registration,
-            tx_chats,
-            arc_dcc_interface_communication_clone,
-        )
-    });
+        let (tx_chats, rx_chats): (gtk::glib::Sender<Response>, gtk::glib::Receiver<Response>) =
+            gtk::glib::MainContext::channel(gtk::glib::PRIORITY_DEFAULT);
+
+        let dcc_interface_communication = HashMap::<String, SyncSender<String>>::new();
+        let arc_dcc_interface_communication = Arc::new(RwLock::new(dcc_interface_communication));
+        let arc_dcc_interface_communication_clone = arc_dcc_interface_communication.clone();
+
+        let router = screens_router(tx_connection, tx_registration, tx_chats.clone());
+        thread::spawn(move || {
+            run_client(
+                rx_cliente,
+                router,
+                tx_chats,
+                arc_dcc_interface_communication_clone,
+            )
+        });
 
+        ConnectionChannels {
+            tx_view,
+            rx_connection,
+            rx_registration,
+            rx_chats,
+            dcc_interface_communication: arc_dcc_interface_communication,
+        }
+    }
+}
+
+/// This function creates the main application, with the screens of a first connection to a server.
+/// The user opens more connections from the sidebar of the main window, each one with its own
+/// builder from the glade file, its own channels and its own thread, and switches between them.
+pub fn client_run_interface() {
     gtk::init().expect("Failed to initialize GTK.");
 
-    let builder = gtk::Builder::from_file("client/src/gui/irc.glade");
+    let builder = gtk::Builder::from_file(GLADE_PATH);
     let app = Application::builder().application_id("irc").build();
 
-    let connection = ConnectionScreen::new(tx_view.clone());
-    let register = RegistrationScreen::new(tx_view.clone());
-    let chats = ChatsScreen::new(tx_view);
-    connection.build(&builder, rx_connection);
-    register.build(&builder, rx_registration);
-    chats.build(&builder, rx_chats, arc_dcc_interface_communication);
+    let theme_manager = Rc::new(ThemeManager::new());
+    theme_manager.build();
+
+    open_connection(&builder, theme_manager.clone());
+    let servers = ServerSwitcher::new(&builder);
+    servers.connect_add_server(move |servers| {
+        let builder = gtk::Builder::from_file(GLADE_PATH);
+        open_connection(&builder, theme_manager.clone());
+        servers.add(&builder);
+    });
 
     app.connect_activate(clone!(@weak builder => move |app| {
         let window: gtk::Window = (builder).object("main_window").unwrap();
@@ -81,62 +129,110 @@ pub fn client_run_interface() {
     app.run();
 }
 
+/// This function builds the screens of a new connection on the builder, creating the thread that
+/// will handle the connection to the server and the channels to communicate with it.
+/// # Arguments
+/// * `builder` - The builder of the connection.
+/// * `theme_manager` - The manager of the preferences, shared by all the connections.
+fn open_connection(builder: &gtk::Builder, theme_manager: Rc<ThemeManager>) {
+    let channels = ConnectionChannels::spawn();
+    PreferencesDialog::new(theme_manager).build(builder);
+
+    let connection = ConnectionScreen::new(channels.tx_view.clone());
+    let register = RegistrationScreen::new(channels.tx_view.clone());
+    let chats = ChatsScreen::new(channels.tx_view);
+    connection.build(builder, channels.rx_connection);
+    register.build(builder, channels.rx_registration);
+    chats.build(
+        builder,
+        channels.rx_chats,
+        channels.dcc_interface_communication,
+    );
+}
+
 /// This function will handle the connection to the server.
 /// It will receive the address from the GUI thread and will try to connect to the server.
 /// If the connection is successful, it will send a ConnectionSuccess message to the GUI thread.
 /// If the connection fails, it will send an ErrorWhileConnecting message to the GUI thread.
 /// It will be the function responsible for handling the communication between the server and the GUI.
+/// If the connection drops without the client quitting, it reconnects and resumes the session,
+/// informing the GUI of each attempt.
+/// The responses are sent to the screens by the router, and the DCC commands use the sender of the chats.
+/// The lag of the connection is measured with PINGs, and its status is sent to the chats after each one.
 fn run_client(
     rx: Receiver<String>,
-    tx_connection: gtk::glib::Sender<Response>,
-    tx_registration: gtk::glib::Sender<Response>,
+    router: ResponseRouter,
     tx_chats: gtk::glib::Sender<Response>,
     arc_dcc_interface_communication: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
 ) -> Result<(), ClientError> {
-    let socket = match connect_to_server(&rx, &tx_connection) {
+    let (socket, address) = match connect_to_server(&rx, &router) {
         Ok(value) => {
-            let response = Response::CommandResponse {
+            router.route(&Response::CommandResponse {
                 response: CommandResponse::ConnectionSuccees,
-            };
-            send_to_screen(
-                tx_connection.clone(),
-                tx_registration.clone(),
-                tx_chats.clone(),
-                response,
-            );
+            });
             value
         }
         Err(_) => {
-            let response = Response::ErrorResponse {
+            router.route(&Response::ErrorResponse {
                 response: ErrorResponse::ErrorWhileConnecting,
-            };
-            send_to_screen(tx_connection, tx_registration, tx_chats, response);
+            });
             return Err(ClientError::ErrorWhileConnecting);
         }
     };
 
-    let arc_socket = Arc::new(socket);
+    let arc_socket: SharedSocket = Arc::new(RwLock::new(Arc::new(socket)));
     let arc_socket_reader = arc_socket.clone();
+    let protocol = Arc::new(RwLock::new(ProtocolSession::new()));
+    let protocol_reader = protocol.clone();
 
     // channels que se van a comunicar con cada thread correspondiente a una conexion DCC
     let dcc_connections = HashMap::<String, SyncSender<String>>::new();
     let arc_dcc_connections = Arc::new(RwLock::new(dcc_connections));
     let arc_dcc_connections_clone = arc_dcc_connections.clone();
+    // salas de chat grupal DCC que el cliente hostea o a las que se unio
+    let arc_dcc_rooms: DccRooms = Arc::new(RwLock::new(HashMap::new()));
+    let arc_dcc_rooms_clone = arc_dcc_rooms.clone();
+    // archivos que se mandan por el server cuando no se puede establecer una conexion DCC
+    let arc_dcc_relays = DccRelays::default();
+    let arc_dcc_relays_clone = arc_dcc_relays.clone();
+
+    let tx_chats_status = tx_chats.clone();
+    handle_lag_meter(arc_socket.clone(), protocol.clone(), move |status, _| {
+        if tx_chats_status.send(status).is_ok() {};
+    });
 
     let tx_chats_clone = tx_chats.clone();
     let arc_dcc_interface_communication_clone = arc_dcc_interface_communication.clone();
     // recibe mensajes de server y se lo manda a la interfaz
     thread::spawn(move || loop {
-        if let Ok(msg) = read_socket(arc_socket_reader.clone()) {
-            if let ControlFlow::Break(_) = read_from_server(
-                msg,
-                tx_connection.clone(),
-                tx_registration.clone(),
-                tx_chats_clone.clone(),
-                arc_dcc_connections_clone.clone(),
-                arc_dcc_interface_communication_clone.clone(),
-            ) {
-                continue;
+        let socket = match current_socket(&arc_socket_reader) {
+            Ok(socket) => socket,
+            Err(_) => break,
+        };
+        match read_socket(socket) {
+            Ok(msg) => {
+                if let ControlFlow::Break(_) = read_from_server(
+                    msg,
+                    &arc_socket_reader,
+                    &protocol_reader,
+                    &router,
+                    tx_chats_clone.clone(),
+                    arc_dcc_connections_clone.clone(),
+                    arc_dcc_interface_communication_clone.clone(),
+                    arc_dcc_rooms_clone.clone(),
+                    arc_dcc_relays_clone.clone(),
+                ) {
+                    continue;
+                }
+            }
+            Err(_) => {
+                if resume_session(&address, &arc_socket_reader, &protocol_reader, &router).is_err()
+                {
+                    router.route(&Response::ErrorResponse {
+                        response: ErrorResponse::ErrorWhileConnecting,
+                    });
+                    break;
+                }
             }
         }
     });
@@ -144,10 +240,13 @@ fn run_client(
     loop {
         read_from_interface(
             &rx,
-            arc_socket.clone(),
+            &arc_socket,
+            &protocol,
             tx_chats.clone(),
             arc_dcc_connections.clone(),
             arc_dcc_interface_communication.clone(),
+            arc_dcc_rooms.clone(),
+            arc_dcc_relays.clone(),
         )?;
     }
 }
@@ -156,16 +255,24 @@ fn run_client(
 /// It will return ClientError::ErrorWhileConnectingWithInterface if there a is a problem receiving a message from the GUI.
 /// It will also return an error if it can't send the message to the server.
 /// It will return Ok(()) if the message was sent successfully.
+/// Every message is recorded in the protocol session, to resume the session after reconnecting.
+/// The files relayed through the server, when a DCC connection can't be established, are sent in their own thread.
+#[allow(clippy::too_many_arguments)]
 fn read_from_interface(
     rx: &Receiver<String>,
-    arc_socket: Arc<TcpStream>,
+    shared_socket: &SharedSocket,
+    protocol: &RwLock<ProtocolSession>,
     tx_chats: gtk::glib::Sender<Response>,
     dcc_connections: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
     arc_dcc_interface_communication: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
+    dcc_rooms: DccRooms,
+    dcc_relays: DccRelays,
 ) -> Result<(), ClientError> {
     match rx.recv() {
         Ok(msg) => {
             println!("[DEBUG] Mensaje recibido de la interfaz: {msg}");
+            protocol.write()?.record(&msg);
+            let arc_socket = current_socket(shared_socket)?;
             // chequeamos si es dcc
             // si es dcc, nos fijamos si es CHAT
             // si es CHAT creamos un thread que va a manejar el nuevo chat p2p con el server
@@ -176,11 +283,18 @@ fn read_from_interface(
                 return Ok(());
             }
             if let Ok(dcc_msg) = DccMessage::deserialize(msg.clone()) {
-                if dcc_msg.command == DccMessageType::Chat && dcc_msg.prefix.is_some() {
+                if dcc_msg.command == DccMessageType::Relay {
+                    return outgoing_relay_request(dcc_msg, arc_socket, dcc_relays, tx_chats);
+                }
+                if matches!(
+                    dcc_msg.command,
+                    DccMessageType::Chat | DccMessageType::SChat
+                ) && dcc_msg.prefix.is_some()
+                {
                     // first DCC CHAT command case
                     let ip = dcc_msg.parameters[1].to_owned();
                     let port = dcc_msg.parameters[2].to_owned();
-                    if check_address(ip, port) {
+                    if dcc_msg.passive_token().is_some() || check_address(ip, port) {
                         write_socket(arc_socket, &msg)?;
                     } else {
                         let sender = dcc_msg.parameters[0].to_owned();
@@ -196,6 +310,7 @@ fn read_from_interface(
                     dcc_connections,
                     tx_chats,
                     arc_dcc_interface_communication,
+                    dcc_rooms,
                 )?;
             } else {
                 write_socket(arc_socket, &msg)?;
@@ -209,35 +324,83 @@ fn read_from_interface(
     Ok(())
 }
 
-/// This function will read from the server, parse the message and send it to the GUI.
+/// This function will reconnect to the server after the connection was lost, unless the
+/// client quitted. Before each attempt it routes a Reconnecting response to the GUI, and a
+/// Reconnected response once the session is resumed.
+/// It will return ClientError::ErrorWhileConnecting if the client quitted or it couldn't reconnect.
+fn resume_session(
+    address: &str,
+    shared_socket: &SharedSocket,
+    protocol: &RwLock<ProtocolSession>,
+    router: &ResponseRouter,
+) -> Result<(), ClientError> {
+    if protocol.read()?.is_quitting() {
+        return Err(ClientError::ErrorWhileConnecting);
+    }
+    reconnect(address, shared_socket, protocol, |attempt, delay| {
+        router.route(&Response::CommandResponse {
+            response: CommandResponse::Reconnecting {
+                attempt,
+                delay: delay.as_secs(),
+            },
+        });
+    })?;
+    router.route(&Response::CommandResponse {
+        response: CommandResponse::Reconnected,
+    });
+    Ok(())
+}
+
+/// This function will read from the server, update the protocol session with the message and route it to the GUI.
 /// It will return ControlFlow::Break if an error ocurred while reading from the server or while parsing the message.
 /// It will return ControlFlow::Continue if the message was parsed and sent correctly.
+/// The CTCP queries are answered automatically to the sender, without sending them to the GUI.
+#[allow(clippy::too_many_arguments)]
 fn read_from_server(
     msg: String,
-    tx_connection: glib::Sender<Response>,
-    tx_registration: glib::Sender<Response>,
+    shared_socket: &SharedSocket,
+    protocol: &RwLock<ProtocolSession>,
+    router: &ResponseRouter,
     tx_chats: glib::Sender<Response>,
     dcc_connections: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
     arc_dcc_interface_communication: Arc<RwLock<HashMap<String, SyncSender<String>>>>,
+    dcc_rooms: DccRooms,
+    dcc_relays: DccRelays,
 ) -> ControlFlow<()> {
-    if let Ok(dcc_msg) = DccMessage::deserialize(msg.clone()) {
-        if manage_dcc_request_from_client(
-            dcc_msg,
-            dcc_connections,
-            tx_chats,
-            arc_dcc_interface_communication,
-        )
-        .is_ok()
-        {}
-    } else {
-        let response = match Response::serialize(msg) {
-            Some(value) => value,
-            None => {
-                println!("[ERROR] Error parsing response");
-                return ControlFlow::Break(());
+    let incoming = match protocol.write() {
+        Ok(mut protocol) => protocol.receive(msg, Instant::now()),
+        Err(_) => return ControlFlow::Break(()),
+    };
+    match incoming {
+        Incoming::Dcc(dcc_msg) => {
+            if let Ok(socket) = current_socket(shared_socket) {
+                if manage_dcc_request_from_client(
+                    dcc_msg,
+                    dcc_connections,
+                    tx_chats,
+                    arc_dcc_interface_communication,
+                    socket,
+                    dcc_rooms,
+                    dcc_relays,
+                )
+                .is_ok()
+                {}
             }
-        };
-        send_to_screen(tx_connection, tx_registration, tx_chats, response);
+        }
+        Incoming::Query { reply, .. } => {
+            if let Ok(socket) = current_socket(shared_socket) {
+                if write_socket(socket, &reply).is_ok() {};
+            }
+        }
+        Incoming::Show(response) | Incoming::Status { response, .. } => {
+            if !router.route(&response) {
+                println!("[ERROR] No screen shows the response {response}");
+            }
+        }
+        Incoming::Unparsed(_) => {
+            println!("[ERROR] Error parsing response");
+            return ControlFlow::Break(());
+        }
     }
 
     ControlFlow::Continue(())
@@ -246,27 +409,24 @@ fn read_from_server(
 /// This function will make the connection between the server and the client.
 /// It will receive the address from the GUI thread and will try to connect to the server.
 /// If the connection is successful, it will send a ConnectionSuccess message to the GUI thread.
-/// If the connection fails, it will send an ErrorWhileConnecting message to the GUI thread.
-/// It will return the TcpStream if the connection was successful.
+/// If the connection fails, it will route an ErrorWhileConnecting message to the GUI thread.
+/// It will return the TcpStream and the address if the connection was successful.
 /// It will return an error if the connection failed.
 fn connect_to_server(
     rx: &Receiver<String>,
-    tx: &glib::Sender<Response>,
-) -> Result<TcpStream, ClientError> {
-    let socket: TcpStream = match rx.recv() {
+    router: &ResponseRouter,
+) -> Result<(TcpStream, String), ClientError> {
+    let (socket, address) = match rx.recv() {
         Ok(address) => {
             println!("Conectándome a {address:?}");
             match TcpStream::connect(&address) {
-                Ok(socket) => socket,
+                Ok(socket) => (socket, address),
                 Err(_) => {
-                    match tx.send(Response::ErrorResponse {
+                    if !router.route(&Response::ErrorResponse {
                         response: ErrorResponse::ErrorWhileConnecting,
                     }) {
-                        Ok(_) => {}
-                        Err(_) => {
-                            println!("Error al enviar mensaje");
-                            return Err(ClientError::ErrorWhileConnectingWithInterface);
-                        }
+                        println!("Error al enviar mensaje");
+                        return Err(ClientError::ErrorWhileConnectingWithInterface);
                     }
                     return Err(ClientError::ErrorWhileConnecting);
                 }
@@ -277,7 +437,7 @@ fn connect_to_server(
             return Err(ClientError::ErrorWhileConnectingWithInterface);
         }
     };
-    Ok(socket)
+    Ok((socket, address))
 }
 
 /// Returns true if the ip:port address is valid, false otherwise.
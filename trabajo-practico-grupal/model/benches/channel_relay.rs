@@ -0,0 +1,76 @@
+//! Microbenchmark of the encoding of a message relayed to the members of a channel.
+//! It compares encoding the message once for each member, like the relay used to do, with
+//! sharing it between them. Run it with `cargo bench -p model`.
+
+use std::{hint::black_box, time::Instant};
+
+use model::responses::{
+    message::MessageResponse,
+    numeric::WireFormat,
+    writer::{ResponseWriter, SharedMessage},
+};
+
+/// The members of the channel the message is relayed to.
+static MEMBERS: usize = 500;
+/// The messages relayed in each run.
+static MESSAGES: usize = 200;
+
+fn format_of(member: usize) -> WireFormat {
+    if member.is_multiple_of(2) {
+        WireFormat::Custom
+    } else {
+        WireFormat::Numeric
+    }
+}
+
+fn channel_message(i: usize) -> String {
+    MessageResponse::ChannelPrivMsg {
+        channel: "#rust".to_string(),
+        sender: "alice".to_string(),
+        message: format!("message number {} sent to everyone in the channel", i),
+    }
+    .to_string()
+}
+
+fn encoded_per_member(members: &[String]) -> usize {
+    let mut bytes = 0;
+    for i in 0..MESSAGES {
+        for (member, nickname) in members.iter().enumerate() {
+            let line = channel_message(i);
+            let writer = ResponseWriter::new(nickname, format_of(member));
+            bytes += black_box(writer.encode(&writer.message_lines(&line))).len();
+        }
+    }
+    bytes
+}
+
+fn shared_by_members(members: &[String]) -> usize {
+    let mut bytes = 0;
+    for i in 0..MESSAGES {
+        let message = SharedMessage::new(channel_message(i).into());
+        for member in 0..members.len() {
+            bytes += black_box(message.bytes(format_of(member))).len();
+        }
+    }
+    bytes
+}
+
+fn run(name: &str, members: &[String], relay: fn(&[String]) -> usize) {
+    let start = Instant::now();
+    let bytes = relay(members);
+    let elapsed = start.elapsed();
+    println!(
+        "{:<20} {:>10.1} ns/delivery ({} bytes)",
+        name,
+        elapsed.as_nanos() as f64 / (MESSAGES * members.len()) as f64,
+        bytes
+    );
+}
+
+fn main() {
+    let members = (0..MEMBERS)
+        .map(|i| format!("user{}", i))
+        .collect::<Vec<_>>();
+    run("encoded per member", &members, encoded_per_member);
+    run("shared by members", &members, shared_by_members);
+}
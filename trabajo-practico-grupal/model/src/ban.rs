@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// Struct that represents a ban of a channel.
+/// # Fields
+/// * `nickname`: The nickname of the banned user.
+/// * `set_by`: The nickname of the operator who set the ban.
+/// * `set_at`: The time, in seconds since the epoch, when the ban was set.
+/// * `expires_at`: The time, in seconds since the epoch, when the ban expires. None if it is permanent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ban {
+    pub nickname: String,
+    pub set_by: String,
+    pub set_at: u64,
+    pub expires_at: Option<u64>,
+}
+
+impl Ban {
+    /// Creates a new instance of a permanent ban.
+    /// # Arguments
+    /// * `nickname` - The nickname of the banned user.
+    /// * `set_by` - The nickname of the operator who set the ban.
+    /// * `set_at` - The time, in seconds since the epoch, when the ban was set.
+    pub fn new(nickname: String, set_by: String, set_at: u64) -> Ban {
+        Ban {
+            nickname,
+            set_by,
+            set_at,
+            expires_at: None,
+        }
+    }
+
+    /// Returns true if the ban has an expiry time and it already passed.
+    /// # Arguments
+    /// * `now` - The current time, in seconds since the epoch.
+    pub fn is_expired(&self, now: u64) -> bool {
+        match self.expires_at {
+            Some(expires_at) => expires_at <= now,
+            None => false,
+        }
+    }
+
+    /// Creates a new instance of the ban from the `nickname:set_by:set_at:expires_at` format.
+    /// It is the opposite of the `Display` trait.
+    /// A bare nickname, as saved before the bans had metadata, is read as a ban with no author.
+    /// It will return none if the string is empty.
+    pub fn serialize(ban: &str) -> Option<Ban> {
+        let mut fields = ban.split(':');
+        let nickname = match fields.next() {
+            Some(nickname) if !nickname.is_empty() => nickname.to_string(),
+            _ => return None,
+        };
+        let set_by = fields.next().unwrap_or("").to_string();
+        let set_at = match fields.next() {
+            Some(set_at) => set_at.parse::<u64>().unwrap_or(0),
+            None => 0,
+        };
+        let mut ban = Ban::new(nickname, set_by, set_at);
+        if let Some(Ok(expires_at)) = fields.next().map(|e| e.parse::<u64>()) {
+            ban.expires_at = Some(expires_at);
+        }
+        Some(ban)
+    }
+}
+
+impl Display for Ban {
+    /// Formats the ban as `nickname:set_by:set_at:expires_at`, with an empty expiry if it is permanent.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let expires_at = match self.expires_at {
+            Some(expires_at) => expires_at.to_string(),
+            None => "".to_string(),
+        };
+        write!(
+            f,
+            "{}:{}:{}:{}",
+            self.nickname, self.set_by, self.set_at, expires_at
+        )
+    }
+}
@@ -5,9 +5,12 @@ modos:
 +s -> secret channel
 +t -> topic settable by channel operator only
 +m -> moderated channel
++f -> forward to another channel when the join fails
++G -> filter the messages with the rules of the channel
++P -> permanent channel, kept when it is left empty
 */
-use crate::channelflag::ChannelFlag;
-use std::fmt::Display;
+use crate::{ban::Ban, channel_stats::ChannelStats, channelflag::ChannelFlag, filter::FilterRule};
+use serde::{Deserialize, Serialize};
 
 /// Struct that represents a channel.
 /// # Fields
@@ -15,22 +18,37 @@ use std::fmt::Display;
 /// * `topic`: The topic of the channel.
 /// * `users`: Vector that contains the users that are in the channel.
 /// * `operators`: Vector that contains the operators (users with special permissions) of the channel.
-/// * `banned_users`: Vector that contains the banned users of the channel.
+/// * `banned_users`: Vector that contains the bans of the channel, with who set them and when.
 /// * `password`: The password of the channel.
 /// * `modes`: The modes of the channel.
 /// * `limit`: The limit of users that can be in the channel.
 /// * `moderators`: The users that can talk in a moderated channel.
-#[derive(Debug, Clone)]
+/// * `forward`: The channel the users are sent to when they can't join this one because it is full or invite only.
+/// * `filters`: The rules of the content filter, applied to the messages while the channel has the `+G` mode.
+/// * `stats`: The statistics of the messages sent to the channel, shown by CHANSTATS.
+///
+/// The lists and the statistics missing from a saved channel are read as empty.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Channel {
     pub name: String,
     pub topic: String,
+    #[serde(default)]
     pub users: Vec<String>,
+    #[serde(default)]
     pub operators: Vec<String>,
-    pub banned_users: Vec<String>,
+    #[serde(default)]
+    pub banned_users: Vec<Ban>,
     pub password: Option<String>,
+    #[serde(default)]
     pub modes: Vec<ChannelFlag>,
     pub limit: Option<i32>,
+    #[serde(default)]
     pub moderators: Vec<String>,
+    pub forward: Option<String>,
+    #[serde(default)]
+    pub filters: Vec<FilterRule>,
+    #[serde(default)]
+    pub stats: ChannelStats,
 }
 impl Channel {
     /// Creates a new instance of the channel.
@@ -49,38 +67,138 @@ impl Channel {
             modes: Vec::new(),
             limit: None,
             moderators: Vec::new(),
+            forward: None,
+            filters: Vec::new(),
+            stats: ChannelStats::default(),
         }
     }
-}
 
-impl Display for Channel {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let mut channel_data = vec![
-            self.name.to_owned(),
-            self.topic.to_owned(),
-            self.users.join(","),
-        ];
-        let mut pass = "".to_string();
-        if let Some(p) = self.password.to_owned() {
-            pass = p;
+    /// Returns true if the channel can be seen by the given user when listing channels.
+    /// Private and secret channels can only be seen by their members.
+    /// # Arguments
+    /// * `nickname` - The nickname of the user.
+    pub fn is_visible_to(&self, nickname: &str) -> bool {
+        self.users.iter().any(|u| u == nickname)
+            || !(self.modes.contains(&ChannelFlag::Private)
+                || self.modes.contains(&ChannelFlag::Secret))
+    }
+
+    /// Returns true if the channel is kept when its last member leaves, instead of being removed.
+    pub fn is_permanent(&self) -> bool {
+        self.modes.contains(&ChannelFlag::Permanent)
+    }
+
+    /// Returns true if the user is banned from the channel. The bans that expired don't count,
+    /// even before they are removed.
+    /// # Arguments
+    /// * `nickname` - The nickname of the user.
+    /// * `now` - The current time, in seconds since the epoch.
+    pub fn is_banned(&self, nickname: &str, now: u64) -> bool {
+        self.banned_users
+            .iter()
+            .any(|ban| ban.nickname == nickname && !ban.is_expired(now))
+    }
+
+    /// Returns true if the user is a member of the channel.
+    /// # Arguments
+    /// * `nickname` - The nickname of the user.
+    pub fn has_member(&self, nickname: &str) -> bool {
+        self.users.iter().any(|u| u == nickname)
+    }
+
+    /// Adds the user to the members of the channel, after the ones that joined before.
+    /// Returns false if the user already was a member.
+    /// # Arguments
+    /// * `nickname` - The nickname of the user.
+    pub fn add_member(&mut self, nickname: &str) -> bool {
+        if self.has_member(nickname) {
+            return false;
         }
-        channel_data.push(pass);
-        channel_data.push(self.banned_users.join(","));
-        channel_data.push(self.operators.join(","));
-        channel_data.push(
-            self.modes
-                .iter()
-                .cloned()
-                .map(|x| ChannelFlag::to_string(&x))
-                .collect::<Vec<_>>()
-                .join(","),
-        );
-        let mut limit = "".to_string();
-        if let Some(l) = self.limit {
-            limit = l.to_string();
+        self.users.push(nickname.to_string());
+        true
+    }
+
+    /// Removes the user from the members of the channel.
+    /// Returns false if the user wasn't a member.
+    /// # Arguments
+    /// * `nickname` - The nickname of the user.
+    pub fn remove_member(&mut self, nickname: &str) -> bool {
+        let members = self.users.len();
+        self.users.retain(|u| u != nickname);
+        self.users.len() != members
+    }
+
+    /// Gives the operator privileges to the member that has been in the channel the longest,
+    /// when no operator is left among the members that are still present.
+    /// Returns the nickname of the new operator, or none if the channel still has an operator.
+    /// # Arguments
+    /// * `is_present` - Returns true if the member is still present in the channel.
+    pub fn transfer_operator<F: Fn(&str) -> bool>(&mut self, is_present: F) -> Option<String> {
+        if self
+            .operators
+            .iter()
+            .any(|op| self.users.contains(op) && is_present(op))
+        {
+            return None;
+        }
+        let successor = self.users.iter().find(|u| is_present(u))?.to_owned();
+        self.operators.push(successor.to_owned());
+        Some(successor)
+    }
+
+    /// Returns the checksum of the members of the channel, which doesn't depend on the order in
+    /// which they joined, so that the servers of the network can compare their channels.
+    /// It is the 64 bits FNV-1a hash of the sorted nicknames, each one followed by a space.
+    pub fn members_checksum(&self) -> u64 {
+        let mut users = self.users.iter().collect::<Vec<_>>();
+        users.sort();
+        users
+            .iter()
+            .flat_map(|user| user.bytes().chain(std::iter::once(b' ')))
+            .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+            })
+    }
+
+    /// Returns the modes of the channel in the standard irc format, like `+ntklf <key> <limit> <forward>`.
+    /// The flags without parameters come first, followed by the key, the limit and the forward channel.
+    /// # Arguments
+    /// * `show_key` - If false, the key is replaced by `*`.
+    pub fn get_mode_string(&self, show_key: bool) -> String {
+        let mut flags = "+".to_string();
+        let mut params = vec![];
+        for flag in [
+            ChannelFlag::NoMessageFromOutside,
+            ChannelFlag::TopicSettableOnlyOperators,
+            ChannelFlag::InviteOnly,
+            ChannelFlag::ModeratedChannel,
+            ChannelFlag::Private,
+            ChannelFlag::Secret,
+            ChannelFlag::Filtered,
+            ChannelFlag::Permanent,
+        ] {
+            if self.modes.contains(&flag) {
+                flags.push_str(&ChannelFlag::to_string(&flag));
+            }
+        }
+        if let Some(password) = &self.password {
+            flags.push_str(&ChannelFlag::to_string(&ChannelFlag::ChannelKey));
+            if show_key {
+                params.push(password.to_owned());
+            } else {
+                params.push("*".to_string());
+            }
         }
-        channel_data.push(limit);
-        channel_data.push(self.moderators.join(","));
-        write!(f, "{}", channel_data.join(";"))
+        if let Some(limit) = self.limit {
+            flags.push_str(&ChannelFlag::to_string(&ChannelFlag::UserLimit));
+            params.push(limit.to_string());
+        }
+        if let Some(forward) = &self.forward {
+            flags.push_str(&ChannelFlag::to_string(&ChannelFlag::Forward));
+            params.push(forward.to_owned());
+        }
+
+        params.insert(0, flags);
+        params.join(" ")
     }
 }
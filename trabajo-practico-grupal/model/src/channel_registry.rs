@@ -0,0 +1,123 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use crate::{
+    channel::Channel,
+    locks::{read_lock, write_lock},
+};
+
+/// Struct that holds the channels of the server, shared by the handlers of every connection.
+/// Each method locks the channels only while it runs, so the handlers that must keep them
+/// locked across several steps use the lock of the registry instead.
+/// # Fields
+/// * `channels`: A hashmap that contains the channels of the server, by name.
+#[derive(Debug, Clone, Default)]
+pub struct ChannelRegistry {
+    channels: Arc<RwLock<HashMap<String, Channel>>>,
+}
+
+impl ChannelRegistry {
+    /// Creates a registry with the channels received, like the ones loaded from the database.
+    /// # Arguments
+    /// * `channels` - The channels of the server, by name.
+    pub fn new(channels: HashMap<String, Channel>) -> ChannelRegistry {
+        ChannelRegistry {
+            channels: Arc::new(RwLock::new(channels)),
+        }
+    }
+
+    /// Returns the lock of the channels.
+    pub fn as_lock(&self) -> &RwLock<HashMap<String, Channel>> {
+        &self.channels
+    }
+
+    /// Returns true if the channel exists.
+    /// # Arguments
+    /// * `channel` - The name of the channel.
+    pub fn contains(&self, channel: &str) -> bool {
+        read_lock(&self.channels, "channels").contains_key(channel)
+    }
+
+    /// Returns the members of the channel, in the order they joined, or none if it doesn't exist.
+    /// # Arguments
+    /// * `channel` - The name of the channel.
+    pub fn members(&self, channel: &str) -> Option<Vec<String>> {
+        read_lock(&self.channels, "channels")
+            .get(channel)
+            .map(|channel| channel.users.to_owned())
+    }
+
+    /// Returns true if the user is a member of the channel.
+    /// # Arguments
+    /// * `channel` - The name of the channel.
+    /// * `nickname` - The nickname of the user.
+    pub fn is_member(&self, channel: &str, nickname: &str) -> bool {
+        read_lock(&self.channels, "channels")
+            .get(channel)
+            .is_some_and(|channel| channel.has_member(nickname))
+    }
+
+    /// Adds the user to the members of the channel.
+    /// Returns false if the channel doesn't exist or the user already was a member.
+    /// # Arguments
+    /// * `channel` - The name of the channel.
+    /// * `nickname` - The nickname of the user.
+    pub fn add_member(&self, channel: &str, nickname: &str) -> bool {
+        write_lock(&self.channels, "channels")
+            .get_mut(channel)
+            .is_some_and(|channel| channel.add_member(nickname))
+    }
+
+    /// Removes the user from the members of the channel.
+    /// Returns false if the channel doesn't exist or the user wasn't a member.
+    /// # Arguments
+    /// * `channel` - The name of the channel.
+    /// * `nickname` - The nickname of the user.
+    pub fn remove_member(&self, channel: &str, nickname: &str) -> bool {
+        write_lock(&self.channels, "channels")
+            .get_mut(channel)
+            .is_some_and(|channel| channel.remove_member(nickname))
+    }
+
+    /// Saves the channel, replacing the one with the same name.
+    /// # Arguments
+    /// * `channel` - The channel to save.
+    pub fn insert(&self, channel: Channel) {
+        write_lock(&self.channels, "channels").insert(channel.name.to_owned(), channel);
+    }
+
+    /// Removes the channel, returning it if it existed.
+    /// # Arguments
+    /// * `channel` - The name of the channel.
+    pub fn remove(&self, channel: &str) -> Option<Channel> {
+        write_lock(&self.channels, "channels").remove(channel)
+    }
+}
+
+#[cfg(test)]
+mod channel_registry_tests {
+    use super::ChannelRegistry;
+    use crate::channel::Channel;
+
+    #[test]
+    fn test_members_are_added_once_and_removed() {
+        let channels = ChannelRegistry::default();
+        channels.insert(Channel::new(
+            "#rust".to_string(),
+            "".to_string(),
+            vec!["alice".to_string()],
+        ));
+
+        assert!(channels.add_member("#rust", "bob"));
+        assert!(!channels.add_member("#rust", "bob"));
+        assert!(!channels.add_member("#missing", "bob"));
+        assert!(channels.is_member("#rust", "bob"));
+        assert!(channels.remove_member("#rust", "alice"));
+        assert!(!channels.remove_member("#rust", "alice"));
+
+        assert_eq!(channels.members("#rust"), Some(vec!["bob".to_string()]));
+        assert_eq!(channels.members("#missing"), None);
+    }
+}
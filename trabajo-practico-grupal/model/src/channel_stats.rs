@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+static SECONDS_PER_HOUR: u64 = 3600;
+static HOURS_KEPT: u64 = 24;
+
+/// Struct that holds the statistics of the messages sent to a channel.
+/// The totals are saved with the channel, while the messages of each hour are only kept in
+/// memory for the last day.
+/// # Fields
+/// * `created`: The time, in seconds since the epoch, when the channel was created, or when this
+///   server learned about it. It is 0 if it is unknown.
+/// * `messages`: The amount of messages sent to the channel.
+/// * `talkers`: The amount of messages sent to the channel by each nickname.
+/// * `hours`: The amount of messages of each of the last hours, with the hours counted since the epoch.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "StatsRecord", from = "StatsRecord")]
+pub struct ChannelStats {
+    pub created: u64,
+    pub messages: u64,
+    pub talkers: HashMap<String, u64>,
+    pub hours: Vec<(u64, u64)>,
+}
+
+impl ChannelStats {
+    /// Creates the statistics of a new channel, without messages.
+    /// # Arguments
+    /// * `created` - The time, in seconds since the epoch, when the channel was created.
+    pub fn new(created: u64) -> ChannelStats {
+        ChannelStats {
+            created,
+            ..Default::default()
+        }
+    }
+
+    /// Counts a message sent to the channel, forgetting the hours older than a day.
+    /// # Arguments
+    /// * `nickname` - The nickname of the sender.
+    /// * `now` - The time of the message, in seconds since the epoch.
+    pub fn record_message(&mut self, nickname: &str, now: u64) {
+        self.messages += 1;
+        *self.talkers.entry(nickname.to_string()).or_insert(0) += 1;
+        let hour = now / SECONDS_PER_HOUR;
+        match self.hours.last_mut() {
+            Some((last, count)) if *last == hour => *count += 1,
+            _ => self.hours.push((hour, 1)),
+        }
+        self.hours
+            .retain(|(kept, _)| kept + HOURS_KEPT > hour && *kept <= hour);
+    }
+
+    /// Returns the amount of messages sent to the channel in the last hours, up to a day.
+    /// # Arguments
+    /// * `now` - The current time, in seconds since the epoch.
+    /// * `hours` - The amount of hours, counting the current one.
+    pub fn messages_in_last_hours(&self, now: u64, hours: u64) -> u64 {
+        let hour = now / SECONDS_PER_HOUR;
+        self.hours
+            .iter()
+            .filter(|(counted, _)| counted + hours > hour && *counted <= hour)
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    /// Returns the nicknames that sent the most messages to the channel, with their amount of
+    /// messages, from the most active. The ties are sorted by nickname.
+    /// # Arguments
+    /// * `amount` - The maximum amount of nicknames returned.
+    pub fn top_talkers(&self, amount: usize) -> Vec<(String, u64)> {
+        let mut talkers = self
+            .talkers
+            .iter()
+            .map(|(nickname, messages)| (nickname.to_owned(), *messages))
+            .collect::<Vec<_>>();
+        talkers.sort_by(|(a, a_messages), (b, b_messages)| {
+            b_messages.cmp(a_messages).then_with(|| a.cmp(b))
+        });
+        talkers.truncate(amount);
+        talkers
+    }
+}
+
+/// The totals of the statistics saved with the channel, with the talkers from the most active.
+/// The channels saved before they had statistics have none, so the missing fields are empty.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct StatsRecord {
+    created: u64,
+    messages: u64,
+    talkers: Vec<TalkerRecord>,
+}
+
+/// The amount of messages sent to a channel by a nickname.
+#[derive(Serialize, Deserialize)]
+struct TalkerRecord {
+    nickname: String,
+    messages: u64,
+}
+
+impl From<ChannelStats> for StatsRecord {
+    fn from(stats: ChannelStats) -> StatsRecord {
+        let talkers = stats
+            .top_talkers(stats.talkers.len())
+            .into_iter()
+            .map(|(nickname, messages)| TalkerRecord { nickname, messages })
+            .collect();
+        StatsRecord {
+            created: stats.created,
+            messages: stats.messages,
+            talkers,
+        }
+    }
+}
+
+impl From<StatsRecord> for ChannelStats {
+    /// The messages of each hour are not saved, so they start empty.
+    fn from(record: StatsRecord) -> ChannelStats {
+        ChannelStats {
+            created: record.created,
+            messages: record.messages,
+            talkers: record
+                .talkers
+                .into_iter()
+                .map(|talker| (talker.nickname, talker.messages))
+                .collect(),
+            hours: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod channel_stats_tests {
+    use super::ChannelStats;
+    use crate::json::{FromJson, ToJson};
+
+    #[test]
+    fn test_messages_are_counted_by_hour_and_by_nickname() {
+        let mut stats = ChannelStats::new(100);
+
+        stats.record_message("alice", 3600);
+        stats.record_message("bob", 3700);
+        stats.record_message("alice", 7300);
+        stats.record_message("carol", 7400);
+        stats.record_message("alice", 7500);
+
+        assert_eq!(stats.messages, 5);
+        assert_eq!(stats.messages_in_last_hours(7500, 1), 3);
+        assert_eq!(stats.messages_in_last_hours(7500, 24), 5);
+        assert_eq!(
+            stats.top_talkers(2),
+            vec![("alice".to_string(), 3), ("bob".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_hours_older_than_a_day_are_forgotten() {
+        let mut stats = ChannelStats::new(0);
+
+        stats.record_message("alice", 0);
+        stats.record_message("alice", 24 * 3600);
+
+        assert_eq!(stats.hours, vec![(24, 1)]);
+        assert_eq!(stats.messages, 2);
+    }
+
+    #[test]
+    fn test_totals_are_saved_and_read_as_json() {
+        let mut stats = ChannelStats::new(10);
+        stats.record_message("alice", 20);
+
+        let json = stats.to_json();
+        let read = ChannelStats::from_json(&json).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"created":10,"messages":1,"talkers":[{"nickname":"alice","messages":1}]}"#
+        );
+        assert_eq!(read.talkers, stats.talkers);
+        assert!(read.hours.is_empty());
+        assert_eq!(
+            ChannelStats::from_json("{}").unwrap(),
+            ChannelStats::default()
+        );
+    }
+}
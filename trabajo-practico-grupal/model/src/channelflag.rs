@@ -1,7 +1,9 @@
+use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 
 ///Enum that represents the flags of the channel, to set the channel mode.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
 pub enum ChannelFlag {
     Private,
     Secret,
@@ -14,6 +16,9 @@ pub enum ChannelFlag {
     Ban,
     ChannelKey,
     SpeakInModeratedChannel,
+    Forward,
+    Filtered,
+    Permanent,
     Other,
 }
 
@@ -32,6 +37,9 @@ impl ChannelFlag {
             ChannelFlag::ChannelOperator => "o".to_string(),
             ChannelFlag::ChannelKey => "k".to_string(),
             ChannelFlag::SpeakInModeratedChannel => "v".to_string(),
+            ChannelFlag::Forward => "f".to_string(),
+            ChannelFlag::Filtered => "G".to_string(),
+            ChannelFlag::Permanent => "P".to_string(),
             ChannelFlag::Other => "-".to_string(),
         }
     }
@@ -50,6 +58,9 @@ impl ChannelFlag {
             'k' => ChannelFlag::ChannelKey,
             'v' => ChannelFlag::SpeakInModeratedChannel,
             'o' => ChannelFlag::ChannelOperator,
+            'f' => ChannelFlag::Forward,
+            'G' => ChannelFlag::Filtered,
+            'P' => ChannelFlag::Permanent,
             _ => ChannelFlag::Other,
         }
     }
@@ -68,6 +79,9 @@ impl ChannelFlag {
             ChannelFlag::ChannelKey,
             ChannelFlag::SpeakInModeratedChannel,
             ChannelFlag::ChannelOperator,
+            ChannelFlag::Forward,
+            ChannelFlag::Filtered,
+            ChannelFlag::Permanent,
         ]
     }
 }
@@ -86,8 +100,30 @@ impl Display for ChannelFlag {
             ChannelFlag::ChannelOperator => "ChannelOperator".to_string(),
             ChannelFlag::ChannelKey => "ChannelKey".to_string(),
             ChannelFlag::SpeakInModeratedChannel => "SpeakInModeratedChannel".to_string(),
+            ChannelFlag::Forward => "Forward".to_string(),
+            ChannelFlag::Filtered => "Filtered".to_string(),
+            ChannelFlag::Permanent => "Permanent".to_string(),
             ChannelFlag::Other => "Other".to_string(),
         };
         write!(f, "{}", r)
     }
 }
+
+impl From<ChannelFlag> for String {
+    /// Writes the character of the flag.
+    fn from(flag: ChannelFlag) -> String {
+        ChannelFlag::to_string(&flag)
+    }
+}
+
+impl TryFrom<String> for ChannelFlag {
+    type Error = String;
+
+    /// Reads the flag of the first character.
+    fn try_from(flag: String) -> Result<Self, Self::Error> {
+        match flag.chars().next() {
+            Some(flag) => Ok(ChannelFlag::match_flag(flag)),
+            None => Err("A flag can't be empty".to_string()),
+        }
+    }
+}
@@ -1,5 +1,6 @@
-use crate::userflag::UserFlag;
-use std::fmt::Display;
+use serde::{Deserialize, Serialize};
+
+use crate::{mask::matches_mask, preferences::Preferences, userflag::UserFlag};
 
 /// Struct that represents a client.
 /// # Fields
@@ -9,20 +10,42 @@ use std::fmt::Display;
 /// * `hostname`: The hostname of the client.
 /// * `servername`: The servername of the client.
 /// * `password`: The password of the client. It can be None.
+/// * `email`: The email the nickname was registered with in NickServ. It can be None.
 /// * `connected`: A boolean that indicates if the client is connected.
 /// * `away_message`: When it is Some, it is the message that is sent to other clients when they send a PRIVMSG to the client.
+/// * `auto_away`: A boolean that indicates if the away message was set by the server because the client was idle.
 /// * `modes`: Vector that contains the modes of the client.
-#[derive(Debug, Clone)]
+/// * `signon`: The time, in seconds since the epoch, when the client connected.
+/// * `last_activity`: The time, in seconds since the epoch, of the last command sent by the client.
+/// * `silenced`: The nickname masks whose messages and invitations are not delivered to the client.
+/// * `preferences`: The settings of the client applied when it registers.
+///
+/// The state of the connection, like the time the client connected, is not saved, so a saved
+/// client is read as disconnected.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Client {
-    pub username: String,
     pub nickname: String,
+    pub username: String,
     pub hostname: String,
     pub servername: String,
     pub realname: String,
     pub password: Option<String>,
+    pub email: Option<String>,
+    #[serde(skip)]
     pub connected: bool,
     pub away_message: Option<String>,
+    #[serde(skip)]
+    pub auto_away: bool,
+    #[serde(default)]
     pub modes: Vec<UserFlag>,
+    #[serde(skip)]
+    pub signon: u64,
+    #[serde(skip)]
+    pub last_activity: u64,
+    #[serde(default)]
+    pub silenced: Vec<String>,
+    #[serde(default)]
+    pub preferences: Preferences,
 }
 
 impl Client {
@@ -43,40 +66,31 @@ impl Client {
             servername,
             realname,
             password,
+            email: None,
             connected,
             away_message: None,
+            auto_away: false,
             modes: Vec::new(),
+            signon: 0,
+            last_activity: 0,
+            silenced: Vec::new(),
+            preferences: Preferences::default(),
         }
     }
-}
 
-impl Display for Client {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let mut client_data = vec![
-            self.nickname.to_owned(),
-            self.username.to_owned(),
-            self.hostname.to_owned(),
-            self.servername.to_owned(),
-            self.realname.to_owned(),
-        ];
-        let mut pass = "".to_string();
-        if let Some(p) = self.password.to_owned() {
-            pass = p;
-        }
-        client_data.push(pass);
-        let mut away = "".to_string();
-        if let Some(a) = self.away_message.to_owned() {
-            away = a;
-        }
-        client_data.push(away);
-        client_data.push(
-            self.modes
-                .iter()
-                .cloned()
-                .map(|x| UserFlag::to_string(&x))
-                .collect::<Vec<_>>()
-                .join(","),
-        );
-        write!(f, "{}", client_data.join(";"))
+    /// Returns the seconds since the last command sent by the client.
+    /// # Arguments
+    /// * `now` - The current time, in seconds since the epoch.
+    pub fn get_idle_time(&self, now: u64) -> u64 {
+        now.saturating_sub(self.last_activity)
+    }
+
+    /// Returns true if the nickname matches one of the masks of the silence list of the client.
+    /// # Arguments
+    /// * `nickname` - The nickname of the sender of a message.
+    pub fn is_silencing(&self, nickname: &str) -> bool {
+        self.silenced
+            .iter()
+            .any(|mask| matches_mask(mask, nickname))
     }
 }
@@ -14,6 +14,7 @@ use crate::{dcc::DccMessageError, message::MessageError};
 /// * CannotPrivmsgToServer -> The client tried to send a message to the server
 /// * CannotWriteSocket -> The client could not write to the socket
 /// * ConnectionFinished -> The connection with the server finished
+/// * KeyExchangeError -> The keys of a secure DCC connection could not be exchanged
 
 #[derive(Debug)]
 pub enum ClientError {
@@ -35,6 +36,7 @@ pub enum ClientError {
     GuiCommunicationError,
     NoOngoingTransfer,
     OngoingTransfer,
+    KeyExchangeError,
 }
 
 impl From<Error> for ClientError {
@@ -52,6 +54,8 @@ impl From<MessageError> for ClientError {
             MessageError::EmptyCommand => ClientError::EmptyCommand,
             MessageError::EmptyMessage => ClientError::EmptyMessage,
             MessageError::InvalidCommand => ClientError::InvalidCommand,
+            MessageError::InvalidPrefix => ClientError::MessageError,
+            MessageError::InvalidEncoding => ClientError::MessageError,
         }
     }
 }
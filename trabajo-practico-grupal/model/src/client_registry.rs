@@ -0,0 +1,382 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    ops::{Deref, DerefMut, Index},
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use crate::{
+    client::Client,
+    locks::{read_lock, write_lock},
+};
+
+/// The shards the clients are split in.
+static CLIENT_SHARDS: usize = 16;
+
+/// The clients of a shard, by nickname.
+type ClientShard = HashMap<String, Client>;
+
+/// The whole registry locked for reading.
+pub type ClientsReadGuard<'a> = ClientsGuard<RwLockReadGuard<'a, HashMap<String, Client>>>;
+
+/// The whole registry locked for writing.
+pub type ClientsWriteGuard<'a> = ClientsGuard<RwLockWriteGuard<'a, HashMap<String, Client>>>;
+
+/// Struct that holds the clients registered in the server, connected or not.
+///
+/// The clients are split in shards by the hash of their nickname, each one behind its own lock,
+/// so the registrations and the commands of different clients don't wait for each other.
+/// Each method locks only the shard of the client it receives, and only while it runs.
+///
+/// The handlers that must keep every client locked across several steps, or go through all of
+/// them, lock the whole registry with `read_all` or `write_all` instead. The shards are always
+/// locked in the same order, so those handlers never wait for each other, and the whole
+/// registry takes the place of the clients in the order of the locks of the server.
+/// # Fields
+/// * `shards`: The clients of the server, split in shards.
+#[derive(Debug, Clone)]
+pub struct ClientRegistry {
+    shards: Arc<[RwLock<ClientShard>]>,
+}
+
+impl Default for ClientRegistry {
+    fn default() -> Self {
+        ClientRegistry {
+            shards: (0..CLIENT_SHARDS)
+                .map(|_| RwLock::new(HashMap::new()))
+                .collect(),
+        }
+    }
+}
+
+impl ClientRegistry {
+    /// Creates a registry with the clients received, like the ones loaded from the database.
+    /// # Arguments
+    /// * `clients` - The clients of the server, by nickname.
+    pub fn new(clients: HashMap<String, Client>) -> ClientRegistry {
+        let registry = ClientRegistry::default();
+        let mut all = registry.write_all();
+        for (nickname, client) in clients {
+            all.insert(nickname, client);
+        }
+        drop(all);
+        registry
+    }
+
+    /// Locks every shard for reading, in order.
+    pub fn read_all(&self) -> ClientsReadGuard<'_> {
+        ClientsGuard {
+            shards: self
+                .shards
+                .iter()
+                .map(|shard| read_lock(shard, "clients"))
+                .collect(),
+        }
+    }
+
+    /// Locks every shard for writing, in order.
+    pub fn write_all(&self) -> ClientsWriteGuard<'_> {
+        ClientsGuard {
+            shards: self
+                .shards
+                .iter()
+                .map(|shard| write_lock(shard, "clients"))
+                .collect(),
+        }
+    }
+
+    /// Locks the shard of the client for writing, so that it can be checked and changed in
+    /// several steps without locking the clients of the other shards.
+    /// # Arguments
+    /// * `nickname` - The nickname of the client.
+    pub fn lock_client(&self, nickname: &str) -> ClientEntry<'_> {
+        ClientEntry {
+            nickname: nickname.to_owned(),
+            shard: write_lock(self.shard(nickname), "clients"),
+        }
+    }
+
+    /// Returns true if the nickname is registered in the server.
+    /// # Arguments
+    /// * `nickname` - The nickname of the client.
+    pub fn contains(&self, nickname: &str) -> bool {
+        read_lock(self.shard(nickname), "clients").contains_key(nickname)
+    }
+
+    /// Returns true if the client is registered and connected to the server.
+    /// # Arguments
+    /// * `nickname` - The nickname of the client.
+    pub fn is_connected(&self, nickname: &str) -> bool {
+        read_lock(self.shard(nickname), "clients")
+            .get(nickname)
+            .is_some_and(|client| client.connected)
+    }
+
+    /// Returns a copy of the client, or none if it is not registered.
+    /// # Arguments
+    /// * `nickname` - The nickname of the client.
+    pub fn get(&self, nickname: &str) -> Option<Client> {
+        read_lock(self.shard(nickname), "clients")
+            .get(nickname)
+            .cloned()
+    }
+
+    /// Saves the client, replacing the one with the same nickname.
+    /// # Arguments
+    /// * `client` - The client to save.
+    pub fn insert(&self, client: Client) {
+        write_lock(self.shard(&client.nickname), "clients")
+            .insert(client.nickname.to_owned(), client);
+    }
+
+    /// Changes the client with the function received.
+    /// Returns false if the client is not registered.
+    /// # Arguments
+    /// * `nickname` - The nickname of the client.
+    /// * `update` - The function that changes the client.
+    pub fn update<F: FnOnce(&mut Client)>(&self, nickname: &str, update: F) -> bool {
+        match write_lock(self.shard(nickname), "clients").get_mut(nickname) {
+            Some(client) => {
+                update(client);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the lock of the shard of the nickname.
+    fn shard(&self, nickname: &str) -> &RwLock<ClientShard> {
+        &self.shards[shard_index(nickname)]
+    }
+}
+
+/// Returns the index of the shard of the nickname.
+fn shard_index(nickname: &str) -> usize {
+    let mut hasher = DefaultHasher::new();
+    nickname.hash(&mut hasher);
+    hasher.finish() as usize % CLIENT_SHARDS
+}
+
+/// Struct that holds the lock of the shard of a client, registered or not.
+/// # Fields
+/// * `nickname`: The nickname of the client.
+/// * `shard`: The locked shard of the client.
+#[derive(Debug)]
+pub struct ClientEntry<'a> {
+    nickname: String,
+    shard: RwLockWriteGuard<'a, ClientShard>,
+}
+
+impl ClientEntry<'_> {
+    /// Returns the client to change it, or none if it is not registered.
+    pub fn get_mut(&mut self) -> Option<&mut Client> {
+        self.shard.get_mut(&self.nickname)
+    }
+
+    /// Saves the client, replacing the one registered with its nickname.
+    /// # Arguments
+    /// * `client` - The client to save, with the nickname of the entry.
+    pub fn insert(&mut self, client: Client) {
+        self.shard.insert(self.nickname.to_owned(), client);
+    }
+}
+
+/// Struct that holds the locks of every shard of the registry, used like a map of the clients
+/// by nickname.
+/// # Fields
+/// * `shards`: The locked shards, in order.
+#[derive(Debug)]
+pub struct ClientsGuard<G> {
+    shards: Vec<G>,
+}
+
+impl<G: Deref<Target = ClientShard>> ClientsGuard<G> {
+    /// Returns the client, or none if it is not registered.
+    /// # Arguments
+    /// * `nickname` - The nickname of the client.
+    pub fn get(&self, nickname: &str) -> Option<&Client> {
+        self.shards[shard_index(nickname)].get(nickname)
+    }
+
+    /// Returns true if the nickname is registered in the server.
+    /// # Arguments
+    /// * `nickname` - The nickname of the client.
+    pub fn contains_key(&self, nickname: &str) -> bool {
+        self.get(nickname).is_some()
+    }
+
+    /// Returns the nickname and the client of every registered client.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Client)> {
+        self.shards.iter().flat_map(|shard| shard.iter())
+    }
+
+    /// Returns the nicknames of the registered clients.
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.iter().map(|(nickname, _)| nickname)
+    }
+
+    /// Returns the registered clients.
+    pub fn values(&self) -> impl Iterator<Item = &Client> {
+        self.iter().map(|(_, client)| client)
+    }
+
+    /// Returns the number of registered clients.
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.len()).sum()
+    }
+
+    /// Returns true if there are no registered clients.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<G: DerefMut<Target = ClientShard>> ClientsGuard<G> {
+    /// Returns the client to change it, or none if it is not registered.
+    /// # Arguments
+    /// * `nickname` - The nickname of the client.
+    pub fn get_mut(&mut self, nickname: &str) -> Option<&mut Client> {
+        self.shards[shard_index(nickname)].get_mut(nickname)
+    }
+
+    /// Saves the client with the nickname, returning the one it replaced.
+    /// # Arguments
+    /// * `nickname` - The nickname of the client.
+    /// * `client` - The client to save.
+    pub fn insert(&mut self, nickname: String, client: Client) -> Option<Client> {
+        self.shards[shard_index(&nickname)].insert(nickname, client)
+    }
+
+    /// Removes the client, returning it if it was registered.
+    /// # Arguments
+    /// * `nickname` - The nickname of the client.
+    pub fn remove(&mut self, nickname: &str) -> Option<Client> {
+        self.shards[shard_index(nickname)].remove(nickname)
+    }
+
+    /// Returns every registered client to change them.
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut Client> {
+        self.shards.iter_mut().flat_map(|shard| shard.values_mut())
+    }
+}
+
+impl<G: Deref<Target = ClientShard>> Index<&str> for ClientsGuard<G> {
+    type Output = Client;
+
+    fn index(&self, nickname: &str) -> &Client {
+        match self.get(nickname) {
+            Some(client) => client,
+            None => panic!("the client {} is not registered", nickname),
+        }
+    }
+}
+
+#[cfg(test)]
+mod client_registry_tests {
+    use std::{sync::Barrier, thread};
+
+    use super::{shard_index, ClientRegistry, CLIENT_SHARDS};
+    use crate::client::Client;
+
+    fn client_for_test(nickname: &str) -> Client {
+        Client::from_connection(
+            nickname.to_string(),
+            nickname.to_string(),
+            "host".to_string(),
+            "server".to_string(),
+            "Alice".to_string(),
+            None,
+            true,
+        )
+    }
+
+    #[test]
+    fn test_clients_are_updated_in_place() {
+        let clients = ClientRegistry::default();
+        clients.insert(client_for_test("alice"));
+
+        assert!(clients.is_connected("alice"));
+        assert!(clients.update("alice", |client| client.connected = false));
+        assert!(!clients.update("bob", |client| client.connected = false));
+
+        assert!(clients.contains("alice"));
+        assert!(!clients.is_connected("alice"));
+        assert_eq!(
+            clients.get("alice").map(|c| c.realname),
+            Some("Alice".to_string())
+        );
+    }
+
+    #[test]
+    fn test_client_is_registered_through_its_entry_without_locking_other_shards() {
+        let clients = ClientRegistry::default();
+        let other = (0..)
+            .map(|i| format!("user{}", i))
+            .find(|nickname| shard_index(nickname) != shard_index("alice"))
+            .unwrap();
+        clients.insert(client_for_test(&other));
+
+        let mut entry = clients.lock_client("alice");
+        assert!(entry.get_mut().is_none());
+        entry.insert(client_for_test("alice"));
+        entry.get_mut().unwrap().connected = false;
+        assert!(clients.update(&other, |client| client.connected = false));
+        drop(entry);
+
+        assert!(clients.contains("alice"));
+        assert!(!clients.is_connected("alice"));
+    }
+
+    #[test]
+    fn test_the_whole_registry_has_the_clients_of_every_shard() {
+        let nicknames = (0..CLIENT_SHARDS * 8)
+            .map(|i| format!("user{}", i))
+            .collect::<Vec<_>>();
+        let clients = ClientRegistry::new(
+            nicknames
+                .iter()
+                .map(|nickname| (nickname.to_owned(), client_for_test(nickname)))
+                .collect(),
+        );
+
+        let mut all = clients.write_all();
+        assert_eq!(all.len(), nicknames.len());
+        assert!(all.remove("user0").is_some());
+        all.values_mut().for_each(|client| client.connected = false);
+        drop(all);
+
+        let all = clients.read_all();
+        let mut saved = all.keys().cloned().collect::<Vec<_>>();
+        saved.sort();
+        let mut expected = nicknames[1..].to_vec();
+        expected.sort();
+        assert_eq!(saved, expected);
+        assert!(all.values().all(|client| !client.connected));
+        assert!(!all["user1"].connected);
+    }
+
+    #[test]
+    fn test_clients_registered_at_the_same_time_are_all_saved() {
+        let clients = ClientRegistry::default();
+        let barrier = std::sync::Arc::new(Barrier::new(8));
+        let registrations = (0..8)
+            .map(|thread| {
+                let clients = clients.clone();
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    barrier.wait();
+                    for i in 0..100 {
+                        let nickname = format!("user{}_{}", thread, i);
+                        clients.insert(client_for_test(&nickname));
+                        assert!(clients.update(&nickname, |c| c.connected = false));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for registration in registrations {
+            registration.join().unwrap();
+        }
+
+        assert_eq!(clients.read_all().len(), 800);
+    }
+}
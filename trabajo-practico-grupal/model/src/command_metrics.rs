@@ -0,0 +1,170 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::locks::lock;
+
+/// The upper bounds, in microseconds, of the buckets of the histograms. The times above the
+/// last bound are counted in one more bucket.
+static BUCKET_BOUNDS: [u64; 10] = [
+    100, 250, 500, 1_000, 2_500, 5_000, 10_000, 50_000, 100_000, 1_000_000,
+];
+
+/// Struct that holds how long the handlers of a command took, counted in buckets of time.
+/// # Fields
+/// * `buckets`: The amount of times in each bucket, with one more bucket for the times above
+///   the last bound.
+/// * `count`: The amount of times recorded.
+/// * `total`: The sum of the times recorded, in microseconds.
+/// * `max`: The longest time recorded, in microseconds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    buckets: [u64; BUCKET_BOUNDS.len() + 1],
+    pub count: u64,
+    pub total: u64,
+    pub max: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        LatencyHistogram {
+            buckets: [0; BUCKET_BOUNDS.len() + 1],
+            count: 0,
+            total: 0,
+            max: 0,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    /// Counts the time in its bucket.
+    /// # Arguments
+    /// * `elapsed` - The time the handler took.
+    pub fn record(&mut self, elapsed: Duration) {
+        let micros = elapsed.as_micros().min(u64::MAX as u128) as u64;
+        let bucket = BUCKET_BOUNDS
+            .iter()
+            .position(|bound| micros <= *bound)
+            .unwrap_or(BUCKET_BOUNDS.len());
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.total = self.total.saturating_add(micros);
+        self.max = self.max.max(micros);
+    }
+
+    /// Returns the mean of the times recorded, in microseconds, or 0 if there are none.
+    pub fn mean(&self) -> u64 {
+        match self.count {
+            0 => 0,
+            count => self.total / count,
+        }
+    }
+
+    /// Returns the upper bound, in microseconds, of the bucket that holds the percentile of the
+    /// times recorded. The times above the last bound use the longest time instead, and 0 is
+    /// returned if there are none.
+    /// # Arguments
+    /// * `percentile` - The percentile, from 0 to 100.
+    pub fn percentile(&self, percentile: u64) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        let rank = (self.count * percentile.min(100)).div_ceil(100).max(1);
+        let mut counted = 0;
+        for (bound, amount) in BUCKET_BOUNDS.iter().zip(self.buckets) {
+            counted += amount;
+            if counted >= rank {
+                return (*bound).min(self.max);
+            }
+        }
+        self.max
+    }
+}
+
+/// Struct that holds the histogram of the processing time of each command handled by the
+/// server, shared by the handlers of every connection.
+/// # Fields
+/// * `histograms`: A hashmap that contains the histogram of each command, by name.
+#[derive(Debug, Clone, Default)]
+pub struct CommandMetrics {
+    histograms: Arc<Mutex<HashMap<String, LatencyHistogram>>>,
+}
+
+impl CommandMetrics {
+    /// Counts the time the handler of the command took.
+    /// # Arguments
+    /// * `command` - The name of the command, like `PRIVMSG`.
+    /// * `elapsed` - The time the handler took.
+    pub fn record(&self, command: &str, elapsed: Duration) {
+        let mut histograms = lock(&self.histograms, "command metrics");
+        match histograms.get_mut(command) {
+            Some(histogram) => histogram.record(elapsed),
+            None => {
+                let mut histogram = LatencyHistogram::default();
+                histogram.record(elapsed);
+                histograms.insert(command.to_string(), histogram);
+            }
+        }
+    }
+
+    /// Returns a copy of the histogram of each command that was handled, sorted by name.
+    pub fn snapshot(&self) -> Vec<(String, LatencyHistogram)> {
+        let mut histograms = lock(&self.histograms, "command metrics")
+            .iter()
+            .map(|(command, histogram)| (command.to_owned(), histogram.clone()))
+            .collect::<Vec<_>>();
+        histograms.sort_by(|(a, _), (b, _)| a.cmp(b));
+        histograms
+    }
+}
+
+#[cfg(test)]
+mod command_metrics_tests {
+    use std::time::Duration;
+
+    use super::{CommandMetrics, LatencyHistogram};
+
+    #[test]
+    fn test_histogram_percentiles_use_the_bounds_of_the_buckets() {
+        let mut histogram = LatencyHistogram::default();
+        for _ in 0..90 {
+            histogram.record(Duration::from_micros(80));
+        }
+        for _ in 0..10 {
+            histogram.record(Duration::from_millis(4));
+        }
+
+        assert_eq!(histogram.count, 100);
+        assert_eq!(histogram.mean(), 472);
+        assert_eq!(histogram.percentile(50), 100);
+        assert_eq!(histogram.percentile(95), 4_000);
+        assert_eq!(histogram.max, 4_000);
+        assert_eq!(LatencyHistogram::default().percentile(95), 0);
+    }
+
+    #[test]
+    fn test_times_above_the_last_bound_use_the_longest_time() {
+        let mut histogram = LatencyHistogram::default();
+        histogram.record(Duration::from_secs(3));
+
+        assert_eq!(histogram.percentile(99), 3_000_000);
+    }
+
+    #[test]
+    fn test_snapshot_holds_the_histogram_of_each_command_by_name() {
+        let metrics = CommandMetrics::default();
+        metrics.record("PRIVMSG", Duration::from_micros(10));
+        metrics.record("JOIN", Duration::from_micros(700));
+        metrics.record("PRIVMSG", Duration::from_micros(30));
+
+        let snapshot = metrics.snapshot();
+
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot[0].0, "JOIN");
+        assert_eq!(snapshot[1].0, "PRIVMSG");
+        assert_eq!(snapshot[1].1.count, 2);
+        assert_eq!(snapshot[1].1.mean(), 20);
+    }
+}
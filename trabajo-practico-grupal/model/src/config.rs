@@ -0,0 +1,339 @@
+use std::{path::PathBuf, time::Duration};
+
+use crate::{
+    channelflag::ChannelFlag, consts::CHANNEL_PREFIXES, mask::matches_mask, userflag::UserFlag,
+};
+
+static DEFAULT_VERSION: &str = "rust-irc-0.1";
+static DEFAULT_NICKLEN: usize = 9;
+static DEFAULT_CHANNELLEN: usize = 50;
+static DEFAULT_CONNECTIONS_PER_IP: usize = 10;
+static DEFAULT_AUTO_AWAY_MINUTES: u64 = 30;
+static DEFAULT_IDLE_TIMEOUT_MINUTES: u64 = 0;
+static DEFAULT_AWAYLEN: usize = 200;
+static DEFAULT_SILENCE_LIMIT: usize = 15;
+/// Our client sends a PING every 15 seconds and the linked servers are pinged every 30, so only
+/// the connections that stopped answering are dropped.
+static DEFAULT_READ_TIMEOUT_SECONDS: u64 = 300;
+static DEFAULT_WRITE_TIMEOUT_SECONDS: u64 = 10;
+static DEFAULT_MAX_CHANNELS: usize = 20;
+static DEFAULT_MAX_TARGETS: usize = 10;
+static DEFAULT_CLIENT_RETENTION_DAYS: u64 = 30;
+
+/// The policies of the server about who can create a channel by joining it.
+/// # Variants
+/// * `Anyone`: Every client can create channels.
+/// * `Registered`: Only the clients registered with a password can create channels.
+/// * `Operators`: Only the operators of the server can create channels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelCreation {
+    Anyone,
+    Registered,
+    Operators,
+}
+
+impl ChannelCreation {
+    /// Returns the policy written in the configuration file, like `registered`.
+    pub fn from_config(value: &str) -> Option<ChannelCreation> {
+        match value.to_lowercase().as_str() {
+            "anyone" => Some(ChannelCreation::Anyone),
+            "registered" => Some(ChannelCreation::Registered),
+            "operators" => Some(ChannelCreation::Operators),
+            _ => None,
+        }
+    }
+}
+
+/// Struct that holds the configuration of the server.
+/// # Fields
+/// * `version`: The version of the server, sent to the clients when they register.
+/// * `created`: The date the server was started.
+/// * `started`: The time, in seconds since the epoch, when the server was started.
+/// * `nicklen`: The maximum length of a nickname.
+/// * `channellen`: The maximum length of a channel name.
+/// * `chantypes`: The characters a channel name can start with.
+/// * `connections_per_ip`: The maximum amount of connections open at the same time from an IP.
+/// * `auto_away_minutes`: The minutes a client can be idle before it is marked as away, 0 to never mark it.
+/// * `idle_timeout_minutes`: The minutes a client can be idle before it is disconnected, 0 to never disconnect it.
+/// * `read_timeout_seconds`: The seconds a connection can send nothing before it is dropped, 0 to wait forever.
+/// * `write_timeout_seconds`: The seconds a write to a connection can block before the connection is dropped, 0 to wait forever.
+/// * `channel_creation`: The clients that can create channels.
+/// * `awaylen`: The maximum length of an away message, longer messages are truncated.
+/// * `silence_limit`: The maximum amount of masks in the silence list of a client.
+/// * `max_channels`: The maximum amount of channels a client can be a member of, 0 for no limit.
+/// * `max_targets`: The maximum amount of targets of a PRIVMSG, NOTICE or JOIN, 0 for no limit.
+/// * `client_retention_days`: The days a client can stay disconnected before it is archived, 0 to never archive it.
+/// * `reserved_nicknames`: The nicknames, or patterns with `*` and `?`, that the clients cannot use.
+/// * `admin_port`: The local port of the web admin interface, 0 to not start it. It is only read when the server starts.
+/// * `admin_token`: The token the admin interface asks for, the interface is disabled while it is empty.
+/// * `websocket_port`: The port of the WebSocket gateway for the browser clients, 0 to not start it. It is only read when the server starts.
+/// * `plugins`: The names of the plugins loaded when the server starts, like `wordfilter`.
+/// * `filtered_words`: The words the `wordfilter` plugin hides in the messages, in lowercase.
+/// * `auto_ops`: The channels and the nickname masks that the `autoop` plugin makes operators when they join, like `#rust` and `alice*`.
+/// * `file`: The file the configuration was loaded from, which is read again by REHASH.
+/// * `audit_file`: The file the actions of the operators are appended to, read by AUDIT.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub version: String,
+    pub created: String,
+    pub started: u64,
+    pub nicklen: usize,
+    pub channellen: usize,
+    pub chantypes: String,
+    pub connections_per_ip: usize,
+    pub auto_away_minutes: u64,
+    pub idle_timeout_minutes: u64,
+    pub read_timeout_seconds: u64,
+    pub write_timeout_seconds: u64,
+    pub channel_creation: ChannelCreation,
+    pub awaylen: usize,
+    pub silence_limit: usize,
+    pub max_channels: usize,
+    pub max_targets: usize,
+    pub client_retention_days: u64,
+    pub reserved_nicknames: Vec<String>,
+    pub admin_port: u16,
+    pub admin_token: String,
+    pub websocket_port: u16,
+    pub plugins: Vec<String>,
+    pub filtered_words: Vec<String>,
+    pub auto_ops: Vec<(String, String)>,
+    pub file: Option<PathBuf>,
+    pub audit_file: Option<PathBuf>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            version: DEFAULT_VERSION.to_string(),
+            created: String::new(),
+            started: 0,
+            nicklen: DEFAULT_NICKLEN,
+            channellen: DEFAULT_CHANNELLEN,
+            chantypes: CHANNEL_PREFIXES.iter().collect(),
+            connections_per_ip: DEFAULT_CONNECTIONS_PER_IP,
+            auto_away_minutes: DEFAULT_AUTO_AWAY_MINUTES,
+            idle_timeout_minutes: DEFAULT_IDLE_TIMEOUT_MINUTES,
+            read_timeout_seconds: DEFAULT_READ_TIMEOUT_SECONDS,
+            write_timeout_seconds: DEFAULT_WRITE_TIMEOUT_SECONDS,
+            channel_creation: ChannelCreation::Anyone,
+            awaylen: DEFAULT_AWAYLEN,
+            silence_limit: DEFAULT_SILENCE_LIMIT,
+            max_channels: DEFAULT_MAX_CHANNELS,
+            max_targets: DEFAULT_MAX_TARGETS,
+            client_retention_days: DEFAULT_CLIENT_RETENTION_DAYS,
+            reserved_nicknames: vec![],
+            admin_port: 0,
+            admin_token: String::new(),
+            websocket_port: 0,
+            plugins: vec![],
+            filtered_words: vec![],
+            auto_ops: vec![],
+            file: None,
+            audit_file: None,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Sets a value of the configuration from its key, as written in the configuration file.
+    /// Unknown keys and invalid values are ignored.
+    /// # Arguments
+    /// * `key` - The name of the option, like `NICKLEN`.
+    /// * `value` - The value of the option.
+    pub fn set(&mut self, key: &str, value: &str) {
+        match key {
+            "VERSION" => self.version = value.to_string(),
+            "NICKLEN" => {
+                if let Ok(nicklen) = value.parse::<usize>() {
+                    self.nicklen = nicklen;
+                }
+            }
+            "CHANNELLEN" => {
+                if let Ok(channellen) = value.parse::<usize>() {
+                    self.channellen = channellen;
+                }
+            }
+            "CHANTYPES" if !value.is_empty() => self.chantypes = value.to_string(),
+            "CONNECTIONSPERIP" => {
+                if let Ok(connections_per_ip) = value.parse::<usize>() {
+                    self.connections_per_ip = connections_per_ip;
+                }
+            }
+            "AUTOAWAY" => {
+                if let Ok(minutes) = value.parse::<u64>() {
+                    self.auto_away_minutes = minutes;
+                }
+            }
+            "IDLETIMEOUT" => {
+                if let Ok(minutes) = value.parse::<u64>() {
+                    self.idle_timeout_minutes = minutes;
+                }
+            }
+            "READTIMEOUT" => {
+                if let Ok(seconds) = value.parse::<u64>() {
+                    self.read_timeout_seconds = seconds;
+                }
+            }
+            "WRITETIMEOUT" => {
+                if let Ok(seconds) = value.parse::<u64>() {
+                    self.write_timeout_seconds = seconds;
+                }
+            }
+            "CHANNELCREATION" => {
+                if let Some(policy) = ChannelCreation::from_config(value) {
+                    self.channel_creation = policy;
+                }
+            }
+            "AWAYLEN" => {
+                if let Ok(awaylen) = value.parse::<usize>() {
+                    self.awaylen = awaylen;
+                }
+            }
+            "SILENCE" => {
+                if let Ok(silence_limit) = value.parse::<usize>() {
+                    self.silence_limit = silence_limit;
+                }
+            }
+            "CHANLIMIT" => {
+                if let Ok(max_channels) = value.parse::<usize>() {
+                    self.max_channels = max_channels;
+                }
+            }
+            "MAXTARGETS" => {
+                if let Ok(max_targets) = value.parse::<usize>() {
+                    self.max_targets = max_targets;
+                }
+            }
+            "CLIENTRETENTION" => {
+                if let Ok(days) = value.parse::<u64>() {
+                    self.client_retention_days = days;
+                }
+            }
+            "RESERVEDNICKS" => self.reserved_nicknames = split_list(value),
+            "ADMINPORT" => {
+                if let Ok(port) = value.parse::<u16>() {
+                    self.admin_port = port;
+                }
+            }
+            "ADMINTOKEN" => self.admin_token = value.to_string(),
+            "WEBSOCKETPORT" => {
+                if let Ok(port) = value.parse::<u16>() {
+                    self.websocket_port = port;
+                }
+            }
+            "PLUGINS" => self.plugins = split_list(value),
+            "FILTEREDWORDS" => self.filtered_words = split_list(value),
+            "AUTOOP" => {
+                self.auto_ops = value
+                    .split(',')
+                    .filter_map(|entry| entry.split_once(':'))
+                    .map(|(channel, mask)| (channel.trim().to_string(), mask.trim().to_string()))
+                    .filter(|(channel, mask)| !channel.is_empty() && !mask.is_empty())
+                    .collect();
+            }
+            _ => (),
+        }
+    }
+
+    /// Returns the read timeout of the connections, or none if they wait forever.
+    pub fn read_timeout(&self) -> Option<Duration> {
+        seconds_to_timeout(self.read_timeout_seconds)
+    }
+
+    /// Returns the write timeout of the connections, or none if they wait forever.
+    pub fn write_timeout(&self) -> Option<Duration> {
+        seconds_to_timeout(self.write_timeout_seconds)
+    }
+
+    /// Returns true if a message can be sent to the amount of targets received.
+    /// # Arguments
+    /// * `targets` - The amount of targets of the message.
+    pub fn allows_targets(&self, targets: usize) -> bool {
+        self.max_targets == 0 || targets <= self.max_targets
+    }
+
+    /// Returns true if the nickname doesn't exceed the maximum length.
+    pub fn is_valid_nickname(&self, nickname: &str) -> bool {
+        !nickname.is_empty() && nickname.chars().count() <= self.nicklen
+    }
+
+    /// Returns true if the nickname matches one of the reserved nicknames, ignoring the case.
+    pub fn is_reserved_nickname(&self, nickname: &str) -> bool {
+        self.reserved_nicknames
+            .iter()
+            .any(|pattern| matches_mask(pattern, nickname))
+    }
+
+    /// Returns true if the channel name starts with one of the channel types
+    /// and doesn't exceed the maximum length.
+    pub fn is_valid_channel_name(&self, channel: &str) -> bool {
+        match channel.chars().next() {
+            Some(first) => {
+                self.chantypes.contains(first) && channel.chars().count() <= self.channellen
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the user modes supported by the server, like `iswo`.
+    pub fn get_user_modes(&self) -> String {
+        UserFlag::iter()
+            .iter()
+            .filter(|flag| **flag != UserFlag::Other)
+            .map(UserFlag::to_string)
+            .collect()
+    }
+
+    /// Returns the channel modes supported by the server, like `psitnmlbkvo`.
+    pub fn get_channel_modes(&self) -> String {
+        ChannelFlag::iter()
+            .iter()
+            .filter(|flag| **flag != ChannelFlag::Other)
+            .map(ChannelFlag::to_string)
+            .collect()
+    }
+
+    /// Returns the ISUPPORT tokens that advertise the limits of the server, like `NICKLEN=9`.
+    /// The limits that are not set are advertised without a value.
+    pub fn get_isupport_tokens(&self) -> Vec<String> {
+        vec![
+            format!("NICKLEN={}", self.nicklen),
+            format!("CHANNELLEN={}", self.channellen),
+            format!("CHANTYPES={}", self.chantypes),
+            format!(
+                "CHANLIMIT={}:{}",
+                self.chantypes,
+                limit_token(self.max_channels)
+            ),
+            format!("MAXTARGETS={}", limit_token(self.max_targets)),
+            format!("AWAYLEN={}", self.awaylen),
+            format!("SILENCE={}", self.silence_limit),
+        ]
+    }
+}
+
+/// Returns the timeout of the seconds of the configuration file, where 0 means no timeout.
+fn seconds_to_timeout(seconds: u64) -> Option<Duration> {
+    match seconds {
+        0 => None,
+        seconds => Some(Duration::from_secs(seconds)),
+    }
+}
+
+/// Returns the value of an ISUPPORT token of a limit, where 0 means no limit.
+fn limit_token(limit: usize) -> String {
+    match limit {
+        0 => String::new(),
+        limit => limit.to_string(),
+    }
+}
+
+/// Returns the items of a list of the configuration file, like `root,admin*`, in lowercase.
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_lowercase())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
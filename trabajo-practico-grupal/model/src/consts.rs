@@ -0,0 +1,40 @@
+/// The most bytes of a message without its `\r\n`, as the RFC 1459 says.
+/// The frames sent between our clients and servers are zero padded to this size.
+pub const MAX_MSG_SIZE: usize = 510;
+/// The terminator of the lines sent by standard irc clients.
+pub const CRLF: &str = "\r\n";
+/// The most parameters of a message, including the trailing one.
+pub const MAX_PARAMETERS: usize = 15;
+
+/// The prefix of the channels shared by every server of the network.
+pub const NETWORK_CHANNEL_PREFIX: char = '#';
+/// The prefix of the channels known only by the server where they were created.
+pub const LOCAL_CHANNEL_PREFIX: char = '&';
+/// The prefixes of the names of the channels, as they are announced in `CHANTYPES`.
+pub const CHANNEL_PREFIXES: [char; 2] = [NETWORK_CHANNEL_PREFIX, LOCAL_CHANNEL_PREFIX];
+
+/// The flag shown before the channel operators in NAMES and WHOIS.
+pub const OPERATOR_FLAG: char = '@';
+/// The flag shown before the users that can speak in a moderated channel.
+pub const VOICE_FLAG: char = '+';
+
+/// The address where the servers listen for connections, every interface of the host.
+pub const LISTEN_ADDRESS: &str = "0.0.0.0";
+
+/// Returns true if the name is the name of a channel, because it starts with one of the
+/// `CHANNEL_PREFIXES`.
+/// # Arguments
+/// * `name` - The name to check, like the target of a message.
+pub fn is_channel_name(name: &str) -> bool {
+    name.starts_with(CHANNEL_PREFIXES)
+}
+
+/// Returns the channel of a target that addresses only the operators of a channel, because it
+/// is the name of the channel after the `OPERATOR_FLAG`, like `@#rust`.
+/// # Arguments
+/// * `target` - The target of a message.
+pub fn operators_channel_name(target: &str) -> Option<&str> {
+    target
+        .strip_prefix(OPERATOR_FLAG)
+        .filter(|channel| is_channel_name(channel))
+}
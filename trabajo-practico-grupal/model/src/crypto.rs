@@ -0,0 +1,790 @@
+use std::{
+    collections::hash_map::RandomState,
+    fmt,
+    fs::File,
+    hash::{BuildHasher, Hasher},
+    io::Read,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The bytes of the authentication tag appended to each sealed message.
+const TAG_LEN: usize = 16;
+/// The bytes of the nonce of the cipher.
+const NONCE_LEN: usize = 12;
+/// The u-coordinate of the base point of Curve25519.
+const BASE_POINT: [u8; 32] = [
+    9, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+/// The constant (486662 - 2) / 4 of the Montgomery ladder, as a field element.
+const A24: FieldElement = [0xdb41, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+/// The initial hash values of SHA-256.
+const SHA256_INITIAL_STATE: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+/// The round constants of SHA-256.
+const SHA256_ROUND_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// The initial hash values of SHA-1.
+const SHA1_INITIAL_STATE: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+/// The characters of the base64 encoding.
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Struct that computes the SHA-256 hash of data given in pieces, like a file read in chunks.
+/// # Fields
+/// * `state`: The hash of the blocks processed.
+/// * `pending`: The bytes that don't fill a block yet.
+/// * `length`: The bytes hashed.
+#[derive(Debug, Clone)]
+pub struct Sha256 {
+    state: [u32; 8],
+    pending: Vec<u8>,
+    length: u64,
+}
+
+impl Default for Sha256 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sha256 {
+    /// Creates a new `Sha256` struct, without data.
+    pub fn new() -> Self {
+        Sha256 {
+            state: SHA256_INITIAL_STATE,
+            pending: Vec::with_capacity(64),
+            length: 0,
+        }
+    }
+
+    /// Adds the data to the hash.
+    pub fn update(&mut self, data: &[u8]) {
+        self.length += data.len() as u64;
+        self.pending.extend_from_slice(data);
+        let full_blocks = self.pending.len() / 64 * 64;
+        for block in self.pending[..full_blocks].chunks(64) {
+            compress(&mut self.state, block);
+        }
+        self.pending.drain(..full_blocks);
+    }
+
+    /// Returns the hash of the data added.
+    pub fn finish(mut self) -> [u8; 32] {
+        let bits = self.length.wrapping_mul(8);
+        self.pending.push(0x80);
+        while self.pending.len() % 64 != 56 {
+            self.pending.push(0);
+        }
+        self.pending.extend_from_slice(&bits.to_be_bytes());
+        for block in self.pending.chunks(64) {
+            compress(&mut self.state, block);
+        }
+        let mut hash = [0u8; 32];
+        for (bytes, word) in hash.chunks_mut(4).zip(self.state) {
+            bytes.copy_from_slice(&word.to_be_bytes());
+        }
+        hash
+    }
+}
+
+/// Processes a block of 64 bytes of SHA-256.
+fn compress(state: &mut [u32; 8], block: &[u8]) {
+    let mut w = [0u32; 64];
+    for (i, word) in block.chunks(4).enumerate() {
+        w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+    }
+    for i in 16..64 {
+        let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+        let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+        w[i] = w[i - 16]
+            .wrapping_add(s0)
+            .wrapping_add(w[i - 7])
+            .wrapping_add(s1);
+    }
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+    for i in 0..64 {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let choice = (e & f) ^ (!e & g);
+        let t1 = h
+            .wrapping_add(s1)
+            .wrapping_add(choice)
+            .wrapping_add(SHA256_ROUND_CONSTANTS[i])
+            .wrapping_add(w[i]);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let majority = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = s0.wrapping_add(majority);
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+    }
+    for (word, value) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *word = word.wrapping_add(value);
+    }
+}
+
+/// Returns the SHA-256 hash of the data.
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    hasher.finish()
+}
+
+/// Returns the SHA-1 hash of the data. It is not secure anymore, it is only used where a
+/// protocol requires it, like the handshake of the WebSockets.
+pub fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64).wrapping_mul(8).to_be_bytes());
+
+    let mut state = SHA1_INITIAL_STATE;
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a827999),
+                20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let t = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = t;
+        }
+        for (word, value) in state.iter_mut().zip([a, b, c, d, e]) {
+            *word = word.wrapping_add(value);
+        }
+    }
+    let mut hash = [0u8; 20];
+    for (bytes, word) in hash.chunks_mut(4).zip(state) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    hash
+}
+
+/// Returns the HMAC-SHA256 of the data, which only someone with the key can compute.
+pub fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    keyed_hmac(&hmac_pads(key), data)
+}
+
+/// Returns the key derived from the password and the salt with PBKDF2-HMAC-SHA256, as long as
+/// one hash. Each iteration computes an HMAC, so guessing the password takes as many times longer.
+pub fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32) -> [u8; 32] {
+    let pads = hmac_pads(password);
+    let mut first = salt.to_vec();
+    first.extend_from_slice(&1u32.to_be_bytes());
+    let mut block = keyed_hmac(&pads, &first);
+    let mut key = block;
+    for _ in 1..iterations {
+        block = keyed_hmac(&pads, &block);
+        for (byte, value) in key.iter_mut().zip(block) {
+            *byte ^= value;
+        }
+    }
+    key
+}
+
+/// Returns the hashers of the inner and the outer pads of the HMAC with the key, so the key is
+/// hashed once for many HMACs.
+fn hmac_pads(key: &[u8]) -> (Sha256, Sha256) {
+    let mut block_key = [0u8; 64];
+    match key.len() > 64 {
+        true => block_key[..32].copy_from_slice(&sha256(key)),
+        false => block_key[..key.len()].copy_from_slice(key),
+    }
+    let mut inner = Sha256::new();
+    inner.update(&block_key.map(|byte| byte ^ 0x36));
+    let mut outer = Sha256::new();
+    outer.update(&block_key.map(|byte| byte ^ 0x5c));
+    (inner, outer)
+}
+
+/// Returns the HMAC of the data with the hashers of the pads of its key.
+fn keyed_hmac(pads: &(Sha256, Sha256), data: &[u8]) -> [u8; 32] {
+    let mut inner = pads.0.clone();
+    inner.update(data);
+    let mut outer = pads.1.clone();
+    outer.update(&inner.finish());
+    outer.finish()
+}
+
+/// The quarter round of ChaCha20.
+fn quarter_round(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(12);
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(7);
+}
+
+/// Returns the 64 bytes of the keystream of ChaCha20 in the block with the counter.
+fn chacha20_block(key: &[u8; 32], counter: u32, nonce: &[u8; NONCE_LEN]) -> [u8; 64] {
+    let word = |bytes: &[u8]| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    let mut initial = [0u32; 16];
+    initial[..4].copy_from_slice(&[0x61707865, 0x3320646e, 0x79622d32, 0x6b206574]);
+    for i in 0..8 {
+        initial[4 + i] = word(&key[4 * i..]);
+    }
+    initial[12] = counter;
+    for i in 0..3 {
+        initial[13 + i] = word(&nonce[4 * i..]);
+    }
+
+    let mut state = initial;
+    for _ in 0..10 {
+        quarter_round(&mut state, 0, 4, 8, 12);
+        quarter_round(&mut state, 1, 5, 9, 13);
+        quarter_round(&mut state, 2, 6, 10, 14);
+        quarter_round(&mut state, 3, 7, 11, 15);
+        quarter_round(&mut state, 0, 5, 10, 15);
+        quarter_round(&mut state, 1, 6, 11, 12);
+        quarter_round(&mut state, 2, 7, 8, 13);
+        quarter_round(&mut state, 3, 4, 9, 14);
+    }
+    let mut block = [0u8; 64];
+    for (i, bytes) in block.chunks_mut(4).enumerate() {
+        bytes.copy_from_slice(&state[i].wrapping_add(initial[i]).to_le_bytes());
+    }
+    block
+}
+
+/// Encrypts or decrypts the data with ChaCha20, as if it were at the position of a longer stream,
+/// so a stream can be encrypted in pieces of any size.
+pub fn chacha20(key: &[u8; 32], nonce: &[u8; NONCE_LEN], position: u64, data: &mut [u8]) {
+    let mut position = position;
+    let mut done = 0;
+    while done < data.len() {
+        let block = chacha20_block(key, (position / 64) as u32, nonce);
+        let start = (position % 64) as usize;
+        let len = (64 - start).min(data.len() - done);
+        for (byte, key_byte) in data[done..done + len].iter_mut().zip(&block[start..]) {
+            *byte ^= key_byte;
+        }
+        done += len;
+        position += len as u64;
+    }
+}
+
+/// A number of the field of Curve25519, in 16 limbs of 16 bits.
+type FieldElement = [i64; 16];
+
+/// Carries the overflow of each limb to the next one.
+fn carry(element: &mut FieldElement) {
+    for i in 0..16 {
+        element[i] += 1 << 16;
+        let overflow = element[i] >> 16;
+        match i < 15 {
+            true => element[i + 1] += overflow - 1,
+            false => element[0] += 38 * (overflow - 1),
+        }
+        element[i] -= overflow << 16;
+    }
+}
+
+/// Swaps the elements if the bit is 1, without branching on the bit.
+fn swap(p: &mut FieldElement, q: &mut FieldElement, bit: i64) {
+    let mask = !(bit - 1);
+    for i in 0..16 {
+        let t = mask & (p[i] ^ q[i]);
+        p[i] ^= t;
+        q[i] ^= t;
+    }
+}
+
+fn pack(element: &FieldElement) -> [u8; 32] {
+    let mut t = *element;
+    carry(&mut t);
+    carry(&mut t);
+    carry(&mut t);
+    for _ in 0..2 {
+        let mut m = [0i64; 16];
+        m[0] = t[0] - 0xffed;
+        for i in 1..15 {
+            m[i] = t[i] - 0xffff - ((m[i - 1] >> 16) & 1);
+            m[i - 1] &= 0xffff;
+        }
+        m[15] = t[15] - 0x7fff - ((m[14] >> 16) & 1);
+        let borrow = (m[15] >> 16) & 1;
+        m[14] &= 0xffff;
+        swap(&mut t, &mut m, 1 - borrow);
+    }
+    let mut bytes = [0u8; 32];
+    for i in 0..16 {
+        bytes[2 * i] = (t[i] & 0xff) as u8;
+        bytes[2 * i + 1] = (t[i] >> 8) as u8;
+    }
+    bytes
+}
+
+fn unpack(bytes: &[u8; 32]) -> FieldElement {
+    let mut element = [0i64; 16];
+    for i in 0..16 {
+        element[i] = bytes[2 * i] as i64 + ((bytes[2 * i + 1] as i64) << 8);
+    }
+    element[15] &= 0x7fff;
+    element
+}
+
+fn add(a: &FieldElement, b: &FieldElement) -> FieldElement {
+    let mut sum = [0i64; 16];
+    for i in 0..16 {
+        sum[i] = a[i] + b[i];
+    }
+    sum
+}
+
+fn sub(a: &FieldElement, b: &FieldElement) -> FieldElement {
+    let mut difference = [0i64; 16];
+    for i in 0..16 {
+        difference[i] = a[i] - b[i];
+    }
+    difference
+}
+
+fn mul(a: &FieldElement, b: &FieldElement) -> FieldElement {
+    let mut product = [0i64; 31];
+    for i in 0..16 {
+        for j in 0..16 {
+            product[i + j] += a[i] * b[j];
+        }
+    }
+    for i in 0..15 {
+        product[i] += 38 * product[i + 16];
+    }
+    let mut element = [0i64; 16];
+    element.copy_from_slice(&product[..16]);
+    carry(&mut element);
+    carry(&mut element);
+    element
+}
+
+/// Returns the inverse of the element, raising it to p - 2.
+fn invert(element: &FieldElement) -> FieldElement {
+    let mut inverse = *element;
+    for bit in (0..=253).rev() {
+        inverse = mul(&inverse, &inverse);
+        if bit != 2 && bit != 4 {
+            inverse = mul(&inverse, element);
+        }
+    }
+    inverse
+}
+
+/// Returns the X25519 function of the scalar and the point, the Diffie-Hellman function of
+/// Curve25519 from RFC 7748.
+pub fn x25519(scalar: &[u8; 32], point: &[u8; 32]) -> [u8; 32] {
+    let mut clamped = *scalar;
+    clamped[31] = (clamped[31] & 127) | 64;
+    clamped[0] &= 248;
+    let x = unpack(point);
+    let (mut a, mut b, mut c, mut d) = ([0i64; 16], x, [0i64; 16], [0i64; 16]);
+    a[0] = 1;
+    d[0] = 1;
+    for i in (0..=254).rev() {
+        let bit = ((clamped[i >> 3] >> (i & 7)) & 1) as i64;
+        swap(&mut a, &mut b, bit);
+        swap(&mut c, &mut d, bit);
+        let mut e = add(&a, &c);
+        a = sub(&a, &c);
+        c = add(&b, &d);
+        b = sub(&b, &d);
+        d = mul(&e, &e);
+        let f = mul(&a, &a);
+        a = mul(&c, &a);
+        c = mul(&b, &e);
+        e = add(&a, &c);
+        a = sub(&a, &c);
+        b = mul(&a, &a);
+        c = sub(&d, &f);
+        a = mul(&c, &A24);
+        a = add(&a, &d);
+        c = mul(&c, &a);
+        a = mul(&d, &f);
+        d = mul(&b, &x);
+        b = mul(&e, &e);
+        swap(&mut a, &mut b, bit);
+        swap(&mut c, &mut d, bit);
+    }
+    pack(&mul(&a, &invert(&c)))
+}
+
+/// Returns random bytes from the random device of the system. If it can't be read, the bytes
+/// are generated from the random seeds of the hash maps and the time.
+pub fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    let from_device =
+        File::open("/dev/urandom").and_then(|mut device| device.read_exact(&mut bytes));
+    if from_device.is_ok() {
+        return bytes;
+    }
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_nanos())
+        .unwrap_or_default();
+    let mut seed = Sha256::new();
+    for _ in 0..4 {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(nanos);
+        seed.update(&hasher.finish().to_le_bytes());
+    }
+    chacha20(&seed.finish(), &[0; NONCE_LEN], 0, &mut bytes);
+    bytes
+}
+
+/// Returns the bytes written in hexadecimal.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Returns the bytes written in base64, with padding.
+pub fn to_base64(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| {
+            group | ((*byte as u32) << (16 - 8 * i))
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(BASE64_ALPHABET[((group >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+/// Returns the bytes written in hexadecimal, or None if the text is not hexadecimal.
+pub fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Struct that represents the keys of one side of a Diffie-Hellman exchange. A new pair is
+/// generated for each connection.
+/// # Fields
+/// * `private_key`: The key that is never sent.
+/// * `public_key`: The key sent to the other side.
+pub struct KeyPair {
+    private_key: [u8; 32],
+    pub public_key: [u8; 32],
+}
+
+impl KeyPair {
+    /// Generates a new pair of random keys.
+    pub fn generate() -> Self {
+        let private_key = random_bytes::<32>();
+        KeyPair {
+            private_key,
+            public_key: x25519(&private_key, &BASE_POINT),
+        }
+    }
+}
+
+/// Struct that represents the keys shared by two peers after a Diffie-Hellman exchange.
+/// The messages are encrypted with ChaCha20 and authenticated with HMAC-SHA256, so someone who
+/// only watches the traffic can't read nor change them. The exchange itself is not authenticated:
+/// the public keys travel through the server, so whoever replaces them in the middle, like the
+/// server itself, shares the keys with each peer and can read and change the messages.
+/// # Fields
+/// * `encryption_key`: The key of the cipher.
+/// * `authentication_key`: The key of the authentication tags.
+#[derive(Clone)]
+pub struct SecureSession {
+    encryption_key: [u8; 32],
+    authentication_key: [u8; 32],
+}
+
+impl fmt::Debug for SecureSession {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecureSession").finish_non_exhaustive()
+    }
+}
+
+impl SecureSession {
+    /// Creates the keys shared with the peer from our keys and its public key.
+    /// Returns None if the public key of the peer is a point that makes the shared secret zero.
+    pub fn new(key_pair: &KeyPair, peer_public_key: &[u8; 32]) -> Option<Self> {
+        let shared_secret = x25519(&key_pair.private_key, peer_public_key);
+        if shared_secret == [0; 32] {
+            return None;
+        }
+        let derive = |purpose: &[u8]| hmac_sha256(&shared_secret, purpose);
+        Some(SecureSession {
+            encryption_key: derive(b"encryption"),
+            authentication_key: derive(b"authentication"),
+        })
+    }
+
+    /// Returns the text encrypted, with its nonce and authentication tag, in hexadecimal.
+    pub fn seal(&self, text: &str) -> String {
+        let nonce = random_bytes::<NONCE_LEN>();
+        let mut sealed = nonce.to_vec();
+        let start = sealed.len();
+        sealed.extend_from_slice(text.as_bytes());
+        chacha20(&self.encryption_key, &nonce, 0, &mut sealed[start..]);
+        let tag = hmac_sha256(&self.authentication_key, &sealed);
+        sealed.extend_from_slice(&tag[..TAG_LEN]);
+        to_hex(&sealed)
+    }
+
+    /// Returns the text of a sealed message, or None if it was not sealed with these keys or
+    /// it was changed.
+    pub fn open(&self, sealed: &str) -> Option<String> {
+        let sealed = from_hex(sealed)?;
+        if sealed.len() < NONCE_LEN + TAG_LEN {
+            return None;
+        }
+        let (body, tag) = sealed.split_at(sealed.len() - TAG_LEN);
+        let expected = hmac_sha256(&self.authentication_key, body);
+        let difference = expected[..TAG_LEN]
+            .iter()
+            .zip(tag)
+            .fold(0, |difference, (a, b)| difference | (a ^ b));
+        if difference != 0 {
+            return None;
+        }
+        let (nonce, cipher_text) = body.split_at(NONCE_LEN);
+        let mut nonce_bytes = [0u8; NONCE_LEN];
+        nonce_bytes.copy_from_slice(nonce);
+        let mut text = cipher_text.to_vec();
+        chacha20(&self.encryption_key, &nonce_bytes, 0, &mut text);
+        String::from_utf8(text).ok()
+    }
+
+    /// Returns the cipher of a new file, with a random nonce.
+    pub fn new_file_cipher(&self) -> FileCipher {
+        FileCipher {
+            key: self.encryption_key,
+            nonce: random_bytes::<NONCE_LEN>(),
+        }
+    }
+
+    /// Returns the cipher of a file whose nonce was sent by the peer in hexadecimal, or None
+    /// if it is not a valid nonce.
+    pub fn file_cipher(&self, nonce: &str) -> Option<FileCipher> {
+        let nonce = from_hex(nonce)?;
+        Some(FileCipher {
+            key: self.encryption_key,
+            nonce: nonce.try_into().ok()?,
+        })
+    }
+}
+
+/// Struct that represents the cipher of the bytes of a file sent through a secure connection.
+/// Each byte is encrypted by its position in the file, so a paused transfer can be resumed
+/// from any offset. The stream is not authenticated: the bytes changed on the way are decrypted
+/// into other bytes without an error, and only the checksum sent with the request, which is
+/// not authenticated either, shows it.
+/// # Fields
+/// * `key`: The key of the cipher.
+/// * `nonce`: The nonce of the file, sent to the peer with the request.
+#[derive(Clone, PartialEq, Eq)]
+pub struct FileCipher {
+    key: [u8; 32],
+    nonce: [u8; NONCE_LEN],
+}
+
+impl fmt::Debug for FileCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileCipher").finish_non_exhaustive()
+    }
+}
+
+impl FileCipher {
+    /// Returns the nonce in hexadecimal, to send it to the peer.
+    pub fn nonce(&self) -> String {
+        to_hex(&self.nonce)
+    }
+
+    /// Encrypts or decrypts the bytes of the file that start at the position.
+    pub fn apply(&self, position: u64, data: &mut [u8]) {
+        chacha20(&self.key, &self.nonce, position, data);
+    }
+}
+
+#[cfg(test)]
+mod crypto_tests {
+    use super::{
+        chacha20, from_hex, hmac_sha256, pbkdf2_sha256, sha1, sha256, to_base64, to_hex, x25519,
+        KeyPair, SecureSession, Sha256, BASE_POINT,
+    };
+
+    fn hex_32(text: &str) -> [u8; 32] {
+        from_hex(text).unwrap().try_into().unwrap()
+    }
+
+    #[test]
+    fn test_hashes_match_the_known_values() {
+        assert_eq!(
+            to_hex(&sha256(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            to_hex(&sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            to_hex(&sha256(
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+            )),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+        let data = vec![b'a'; 1000];
+        let mut hasher = Sha256::new();
+        for chunk in data.chunks(7) {
+            hasher.update(chunk);
+        }
+        assert_eq!(hasher.finish(), sha256(&data));
+        assert_eq!(
+            to_hex(&hmac_sha256(
+                b"key",
+                b"The quick brown fox jumps over the lazy dog"
+            )),
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+        assert_eq!(
+            to_hex(&sha1(b"abc")),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(
+            to_hex(&sha1(&data)),
+            "291e9a6c66994949b57ba5e650361e98fc36b1ba"
+        );
+    }
+
+    #[test]
+    fn test_pbkdf2_matches_the_known_values() {
+        assert_eq!(
+            to_hex(&pbkdf2_sha256(b"password", b"salt", 1)),
+            "120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b"
+        );
+        assert_eq!(
+            to_hex(&pbkdf2_sha256(b"password", b"salt", 2)),
+            "ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43"
+        );
+        assert_eq!(
+            to_hex(&pbkdf2_sha256(b"password", b"salt", 4096)),
+            "c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a"
+        );
+    }
+
+    #[test]
+    fn test_base64_is_padded() {
+        assert_eq!(to_base64(b""), "");
+        assert_eq!(to_base64(b"f"), "Zg==");
+        assert_eq!(to_base64(b"fo"), "Zm8=");
+        assert_eq!(to_base64(b"foo"), "Zm9v");
+        assert_eq!(to_base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn test_chacha20_matches_the_rfc_and_can_be_applied_in_pieces() {
+        let key = (0..32).collect::<Vec<u8>>().try_into().unwrap();
+        let nonce = [0, 0, 0, 0, 0, 0, 0, 0x4a, 0, 0, 0, 0];
+        let text = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+        let mut data = text.to_vec();
+        chacha20(&key, &nonce, 64, &mut data);
+        assert_eq!(
+            to_hex(&data[..32]),
+            "6e2e359a2568f98041ba0728dd0d6981e97e7aec1d4360c20a27afccfd9fae0b"
+        );
+
+        let mut pieces = text.to_vec();
+        let (first, second) = pieces.split_at_mut(50);
+        chacha20(&key, &nonce, 64, first);
+        chacha20(&key, &nonce, 114, second);
+        assert_eq!(pieces, data);
+        chacha20(&key, &nonce, 64, &mut data);
+        assert_eq!(data, text.to_vec());
+    }
+
+    #[test]
+    fn test_x25519_matches_the_rfc() {
+        let alice = hex_32("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a");
+        let bob = hex_32("5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb");
+        let alice_public = x25519(&alice, &BASE_POINT);
+        let bob_public = x25519(&bob, &BASE_POINT);
+        assert_eq!(
+            to_hex(&alice_public),
+            "8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a"
+        );
+        assert_eq!(
+            to_hex(&bob_public),
+            "de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f"
+        );
+        assert_eq!(
+            to_hex(&x25519(&alice, &bob_public)),
+            "4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742"
+        );
+        assert_eq!(x25519(&bob, &alice_public), x25519(&alice, &bob_public));
+    }
+
+    #[test]
+    fn test_peers_share_the_keys_and_read_only_their_sealed_messages() {
+        let (alice, bob) = (KeyPair::generate(), KeyPair::generate());
+        let alice_session = SecureSession::new(&alice, &bob.public_key).unwrap();
+        let bob_session = SecureSession::new(&bob, &alice.public_key).unwrap();
+
+        let sealed = alice_session.seal("hola, ¿cómo estás?");
+        assert_ne!(alice_session.seal("hola, ¿cómo estás?"), sealed);
+        assert_eq!(bob_session.open(&sealed).unwrap(), "hola, ¿cómo estás?");
+
+        let mut changed = sealed.into_bytes();
+        changed[30] = if changed[30] == b'0' { b'1' } else { b'0' };
+        assert_eq!(bob_session.open(&String::from_utf8(changed).unwrap()), None);
+        let eve = SecureSession::new(&KeyPair::generate(), &alice.public_key).unwrap();
+        assert_eq!(eve.open(&alice_session.seal("secret")), None);
+        assert_eq!(SecureSession::new(&alice, &[0; 32]).map(|_| ()), None);
+
+        let cipher = alice_session.new_file_cipher();
+        let received = bob_session.file_cipher(&cipher.nonce()).unwrap();
+        assert_eq!(received, cipher);
+        let mut chunk = b"file chunk".to_vec();
+        cipher.apply(1024, &mut chunk);
+        received.apply(1024, &mut chunk);
+        assert_eq!(chunk, b"file chunk");
+        assert_eq!(bob_session.file_cipher("xyz"), None);
+    }
+}
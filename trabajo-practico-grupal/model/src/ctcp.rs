@@ -0,0 +1,166 @@
+use std::fmt::Display;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The character that delimits a CTCP message inside the text of a PRIVMSG or NOTICE.
+pub const CTCP_DELIMITER: char = '\x01';
+
+/// The version answered to the CTCP VERSION queries.
+pub const CLIENT_VERSION: &str = "taller-irc 0.1.0";
+
+/// Struct that represents a CTCP message, sent between two delimiters inside the text of a
+/// PRIVMSG, for actions and queries, or of a NOTICE, for the replies to the queries.
+/// # Fields
+/// * `command`: The CTCP command, like ACTION or VERSION.
+/// * `parameters`: The text after the command, which may be empty.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CtcpMessage {
+    pub command: String,
+    pub parameters: String,
+}
+
+impl CtcpMessage {
+    /// Creates a new CTCP message.
+    pub fn new(command: &str, parameters: &str) -> Self {
+        Self {
+            command: command.to_uppercase(),
+            parameters: parameters.to_string(),
+        }
+    }
+
+    /// Parses the text of a PRIVMSG or NOTICE, returning None if it isn't a CTCP message.
+    /// The closing delimiter is optional, since some clients don't send it.
+    pub fn parse(text: &str) -> Option<CtcpMessage> {
+        let inner = text.strip_prefix(CTCP_DELIMITER)?;
+        let inner = inner.strip_suffix(CTCP_DELIMITER).unwrap_or(inner);
+        let (command, parameters) = inner.split_once(' ').unwrap_or((inner, ""));
+        if command.is_empty() {
+            return None;
+        }
+        Some(CtcpMessage::new(command, parameters))
+    }
+
+    /// Returns the reply to a CTCP query, to send back in a NOTICE.
+    /// VERSION is answered with the version of the client, PING with the same parameters
+    /// and TIME with the current time. Other messages, like ACTION, have no reply.
+    pub fn reply(&self) -> Option<CtcpMessage> {
+        let parameters = match self.command.as_str() {
+            "VERSION" => CLIENT_VERSION.to_string(),
+            "PING" => self.parameters.to_owned(),
+            "TIME" => format_timestamp(current_timestamp()),
+            _ => return None,
+        };
+        Some(CtcpMessage::new(&self.command, &parameters))
+    }
+}
+
+impl Display for CtcpMessage {
+    /// Formats the CTCP message between its delimiters.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.parameters.is_empty() {
+            write!(f, "{}{}{}", CTCP_DELIMITER, self.command, CTCP_DELIMITER)
+        } else {
+            write!(
+                f,
+                "{}{} {}{}",
+                CTCP_DELIMITER, self.command, self.parameters, CTCP_DELIMITER
+            )
+        }
+    }
+}
+
+/// Returns the NOTICE that answers a CTCP query received in a PRIVMSG, or None if the
+/// text is not a query with a reply.
+/// # Arguments
+/// * `sender` - The sender of the query, who receives the reply.
+/// * `text` - The text of the PRIVMSG.
+pub fn reply_to_query(sender: &str, text: &str) -> Option<String> {
+    let reply = CtcpMessage::parse(text)?.reply()?;
+    Some(format!("NOTICE {} :{}", sender, reply))
+}
+
+/// Returns the text of a PRIVMSG or NOTICE to show it to the user.
+/// An ACTION is shown as `* <sender> <action>`, a reply to a query as `<command> reply: <text>`
+/// and any other text as it is.
+pub fn render_text(sender: &str, text: &str) -> String {
+    match CtcpMessage::parse(text) {
+        Some(ctcp) if ctcp.command == "ACTION" => format!("* {} {}", sender, ctcp.parameters),
+        Some(ctcp) => format!("{} reply: {}", ctcp.command, ctcp.parameters),
+        None => text.to_string(),
+    }
+}
+
+/// Returns the current time in seconds since the epoch.
+pub fn current_timestamp() -> u64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs(),
+        Err(_) => 0,
+    }
+}
+
+/// Formats seconds since the epoch as a UTC date, like `2023-06-01 18:30:00`.
+pub fn format_timestamp(seconds: u64) -> String {
+    let days = (seconds / 86400) as i64;
+    let time = seconds % 86400;
+    // Converts the days since the epoch to a civil date, counting eras of 400 years from March.
+    let shifted = days + 719468;
+    let era = shifted.div_euclid(146097);
+    let day_of_era = shifted.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod ctcp_tests {
+    use super::{format_timestamp, render_text, reply_to_query, CtcpMessage, CLIENT_VERSION};
+
+    #[test]
+    fn test_ctcp_messages_are_parsed_and_answered() {
+        let action = CtcpMessage::parse("\x01ACTION waves at you\x01").unwrap();
+        assert_eq!(action, CtcpMessage::new("ACTION", "waves at you"));
+        assert_eq!(action.to_string(), "\x01ACTION waves at you\x01");
+        assert_eq!(action.reply(), None);
+
+        let ping = CtcpMessage::parse("\x01ping 12345").unwrap();
+        assert_eq!(ping.reply(), Some(CtcpMessage::new("PING", "12345")));
+        let version = CtcpMessage::parse("\x01VERSION\x01").unwrap();
+        assert_eq!(
+            version.reply().unwrap().to_string(),
+            format!("\x01VERSION {}\x01", CLIENT_VERSION)
+        );
+
+        assert_eq!(
+            reply_to_query("bob", "\x01PING 1\x01"),
+            Some("NOTICE bob :\x01PING 1\x01".to_string())
+        );
+        assert_eq!(reply_to_query("bob", "\x01ACTION waves\x01"), None);
+        assert_eq!(CtcpMessage::parse("hello"), None);
+        assert_eq!(CtcpMessage::parse("\x01\x01"), None);
+        assert_eq!(render_text("bob", "\x01ACTION waves\x01"), "* bob waves");
+        assert_eq!(render_text("bob", "hello"), "hello");
+    }
+
+    #[test]
+    fn test_timestamps_are_formatted_as_utc_dates() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00");
+        assert_eq!(format_timestamp(951782400), "2000-02-29 00:00:00");
+        assert_eq!(format_timestamp(1685644200), "2023-06-01 18:30:00");
+    }
+}
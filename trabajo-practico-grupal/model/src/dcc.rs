@@ -1,3 +1,5 @@
+use crate::prefix::Prefix;
+
 #[derive(Debug)]
 pub enum DccMessageError {
     InvalidMessage,
@@ -15,6 +17,13 @@ pub enum DccMessageType {
     Resume,
     Msg,
     Stop,
+    SChat,
+    SSend,
+    Key,
+    Room,
+    Typing,
+    Error,
+    Relay,
     Invalid,
 }
 impl DccMessageType {
@@ -27,6 +36,13 @@ impl DccMessageType {
             "MSG" => Ok(DccMessageType::Msg),
             "CLOSE" => Ok(DccMessageType::Close),
             "STOP" => Ok(DccMessageType::Stop),
+            "SCHAT" => Ok(DccMessageType::SChat),
+            "SSEND" => Ok(DccMessageType::SSend),
+            "KEY" => Ok(DccMessageType::Key),
+            "ROOM" => Ok(DccMessageType::Room),
+            "TYPING" => Ok(DccMessageType::Typing),
+            "ERROR" => Ok(DccMessageType::Error),
+            "RELAY" => Ok(DccMessageType::Relay),
             _ => Err(DccMessageError::InvalidCommand),
         }
     }
@@ -40,6 +56,13 @@ impl DccMessageType {
             DccMessageType::Msg => Ok("MSG".to_string()),
             DccMessageType::Close => Ok("CLOSE".to_string()),
             DccMessageType::Stop => Ok("STOP".to_string()),
+            DccMessageType::SChat => Ok("SCHAT".to_string()),
+            DccMessageType::SSend => Ok("SSEND".to_string()),
+            DccMessageType::Key => Ok("KEY".to_string()),
+            DccMessageType::Room => Ok("ROOM".to_string()),
+            DccMessageType::Typing => Ok("TYPING".to_string()),
+            DccMessageType::Error => Ok("ERROR".to_string()),
+            DccMessageType::Relay => Ok("RELAY".to_string()),
             _ => Err(DccMessageError::InvalidCommand),
         }
     }
@@ -82,7 +105,11 @@ impl DccMessage {
                     if msg[1] != "DCC" {
                         return Err(DccMessageError::InvalidMessage);
                     } else {
-                        Some(p.to_string())
+                        // the clients are known by their nickname, even if the prefix has their mask
+                        let prefix = p
+                            .parse::<Prefix>()
+                            .map_err(|_| DccMessageError::CannotParsePrefix)?;
+                        Some(prefix.name().to_string())
                     }
                 }
                 None => return Err(DccMessageError::CannotParsePrefix),
@@ -117,4 +144,46 @@ impl DccMessage {
             parameters,
         })
     }
+
+    /// Returns the token of a passive DCC CHAT or SCHAT offer, where the client who receives the offer
+    /// opens the listening socket, for a client behind a NAT that can't be reached.
+    /// A passive offer has the port 0 and a token after the address:
+    /// `DCC CHAT <client_requested> 0 0 <token>`
+    pub fn passive_token(&self) -> Option<&str> {
+        if !matches!(self.command, DccMessageType::Chat | DccMessageType::SChat)
+            || self.parameters.get(2).map(|port| port.as_str()) != Some("0")
+        {
+            return None;
+        }
+        self.parameters.get(3).map(|token| token.as_str())
+    }
+}
+
+#[cfg(test)]
+mod dcc_tests {
+    use super::DccMessage;
+
+    #[test]
+    fn test_the_prefix_is_the_nickname_of_the_client() {
+        let offer =
+            DccMessage::deserialize(":alice!al@host DCC CHAT bob 10.0.0.1 4567".to_string())
+                .unwrap();
+        assert_eq!(offer.prefix, Some("alice".to_string()));
+    }
+
+    #[test]
+    fn test_passive_offers_have_port_zero_and_a_token() {
+        let offer = DccMessage::deserialize(":alice DCC CHAT bob 0 0 1234".to_string()).unwrap();
+        assert_eq!(offer.passive_token(), Some("1234"));
+        let offer = DccMessage::deserialize("DCC SCHAT bob 0 0 99".to_string()).unwrap();
+        assert_eq!(offer.passive_token(), Some("99"));
+
+        let reply =
+            DccMessage::deserialize("DCC CHAT alice 10.0.0.2 4567 1234".to_string()).unwrap();
+        assert_eq!(reply.passive_token(), None);
+        let active = DccMessage::deserialize("DCC CHAT bob 127.0.0.1 8080".to_string()).unwrap();
+        assert_eq!(active.passive_token(), None);
+        let send = DccMessage::deserialize("DCC SEND bob file 0 0 12".to_string()).unwrap();
+        assert_eq!(send.passive_token(), None);
+    }
 }
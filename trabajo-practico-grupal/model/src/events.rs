@@ -0,0 +1,142 @@
+use std::{
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+use crate::session::Session;
+
+/// Enum that represents the events of the server, published by the command handlers once a
+/// command succeeded.
+/// * ClientRegistered -> A client registered, or connected again with a registered nickname
+/// * ClientQuit -> A client sent QUIT, with its quit message if it had one
+/// * ClientDisconnected -> The connection of a client closed, after a QUIT or not
+/// * ChannelJoined -> A client joined a channel
+/// * ChannelParted -> A client left a channel
+/// * ClientKicked -> A client was kicked from a channel by an operator
+/// * MessageSent -> A client sent a PRIVMSG or a NOTICE to a user or a channel
+/// * ModeChanged -> A client changed the modes of a user or a channel, like `+o bob`
+/// * TopicChanged -> An operator changed the topic of a channel
+/// * OperatorGranted -> A client became an operator of the server with OPER
+/// * ClientKilled -> An operator of the server disconnected a client
+/// * ServerQuit -> A server left the network with SQUIT, or its link stopped answering the pings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerEvent {
+    ClientRegistered {
+        nickname: String,
+        username: String,
+        hostname: String,
+    },
+    ClientQuit {
+        nickname: String,
+        reason: Option<String>,
+    },
+    ClientDisconnected {
+        nickname: String,
+    },
+    ChannelJoined {
+        channel: String,
+        nickname: String,
+    },
+    ChannelParted {
+        channel: String,
+        nickname: String,
+    },
+    ClientKicked {
+        channel: String,
+        nickname: String,
+        kicked_by: String,
+    },
+    MessageSent {
+        sender: String,
+        target: String,
+        message: String,
+    },
+    ModeChanged {
+        target: String,
+        modes: String,
+        set_by: String,
+    },
+    TopicChanged {
+        channel: String,
+        topic: String,
+        set_by: String,
+    },
+    OperatorGranted {
+        nickname: String,
+    },
+    ClientKilled {
+        nickname: String,
+        killed_by: String,
+        reason: String,
+    },
+    ServerQuit {
+        server: String,
+        reason: Option<String>,
+    },
+}
+
+/// Trait of the features that react to the events of the server, like the notices to the
+/// operators, so that the command handlers don't have to call each of them.
+pub trait Subscriber: Send + Sync {
+    /// Handles an event. It is called by the thread that published it, which doesn't hold any
+    /// lock of the session, so it can use the session like a command handler does.
+    /// # Arguments
+    /// * `session` - The session of the server where the event happened.
+    /// * `event` - The event published.
+    fn notify(&self, session: &Session, event: &ServerEvent);
+}
+
+/// Struct that delivers the events published by the command handlers to every subscriber, in
+/// the order they subscribed. A clone delivers to the same subscribers.
+/// # Fields
+/// * `subscribers` - The subscribers of the events.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<RwLock<Vec<Arc<dyn Subscriber>>>>,
+}
+
+impl EventBus {
+    /// Adds a subscriber, that receives the events published from now on.
+    /// # Arguments
+    /// * `subscriber` - The subscriber to add.
+    pub fn subscribe(&self, subscriber: Arc<dyn Subscriber>) {
+        match self.subscribers.write() {
+            Ok(mut subscribers) => subscribers.push(subscriber),
+            Err(poisoned) => poisoned.into_inner().push(subscriber),
+        }
+    }
+
+    /// Delivers the event to every subscriber. The subscribers are called after releasing the
+    /// list, so they can subscribe others while handling it.
+    /// # Arguments
+    /// * `session` - The session of the server where the event happened.
+    /// * `event` - The event to deliver.
+    pub fn publish(&self, session: &Session, event: ServerEvent) {
+        let subscribers = match self.subscribers.read() {
+            Ok(subscribers) => subscribers.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        };
+        for subscriber in subscribers {
+            subscriber.notify(session, &event);
+        }
+    }
+
+    /// Returns the amount of subscribers.
+    pub fn len(&self) -> usize {
+        match self.subscribers.read() {
+            Ok(subscribers) => subscribers.len(),
+            Err(poisoned) => poisoned.into_inner().len(),
+        }
+    }
+
+    /// Returns true if nothing subscribed to the events.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "EventBus {{ subscribers: {} }}", self.len())
+    }
+}
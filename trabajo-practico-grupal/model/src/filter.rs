@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+
+use crate::mask::matches_mask;
+
+static CENSOR: &str = "*";
+
+/// Enum that represents what the filter of a channel does with a message that has a word
+/// matching one of its patterns.
+/// * Censor -> The word is replaced by asterisks and the message is delivered
+/// * Block -> The message is not delivered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum FilterAction {
+    Censor,
+    Block,
+}
+
+impl FilterAction {
+    /// Returns the action with the name received, ignoring the case, like `BLOCK`.
+    pub fn parse(name: &str) -> Option<FilterAction> {
+        match name.to_lowercase().as_str() {
+            "censor" => Some(FilterAction::Censor),
+            "block" => Some(FilterAction::Block),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the action, in lowercase.
+    pub fn name(&self) -> &'static str {
+        match self {
+            FilterAction::Censor => "censor",
+            FilterAction::Block => "block",
+        }
+    }
+}
+
+impl From<FilterAction> for String {
+    fn from(action: FilterAction) -> String {
+        action.name().to_string()
+    }
+}
+
+impl TryFrom<String> for FilterAction {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        FilterAction::parse(&name).ok_or(format!("Unknown filter action {}", name))
+    }
+}
+
+/// Struct that represents a rule of the content filter of a channel, applied while the channel
+/// has the `+G` mode.
+/// # Fields
+/// * `pattern`: The mask of the words the rule applies to, in lowercase, like `darn*`.
+/// * `action`: What the filter does with the messages that have a matching word.
+/// * `set_by`: The nickname of the operator who added the rule.
+/// * `set_at`: The time, in seconds since the epoch, when the rule was added.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FilterRule {
+    pub pattern: String,
+    pub action: FilterAction,
+    pub set_by: String,
+    pub set_at: u64,
+}
+
+impl FilterRule {
+    /// Creates a new instance of the rule.
+    /// # Arguments
+    /// * `pattern` - The mask of the words the rule applies to.
+    /// * `action` - What the filter does with the messages that have a matching word.
+    /// * `set_by` - The nickname of the operator who added the rule.
+    /// * `set_at` - The time, in seconds since the epoch, when the rule was added.
+    pub fn new(pattern: &str, action: FilterAction, set_by: String, set_at: u64) -> FilterRule {
+        FilterRule {
+            pattern: pattern.to_lowercase(),
+            action,
+            set_by,
+            set_at,
+        }
+    }
+}
+
+/// Enum that represents the result of applying the rules of a filter to the text of a message.
+/// * Clean -> No word matched a rule
+/// * Censored -> Some words matched a censoring rule, the text has them replaced by asterisks
+/// * Blocked -> A word matched a blocking rule, which has the pattern received
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterOutcome {
+    Clean,
+    Censored(String),
+    Blocked(String),
+}
+
+/// Applies the rules to each word of the text, as the runs of letters and digits of it.
+/// A blocking rule wins over the censoring ones.
+/// # Arguments
+/// * `rules` - The rules of the filter.
+/// * `text` - The text of the message.
+pub fn apply_filters(rules: &[FilterRule], text: &str) -> FilterOutcome {
+    let mut censored = String::with_capacity(text.len());
+    let mut word = String::new();
+    let mut changed = false;
+    for c in text.chars().chain(std::iter::once(' ')) {
+        if c.is_alphanumeric() {
+            word.push(c);
+            continue;
+        }
+        if !word.is_empty() {
+            match rules.iter().find(|rule| matches_mask(&rule.pattern, &word)) {
+                Some(rule) if rule.action == FilterAction::Block => {
+                    return FilterOutcome::Blocked(rule.pattern.to_owned());
+                }
+                Some(_) => {
+                    censored.push_str(&CENSOR.repeat(word.chars().count()));
+                    changed = true;
+                }
+                None => censored.push_str(&word),
+            }
+            word.clear();
+        }
+        censored.push(c);
+    }
+    censored.pop();
+    if changed {
+        FilterOutcome::Censored(censored)
+    } else {
+        FilterOutcome::Clean
+    }
+}
+
+#[cfg(test)]
+mod filter_tests {
+    use super::{apply_filters, FilterAction, FilterOutcome, FilterRule};
+    use crate::json::{FromJson, ToJson};
+
+    fn rule(pattern: &str, action: FilterAction) -> FilterRule {
+        FilterRule::new(pattern, action, "op".to_string(), 0)
+    }
+
+    #[test]
+    fn test_matching_words_are_censored_keeping_the_punctuation() {
+        let rules = vec![rule("darn*", FilterAction::Censor)];
+
+        assert_eq!(
+            apply_filters(&rules, "Darned printer, darn!"),
+            FilterOutcome::Censored("****** printer, ****!".to_string())
+        );
+        assert_eq!(
+            apply_filters(&rules, "no problem here"),
+            FilterOutcome::Clean
+        );
+    }
+
+    #[test]
+    fn test_blocking_rules_win_over_the_censoring_ones() {
+        let rules = vec![
+            rule("darn", FilterAction::Censor),
+            rule("spam?", FilterAction::Block),
+        ];
+
+        assert_eq!(
+            apply_filters(&rules, "darn spams"),
+            FilterOutcome::Blocked("spam?".to_string())
+        );
+    }
+
+    #[test]
+    fn test_rule_is_saved_and_read_as_json() {
+        let rule = rule("Heck", FilterAction::Block);
+
+        let json = rule.to_json();
+
+        assert_eq!(
+            json,
+            r#"{"pattern":"heck","action":"block","set_by":"op","set_at":0}"#
+        );
+        assert_eq!(FilterRule::from_json(&json).unwrap(), rule);
+    }
+}
@@ -0,0 +1,230 @@
+/// The control code that switches the bold text on and off.
+pub const BOLD: char = '\x02';
+/// The control code that switches the italic text on and off.
+pub const ITALIC: char = '\x1D';
+/// The control code that switches the underlined text on and off.
+pub const UNDERLINE: char = '\x1F';
+/// The control code that switches every format off.
+pub const RESET: char = '\x0F';
+/// The control code of the colors, followed by up to two digits and an optional background.
+/// The colors are not shown, so they are only skipped.
+pub const COLOR: char = '\x03';
+
+/// The formats of the text of a message, encoded with the mIRC control codes that most
+/// clients understand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatStyle {
+    Bold,
+    Italic,
+    Underline,
+}
+
+impl FormatStyle {
+    /// Returns the control code that switches the format on and off.
+    pub fn code(&self) -> char {
+        match self {
+            FormatStyle::Bold => BOLD,
+            FormatStyle::Italic => ITALIC,
+            FormatStyle::Underline => UNDERLINE,
+        }
+    }
+
+    /// Returns the text between the control codes of the format.
+    pub fn apply(&self, text: &str) -> String {
+        format!("{}{}{}", self.code(), text, self.code())
+    }
+}
+
+/// Struct that represents a piece of the text of a message that has the same format.
+/// # Fields
+/// * `text`: The text, without the control codes.
+/// * `bold`: True if the text is bold.
+/// * `italic`: True if the text is italic.
+/// * `underline`: True if the text is underlined.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FormattedSpan {
+    pub text: String,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+impl FormattedSpan {
+    /// Returns true if the span has any format.
+    fn is_formatted(&self) -> bool {
+        self.bold || self.italic || self.underline
+    }
+
+    /// Returns the Pango markup of the text of the span, already escaped, between the tags
+    /// of the formats of the span.
+    pub fn wrap_markup(&self, mut markup: String) -> String {
+        if self.underline {
+            markup = format!("<u>{}</u>", markup);
+        }
+        if self.italic {
+            markup = format!("<i>{}</i>", markup);
+        }
+        if self.bold {
+            markup = format!("<b>{}</b>", markup);
+        }
+        markup
+    }
+}
+
+/// Splits the text of a message into the pieces that have the same format. Each control code
+/// switches its format, the reset code switches every format off and the colors are skipped.
+pub fn parse_formatting(text: &str) -> Vec<FormattedSpan> {
+    let mut spans = vec![];
+    let mut current = FormattedSpan::default();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if ![BOLD, ITALIC, UNDERLINE, RESET, COLOR].contains(&c) {
+            current.text.push(c);
+            continue;
+        }
+        if !current.text.is_empty() {
+            spans.push(current.clone());
+            current.text.clear();
+        }
+        match c {
+            BOLD => current.bold = !current.bold,
+            ITALIC => current.italic = !current.italic,
+            UNDERLINE => current.underline = !current.underline,
+            RESET => current = FormattedSpan::default(),
+            _ => {
+                skip_digits(&mut chars);
+                if chars.peek() == Some(&',') {
+                    chars.next();
+                    skip_digits(&mut chars);
+                }
+            }
+        }
+    }
+    if !current.text.is_empty() {
+        spans.push(current);
+    }
+    spans
+}
+
+/// Skips up to two digits of a color.
+fn skip_digits(chars: &mut std::iter::Peekable<std::str::Chars>) {
+    for _ in 0..2 {
+        match chars.peek() {
+            Some(c) if c.is_ascii_digit() => {
+                chars.next();
+            }
+            _ => break,
+        }
+    }
+}
+
+/// Returns the text of a message without the control codes.
+pub fn strip_formatting(text: &str) -> String {
+    parse_formatting(text)
+        .into_iter()
+        .map(|span| span.text)
+        .collect()
+}
+
+/// Returns the text of a message as Pango markup, the markup of the labels of the interface,
+/// with the special characters of the markup escaped.
+pub fn to_markup(text: &str) -> String {
+    parse_formatting(text)
+        .into_iter()
+        .map(|span| span.wrap_markup(escape_markup(&span.text)))
+        .collect()
+}
+
+/// Returns the text with the special characters of the Pango markup escaped.
+pub fn escape_markup(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Returns the text of a message with the ANSI escape codes of the terminal, restoring the
+/// format of the terminal after each formatted piece.
+pub fn to_ansi(text: &str) -> String {
+    let mut ansi = String::new();
+    for span in parse_formatting(text) {
+        if !span.is_formatted() {
+            ansi.push_str(&span.text);
+            continue;
+        }
+        if span.bold {
+            ansi.push_str("\x1b[1m");
+        }
+        if span.italic {
+            ansi.push_str("\x1b[3m");
+        }
+        if span.underline {
+            ansi.push_str("\x1b[4m");
+        }
+        ansi.push_str(&span.text);
+        ansi.push_str("\x1b[0m");
+    }
+    ansi
+}
+
+#[cfg(test)]
+mod formatting_tests {
+    use super::{
+        parse_formatting, strip_formatting, to_ansi, to_markup, FormatStyle, FormattedSpan,
+    };
+
+    #[test]
+    fn test_formats_are_switched_by_their_control_codes() {
+        let text = format!(
+            "plain {} and \x0304,01{}\x0f end",
+            FormatStyle::Bold.apply("bold"),
+            FormatStyle::Italic.apply(&FormatStyle::Underline.apply("both"))
+        );
+        let spans = parse_formatting(&text);
+        assert_eq!(
+            spans,
+            vec![
+                FormattedSpan {
+                    text: "plain ".to_string(),
+                    ..Default::default()
+                },
+                FormattedSpan {
+                    text: "bold".to_string(),
+                    bold: true,
+                    ..Default::default()
+                },
+                FormattedSpan {
+                    text: " and ".to_string(),
+                    ..Default::default()
+                },
+                FormattedSpan {
+                    text: "both".to_string(),
+                    italic: true,
+                    underline: true,
+                    ..Default::default()
+                },
+                FormattedSpan {
+                    text: " end".to_string(),
+                    ..Default::default()
+                },
+            ]
+        );
+        assert_eq!(strip_formatting(&text), "plain bold and both end");
+    }
+
+    #[test]
+    fn test_formatted_text_is_rendered_for_the_interface_and_the_terminal() {
+        let text = format!("{} <3 & more", FormatStyle::Bold.apply("hi"));
+        assert_eq!(to_markup(&text), "<b>hi</b> &lt;3 &amp; more");
+        assert_eq!(to_ansi(&text), "\x1b[1mhi\x1b[0m <3 & more");
+        assert_eq!(to_markup("\x1funderlined"), "<u>underlined</u>");
+    }
+}
@@ -0,0 +1,69 @@
+use std::sync::{Arc, Mutex};
+
+use crate::locks::lock;
+
+/// Struct that holds the totals of the garbage collection passes of the server.
+/// # Fields
+/// * `passes`: The amount of passes that ran.
+/// * `channels_removed`: The amount of empty channels removed.
+/// * `clients_archived`: The amount of clients archived after being disconnected for too long.
+/// * `last_pass`: The time, in seconds since the epoch, of the last pass, or 0 if none ran yet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GcTotals {
+    pub passes: u64,
+    pub channels_removed: u64,
+    pub clients_archived: u64,
+    pub last_pass: u64,
+}
+
+/// Struct that holds the totals of the garbage collection, shared by the thread that runs the
+/// passes and the handlers that report them.
+/// # Fields
+/// * `totals`: The totals of the passes that ran.
+#[derive(Debug, Clone, Default)]
+pub struct GcStats {
+    totals: Arc<Mutex<GcTotals>>,
+}
+
+impl GcStats {
+    /// Counts a pass of the garbage collection.
+    /// # Arguments
+    /// * `channels_removed` - The amount of channels the pass removed.
+    /// * `clients_archived` - The amount of clients the pass archived.
+    /// * `now` - The time of the pass, in seconds since the epoch.
+    pub fn record_pass(&self, channels_removed: usize, clients_archived: usize, now: u64) {
+        let mut totals = lock(&self.totals, "garbage collection stats");
+        totals.passes += 1;
+        totals.channels_removed += channels_removed as u64;
+        totals.clients_archived += clients_archived as u64;
+        totals.last_pass = now;
+    }
+
+    /// Returns the totals of the passes that ran.
+    pub fn totals(&self) -> GcTotals {
+        *lock(&self.totals, "garbage collection stats")
+    }
+}
+
+#[cfg(test)]
+mod gc_stats_tests {
+    use super::{GcStats, GcTotals};
+
+    #[test]
+    fn test_passes_are_added_to_the_totals() {
+        let stats = GcStats::default();
+
+        stats.record_pass(2, 0, 100);
+        stats.record_pass(1, 3, 200);
+
+        assert_eq!(
+            stats.totals(),
+            GcTotals {
+                passes: 2,
+                channels_removed: 3,
+                clients_archived: 3,
+                last_pass: 200,
+            }
+        );
+    }
+}
@@ -0,0 +1,451 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::{
+    message::{Message, MessageType},
+    prefix::Prefix,
+    responses::{
+        dcc::{DccResponse, TransferState},
+        errors::ErrorResponse,
+        message::MessageResponse,
+        replies::{CommandResponse, ConnectionState},
+        response::Response,
+    },
+};
+
+static CASES: u64 = 500;
+
+/// Characters used to build the tokens, with the ones that mean something to the parsers.
+static TOKEN_CHARS: &[char] = &[
+    'a', 'b', 'z', 'A', 'Z', '0', '1', '9', ':', ';', ',', '#', '&', '@', '+', '-', '!', '*', '.',
+    '\\', '\u{1}', 'ñ', 'é', '日',
+];
+
+/// Characters used to build the names of the prefixes, without their separators.
+static NAME_CHARS: &[char] = &['a', 'b', 'z', 'A', 'Z', '0', '1', '9', '-', '_', 'ñ', '日'];
+
+/// Bytes used to build arbitrary input, biased towards separators and the digits of the codes.
+static INPUT_BYTES: &[u8] = &[
+    b' ', b' ', b':', b'\r', b'\n', 0, b'0', b'1', b'2', b'3', b'4', b'5', b'9', b'#', b';', b',',
+    b'a', b'Z', 0xc3, 0xb1, 0xff,
+];
+
+static MESSAGE_TYPES: &[MessageType] = &[
+    MessageType::Pass,
+    MessageType::Nick,
+    MessageType::User,
+    MessageType::Privmsg,
+    MessageType::Join,
+    MessageType::Part,
+    MessageType::Quit,
+    MessageType::Kick,
+    MessageType::Names,
+    MessageType::Topic,
+    MessageType::List,
+    MessageType::Mode,
+    MessageType::Oper,
+    MessageType::Invite,
+    MessageType::Who,
+    MessageType::WhoIs,
+    MessageType::Away,
+    MessageType::Server,
+    MessageType::Squit,
+    MessageType::Dcc,
+    MessageType::Ghost,
+    MessageType::Reclaim,
+    MessageType::Stats,
+    MessageType::Rehash,
+    MessageType::Help,
+    MessageType::Silence,
+    MessageType::SetPref,
+    MessageType::Prefs,
+    MessageType::SetAutoJoin,
+    MessageType::Notice,
+    MessageType::Filter,
+    MessageType::Audit,
+    MessageType::ChanStats,
+    MessageType::ChanSum,
+    MessageType::ChanSync,
+    MessageType::Reply,
+    MessageType::Ping,
+    MessageType::Pong,
+];
+
+/// Pseudo random generator of the values used by the property tests of the parsers.
+/// Every case is generated from its own seed, so a failing case can be reproduced.
+pub struct Gen {
+    state: u64,
+}
+
+impl Gen {
+    /// Creates a generator from the seed received.
+    pub fn new(seed: u64) -> Gen {
+        Gen {
+            state: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1,
+        }
+    }
+
+    /// Returns the next number of the sequence, using xorshift64*.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Returns a number lower than the one received.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Returns true half of the times.
+    pub fn bool(&mut self) -> bool {
+        self.next_u64() % 2 == 0
+    }
+
+    /// Returns one of the items received.
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+
+    /// Returns a number between 0 and 99999, to fill the numeric fields.
+    pub fn number(&mut self) -> u64 {
+        self.next_u64() % 100000
+    }
+
+    /// Returns arbitrary bytes, of up to the length received.
+    pub fn bytes(&mut self, max_len: usize) -> Vec<u8> {
+        let len = self.below(max_len + 1);
+        (0..len)
+            .map(|_| {
+                if self.below(4) == 0 {
+                    self.next_u64() as u8
+                } else {
+                    *self.pick(INPUT_BYTES)
+                }
+            })
+            .collect()
+    }
+
+    /// Returns a token without whitespace, with at least one character.
+    pub fn token(&mut self) -> String {
+        let len = 1 + self.below(8);
+        (0..len).map(|_| *self.pick(TOKEN_CHARS)).collect()
+    }
+
+    /// Returns a token that can be sent as a middle parameter, so it does not start with `:`.
+    pub fn parameter(&mut self) -> String {
+        let token = self.token();
+        match token.strip_prefix(':') {
+            Some(rest) => format!("p{}", rest),
+            None => token,
+        }
+    }
+
+    /// Returns a text of tokens separated by a single space, possibly empty.
+    pub fn text(&mut self) -> String {
+        let words = self.below(5);
+        (0..words)
+            .map(|_| self.token())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Returns a name of a client or server, without the separators of a prefix.
+    pub fn name(&mut self) -> String {
+        let len = 1 + self.below(8);
+        (0..len).map(|_| *self.pick(NAME_CHARS)).collect()
+    }
+
+    /// Returns the prefix of a server, or of a client with an arbitrary part of its mask.
+    pub fn prefix(&mut self) -> Prefix {
+        match self.below(3) {
+            0 => Prefix::ServerName(format!("{}.{}", self.name(), self.name())),
+            1 => Prefix::nick(&self.name()),
+            _ => Prefix::NickMask {
+                nick: self.name(),
+                user: if self.bool() { Some(self.name()) } else { None },
+                host: if self.bool() {
+                    Some(format!("{}.{}", self.name(), self.name()))
+                } else {
+                    None
+                },
+            },
+        }
+    }
+
+    /// Returns a message with an arbitrary prefix, command, parameters and trailing.
+    pub fn message(&mut self) -> Message {
+        let prefix = if self.bool() {
+            Some(self.prefix())
+        } else {
+            None
+        };
+        let command = self.pick(MESSAGE_TYPES).clone();
+        let parameters = (0..self.below(6)).map(|_| self.parameter()).collect();
+        let trailing = if self.bool() { Some(self.text()) } else { None };
+        Message::new(prefix, command, parameters, trailing)
+    }
+
+    /// Returns a response of the ones whose text is parsed back into the same response.
+    pub fn response(&mut self) -> Response {
+        match self.below(38) {
+            0 => Response::CommandResponse {
+                response: CommandResponse::Welcome {
+                    nickname: self.parameter(),
+                    username: self.parameter(),
+                    hostname: self.parameter(),
+                },
+            },
+            1 => Response::CommandResponse {
+                response: CommandResponse::Names {
+                    channel: self.parameter(),
+                    names: (0..self.below(5)).map(|_| self.parameter()).collect(),
+                },
+            },
+            2 => Response::CommandResponse {
+                response: CommandResponse::List {
+                    channel: self.parameter(),
+                    topic: self.text(),
+                },
+            },
+            3 => Response::CommandResponse {
+                response: CommandResponse::Away {
+                    nickname: self.parameter(),
+                    message: self.text(),
+                },
+            },
+            4 => Response::CommandResponse {
+                response: CommandResponse::WhoIsIdle {
+                    nickname: self.parameter(),
+                    idle: self.number(),
+                    signon: self.number(),
+                },
+            },
+            5 => Response::CommandResponse {
+                response: CommandResponse::StatsUptime {
+                    seconds: self.number(),
+                },
+            },
+            6 => Response::CommandResponse {
+                response: CommandResponse::Inviting {
+                    channel: self.parameter(),
+                    nickname: self.parameter(),
+                },
+            },
+            7 => Response::CommandResponse {
+                response: CommandResponse::Ghost {
+                    nickname: self.parameter(),
+                },
+            },
+            8 => Response::ErrorResponse {
+                response: ErrorResponse::NoSuchNick {
+                    nickname: self.parameter(),
+                },
+            },
+            9 => Response::ErrorResponse {
+                response: ErrorResponse::UserNotInChannel {
+                    nickname: self.parameter(),
+                    channel: self.parameter(),
+                },
+            },
+            10 => Response::MessageResponse {
+                response: MessageResponse::UserPrivMsg {
+                    sender: self.parameter(),
+                    message: self.text(),
+                },
+            },
+            11 => Response::MessageResponse {
+                response: MessageResponse::ChannelPrivMsg {
+                    channel: self.parameter(),
+                    sender: self.parameter(),
+                    message: self.text(),
+                },
+            },
+            12 => Response::MessageResponse {
+                response: MessageResponse::KickMsg {
+                    message: self.text(),
+                },
+            },
+            13 => Response::CommandResponse {
+                response: CommandResponse::Rehashing {
+                    file: self.parameter(),
+                },
+            },
+            14 => Response::CommandResponse {
+                response: CommandResponse::HelpText {
+                    topic: self.parameter(),
+                    text: self.text(),
+                },
+            },
+            15 => Response::MessageResponse {
+                response: MessageResponse::UserNotice {
+                    sender: self.parameter(),
+                    message: self.text(),
+                },
+            },
+            16 => Response::DccResponse {
+                response: DccResponse::ChatMessage {
+                    sender: self.parameter(),
+                    message: self.text(),
+                },
+            },
+            17 => Response::MessageResponse {
+                response: MessageResponse::JoinMsg {
+                    channel: self.parameter(),
+                    nickname: self.parameter(),
+                },
+            },
+            18 => Response::MessageResponse {
+                response: MessageResponse::PartMsg {
+                    channel: self.parameter(),
+                    nickname: self.parameter(),
+                },
+            },
+            19 => Response::MessageResponse {
+                response: MessageResponse::QuitMsg {
+                    nickname: self.parameter(),
+                    message: self.text(),
+                },
+            },
+            20 => Response::DccResponse {
+                response: DccResponse::Secured {
+                    sender: self.parameter(),
+                },
+            },
+            21 => Response::DccResponse {
+                response: DccResponse::TransferFailed {
+                    sender: self.parameter(),
+                    file_name: self.parameter(),
+                },
+            },
+            23 => Response::DccResponse {
+                response: DccResponse::TransferQueue {
+                    sender: self.parameter(),
+                    file_name: self.parameter(),
+                    state: *self.pick(&[
+                        TransferState::Pending,
+                        TransferState::Active,
+                        TransferState::Done,
+                    ]),
+                },
+            },
+            22 => Response::DccResponse {
+                response: DccResponse::TransferProgress {
+                    sender: self.parameter(),
+                    file_name: self.parameter(),
+                    progress: self.number() as f64 / 100000.0,
+                    bytes_per_second: self.number(),
+                    seconds_left: self.number(),
+                },
+            },
+            24 => Response::DccResponse {
+                response: DccResponse::TransferRestored {
+                    sender: self.parameter(),
+                    file_name: self.parameter(),
+                    file_size: self.number() as f64,
+                    progress: self.number() as f64 / 100000.0,
+                    received: self.below(2) == 0,
+                },
+            },
+            25 => Response::DccResponse {
+                response: DccResponse::FileReceived {
+                    sender: self.parameter(),
+                    file_name: self.parameter(),
+                    path: self.text(),
+                },
+            },
+            26 => Response::DccResponse {
+                response: DccResponse::RequestExpired {
+                    sender: self.parameter(),
+                },
+            },
+            27 => Response::DccResponse {
+                response: DccResponse::RoomMembers {
+                    room: self.parameter(),
+                    host: self.parameter(),
+                    members: (0..self.below(4)).map(|_| self.parameter()).collect(),
+                },
+            },
+            28 => Response::DccResponse {
+                response: DccResponse::RoomMessage {
+                    room: self.parameter(),
+                    sender: self.parameter(),
+                    message: self.text(),
+                },
+            },
+            29 => Response::DccResponse {
+                response: DccResponse::RoomClosed {
+                    room: self.parameter(),
+                },
+            },
+            30 => Response::DccResponse {
+                response: DccResponse::Typing {
+                    sender: self.parameter(),
+                    typing: self.bool(),
+                },
+            },
+            31 => Response::DccResponse {
+                response: DccResponse::Relayed {
+                    sender: self.parameter(),
+                },
+            },
+            32 => Response::CommandResponse {
+                response: CommandResponse::StatsCommandLatency {
+                    command: self.parameter(),
+                    count: self.number(),
+                    mean: self.number(),
+                    p95: self.number(),
+                    max: self.number(),
+                },
+            },
+            33 => Response::CommandResponse {
+                response: CommandResponse::StatsGarbage {
+                    passes: self.number(),
+                    channels_removed: self.number(),
+                    clients_archived: self.number(),
+                    last_pass: self.number(),
+                },
+            },
+            34 => Response::CommandResponse {
+                response: CommandResponse::Pong {
+                    server: self.parameter(),
+                    token: self.parameter(),
+                },
+            },
+            35 => Response::CommandResponse {
+                response: CommandResponse::ConnectionStatus {
+                    state: *self.pick(&[
+                        ConnectionState::Connected,
+                        ConnectionState::Lagging,
+                        ConnectionState::Reconnecting,
+                    ]),
+                    lag: self.number(),
+                },
+            },
+            36 => Response::ErrorResponse {
+                response: ErrorResponse::InvalidEncoding,
+            },
+            _ => Response::DccResponse {
+                response: DccResponse::TransferRequest {
+                    sender: self.parameter(),
+                    file_name: self.parameter(),
+                    file_size: self.number() as f64,
+                },
+            },
+        }
+    }
+}
+
+/// Checks the property with many generated cases.
+/// If a case fails, it panics with the seed of the case to reproduce it.
+/// # Arguments
+/// * `name` - The name of the property, shown when it fails.
+/// * `property` - The property, that panics if it does not hold for the values generated.
+pub fn check<F: Fn(&mut Gen)>(name: &str, property: F) {
+    for seed in 0..CASES {
+        let mut gen = Gen::new(seed);
+        if catch_unwind(AssertUnwindSafe(|| property(&mut gen))).is_err() {
+            panic!("Property {:?} failed for the seed {}", name, seed);
+        }
+    }
+}
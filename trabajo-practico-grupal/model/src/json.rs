@@ -0,0 +1,101 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+pub use serde_json::{Error as JsonError, Value as Json};
+
+/// Trait of the types that are written as JSON, implemented for every type that serde can
+/// serialize. The records of the database are one JSON object per line.
+pub trait ToJson {
+    /// Returns the JSON of the instance, in a single line and without whitespace.
+    fn to_json(&self) -> String;
+}
+
+/// Trait of the types that are read from JSON, implemented for every type that serde can
+/// deserialize. It is the opposite of `ToJson`.
+pub trait FromJson: Sized {
+    /// Returns the instance read from the text.
+    /// Returns a JsonError if the text is not JSON or doesn't have the fields of the type.
+    /// # Arguments
+    /// * `text` - The JSON of the instance.
+    fn from_json(text: &str) -> Result<Self, JsonError>;
+}
+
+impl<T: Serialize + ?Sized> ToJson for T {
+    /// The values of the model only have text keys, so writing them can't fail.
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+impl<T: DeserializeOwned> FromJson for T {
+    fn from_json(text: &str) -> Result<Self, JsonError> {
+        serde_json::from_str(text)
+    }
+}
+
+#[cfg(test)]
+mod json_tests {
+    use crate::{
+        ban::Ban, channel::Channel, channelflag::ChannelFlag, client::Client, filter::FilterAction,
+        generators::check,
+    };
+
+    use super::{FromJson, Json, ToJson};
+
+    #[test]
+    fn test_strings_with_quotes_and_separators_are_written_in_a_single_line() {
+        let text = "a \"quoted\" topic; with\\ commas, and a\nline \u{1} ñ";
+        let json = text.to_json();
+        assert!(!json.contains('\n'));
+        assert_eq!(String::from_json(&json).unwrap(), text);
+    }
+
+    #[test]
+    fn test_written_strings_are_parsed_back() {
+        check("json strings round trip", |gen| {
+            let text = String::from_utf8_lossy(&gen.bytes(64)).to_string();
+            let record = (text, gen.number());
+            assert_eq!(
+                <(String, u64)>::from_json(&record.to_json()).unwrap(),
+                record
+            );
+        });
+    }
+
+    #[test]
+    fn test_missing_fields_are_empty_if_they_can_be() {
+        let channel = Channel::from_json(r##"{"name":"#rust","topic":"news"}"##).unwrap();
+        assert_eq!(channel.password, None);
+        assert!(channel.users.is_empty());
+        assert!(channel.modes.is_empty());
+        assert!(Channel::from_json(r##"{"name":"#rust"}"##).is_err());
+        assert!(Client::from_json("alice;a").is_err());
+        assert!(FilterAction::from_json("\"drop\"").is_err());
+    }
+
+    #[test]
+    fn test_records_with_separators_in_their_fields_are_read_back() {
+        let mut channel = Channel::new(
+            "#rust".to_string(),
+            "news; links, and \"quotes\"".to_string(),
+            vec!["alice".to_string(), "bob".to_string()],
+        );
+        channel.password = Some("a;b".to_string());
+        channel
+            .banned_users
+            .push(Ban::new("carol".to_string(), "alice".to_string(), 10));
+        channel.modes.push(ChannelFlag::InviteOnly);
+        channel.limit = Some(5);
+
+        let record = channel.to_json();
+        let modes = Json::from_json(&record).unwrap()["modes"].clone();
+        assert_eq!(modes, Json::from_json(r#"["i"]"#).unwrap());
+        let read = Channel::from_json(&record).unwrap();
+        assert_eq!(read.topic, channel.topic);
+        assert_eq!(read.users, channel.users);
+        assert_eq!(read.password, channel.password);
+        assert_eq!(read.banned_users, channel.banned_users);
+        assert_eq!(read.modes, channel.modes);
+        assert_eq!(read.limit, Some(5));
+        assert_eq!(read.forward, None);
+    }
+}
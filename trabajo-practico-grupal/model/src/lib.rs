@@ -1,13 +1,38 @@
+pub mod ban;
 pub mod channel;
+pub mod channel_registry;
+pub mod channel_stats;
 pub mod channelflag;
 pub mod client;
+pub mod client_registry;
+pub mod command_metrics;
 pub mod client_errors;
+pub mod config;
+pub mod consts;
+pub mod crypto;
+pub mod ctcp;
 pub mod dcc;
+pub mod events;
+pub mod filter;
+pub mod formatting;
+pub mod gc_stats;
+#[cfg(test)]
+mod generators;
+pub mod json;
+pub mod link_queues;
+pub mod locks;
+pub mod mask;
 pub mod message;
 pub mod network;
 pub mod persistence;
+pub mod plugins;
+pub mod preferences;
+pub mod prefix;
 pub mod responses;
+pub mod sanitize;
 pub mod server;
 pub mod session;
 pub mod socket;
+pub mod socket_registry;
 pub mod userflag;
+pub mod who_query;
@@ -0,0 +1,164 @@
+use std::{
+    collections::HashMap,
+    io::Write,
+    net::{Shutdown, TcpStream},
+    sync::{
+        mpsc::{sync_channel, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use crate::locks::lock;
+
+/// The messages a link can have waiting to be written before it is dropped.
+static DEFAULT_LINK_QUEUE_CAPACITY: usize = 1024;
+
+/// The socket of a link with the sender of its queue.
+type LinkQueue = (Arc<TcpStream>, SyncSender<Vec<u8>>);
+
+/// Errors of the queue of a link with a server.
+/// # Variants
+/// * `Full`: The link has too many messages waiting, the server stopped reading them.
+/// * `Closed`: The writer of the link stopped, because the socket failed.
+#[derive(Debug, PartialEq, Eq)]
+pub enum LinkQueueError {
+    Full,
+    Closed,
+}
+
+/// Struct that holds the outbound queue of each link with a server connected to this one.
+/// Each queue has its own thread writing to the socket of the link, so a slow server only
+/// delays its own messages instead of the handlers that send them.
+/// # Fields
+/// * `capacity`: The messages each link can have waiting to be written.
+/// * `queues`: The socket of each link with the sender of its queue, by name of the server.
+#[derive(Debug, Clone)]
+pub struct LinkQueues {
+    capacity: usize,
+    queues: Arc<Mutex<HashMap<String, LinkQueue>>>,
+}
+
+impl Default for LinkQueues {
+    fn default() -> Self {
+        LinkQueues::with_capacity(DEFAULT_LINK_QUEUE_CAPACITY)
+    }
+}
+
+impl LinkQueues {
+    /// Creates the queues of the links, each one holding up to `capacity` messages.
+    /// # Arguments
+    /// * `capacity` - The messages each link can have waiting to be written.
+    pub fn with_capacity(capacity: usize) -> LinkQueues {
+        LinkQueues {
+            capacity,
+            queues: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Queues the bytes to be written to the link with the server. The writer of the link is
+    /// started the first time the socket is used, replacing the one of a previous socket.
+    /// # Arguments
+    /// * `server_name` - The name of the linked server.
+    /// * `socket` - The socket of the link.
+    /// * `bytes` - The bytes to write.
+    /// # Errors
+    /// Returns `LinkQueueError::Full` if the queue of the link is full, and
+    /// `LinkQueueError::Closed` if its writer stopped. In both cases the queue is removed and
+    /// the socket is shut down, so the link must be dropped.
+    pub fn send(
+        &self,
+        server_name: &str,
+        socket: &Arc<TcpStream>,
+        bytes: Vec<u8>,
+    ) -> Result<(), LinkQueueError> {
+        let mut queues = lock(&self.queues, "link queues");
+        let sender = match queues.get(server_name) {
+            Some((current, sender)) if Arc::ptr_eq(current, socket) => sender.clone(),
+            _ => {
+                let sender = spawn_link_writer(socket.clone(), self.capacity);
+                queues.insert(server_name.to_string(), (socket.clone(), sender.clone()));
+                sender
+            }
+        };
+        let error = match sender.try_send(bytes) {
+            Ok(()) => return Ok(()),
+            Err(TrySendError::Full(_)) => LinkQueueError::Full,
+            Err(TrySendError::Disconnected(_)) => LinkQueueError::Closed,
+        };
+        queues.remove(server_name);
+        drop(queues);
+        if let Err(e) = socket.shutdown(Shutdown::Both) {
+            println!("Error closing the link with {}: {:?}", server_name, e);
+        }
+        Err(error)
+    }
+
+    /// Removes the queue of the link with the server. Its writer stops once it writes the
+    /// messages that were waiting.
+    /// # Arguments
+    /// * `server_name` - The name of the linked server.
+    pub fn remove(&self, server_name: &str) {
+        lock(&self.queues, "link queues").remove(server_name);
+    }
+}
+
+/// Starts the thread that writes the messages of the queue of a link to its socket, and
+/// returns the sender of the queue. The thread stops when the queue is removed, or shuts the
+/// socket down when a write fails.
+/// # Arguments
+/// * `socket` - The socket of the link.
+/// * `capacity` - The messages the queue can hold.
+fn spawn_link_writer(socket: Arc<TcpStream>, capacity: usize) -> SyncSender<Vec<u8>> {
+    let (sender, receiver) = sync_channel::<Vec<u8>>(capacity);
+    thread::spawn(move || {
+        for bytes in receiver {
+            if let Err(e) = socket.as_ref().write_all(&bytes) {
+                println!("Error writing to a server link: {:?}", e);
+                let _ = socket.shutdown(Shutdown::Both);
+                break;
+            }
+        }
+    });
+    sender
+}
+
+#[cfg(test)]
+mod link_queues_tests {
+    use std::{
+        io::Read,
+        net::{TcpListener, TcpStream},
+        sync::Arc,
+    };
+
+    use super::{LinkQueueError, LinkQueues};
+
+    #[test]
+    fn test_messages_are_written_in_order_by_the_writer_of_the_link() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let socket = Arc::new(TcpStream::connect(listener.local_addr().unwrap()).unwrap());
+        let (mut reader, _) = listener.accept().unwrap();
+        let links = LinkQueues::default();
+
+        links.send("child", &socket, b"first ".to_vec()).unwrap();
+        links.send("child", &socket, b"second".to_vec()).unwrap();
+
+        let mut buf = [0u8; 12];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"first second");
+    }
+
+    #[test]
+    fn test_link_that_stops_reading_overflows_its_queue() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let socket = Arc::new(TcpStream::connect(listener.local_addr().unwrap()).unwrap());
+        let (_reader, _) = listener.accept().unwrap();
+        let links = LinkQueues::with_capacity(4);
+
+        let result = (0..100_000)
+            .map(|_| links.send("child", &socket, vec![0u8; 1024]))
+            .find(Result::is_err);
+
+        assert_eq!(result, Some(Err(LinkQueueError::Full)));
+    }
+}
@@ -0,0 +1,59 @@
+use std::sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+// A lock is poisoned when a thread panics while holding it. The panicking handler
+// already lost its client, so the lock is recovered instead of failing every command
+// that comes after it: the data is used as the panicking thread left it.
+
+/// Locks the data for reading, recovering the lock if it is poisoned.
+/// # Arguments
+/// * `lock` - The lock of the data.
+/// * `name` - The name of the data, shown when the lock is recovered.
+pub fn read_lock<'a, T>(lock: &'a RwLock<T>, name: &str) -> RwLockReadGuard<'a, T> {
+    match lock.read() {
+        Ok(guard) => guard,
+        Err(poisoned) => {
+            println!(
+                "Recovering the {} lock, poisoned by a panicked thread",
+                name
+            );
+            lock.clear_poison();
+            poisoned.into_inner()
+        }
+    }
+}
+
+/// Locks the data for writing, recovering the lock if it is poisoned.
+/// # Arguments
+/// * `lock` - The lock of the data.
+/// * `name` - The name of the data, shown when the lock is recovered.
+pub fn write_lock<'a, T>(lock: &'a RwLock<T>, name: &str) -> RwLockWriteGuard<'a, T> {
+    match lock.write() {
+        Ok(guard) => guard,
+        Err(poisoned) => {
+            println!(
+                "Recovering the {} lock, poisoned by a panicked thread",
+                name
+            );
+            lock.clear_poison();
+            poisoned.into_inner()
+        }
+    }
+}
+
+/// Locks the data, recovering the lock if it is poisoned.
+/// # Arguments
+/// * `lock` - The lock of the data.
+/// * `name` - The name of the data, shown when the lock is recovered.
+pub fn lock<'a, T>(lock: &'a Mutex<T>, name: &str) -> MutexGuard<'a, T> {
+    match lock.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => {
+            println!(
+                "Recovering the {} lock, poisoned by a panicked thread",
+                name
+            );
+            lock.clear_poison();
+            poisoned.into_inner()
+        }
+    }
+}
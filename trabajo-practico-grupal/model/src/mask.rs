@@ -0,0 +1,23 @@
+/// Returns true if the text matches the mask, ignoring the case, where `*` matches any
+/// amount of characters and `?` matches exactly one.
+/// # Arguments
+/// * `mask` - The mask, like `guest*`.
+/// * `text` - The text to match, like a nickname.
+pub fn matches_mask(mask: &str, text: &str) -> bool {
+    let mask = mask.to_lowercase().chars().collect::<Vec<_>>();
+    let text = text.to_lowercase().chars().collect::<Vec<_>>();
+    matches_pattern(&mask, &text)
+}
+
+fn matches_pattern(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => (0..=text.len()).any(|skip| matches_pattern(rest, &text[skip..])),
+        Some((expected, rest)) => match text.split_first() {
+            Some((actual, text)) => {
+                (expected == &'?' || expected == actual) && matches_pattern(rest, text)
+            }
+            None => false,
+        },
+    }
+}
@@ -1,4 +1,8 @@
-static CRLF: &str = "\r\n";
+use crate::{
+    consts::{CRLF, MAX_PARAMETERS},
+    prefix::Prefix,
+};
+
 /// Represents the types of messages that can be sent to the server.
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum MessageType {
@@ -22,6 +26,24 @@ pub enum MessageType {
     Server,
     Squit,
     Dcc,
+    Ghost,
+    Reclaim,
+    Stats,
+    Rehash,
+    Help,
+    Silence,
+    SetPref,
+    Prefs,
+    SetAutoJoin,
+    Notice,
+    Filter,
+    Audit,
+    ChanStats,
+    ChanSum,
+    ChanSync,
+    Reply,
+    Ping,
+    Pong,
 }
 
 impl MessageType {
@@ -48,6 +70,24 @@ impl MessageType {
             "SERVER" => MessageType::Server,
             "SQUIT" => MessageType::Squit,
             "DCC" => MessageType::Dcc,
+            "GHOST" => MessageType::Ghost,
+            "RECLAIM" => MessageType::Reclaim,
+            "STATS" => MessageType::Stats,
+            "REHASH" => MessageType::Rehash,
+            "HELP" => MessageType::Help,
+            "SILENCE" => MessageType::Silence,
+            "SETPREF" => MessageType::SetPref,
+            "PREFS" => MessageType::Prefs,
+            "SETAUTOJOIN" => MessageType::SetAutoJoin,
+            "NOTICE" => MessageType::Notice,
+            "FILTER" => MessageType::Filter,
+            "AUDIT" => MessageType::Audit,
+            "CHANSTATS" => MessageType::ChanStats,
+            "CHANSUM" => MessageType::ChanSum,
+            "CHANSYNC" => MessageType::ChanSync,
+            "REPLY" => MessageType::Reply,
+            "PING" => MessageType::Ping,
+            "PONG" => MessageType::Pong,
             _ => return Err(MessageError::InvalidCommand),
         };
         Ok(message_type)
@@ -76,6 +116,24 @@ impl MessageType {
             MessageType::Server => "SERVER".to_string(),
             MessageType::Squit => "SQUIT".to_string(),
             MessageType::Dcc => "DCC".to_string(),
+            MessageType::Ghost => "GHOST".to_string(),
+            MessageType::Reclaim => "RECLAIM".to_string(),
+            MessageType::Stats => "STATS".to_string(),
+            MessageType::Rehash => "REHASH".to_string(),
+            MessageType::Help => "HELP".to_string(),
+            MessageType::Silence => "SILENCE".to_string(),
+            MessageType::SetPref => "SETPREF".to_string(),
+            MessageType::Prefs => "PREFS".to_string(),
+            MessageType::SetAutoJoin => "SETAUTOJOIN".to_string(),
+            MessageType::Notice => "NOTICE".to_string(),
+            MessageType::Filter => "FILTER".to_string(),
+            MessageType::Audit => "AUDIT".to_string(),
+            MessageType::ChanStats => "CHANSTATS".to_string(),
+            MessageType::ChanSum => "CHANSUM".to_string(),
+            MessageType::ChanSync => "CHANSYNC".to_string(),
+            MessageType::Reply => "REPLY".to_string(),
+            MessageType::Ping => "PING".to_string(),
+            MessageType::Pong => "PONG".to_string(),
         };
         Ok(command_string)
     }
@@ -85,24 +143,94 @@ impl MessageType {
 /// The message is composed of a message type, a prefix, a command, a list of parameters and a trailing parameter.
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Message {
-    pub prefix: Option<String>,
+    pub prefix: Option<Prefix>,
     pub command: MessageType,
     pub parameters: Vec<String>,
     pub trailing: Option<String>,
 }
 
 /// Represents the errors that can occur when parsing a message.
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub enum MessageError {
     EmptyMessage,
     EmptyCommand,
     InvalidCommand,
+    InvalidPrefix,
+    InvalidEncoding,
+}
+
+/// The tokens of a message line, following the grammar of the RFC:
+/// `[":" prefix SPACE] command *14(SPACE middle) [SPACE ":" trailing]`
+/// After 14 middle parameters, the rest of the line is the trailing, even if it doesn't start with `:`.
+/// The tokens are separated by one or more spaces, and the trailing keeps its spaces and colons as they were sent.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Tokens<'a> {
+    pub prefix: Option<&'a str>,
+    pub command: &'a str,
+    pub middles: Vec<&'a str>,
+    pub trailing: Option<&'a str>,
+}
+
+/// Splits a message line into its tokens. The CRLF at the end of the line is not part of them.
+/// Returns a message error if the line is empty, or it has a prefix without name or without command.
+pub fn tokenize(line: &str) -> Result<Tokens<'_>, MessageError> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    let mut rest = line.trim_start_matches(' ');
+    if rest.is_empty() {
+        return Err(MessageError::EmptyMessage);
+    }
+
+    let mut prefix = None;
+    if let Some(line) = rest.strip_prefix(':') {
+        let (name, after) = next_token(line);
+        if name.is_empty() {
+            return Err(MessageError::InvalidPrefix);
+        }
+        prefix = Some(name);
+        rest = after;
+    }
+
+    let (command, mut rest) = next_token(rest);
+    if command.is_empty() {
+        return Err(MessageError::EmptyCommand);
+    }
+
+    let mut middles = vec![];
+    let mut trailing = None;
+    while !rest.is_empty() {
+        if let Some(text) = rest.strip_prefix(':') {
+            trailing = Some(text);
+            break;
+        }
+        if middles.len() == MAX_PARAMETERS - 1 {
+            trailing = Some(rest);
+            break;
+        }
+        let (middle, after) = next_token(rest);
+        middles.push(middle);
+        rest = after;
+    }
+
+    Ok(Tokens {
+        prefix,
+        command,
+        middles,
+        trailing,
+    })
+}
+
+/// Returns the text until the first space, and the text after the spaces that follow it.
+fn next_token(text: &str) -> (&str, &str) {
+    match text.split_once(' ') {
+        Some((token, rest)) => (token, rest.trim_start_matches(' ')),
+        None => (text, ""),
+    }
 }
 
 impl Message {
     /// Creates a new message.
     pub fn new(
-        prefix: Option<String>,
+        prefix: Option<Prefix>,
         command: MessageType,
         parameters: Vec<String>,
         trailing: Option<String>,
@@ -116,57 +244,33 @@ impl Message {
     }
 
     /// Serializes the received string into a Message.
+    /// The string is split with the tokenizer, so the trailing keeps its spaces and colons.
     /// Returns a message error if the message is invalid.
     pub fn serialize(msg: String) -> Result<Message, MessageError> {
-        fn load_parameters(
-            msg: std::str::SplitWhitespace,
-            parameters: &mut Vec<String>,
-        ) -> Option<String> {
-            let mut trailing = vec![];
-            for param in msg {
-                if param.starts_with(':') || !trailing.is_empty() {
-                    match param.strip_prefix(':') {
-                        Some(t) => trailing.push(t.to_string()),
-                        None => trailing.push(param.to_string()),
-                    }
-                } else {
-                    parameters.push(param.to_string());
-                }
-            }
-            if !trailing.is_empty() {
-                return Some(trailing.join(" "));
-            }
-            None
-        }
-
-        let mut msg = msg.split_whitespace();
-        let mut prefix = None;
-        let command;
-        let mut parameters = vec![];
-        let first_token = match msg.next() {
-            Some(token) => token,
-            None => return Err(MessageError::EmptyMessage),
-        };
-        if first_token.starts_with(':') {
-            prefix = first_token.strip_prefix(':').map(|p| p.to_owned());
-            match msg.next() {
-                Some(token) => command = MessageType::string_to_message_type(token.to_owned())?,
-                None => return Err(MessageError::EmptyCommand),
-            };
-        } else {
-            command = MessageType::string_to_message_type(first_token.to_owned())?
-        }
-        let trailing = load_parameters(msg, &mut parameters);
+        let tokens = tokenize(&msg)?;
+        let prefix = tokens.prefix.map(|prefix| prefix.parse()).transpose()?;
+        let command = MessageType::string_to_message_type(tokens.command.to_owned())?;
 
         let message = Message {
             prefix,
             command,
-            parameters,
-            trailing,
+            parameters: tokens.middles.into_iter().map(|p| p.to_owned()).collect(),
+            trailing: tokens.trailing.map(|trailing| trailing.to_owned()),
         };
         Ok(message)
     }
 
+    /// Returns the nickname or server name of the prefix of the message, if it has one.
+    pub fn prefix_name(&self) -> Option<String> {
+        self.prefix.as_ref().map(|prefix| prefix.name().to_owned())
+    }
+
+    /// Sets the prefix of the message to the nickname, keeping the user and host of its prefix if it is
+    /// already the prefix of the nickname, so the messages relayed to other servers keep the full mask.
+    pub fn set_nick_prefix(&mut self, nickname: &str) {
+        self.prefix = Some(Prefix::of_nick(self.prefix.as_ref(), nickname));
+    }
+
     pub fn deserialize(message: Message) -> Result<String, MessageError> {
         let mut msg = String::new();
         if let Some(prefix) = message.prefix.to_owned() {
@@ -274,3 +378,190 @@ mod nick_tests {
     }
 }
 */
+
+#[cfg(test)]
+mod message_tests {
+    use crate::{
+        consts::MAX_PARAMETERS,
+        generators::check,
+        message::{tokenize, Message, MessageError, MessageType, Tokens},
+        prefix::Prefix,
+    };
+
+    #[test]
+    fn test_deserialized_message_is_serialized_back_into_the_same_message() {
+        check("message round trip", |gen| {
+            let message = gen.message();
+            let text = Message::deserialize(message.clone()).unwrap();
+            assert_eq!(Message::serialize(text).unwrap(), message);
+        });
+    }
+
+    #[test]
+    fn test_serialize_never_panics_with_arbitrary_input() {
+        check("message from arbitrary bytes", |gen| {
+            let input = String::from_utf8_lossy(&gen.bytes(64)).to_string();
+            let _ = Message::serialize(input);
+        });
+    }
+
+    #[test]
+    fn test_trailing_keeps_the_colons_after_its_first_word() {
+        let message = Message::serialize("PRIVMSG #channel :hi :) bye".to_string()).unwrap();
+
+        assert_eq!(message.command, MessageType::Privmsg);
+        assert_eq!(message.parameters, vec!["#channel".to_string()]);
+        assert_eq!(message.trailing, Some("hi :) bye".to_string()));
+    }
+
+    #[test]
+    fn test_tokens_of_a_full_message() {
+        let tokens = tokenize(":nick!user@host PRIVMSG #channel bob :hi there\r\n").unwrap();
+
+        assert_eq!(
+            tokens,
+            Tokens {
+                prefix: Some("nick!user@host"),
+                command: "PRIVMSG",
+                middles: vec!["#channel", "bob"],
+                trailing: Some("hi there"),
+            }
+        );
+    }
+
+    #[test]
+    fn test_several_spaces_separate_the_middle_parameters() {
+        let message =
+            Message::serialize(":nick   MODE  #channel    +o   bob  ".to_string()).unwrap();
+
+        assert_eq!(message.prefix, Some(Prefix::nick("nick")));
+        assert_eq!(message.command, MessageType::Mode);
+        assert_eq!(message.parameters, vec!["#channel", "+o", "bob"]);
+        assert_eq!(message.trailing, None);
+    }
+
+    #[test]
+    fn test_trailing_keeps_its_spaces() {
+        let message = Message::serialize("PRIVMSG #channel :  hi   there  ".to_string()).unwrap();
+
+        assert_eq!(message.parameters, vec!["#channel".to_string()]);
+        assert_eq!(message.trailing, Some("  hi   there  ".to_string()));
+    }
+
+    #[test]
+    fn test_colons_inside_middle_parameters_do_not_start_the_trailing() {
+        let message = Message::serialize("PRIVMSG a:b c: ::d : e".to_string()).unwrap();
+
+        assert_eq!(message.parameters, vec!["a:b", "c:"]);
+        assert_eq!(message.trailing, Some(":d : e".to_string()));
+    }
+
+    #[test]
+    fn test_trailing_can_be_empty() {
+        let message = Message::serialize("TOPIC #channel :".to_string()).unwrap();
+        assert_eq!(message.parameters, vec!["#channel".to_string()]);
+        assert_eq!(message.trailing, Some("".to_string()));
+
+        let message = Message::serialize("AWAY :\r\n".to_string()).unwrap();
+        assert!(message.parameters.is_empty());
+        assert_eq!(message.trailing, Some("".to_string()));
+    }
+
+    #[test]
+    fn test_the_line_ending_is_not_part_of_the_message() {
+        for line in ["NICK bob\r\n", "NICK bob\n", "NICK bob\r", "NICK bob"] {
+            let message = Message::serialize(line.to_string()).unwrap();
+            assert_eq!(message.parameters, vec!["bob".to_string()]);
+            assert_eq!(message.trailing, None);
+        }
+
+        let message = Message::serialize("QUIT :bye \r\n".to_string()).unwrap();
+        assert_eq!(message.trailing, Some("bye ".to_string()));
+    }
+
+    #[test]
+    fn test_the_rest_of_the_line_after_fourteen_middles_is_the_trailing() {
+        let middles = (1..MAX_PARAMETERS)
+            .map(|i| format!("p{i}"))
+            .collect::<Vec<_>>();
+
+        let line = format!("PRIVMSG {}  last  words :here", middles.join(" "));
+        let message = Message::serialize(line).unwrap();
+        assert_eq!(message.parameters, middles);
+        assert_eq!(message.trailing, Some("last  words :here".to_string()));
+
+        let line = format!("PRIVMSG {} :last", middles.join(" "));
+        let message = Message::serialize(line).unwrap();
+        assert_eq!(message.parameters, middles);
+        assert_eq!(message.trailing, Some("last".to_string()));
+
+        let line = format!("JOIN {}", middles[..13].join(" "));
+        let message = Message::serialize(line).unwrap();
+        assert_eq!(message.parameters, middles[..13].to_vec());
+        assert_eq!(message.trailing, None);
+    }
+
+    #[test]
+    fn test_invalid_lines_are_errors() {
+        for line in ["", "   ", "\r\n", " \r\n"] {
+            assert_eq!(tokenize(line), Err(MessageError::EmptyMessage));
+        }
+        for line in [":nick", ":nick   ", ":nick\r\n"] {
+            assert_eq!(tokenize(line), Err(MessageError::EmptyCommand));
+        }
+        for line in [":", ": NICK bob", ":\r\n"] {
+            assert_eq!(tokenize(line), Err(MessageError::InvalidPrefix));
+        }
+        assert!(matches!(
+            Message::serialize("NOTACOMMAND bob".to_string()),
+            Err(MessageError::InvalidCommand)
+        ));
+    }
+
+    #[test]
+    fn test_trailing_with_any_spaces_is_serialized_back_into_the_same_message() {
+        check("message with spaced trailing", |gen| {
+            let mut message = gen.message();
+            let words = (0..gen.below(4))
+                .map(|_| format!("{}{}", " ".repeat(gen.below(3)), gen.token()))
+                .collect::<String>();
+            message.trailing = Some(format!("{words}{}", " ".repeat(gen.below(3))));
+            let text = Message::deserialize(message.clone()).unwrap();
+            assert_eq!(Message::serialize(text).unwrap(), message);
+        });
+    }
+
+    #[test]
+    fn test_prefix_is_parsed_into_its_mask() {
+        let message = Message::serialize(":alice!al@localhost JOIN #channel".to_string()).unwrap();
+        assert_eq!(
+            message.prefix,
+            Some(Prefix::mask("alice", "al", "localhost"))
+        );
+        assert_eq!(message.prefix_name(), Some("alice".to_string()));
+
+        let message = Message::serialize(":irc.fiuba.ar SERVER uno 1".to_string()).unwrap();
+        assert_eq!(
+            message.prefix,
+            Some(Prefix::ServerName("irc.fiuba.ar".to_string()))
+        );
+
+        assert!(matches!(
+            Message::serialize(":alice!@host JOIN #channel".to_string()),
+            Err(MessageError::InvalidPrefix)
+        ));
+    }
+
+    #[test]
+    fn test_relayed_messages_keep_the_mask_of_the_nickname() {
+        let mut message = Message::serialize(":alice!al@host PART #channel".to_string()).unwrap();
+        message.set_nick_prefix("alice");
+        assert_eq!(
+            Message::deserialize(message.clone()).unwrap(),
+            ":alice!al@host PART #channel\r\n"
+        );
+
+        message.set_nick_prefix("bob");
+        assert_eq!(message.prefix, Some(Prefix::nick("bob")));
+    }
+}
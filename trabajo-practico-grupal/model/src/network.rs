@@ -1,7 +1,8 @@
-use crate::server::Server;
+use crate::{client::Client, link_queues::LinkQueues, server::Server, userflag::UserFlag};
 use std::{
     collections::HashMap,
     sync::{Arc, RwLock},
+    time::Instant,
 };
 
 /// Struct that holds the information about the servers in the network,
@@ -10,9 +11,200 @@ use std::{
 /// * `server`: The current server running.
 /// * `servers`: The servers in the network.
 /// * `clients`: The clients in the network.
+/// * `links`: The outbound queues of the links with the servers connected to the current one.
 #[derive(Debug, Clone)]
 pub struct Network {
     pub server: Arc<RwLock<Server>>,
-    pub servers: Arc<RwLock<HashMap<String, u8>>>,
-    pub clients: Arc<RwLock<HashMap<String, u8>>>,
+    pub servers: Arc<RwLock<HashMap<String, LinkedServer>>>,
+    pub clients: Arc<RwLock<HashMap<String, RemoteClient>>>,
+    pub links: LinkQueues,
+}
+
+/// Struct that holds what the server knows about another server of the network.
+/// # Fields
+/// * `hopcount`: The distance (in servers) to the server.
+/// * `latency`: The round-trip time of the last PING the server answered, in milliseconds.
+///   None if the server is not linked to the current one or it didn't answer yet.
+/// * `ping_sent`: When the PING that the server didn't answer yet was sent.
+/// * `missed_pongs`: The PINGs in a row that the server didn't answer.
+#[derive(Debug, Clone)]
+pub struct LinkedServer {
+    pub hopcount: u8,
+    pub latency: Option<u64>,
+    pub ping_sent: Option<Instant>,
+    pub missed_pongs: u8,
+}
+
+impl LinkedServer {
+    /// Creates a server of the network that wasn't pinged yet.
+    pub fn new(hopcount: u8) -> LinkedServer {
+        LinkedServer {
+            hopcount,
+            latency: None,
+            ping_sent: None,
+            missed_pongs: 0,
+        }
+    }
+
+    /// Records that a PING was sent to the server, counting the previous one as missed if
+    /// the server didn't answer it. Returns the PINGs in a row that the server didn't answer.
+    /// # Arguments
+    /// * `now` - When the PING was sent.
+    pub fn record_ping(&mut self, now: Instant) -> u8 {
+        if self.ping_sent.is_some() {
+            self.missed_pongs = self.missed_pongs.saturating_add(1);
+        }
+        self.ping_sent = Some(now);
+        self.missed_pongs
+    }
+
+    /// Records the PONG of the server, saving the latency of the link.
+    /// A PONG that answers no PING is ignored.
+    /// # Arguments
+    /// * `now` - When the PONG was received.
+    pub fn record_pong(&mut self, now: Instant) {
+        if let Some(sent) = self.ping_sent.take() {
+            self.latency = Some(now.duration_since(sent).as_millis() as u64);
+            self.missed_pongs = 0;
+        }
+    }
+}
+
+/// Struct that holds what the server knows about a client of the network.
+/// The user information of the clients of other servers is learned from the USER message
+/// that their server sends after their NICK, so it is empty until that message arrives.
+/// # Fields
+/// * `hopcount`: The distance (in servers) to the server the client is connected to.
+/// * `username`: The username of the client.
+/// * `hostname`: The hostname of the client.
+/// * `servername`: The servername the client registered with.
+/// * `realname`: The realname of the client.
+/// * `modes`: The user modes of the client.
+/// * `link`: The linked server the messages to the client are sent through, none if it is a
+///   client of the current server.
+#[derive(Debug, Clone)]
+pub struct RemoteClient {
+    pub hopcount: u8,
+    pub username: String,
+    pub hostname: String,
+    pub servername: String,
+    pub realname: String,
+    pub modes: Vec<UserFlag>,
+    pub link: Option<String>,
+}
+
+impl RemoteClient {
+    /// Creates a new network client with no user information and no modes set.
+    pub fn new(hopcount: u8) -> RemoteClient {
+        RemoteClient {
+            hopcount,
+            username: String::new(),
+            hostname: String::new(),
+            servername: String::new(),
+            realname: String::new(),
+            modes: Vec::new(),
+            link: None,
+        }
+    }
+
+    /// Creates the network client of a client of the current server.
+    /// # Arguments
+    /// * `client` - The client of the current server.
+    pub fn from_client(client: &Client) -> RemoteClient {
+        RemoteClient {
+            hopcount: 0,
+            username: client.username.to_owned(),
+            hostname: client.hostname.to_owned(),
+            servername: client.servername.to_owned(),
+            realname: client.realname.to_owned(),
+            modes: client.modes.to_owned(),
+            link: None,
+        }
+    }
+
+    /// Returns the network client reached through the linked server received.
+    /// # Arguments
+    /// * `link` - The name of the linked server.
+    pub fn with_link(mut self, link: &str) -> RemoteClient {
+        self.link = Some(link.to_string());
+        self
+    }
+
+    /// Returns true if the client has the invisible mode set.
+    pub fn is_invisible(&self) -> bool {
+        self.modes.contains(&UserFlag::Invisible)
+    }
+
+    /// Returns true if the mask is the username, hostname, servername or realname of the client.
+    /// Nothing matches the information that wasn't learned yet.
+    /// # Arguments
+    /// * `mask` - The mask of a WHO query.
+    pub fn matches_user(&self, mask: &str) -> bool {
+        !mask.is_empty()
+            && (self.username == mask
+                || self.hostname == mask
+                || self.servername == mask
+                || self.realname == mask)
+    }
+
+    /// Returns the USER message that informs the other servers of the user information of the
+    /// client, like `:alice USER alice host server :Alice Smith`.
+    /// # Arguments
+    /// * `nickname` - The nickname of the client.
+    pub fn user_message(&self, nickname: &str) -> String {
+        format!(
+            ":{} USER {} {} {} :{}",
+            nickname, self.username, self.hostname, self.servername, self.realname
+        )
+    }
+}
+
+#[cfg(test)]
+mod network_tests {
+    use std::time::{Duration, Instant};
+
+    use super::{LinkedServer, RemoteClient};
+
+    #[test]
+    fn test_pong_saves_the_latency_and_resets_the_missed_pings() {
+        let mut server = LinkedServer::new(1);
+        let start = Instant::now();
+
+        assert_eq!(server.record_ping(start), 0);
+        assert_eq!(server.record_ping(start), 1);
+        assert_eq!(server.record_ping(start), 2);
+        server.record_pong(start + Duration::from_millis(40));
+
+        assert_eq!(server.latency, Some(40));
+        assert_eq!(server.missed_pongs, 0);
+        assert!(server.ping_sent.is_none());
+    }
+
+    #[test]
+    fn test_pong_without_ping_is_ignored() {
+        let mut server = LinkedServer::new(2);
+
+        server.record_pong(Instant::now());
+
+        assert_eq!(server.latency, None);
+    }
+
+    #[test]
+    fn test_remote_client_matches_its_user_information() {
+        let mut client = RemoteClient::new(1);
+
+        assert!(!client.matches_user(""));
+        client.username = "alice".to_string();
+        client.hostname = "host".to_string();
+        client.servername = "server".to_string();
+        client.realname = "Alice Smith".to_string();
+
+        assert!(client.matches_user("alice"));
+        assert!(client.matches_user("Alice Smith"));
+        assert!(!client.matches_user("bob"));
+        assert_eq!(
+            client.user_message("ali"),
+            ":ali USER alice host server :Alice Smith".to_string()
+        );
+    }
 }
@@ -8,10 +8,14 @@ pub enum PersistenceType {
     ClientUpdate(String),
     /// deletes an existing client (identified by nickname) in the database
     ClientDelete(String),
+    /// moves an existing client (identified by nickname) from the database to the archive
+    ClientArchive(String),
     /// inserts a new channel in the database
     ChannelSave,
     /// updates an existing channel (identified by name) in the database
     ChannelUpdate(String),
     /// deletes an existing channel (identified by name) in the database
     ChannelDelete(String),
+    /// appends a record to the audit log
+    AuditRecord,
 }
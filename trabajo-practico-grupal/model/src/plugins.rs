@@ -0,0 +1,153 @@
+use std::{
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+use crate::{
+    events::{ServerEvent, Subscriber},
+    message::Message,
+    session::Session,
+};
+
+/// Enum that represents what a plugin decides about a command sent by a client, before it is
+/// handled.
+/// * Allow -> The command is handled as it was sent
+/// * Deny -> The command is discarded, and the client is told the reason
+/// * Rewrite -> The command is replaced by another one, like the same message with other text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookAction {
+    Allow,
+    Deny(String),
+    Rewrite(Message),
+}
+
+/// Trait of the extensions of the server. Every hook does nothing by default, so a plugin only
+/// implements the ones it needs.
+pub trait Plugin: Send + Sync {
+    /// Returns the name of the plugin, as written in the configuration.
+    fn name(&self) -> &str;
+
+    /// Decides about a command sent by a registered client, before it is handled.
+    /// It is called without holding any lock of the session.
+    /// # Arguments
+    /// * `session` - The session of the server.
+    /// * `nickname` - The nickname of the client that sent the command.
+    /// * `message` - The command, as the plugins before this one left it.
+    fn on_pre_command(
+        &self,
+        _session: &Session,
+        _nickname: &str,
+        _message: &Message,
+    ) -> HookAction {
+        HookAction::Allow
+    }
+
+    /// Reacts to a PRIVMSG or a NOTICE once it was delivered.
+    /// # Arguments
+    /// * `session` - The session of the server.
+    /// * `sender` - The nickname of the client that sent the message.
+    /// * `target` - The user or the channel the message was sent to.
+    /// * `text` - The text of the message.
+    fn on_message(&self, _session: &Session, _sender: &str, _target: &str, _text: &str) {}
+
+    /// Reacts to a client joining a channel.
+    /// # Arguments
+    /// * `session` - The session of the server.
+    /// * `channel` - The channel joined.
+    /// * `nickname` - The nickname of the client.
+    fn on_join(&self, _session: &Session, _channel: &str, _nickname: &str) {}
+}
+
+/// Struct that holds the plugins of the server, in the order they were registered. A clone
+/// holds the same plugins.
+/// It is subscribed to the events of the server to call the hooks of the messages and the joins.
+/// # Fields
+/// * `plugins` - The plugins registered.
+#[derive(Clone, Default)]
+pub struct Plugins {
+    plugins: Arc<RwLock<Vec<Arc<dyn Plugin>>>>,
+}
+
+impl Plugins {
+    /// Adds a plugin, that is called after the ones registered before it.
+    /// # Arguments
+    /// * `plugin` - The plugin to add.
+    pub fn register(&self, plugin: Arc<dyn Plugin>) {
+        match self.plugins.write() {
+            Ok(mut plugins) => plugins.push(plugin),
+            Err(poisoned) => poisoned.into_inner().push(plugin),
+        }
+    }
+
+    /// Returns the names of the plugins registered.
+    pub fn names(&self) -> Vec<String> {
+        self.list()
+            .iter()
+            .map(|plugin| plugin.name().to_string())
+            .collect()
+    }
+
+    /// Returns true if no plugin was registered.
+    pub fn is_empty(&self) -> bool {
+        self.list().is_empty()
+    }
+
+    /// Asks every plugin about a command sent by a client. Each plugin receives the command as
+    /// the previous one rewrote it, and the first one that denies it stops the others.
+    /// Returns `Rewrite` with the final command if any plugin rewrote it.
+    /// # Arguments
+    /// * `session` - The session of the server.
+    /// * `nickname` - The nickname of the client that sent the command.
+    /// * `message` - The command sent.
+    pub fn pre_command(&self, session: &Session, nickname: &str, message: &Message) -> HookAction {
+        let mut rewritten: Option<Message> = None;
+        for plugin in self.list() {
+            let current = rewritten.as_ref().unwrap_or(message);
+            match plugin.on_pre_command(session, nickname, current) {
+                HookAction::Allow => (),
+                HookAction::Deny(reason) => return HookAction::Deny(reason),
+                HookAction::Rewrite(message) => rewritten = Some(message),
+            }
+        }
+        match rewritten {
+            Some(message) => HookAction::Rewrite(message),
+            None => HookAction::Allow,
+        }
+    }
+
+    /// Returns the plugins, released from the lock so they can register others.
+    fn list(&self) -> Vec<Arc<dyn Plugin>> {
+        match self.plugins.read() {
+            Ok(plugins) => plugins.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+}
+
+impl Subscriber for Plugins {
+    fn notify(&self, session: &Session, event: &ServerEvent) {
+        match event {
+            ServerEvent::MessageSent {
+                sender,
+                target,
+                message,
+            } => {
+                for plugin in self.list() {
+                    plugin.on_message(session, sender, target, message);
+                }
+            }
+            ServerEvent::ChannelJoined { channel, nickname } => {
+                for plugin in self.list() {
+                    plugin.on_join(session, channel, nickname);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+impl fmt::Debug for Plugins {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Plugins {{ plugins: {:?} }}", self.names())
+    }
+}
@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+
+/// Enum that represents the preferences a client can save with its record.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Preference {
+    PartMessage,
+    QuitMessage,
+    AutoJoin,
+    Away,
+}
+
+impl Preference {
+    /// Returns the name of the preference used by the commands.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preference::PartMessage => "PARTMSG",
+            Preference::QuitMessage => "QUITMSG",
+            Preference::AutoJoin => "AUTOJOIN",
+            Preference::Away => "AWAY",
+        }
+    }
+
+    /// Returns the preference with the given name, ignoring the case.
+    pub fn from_name(name: &str) -> Option<Preference> {
+        Preference::iter()
+            .into_iter()
+            .find(|preference| preference.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Return a vector of preferences to iterate them.
+    pub fn iter() -> Vec<Preference> {
+        vec![
+            Preference::PartMessage,
+            Preference::QuitMessage,
+            Preference::AutoJoin,
+            Preference::Away,
+        ]
+    }
+}
+
+/// Struct that represents the preferences of a client, applied when the client registers.
+/// # Fields
+/// * `part_message`: The message sent when the client leaves a channel without one.
+/// * `quit_message`: The message sent when the client quits without one.
+/// * `autojoin`: The channels the client joins after registering.
+/// * `away_message`: The away message set when the client registers.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    pub part_message: Option<String>,
+    pub quit_message: Option<String>,
+    pub autojoin: Vec<String>,
+    pub away_message: Option<String>,
+}
+
+impl Preferences {
+    /// Returns the value of the preference, or an empty string if it is not set.
+    /// The channels of the auto-join list are separated by commas.
+    pub fn get(&self, preference: &Preference) -> String {
+        match preference {
+            Preference::PartMessage => self.part_message.to_owned().unwrap_or_default(),
+            Preference::QuitMessage => self.quit_message.to_owned().unwrap_or_default(),
+            Preference::AutoJoin => self.autojoin.join(","),
+            Preference::Away => self.away_message.to_owned().unwrap_or_default(),
+        }
+    }
+
+    /// Sets the value of the preference. An empty value removes it.
+    /// The channels of the auto-join list are separated by commas.
+    pub fn set(&mut self, preference: &Preference, value: &str) {
+        let text = Some(value.to_string()).filter(|value| !value.is_empty());
+        match preference {
+            Preference::PartMessage => self.part_message = text,
+            Preference::QuitMessage => self.quit_message = text,
+            Preference::AutoJoin => {
+                self.autojoin = value
+                    .split(',')
+                    .filter(|channel| !channel.is_empty())
+                    .map(|channel| channel.to_string())
+                    .collect()
+            }
+            Preference::Away => self.away_message = text,
+        }
+    }
+
+    /// Returns the preferences saved as fields of the record of the client, in the order of
+    /// `Preference::iter`, as they were saved before the records were written as JSON.
+    /// The missing fields leave their preferences unset.
+    pub fn from_fields(fields: &[&str]) -> Preferences {
+        let mut preferences = Preferences::default();
+        for (preference, value) in Preference::iter().iter().zip(fields) {
+            preferences.set(preference, value);
+        }
+        preferences
+    }
+}
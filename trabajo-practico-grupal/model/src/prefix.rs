@@ -0,0 +1,172 @@
+use std::{fmt, str::FromStr};
+
+use crate::message::MessageError;
+
+/// The origin of a message, sent after its `:`.
+/// * ServerName -> The name of a server, like `irc.fiuba.ar`
+/// * NickMask -> A client, as `nick`, `nick@host` or `nick!user@host`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Prefix {
+    ServerName(String),
+    NickMask {
+        nick: String,
+        user: Option<String>,
+        host: Option<String>,
+    },
+}
+
+impl Prefix {
+    /// Creates the prefix of a client known only by its nickname.
+    pub fn nick(nick: &str) -> Self {
+        Prefix::NickMask {
+            nick: nick.to_owned(),
+            user: None,
+            host: None,
+        }
+    }
+
+    /// Creates the full prefix of a client, `nick!user@host`.
+    pub fn mask(nick: &str, user: &str, host: &str) -> Self {
+        Prefix::NickMask {
+            nick: nick.to_owned(),
+            user: Some(user.to_owned()),
+            host: Some(host.to_owned()),
+        }
+    }
+
+    /// Returns the nickname of the client or the name of the server, which the handlers use to find
+    /// who sent the message.
+    pub fn name(&self) -> &str {
+        match self {
+            Prefix::ServerName(name) => name,
+            Prefix::NickMask { nick, .. } => nick,
+        }
+    }
+
+    /// Returns the prefix of the nickname, keeping the user and host of this prefix if it is the
+    /// prefix of the same client.
+    pub fn of_nick(prefix: Option<&Prefix>, nickname: &str) -> Self {
+        match prefix {
+            Some(prefix @ Prefix::NickMask { nick, .. }) if nick == nickname => prefix.clone(),
+            _ => Prefix::nick(nickname),
+        }
+    }
+}
+
+impl FromStr for Prefix {
+    type Err = MessageError;
+
+    /// Parses the prefix without its `:`. A prefix with `!` or `@` is a client, and one without them is
+    /// a server if it has a `.`, since nicknames can't have it, or a nickname otherwise.
+    /// Returns MessageError::InvalidPrefix if the prefix, or one of the parts of the mask, is empty or
+    /// has spaces.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if text.is_empty() || text.contains(' ') {
+            return Err(MessageError::InvalidPrefix);
+        }
+        let (rest, host) = match text.split_once('@') {
+            Some((rest, host)) => (rest, Some(host)),
+            None => (text, None),
+        };
+        let (nick, user) = match rest.split_once('!') {
+            Some((nick, user)) => (nick, Some(user)),
+            None => (rest, None),
+        };
+        if nick.is_empty() || user == Some("") || host == Some("") {
+            return Err(MessageError::InvalidPrefix);
+        }
+        if user.is_none() && host.is_none() && nick.contains('.') {
+            return Ok(Prefix::ServerName(nick.to_owned()));
+        }
+        Ok(Prefix::NickMask {
+            nick: nick.to_owned(),
+            user: user.map(|user| user.to_owned()),
+            host: host.map(|host| host.to_owned()),
+        })
+    }
+}
+
+impl fmt::Display for Prefix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Prefix::ServerName(name) => write!(f, "{}", name),
+            Prefix::NickMask { nick, user, host } => {
+                write!(f, "{}", nick)?;
+                if let Some(user) = user {
+                    write!(f, "!{}", user)?;
+                }
+                if let Some(host) = host {
+                    write!(f, "@{}", host)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod prefix_tests {
+    use crate::{generators::check, message::MessageError, prefix::Prefix};
+
+    #[test]
+    fn test_prefixes_of_clients_and_servers_are_parsed() {
+        assert_eq!("alice".parse(), Ok(Prefix::nick("alice")));
+        assert_eq!(
+            "alice!al@localhost".parse(),
+            Ok(Prefix::mask("alice", "al", "localhost"))
+        );
+        assert_eq!(
+            "alice@localhost".parse(),
+            Ok(Prefix::NickMask {
+                nick: "alice".to_string(),
+                user: None,
+                host: Some("localhost".to_string()),
+            })
+        );
+        assert_eq!(
+            "irc.fiuba.ar".parse(),
+            Ok(Prefix::ServerName("irc.fiuba.ar".to_string()))
+        );
+        assert_eq!(
+            "alice!al@10.0.0.1".parse(),
+            Ok(Prefix::mask("alice", "al", "10.0.0.1"))
+        );
+    }
+
+    #[test]
+    fn test_prefixes_with_empty_parts_are_invalid() {
+        for text in ["", "!al@host", "alice!@host", "alice!al@", "alice@", "a b"] {
+            assert_eq!(text.parse::<Prefix>(), Err(MessageError::InvalidPrefix));
+        }
+    }
+
+    #[test]
+    fn test_the_name_of_a_prefix_is_its_nickname_or_server() {
+        assert_eq!(Prefix::mask("alice", "al", "host").name(), "alice");
+        assert_eq!(
+            Prefix::ServerName("irc.fiuba.ar".to_string()).name(),
+            "irc.fiuba.ar"
+        );
+    }
+
+    #[test]
+    fn test_the_prefix_of_a_nickname_keeps_its_mask() {
+        let mask = Prefix::mask("alice", "al", "host");
+
+        assert_eq!(Prefix::of_nick(Some(&mask), "alice"), mask);
+        assert_eq!(Prefix::of_nick(Some(&mask), "bob"), Prefix::nick("bob"));
+        assert_eq!(Prefix::of_nick(None, "bob"), Prefix::nick("bob"));
+        assert_eq!(
+            Prefix::of_nick(Some(&Prefix::ServerName("a.b".to_string())), "a.b"),
+            Prefix::nick("a.b")
+        );
+    }
+
+    #[test]
+    fn test_displayed_prefixes_are_parsed_back_into_the_same_prefix() {
+        check("prefix round trip", |gen| {
+            let prefix = gen.prefix();
+            assert_eq!(prefix.to_string().parse(), Ok(prefix));
+        });
+    }
+}
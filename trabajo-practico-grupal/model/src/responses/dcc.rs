@@ -1,6 +1,36 @@
 use std::fmt::Display;
+
+/// The states of a file in the transfer queue of a dcc connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferState {
+    Pending,
+    Active,
+    Done,
+}
+
+impl TransferState {
+    /// Returns the name of the state sent to the interface
+    pub fn as_str(&self) -> &str {
+        match self {
+            TransferState::Pending => "pending",
+            TransferState::Active => "active",
+            TransferState::Done => "done",
+        }
+    }
+
+    /// Returns the state with the name, or None if there is no state with it
+    pub fn from_name(name: &str) -> Option<TransferState> {
+        match name {
+            "pending" => Some(TransferState::Pending),
+            "active" => Some(TransferState::Active),
+            "done" => Some(TransferState::Done),
+            _ => None,
+        }
+    }
+}
+
 ///Enum that represents the different types of DCC responses the client can send to the interface
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum DccResponse {
     Pending {
         sender: String,
@@ -22,6 +52,8 @@ pub enum DccResponse {
         sender: String,
         file_name: String,
         progress: f64,
+        bytes_per_second: u64,
+        seconds_left: u64,
     },
     CloseConnection {
         sender: String,
@@ -61,6 +93,53 @@ pub enum DccResponse {
         sender: String,
         file_name: String,
     },
+    Secured {
+        sender: String,
+    },
+    TransferFailed {
+        sender: String,
+        file_name: String,
+    },
+    TransferQueue {
+        sender: String,
+        file_name: String,
+        state: TransferState,
+    },
+    TransferRestored {
+        sender: String,
+        file_name: String,
+        file_size: f64,
+        progress: f64,
+        received: bool,
+    },
+    FileReceived {
+        sender: String,
+        file_name: String,
+        path: String,
+    },
+    RequestExpired {
+        sender: String,
+    },
+    RoomMembers {
+        room: String,
+        host: String,
+        members: Vec<String>,
+    },
+    RoomMessage {
+        room: String,
+        sender: String,
+        message: String,
+    },
+    RoomClosed {
+        room: String,
+    },
+    Typing {
+        sender: String,
+        typing: bool,
+    },
+    Relayed {
+        sender: String,
+    },
 }
 
 impl Display for DccResponse {
@@ -89,7 +168,12 @@ impl Display for DccResponse {
                 sender,
                 file_name,
                 progress,
-            } => format!("206 {} {} {}", sender, file_name, progress),
+                bytes_per_second,
+                seconds_left,
+            } => format!(
+                "206 {} {} {} {} {}",
+                sender, file_name, progress, bytes_per_second, seconds_left
+            ),
             DccResponse::TransferRequest {
                 sender,
                 file_name,
@@ -121,6 +205,60 @@ impl Display for DccResponse {
             DccResponse::OngoingTransfer { sender, file_name } => {
                 format!("215 {} {}", sender, file_name)
             }
+            DccResponse::Secured { sender } => {
+                format!("218 {}", sender)
+            }
+            DccResponse::TransferFailed { sender, file_name } => {
+                format!("220 {} {}", sender, file_name)
+            }
+            DccResponse::TransferQueue {
+                sender,
+                file_name,
+                state,
+            } => {
+                format!("222 {} {} {}", sender, file_name, state.as_str())
+            }
+            DccResponse::TransferRestored {
+                sender,
+                file_name,
+                file_size,
+                progress,
+                received,
+            } => {
+                let direction = if *received { "received" } else { "sent" };
+                format!(
+                    "223 {} {} {} {} {}",
+                    sender, file_name, file_size, progress, direction
+                )
+            }
+            DccResponse::FileReceived {
+                sender,
+                file_name,
+                path,
+            } => {
+                format!("224 {} {} {}", sender, file_name, path)
+            }
+            DccResponse::RequestExpired { sender } => format!("225 {}", sender),
+            DccResponse::RoomMembers {
+                room,
+                host,
+                members,
+            } => {
+                format!("226 {} {} {}", room, host, members.join(" "))
+            }
+            DccResponse::RoomMessage {
+                room,
+                sender,
+                message,
+            } => {
+                format!("227 {} {} {}", room, sender, message)
+            }
+            DccResponse::RoomClosed { room } => format!("228 {}", room),
+            DccResponse::Typing { sender, typing } => {
+                let state = if *typing { "on" } else { "off" };
+                format!("229 {} {}", sender, state)
+            }
+            DccResponse::Relayed { sender } => format!("230 {}", sender),
         };
         write!(f, "{}", r)
     }
@@ -135,66 +273,118 @@ impl DccResponse {
             .split_whitespace()
             .map(|x| x.to_string())
             .collect::<Vec<_>>();
-        let command = msg[0].as_str();
+        let command = msg.first()?.as_str();
         match command {
             "200" => Some(DccResponse::Accepted {
-                sender: msg[1].clone(),
+                sender: msg.get(1)?.clone(),
             }),
             "201" => Some(DccResponse::Pending {
-                sender: msg[1].clone(),
+                sender: msg.get(1)?.clone(),
             }),
             "202" => Some(DccResponse::Rejected {
-                sender: msg[1].clone(),
+                sender: msg.get(1)?.clone(),
             }),
             "203" => Some(DccResponse::ChatRequest {
-                sender: msg[1].clone(),
+                sender: msg.get(1)?.clone(),
             }),
             "204" => Some(DccResponse::ChatMessage {
-                sender: msg[1].clone(),
-                message: msg[2..].join(" "),
+                sender: msg.get(1)?.clone(),
+                message: msg.get(2..)?.join(" "),
             }),
             "205" => Some(DccResponse::CloseConnection {
-                sender: msg[1].clone(),
+                sender: msg.get(1)?.clone(),
             }),
             "206" => Some(DccResponse::TransferProgress {
-                sender: msg[1].clone(),
-                file_name: msg[2].clone(),
-                progress: msg[3].parse::<f64>().unwrap_or(0.0),
+                sender: msg.get(1)?.clone(),
+                file_name: msg.get(2)?.clone(),
+                progress: msg.get(3)?.parse::<f64>().unwrap_or(0.0),
+                bytes_per_second: msg.get(4).and_then(|speed| speed.parse().ok()).unwrap_or(0),
+                seconds_left: msg
+                    .get(5)
+                    .and_then(|seconds| seconds.parse().ok())
+                    .unwrap_or(0),
             }),
             "207" => Some(DccResponse::TransferRequest {
-                sender: msg[1].clone(),
-                file_name: msg[2].clone(),
-                file_size: msg[3].parse::<f64>().unwrap_or(0.0),
+                sender: msg.get(1)?.clone(),
+                file_name: msg.get(2)?.clone(),
+                file_size: msg.get(3)?.parse::<f64>().unwrap_or(0.0),
             }),
             "208" => Some(DccResponse::TransferDeclined {
-                sender: msg[1].clone(),
-                file_name: msg[2].clone(),
+                sender: msg.get(1)?.clone(),
+                file_name: msg.get(2)?.clone(),
             }),
             "209" => Some(DccResponse::TransferPaused {
-                sender: msg[1].clone(),
-                file_name: msg[2].clone(),
+                sender: msg.get(1)?.clone(),
+                file_name: msg.get(2)?.clone(),
             }),
             "210" => Some(DccResponse::ErrorResponse {
-                description: msg[1..].join(" "),
+                description: msg.get(1..)?.join(" "),
             }),
             "211" => Some(DccResponse::TransferResumed {
-                sender: msg[1].clone(),
-                file_name: msg[2].clone(),
+                sender: msg.get(1)?.clone(),
+                file_name: msg.get(2)?.clone(),
             }),
             "212" => Some(DccResponse::ResumeAddressErrorResponse {
-                sender: msg[1].clone(),
-                file_name: msg[2].clone(),
+                sender: msg.get(1)?.clone(),
+                file_name: msg.get(2)?.clone(),
             }),
             "213" => Some(DccResponse::SendAddressErrorResponse {
-                sender: msg[1].clone(),
-                file_name: msg[2].clone(),
+                sender: msg.get(1)?.clone(),
+                file_name: msg.get(2)?.clone(),
             }),
             "214" => Some(DccResponse::ChatAddressErrorResponse {
-                sender: msg[1].clone(),
+                sender: msg.get(1)?.clone(),
             }),
             "215" => Some(DccResponse::OngoingTransfer {
-                sender: msg[1].clone(),
-                file_name: msg[2].clone(),
+                sender: msg.get(1)?.clone(),
+                file_name: msg.get(2)?.clone(),
+            }),
+            "218" => Some(DccResponse::Secured {
+                sender: msg.get(1)?.clone(),
+            }),
+            "220" => Some(DccResponse::TransferFailed {
+                sender: msg.get(1)?.clone(),
+                file_name: msg.get(2)?.clone(),
+            }),
+            "222" => Some(DccResponse::TransferQueue {
+                sender: msg.get(1)?.clone(),
+                file_name: msg.get(2)?.clone(),
+                state: TransferState::from_name(msg.get(3)?)?,
+            }),
+            "223" => Some(DccResponse::TransferRestored {
+                sender: msg.get(1)?.clone(),
+                file_name: msg.get(2)?.clone(),
+                file_size: msg.get(3)?.parse::<f64>().unwrap_or(0.0),
+                progress: msg.get(4)?.parse::<f64>().unwrap_or(0.0),
+                received: msg.get(5)? == "received",
+            }),
+            "224" => Some(DccResponse::FileReceived {
+                sender: msg.get(1)?.clone(),
+                file_name: msg.get(2)?.clone(),
+                path: msg.get(3..)?.join(" "),
+            }),
+            "225" => Some(DccResponse::RequestExpired {
+                sender: msg.get(1)?.clone(),
+            }),
+            "226" => Some(DccResponse::RoomMembers {
+                room: msg.get(1)?.clone(),
+                host: msg.get(2)?.clone(),
+                members: msg.get(3..)?.to_vec(),
+            }),
+            "227" => Some(DccResponse::RoomMessage {
+                room: msg.get(1)?.clone(),
+                sender: msg.get(2)?.clone(),
+                message: msg.get(3..)?.join(" "),
+            }),
+            "228" => Some(DccResponse::RoomClosed {
+                room: msg.get(1)?.clone(),
+            }),
+            "229" => Some(DccResponse::Typing {
+                sender: msg.get(1)?.clone(),
+                typing: msg.get(2)? == "on",
+            }),
+            "230" => Some(DccResponse::Relayed {
+                sender: msg.get(1)?.clone(),
             }),
             _ => None,
         }
@@ -9,6 +9,7 @@ pub enum ErrorResponse {
     NoSuchChannel { channel: String },
     CannotSendToChannel { channel: String },
     TooManyChannels { channel: String },
+    TooManyTargets { target: String },
     UnknownCommand { command: String },
     NickInUse { nickname: String },
     NoNicknameGiven,
@@ -33,6 +34,12 @@ pub enum ErrorResponse {
     UsersDontMatch,
     KeySet { channel: String },
     ClientDisconnected { nickname: String },
+    TooManyConnections { host: String },
+    ChannelCreationDenied { channel: String },
+    HelpNotFound { topic: String },
+    SilenceListFull { mask: String },
+    LinkChannel { channel: String, target: String },
+    InvalidEncoding,
 }
 
 impl Display for ErrorResponse {
@@ -54,7 +61,23 @@ impl Display for ErrorResponse {
             ErrorResponse::ChannelIsFull { channel } => {
                 format!("471 {} :Cannot join channel (+l)", channel)
             }
+            ErrorResponse::LinkChannel { channel, target } => {
+                format!("470 {} {} :Forwarding to another channel", channel, target)
+            }
             ErrorResponse::YouAreBanned => "465 :You are banned from this server".to_string(),
+            ErrorResponse::InvalidEncoding => "400 :Message is not valid UTF-8".to_string(),
+            ErrorResponse::ChannelCreationDenied { channel } => {
+                format!("479 {} :You are not allowed to create channels", channel)
+            }
+            ErrorResponse::HelpNotFound { topic } => {
+                format!("524 {} :No help available on this topic", topic)
+            }
+            ErrorResponse::SilenceListFull { mask } => {
+                format!("511 {} :Your silence list is full", mask)
+            }
+            ErrorResponse::TooManyConnections { host } => {
+                format!("463 {} :Too many connections from your host", host)
+            }
             ErrorResponse::PasswordMismatch => "464 :Password incorrect".to_string(),
             ErrorResponse::AlreadyRegistered { nickname } => {
                 format!("462 {} :You may not reregister", nickname)
@@ -80,6 +103,9 @@ impl Display for ErrorResponse {
             ErrorResponse::TooManyChannels { channel } => {
                 format!("405 {} :You have joined too many channels", channel)
             }
+            ErrorResponse::TooManyTargets { target } => {
+                format!("407 {} :Too many targets", target)
+            }
             ErrorResponse::CannotSendToChannel { channel } => {
                 format!("404 {} :Cannot send to channel", channel)
             }
@@ -122,79 +148,99 @@ impl ErrorResponse {
             .split_whitespace()
             .map(|x| x.to_string())
             .collect::<Vec<_>>();
-        let error = msg[0].as_str();
+        let error = msg.first()?.as_str();
         match error {
+            "400" => Some(ErrorResponse::InvalidEncoding),
             "401" => Some(ErrorResponse::NoSuchNick {
-                nickname: msg[1].clone(),
+                nickname: msg.get(1)?.clone(),
             }),
             "402" => Some(ErrorResponse::NoSuchServer {
-                servername: msg[1].clone(),
+                servername: msg.get(1)?.clone(),
             }),
             "403" => Some(ErrorResponse::NoSuchChannel {
-                channel: msg[1].clone(),
+                channel: msg.get(1)?.clone(),
             }),
             "404" => Some(ErrorResponse::CannotSendToChannel {
-                channel: msg[1].clone(),
+                channel: msg.get(1)?.clone(),
             }),
             "405" => Some(ErrorResponse::TooManyChannels {
-                channel: msg[1].clone(),
+                channel: msg.get(1)?.clone(),
+            }),
+            "407" => Some(ErrorResponse::TooManyTargets {
+                target: msg.get(1)?.clone(),
             }),
             "421" => Some(ErrorResponse::UnknownCommand {
-                command: msg[1].clone(),
+                command: msg.get(1)?.clone(),
             }),
             "431" => Some(ErrorResponse::NoNicknameGiven),
             "432" => Some(ErrorResponse::NotRegistered),
             "441" => Some(ErrorResponse::UserNotInChannel {
-                nickname: msg[1].clone(),
-                channel: msg[2].clone(),
+                nickname: msg.get(1)?.clone(),
+                channel: msg.get(2)?.clone(),
             }),
             "442" => Some(ErrorResponse::NotOnChannel {
-                channel: msg[1].clone(),
+                channel: msg.get(1)?.clone(),
             }),
             "443" => Some(ErrorResponse::UserOnChannel {
-                nickname: msg[1].clone(),
-                channel: msg[2].clone(),
+                nickname: msg.get(1)?.clone(),
+                channel: msg.get(2)?.clone(),
             }),
             "444" => Some(ErrorResponse::NoLogin {
-                nickname: msg[1].clone(),
+                nickname: msg.get(1)?.clone(),
             }),
             "461" => Some(ErrorResponse::NeedMoreParams {
-                command: msg[1].clone(),
+                command: msg.get(1)?.clone(),
             }),
             "462" => Some(ErrorResponse::AlreadyRegistered {
-                nickname: msg[1].clone(),
+                nickname: msg.get(1)?.clone(),
             }),
             "464" => Some(ErrorResponse::PasswordMismatch),
             "465" => Some(ErrorResponse::YouAreBanned),
+            "479" => Some(ErrorResponse::ChannelCreationDenied {
+                channel: msg.get(1)?.clone(),
+            }),
+            "524" => Some(ErrorResponse::HelpNotFound {
+                topic: msg.get(1)?.clone(),
+            }),
+            "511" => Some(ErrorResponse::SilenceListFull {
+                mask: msg.get(1)?.clone(),
+            }),
+            "463" => Some(ErrorResponse::TooManyConnections {
+                host: msg.get(1)?.clone(),
+            }),
             "467" => Some(ErrorResponse::KeySet {
-                channel: msg[1].clone(),
+                channel: msg.get(1)?.clone(),
             }),
             "471" => Some(ErrorResponse::ChannelIsFull {
-                channel: msg[1].clone(),
+                channel: msg.get(1)?.clone(),
+            }),
+            "470" => Some(ErrorResponse::LinkChannel {
+                channel: msg.get(1)?.clone(),
+                target: msg.get(2)?.clone(),
             }),
             "473" => Some(ErrorResponse::InviteOnlyChannel {
-                channel: msg[1].clone(),
+                channel: msg.get(1)?.clone(),
             }),
             "474" => Some(ErrorResponse::BannedFromChannel {
-                channel: msg[1].clone(),
+                channel: msg.get(1)?.clone(),
             }),
             "475" => Some(ErrorResponse::BadChannelKey {
-                channel: msg[1].clone(),
+                channel: msg.get(1)?.clone(),
             }),
             "481" => Some(ErrorResponse::NoPrivileges),
             "482" => Some(ErrorResponse::ChanOPrivsNeeded {
-                channel: msg[1].clone(),
+                channel: msg.get(1)?.clone(),
             }),
             "501" => Some(ErrorResponse::UnknownModeFlag),
             "502" => Some(ErrorResponse::UsersDontMatch),
             "472" => Some(ErrorResponse::UnknownMode {
-                character: msg[1].chars().next().unwrap(),
+                character: msg.get(1)?.chars().next()?,
             }),
             "433" => Some(ErrorResponse::NickInUse {
-                nickname: msg[1].clone(),
+                nickname: msg.get(1)?.clone(),
             }),
             "999" => Some(ErrorResponse::ClientDisconnected {
-                nickname: msg[1].clone(),
+                nickname: msg.get(1)?.clone(),
             }),
             _ => None,
         }
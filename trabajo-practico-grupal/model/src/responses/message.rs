@@ -1,7 +1,7 @@
 use std::fmt::Display;
 
 /// Message that is sent by the server to the client. It is used to send notifications and private messages to the client.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum MessageResponse {
     UserPrivMsg {
         sender: String,
@@ -18,6 +18,35 @@ pub enum MessageResponse {
     InviteMsg {
         message: String,
     },
+    ChannelModeMsg {
+        channel: String,
+        sender: String,
+        modes: String,
+    },
+    VoiceMsg {
+        message: String,
+    },
+    UserNotice {
+        sender: String,
+        message: String,
+    },
+    ChannelNotice {
+        channel: String,
+        sender: String,
+        message: String,
+    },
+    JoinMsg {
+        channel: String,
+        nickname: String,
+    },
+    PartMsg {
+        channel: String,
+        nickname: String,
+    },
+    QuitMsg {
+        nickname: String,
+        message: String,
+    },
 }
 
 impl Display for MessageResponse {
@@ -40,12 +69,65 @@ impl Display for MessageResponse {
             MessageResponse::InviteMsg { message } => {
                 format!("005 {}", message)
             }
+            MessageResponse::ChannelModeMsg {
+                channel,
+                sender,
+                modes,
+            } => {
+                format!("011 {} {} {}", channel, sender, modes)
+            }
+            MessageResponse::VoiceMsg { message } => {
+                format!("012 {}", message)
+            }
+            MessageResponse::UserNotice { sender, message } => {
+                format!("015 {} {}", sender, message)
+            }
+            MessageResponse::ChannelNotice {
+                channel,
+                sender,
+                message,
+            } => {
+                format!("016 {} {} {}", channel, sender, message)
+            }
+            MessageResponse::JoinMsg { channel, nickname } => {
+                format!("017 {} {}", channel, nickname)
+            }
+            MessageResponse::PartMsg { channel, nickname } => {
+                format!("018 {} {}", channel, nickname)
+            }
+            MessageResponse::QuitMsg { nickname, message } => {
+                format!("019 {} {}", nickname, message)
+            }
         };
         write!(f, "{}", r)
     }
 }
 
 impl MessageResponse {
+    /// Returns the text of a presence or mode event shown to the user as a system line, like
+    /// `alice joined #rust` or `bob set +i`, or None if the message is not an event.
+    pub fn event_text(&self) -> Option<String> {
+        let text = match self {
+            MessageResponse::JoinMsg { channel, nickname } => {
+                format!("{} joined {}", nickname, channel)
+            }
+            MessageResponse::PartMsg { channel, nickname } => {
+                format!("{} left {}", nickname, channel)
+            }
+            MessageResponse::QuitMsg { nickname, message } if message.is_empty() => {
+                format!("{} quit", nickname)
+            }
+            MessageResponse::QuitMsg { nickname, message } => {
+                format!("{} quit ({})", nickname, message)
+            }
+            MessageResponse::ChannelModeMsg { sender, modes, .. } => {
+                format!("{} set {}", sender, modes)
+            }
+            _ => return None,
+        };
+        Some(text)
+    }
+
     /// Creates a new instance of the message response enum, it will parse the string and return the correct enum variant.
     /// It is the opposite of the `Display` trait.
     /// It will return none if the string is not a valid message response.
@@ -54,30 +136,84 @@ impl MessageResponse {
             .split_whitespace()
             .map(|x| x.to_string())
             .collect::<Vec<_>>();
-        let command = msg[0].as_str();
+        let command = msg.first()?.as_str();
         match command {
             "002" => Some(MessageResponse::UserPrivMsg {
-                sender: msg[1].clone(),
-                message: msg[2..].to_owned().join(" "),
+                sender: msg.get(1)?.clone(),
+                message: msg.get(2..)?.to_owned().join(" "),
             }),
             "003" => {
-                match msg[1].strip_prefix(':') {
+                match msg.get(1)?.strip_prefix(':') {
                     Some(p) => p.to_owned(),
                     None => "".to_owned(),
                 };
                 Some(MessageResponse::ChannelPrivMsg {
-                    channel: msg[1].clone(),
-                    sender: msg[2].clone(),
-                    message: msg[3..].to_owned().join(" "),
+                    channel: msg.get(1)?.clone(),
+                    sender: msg.get(2)?.clone(),
+                    message: msg.get(3..)?.to_owned().join(" "),
                 })
             }
             "004" => Some(MessageResponse::KickMsg {
-                message: msg[1..].to_owned().join(" "),
+                message: msg.get(1..)?.to_owned().join(" "),
             }),
             "005" => Some(MessageResponse::InviteMsg {
-                message: msg[1..].to_owned().join(" "),
+                message: msg.get(1..)?.to_owned().join(" "),
+            }),
+            "011" if msg.len() > 3 => Some(MessageResponse::ChannelModeMsg {
+                channel: msg.get(1)?.clone(),
+                sender: msg.get(2)?.clone(),
+                modes: msg.get(3..)?.to_owned().join(" "),
+            }),
+            "012" => Some(MessageResponse::VoiceMsg {
+                message: msg.get(1..)?.to_owned().join(" "),
+            }),
+            "015" => Some(MessageResponse::UserNotice {
+                sender: msg.get(1)?.clone(),
+                message: msg.get(2..)?.to_owned().join(" "),
+            }),
+            "016" => Some(MessageResponse::ChannelNotice {
+                channel: msg.get(1)?.clone(),
+                sender: msg.get(2)?.clone(),
+                message: msg.get(3..)?.to_owned().join(" "),
+            }),
+            "017" => Some(MessageResponse::JoinMsg {
+                channel: msg.get(1)?.clone(),
+                nickname: msg.get(2)?.clone(),
+            }),
+            "018" => Some(MessageResponse::PartMsg {
+                channel: msg.get(1)?.clone(),
+                nickname: msg.get(2)?.clone(),
+            }),
+            "019" => Some(MessageResponse::QuitMsg {
+                nickname: msg.get(1)?.clone(),
+                message: msg.get(2..)?.to_owned().join(" "),
             }),
             _ => None,
         }
     }
 }
+
+#[cfg(test)]
+mod message_tests {
+    use super::MessageResponse;
+
+    #[test]
+    fn test_events_are_shown_as_system_lines() {
+        let join = MessageResponse::serialize("017 #rust alice".to_string()).unwrap();
+        assert_eq!(join.event_text().unwrap(), "alice joined #rust");
+        let part = MessageResponse::serialize("018 #rust alice".to_string()).unwrap();
+        assert_eq!(part.event_text().unwrap(), "alice left #rust");
+        let quit = MessageResponse::serialize("019 alice see you".to_string()).unwrap();
+        assert_eq!(quit.event_text().unwrap(), "alice quit (see you)");
+        let quit = MessageResponse::QuitMsg {
+            nickname: "alice".to_string(),
+            message: "".to_string(),
+        };
+        assert_eq!(quit.event_text().unwrap(), "alice quit");
+        let mode = MessageResponse::serialize("011 #rust bob +i".to_string()).unwrap();
+        assert_eq!(mode.event_text().unwrap(), "bob set +i");
+
+        let privmsg = MessageResponse::serialize("002 alice hello".to_string()).unwrap();
+        assert!(privmsg.event_text().is_none());
+    }
+}
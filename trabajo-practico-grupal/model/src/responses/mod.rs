@@ -1,6 +1,8 @@
 pub mod dcc;
 pub mod errors;
 pub mod message;
+pub mod numeric;
 pub mod ongoing_transfer;
 pub mod replies;
 pub mod response;
+pub mod writer;
@@ -0,0 +1,408 @@
+use crate::userflag::UserFlag;
+
+use super::{
+    errors::ErrorResponse, message::MessageResponse, replies::CommandResponse, response::Response,
+    writer::ResponseWriter,
+};
+
+/// The formats in which a connection sends and receives messages.
+/// # Variants
+/// * `Custom`: Zero padded frames of 510 bytes with the responses of this server, used by our client.
+/// * `Numeric`: Lines terminated in `\r\n` with the numeric replies of the RFC 1459, used by standard irc clients.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireFormat {
+    Custom,
+    Numeric,
+}
+
+impl Response {
+    /// Encodes the response as the RFC 1459 lines that are sent to the given client.
+    /// The lines don't have the trailing `\r\n` nor the prefix of the server, which is optional.
+    /// # Arguments
+    /// * `nickname` - The nickname of the client that receives the response, `*` if it is not registered yet.
+    pub fn to_numeric(&self, nickname: &str) -> Vec<String> {
+        match self {
+            Response::CommandResponse { response } => encode_command_response(response, nickname),
+            Response::ErrorResponse { response } => encode_error_response(response, nickname),
+            Response::MessageResponse { response } => encode_message_response(response, nickname),
+            Response::DccResponse { response } => {
+                vec![format!("NOTICE {} :{}", nickname, response)]
+            }
+        }
+    }
+}
+
+/// Encodes a command response as numeric replies.
+fn encode_command_response(response: &CommandResponse, nickname: &str) -> Vec<String> {
+    let line = match response {
+        CommandResponse::Welcome {
+            nickname: _,
+            username,
+            hostname,
+        } => format!(
+            "001 {} :Welcome to the Internet Rust Network {}!{}@{}",
+            nickname, nickname, username, hostname
+        ),
+        CommandResponse::YourHost {
+            servername,
+            version,
+        } => format!(
+            "002 {} :Your host is {}, running version {}",
+            nickname, servername, version
+        ),
+        CommandResponse::Created { date } => {
+            format!("003 {} :This server was created {}", nickname, date)
+        }
+        CommandResponse::MyInfo {
+            servername,
+            version,
+            user_modes,
+            channel_modes,
+        } => format!(
+            "004 {} {} {} {} {}",
+            nickname, servername, version, user_modes, channel_modes
+        ),
+        CommandResponse::ISupport { tokens } => format!(
+            "005 {} {} :are supported by this server",
+            nickname,
+            tokens.join(" ")
+        ),
+        CommandResponse::ConnectionSuccees => {
+            format!("NOTICE {} :Connection successful", nickname)
+        }
+        CommandResponse::UserMode { user: _, modes } => {
+            let mut flags = "+".to_string();
+            for flag in UserFlag::iter() {
+                if modes.get(&flag.to_string()) == Some(&"+".to_string()) {
+                    flags.push_str(&UserFlag::to_string(&flag));
+                }
+            }
+            format!("221 {} {}", nickname, flags)
+        }
+        CommandResponse::Away {
+            nickname: away_nickname,
+            message,
+        } => format!("301 {} {} :{}", nickname, away_nickname, message),
+        CommandResponse::UnAway => {
+            format!("305 {} :You are no longer marked as being away", nickname)
+        }
+        CommandResponse::NowAway => {
+            format!("306 {} :You have been marked as being away", nickname)
+        }
+        CommandResponse::WhoIsUser {
+            nickname: whois_nickname,
+            username,
+            hostname,
+            servername: _,
+            realname,
+        } => format!(
+            "311 {} {} {} {} * :{}",
+            nickname, whois_nickname, username, hostname, realname
+        ),
+        CommandResponse::WhoIsServer {
+            nickname: whois_nickname,
+            servername,
+            serverinfo,
+        } => format!(
+            "312 {} {} {} :{}",
+            nickname, whois_nickname, servername, serverinfo
+        ),
+        CommandResponse::EndOfWho => format!("315 {} * :End of /WHO list", nickname),
+        CommandResponse::EndOfWhoIs => format!("318 {} * :End of /WHOIS list", nickname),
+        CommandResponse::WhoIsChannels {
+            nickname: whois_nickname,
+            channels,
+        } => {
+            let channels = channels
+                .iter()
+                .map(|(channel, flags)| format!("{}{}", flags, channel))
+                .collect::<Vec<_>>();
+            format!(
+                "319 {} {} :{}",
+                nickname,
+                whois_nickname,
+                channels.join(" ")
+            )
+        }
+        CommandResponse::WhoIsIdle {
+            nickname: whois_nickname,
+            idle,
+            signon,
+        } => format!(
+            "317 {} {} {} {} :seconds idle, signon time",
+            nickname, whois_nickname, idle, signon
+        ),
+        CommandResponse::StatsLinkInfo {
+            nickname: client,
+            idle,
+            signon,
+        } => format!(
+            "211 {} {} :idle {} signon {}",
+            nickname, client, idle, signon
+        ),
+        CommandResponse::StatsConnections { ip, connections } => {
+            format!("249 {} :{} has {} connections", nickname, ip, connections)
+        }
+        CommandResponse::StatsServerLink {
+            server,
+            hopcount,
+            latency,
+        } => match latency {
+            Some(latency) => format!(
+                "211 {} {} :hopcount {} latency {}ms",
+                nickname, server, hopcount, latency
+            ),
+            None => format!(
+                "211 {} {} :hopcount {} latency unknown",
+                nickname, server, hopcount
+            ),
+        },
+        CommandResponse::StatsUptime { seconds } => format!(
+            "242 {} :Server Up {} days {}:{:02}:{:02}",
+            nickname,
+            seconds / 86400,
+            (seconds % 86400) / 3600,
+            (seconds % 3600) / 60,
+            seconds % 60
+        ),
+        CommandResponse::StatsCommandLatency {
+            command,
+            count,
+            mean,
+            p95,
+            max,
+        } => format!(
+            "212 {} {} {} :mean {}us p95 {}us max {}us",
+            nickname, command, count, mean, p95, max
+        ),
+        CommandResponse::StatsMemory {
+            clients,
+            client_bytes,
+            channels,
+            channel_bytes,
+        } => format!(
+            "249 {} :{} clients use {} bytes, {} channels use {} bytes",
+            nickname, clients, client_bytes, channels, channel_bytes
+        ),
+        CommandResponse::StatsGarbage {
+            passes,
+            channels_removed,
+            clients_archived,
+            last_pass,
+        } => format!(
+            "249 {} :{} collections removed {} channels and archived {} clients, the last one at {}",
+            nickname, passes, channels_removed, clients_archived, last_pass
+        ),
+        CommandResponse::EndOfStats { query } => {
+            format!("219 {} {} :End of /STATS report", nickname, query)
+        }
+        CommandResponse::ListStart => format!("321 {} Channel :Users  Name", nickname),
+        CommandResponse::List { channel, topic } => {
+            format!("322 {} {} 0 :{}", nickname, channel, topic)
+        }
+        CommandResponse::ListEnd => format!("323 {} :End of /LIST", nickname),
+        CommandResponse::ChannelMode {
+            channel,
+            mode_string,
+            modes: _,
+        } => format!("324 {} {} {}", nickname, channel, mode_string),
+        CommandResponse::NoTopic { channel } => {
+            format!("331 {} {} :No topic is set", nickname, channel)
+        }
+        CommandResponse::Topic { channel, topic } => {
+            format!("332 {} {} :{}", nickname, channel, topic)
+        }
+        CommandResponse::Inviting {
+            channel,
+            nickname: invited,
+        } => format!("341 {} {} {}", nickname, channel, invited),
+        CommandResponse::WhoReply { users } => {
+            return users
+                .iter()
+                .map(|user| format!("352 {} * {} * * {} H :0 {}", nickname, user, user, user))
+                .collect();
+        }
+        CommandResponse::Names { channel, names } => {
+            format!("353 {} = {} :{}", nickname, channel, names.join(" "))
+        }
+        CommandResponse::EndNames => format!("366 {} * :End of /NAMES list", nickname),
+        CommandResponse::BanList { channel, ban_list } => {
+            return ban_list
+                .iter()
+                .map(|ban| {
+                    format!(
+                        "367 {} {} {} {} {}",
+                        nickname, channel, ban.nickname, ban.set_by, ban.set_at
+                    )
+                })
+                .collect();
+        }
+        CommandResponse::EndBanList => format!("368 {} * :End of channel ban list", nickname),
+        CommandResponse::YouAreOperator => {
+            format!("381 {} :You are now an IRC operator", nickname)
+        }
+        CommandResponse::Rehashing { file } => format!("382 {} {} :Rehashing", nickname, file),
+        CommandResponse::HelpText { topic, text } => {
+            format!("705 {} {} :{}", nickname, topic, text)
+        }
+        CommandResponse::EndOfHelp { topic } => {
+            format!("706 {} {} :End of /HELP", nickname, topic)
+        }
+        CommandResponse::SilenceList { mask } => format!("271 {} {}", nickname, mask),
+        CommandResponse::EndOfSilenceList => {
+            format!("272 {} :End of Silence List", nickname)
+        }
+        CommandResponse::DeliveryReceipt {
+            nickname: receiver,
+            status,
+        } => format!("NOTICE {} :Message to {} {}", nickname, receiver, status),
+        CommandResponse::Preference { name, value } => {
+            format!("NOTICE {} :{} = {}", nickname, name, value)
+        }
+        CommandResponse::EndOfPreferences => {
+            format!("NOTICE {} :End of preferences", nickname)
+        }
+        CommandResponse::FilterList {
+            channel,
+            pattern,
+            action,
+        } => format!(
+            "NOTICE {} :{} filter {} ({})",
+            nickname, channel, pattern, action
+        ),
+        CommandResponse::EndOfFilterList { channel } => {
+            format!("NOTICE {} :End of {} filter list", nickname, channel)
+        }
+        CommandResponse::AuditEntry {
+            time,
+            action,
+            actor,
+            target,
+            parameters,
+        } => format!(
+            "NOTICE {} :[{}] {} by {} on {} {}",
+            nickname, time, action, actor, target, parameters
+        ),
+        CommandResponse::EndOfAudit { page, pages } => {
+            format!(
+                "NOTICE {} :End of audit log, page {} of {}",
+                nickname, page, pages
+            )
+        }
+        CommandResponse::ChannelStats {
+            channel,
+            created,
+            messages,
+            last_hour,
+            last_day,
+        } => format!(
+            "NOTICE {} :{} created at {}, {} messages, {} in the last hour, {} in the last day",
+            nickname, channel, created, messages, last_hour, last_day
+        ),
+        CommandResponse::ChannelTalker {
+            channel,
+            nickname: talker,
+            messages,
+        } => format!(
+            "NOTICE {} :{} talker {} ({} messages)",
+            nickname, channel, talker, messages
+        ),
+        CommandResponse::EndOfChannelStats { channel } => {
+            format!("NOTICE {} :End of {} statistics", nickname, channel)
+        }
+        CommandResponse::Reconnecting { attempt, delay } => format!(
+            "NOTICE {} :Reconnecting to the server in {} seconds (attempt {})",
+            nickname, delay, attempt
+        ),
+        CommandResponse::Reconnected => {
+            format!("NOTICE {} :Reconnected to the server", nickname)
+        }
+        CommandResponse::Pong { server, token } => format!("PONG {} :{}", server, token),
+        CommandResponse::ConnectionStatus { state, lag } => format!(
+            "NOTICE {} :The connection is {}, with a lag of {} ms",
+            nickname,
+            state.as_str(),
+            lag
+        ),
+        CommandResponse::Server { servers: _ } => return vec![],
+        CommandResponse::Ghost { nickname: ghost } => format!(
+            "NOTICE {} :Ghost with nickname {} has been disconnected",
+            nickname, ghost
+        ),
+    };
+    vec![line]
+}
+
+/// Encodes an error response as numeric replies.
+/// The errors of this server already follow the RFC, so the target of the reply is added after the numeric.
+fn encode_error_response(response: &ErrorResponse, nickname: &str) -> Vec<String> {
+    match response {
+        ErrorResponse::ErrorWhileConnecting => vec![],
+        ErrorResponse::NotRegistered => {
+            vec![format!("451 {} :You have not registered", nickname)]
+        }
+        ErrorResponse::ClientDisconnected {
+            nickname: disconnected,
+        } => vec![format!(
+            "401 {} {} :No such nick/channel",
+            nickname, disconnected
+        )],
+        _ => {
+            let response = response.to_string();
+            match response.split_once(' ') {
+                Some((numeric, params)) => vec![format!("{} {} {}", numeric, nickname, params)],
+                None => vec![format!("{} {}", response, nickname)],
+            }
+        }
+    }
+}
+
+/// Encodes a message response as the PRIVMSG and NOTICE messages of the RFC.
+fn encode_message_response(response: &MessageResponse, nickname: &str) -> Vec<String> {
+    let line = match response {
+        MessageResponse::UserPrivMsg { sender, message } => {
+            format!(":{} PRIVMSG {} :{}", sender, nickname, message)
+        }
+        MessageResponse::ChannelPrivMsg {
+            channel,
+            sender,
+            message,
+        } => format!(":{} PRIVMSG {} :{}", sender, channel, message),
+        MessageResponse::KickMsg { message } => format!("NOTICE {} :{}", nickname, message),
+        MessageResponse::InviteMsg { message } => format!("NOTICE {} :{}", nickname, message),
+        MessageResponse::ChannelModeMsg {
+            channel,
+            sender,
+            modes,
+        } => format!(":{} MODE {} {}", sender, channel, modes),
+        MessageResponse::VoiceMsg { message } => format!("NOTICE {} :{}", nickname, message),
+        MessageResponse::UserNotice { sender, message } => {
+            format!(":{} NOTICE {} :{}", sender, nickname, message)
+        }
+        MessageResponse::ChannelNotice {
+            channel,
+            sender,
+            message,
+        } => format!(":{} NOTICE {} :{}", sender, channel, message),
+        MessageResponse::JoinMsg { channel, nickname } => {
+            format!(":{} JOIN {}", nickname, channel)
+        }
+        MessageResponse::PartMsg { channel, nickname } => {
+            format!(":{} PART {}", nickname, channel)
+        }
+        MessageResponse::QuitMsg { nickname, message } => {
+            format!(":{} QUIT :{}", nickname, message)
+        }
+    };
+    vec![line]
+}
+
+/// Encodes a message sent by this server as RFC 1459 lines.
+/// Responses are encoded as numeric replies, while the rest of the messages, like the ones
+/// relayed from other users, are already valid irc messages and are returned as they are.
+/// # Arguments
+/// * `message` - The message to encode.
+/// * `nickname` - The nickname of the client that receives the message, `*` if it is not registered yet.
+pub fn encode_numeric(message: &str, nickname: &str) -> Vec<String> {
+    ResponseWriter::new(nickname, WireFormat::Numeric).message_lines(message)
+}
@@ -1,6 +1,11 @@
+use crate::crypto::FileCipher;
+
 #[derive(Debug, Clone)]
 pub struct OngoingTransfer {
     pub file_offset: u64,
     pub file_size: f64,
     pub file_path: String,
+    pub cipher: Option<FileCipher>,
+    pub checksum: Option<String>,
+    pub save_path: String,
 }
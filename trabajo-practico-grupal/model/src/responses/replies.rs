@@ -1,9 +1,56 @@
+use crate::ban::Ban;
+use crate::consts::{MAX_MSG_SIZE, OPERATOR_FLAG, VOICE_FLAG};
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::fmt::Write as _;
 
+/// The states of the connection of the client with the server, measured by the lag of its PINGs.
+/// # Variants
+/// * `Connected`: The server answers the PINGs in time.
+/// * `Lagging`: The server takes too long to answer the PINGs.
+/// * `Reconnecting`: The connection was lost and the client is connecting again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    Connected,
+    Lagging,
+    Reconnecting,
+}
+
+impl ConnectionState {
+    /// Returns the name of the state sent to the interface
+    pub fn as_str(&self) -> &str {
+        match self {
+            ConnectionState::Connected => "connected",
+            ConnectionState::Lagging => "lagging",
+            ConnectionState::Reconnecting => "reconnecting",
+        }
+    }
+
+    /// Returns the state with the name, or None if there is no state with it
+    pub fn from_name(name: &str) -> Option<ConnectionState> {
+        match name {
+            "connected" => Some(ConnectionState::Connected),
+            "lagging" => Some(ConnectionState::Lagging),
+            "reconnecting" => Some(ConnectionState::Reconnecting),
+            _ => None,
+        }
+    }
+}
+
 /// Enum that represents the different types of responses that the server can send according to the irc protocol.
-#[derive(Debug)]
+/// The codes from 002 to 005 are used by the messages of our client, so the rest of the welcome
+/// replies use the codes from 006 to 009, and they are sent as 002 to 005 to standard irc clients.
+/// In the same way, the codes from 200 to 215, 218, 220 and 222 to 230 are used by the dcc responses, so the information
+/// of the clients in STATS uses the code 216 instead of 211, the connections of each IP use the code 217
+/// and the servers of the network, with the latency of their links, use the code 221. The processing
+/// time of each command, in microseconds, uses the code 248 instead of 212. The memory held by the
+/// clients and channels and the totals of the garbage collection use the codes 245 and 246.
+/// The PONG that answers the PING of a client uses the code 020, and is sent as a PONG to standard irc
+/// clients. The state of the connection and its lag, in milliseconds, use the code 021, which our client
+/// only sends to its interface.
+/// The delivery receipts of the private messages use the code 707, which is not part of the protocol,
+/// so they are sent as notices to standard irc clients. Their status is `delivered`, `away` or `offline`.
+#[derive(Debug, Clone)]
 pub enum CommandResponse {
     ConnectionSuccees,
     Topic {
@@ -15,11 +62,12 @@ pub enum CommandResponse {
     },
     ChannelMode {
         channel: String,
+        mode_string: String,
         modes: HashMap<String, String>,
     },
     BanList {
         channel: String,
-        ban_list: Vec<String>,
+        ban_list: Vec<Ban>,
     },
     EndBanList,
     UserMode {
@@ -53,6 +101,22 @@ pub enum CommandResponse {
         username: String,
         hostname: String,
     },
+    YourHost {
+        servername: String,
+        version: String,
+    },
+    Created {
+        date: String,
+    },
+    MyInfo {
+        servername: String,
+        version: String,
+        user_modes: String,
+        channel_modes: String,
+    },
+    ISupport {
+        tokens: Vec<String>,
+    },
     WhoIsUser {
         nickname: String,
         username: String,
@@ -69,7 +133,54 @@ pub enum CommandResponse {
         nickname: String,
         channels: HashMap<String, String>,
     },
+    WhoIsIdle {
+        nickname: String,
+        idle: u64,
+        signon: u64,
+    },
+    StatsLinkInfo {
+        nickname: String,
+        idle: u64,
+        signon: u64,
+    },
+    StatsConnections {
+        ip: String,
+        connections: usize,
+    },
+    StatsServerLink {
+        server: String,
+        hopcount: u8,
+        latency: Option<u64>,
+    },
+    StatsUptime {
+        seconds: u64,
+    },
+    StatsCommandLatency {
+        command: String,
+        count: u64,
+        mean: u64,
+        p95: u64,
+        max: u64,
+    },
+    StatsMemory {
+        clients: usize,
+        client_bytes: usize,
+        channels: usize,
+        channel_bytes: usize,
+    },
+    StatsGarbage {
+        passes: u64,
+        channels_removed: u64,
+        clients_archived: u64,
+        last_pass: u64,
+    },
+    EndOfStats {
+        query: String,
+    },
     YouAreOperator,
+    Rehashing {
+        file: String,
+    },
     WhoReply {
         users: Vec<String>,
     },
@@ -78,6 +189,76 @@ pub enum CommandResponse {
     Server {
         servers: HashMap<String, u8>,
     },
+    Ghost {
+        nickname: String,
+    },
+    HelpText {
+        topic: String,
+        text: String,
+    },
+    EndOfHelp {
+        topic: String,
+    },
+    SilenceList {
+        mask: String,
+    },
+    EndOfSilenceList,
+    DeliveryReceipt {
+        nickname: String,
+        status: String,
+    },
+    Preference {
+        name: String,
+        value: String,
+    },
+    EndOfPreferences,
+    FilterList {
+        channel: String,
+        pattern: String,
+        action: String,
+    },
+    EndOfFilterList {
+        channel: String,
+    },
+    AuditEntry {
+        time: u64,
+        action: String,
+        actor: String,
+        target: String,
+        parameters: String,
+    },
+    EndOfAudit {
+        page: usize,
+        pages: usize,
+    },
+    ChannelStats {
+        channel: String,
+        created: u64,
+        messages: u64,
+        last_hour: u64,
+        last_day: u64,
+    },
+    ChannelTalker {
+        channel: String,
+        nickname: String,
+        messages: u64,
+    },
+    EndOfChannelStats {
+        channel: String,
+    },
+    Reconnecting {
+        attempt: u32,
+        delay: u64,
+    },
+    Reconnected,
+    Pong {
+        server: String,
+        token: String,
+    },
+    ConnectionStatus {
+        state: ConnectionState,
+        lag: u64,
+    },
 }
 
 impl Display for CommandResponse {
@@ -92,6 +273,28 @@ impl Display for CommandResponse {
                 "001 {} {} {} :Welcome to the Internet Rust Network {}",
                 nickname, username, hostname, nickname
             ),
+            CommandResponse::YourHost {
+                servername,
+                version,
+            } => format!(
+                "006 {} {} :Your host is {}, running version {}",
+                servername, version, servername, version
+            ),
+            CommandResponse::Created { date } => {
+                format!("007 :This server was created {}", date)
+            }
+            CommandResponse::MyInfo {
+                servername,
+                version,
+                user_modes,
+                channel_modes,
+            } => format!(
+                "008 {} {} {} {}",
+                servername, version, user_modes, channel_modes
+            ),
+            CommandResponse::ISupport { tokens } => {
+                format!("009 {} :are supported by this server", tokens.join(" "))
+            }
             CommandResponse::ConnectionSuccees => "000 Connection successful".to_string(),
             CommandResponse::Away { nickname, message } => format!("301 {} :{}", nickname, message),
             CommandResponse::UnAway => "305 :You are no longer marked as being away".to_string(),
@@ -118,15 +321,78 @@ impl Display for CommandResponse {
                 }
                 format!("319 {} {}", nickname, channels_str)
             }
+            CommandResponse::WhoIsIdle {
+                nickname,
+                idle,
+                signon,
+            } => format!(
+                "317 {} {} {} :seconds idle, signon time",
+                nickname, idle, signon
+            ),
+            CommandResponse::StatsLinkInfo {
+                nickname,
+                idle,
+                signon,
+            } => format!("216 {} {} {}", nickname, idle, signon),
+            CommandResponse::StatsConnections { ip, connections } => {
+                format!("217 {} {}", ip, connections)
+            }
+            CommandResponse::StatsServerLink {
+                server,
+                hopcount,
+                latency,
+            } => match latency {
+                Some(latency) => format!("221 {} {} {}", server, hopcount, latency),
+                None => format!("221 {} {} *", server, hopcount),
+            },
+            CommandResponse::StatsUptime { seconds } => format!(
+                "242 :Server Up {} days {}:{:02}:{:02}",
+                seconds / 86400,
+                (seconds % 86400) / 3600,
+                (seconds % 3600) / 60,
+                seconds % 60
+            ),
+            CommandResponse::StatsCommandLatency {
+                command,
+                count,
+                mean,
+                p95,
+                max,
+            } => format!("248 {} {} {} {} {}", command, count, mean, p95, max),
+            CommandResponse::StatsMemory {
+                clients,
+                client_bytes,
+                channels,
+                channel_bytes,
+            } => format!(
+                "245 {} {} {} {}",
+                clients, client_bytes, channels, channel_bytes
+            ),
+            CommandResponse::StatsGarbage {
+                passes,
+                channels_removed,
+                clients_archived,
+                last_pass,
+            } => format!(
+                "246 {} {} {} {}",
+                passes, channels_removed, clients_archived, last_pass
+            ),
+            CommandResponse::EndOfStats { query } => {
+                format!("219 {} :End of /STATS report", query)
+            }
             CommandResponse::ListStart => "321 Channel :Users Name".to_string(),
             CommandResponse::List { channel, topic } => format!("322 {} {}", channel, topic),
             CommandResponse::ListEnd => "323 :End of /LIST".to_string(),
-            CommandResponse::ChannelMode { channel, modes } => {
+            CommandResponse::ChannelMode {
+                channel,
+                mode_string,
+                modes,
+            } => {
                 let mut modes_str = String::new();
                 for (key, value) in modes {
                     let _ = write!(modes_str, " {};{}", key, value);
                 }
-                format!("324 {}{}", channel, modes_str)
+                format!("324 {} {}{}", channel, mode_string, modes_str)
             }
             CommandResponse::NoTopic { channel } => format!("331 {} :No topic is set", channel),
             CommandResponse::Topic { channel, topic } => format!("332 {} {}", channel, topic),
@@ -143,8 +409,8 @@ impl Display for CommandResponse {
             CommandResponse::EndNames => "366 :End of /NAMES list.".to_string(),
             CommandResponse::BanList { channel, ban_list } => {
                 let mut ban_list_str = String::new();
-                for user in ban_list {
-                    let _ = write!(ban_list_str, "{} ", user);
+                for ban in ban_list {
+                    let _ = write!(ban_list_str, "{} ", ban);
                 }
                 format!("367 {} :{}", channel, ban_list_str)
             }
@@ -164,6 +430,69 @@ impl Display for CommandResponse {
                 format!("352 {}", users_str.join(" "))
             }
             CommandResponse::YouAreOperator => "381 :You are now an IRC operator".to_string(),
+            CommandResponse::Rehashing { file } => format!("382 {} :Rehashing", file),
+            CommandResponse::HelpText { topic, text } => format!("705 {} :{}", topic, text),
+            CommandResponse::EndOfHelp { topic } => format!("706 {} :End of /HELP", topic),
+            CommandResponse::SilenceList { mask } => format!("271 {}", mask),
+            CommandResponse::EndOfSilenceList => "272 :End of /SILENCE list".to_string(),
+            CommandResponse::DeliveryReceipt { nickname, status } => {
+                format!("707 {} {} :Message {}", nickname, status, status)
+            }
+            CommandResponse::Preference { name, value } => format!("708 {} :{}", name, value),
+            CommandResponse::EndOfPreferences => "709 :End of /PREFS list".to_string(),
+            CommandResponse::FilterList {
+                channel,
+                pattern,
+                action,
+            } => format!("710 {} {} {}", channel, pattern, action),
+            CommandResponse::EndOfFilterList { channel } => {
+                format!("711 {} :End of /FILTER list", channel)
+            }
+            CommandResponse::AuditEntry {
+                time,
+                action,
+                actor,
+                target,
+                parameters,
+            } => format!(
+                "712 {} {} {} {} :{}",
+                time, action, actor, target, parameters
+            ),
+            CommandResponse::EndOfAudit { page, pages } => {
+                format!("713 {} {} :End of /AUDIT", page, pages)
+            }
+            CommandResponse::ChannelStats {
+                channel,
+                created,
+                messages,
+                last_hour,
+                last_day,
+            } => format!(
+                "714 {} {} {} {} {}",
+                channel, created, messages, last_hour, last_day
+            ),
+            CommandResponse::ChannelTalker {
+                channel,
+                nickname,
+                messages,
+            } => format!("715 {} {} {}", channel, nickname, messages),
+            CommandResponse::EndOfChannelStats { channel } => {
+                format!("716 {} :End of /CHANSTATS", channel)
+            }
+            CommandResponse::Reconnecting { attempt, delay } => {
+                format!("013 {} {} :Reconnecting to the server", attempt, delay)
+            }
+            CommandResponse::Reconnected => "014 :Reconnected to the server".to_string(),
+            CommandResponse::Pong { server, token } => format!("020 {} {}", server, token),
+            CommandResponse::ConnectionStatus { state, lag } => {
+                format!("021 {} {} :Lag of the connection", state.as_str(), lag)
+            }
+            CommandResponse::Ghost { nickname } => {
+                format!(
+                    "010 {} :Ghost with your nickname has been disconnected",
+                    nickname
+                )
+            }
             CommandResponse::EndOfWho => "315 :End of /WHO list".to_string(),
             CommandResponse::EndOfWhoIs => "318 :End of /WHOIS list".to_string(),
             CommandResponse::Server { servers } => {
@@ -187,48 +516,74 @@ impl CommandResponse {
             .split_whitespace()
             .map(|x| x.to_string())
             .collect::<Vec<_>>();
-        let command = msg[0].as_str();
+        let command = msg.first()?.as_str();
         match command {
             "000" => Some(CommandResponse::ConnectionSuccees),
             "001" => Some(CommandResponse::Welcome {
-                nickname: msg[1].to_owned(),
-                username: msg[2].to_owned(),
-                hostname: msg[3].to_owned(),
+                nickname: msg.get(1)?.to_owned(),
+                username: msg.get(2)?.to_owned(),
+                hostname: msg.get(3)?.to_owned(),
+            }),
+            "006" => Some(CommandResponse::YourHost {
+                servername: msg.get(1)?.to_owned(),
+                version: msg.get(2)?.to_owned(),
+            }),
+            "007" => {
+                let date = msg.get(1..)?.to_owned().join(" ");
+                let date = match date.strip_prefix(":This server was created ") {
+                    Some(d) => d.to_owned(),
+                    None => date,
+                };
+                Some(CommandResponse::Created { date })
+            }
+            "008" => Some(CommandResponse::MyInfo {
+                servername: msg.get(1)?.to_owned(),
+                version: msg.get(2)?.to_owned(),
+                user_modes: msg.get(3)?.to_owned(),
+                channel_modes: msg.get(4)?.to_owned(),
+            }),
+            "009" => Some(CommandResponse::ISupport {
+                tokens: msg
+                    .get(1..)?
+                    .iter()
+                    .take_while(|x| !x.starts_with(':'))
+                    .map(|x| x.to_owned())
+                    .collect(),
             }),
             "301" => {
-                msg[2] = match msg[2].strip_prefix(':') {
+                *msg.get_mut(2)? = match msg.get(2)?.strip_prefix(':') {
                     Some(p) => p.to_owned(),
                     None => "".to_owned(),
                 };
                 Some(CommandResponse::Away {
-                    nickname: msg[1].to_owned(),
-                    message: msg[2..].to_owned().join(" "),
+                    nickname: msg.get(1)?.to_owned(),
+                    message: msg.get(2..)?.to_owned().join(" "),
                 })
             }
             "305" => Some(CommandResponse::UnAway),
             "306" => Some(CommandResponse::NowAway),
             "311" => Some(CommandResponse::WhoIsUser {
-                nickname: msg[1].to_owned(),
-                username: msg[2].to_owned(),
-                hostname: msg[3].to_owned(),
-                servername: msg[4].to_owned(),
-                realname: msg[5].to_owned(),
+                nickname: msg.get(1)?.to_owned(),
+                username: msg.get(2)?.to_owned(),
+                hostname: msg.get(3)?.to_owned(),
+                servername: msg.get(4)?.to_owned(),
+                realname: msg.get(5)?.to_owned(),
             }),
             "312" => Some(CommandResponse::WhoIsServer {
-                nickname: msg[1].to_owned(),
-                servername: msg[2].to_owned(),
-                serverinfo: msg[3].to_owned(),
+                nickname: msg.get(1)?.to_owned(),
+                servername: msg.get(2)?.to_owned(),
+                serverinfo: msg.get(3)?.to_owned(),
             }),
             "315" => Some(CommandResponse::EndOfWho),
             "318" => Some(CommandResponse::EndOfWhoIs),
             "319" => {
-                let nickname = msg[1].to_owned();
+                let nickname = msg.get(1)?.to_owned();
                 let mut channels = HashMap::new();
-                for channel in msg[2..].iter() {
-                    let channel = channel.strip_prefix(':').unwrap();
+                for channel in msg.get(2..)?.iter() {
+                    let channel = channel.strip_prefix(':').unwrap_or(channel);
                     let flags = channel
                         .chars()
-                        .take_while(|x| (x == &'@' || x == &'&'))
+                        .take_while(|x| x == &OPERATOR_FLAG || x == &VOICE_FLAG)
                         .collect::<String>();
                     let channel = match channel.strip_prefix(&flags) {
                         Some(p) => p.to_owned(),
@@ -238,17 +593,79 @@ impl CommandResponse {
                 }
                 Some(CommandResponse::WhoIsChannels { nickname, channels })
             }
+            "317" => Some(CommandResponse::WhoIsIdle {
+                nickname: msg.get(1)?.to_owned(),
+                idle: msg.get(2)?.parse::<u64>().unwrap_or(0),
+                signon: msg.get(3)?.parse::<u64>().unwrap_or(0),
+            }),
+            "216" => Some(CommandResponse::StatsLinkInfo {
+                nickname: msg.get(1)?.to_owned(),
+                idle: msg.get(2)?.parse::<u64>().unwrap_or(0),
+                signon: msg.get(3)?.parse::<u64>().unwrap_or(0),
+            }),
+            "217" => Some(CommandResponse::StatsConnections {
+                ip: msg.get(1)?.to_owned(),
+                connections: msg.get(2)?.parse::<usize>().unwrap_or(0),
+            }),
+            "221" => Some(CommandResponse::StatsServerLink {
+                server: msg.get(1)?.to_owned(),
+                hopcount: msg.get(2)?.parse::<u8>().unwrap_or(0),
+                latency: msg.get(3)?.parse::<u64>().ok(),
+            }),
+            "242" => {
+                let mut seconds = 0;
+                if msg.len() > 5 {
+                    let days = msg.get(3)?.parse::<u64>().unwrap_or(0);
+                    let time = msg
+                        .get(5)?
+                        .split(':')
+                        .map(|x| x.parse::<u64>().unwrap_or(0))
+                        .collect::<Vec<_>>();
+                    seconds = days * 86400;
+                    for (value, unit) in time.iter().zip([3600, 60, 1]) {
+                        seconds += value * unit;
+                    }
+                }
+                Some(CommandResponse::StatsUptime { seconds })
+            }
+            "248" => Some(CommandResponse::StatsCommandLatency {
+                command: msg.get(1)?.to_owned(),
+                count: msg.get(2)?.parse::<u64>().unwrap_or(0),
+                mean: msg.get(3)?.parse::<u64>().unwrap_or(0),
+                p95: msg.get(4)?.parse::<u64>().unwrap_or(0),
+                max: msg.get(5)?.parse::<u64>().unwrap_or(0),
+            }),
+            "245" => Some(CommandResponse::StatsMemory {
+                clients: msg.get(1)?.parse::<usize>().unwrap_or(0),
+                client_bytes: msg.get(2)?.parse::<usize>().unwrap_or(0),
+                channels: msg.get(3)?.parse::<usize>().unwrap_or(0),
+                channel_bytes: msg.get(4)?.parse::<usize>().unwrap_or(0),
+            }),
+            "246" => Some(CommandResponse::StatsGarbage {
+                passes: msg.get(1)?.parse::<u64>().unwrap_or(0),
+                channels_removed: msg.get(2)?.parse::<u64>().unwrap_or(0),
+                clients_archived: msg.get(3)?.parse::<u64>().unwrap_or(0),
+                last_pass: msg.get(4)?.parse::<u64>().unwrap_or(0),
+            }),
+            "219" => Some(CommandResponse::EndOfStats {
+                query: msg.get(1)?.to_owned(),
+            }),
             "321" => Some(CommandResponse::ListStart),
             "322" => Some(CommandResponse::List {
-                channel: msg[1].to_owned(),
-                topic: msg[2..].to_owned().join(" "),
+                channel: msg.get(1)?.to_owned(),
+                topic: msg.get(2..)?.to_owned().join(" "),
             }),
 
             "323" => Some(CommandResponse::ListEnd),
             "324" => {
-                let channel = msg[1].to_owned();
+                let channel = msg.get(1)?.to_owned();
+                let mut mode_string = vec![];
                 let mut modes = HashMap::new();
-                for mode in msg[2..].iter() {
+                for mode in msg.get(2..)?.iter() {
+                    if !mode.contains(';') {
+                        mode_string.push(mode.to_owned());
+                        continue;
+                    }
                     let mut mode = mode.split(';');
                     let key = match mode.next() {
                         Some(x) => x.to_owned(),
@@ -264,49 +681,57 @@ impl CommandResponse {
                     };
                     modes.insert(key, value);
                 }
-                Some(CommandResponse::ChannelMode { channel, modes })
+                Some(CommandResponse::ChannelMode {
+                    channel,
+                    mode_string: mode_string.join(" "),
+                    modes,
+                })
             }
             "332" => {
                 let mut topic = "".to_string();
                 if msg.len() > 2 {
-                    msg[2] = match msg[2].strip_prefix(':') {
+                    *msg.get_mut(2)? = match msg.get(2)?.strip_prefix(':') {
                         Some(p) => p.to_owned(),
                         None => "".to_owned(),
                     };
-                    topic = msg[2..].to_owned().join(" ");
+                    topic = msg.get(2..)?.to_owned().join(" ");
                 }
                 Some(CommandResponse::Topic {
-                    channel: msg[1].to_owned(),
+                    channel: msg.get(1)?.to_owned(),
                     topic,
                 })
             }
             "341" => Some(CommandResponse::Inviting {
-                channel: msg[1].to_owned(),
-                nickname: msg[2].to_owned(),
+                channel: msg.get(1)?.to_owned(),
+                nickname: msg.get(2)?.to_owned(),
             }),
             "352" => Some(CommandResponse::WhoReply {
-                users: msg[1..].to_owned().to_vec(),
+                users: msg.get(1..)?.to_owned().to_vec(),
             }),
             "353" => Some(CommandResponse::Names {
-                channel: msg[1].to_owned(),
-                names: msg[2..].to_owned().to_vec(),
+                channel: msg.get(1)?.to_owned(),
+                names: msg.get(2..)?.to_owned().to_vec(),
             }),
             "366" => Some(CommandResponse::EndNames),
             "367" => {
-                msg[2] = match msg[2].strip_prefix(':') {
+                *msg.get_mut(2)? = match msg.get(2)?.strip_prefix(':') {
                     Some(p) => p.to_owned(),
                     None => "".to_owned(),
                 };
                 Some(CommandResponse::BanList {
-                    channel: msg[1].to_owned(),
-                    ban_list: msg[2..].to_owned().to_vec(),
+                    channel: msg.get(1)?.to_owned(),
+                    ban_list: msg
+                        .get(2..)?
+                        .iter()
+                        .filter_map(|b| Ban::serialize(b))
+                        .collect(),
                 })
             }
             "368" => Some(CommandResponse::EndBanList),
             "370" => {
                 let mut servers = HashMap::new();
-                let servers_str = msg[1]
-                    .to_owned()
+                let servers_str = msg
+                    .get(1)?
                     .split(';')
                     .map(|x| x.to_owned())
                     .collect::<Vec<_>>();
@@ -325,7 +750,250 @@ impl CommandResponse {
                 Some(CommandResponse::Server { servers })
             }
             "381" => Some(CommandResponse::YouAreOperator),
+            "382" => Some(CommandResponse::Rehashing {
+                file: msg.get(1)?.to_owned(),
+            }),
+            "705" => {
+                *msg.get_mut(2)? = match msg.get(2)?.strip_prefix(':') {
+                    Some(p) => p.to_owned(),
+                    None => "".to_owned(),
+                };
+                Some(CommandResponse::HelpText {
+                    topic: msg.get(1)?.to_owned(),
+                    text: msg.get(2..)?.to_owned().join(" "),
+                })
+            }
+            "706" => Some(CommandResponse::EndOfHelp {
+                topic: msg.get(1)?.to_owned(),
+            }),
+            "271" => Some(CommandResponse::SilenceList {
+                mask: msg.get(1)?.to_owned(),
+            }),
+            "272" => Some(CommandResponse::EndOfSilenceList),
+            "707" => Some(CommandResponse::DeliveryReceipt {
+                nickname: msg.get(1)?.to_owned(),
+                status: msg.get(2)?.to_owned(),
+            }),
+            "708" => {
+                *msg.get_mut(2)? = match msg.get(2)?.strip_prefix(':') {
+                    Some(p) => p.to_owned(),
+                    None => "".to_owned(),
+                };
+                Some(CommandResponse::Preference {
+                    name: msg.get(1)?.to_owned(),
+                    value: msg.get(2..)?.to_owned().join(" "),
+                })
+            }
+            "709" => Some(CommandResponse::EndOfPreferences),
+            "710" => Some(CommandResponse::FilterList {
+                channel: msg.get(1)?.to_owned(),
+                pattern: msg.get(2)?.to_owned(),
+                action: msg.get(3)?.to_owned(),
+            }),
+            "711" => Some(CommandResponse::EndOfFilterList {
+                channel: msg.get(1)?.to_owned(),
+            }),
+            "712" => {
+                *msg.get_mut(5)? = match msg.get(5)?.strip_prefix(':') {
+                    Some(p) => p.to_owned(),
+                    None => "".to_owned(),
+                };
+                Some(CommandResponse::AuditEntry {
+                    time: msg.get(1)?.parse().ok()?,
+                    action: msg.get(2)?.to_owned(),
+                    actor: msg.get(3)?.to_owned(),
+                    target: msg.get(4)?.to_owned(),
+                    parameters: msg.get(5..)?.to_owned().join(" "),
+                })
+            }
+            "713" => Some(CommandResponse::EndOfAudit {
+                page: msg.get(1)?.parse().ok()?,
+                pages: msg.get(2)?.parse().ok()?,
+            }),
+            "714" => Some(CommandResponse::ChannelStats {
+                channel: msg.get(1)?.to_owned(),
+                created: msg.get(2)?.parse().ok()?,
+                messages: msg.get(3)?.parse().ok()?,
+                last_hour: msg.get(4)?.parse().ok()?,
+                last_day: msg.get(5)?.parse().ok()?,
+            }),
+            "715" => Some(CommandResponse::ChannelTalker {
+                channel: msg.get(1)?.to_owned(),
+                nickname: msg.get(2)?.to_owned(),
+                messages: msg.get(3)?.parse().ok()?,
+            }),
+            "716" => Some(CommandResponse::EndOfChannelStats {
+                channel: msg.get(1)?.to_owned(),
+            }),
+            "013" => Some(CommandResponse::Reconnecting {
+                attempt: msg.get(1)?.parse().ok()?,
+                delay: msg.get(2)?.parse().ok()?,
+            }),
+            "014" => Some(CommandResponse::Reconnected),
+            "020" => Some(CommandResponse::Pong {
+                server: msg.get(1)?.to_owned(),
+                token: msg.get(2)?.to_owned(),
+            }),
+            "021" => Some(CommandResponse::ConnectionStatus {
+                state: ConnectionState::from_name(msg.get(1)?)?,
+                lag: msg.get(2)?.parse().ok()?,
+            }),
+            "010" => Some(CommandResponse::Ghost {
+                nickname: msg.get(1)?.to_owned(),
+            }),
             _ => None,
         }
     }
+
+    /// Splits a list reply, like the names of a channel, the users of a WHO or a ban list, in
+    /// replies of the same list that fit in a line of `MAX_MSG_SIZE` bytes.
+    /// Returns none if the reply is not a list or already fits in a line.
+    pub fn split_list(&self) -> Option<Vec<CommandResponse>> {
+        let replies = match self {
+            CommandResponse::Names { channel, names } => {
+                let head = format!("353 {} ", channel).len();
+                chunk_list(names, head, |name| name.len() + 1)
+                    .into_iter()
+                    .map(|names| CommandResponse::Names {
+                        channel: channel.to_owned(),
+                        names,
+                    })
+                    .collect::<Vec<_>>()
+            }
+            CommandResponse::WhoReply { users } => {
+                chunk_list(users, "352".len(), |user| user.len() + 1)
+                    .into_iter()
+                    .map(|users| CommandResponse::WhoReply { users })
+                    .collect()
+            }
+            CommandResponse::BanList { channel, ban_list } => {
+                let head = format!("367 {} :", channel).len();
+                chunk_list(ban_list, head, |ban| ban.to_string().len() + 1)
+                    .into_iter()
+                    .map(|ban_list| CommandResponse::BanList {
+                        channel: channel.to_owned(),
+                        ban_list,
+                    })
+                    .collect()
+            }
+            _ => return None,
+        };
+        if replies.len() > 1 {
+            Some(replies)
+        } else {
+            None
+        }
+    }
+}
+
+/// Splits the items of a list in chunks whose line is at most `MAX_MSG_SIZE` bytes long.
+/// An item longer than a line is left alone in its chunk, and an empty list is a single empty chunk.
+/// # Arguments
+/// * `items` - The items of the list.
+/// * `head` - The bytes of the line before the items.
+/// * `size` - The bytes that an item adds to the line, with its separator.
+fn chunk_list<T: Clone>(items: &[T], head: usize, size: impl Fn(&T) -> usize) -> Vec<Vec<T>> {
+    let mut chunks = vec![];
+    let mut chunk = vec![];
+    let mut line = head;
+    for item in items {
+        let item_size = size(item);
+        if !chunk.is_empty() && line + item_size > MAX_MSG_SIZE {
+            chunks.push(std::mem::take(&mut chunk));
+            line = head;
+        }
+        line += item_size;
+        chunk.push(item.clone());
+    }
+    if chunks.is_empty() || !chunk.is_empty() {
+        chunks.push(chunk);
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod replies_tests {
+    use crate::{ban::Ban, consts::MAX_MSG_SIZE};
+
+    use super::CommandResponse;
+
+    fn users(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("user{}", i)).collect()
+    }
+
+    #[test]
+    fn test_the_names_of_a_crowded_channel_are_split_in_lines() {
+        let names = users(300);
+        let response = CommandResponse::Names {
+            channel: "#rust".to_string(),
+            names: names.clone(),
+        };
+
+        let replies = response.split_list().unwrap();
+        assert!(replies.len() > 1);
+        let mut split_names = vec![];
+        for reply in replies {
+            assert!(reply.to_string().len() <= MAX_MSG_SIZE);
+            match CommandResponse::serialize(reply.to_string()) {
+                Some(CommandResponse::Names { channel, names }) => {
+                    assert_eq!(channel, "#rust");
+                    split_names.extend(names);
+                }
+                _ => panic!("the reply is not a names reply"),
+            }
+        }
+        assert_eq!(split_names, names);
+    }
+
+    #[test]
+    fn test_the_users_of_a_who_are_split_in_lines() {
+        let response = CommandResponse::WhoReply { users: users(500) };
+
+        let replies = response.split_list().unwrap();
+        let mut split_users = vec![];
+        for reply in replies {
+            assert!(reply.to_string().len() <= MAX_MSG_SIZE);
+            if let CommandResponse::WhoReply { users } = reply {
+                split_users.extend(users);
+            }
+        }
+        assert_eq!(split_users, users(500));
+    }
+
+    #[test]
+    fn test_long_ban_lists_are_split_in_lines() {
+        let ban_list = users(200)
+            .iter()
+            .map(|user| Ban::new(user.to_string(), "alice".to_string(), 1700000000))
+            .collect::<Vec<_>>();
+        let response = CommandResponse::BanList {
+            channel: "#rust".to_string(),
+            ban_list: ban_list.clone(),
+        };
+
+        let replies = response.split_list().unwrap();
+        let mut split_bans = vec![];
+        for reply in replies {
+            assert!(reply.to_string().len() <= MAX_MSG_SIZE);
+            if let CommandResponse::BanList { ban_list, .. } = reply {
+                split_bans.extend(ban_list);
+            }
+        }
+        assert_eq!(split_bans, ban_list);
+    }
+
+    #[test]
+    fn test_short_lists_and_other_replies_are_not_split() {
+        let response = CommandResponse::Names {
+            channel: "#rust".to_string(),
+            names: users(10),
+        };
+        assert!(response.split_list().is_none());
+        let response = CommandResponse::Names {
+            channel: "#rust".to_string(),
+            names: vec![],
+        };
+        assert!(response.split_list().is_none());
+        assert!(CommandResponse::EndNames.split_list().is_none());
+    }
 }
@@ -11,6 +11,7 @@ use super::{
 /// * `Message`: The message that was sent by a user.
 /// * `ErrorResponse`: The error that was sent by the server.
 /// * `MessageResponse`: PRIVMSG and notifications messages.
+#[derive(Clone)]
 pub enum Response {
     CommandResponse { response: replies::CommandResponse },
     ErrorResponse { response: errors::ErrorResponse },
@@ -47,3 +48,51 @@ impl Response {
         }
     }
 }
+
+#[cfg(test)]
+mod response_tests {
+    use crate::generators::check;
+
+    use super::Response;
+
+    static CODES: &[&str] = &[
+        "000", "001", "002", "003", "004", "005", "006", "007", "008", "009", "010", "011", "012",
+        "013", "014", "015", "016", "017", "018", "019", "020", "021", "200", "201", "202", "203",
+        "204", "205", "206", "207", "208", "209", "210", "211", "212", "213", "214", "215", "216",
+        "218", "219", "220", "221", "222", "223", "224", "225", "226", "227", "228", "229", "230",
+        "242", "245", "246", "248", "271", "272", "301", "305", "306", "311", "312", "315", "317",
+        "318", "319", "321", "322", "323", "324", "331", "332", "341", "352", "353", "366", "367",
+        "368", "370", "381", "382", "400", "401", "402", "403", "404", "405", "407", "421", "431",
+        "432", "433", "441", "442", "443", "444", "461", "462", "464", "465", "467", "470", "471",
+        "472", "473", "474", "475", "481", "482", "501", "502", "511", "524", "705", "706", "707",
+        "710", "711", "712", "713", "714", "715", "716", "999",
+    ];
+
+    #[test]
+    fn test_response_is_serialized_back_into_the_same_response() {
+        check("response round trip", |gen| {
+            let response = gen.response();
+            let text = response.to_string();
+            match Response::serialize(text.clone()) {
+                Some(parsed) => assert_eq!(parsed.to_string(), text),
+                None => panic!("{:?} was not parsed", text),
+            }
+        });
+    }
+
+    #[test]
+    fn test_serialize_never_panics_with_arbitrary_input() {
+        check("response from arbitrary bytes", |gen| {
+            let input = String::from_utf8_lossy(&gen.bytes(64)).to_string();
+            let _ = Response::serialize(input);
+        });
+    }
+
+    #[test]
+    fn test_serialize_never_panics_with_known_codes_and_missing_parameters() {
+        check("response from a known code", |gen| {
+            let input = format!("{} {}", gen.pick(CODES), gen.text());
+            let _ = Response::serialize(input);
+        });
+    }
+}
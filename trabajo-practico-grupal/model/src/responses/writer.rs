@@ -0,0 +1,412 @@
+use std::{
+    io::{self, Write},
+    sync::{Arc, OnceLock},
+};
+
+use crate::{
+    consts::{CRLF, MAX_MSG_SIZE},
+    prefix::Prefix,
+};
+
+use super::{numeric::WireFormat, response::Response};
+
+/// Struct that writes the responses and messages sent to a connection, so that the handlers
+/// don't format the protocol strings by hand.
+/// It encodes responses as numeric replies for `Numeric` connections, splits the lines longer
+/// than the RFC allows and ends every line as its wire format expects.
+/// # Fields
+/// * `nickname` - The nickname of the client that receives the messages, `*` if it is not registered yet.
+/// * `format` - The format used by the connection.
+/// * `prefix` - The prefix added to the numeric replies, usually the name of this server.
+#[derive(Debug, Clone)]
+pub struct ResponseWriter {
+    nickname: String,
+    format: WireFormat,
+    prefix: Option<Prefix>,
+}
+
+impl ResponseWriter {
+    /// Creates a writer for the connection, without a prefix for the numeric replies.
+    /// # Arguments
+    /// * `nickname` - The nickname of the client that receives the messages, `*` if it is not registered yet.
+    /// * `format` - The format used by the connection.
+    pub fn new(nickname: &str, format: WireFormat) -> Self {
+        ResponseWriter {
+            nickname: nickname.to_owned(),
+            format,
+            prefix: None,
+        }
+    }
+
+    /// Sets the prefix added to the numeric replies. The messages relayed from other users keep
+    /// their own prefix.
+    /// # Arguments
+    /// * `prefix` - The prefix of the replies.
+    pub fn with_prefix(mut self, prefix: Prefix) -> Self {
+        self.prefix = Some(prefix);
+        self
+    }
+
+    /// Returns the lines sent for the response, without their line ending.
+    /// List replies are split in replies that fit in a line.
+    /// # Arguments
+    /// * `response` - The response to send.
+    pub fn response_lines(&self, response: &Response) -> Vec<String> {
+        if let Response::CommandResponse { response } = response {
+            if let Some(replies) = response.split_list() {
+                return replies
+                    .into_iter()
+                    .flat_map(|response| {
+                        self.response_lines(&Response::CommandResponse { response })
+                    })
+                    .collect();
+            }
+        }
+        let lines = match self.format {
+            WireFormat::Custom => vec![response.to_string()],
+            WireFormat::Numeric => response
+                .to_numeric(&self.nickname)
+                .into_iter()
+                .map(|line| self.prefixed(line))
+                .collect(),
+        };
+        split_lines(lines)
+    }
+
+    /// Returns the lines sent for a message, without their line ending.
+    /// Responses of this server are encoded as responses, while the rest of the messages, like the
+    /// ones relayed from other users, are sent as they are. `Custom` responses that don't fit in a
+    /// line are sent as responses too, so that their lists are split.
+    /// # Arguments
+    /// * `message` - The message to send.
+    pub fn message_lines(&self, message: &str) -> Vec<String> {
+        let is_long = message.len() > MAX_MSG_SIZE;
+        if (self.format == WireFormat::Numeric || is_long) && starts_with_code(message) {
+            if let Some(response) = Response::serialize(message.to_string()) {
+                return self.response_lines(&response);
+            }
+        }
+        split_lines(vec![message.to_string()])
+    }
+
+    /// Returns the bytes sent for the lines: `Custom` lines are zero padded frames of
+    /// `MAX_MSG_SIZE` bytes and `Numeric` lines end in `\r\n`.
+    /// # Arguments
+    /// * `lines` - The lines to encode, at most `MAX_MSG_SIZE` bytes long.
+    pub fn encode(&self, lines: &[String]) -> Vec<u8> {
+        let mut bytes = vec![];
+        for line in lines {
+            bytes.extend_from_slice(line.as_bytes());
+            match self.format {
+                WireFormat::Custom => bytes.resize(bytes.len() + MAX_MSG_SIZE - line.len(), 0),
+                WireFormat::Numeric => bytes.extend_from_slice(CRLF.as_bytes()),
+            }
+        }
+        bytes
+    }
+
+    /// Writes the response to the connection.
+    /// # Arguments
+    /// * `out` - The connection to write to.
+    /// * `response` - The response to send.
+    pub fn write_response<W: Write>(&self, out: &mut W, response: &Response) -> io::Result<()> {
+        out.write_all(&self.encode(&self.response_lines(response)))
+    }
+
+    /// Writes the message to the connection.
+    /// # Arguments
+    /// * `out` - The connection to write to.
+    /// * `message` - The message to send.
+    pub fn write_message<W: Write>(&self, out: &mut W, message: &str) -> io::Result<()> {
+        out.write_all(&self.encode(&self.message_lines(message)))
+    }
+
+    /// Adds the prefix to the numeric replies, the lines that don't have a prefix already.
+    fn prefixed(&self, line: String) -> String {
+        match &self.prefix {
+            Some(prefix) if !line.starts_with(':') => format!(":{} {}", prefix, line),
+            _ => line,
+        }
+    }
+}
+
+/// Struct that holds a message sent to many connections, like the ones relayed to the members
+/// of a channel. The message is formatted once and shared by every receiver, and it is encoded
+/// once for each wire format, the first time a connection with that format needs it.
+/// Only the messages whose lines don't depend on the receiver can be shared, like the ones sent
+/// to a channel, which have the channel as target.
+/// # Fields
+/// * `message` - The message to send.
+/// * `custom` - The bytes of the message for the `Custom` connections.
+/// * `numeric` - The bytes of the message for the `Numeric` connections.
+#[derive(Debug, Clone)]
+pub struct SharedMessage {
+    message: Arc<str>,
+    custom: OnceLock<Arc<[u8]>>,
+    numeric: OnceLock<Arc<[u8]>>,
+}
+
+impl SharedMessage {
+    /// Creates the message shared by the receivers.
+    /// # Arguments
+    /// * `message` - The message to send.
+    pub fn new(message: Arc<str>) -> Self {
+        SharedMessage {
+            message,
+            custom: OnceLock::new(),
+            numeric: OnceLock::new(),
+        }
+    }
+
+    /// Returns the message to send.
+    pub fn message(&self) -> &Arc<str> {
+        &self.message
+    }
+
+    /// Returns the bytes sent to the connections with the format, encoding them the first time.
+    /// # Arguments
+    /// * `format` - The format used by the connection.
+    pub fn bytes(&self, format: WireFormat) -> &Arc<[u8]> {
+        let encoded = match format {
+            WireFormat::Custom => &self.custom,
+            WireFormat::Numeric => &self.numeric,
+        };
+        encoded.get_or_init(|| {
+            let writer = ResponseWriter::new("*", format);
+            writer.encode(&writer.message_lines(&self.message)).into()
+        })
+    }
+}
+
+/// Returns true if the first word of the message is a 3 digit code, like the responses of this server.
+fn starts_with_code(message: &str) -> bool {
+    match message.split_whitespace().next() {
+        Some(token) => token.len() == 3 && token.chars().all(|c| c.is_ascii_digit()),
+        None => false,
+    }
+}
+
+fn split_lines(lines: Vec<String>) -> Vec<String> {
+    lines
+        .into_iter()
+        .flat_map(|line| split_line(&line, MAX_MSG_SIZE))
+        .collect()
+}
+
+/// Splits a line in lines of at most `max` bytes.
+/// When the line has a trailing parameter its text is split between lines that repeat everything
+/// before it, cutting at the spaces if possible so that lists of names are not broken. Lines without
+/// a trailing parameter are cut. A line is never cut inside of a character.
+/// # Arguments
+/// * `line` - The line to split, without its line ending.
+/// * `max` - The most bytes of each line.
+pub fn split_line(line: &str, max: usize) -> Vec<String> {
+    if line.len() <= max {
+        return vec![line.to_string()];
+    }
+    let start = if line.starts_with(':') {
+        line.find(' ').unwrap_or(line.len())
+    } else {
+        0
+    };
+    let (head, mut text) = match line[start..].find(" :") {
+        Some(index) => line.split_at(start + index + 2),
+        None => return vec![line[..floor_char_boundary(line, max)].to_string()],
+    };
+    if head.len() >= max {
+        return vec![line[..floor_char_boundary(line, max)].to_string()];
+    }
+    let room = max - head.len();
+    let mut lines = vec![];
+    while text.len() > room {
+        let end = floor_char_boundary(text, room);
+        let (chunk, rest) = match text[..end].rfind(' ') {
+            Some(space) if space > 0 => (&text[..space], &text[space + 1..]),
+            _ => text.split_at(end),
+        };
+        lines.push(format!("{}{}", head, chunk));
+        text = rest;
+    }
+    lines.push(format!("{}{}", head, text));
+    lines
+}
+
+/// Returns the largest index of the text that is at most `index` and is the start of a character.
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod writer_tests {
+    use crate::{
+        consts::MAX_MSG_SIZE,
+        generators::check,
+        prefix::Prefix,
+        responses::{
+            message::MessageResponse, numeric::WireFormat, replies::CommandResponse,
+            response::Response,
+        },
+    };
+
+    use super::{split_line, ResponseWriter, SharedMessage};
+
+    #[test]
+    fn test_numeric_replies_have_the_prefix_and_end_in_crlf() {
+        let writer = ResponseWriter::new("alice", WireFormat::Numeric)
+            .with_prefix(Prefix::ServerName("irc.fiuba.ar".to_string()));
+        let response = Response::CommandResponse {
+            response: CommandResponse::NowAway,
+        };
+
+        let mut out = vec![];
+        writer.write_response(&mut out, &response).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            ":irc.fiuba.ar 306 alice :You have been marked as being away\r\n"
+        );
+    }
+
+    #[test]
+    fn test_relayed_messages_are_sent_as_they_are() {
+        let writer = ResponseWriter::new("alice", WireFormat::Numeric)
+            .with_prefix(Prefix::ServerName("irc.fiuba.ar".to_string()));
+
+        assert_eq!(
+            writer.message_lines(":bob!b@host PRIVMSG alice :hi"),
+            vec![":bob!b@host PRIVMSG alice :hi".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_shared_messages_are_encoded_like_the_messages_of_each_receiver() {
+        let line = MessageResponse::ChannelPrivMsg {
+            channel: "#rust".to_string(),
+            sender: "bob".to_string(),
+            message: "hi all".to_string(),
+        }
+        .to_string();
+        let shared = SharedMessage::new(line.as_str().into());
+
+        for format in [WireFormat::Custom, WireFormat::Numeric] {
+            let writer = ResponseWriter::new("alice", format);
+            let expected = writer.encode(&writer.message_lines(&line));
+            assert_eq!(shared.bytes(format).as_ref(), expected.as_slice());
+        }
+        assert!(std::sync::Arc::ptr_eq(
+            shared.bytes(WireFormat::Numeric),
+            shared.bytes(WireFormat::Numeric)
+        ));
+    }
+
+    #[test]
+    fn test_custom_messages_are_zero_padded_frames() {
+        let writer = ResponseWriter::new("alice", WireFormat::Custom);
+
+        let mut out = vec![];
+        writer.write_message(&mut out, "PING :a").unwrap();
+        assert_eq!(out.len(), MAX_MSG_SIZE);
+        assert_eq!(&out[..7], b"PING :a");
+        assert!(out[7..].iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn test_long_trailings_are_split_repeating_the_head_of_the_line() {
+        let names = (0..200)
+            .map(|i| format!("user{}", i))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let line = format!(":irc.fiuba.ar 353 alice = #rust :{}", names);
+
+        let lines = split_line(&line, MAX_MSG_SIZE);
+        assert!(lines.len() > 1);
+        let mut split_names = vec![];
+        for line in lines.iter() {
+            assert!(line.len() <= MAX_MSG_SIZE);
+            let (head, names) = line.split_once(" :").unwrap();
+            assert_eq!(head, ":irc.fiuba.ar 353 alice = #rust");
+            split_names.extend(names.split(' ').map(|name| name.to_string()));
+        }
+        assert_eq!(split_names.join(" "), names);
+    }
+
+    #[test]
+    fn test_custom_names_of_hundreds_of_users_are_sent_in_several_frames() {
+        let writer = ResponseWriter::new("alice", WireFormat::Custom);
+        let names = (0..400).map(|i| format!("user{}", i)).collect::<Vec<_>>();
+        let message = CommandResponse::Names {
+            channel: "#rust".to_string(),
+            names: names.clone(),
+        }
+        .to_string();
+
+        let mut out = vec![];
+        writer.write_message(&mut out, &message).unwrap();
+        assert!(out.len() > MAX_MSG_SIZE);
+        assert_eq!(out.len() % MAX_MSG_SIZE, 0);
+        let mut split_names = vec![];
+        for frame in out.chunks(MAX_MSG_SIZE) {
+            let line = frame.iter().take_while(|&&byte| byte != 0).copied();
+            let line = String::from_utf8(line.collect()).unwrap();
+            match CommandResponse::serialize(line) {
+                Some(CommandResponse::Names { names, .. }) => split_names.extend(names),
+                _ => panic!("the frame is not a names reply"),
+            }
+        }
+        assert_eq!(split_names, names);
+    }
+
+    #[test]
+    fn test_numeric_names_of_hundreds_of_users_are_sent_in_several_lines() {
+        let writer = ResponseWriter::new("alice", WireFormat::Numeric)
+            .with_prefix(Prefix::ServerName("irc.fiuba.ar".to_string()));
+        let names = (0..400).map(|i| format!("user{}", i)).collect::<Vec<_>>();
+        let response = Response::CommandResponse {
+            response: CommandResponse::Names {
+                channel: "#rust".to_string(),
+                names: names.clone(),
+            },
+        };
+
+        let lines = writer.response_lines(&response);
+        assert!(lines.len() > 1);
+        let mut split_names = vec![];
+        for line in lines {
+            assert!(line.len() <= MAX_MSG_SIZE);
+            let (head, names) = line.split_once(" :").unwrap();
+            assert_eq!(head, ":irc.fiuba.ar 353 alice = #rust");
+            split_names.extend(names.split(' ').map(|name| name.to_string()));
+        }
+        assert_eq!(split_names, names);
+    }
+
+    #[test]
+    fn test_lines_without_trailing_are_cut() {
+        let line = format!("JOIN {}", "#a,".repeat(300));
+
+        let lines = split_line(&line, MAX_MSG_SIZE);
+        assert_eq!(lines, vec![line[..MAX_MSG_SIZE].to_string()]);
+    }
+
+    #[test]
+    fn test_lines_are_not_cut_inside_a_character() {
+        check("split lines keep their characters", |gen| {
+            let text = (0..gen.below(400))
+                .map(|_| *gen.pick(&["ñ", "a", " ", "日", ":"]))
+                .collect::<String>();
+            let line = format!("PRIVMSG #rust :{}", text);
+
+            let lines = split_line(&line, 40);
+            let mut joined = String::new();
+            for line in lines.iter() {
+                assert!(line.len() <= 40);
+                joined.push_str(line.strip_prefix("PRIVMSG #rust :").unwrap());
+            }
+            let removed = text.len() - joined.len();
+            assert!(removed < lines.len());
+        });
+    }
+}
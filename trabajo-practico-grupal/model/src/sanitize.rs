@@ -0,0 +1,81 @@
+use crate::{
+    ctcp::CTCP_DELIMITER,
+    formatting::{BOLD, COLOR, ITALIC, RESET, UNDERLINE},
+    message::MessageError,
+};
+
+/// The control characters kept in the text of the messages: the delimiter of the CTCP messages
+/// and the mIRC formatting codes.
+const ALLOWED_CONTROLS: [char; 6] = [CTCP_DELIMITER, BOLD, COLOR, RESET, ITALIC, UNDERLINE];
+
+/// Returns the text of the bytes read from a connection, without the control characters that
+/// are not part of the formatting of the text, like line breaks that would start a new message.
+/// Returns MessageError::InvalidEncoding if the bytes are not valid UTF-8, instead of replacing
+/// the invalid bytes.
+/// # Arguments
+/// * `bytes` - The bytes of the message, without its terminator.
+pub fn sanitize(bytes: &[u8]) -> Result<String, MessageError> {
+    let text = std::str::from_utf8(bytes).map_err(|_| MessageError::InvalidEncoding)?;
+    Ok(strip_controls(text))
+}
+
+/// Returns the text without its control characters, keeping the CTCP delimiter and the mIRC
+/// formatting codes.
+/// # Arguments
+/// * `text` - The text to clean.
+pub fn strip_controls(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_control() || ALLOWED_CONTROLS.contains(c))
+        .collect()
+}
+
+#[cfg(test)]
+mod sanitize_tests {
+    use crate::{generators::check, message::MessageError};
+
+    use super::{sanitize, strip_controls};
+
+    #[test]
+    fn test_invalid_utf8_is_rejected() {
+        assert_eq!(
+            sanitize(b"PRIVMSG #rust :caf\xe9"),
+            Err(MessageError::InvalidEncoding)
+        );
+        assert_eq!(sanitize(b"\xff\xfe"), Err(MessageError::InvalidEncoding));
+    }
+
+    #[test]
+    fn test_valid_text_is_kept() {
+        assert_eq!(
+            sanitize("PRIVMSG #rust :¡hola, señor! 日本".as_bytes()),
+            Ok("PRIVMSG #rust :¡hola, señor! 日本".to_string())
+        );
+    }
+
+    #[test]
+    fn test_control_characters_are_stripped() {
+        assert_eq!(
+            sanitize(b"PRIVMSG #rust :hi\r\nQUIT :bye"),
+            Ok("PRIVMSG #rust :hiQUIT :bye".to_string())
+        );
+        assert_eq!(strip_controls("a\x00b\x07c\x1bd\x7fe"), "abcde");
+    }
+
+    #[test]
+    fn test_formatting_codes_and_ctcp_are_kept() {
+        let text =
+            "PRIVMSG #rust :\x01ACTION \x02waves\x02 \x0304red\x0F \x1Dit\x1D \x1Fun\x1F\x01";
+        assert_eq!(sanitize(text.as_bytes()), Ok(text.to_string()));
+    }
+
+    #[test]
+    fn test_sanitized_text_has_no_line_breaks() {
+        check("sanitized text has no line breaks", |gen| {
+            let bytes = gen.bytes(64);
+            if let Ok(text) = sanitize(&bytes) {
+                assert!(!text.contains('\r') && !text.contains('\n') && !text.contains('\0'));
+                assert_eq!(strip_controls(&text), text);
+            }
+        });
+    }
+}
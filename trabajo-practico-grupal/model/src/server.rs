@@ -1,3 +1,4 @@
+use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, net::TcpStream, sync::Arc};
 
 /// Struct that holds the information of the server
@@ -8,13 +9,18 @@ use std::{collections::HashMap, net::TcpStream, sync::Arc};
 /// * `operators`: The operators of the server.
 /// * `father`: The father of the server.
 /// * `children`: The children of the server.
-#[derive(Debug, Clone)]
+///
+/// The connections to its father and children are not saved, so a server is read without them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Server {
     pub name: String,
     pub ip: String,
     pub port: String,
+    #[serde(default)]
     pub operators: Vec<String>,
+    #[serde(skip)]
     pub father: Option<(String, Arc<TcpStream>)>,
+    #[serde(skip)]
     pub children: HashMap<String, Arc<TcpStream>>,
 }
 impl Server {
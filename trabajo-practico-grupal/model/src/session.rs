@@ -1,21 +1,57 @@
 use std::{
     collections::HashMap,
-    net::TcpStream,
     sync::{Arc, Mutex, RwLock},
 };
 
-use crate::{channel::Channel, client::Client, persistence::PersistenceType};
+use crate::{
+    channel_registry::ChannelRegistry,
+    client_registry::ClientRegistry,
+    command_metrics::CommandMetrics,
+    config::ServerConfig,
+    events::{EventBus, ServerEvent},
+    gc_stats::GcStats,
+    persistence::PersistenceType,
+    plugins::Plugins,
+    socket_registry::SocketRegistry,
+    who_query::WhoQuery,
+};
 
 /// Struct that holds the information of the server session
 /// # Fields
-/// * `clients`: A hashmap that contains the clients of the server.
-/// * `channels`: A hashmap that contains the channels of the server.
-/// * `sockets`: A hashmap that contains the sockets of the clients.
+/// * `clients`: The registry of the clients of the server.
+/// * `channels`: The registry of the channels of the server.
+/// * `sockets`: The registry of the connections of the clients, with the format used by each one.
+/// * `connections`: A hashmap that contains the amount of open connections of each IP.
+/// * `rejected_connections`: A hashmap that contains the amount of connections rejected for each reason.
 /// * `database_sender`: The sender of the server that informs the database about changes of channels and clients.
+/// * `config`: The configuration of the server.
+/// * `events`: The bus where the command handlers publish the events of the server.
+/// * `plugins`: The plugins of the server, asked about the commands of the clients before they are handled.
+/// * `who_queries`: A hashmap that contains the WHO query of each client waiting for the answers of the other servers.
+/// * `command_metrics`: The histograms of the time the server took to handle each command.
+/// * `gc_stats`: The totals of the passes that remove the empty channels and archive the clients disconnected for too long.
 #[derive(Debug, Clone)]
 pub struct Session {
-    pub clients: Arc<RwLock<HashMap<String, Client>>>,
-    pub sockets: Arc<Mutex<HashMap<String, Arc<TcpStream>>>>,
-    pub channels: Arc<RwLock<HashMap<String, Channel>>>,
+    pub clients: ClientRegistry,
+    pub sockets: SocketRegistry,
+    pub connections: Arc<Mutex<HashMap<String, usize>>>,
+    pub rejected_connections: Arc<Mutex<HashMap<String, usize>>>,
+    pub channels: ChannelRegistry,
     pub database_sender: std::sync::mpsc::Sender<(PersistenceType, String)>,
+    pub config: Arc<RwLock<ServerConfig>>,
+    pub events: EventBus,
+    pub plugins: Plugins,
+    pub who_queries: Arc<Mutex<HashMap<String, WhoQuery>>>,
+    pub command_metrics: CommandMetrics,
+    pub gc_stats: GcStats,
+}
+
+impl Session {
+    /// Publishes the event to the subscribers of the events of the server.
+    /// It must be called without holding any lock of the session.
+    /// # Arguments
+    /// * `event` - The event to publish.
+    pub fn publish(&self, event: ServerEvent) {
+        self.events.publish(self, event);
+    }
 }
@@ -4,9 +4,7 @@ use std::{
     sync::Arc,
 };
 
-use crate::client_errors::ClientError;
-
-const MAX_MSG_SIZE: usize = 510;
+use crate::{client_errors::ClientError, consts::MAX_MSG_SIZE, sanitize::sanitize};
 
 /// Function that writes the socket received.
 /// # Arguments
@@ -20,12 +18,18 @@ pub fn write_socket(arc_socket: Arc<TcpStream>, message: &str) -> Result<(), Cli
 }
 
 /// Function that reads the socket received. It returs
-/// the message read in a String.
+/// the message read in a String, without its control characters.
+/// The messages that are not valid UTF-8 are discarded.
 /// # Arguments
 /// * `arc_socket` - The socket to read from.
 pub fn read_socket(arc_socket: Arc<TcpStream>) -> Result<String, ClientError> {
-    let mut buff = [0u8; MAX_MSG_SIZE];
-    arc_socket.as_ref().read_exact(&mut buff)?;
-    let msg = buff.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
-    Ok(String::from_utf8_lossy(&msg).to_string())
+    loop {
+        let mut buff = [0u8; MAX_MSG_SIZE];
+        arc_socket.as_ref().read_exact(&mut buff)?;
+        let msg = buff.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        match sanitize(&msg) {
+            Ok(msg) => return Ok(msg),
+            Err(e) => println!("[ERROR] Discarding message: {:?}", e),
+        }
+    }
 }
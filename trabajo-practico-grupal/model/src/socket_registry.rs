@@ -0,0 +1,118 @@
+use std::{
+    collections::HashMap,
+    net::TcpStream,
+    sync::{Arc, Mutex},
+};
+
+use crate::{locks::lock, responses::numeric::WireFormat};
+
+/// Struct that holds the connections of the clients of the server, each one with the format
+/// its responses are encoded in. A connection and its format are always saved and removed
+/// together, locking the sockets before the formats.
+/// # Fields
+/// * `sockets`: A hashmap that contains the sockets of the clients, by nickname.
+/// * `wire_formats`: A hashmap that contains the format used by the connection of each client.
+#[derive(Debug, Clone, Default)]
+pub struct SocketRegistry {
+    sockets: Arc<Mutex<HashMap<String, Arc<TcpStream>>>>,
+    wire_formats: Arc<Mutex<HashMap<String, WireFormat>>>,
+}
+
+impl SocketRegistry {
+    /// Returns the lock of the sockets.
+    pub fn as_sockets_lock(&self) -> &Mutex<HashMap<String, Arc<TcpStream>>> {
+        &self.sockets
+    }
+
+    /// Returns the lock of the formats of the connections.
+    pub fn as_wire_formats_lock(&self) -> &Mutex<HashMap<String, WireFormat>> {
+        &self.wire_formats
+    }
+
+    /// Saves the connection of the client with its format, replacing the previous one.
+    /// # Arguments
+    /// * `nickname` - The nickname of the client.
+    /// * `socket` - The socket of the connection.
+    /// * `format` - The format the responses to the client are encoded in.
+    pub fn register(&self, nickname: &str, socket: Arc<TcpStream>, format: WireFormat) {
+        let mut sockets = lock(&self.sockets, "sockets");
+        sockets.insert(nickname.to_string(), socket);
+        lock(&self.wire_formats, "wire formats").insert(nickname.to_string(), format);
+        drop(sockets);
+    }
+
+    /// Removes the connection of the client and its format, returning the socket if it had one.
+    /// # Arguments
+    /// * `nickname` - The nickname of the client.
+    pub fn unregister(&self, nickname: &str) -> Option<Arc<TcpStream>> {
+        let mut sockets = lock(&self.sockets, "sockets");
+        let socket = sockets.remove(nickname);
+        lock(&self.wire_formats, "wire formats").remove(nickname);
+        drop(sockets);
+        socket
+    }
+
+    /// Returns the socket of the client, or none if it is not connected.
+    /// # Arguments
+    /// * `nickname` - The nickname of the client.
+    pub fn socket(&self, nickname: &str) -> Option<Arc<TcpStream>> {
+        lock(&self.sockets, "sockets").get(nickname).cloned()
+    }
+
+    /// Returns true if the socket is the one saved for the client, which is not the case once
+    /// another connection reclaims the nickname.
+    /// # Arguments
+    /// * `nickname` - The nickname of the client.
+    /// * `socket` - The socket of a connection.
+    pub fn is_current(&self, nickname: &str, socket: &Arc<TcpStream>) -> bool {
+        lock(&self.sockets, "sockets")
+            .get(nickname)
+            .is_some_and(|current| Arc::ptr_eq(current, socket))
+    }
+
+    /// Returns the format of the connection of the client.
+    /// Clients whose format is not known yet use the `Custom` format.
+    /// # Arguments
+    /// * `nickname` - The nickname of the client.
+    pub fn wire_format(&self, nickname: &str) -> WireFormat {
+        match lock(&self.wire_formats, "wire formats").get(nickname) {
+            Some(format) => *format,
+            None => WireFormat::Custom,
+        }
+    }
+
+    /// Changes the format of the connection of the client.
+    /// # Arguments
+    /// * `nickname` - The nickname of the client.
+    /// * `format` - The format the responses to the client are encoded in.
+    pub fn set_wire_format(&self, nickname: &str, format: WireFormat) {
+        lock(&self.wire_formats, "wire formats").insert(nickname.to_string(), format);
+    }
+}
+
+#[cfg(test)]
+mod socket_registry_tests {
+    use std::{
+        net::{TcpListener, TcpStream},
+        sync::Arc,
+    };
+
+    use super::SocketRegistry;
+    use crate::responses::numeric::WireFormat;
+
+    #[test]
+    fn test_connection_and_format_are_removed_together() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let socket = Arc::new(TcpStream::connect(listener.local_addr().unwrap()).unwrap());
+        let sockets = SocketRegistry::default();
+
+        sockets.register("alice", socket.clone(), WireFormat::Numeric);
+        assert!(sockets.is_current("alice", &socket));
+        assert_eq!(sockets.wire_format("alice"), WireFormat::Numeric);
+
+        assert!(sockets.unregister("alice").is_some());
+        assert!(sockets.socket("alice").is_none());
+        assert!(!sockets.is_current("alice", &socket));
+        assert_eq!(sockets.wire_format("alice"), WireFormat::Custom);
+    }
+}
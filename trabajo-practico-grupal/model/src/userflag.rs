@@ -1,12 +1,16 @@
+use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 
 /// The user flags that can be set on a user.
-#[derive(Debug, PartialEq, Eq, Clone)]
+/// With `DeliveryReceipts` the user is informed whether its private messages reached the receivers.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
 pub enum UserFlag {
     Invisible,
     ServerNotice,
     Wallops,
     Operator,
+    DeliveryReceipts,
     Other,
 }
 
@@ -18,6 +22,7 @@ impl UserFlag {
             UserFlag::ServerNotice => "s".to_string(),
             UserFlag::Wallops => "w".to_string(),
             UserFlag::Operator => "o".to_string(),
+            UserFlag::DeliveryReceipts => "d".to_string(),
             UserFlag::Other => "-".to_string(),
         }
     }
@@ -29,6 +34,7 @@ impl UserFlag {
             's' => UserFlag::ServerNotice,
             'w' => UserFlag::Wallops,
             'o' => UserFlag::Operator,
+            'd' => UserFlag::DeliveryReceipts,
             _ => UserFlag::Other,
         }
     }
@@ -40,6 +46,7 @@ impl UserFlag {
             UserFlag::ServerNotice,
             UserFlag::Wallops,
             UserFlag::Operator,
+            UserFlag::DeliveryReceipts,
             UserFlag::Other,
         ]
     }
@@ -53,8 +60,28 @@ impl Display for UserFlag {
             UserFlag::ServerNotice => "ServerNotice".to_string(),
             UserFlag::Wallops => "Wallops".to_string(),
             UserFlag::Operator => "Operator".to_string(),
+            UserFlag::DeliveryReceipts => "DeliveryReceipts".to_string(),
             UserFlag::Other => "Other".to_string(),
         };
         write!(f, "{}", r)
     }
 }
+
+impl From<UserFlag> for String {
+    /// Writes the character of the flag.
+    fn from(flag: UserFlag) -> String {
+        UserFlag::to_string(&flag)
+    }
+}
+
+impl TryFrom<String> for UserFlag {
+    type Error = String;
+
+    /// Reads the flag of the first character.
+    fn try_from(flag: String) -> Result<Self, Self::Error> {
+        match flag.chars().next() {
+            Some(flag) => Ok(UserFlag::match_flag(flag)),
+            None => Err("A flag can't be empty".to_string()),
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::{collections::HashSet, time::Instant};
+
+/// Struct that holds a WHO query of a client that was forwarded to the other servers of the
+/// network, while the server waits for their answers.
+/// # Fields
+/// * `started`: When the query was forwarded, which tells it apart from the next query of the client.
+/// * `users`: The users that matched the query so far, without repeated ones.
+/// * `waiting`: The servers that haven't answered the query yet.
+#[derive(Debug, Clone)]
+pub struct WhoQuery {
+    pub started: Instant,
+    pub users: Vec<String>,
+    pub waiting: HashSet<String>,
+}
+
+impl WhoQuery {
+    /// Creates a query with the users that matched in the current server.
+    /// # Arguments
+    /// * `users` - The users that matched the query in the current server.
+    /// * `servers` - The other servers of the network, which must answer the query.
+    pub fn new(users: Vec<String>, servers: HashSet<String>) -> WhoQuery {
+        let mut query = WhoQuery {
+            started: Instant::now(),
+            users: vec![],
+            waiting: servers,
+        };
+        query.add_users(users);
+        query
+    }
+
+    /// Adds the users that matched the query in another server.
+    /// # Arguments
+    /// * `users` - The users that matched.
+    pub fn add_users(&mut self, users: Vec<String>) {
+        for user in users {
+            if !self.users.contains(&user) {
+                self.users.push(user);
+            }
+        }
+    }
+
+    /// Marks that a server answered the query, and returns true if every server answered it.
+    /// # Arguments
+    /// * `server` - The name of the server that answered.
+    pub fn answered_by(&mut self, server: &str) -> bool {
+        self.waiting.remove(server);
+        self.waiting.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod who_query_tests {
+    use std::collections::HashSet;
+
+    use super::WhoQuery;
+
+    #[test]
+    fn test_query_is_complete_when_every_server_answered() {
+        let servers = HashSet::from(["a".to_string(), "b".to_string()]);
+        let mut query = WhoQuery::new(vec!["alice".to_string()], servers);
+
+        query.add_users(vec!["bob".to_string(), "alice".to_string()]);
+
+        assert!(!query.answered_by("a"));
+        assert!(!query.answered_by("unknown"));
+        assert!(query.answered_by("b"));
+        assert_eq!(query.users, vec!["alice".to_string(), "bob".to_string()]);
+    }
+}
@@ -0,0 +1,981 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io::BufReader,
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use model::{
+    ban::Ban,
+    consts::NETWORK_CHANNEL_PREFIX,
+    events::{ServerEvent, Subscriber},
+    json::ToJson,
+    message::{Message, MessageType},
+    network::Network,
+    persistence::PersistenceType,
+    prefix::Prefix,
+    session::Session,
+};
+use serde::Serialize;
+
+use crate::locks::{
+    lock_connections, lock_rejected_connections, read_lock_channels, read_lock_clients,
+    read_lock_config, read_lock_network_clients, read_lock_servers, write_lock_channels,
+};
+use crate::{
+    commands::{
+        command_utils::get_current_timestamp,
+        kick::{inform_kick, remove_from_channel},
+        mode::inform_channel_about_mode,
+        quit::handle_quit_command,
+    },
+    database::inform_database,
+    http::{escape_html, percent_decode, percent_encode, HttpRequest, HttpResponse},
+    server_errors::ServerError,
+    socket::inform_network,
+};
+
+static ADMIN_ADDRESS: &str = "127.0.0.1";
+static ADMIN_READ_TIMEOUT: Duration = Duration::from_secs(5);
+static RECENT_EVENTS_CAPACITY: usize = 50;
+static SECONDS_PER_DAY: u64 = 86400;
+static KILL_MESSAGE: &str = "Killed by the administrator";
+static TOKEN_COOKIE: &str = "admin_token";
+
+/// Function that starts the web admin interface of the server, if the configuration sets its
+/// port and its token. The interface listens on the local address only, in its own thread,
+/// and keeps the recent events of the server from the moment it starts.
+/// # Arguments
+/// * `session` - The session of the current server.
+/// * `network` - The network of the current server.
+/// * `server_name` - The name of the current server.
+pub fn handle_admin(
+    session: Session,
+    network: Network,
+    server_name: String,
+) -> Result<(), ServerError> {
+    let config = read_lock_config(&session)?;
+    let port = config.admin_port;
+    let enabled = port != 0 && !config.admin_token.is_empty();
+    drop(config);
+    if !enabled {
+        return Ok(());
+    }
+
+    let listener = TcpListener::bind(format!("{}:{}", ADMIN_ADDRESS, port))?;
+    println!("Admin interface listening on {}:{}", ADMIN_ADDRESS, port);
+    let admin = Admin::new(session, network, server_name);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(e) = admin.handle_connection(stream) {
+                        println!("Error handling admin request: {:?}", e);
+                    }
+                }
+                Err(e) => println!("Error accepting admin connection: {:?}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Struct that answers the requests of the web admin interface.
+/// # Fields
+/// * `session` - The session of the current server.
+/// * `network` - The network of the current server.
+/// * `server_name` - The name of the current server, that the actions are made by.
+/// * `events` - The recent events of the server, shown in the dashboard.
+pub struct Admin {
+    session: Session,
+    network: Network,
+    server_name: String,
+    events: Arc<RecentEvents>,
+}
+
+impl Admin {
+    /// Creates the admin interface and subscribes its recent events to the events of the session.
+    /// # Arguments
+    /// * `session` - The session of the current server.
+    /// * `network` - The network of the current server.
+    /// * `server_name` - The name of the current server.
+    pub fn new(session: Session, network: Network, server_name: String) -> Admin {
+        let events = Arc::new(RecentEvents::new(RECENT_EVENTS_CAPACITY));
+        session.events.subscribe(events.clone());
+        Admin {
+            session,
+            network,
+            server_name,
+            events,
+        }
+    }
+
+    /// Reads a request from the connection and writes the response.
+    fn handle_connection(&self, stream: TcpStream) -> Result<(), ServerError> {
+        stream.set_read_timeout(Some(ADMIN_READ_TIMEOUT))?;
+        let response = match HttpRequest::read(&mut BufReader::new(&stream)) {
+            Ok(request) => self.respond(&request),
+            Err(_) => HttpResponse::error(400, "Bad Request"),
+        };
+        response.write(&mut &stream)
+    }
+
+    /// Returns the response to a request. Every request but the login must carry the token of
+    /// the configuration, as a bearer token or in the cookie set by the login.
+    /// # Arguments
+    /// * `request` - The request received.
+    pub fn respond(&self, request: &HttpRequest) -> HttpResponse {
+        let token = match read_lock_config(&self.session) {
+            Ok(config) => config.admin_token.to_owned(),
+            Err(_) => return HttpResponse::error(500, "Internal Server Error"),
+        };
+        match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/login") => return HttpResponse::html(login_page()),
+            ("POST", "/login") => return login(request, &token),
+            (_, "/login") => return HttpResponse::error(405, "Method Not Allowed"),
+            ("GET", "/") if !is_authorized(request, &token) => {
+                return HttpResponse::redirect("/login")
+            }
+            _ => {}
+        }
+        if !is_authorized(request, &token) {
+            let mut response = HttpResponse::error(401, "Unauthorized");
+            response
+                .headers
+                .push(("WWW-Authenticate".to_string(), "Bearer".to_string()));
+            return response;
+        }
+
+        let result = match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/") => self
+                .status()
+                .map(|status| HttpResponse::html(status.to_html())),
+            ("GET", "/api/status") => self
+                .status()
+                .map(|status| HttpResponse::json(status.to_json())),
+            ("GET", "/api/metrics") => Ok(HttpResponse::json(self.metrics().to_json())),
+            ("POST", "/kick") => self.run_action(request, &["channel", "nickname"], |params| {
+                self.kick(params[0], params[1])
+            }),
+            ("POST", "/ban") => self.run_action(request, &["channel", "nickname"], |params| {
+                self.ban(params[0], params[1])
+            }),
+            ("POST", "/kill") => {
+                self.run_action(request, &["nickname"], |params| self.kill(params[0]))
+            }
+            (_, "/" | "/api/status" | "/api/metrics" | "/kick" | "/ban" | "/kill") => {
+                Ok(HttpResponse::error(405, "Method Not Allowed"))
+            }
+            _ => Ok(HttpResponse::error(404, "Not Found")),
+        };
+        match result {
+            Ok(response) => response,
+            Err(e) => error_response(e),
+        }
+    }
+
+    /// Runs an action with the parameters of the request, sending the browser back to the
+    /// dashboard once it is done.
+    fn run_action<F>(
+        &self,
+        request: &HttpRequest,
+        names: &[&str],
+        action: F,
+    ) -> Result<HttpResponse, ServerError>
+    where
+        F: FnOnce(Vec<&str>) -> Result<(), ServerError>,
+    {
+        let mut params = vec![];
+        for name in names {
+            match request.param(name) {
+                Some(value) if !value.is_empty() => params.push(value.as_str()),
+                _ => return Err(ServerError::InvalidParameters),
+            }
+        }
+        action(params)?;
+        Ok(HttpResponse::redirect("/"))
+    }
+
+    /// Removes the user from the channel, like a KICK sent by the server. The members of the
+    /// channel are told that the user left it, and so are the other servers for the channels
+    /// of the network.
+    /// # Arguments
+    /// * `channel_name` - The channel to remove the user from.
+    /// * `nickname` - The nickname of the user.
+    pub fn kick(&self, channel_name: &str, nickname: &str) -> Result<(), ServerError> {
+        let (channel, successor) = remove_from_channel(channel_name, nickname, &self.session)?;
+        println!(
+            "Client {} kicked from {} by the admin",
+            nickname, channel.name
+        );
+        let message = format!("{} kicked you from {}", self.server_name, channel.name);
+        inform_kick(&channel, nickname, message, successor, &self.session)?;
+        if channel.name.starts_with(NETWORK_CHANNEL_PREFIX) {
+            let part = Message::new(
+                Some(Prefix::nick(nickname)),
+                MessageType::Part,
+                vec![channel.name.to_owned()],
+                None,
+            );
+            inform_network(
+                &self.network,
+                &self.server_name,
+                &Message::deserialize(part)?,
+            )?;
+        }
+
+        self.session.publish(ServerEvent::ClientKicked {
+            channel: channel_name.to_string(),
+            nickname: nickname.to_string(),
+            kicked_by: self.server_name.to_owned(),
+        });
+        Ok(())
+    }
+
+    /// Bans the user from the channel, with the server as who set the ban, and removes it from
+    /// the channel if it is on it. The ban only applies to this server, the other servers of the
+    /// network only learn that the user left the channel.
+    /// # Arguments
+    /// * `channel_name` - The channel to ban the user from.
+    /// * `nickname` - The nickname of the user.
+    pub fn ban(&self, channel_name: &str, nickname: &str) -> Result<(), ServerError> {
+        let (channel, newly_banned) = {
+            let mut channels = write_lock_channels(&self.session)?;
+            let channel = match channels.get_mut(channel_name) {
+                Some(channel) => channel,
+                None => return Err(ServerError::ChannelNotFound),
+            };
+            let now = get_current_timestamp();
+            let newly_banned = !channel.is_banned(nickname, now);
+            if newly_banned {
+                println!(
+                    "{:?} is now banned from {:?} by the admin",
+                    nickname, channel_name
+                );
+                let ban = Ban::new(nickname.to_string(), self.server_name.to_owned(), now);
+                channel.banned_users.retain(|ban| ban.nickname != nickname);
+                channel.banned_users.push(ban);
+                inform_database(
+                    PersistenceType::ChannelUpdate(channel.name.to_owned()),
+                    channel.to_json(),
+                    &self.session,
+                )?;
+            }
+            (channel.clone(), newly_banned)
+        };
+
+        if newly_banned {
+            let modes = format!("+b {}", nickname);
+            if let Err(e) = inform_channel_about_mode(
+                &channel,
+                modes.to_owned(),
+                &self.server_name,
+                &self.session,
+            ) {
+                println!("Error informing the ban of {}: {:?}", nickname, e);
+            }
+            self.session.publish(ServerEvent::ModeChanged {
+                target: channel_name.to_string(),
+                modes,
+                set_by: self.server_name.to_owned(),
+            });
+        }
+        if channel.users.iter().any(|user| user == nickname) {
+            self.kick(channel_name, nickname)?;
+        }
+        Ok(())
+    }
+
+    /// Disconnects a client connected to this server, like a QUIT with the kill message.
+    /// # Arguments
+    /// * `nickname` - The nickname of the client.
+    pub fn kill(&self, nickname: &str) -> Result<(), ServerError> {
+        match read_lock_clients(&self.session)?.get(nickname) {
+            Some(client) if client.connected => (),
+            Some(_) => return Err(ServerError::ClientNotConnected),
+            None => return Err(ServerError::ClientNotFound),
+        }
+        println!("Disconnecting {} by the admin", nickname);
+        let message = Message::new(
+            None,
+            MessageType::Quit,
+            vec![],
+            Some(KILL_MESSAGE.to_string()),
+        );
+        handle_quit_command(
+            message,
+            nickname.to_string(),
+            &self.session,
+            &self.network,
+            &self.server_name,
+        )?;
+        self.session.publish(ServerEvent::ClientKilled {
+            nickname: nickname.to_string(),
+            killed_by: self.server_name.to_owned(),
+            reason: KILL_MESSAGE.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Returns the state of the server shown by the dashboard. Each lock is released before
+    /// acquiring the next one.
+    fn status(&self) -> Result<AdminStatus, ServerError> {
+        let now = get_current_timestamp();
+        let config = read_lock_config(&self.session)?;
+        let version = config.version.to_owned();
+        let uptime = now.saturating_sub(config.started);
+        drop(config);
+
+        let mut channels = read_lock_channels(&self.session)?
+            .values()
+            .map(|channel| ChannelRow {
+                name: channel.name.to_owned(),
+                topic: channel.topic.to_owned(),
+                modes: channel.get_mode_string(false),
+                users: channel.users.to_owned(),
+            })
+            .collect::<Vec<_>>();
+        channels.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut clients = read_lock_clients(&self.session)?
+            .values()
+            .map(|client| ClientRow {
+                nickname: client.nickname.to_owned(),
+                username: client.username.to_owned(),
+                hostname: client.hostname.to_owned(),
+                connected: client.connected,
+                away: client.away_message.is_some(),
+                modes: client.modes.iter().map(|mode| mode.to_string()).collect(),
+                idle: if client.connected {
+                    client.get_idle_time(now)
+                } else {
+                    0
+                },
+            })
+            .collect::<Vec<_>>();
+        clients.sort_by(|a, b| a.nickname.cmp(&b.nickname));
+
+        let network_clients = read_lock_network_clients(&self.network)?.len();
+        let connections = lock_connections(&self.session)?.values().sum();
+        let rejected_connections = lock_rejected_connections(&self.session)?
+            .iter()
+            .map(|(reason, rejected)| (reason.to_owned(), *rejected))
+            .collect::<BTreeMap<_, _>>();
+        let mut links = read_lock_servers(&self.network)?
+            .iter()
+            .map(|(name, server)| LinkRow {
+                name: name.to_owned(),
+                hopcount: server.hopcount,
+                latency: server.latency,
+            })
+            .collect::<Vec<_>>();
+        links.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(AdminStatus {
+            server_name: self.server_name.to_owned(),
+            version,
+            uptime,
+            connections,
+            rejected_connections,
+            network_clients,
+            clients,
+            channels,
+            links,
+            events: self.events.lines(),
+        })
+    }
+
+    /// Returns the processing time of each command handled by the server, in microseconds,
+    /// so the slow handlers can be found.
+    fn metrics(&self) -> AdminMetrics {
+        let commands = self
+            .session
+            .command_metrics
+            .snapshot()
+            .into_iter()
+            .map(|(command, histogram)| CommandRow {
+                count: histogram.count,
+                mean: histogram.mean(),
+                p50: histogram.percentile(50),
+                p95: histogram.percentile(95),
+                p99: histogram.percentile(99),
+                max: histogram.max,
+                command,
+            })
+            .collect();
+        AdminMetrics {
+            server_name: self.server_name.to_owned(),
+            commands,
+        }
+    }
+}
+
+/// Returns true if the request carries the token, as a bearer token or in the cookie set by
+/// the login. The token is never read from the query, so it isn't left in the history of the
+/// browser or in the logs. An empty token authorizes nothing.
+/// # Arguments
+/// * `request` - The request received.
+/// * `token` - The token of the configuration.
+pub fn is_authorized(request: &HttpRequest, token: &str) -> bool {
+    if token.is_empty() {
+        return false;
+    }
+    let bearer = request
+        .header("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let received = match (bearer, request.cookie(TOKEN_COOKIE)) {
+        (Some(received), _) => received.trim().to_string(),
+        (None, Some(cookie)) => percent_decode(cookie),
+        (None, None) => return false,
+    };
+    tokens_match(&received, token)
+}
+
+/// Returns the response to the login form. If the form sends the right token, the browser is
+/// given the cookie that authorizes the next requests and sent to the dashboard.
+/// # Arguments
+/// * `request` - The request received, with the token in its form.
+/// * `token` - The token of the configuration.
+fn login(request: &HttpRequest, token: &str) -> HttpResponse {
+    let received = request.form.get("token").map(String::as_str);
+    match received {
+        Some(received) if !token.is_empty() && tokens_match(received, token) => {
+            let mut response = HttpResponse::redirect("/");
+            response.headers.push((
+                "Set-Cookie".to_string(),
+                format!(
+                    "{}={}; Path=/; HttpOnly; SameSite=Strict",
+                    TOKEN_COOKIE,
+                    percent_encode(token)
+                ),
+            ));
+            response
+        }
+        _ => HttpResponse::error(401, "Unauthorized"),
+    }
+}
+
+/// Returns the page with the form that asks for the token.
+fn login_page() -> String {
+    "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Admin login</title></head><body>\n<form method=\"post\" action=\"/login\"><input type=\"password\" name=\"token\"><button>Log in</button></form>\n</body></html>\n".to_string()
+}
+
+/// Compares the tokens reading every byte, so that the time it takes doesn't tell how much of
+/// the token was right.
+fn tokens_match(received: &str, token: &str) -> bool {
+    if received.len() != token.len() {
+        return false;
+    }
+    received
+        .bytes()
+        .zip(token.bytes())
+        .fold(0, |difference, (a, b)| difference | (a ^ b))
+        == 0
+}
+
+/// Returns the response to an action that failed.
+fn error_response(error: ServerError) -> HttpResponse {
+    match error {
+        ServerError::InvalidParameters => HttpResponse::error(400, "Bad Request"),
+        ServerError::ChannelNotFound
+        | ServerError::ClientNotFound
+        | ServerError::UserNotInChannel => HttpResponse::error(404, "Not Found"),
+        ServerError::ClientNotConnected => HttpResponse::error(409, "Conflict"),
+        e => {
+            println!("Error running admin action: {:?}", e);
+            HttpResponse::error(500, "Internal Server Error")
+        }
+    }
+}
+
+/// Returns the time of the day of the timestamp, like `18:05:12`, in UTC.
+fn format_time(seconds: u64) -> String {
+    let time = seconds % SECONDS_PER_DAY;
+    format!(
+        "{:02}:{:02}:{:02}",
+        time / 3600,
+        (time % 3600) / 60,
+        time % 60
+    )
+}
+
+/// Subscriber that keeps the last events of the server as lines of a log, dropping the oldest
+/// ones. The text of the messages sent by the clients is not kept.
+/// # Fields
+/// * `capacity` - The amount of lines kept.
+/// * `lines` - The lines of the events, from the oldest.
+pub struct RecentEvents {
+    capacity: usize,
+    lines: Mutex<VecDeque<String>>,
+}
+
+impl RecentEvents {
+    /// Creates the log of recent events, empty.
+    /// # Arguments
+    /// * `capacity` - The amount of lines kept.
+    pub fn new(capacity: usize) -> RecentEvents {
+        RecentEvents {
+            capacity,
+            lines: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Returns the lines of the recent events, from the oldest.
+    pub fn lines(&self) -> Vec<String> {
+        match self.lines.lock() {
+            Ok(lines) => lines.iter().cloned().collect(),
+            Err(poisoned) => poisoned.into_inner().iter().cloned().collect(),
+        }
+    }
+
+    /// Adds a line, dropping the oldest one if the log is full.
+    fn push(&self, line: String) {
+        let mut lines = match self.lines.lock() {
+            Ok(lines) => lines,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if lines.len() >= self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+}
+
+impl Subscriber for RecentEvents {
+    fn notify(&self, _session: &Session, event: &ServerEvent) {
+        let text = match event {
+            ServerEvent::ClientRegistered {
+                nickname,
+                username,
+                hostname,
+            } => format!("{} registered ({}@{})", nickname, username, hostname),
+            ServerEvent::ClientQuit { nickname, reason } => match reason {
+                Some(reason) => format!("{} quit ({})", nickname, reason),
+                None => format!("{} quit", nickname),
+            },
+            ServerEvent::ClientDisconnected { nickname } => format!("{} disconnected", nickname),
+            ServerEvent::ChannelJoined { channel, nickname } => {
+                format!("{} joined {}", nickname, channel)
+            }
+            ServerEvent::ChannelParted { channel, nickname } => {
+                format!("{} left {}", nickname, channel)
+            }
+            ServerEvent::ClientKicked {
+                channel,
+                nickname,
+                kicked_by,
+            } => format!("{} kicked {} from {}", kicked_by, nickname, channel),
+            ServerEvent::MessageSent { sender, target, .. } => {
+                format!("{} sent a message to {}", sender, target)
+            }
+            ServerEvent::ModeChanged {
+                target,
+                modes,
+                set_by,
+            } => format!("{} set {} on {}", set_by, modes, target),
+            ServerEvent::TopicChanged {
+                channel, set_by, ..
+            } => format!("{} changed the topic of {}", set_by, channel),
+            ServerEvent::OperatorGranted { nickname } => {
+                format!("{} became an operator", nickname)
+            }
+            ServerEvent::ClientKilled {
+                nickname,
+                killed_by,
+                ..
+            } => format!("{} killed {}", killed_by, nickname),
+            ServerEvent::ServerQuit { server, .. } => format!("{} left the network", server),
+        };
+        self.push(format!("{} {}", format_time(get_current_timestamp()), text));
+    }
+}
+
+/// Struct that holds the state of the server shown by the dashboard.
+#[derive(Serialize)]
+struct AdminStatus {
+    #[serde(rename = "server")]
+    server_name: String,
+    version: String,
+    uptime: u64,
+    connections: usize,
+    rejected_connections: BTreeMap<String, usize>,
+    network_clients: usize,
+    clients: Vec<ClientRow>,
+    channels: Vec<ChannelRow>,
+    links: Vec<LinkRow>,
+    events: Vec<String>,
+}
+
+/// Struct that holds what the dashboard shows of a client of this server.
+#[derive(Serialize)]
+struct ClientRow {
+    nickname: String,
+    username: String,
+    hostname: String,
+    connected: bool,
+    away: bool,
+    modes: String,
+    idle: u64,
+}
+
+/// Struct that holds what the dashboard shows of a channel.
+#[derive(Serialize)]
+struct ChannelRow {
+    name: String,
+    topic: String,
+    modes: String,
+    users: Vec<String>,
+}
+
+/// Struct that holds what the dashboard shows of a server linked to this one.
+#[derive(Serialize)]
+struct LinkRow {
+    name: String,
+    hopcount: u8,
+    latency: Option<u64>,
+}
+
+/// Struct that holds the processing time of each command handled by the server, in
+/// microseconds.
+#[derive(Serialize)]
+struct AdminMetrics {
+    #[serde(rename = "server")]
+    server_name: String,
+    commands: Vec<CommandRow>,
+}
+
+/// Struct that holds the processing time of the requests of a command.
+#[derive(Serialize)]
+struct CommandRow {
+    command: String,
+    count: u64,
+    mean: u64,
+    p50: u64,
+    p95: u64,
+    p99: u64,
+    max: u64,
+}
+
+impl AdminStatus {
+    /// Returns the dashboard page, with the forms of the actions.
+    fn to_html(&self) -> String {
+        let mut page = format!(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{} admin</title></head><body>\n",
+            escape_html(&self.server_name)
+        );
+        page.push_str(&format!(
+            "<h1>{}</h1>\n<p>{} - up for {}s - {} connections - {} rejected connections - {} clients in the network</p>\n",
+            escape_html(&self.server_name),
+            escape_html(&self.version),
+            self.uptime,
+            self.connections,
+            self.rejected_connections.values().sum::<usize>(),
+            self.network_clients
+        ));
+
+        page.push_str("<h2>Clients</h2>\n<table><tr><th>Nickname</th><th>User</th><th>Modes</th><th>State</th><th>Idle</th><th></th></tr>\n");
+        for client in self.clients.iter() {
+            let state = match (client.connected, client.away) {
+                (false, _) => "offline",
+                (true, true) => "away",
+                (true, false) => "online",
+            };
+            let kill = if client.connected {
+                action_form("/kill", &[("nickname", &client.nickname)], "Kill")
+            } else {
+                String::new()
+            };
+            page.push_str(&format!(
+                "<tr><td>{}</td><td>{}@{}</td><td>+{}</td><td>{}</td><td>{}s</td><td>{}</td></tr>\n",
+                escape_html(&client.nickname),
+                escape_html(&client.username),
+                escape_html(&client.hostname),
+                escape_html(&client.modes),
+                state,
+                client.idle,
+                kill
+            ));
+        }
+        page.push_str("</table>\n");
+
+        page.push_str("<h2>Channels</h2>\n<table><tr><th>Channel</th><th>Modes</th><th>Topic</th><th>Members</th></tr>\n");
+        for channel in self.channels.iter() {
+            let members = channel
+                .users
+                .iter()
+                .map(|user| {
+                    let params = [("channel", channel.name.as_str()), ("nickname", user)];
+                    format!(
+                        "{} {}{}",
+                        escape_html(user),
+                        action_form("/kick", &params, "Kick"),
+                        action_form("/ban", &params, "Ban")
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("<br>");
+            page.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                escape_html(&channel.name),
+                escape_html(&channel.modes),
+                escape_html(&channel.topic),
+                members
+            ));
+        }
+        page.push_str("</table>\n");
+
+        page.push_str("<h2>Server links</h2>\n<ul>\n");
+        for link in self.links.iter() {
+            let latency = match link.latency {
+                Some(latency) => format!(", {}ms", latency),
+                None => String::new(),
+            };
+            page.push_str(&format!(
+                "<li>{} ({} hops{})</li>\n",
+                escape_html(&link.name),
+                link.hopcount,
+                latency
+            ));
+        }
+        page.push_str("</ul>\n<h2>Recent events</h2>\n<pre>\n");
+        for line in self.events.iter().rev() {
+            page.push_str(&format!("{}\n", escape_html(line)));
+        }
+        page.push_str("</pre>\n</body></html>\n");
+        page
+    }
+}
+
+/// Returns the form of a button that runs an action of the admin interface.
+/// # Arguments
+/// * `action` - The path of the action.
+/// * `params` - The parameters of the action.
+/// * `label` - The text of the button.
+fn action_form(action: &str, params: &[(&str, &str)], label: &str) -> String {
+    let mut form = format!(
+        "<form method=\"post\" action=\"{}\" style=\"display:inline\">",
+        action
+    );
+    for (name, value) in params {
+        form.push_str(&format!(
+            "<input type=\"hidden\" name=\"{}\" value=\"{}\">",
+            name,
+            escape_html(value)
+        ));
+    }
+    form.push_str(&format!("<button>{}</button></form>", label));
+    form
+}
+
+#[cfg(test)]
+mod admin_tests {
+    use std::{
+        collections::HashMap,
+        net::TcpListener,
+        sync::{Arc, RwLock},
+        time::Duration,
+    };
+
+    use model::{
+        channel::Channel,
+        events::{ServerEvent, Subscriber},
+        link_queues::LinkQueues,
+        network::Network,
+        persistence::PersistenceType,
+        server::Server,
+        session::Session,
+    };
+
+    use super::{Admin, RecentEvents};
+    use crate::{
+        commands::command_utils::{create_client_for_test, create_session_for_test},
+        database::{handle_database, DatabasePaths},
+        http::HttpRequest,
+        locks::{read_lock_channels, write_lock_channels, write_lock_config},
+    };
+
+    fn create_admin_for_test() -> (Admin, Session) {
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        write_lock_config(&session).unwrap().admin_token = "secret".to_string();
+        let network = Network {
+            server: Arc::new(RwLock::new(Server::new_main_server(
+                "127.0.0.1".to_string(),
+                "0".to_string(),
+                "test".to_string(),
+            ))),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        };
+        let admin = Admin::new(session.clone(), network, "test".to_string());
+        (admin, session)
+    }
+
+    fn request(
+        method: &str,
+        path: &str,
+        headers: &[(&str, &str)],
+        form: &[(&str, &str)],
+    ) -> HttpRequest {
+        HttpRequest {
+            method: method.to_string(),
+            path: path.to_string(),
+            query: HashMap::new(),
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            form: form
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_requests_without_the_token_are_unauthorized() {
+        let (admin, _session) = create_admin_for_test();
+
+        let missing = admin.respond(&request("GET", "/api/status", &[], &[]));
+        let wrong = admin.respond(&request(
+            "GET",
+            "/api/status",
+            &[("authorization", "Bearer secreT")],
+            &[],
+        ));
+        let right = admin.respond(&request(
+            "GET",
+            "/api/status",
+            &[("authorization", "Bearer secret")],
+            &[],
+        ));
+
+        assert_eq!(missing.status, 401);
+        assert_eq!(wrong.status, 401);
+        assert_eq!(right.status, 200);
+        assert!(right.body.starts_with(r#"{"server":"test","#));
+    }
+
+    #[test]
+    fn test_token_is_only_taken_from_the_login_cookie_or_the_header() {
+        let (admin, _session) = create_admin_for_test();
+        let mut in_query = request("GET", "/api/metrics", &[], &[]);
+        in_query
+            .query
+            .insert("token".to_string(), "secret".to_string());
+
+        let wrong_login = admin.respond(&request("POST", "/login", &[], &[("token", "secreT")]));
+        let login = admin.respond(&request("POST", "/login", &[], &[("token", "secret")]));
+        let dashboard = admin.respond(&request("GET", "/", &[], &[]));
+        let with_cookie = admin.respond(&request(
+            "GET",
+            "/api/metrics",
+            &[("cookie", "theme=dark; admin_token=secret")],
+            &[],
+        ));
+
+        assert_eq!(admin.respond(&in_query).status, 401);
+        assert_eq!(wrong_login.status, 401);
+        assert_eq!(login.status, 303);
+        assert!(login.headers.contains(&(
+            "Set-Cookie".to_string(),
+            "admin_token=secret; Path=/; HttpOnly; SameSite=Strict".to_string()
+        )));
+        assert_eq!(dashboard.status, 303);
+        assert!(dashboard
+            .headers
+            .contains(&("Location".to_string(), "/login".to_string())));
+        assert_eq!(with_cookie.status, 200);
+    }
+
+    #[test]
+    fn test_kick_removes_the_user_from_the_channel() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (admin, session) = create_admin_for_test();
+        create_client_for_test(&session, addr.to_owned(), "alice".to_string());
+        create_client_for_test(&session, addr, "bob".to_string());
+        let mut channel = Channel::new(
+            "&rust".to_string(),
+            "".to_string(),
+            vec!["alice".to_string(), "bob".to_string()],
+        );
+        channel.operators.push("alice".to_string());
+        write_lock_channels(&session)
+            .unwrap()
+            .insert(channel.name.to_owned(), channel);
+
+        let response = admin.respond(&request(
+            "POST",
+            "/kick",
+            &[("cookie", "admin_token=secret")],
+            &[("channel", "&rust"), ("nickname", "bob")],
+        ));
+        let again = admin.respond(&request(
+            "POST",
+            "/kick",
+            &[("cookie", "admin_token=secret")],
+            &[("channel", "&rust"), ("nickname", "bob")],
+        ));
+
+        assert_eq!(response.status, 303);
+        assert!(response
+            .headers
+            .contains(&("Location".to_string(), "/".to_string())));
+        assert_eq!(again.status, 404);
+        let channels = read_lock_channels(&session).unwrap();
+        assert_eq!(channels.get("&rust").unwrap().users, vec!["alice"]);
+        let events = admin.events.lines();
+        assert!(events
+            .last()
+            .unwrap()
+            .ends_with("test kicked bob from &rust"));
+    }
+
+    #[test]
+    fn test_recent_events_keep_the_last_lines_without_the_messages() {
+        let (_admin, session) = create_admin_for_test();
+        let events = RecentEvents::new(2);
+        for target in ["#a", "#b", "#c"] {
+            events.notify(
+                &session,
+                &ServerEvent::MessageSent {
+                    sender: "alice".to_string(),
+                    target: target.to_string(),
+                    message: "private text".to_string(),
+                },
+            );
+        }
+
+        let lines = events.lines();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("alice sent a message to #b"));
+        assert!(lines[1].ends_with("alice sent a message to #c"));
+        assert!(!lines.iter().any(|line| line.contains("private text")));
+    }
+
+    #[test]
+    fn test_metrics_show_the_processing_time_of_the_commands() {
+        let (admin, session) = create_admin_for_test();
+        session
+            .command_metrics
+            .record("JOIN", Duration::from_micros(1_500));
+
+        let response = admin.respond(&request(
+            "GET",
+            "/api/metrics",
+            &[("authorization", "Bearer secret")],
+            &[],
+        ));
+
+        assert_eq!(response.status, 200);
+        assert_eq!(
+            response.body,
+            r#"{"server":"test","commands":[{"command":"JOIN","count":1,"mean":1500,"p50":1500,"p95":1500,"p99":1500,"max":1500}]}"#
+        );
+    }
+}
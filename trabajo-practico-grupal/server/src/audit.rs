@@ -0,0 +1,236 @@
+use std::path::Path;
+
+use model::{
+    events::{ServerEvent, Subscriber},
+    json::{FromJson, ToJson},
+    persistence::PersistenceType,
+    session::Session,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    commands::command_utils::get_current_timestamp, database::inform_database,
+    server_errors::ServerError,
+};
+
+/// Struct that represents an action of an operator or of the server, as it is saved in the
+/// audit log.
+/// # Fields
+/// * `time`: The time, in seconds since the epoch, when the action was done.
+/// * `action`: The kind of the action, like `KICK` or `BAN`.
+/// * `actor`: The nickname of the operator, or the name of the server, that did the action.
+/// * `target`: The client, channel or server the action was done to.
+/// * `parameters`: The rest of the action, like the modes changed or the reason of a kill.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub time: u64,
+    pub action: String,
+    pub actor: String,
+    pub target: String,
+    pub parameters: String,
+}
+
+impl AuditRecord {
+    /// Returns the record of the event, if it is one of the actions that are audited: OPER,
+    /// KILL, KICK, MODE, the bans and SQUIT.
+    /// # Arguments
+    /// * `event` - The event published by the server.
+    /// * `time` - The time of the event, in seconds since the epoch.
+    pub fn from_event(event: &ServerEvent, time: u64) -> Option<AuditRecord> {
+        let (action, actor, target, parameters) = match event {
+            ServerEvent::OperatorGranted { nickname } => {
+                ("OPER", nickname, nickname, String::new())
+            }
+            ServerEvent::ClientKilled {
+                nickname,
+                killed_by,
+                reason,
+            } => ("KILL", killed_by, nickname, reason.to_owned()),
+            ServerEvent::ClientKicked {
+                channel,
+                nickname,
+                kicked_by,
+            } => ("KICK", kicked_by, channel, nickname.to_owned()),
+            ServerEvent::ModeChanged {
+                target,
+                modes,
+                set_by,
+            } => {
+                let action = if modes.starts_with("+b ") {
+                    "BAN"
+                } else if modes.starts_with("-b ") {
+                    "UNBAN"
+                } else {
+                    "MODE"
+                };
+                (action, set_by, target, modes.to_owned())
+            }
+            ServerEvent::ServerQuit { server, reason } => (
+                "SQUIT",
+                server,
+                server,
+                reason.to_owned().unwrap_or_default(),
+            ),
+            _ => return None,
+        };
+        Some(AuditRecord {
+            time,
+            action: action.to_string(),
+            actor: actor.to_owned(),
+            target: target.to_owned(),
+            parameters,
+        })
+    }
+}
+
+/// Subscriber that appends the actions of the operators and of the server to the audit log,
+/// through the database, apart from the debug output of the server.
+pub struct AuditLog;
+
+impl Subscriber for AuditLog {
+    fn notify(&self, session: &Session, event: &ServerEvent) {
+        let record = match AuditRecord::from_event(event, get_current_timestamp()) {
+            Some(record) => record,
+            None => return,
+        };
+        let data = record.to_json();
+        if let Err(e) = inform_database(PersistenceType::AuditRecord, data, session) {
+            println!("Error saving audit record: {:?}", e);
+        }
+    }
+}
+
+/// Reads a page of the audit log, with the newest records first, and returns it with the
+/// amount of pages of the log, which is at least one. The lines that are not a record are
+/// skipped, and a log that doesn't exist yet has no records.
+/// # Arguments
+/// * `file` - The file of the audit log.
+/// * `page` - The number of the page, starting from 1.
+/// * `page_size` - The amount of records of each page.
+pub fn read_audit_page(
+    file: &Path,
+    page: usize,
+    page_size: usize,
+) -> Result<(Vec<AuditRecord>, usize), ServerError> {
+    if !file.exists() {
+        return Ok((vec![], 1));
+    }
+    let log = std::fs::read_to_string(file)?;
+    let mut records = log
+        .lines()
+        .filter_map(|line| AuditRecord::from_json(line).ok())
+        .collect::<Vec<_>>();
+    records.reverse();
+    let pages = records.len().div_ceil(page_size).max(1);
+    let records = records
+        .into_iter()
+        .skip(page.saturating_sub(1) * page_size)
+        .take(page_size)
+        .collect();
+    Ok((records, pages))
+}
+
+#[cfg(test)]
+mod audit_tests {
+    use model::{
+        events::{ServerEvent, Subscriber},
+        json::ToJson,
+        persistence::PersistenceType,
+    };
+
+    use super::{read_audit_page, AuditLog, AuditRecord};
+    use crate::{
+        commands::command_utils::create_session_for_test,
+        database::{append_audit_record, DatabasePaths},
+    };
+
+    fn record_for_test(action: &str, time: u64) -> AuditRecord {
+        AuditRecord {
+            time,
+            action: action.to_string(),
+            actor: "op".to_string(),
+            target: "#rust".to_string(),
+            parameters: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_actions_of_the_operators_are_audited() {
+        let ban = ServerEvent::ModeChanged {
+            target: "#rust".to_string(),
+            modes: "+b bob".to_string(),
+            set_by: "alice".to_string(),
+        };
+        let kill = ServerEvent::ClientKilled {
+            nickname: "bob".to_string(),
+            killed_by: "test".to_string(),
+            reason: "Flooding".to_string(),
+        };
+        let join = ServerEvent::ChannelJoined {
+            channel: "#rust".to_string(),
+            nickname: "bob".to_string(),
+        };
+
+        assert_eq!(
+            AuditRecord::from_event(&ban, 7),
+            Some(AuditRecord {
+                time: 7,
+                action: "BAN".to_string(),
+                actor: "alice".to_string(),
+                target: "#rust".to_string(),
+                parameters: "+b bob".to_string(),
+            })
+        );
+        let kill = AuditRecord::from_event(&kill, 8).unwrap();
+        assert_eq!(
+            (
+                kill.action.as_str(),
+                kill.actor.as_str(),
+                kill.target.as_str()
+            ),
+            ("KILL", "test", "bob")
+        );
+        assert_eq!(AuditRecord::from_event(&join, 9), None);
+    }
+
+    #[test]
+    fn test_subscriber_sends_the_records_to_the_database() {
+        let (tx, rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        let session = create_session_for_test(tx);
+
+        AuditLog.notify(
+            &session,
+            &ServerEvent::OperatorGranted {
+                nickname: "alice".to_string(),
+            },
+        );
+
+        let (persistence_type, data) = rx.try_recv().unwrap();
+        assert!(matches!(persistence_type, PersistenceType::AuditRecord));
+        assert!(data.contains(r#""action":"OPER","actor":"alice","target":"alice""#));
+    }
+
+    #[test]
+    fn test_pages_have_the_newest_records_first() {
+        let paths = DatabasePaths::temporary().unwrap();
+        for time in 1..=5 {
+            let record = record_for_test("MODE", time);
+            append_audit_record(record.to_json(), &paths).unwrap();
+        }
+        append_audit_record("not a record".to_string(), &paths).unwrap();
+
+        let (first, pages) = read_audit_page(&paths.audit, 1, 2).unwrap();
+        let (last, _) = read_audit_page(&paths.audit, 3, 2).unwrap();
+        let (after, _) = read_audit_page(&paths.audit, 4, 2).unwrap();
+
+        assert_eq!(pages, 3);
+        assert_eq!(
+            first.iter().map(|record| record.time).collect::<Vec<_>>(),
+            vec![5, 4]
+        );
+        assert_eq!(last, vec![record_for_test("MODE", 1)]);
+        assert!(after.is_empty());
+        let missing = paths.audit.with_file_name("missing.txt");
+        assert_eq!(read_audit_page(&missing, 1, 2).unwrap(), (vec![], 1));
+    }
+}
@@ -0,0 +1,158 @@
+use std::{thread, time::Duration};
+
+use model::{events::ServerEvent, json::ToJson, persistence::PersistenceType, session::Session};
+
+use crate::locks::write_lock_channels;
+use crate::{
+    commands::{command_utils::get_current_timestamp, mode::inform_channel_about_mode},
+    database::inform_database,
+    server_errors::ServerError,
+};
+
+static BAN_SWEEP_INTERVAL: u64 = 30;
+
+/// Function that periodically removes the timed bans that expired.
+/// # Arguments
+/// * `session` - The session of the current server.
+/// * `server_name` - The name of the current server, used as the sender of the notifications.
+pub fn handle_ban_expiry(session: Session, server_name: String) {
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(BAN_SWEEP_INTERVAL));
+        match remove_expired_bans(&session, &server_name, get_current_timestamp()) {
+            Ok(_) => (),
+            Err(e) => println!("Error removing expired bans: {:?}", e),
+        }
+    });
+}
+
+/// Removes the bans that expired from every channel.
+/// Each channel changed is persisted, and once the channels are released its members are
+/// informed of the bans removed and the removals are published as mode changes of the server.
+/// A channel that can't be persisted or informed is logged and doesn't stop the others.
+/// # Arguments
+/// * `session` - The session of the current server.
+/// * `server_name` - The name of the current server, used as the sender of the notifications.
+/// * `now` - The current time, in seconds since the epoch.
+pub fn remove_expired_bans(
+    session: &Session,
+    server_name: &str,
+    now: u64,
+) -> Result<(), ServerError> {
+    let mut changed = vec![];
+    let mut channels = write_lock_channels(session)?;
+    for channel in channels.values_mut() {
+        let (expired, bans) = channel
+            .banned_users
+            .drain(..)
+            .partition::<Vec<_>, _>(|ban| ban.is_expired(now));
+        channel.banned_users = bans;
+        if expired.is_empty() {
+            continue;
+        }
+        if let Err(e) = inform_database(
+            PersistenceType::ChannelUpdate(channel.name.to_owned()),
+            channel.to_json(),
+            session,
+        ) {
+            println!("Error saving the expired bans of {}: {:?}", channel.name, e);
+        }
+        let modes = expired
+            .iter()
+            .map(|ban| format!("-b {}", ban.nickname))
+            .collect::<Vec<_>>();
+        changed.push((channel.clone(), modes));
+    }
+    drop(channels);
+
+    for (channel, modes) in changed {
+        for modes in modes {
+            println!("Ban in {:?} expired: {}", channel.name, modes);
+            if let Err(e) =
+                inform_channel_about_mode(&channel, modes.to_owned(), server_name, session)
+            {
+                println!(
+                    "Error informing the expired ban in {}: {:?}",
+                    channel.name, e
+                );
+            }
+            session.publish(ServerEvent::ModeChanged {
+                target: channel.name.to_owned(),
+                modes,
+                set_by: server_name.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod ban_expiry_tests {
+    use model::consts::MAX_MSG_SIZE;
+    use std::{io::Read, net::TcpListener};
+
+    use model::{
+        ban::Ban,
+        channel::Channel,
+        persistence::PersistenceType,
+        responses::{message::MessageResponse, response::Response},
+    };
+
+    use crate::locks::{read_lock_channels, write_lock_channels};
+    use crate::{
+        ban_expiry::remove_expired_bans,
+        commands::command_utils::{create_client_for_test, create_session_for_test},
+        database::{handle_database, DatabasePaths},
+    };
+
+    #[test]
+    fn test_remove_expired_bans() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr, "member".to_string());
+        let (mut reader, _) = listener.accept().unwrap();
+
+        let mut channel = Channel::new(
+            "#channel_test".to_string(),
+            "".to_string(),
+            vec!["member".to_string()],
+        );
+        let mut expired = Ban::new("expired".to_string(), "member".to_string(), 100);
+        expired.expires_at = Some(200);
+        let mut timed = Ban::new("timed".to_string(), "member".to_string(), 100);
+        timed.expires_at = Some(400);
+        let permanent = Ban::new("permanent".to_string(), "member".to_string(), 100);
+        channel.banned_users = vec![expired, timed, permanent];
+        write_lock_channels(&session)
+            .unwrap()
+            .insert(channel.name.clone(), channel);
+
+        let result = remove_expired_bans(&session, "test", 300);
+
+        assert!(result.is_ok());
+        let channels = read_lock_channels(&session).unwrap();
+        let channel = &channels["#channel_test"];
+        assert!(channel
+            .banned_users
+            .iter()
+            .all(|ban| ban.nickname != "expired"));
+        assert!(channel.is_banned("timed", 300));
+        assert!(channel.is_banned("permanent", 300));
+        drop(channels);
+
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        match Response::serialize(String::from_utf8(msg).unwrap()).unwrap() {
+            Response::MessageResponse {
+                response: MessageResponse::ChannelModeMsg { sender, modes, .. },
+            } => {
+                assert_eq!(sender, "test");
+                assert_eq!(modes, "-b expired");
+            }
+            _ => panic!("Expected a channel mode notification"),
+        }
+    }
+}
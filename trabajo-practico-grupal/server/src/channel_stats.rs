@@ -0,0 +1,85 @@
+use model::{
+    consts::is_channel_name,
+    events::{ServerEvent, Subscriber},
+    session::Session,
+};
+
+use crate::{
+    commands::command_utils::get_current_timestamp, locks::write_lock_channels,
+    server_errors::ServerError,
+};
+
+/// Subscriber that counts the messages sent to each channel in its statistics, the messages of
+/// the clients of this server and the ones relayed by the other servers alike.
+pub struct MessageCounter;
+
+impl Subscriber for MessageCounter {
+    fn notify(&self, session: &Session, event: &ServerEvent) {
+        if let ServerEvent::MessageSent { sender, target, .. } = event {
+            if !is_channel_name(target) {
+                return;
+            }
+            if let Err(e) = count_message(session, target, sender) {
+                println!("Error counting message to {}: {:?}", target, e);
+            }
+        }
+    }
+}
+
+/// Counts a message in the statistics of the channel.
+/// # Arguments
+/// * `session` - The session of the current server.
+/// * `channel_name` - The channel the message was sent to.
+/// * `nickname` - The nickname of the sender.
+fn count_message(session: &Session, channel_name: &str, nickname: &str) -> Result<(), ServerError> {
+    let mut channels = write_lock_channels(session)?;
+    match channels.get_mut(channel_name) {
+        Some(channel) => channel
+            .stats
+            .record_message(nickname, get_current_timestamp()),
+        None => return Err(ServerError::ChannelNotFound),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod channel_stats_tests {
+    use model::{
+        channel::Channel,
+        events::{ServerEvent, Subscriber},
+        persistence::PersistenceType,
+    };
+
+    use super::MessageCounter;
+    use crate::{
+        commands::command_utils::create_session_for_test,
+        locks::{read_lock_channels, write_lock_channels},
+    };
+
+    fn message_for_test(target: &str) -> ServerEvent {
+        ServerEvent::MessageSent {
+            sender: "alice".to_string(),
+            target: target.to_string(),
+            message: "hello".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_messages_to_channels_are_counted() {
+        let (tx, _rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        let session = create_session_for_test(tx);
+        let channel = Channel::new("#rust".to_string(), "".to_string(), vec![]);
+        write_lock_channels(&session)
+            .unwrap()
+            .insert(channel.name.to_owned(), channel);
+
+        MessageCounter.notify(&session, &message_for_test("#rust"));
+        MessageCounter.notify(&session, &message_for_test("#rust"));
+        MessageCounter.notify(&session, &message_for_test("bob"));
+
+        let channels = read_lock_channels(&session).unwrap();
+        let stats = &channels["#rust"].stats;
+        assert_eq!(stats.messages, 2);
+        assert_eq!(stats.top_talkers(1), vec![("alice".to_string(), 2)]);
+    }
+}
@@ -0,0 +1,534 @@
+use std::{collections::HashSet, thread, time::Duration};
+
+use model::{
+    channel::Channel,
+    channel_stats::ChannelStats,
+    consts::{MAX_MSG_SIZE, NETWORK_CHANNEL_PREFIX},
+    message::Message,
+    network::Network,
+    responses::message::MessageResponse,
+    session::Session,
+};
+
+use crate::{
+    commands::command_utils::get_current_timestamp,
+    locks::{read_lock_channels, read_lock_clients, read_lock_server, write_lock_channels},
+    server_errors::ServerError,
+    socket::{inform_client, inform_network, inform_server},
+};
+
+static CHECKSUM_INTERVAL: u64 = 60;
+/// The bound of a range of members that leaves it open, before the first or after the last.
+static UNBOUNDED: &str = "*";
+static JOINED: &str = "+";
+static LEFT: &str = "-";
+
+/// Function that periodically sends the checksums of the members of the distributed channels
+/// to the servers linked to this one, so that the channels that drifted are synchronized.
+/// # Arguments
+/// * `session` - The session of the current server.
+/// * `network` - The struct that contains the information of the network.
+pub fn handle_channel_sync(session: Session, network: Network) {
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(CHECKSUM_INTERVAL));
+        if let Err(e) = send_channel_checksums(&session, &network) {
+            println!("Error sending channel checksums: {:?}", e);
+        }
+    });
+}
+
+/// Sends `CHANSUM <channel> <checksum>` to the father and the children of this server for each
+/// distributed channel, with the checksum of its members in hexadecimal.
+/// # Arguments
+/// * `session` - The session of the current server.
+/// * `network` - The struct that contains the information of the network.
+pub fn send_channel_checksums(session: &Session, network: &Network) -> Result<(), ServerError> {
+    let messages = read_lock_channels(session)?
+        .values()
+        .filter(|channel| channel.name.starts_with(NETWORK_CHANNEL_PREFIX))
+        .map(|channel| format!("CHANSUM {} {:x}", channel.name, channel.members_checksum()))
+        .collect::<Vec<_>>();
+    for message in messages {
+        inform_network(network, &String::new(), &message)?;
+    }
+    Ok(())
+}
+
+/// Handles the CHANSUM message that a linked server sends with the checksum of the members of
+/// one of its channels. A channel this server doesn't have counts as one without members.
+/// When the checksums differ, the father of this server is the one whose members are right:
+/// they are asked to the father, or sent to the child that has a different checksum.
+/// # Arguments
+/// * `message` - The message received, like `CHANSUM #rust 5c2b9d0e1f3a4b67`.
+/// * `server_name` - The name of the server that sent the message.
+/// * `session` - The session of the current server.
+/// * `network` - The struct that contains the information of the network.
+/// # Errors
+/// ServerError::InvalidParameters if the message doesn't have a channel and a checksum.
+pub fn handle_chansum_command(
+    message: Message,
+    server_name: &str,
+    session: &Session,
+    network: &Network,
+) -> Result<(), ServerError> {
+    if message.parameters.len() < 2 {
+        return Err(ServerError::InvalidParameters);
+    }
+    let channel_name = &message.parameters[0];
+    let checksum = u64::from_str_radix(&message.parameters[1], 16)
+        .map_err(|_| ServerError::InvalidParameters)?;
+    let local = match read_lock_channels(session)?.get(channel_name) {
+        Some(channel) => channel.members_checksum(),
+        None => Channel::new(channel_name.to_owned(), "".to_string(), vec![]).members_checksum(),
+    };
+    if local == checksum {
+        return Ok(());
+    }
+    println!("Channel {} differs from {}", channel_name, server_name);
+    if is_father(network, server_name)? {
+        let request = format!("CHANSYNC {}", channel_name);
+        inform_server(network, &server_name.to_string(), &request)?;
+    } else {
+        send_members(channel_name, server_name, session, network)?;
+    }
+    Ok(())
+}
+
+/// Handles the CHANSYNC message, which synchronizes the members of a channel. It has three forms:
+/// * `CHANSYNC <channel>` -> A child asks for the members of the channel.
+/// * `CHANSYNC <channel> <after> <until> :<members>` -> The father sends the members whose
+///   nicknames are after `<after>` and up to `<until>`, which are `*` for the first and the last
+///   part of the members.
+/// * `CHANSYNC <channel> <+|-> :<nicknames>` -> The server of the clients reports that they are
+///   members, or that they left, and the report is relayed to the rest of the network.
+///
+/// The members that are clients of this server are always kept as this server knows them, and
+/// when the father is wrong about them, it is informed with a report.
+/// The members of this server are informed of the members that joined or left.
+/// # Arguments
+/// * `message` - The message received.
+/// * `server_name` - The name of the server that sent the message.
+/// * `session` - The session of the current server.
+/// * `network` - The struct that contains the information of the network.
+/// # Errors
+/// ServerError::InvalidParameters if the message doesn't have any of the forms.
+pub fn handle_chansync_command(
+    message: Message,
+    server_name: &str,
+    session: &Session,
+    network: &Network,
+) -> Result<(), ServerError> {
+    let nicknames = match &message.trailing {
+        Some(trailing) => trailing
+            .split_whitespace()
+            .map(str::to_string)
+            .collect::<Vec<_>>(),
+        None => vec![],
+    };
+    match message.parameters.as_slice() {
+        [channel_name] => {
+            if !is_father(network, server_name)? {
+                send_members(channel_name, server_name, session, network)?;
+            }
+        }
+        [channel_name, change] if change == JOINED || change == LEFT => {
+            update_members(session, channel_name, |users| {
+                let mut users = users
+                    .into_iter()
+                    .filter(|user| !nicknames.contains(user))
+                    .collect::<Vec<_>>();
+                if change == JOINED {
+                    users.extend(nicknames.iter().cloned());
+                }
+                users
+            })?;
+            let message = Message::deserialize(message.to_owned())?;
+            inform_network(network, &server_name.to_string(), &message)?;
+        }
+        [channel_name, after, until] => {
+            if !is_father(network, server_name)? {
+                return Ok(());
+            }
+            let range = MembersRange::new(after, until);
+            let (joined, left) = sync_members(session, channel_name, &range, &nicknames)?;
+            for (change, nicknames) in [(JOINED, joined), (LEFT, left)] {
+                if !nicknames.is_empty() {
+                    let report = format!(
+                        "CHANSYNC {} {} :{}",
+                        channel_name,
+                        change,
+                        nicknames.join(" ")
+                    );
+                    inform_server(network, &server_name.to_string(), &report)?;
+                }
+            }
+        }
+        _ => return Err(ServerError::InvalidParameters),
+    }
+    Ok(())
+}
+
+/// Struct that holds the range of nicknames covered by a part of the members of a channel.
+/// # Fields
+/// * `after`: The nickname before the range, none if it starts at the first one.
+/// * `until`: The last nickname of the range, none if it ends at the last one.
+#[derive(Debug)]
+struct MembersRange<'a> {
+    after: Option<&'a str>,
+    until: Option<&'a str>,
+}
+
+impl<'a> MembersRange<'a> {
+    fn new(after: &'a str, until: &'a str) -> MembersRange<'a> {
+        let bound = |nickname: &'a str| Some(nickname).filter(|n| *n != UNBOUNDED);
+        MembersRange {
+            after: bound(after),
+            until: bound(until),
+        }
+    }
+
+    fn contains(&self, nickname: &str) -> bool {
+        self.after.is_none_or(|after| nickname > after)
+            && self.until.is_none_or(|until| nickname <= until)
+    }
+}
+
+/// Returns true if the server is the father of the current server.
+fn is_father(network: &Network, server_name: &str) -> Result<bool, ServerError> {
+    Ok(matches!(
+        &read_lock_server(network)?.father,
+        Some((father_name, _)) if father_name == server_name
+    ))
+}
+
+/// Sends the members of the channel to a child, in as many CHANSYNC messages as they need.
+fn send_members(
+    channel_name: &str,
+    server_name: &str,
+    session: &Session,
+    network: &Network,
+) -> Result<(), ServerError> {
+    let mut members = match read_lock_channels(session)?.get(channel_name) {
+        Some(channel) => channel.users.to_owned(),
+        None => vec![],
+    };
+    members.sort();
+    for message in members_messages(channel_name, &members) {
+        inform_server(network, &server_name.to_string(), &message)?;
+    }
+    Ok(())
+}
+
+/// Splits the sorted members of a channel in the CHANSYNC messages that send them, each one with
+/// the range of nicknames it covers and short enough to be sent in one message.
+/// # Arguments
+/// * `channel_name` - The name of the channel.
+/// * `members` - The members of the channel, sorted.
+pub fn members_messages(channel_name: &str, members: &[String]) -> Vec<String> {
+    let format = |after: &str, until: &str, part: &[String]| {
+        format!(
+            "CHANSYNC {} {} {} :{}",
+            channel_name,
+            after,
+            until,
+            part.join(" ")
+        )
+    };
+    let mut messages = vec![];
+    let mut after = UNBOUNDED.to_string();
+    let mut part: Vec<String> = vec![];
+    for member in members {
+        part.push(member.to_owned());
+        if part.len() > 1 && format(&after, member, &part).len() > MAX_MSG_SIZE {
+            part.pop();
+            let until = part[part.len() - 1].to_owned();
+            messages.push(format(&after, &until, &part));
+            after = until;
+            part = vec![member.to_owned()];
+        }
+    }
+    messages.push(format(&after, UNBOUNDED, &part));
+    messages
+}
+
+/// Replaces the members of the channel in the range with the members received from the father,
+/// except for the clients of this server, which are kept as they are.
+/// Returns the clients of this server that the father doesn't know as members and the ones it
+/// wrongly knows as members.
+fn sync_members(
+    session: &Session,
+    channel_name: &str,
+    range: &MembersRange,
+    members: &[String],
+) -> Result<(Vec<String>, Vec<String>), ServerError> {
+    let local = read_lock_clients(session)?
+        .keys()
+        .cloned()
+        .collect::<HashSet<_>>();
+    let mut missing = vec![];
+    let mut wrong = vec![];
+    update_members(session, channel_name, |users| {
+        missing = users
+            .iter()
+            .filter(|user| local.contains(*user) && range.contains(user))
+            .filter(|user| !members.contains(user))
+            .cloned()
+            .collect();
+        wrong = members
+            .iter()
+            .filter(|member| local.contains(*member) && !users.contains(member))
+            .cloned()
+            .collect();
+        let mut synced = users
+            .into_iter()
+            .filter(|user| local.contains(user) || !range.contains(user))
+            .collect::<Vec<_>>();
+        synced.extend(
+            members
+                .iter()
+                .filter(|member| !local.contains(*member) && range.contains(member))
+                .cloned(),
+        );
+        synced
+    })?;
+    Ok((missing, wrong))
+}
+
+/// Changes the members of a distributed channel with the function received, which returns the
+/// new members from the current ones. The channel is created if it didn't exist, and removed if
+/// it has no members left.
+/// The members of this server are informed of the members that joined or left.
+fn update_members<F>(session: &Session, channel_name: &str, update: F) -> Result<(), ServerError>
+where
+    F: FnOnce(Vec<String>) -> Vec<String>,
+{
+    if !channel_name.starts_with(NETWORK_CHANNEL_PREFIX) {
+        return Err(ServerError::InvalidParameters);
+    }
+    let mut channels = write_lock_channels(session)?;
+    let users = match channels.get(channel_name) {
+        Some(channel) => channel.users.to_owned(),
+        None => vec![],
+    };
+    let synced = update(users.to_owned());
+    let joined = synced
+        .iter()
+        .filter(|user| !users.contains(user))
+        .cloned()
+        .collect::<Vec<_>>();
+    let left = users
+        .iter()
+        .filter(|user| !synced.contains(user))
+        .cloned()
+        .collect::<Vec<_>>();
+    if synced.is_empty() {
+        channels.remove(channel_name);
+    } else {
+        let channel = channels.entry(channel_name.to_string()).or_insert_with(|| {
+            let mut channel = Channel::new(channel_name.to_string(), "".to_string(), vec![]);
+            channel.stats = ChannelStats::new(get_current_timestamp());
+            println!("New distributed channel: {}", channel_name);
+            channel
+        });
+        channel.users = synced.to_owned();
+    }
+    drop(channels);
+
+    for nickname in joined.iter() {
+        let response = MessageResponse::JoinMsg {
+            channel: channel_name.to_string(),
+            nickname: nickname.to_owned(),
+        }
+        .to_string();
+        for user in synced.iter().filter(|user| *user != nickname) {
+            inform_client(session, user, &response)?;
+        }
+    }
+    for nickname in left.iter() {
+        let response = MessageResponse::PartMsg {
+            channel: channel_name.to_string(),
+            nickname: nickname.to_owned(),
+        }
+        .to_string();
+        for user in synced.iter() {
+            inform_client(session, user, &response)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod channel_sync_tests {
+    use model::consts::MAX_MSG_SIZE;
+    use std::{
+        collections::HashMap,
+        io::Read,
+        net::{TcpListener, TcpStream},
+        sync::{Arc, RwLock},
+    };
+
+    use model::{
+        channel::Channel, link_queues::LinkQueues, message::Message, network::Network,
+        persistence::PersistenceType, server::Server, session::Session,
+    };
+
+    use super::{handle_chansum_command, handle_chansync_command, members_messages};
+    use crate::{
+        commands::command_utils::{create_client_for_test, create_session_for_test},
+        locks::{read_lock_channels, write_lock_channels},
+    };
+
+    fn read_message_for_test(reader: &mut TcpStream) -> String {
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        String::from_utf8(msg).unwrap()
+    }
+
+    /// Creates a child server with the local client `alice` in `#rust`, along with `bob`, and
+    /// returns the ends where its father and alice read.
+    fn create_child_for_test() -> (Session, Network, TcpStream, TcpStream) {
+        let (tx, _rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        let session = create_session_for_test(tx);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        create_client_for_test(&session, addr.to_owned(), "alice".to_string());
+        let (alice_reader, _) = listener.accept().unwrap();
+        let channel = Channel::new(
+            "#rust".to_string(),
+            "".to_string(),
+            vec!["alice".to_string(), "bob".to_string()],
+        );
+        write_lock_channels(&session)
+            .unwrap()
+            .insert(channel.name.to_owned(), channel);
+
+        let father_socket = TcpStream::connect(addr).unwrap();
+        let (father_reader, _) = listener.accept().unwrap();
+        let network = Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "child".to_string(),
+                operators: vec![],
+                father: Some(("father".to_string(), Arc::new(father_socket))),
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        };
+        (session, network, father_reader, alice_reader)
+    }
+
+    fn members_for_test(session: &Session) -> Option<Vec<String>> {
+        let channels = read_lock_channels(session).unwrap();
+        let mut members = channels.get("#rust")?.users.to_owned();
+        members.sort();
+        Some(members)
+    }
+
+    #[test]
+    fn test_members_checksum_doesnt_depend_on_the_order_of_the_members() {
+        let users = vec!["alice".to_string(), "bob".to_string()];
+        let channel = Channel::new("#rust".to_string(), "".to_string(), users);
+        let mut reversed = channel.clone();
+        reversed.users.reverse();
+        let mut other = channel.clone();
+        other.users.push("carol".to_string());
+
+        assert_eq!(channel.members_checksum(), reversed.members_checksum());
+        assert_ne!(channel.members_checksum(), other.members_checksum());
+    }
+
+    #[test]
+    fn test_members_are_split_in_ranges_that_fit_in_a_message() {
+        let members = (0..100)
+            .map(|i| format!("member{:03}", i))
+            .collect::<Vec<_>>();
+
+        let messages = members_messages("#rust", &members);
+
+        assert!(messages.len() > 1);
+        assert!(messages.iter().all(|message| message.len() <= MAX_MSG_SIZE));
+        let mut after = "*".to_string();
+        let mut received = vec![];
+        for message in messages.iter() {
+            let message = Message::serialize(message.to_owned()).unwrap();
+            assert_eq!(message.parameters[1], after);
+            after = message.parameters[2].to_owned();
+            received.extend(message.trailing.unwrap().split(' ').map(str::to_string));
+        }
+        assert_eq!(after, "*");
+        assert_eq!(received, members);
+    }
+
+    #[test]
+    fn test_different_checksum_of_the_father_asks_for_the_members() {
+        let (session, network, mut father_reader, _alice_reader) = create_child_for_test();
+        let message = Message::serialize("CHANSUM #rust 1234".to_string()).unwrap();
+
+        handle_chansum_command(message, "father", &session, &network).unwrap();
+
+        assert_eq!(read_message_for_test(&mut father_reader), "CHANSYNC #rust");
+    }
+
+    #[test]
+    fn test_members_of_the_father_replace_the_remote_members() {
+        let (session, network, mut father_reader, _alice_reader) = create_child_for_test();
+        let message = Message::serialize("CHANSYNC #rust * * :carol dave".to_string()).unwrap();
+
+        handle_chansync_command(message, "father", &session, &network).unwrap();
+
+        // bob was a remote member, while alice is a client of this server the father missed
+        assert_eq!(
+            members_for_test(&session),
+            Some(vec![
+                "alice".to_string(),
+                "carol".to_string(),
+                "dave".to_string()
+            ])
+        );
+        assert_eq!(
+            read_message_for_test(&mut father_reader),
+            "CHANSYNC #rust + :alice"
+        );
+    }
+
+    #[test]
+    fn test_members_out_of_the_range_are_kept() {
+        let (session, network, _father_reader, _alice_reader) = create_child_for_test();
+        let message = Message::serialize("CHANSYNC #rust * alice :alice".to_string()).unwrap();
+
+        handle_chansync_command(message, "father", &session, &network).unwrap();
+
+        assert_eq!(
+            members_for_test(&session),
+            Some(vec!["alice".to_string(), "bob".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_members_sent_by_a_child_are_ignored() {
+        let (session, network, _father_reader, _alice_reader) = create_child_for_test();
+        let message = Message::serialize("CHANSYNC #rust * * :carol".to_string()).unwrap();
+
+        handle_chansync_command(message, "other", &session, &network).unwrap();
+
+        assert_eq!(
+            members_for_test(&session),
+            Some(vec!["alice".to_string(), "bob".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_reported_members_that_left_are_removed() {
+        let (session, network, _father_reader, mut alice_reader) = create_child_for_test();
+        let message = Message::serialize("CHANSYNC #rust - :bob".to_string()).unwrap();
+
+        handle_chansync_command(message, "father", &session, &network).unwrap();
+
+        assert_eq!(members_for_test(&session), Some(vec!["alice".to_string()]));
+        assert_eq!(read_message_for_test(&mut alice_reader), "018 #rust bob");
+    }
+}
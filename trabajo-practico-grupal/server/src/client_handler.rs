@@ -2,78 +2,101 @@ use std::{net::TcpStream, sync::Arc};
 
 use model::{
     client::Client,
+    events::ServerEvent,
+    json::ToJson,
     message::{Message, MessageType},
-    network::Network,
+    network::{Network, RemoteClient},
     persistence::PersistenceType,
-    responses::{errors::ErrorResponse, replies::CommandResponse},
+    plugins::HookAction,
+    prefix::Prefix,
+    responses::{
+        errors::ErrorResponse, message::MessageResponse, numeric::WireFormat,
+        replies::CommandResponse,
+    },
     session::Session,
 };
 
 use crate::{
+    commands::{
+        command_utils::{get_current_timestamp, update_last_activity},
+        mode::get_user_modes_string,
+    },
     database::inform_database,
+    handshake::Handshake,
+    locks::{
+        read_lock_clients, read_lock_config, read_lock_network_clients, write_lock_network_clients,
+    },
     message_handler::handle_client_message,
     registration::handle_registration,
+    run::{count_rejected_connection, REJECTED_FAILED_REGISTRATION, REJECTED_REGISTRATION_TIMEOUT},
     server_errors::ServerError,
-    socket::{inform_network, read_socket, write_socket},
+    socket::{inform_client, inform_network, read_socket_as, write_socket_as},
 };
 
 ///Handles the client registration and login, and returns a ServerError in case of failure.
 /// If the client is already registered, it handles the client messages.
 /// If the client is not registered, it handles the registration.
 /// If the client is not registered and the registration fails, it sends an error response to the client.
+/// The messages are read and written in the wire format of the connection, and the ones that are
+/// not valid UTF-8 are answered with the error of invalid encoding.
+/// The registration is bounded by the handshake of the connection: a client that doesn't
+/// register before its deadline, or sends too many messages that can't be parsed or are refused,
+/// is dropped and counted in the rejected connections. Once registered, the connection gets the
+/// read timeout of the configuration.
 pub fn handle_client(
-    arc_socket: Arc<TcpStream>,
+    connection: (Arc<TcpStream>, WireFormat, Handshake),
     message: Message,
     session: Session,
     network: Network,
     server_name: &String,
 ) -> Result<(), ServerError> {
+    let (arc_socket, format, mut handshake) = connection;
     let mut nickname: Option<String> = Option::None;
     let mut user_parameters = Option::None;
     let mut password = Option::None;
 
-    if register_client(
-        message,
-        (&mut nickname, &mut user_parameters),
-        &mut password,
-        arc_socket.clone(),
-        &session,
-        &network,
-        server_name,
-    )
-    .is_err()
-    {
-        println!("Error registering client");
-    }
-
+    let mut message = Some(message);
     while nickname.is_none() || user_parameters.is_none() {
-        let msg = read_socket(arc_socket.clone())?;
-        let message = match Message::serialize(msg) {
-            Ok(m) => m,
-            Err(e) => {
-                println!("Error parsing message: {:?}", e);
-                continue;
-            }
+        let next = match message.take() {
+            Some(first) => Ok(first),
+            None => read_registration_message(&arc_socket, format, &nickname, &mut handshake),
         };
-
-        match register_client(
-            message,
-            (&mut nickname, &mut user_parameters),
-            &mut password,
-            arc_socket.clone(),
-            &session,
-            &network,
-            server_name,
-        ) {
-            Ok(_) => (),
-            Err(e) => {
+        let result = next.and_then(|message| {
+            register_client(
+                message,
+                (&mut nickname, &mut user_parameters),
+                &mut password,
+                (arc_socket.clone(), format),
+                &session,
+                &network,
+                server_name,
+            )
+            .or_else(|e| {
                 println!("Error registering client: {:?}", e);
-                continue;
-            }
+                handshake.fail()
+            })
+        });
+        if let Err(e) = result {
+            reject_registration(&e, &session);
+            return Err(e);
         }
     }
+    let read_timeout = read_lock_config(&session)?.read_timeout();
+    arc_socket.set_read_timeout(read_timeout)?;
 
-    while let Ok(msg) = read_socket(arc_socket.clone()) {
+    loop {
+        let msg = match read_socket_as(arc_socket.clone(), format) {
+            Ok(msg) => msg,
+            Err(ServerError::SocketTimeout) => {
+                println!("Connection of {:?} timed out", nickname);
+                break;
+            }
+            Err(ServerError::InvalidEncoding) => {
+                inform_invalid_encoding(&arc_socket, format, &nickname);
+                continue;
+            }
+            Err(_) => break,
+        };
         let msg = match Message::serialize(msg) {
             Ok(m) => m,
             Err(e) => {
@@ -83,6 +106,20 @@ pub fn handle_client(
         };
 
         if let Some(nick) = nickname.clone() {
+            let msg = match authenticate_prefix(msg, &client_mask(&nick, &session)) {
+                Ok(m) => m,
+                Err(e) => {
+                    println!("Discarding message of {}: {:?}", nick, e);
+                    continue;
+                }
+            };
+            if let Err(e) = update_last_activity(&nick, &session) {
+                println!("Error updating the activity of {}: {:?}", nick, e);
+            }
+            let msg = match run_plugins(msg, &nick, &session, server_name) {
+                Some(m) => m,
+                None => continue,
+            };
             match handle_client_message(msg, nick, &session, &network, server_name) {
                 Ok(_) => (),
                 Err(e) => println!("Error handling message: {:?}", e),
@@ -90,45 +127,192 @@ pub fn handle_client(
         }
     }
 
-    disconnect_client(&nickname, &session);
+    disconnect_client(&nickname, &arc_socket, &session);
     Ok(())
 }
 
+/// Reads the next message of a client that is not registered yet, before the deadline of its
+/// handshake. The messages that can't be parsed or are not valid UTF-8 count as failed attempts.
+/// # Arguments
+/// * `arc_socket` - The socket of the client.
+/// * `format` - The format used by the connection.
+/// * `nickname` - The nickname of the client, if it sent one.
+/// * `handshake` - The deadline and the attempts of the connection.
+/// # Errors
+/// Returns `ServerError::SocketTimeout` if the deadline passes, `ServerError::InvalidMessage` if
+/// the client runs out of attempts, or the error of the socket if the connection closes.
+fn read_registration_message(
+    arc_socket: &Arc<TcpStream>,
+    format: WireFormat,
+    nickname: &Option<String>,
+    handshake: &mut Handshake,
+) -> Result<Message, ServerError> {
+    loop {
+        handshake.set_read_timeout(arc_socket)?;
+        match read_socket_as(arc_socket.clone(), format) {
+            Ok(msg) => match Message::serialize(msg) {
+                Ok(message) => return Ok(message),
+                Err(e) => println!("Error parsing message: {:?}", e),
+            },
+            Err(ServerError::InvalidEncoding) => {
+                inform_invalid_encoding(arc_socket, format, nickname)
+            }
+            Err(e) => return Err(e),
+        }
+        handshake.fail()?;
+    }
+}
+
+/// Counts a client dropped before registering in the rejected connections, if it was dropped
+/// for running out of time or of attempts rather than for closing its connection.
+/// # Arguments
+/// * `error` - The error the registration ended with.
+/// * `session` - The session of the server.
+fn reject_registration(error: &ServerError, session: &Session) {
+    let reason = match error {
+        ServerError::SocketTimeout => REJECTED_REGISTRATION_TIMEOUT,
+        ServerError::InvalidMessage => REJECTED_FAILED_REGISTRATION,
+        _ => return,
+    };
+    println!("Dropping unregistered connection: {}", reason);
+    count_rejected_connection(session, reason);
+}
+
+/// Answers a message of the client that is not valid UTF-8 with the error of invalid encoding,
+/// since the message was read but can't be handled.
+/// # Arguments
+/// * `arc_socket` - The socket of the client.
+/// * `format` - The format used by the connection.
+/// * `nickname` - The nickname of the client, if it has one.
+fn inform_invalid_encoding(
+    arc_socket: &Arc<TcpStream>,
+    format: WireFormat,
+    nickname: &Option<String>,
+) {
+    let nickname = nickname.as_deref().unwrap_or("*");
+    let response = ErrorResponse::InvalidEncoding.to_string();
+    if let Err(e) = write_socket_as(arc_socket.clone(), &response, format, nickname) {
+        println!(
+            "Error informing {} of the invalid encoding: {:?}",
+            nickname, e
+        );
+    }
+}
+
+/// Asks the plugins of the server about a message sent by a registered client, before it is
+/// handled. Returns the message to handle, as the plugins rewrote it, or None if a plugin denied
+/// it, after telling the client the reason with a notice of the server.
+/// # Arguments
+/// * `message` - The message sent by the client.
+/// * `nickname` - The nickname of the client.
+/// * `session` - The session of the server.
+/// * `server_name` - The name of the current server.
+fn run_plugins(
+    message: Message,
+    nickname: &String,
+    session: &Session,
+    server_name: &str,
+) -> Option<Message> {
+    match session.plugins.pre_command(session, nickname, &message) {
+        HookAction::Allow => Some(message),
+        HookAction::Rewrite(rewritten) => Some(rewritten),
+        HookAction::Deny(reason) => {
+            let response = MessageResponse::UserNotice {
+                sender: server_name.to_string(),
+                message: reason,
+            };
+            if let Err(e) = inform_client(session, nickname, &response.to_string()) {
+                println!(
+                    "Error informing {} about a denied message: {:?}",
+                    nickname, e
+                );
+            }
+            None
+        }
+    }
+}
+
+/// Returns the prefix `nickname!user@host` of the client, with the user and host it registered
+/// with, or just its nickname if it can't be found.
+/// # Arguments
+/// * `nickname` - The nickname of the client.
+/// * `session` - The session of the server.
+fn client_mask(nickname: &str, session: &Session) -> Prefix {
+    match session.clients.get(nickname) {
+        Some(client) => Prefix::mask(nickname, &client.username, &client.hostname),
+        None => Prefix::nick(nickname),
+    }
+}
+
+/// Checks that the prefix of a message sent by a registered client is its own nickname,
+/// and sets it to the mask of the client. The handlers relay the prefix of the messages,
+/// so they can trust it as they trust the prefix of the messages of other servers.
+/// A prefix with the form `nickname!user@host` is checked by its nickname.
+/// # Arguments
+/// * `message` - The message sent by the client.
+/// * `mask` - The prefix of the client, with the nickname it registered with.
+/// # Errors
+/// Returns `ServerError::SpoofedPrefix` if the prefix is not the nickname of the client,
+/// and the message must be discarded.
+pub fn authenticate_prefix(mut message: Message, mask: &Prefix) -> Result<Message, ServerError> {
+    if let Some(prefix) = &message.prefix {
+        if prefix.name() != mask.name() {
+            return Err(ServerError::SpoofedPrefix);
+        }
+    }
+    message.prefix = Some(mask.clone());
+    Ok(message)
+}
+
 /// Disconnects the client from the server.
+/// If the nickname was reclaimed by another connection, the client is not disconnected.
+/// A client that was connected is published as disconnected, whether it quit or not.
 /// # Arguments
 /// * `nickname` - The nickname of the client to disconnect.
+/// * `arc_socket` - The socket of the connection that was closed.
 /// * `session` - The session of the server.
-fn disconnect_client(nickname: &Option<String>, session: &Session) {
+fn disconnect_client(nickname: &Option<String>, arc_socket: &Arc<TcpStream>, session: &Session) {
     if let Some(n) = nickname.to_owned() {
-        match session.clients.as_ref().write() {
-            Ok(mut clients) => {
-                if let Some(c) = clients.get_mut(&n) {
-                    c.connected = false;
-                    println!("Client {} left the server", n);
-                }
-                drop(clients);
-            }
-            Err(_) => println!("Error locking clients"),
+        let reclaimed = session
+            .sockets
+            .socket(&n)
+            .is_some_and(|socket| !Arc::ptr_eq(&socket, arc_socket));
+        if reclaimed {
+            println!("Client {} was reclaimed by another connection", n);
+            return;
+        }
+        let mut was_connected = false;
+        if session.clients.update(&n, |c| {
+            was_connected = c.connected;
+            c.connected = false;
+        }) {
+            println!("Client {} left the server", n);
+        }
+        if was_connected {
+            session.publish(ServerEvent::ClientDisconnected { nickname: n });
         }
     }
 }
 
 /// Registers the client if it is not already registered. If an error occurs it sends an error response to the client.
+/// Once registered, the client joins the channels of the auto-join list of its preferences.
+/// The connection is given with the stream of the client and the wire format it uses.
+/// For each error it sends an error response to the client.
 ///  # Errors
 /// * ServerError::LockError - If the clients cannot be locked.
 /// * ServerError::NicknameInUse - If the nickname is already in use.
 /// * ServerError::InvalidPassword - If the password is incorrect.
 /// * ServerError::ErroneusNickname - If the nickname is invalid.
-/// For each error it sends an error response to the client.
 pub fn register_client(
     message: Message,
     credentials: (&mut Option<String>, &mut Option<Vec<String>>),
     password: &mut Option<String>,
-    client_stream: Arc<TcpStream>,
+    connection: (Arc<TcpStream>, WireFormat),
     session: &Session,
     network: &Network,
     server_name: &String,
 ) -> Result<(), ServerError> {
+    let (client_stream, format) = connection;
     match handle_registration(
         message,
         credentials.0,
@@ -139,38 +323,48 @@ pub fn register_client(
     ) {
         Ok(some_client) => {
             if let Some(client) = some_client {
-                match save_client(session, network, client, client_stream, server_name) {
-                    Ok(_) => match session.clients.as_ref().read() {
-                        Ok(clients) => {
-                            let nicknames = clients.keys().collect::<Vec<_>>();
-                            for nick in nicknames.clone() {
-                                let msg = Message::new(None, MessageType::Names, vec![], None);
-                                match handle_client_message(
-                                    msg,
-                                    nick.to_string(),
-                                    session,
-                                    network,
-                                    server_name,
-                                ) {
-                                    Ok(_) => (),
-                                    Err(e) => println!("Error handling message: {:?}", e),
+                let nick = client.nickname.to_owned();
+                match save_client(
+                    session,
+                    network,
+                    client,
+                    (client_stream, format),
+                    server_name,
+                ) {
+                    Ok(_) => {
+                        join_autojoin_channels(&nick, session, network, server_name)?;
+                        match read_lock_clients(session) {
+                            Ok(clients) => {
+                                let nicknames = clients.keys().cloned().collect::<Vec<_>>();
+                                drop(clients);
+                                for nick in nicknames.iter() {
+                                    let msg = Message::new(None, MessageType::Names, vec![], None);
+                                    match handle_client_message(
+                                        msg,
+                                        nick.to_string(),
+                                        session,
+                                        network,
+                                        server_name,
+                                    ) {
+                                        Ok(_) => (),
+                                        Err(e) => println!("Error handling message: {:?}", e),
+                                    }
                                 }
-                            }
-                            let network_clients = network.clients.read()?;
-                            for net_nick in network_clients.keys() {
-                                if nicknames.contains(&net_nick) {
-                                    continue;
+                                let network_clients = read_lock_network_clients(network)?;
+                                for net_nick in network_clients.keys() {
+                                    if nicknames.contains(net_nick) {
+                                        continue;
+                                    }
+                                    let message = format!(":{} NAMES", net_nick);
+                                    inform_network(network, server_name, &message)?;
                                 }
-                                let message = format!(":{} NAMES", net_nick);
-                                inform_network(network, server_name, &message)?;
+                                drop(network_clients);
+                            }
+                            Err(_) => {
+                                return Err(ServerError::LockError);
                             }
-                            drop(network_clients);
-                            drop(clients);
-                        }
-                        Err(_) => {
-                            return Err(ServerError::LockError);
                         }
-                    },
+                    }
                     Err(e) => {
                         println!("Error saving client: {:?}", e);
                         *credentials.0 = None;
@@ -185,15 +379,15 @@ pub fn register_client(
             match e {
                 ServerError::NicknameInUse(nickname) => {
                     let response = ErrorResponse::NickInUse { nickname }.to_string();
-                    write_socket(client_stream, response.as_str())?;
+                    write_socket_as(client_stream, response.as_str(), format, "*")?;
                 }
                 ServerError::InvalidPassword => {
                     let response = ErrorResponse::NotRegistered.to_string();
-                    write_socket(client_stream, response.as_str())?;
+                    write_socket_as(client_stream, response.as_str(), format, "*")?;
                 }
                 ServerError::ErroneusNickname => {
                     let response = ErrorResponse::NotRegistered.to_string();
-                    write_socket(client_stream, response.as_str())?;
+                    write_socket_as(client_stream, response.as_str(), format, "*")?;
                 }
                 _ => return Err(e),
             }
@@ -203,62 +397,190 @@ pub fn register_client(
 }
 
 /// Saves the client in the server, and if everything is ok it sends a welcome message to the client and informs the network.
+/// A client that registers again gets the away message of its preferences, if it is not already away.
+/// The wire format of the connection is saved in the session, so the client is informed in that format.
+/// For each error it sends an error response to the client.
 /// # Errors
 /// * ServerError::LockError - If the clients cannot be locked.
 /// * ServerError::ClientConnected - If a client is already connected with the same nickname.
-/// For each error it sends an error response to the client.
 fn save_client(
     session: &Session,
     network: &Network,
-    client: Client,
-    client_stream: Arc<TcpStream>,
+    mut client: Client,
+    connection: (Arc<TcpStream>, WireFormat),
     server_name: &String,
 ) -> Result<(), ServerError> {
+    let (client_stream, format) = connection;
     let nick = client.nickname.to_owned();
-    match session.clients.as_ref().write() {
-        Ok(mut clients) => {
-            if let Some(c) = clients.get_mut(&nick) {
-                if c.connected {
-                    let response = (ErrorResponse::NickInUse {
-                        nickname: c.nickname.clone(),
-                    })
-                    .to_string();
-                    write_socket(client_stream, response.as_str())?;
-                    return Err(ServerError::ClientConnected);
-                }
-                c.connected = true;
-            } else {
-                clients.insert(nick.to_owned(), client.to_owned());
-                inform_database(PersistenceType::ClientSave, client.to_string(), session)?;
-                let mut network_clients = network.clients.as_ref().write()?;
-                network_clients.insert(nick.to_owned(), 0);
-                drop(network_clients);
-            }
-            let response = (CommandResponse::Welcome {
-                nickname: client.nickname,
-                username: client.username,
-                hostname: client.hostname,
+    let user_message = RemoteClient::from_client(&client).user_message(&nick);
+    let registered = ServerEvent::ClientRegistered {
+        nickname: nick.to_owned(),
+        username: client.username.to_owned(),
+        hostname: client.hostname.to_owned(),
+    };
+    let now = get_current_timestamp();
+    let mut entry = session.clients.lock_client(&nick);
+    let modes = if let Some(c) = entry.get_mut() {
+        if c.connected {
+            let response = (ErrorResponse::NickInUse {
+                nickname: c.nickname.clone(),
             })
             .to_string();
-            write_socket(client_stream.clone(), response.as_str())?;
-            drop(clients);
-        }
-        Err(_) => {
-            return Err(ServerError::LockError);
+            drop(entry);
+            write_socket_as(client_stream, response.as_str(), format, "*")?;
+            return Err(ServerError::ClientConnected);
         }
-    }
-    match session.sockets.as_ref().lock() {
-        Ok(mut sockets) => {
-            sockets.insert(nick.to_owned(), client_stream);
-            drop(sockets);
-        }
-        Err(_) => {
-            return Err(ServerError::LockError);
+        c.connected = true;
+        c.signon = now;
+        c.last_activity = now;
+        if c.away_message.is_none() {
+            c.away_message = c.preferences.away_message.to_owned();
         }
+        c.modes.to_owned()
+    } else {
+        client.signon = now;
+        client.last_activity = now;
+        entry.insert(client.to_owned());
+        inform_database(PersistenceType::ClientSave, client.to_json(), session)?;
+        let mut network_clients = write_lock_network_clients(network)?;
+        network_clients.insert(nick.to_owned(), RemoteClient::from_client(&client));
+        drop(network_clients);
+        client.modes.to_owned()
+    };
+    let response = (CommandResponse::Welcome {
+        nickname: client.nickname,
+        username: client.username,
+        hostname: client.hostname,
+    })
+    .to_string();
+    write_socket_as(client_stream.clone(), response.as_str(), format, &nick)?;
+    drop(entry);
+    for response in get_welcome_burst(session, server_name)? {
+        write_socket_as(client_stream.clone(), response.as_str(), format, &nick)?;
     }
+    session.sockets.register(&nick, client_stream, format);
 
     let msg = format!(":{} NICK {} 1", server_name, nick);
     inform_network(network, server_name, &msg)?;
+    inform_network(network, server_name, &user_message)?;
+    if !modes.is_empty() {
+        let msg = format!(":{} MODE {} {}", nick, nick, get_user_modes_string(&modes));
+        inform_network(network, server_name, &msg)?;
+    }
+    session.publish(registered);
+
+    Ok(())
+}
 
+/// Joins the client to the channels of the auto-join list of its preferences, as if it sent
+/// the JOIN command itself, so the channels it can't join are reported to it as usual.
+/// # Arguments
+/// * `nickname` - The nickname of the client that registered.
+/// * `session` - The session of the current server.
+/// * `network` - The network the client is connected to.
+/// * `server_name` - The name of the current server.
+fn join_autojoin_channels(
+    nickname: &String,
+    session: &Session,
+    network: &Network,
+    server_name: &String,
+) -> Result<(), ServerError> {
+    let autojoin = match session.clients.get(nickname) {
+        Some(client) => client.preferences.autojoin.join(","),
+        None => return Ok(()),
+    };
+    if autojoin.is_empty() {
+        return Ok(());
+    }
+    let message = Message::new(None, MessageType::Join, vec![autojoin], None);
+    if let Err(e) =
+        handle_client_message(message, nickname.to_owned(), session, network, server_name)
+    {
+        println!(
+            "Error joining the auto-join channels of {}: {:?}",
+            nickname, e
+        );
+    }
     Ok(())
 }
+
+/// Returns the replies sent to a client after the welcome message, with the information
+/// of the server and the limits it supports, taken from the configuration of the server.
+/// # Arguments
+/// * `session` - The session of the current server.
+/// * `server_name` - The name of the current server.
+fn get_welcome_burst(session: &Session, server_name: &str) -> Result<Vec<String>, ServerError> {
+    let config = read_lock_config(session)?;
+    let burst = [
+        CommandResponse::YourHost {
+            servername: server_name.to_string(),
+            version: config.version.to_owned(),
+        },
+        CommandResponse::Created {
+            date: config.created.to_owned(),
+        },
+        CommandResponse::MyInfo {
+            servername: server_name.to_string(),
+            version: config.version.to_owned(),
+            user_modes: config.get_user_modes(),
+            channel_modes: config.get_channel_modes(),
+        },
+        CommandResponse::ISupport {
+            tokens: config.get_isupport_tokens(),
+        },
+    ];
+    drop(config);
+    Ok(burst.iter().map(|response| response.to_string()).collect())
+}
+
+#[cfg(test)]
+mod client_handler_tests {
+    use model::{
+        message::{Message, MessageType},
+        prefix::Prefix,
+    };
+
+    use crate::server_errors::ServerError;
+
+    use super::authenticate_prefix;
+
+    fn privmsg(prefix: Option<&str>) -> Message {
+        Message::new(
+            prefix.map(|p| p.parse().unwrap()),
+            MessageType::Privmsg,
+            vec!["bob".to_string()],
+            Some("hi".to_string()),
+        )
+    }
+
+    #[test]
+    fn test_message_without_prefix_gets_the_nickname_of_the_client() {
+        let mask = Prefix::mask("alice", "al", "localhost");
+        let message = authenticate_prefix(privmsg(None), &mask).unwrap();
+
+        assert_eq!(message.prefix, Some(mask));
+    }
+
+    #[test]
+    fn test_message_with_the_nickname_of_the_client_is_accepted() {
+        let mask = Prefix::mask("alice", "al", "localhost");
+        let message = authenticate_prefix(privmsg(Some("alice!alice@host")), &mask).unwrap();
+        assert_eq!(message.prefix, Some(mask.clone()));
+
+        let message = authenticate_prefix(privmsg(Some("alice")), &mask).unwrap();
+        assert_eq!(message.prefix, Some(mask));
+    }
+
+    #[test]
+    fn test_message_with_the_nickname_of_other_client_is_rejected() {
+        let mask = Prefix::nick("alice");
+        assert_eq!(
+            authenticate_prefix(privmsg(Some("carol")), &mask),
+            Err(ServerError::SpoofedPrefix)
+        );
+        assert_eq!(
+            authenticate_prefix(privmsg(Some("carol!alice@host")), &mask),
+            Err(ServerError::SpoofedPrefix)
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use crate::{
+    audit::read_audit_page, locks::read_lock_config, server_errors::ServerError,
+    socket::inform_client,
+};
+use model::{
+    message::Message,
+    responses::{errors::ErrorResponse, replies::CommandResponse},
+    session::Session,
+};
+
+static AUDIT_PAGE_SIZE: usize = 20;
+
+/// Handles the AUDIT command, which shows a page of the audit log of the server, with the
+/// actions of the operators and of the server, the newest first. Without parameters it shows
+/// the first page.
+/// Only the operators of the server can send it, which is checked by the registry of the commands.
+/// # Arguments
+/// * `message` - The message sent by the client.
+/// * `nickname` - The nickname of the client that sent the message.
+/// * `session` - The session of the current server.
+/// # Errors
+/// ServerError::InvalidParameters if the page is not a positive number.
+///
+/// For each error a message is sent to the client to inform them of the error.
+pub fn handle_audit_command(
+    message: Message,
+    nickname: String,
+    session: &Session,
+) -> Result<(), ServerError> {
+    let page = match message.parameters.first() {
+        Some(page) => page.parse::<usize>().ok().filter(|page| *page > 0),
+        None => Some(1),
+    };
+    let page = match page {
+        Some(page) => page,
+        None => {
+            let response = ErrorResponse::NeedMoreParams {
+                command: "AUDIT".to_string(),
+            }
+            .to_string();
+            inform_client(session, &nickname, &response)?;
+            return Err(ServerError::InvalidParameters);
+        }
+    };
+    let file = read_lock_config(session)?.audit_file.to_owned();
+    let (records, pages) = match file {
+        Some(file) => read_audit_page(&file, page, AUDIT_PAGE_SIZE)?,
+        None => (vec![], 1),
+    };
+
+    for record in records {
+        let response = CommandResponse::AuditEntry {
+            time: record.time,
+            action: record.action,
+            actor: record.actor,
+            target: record.target,
+            parameters: record.parameters,
+        }
+        .to_string();
+        inform_client(session, &nickname, &response)?;
+    }
+    let response = CommandResponse::EndOfAudit { page, pages }.to_string();
+    inform_client(session, &nickname, &response)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod audit_tests {
+    use model::consts::MAX_MSG_SIZE;
+    use std::{
+        io::Read,
+        net::{TcpListener, TcpStream},
+    };
+
+    use model::{
+        message::MessageType,
+        persistence::PersistenceType,
+        responses::{errors::ErrorResponse, replies::CommandResponse, response::Response},
+        session::Session,
+    };
+
+    use crate::{
+        commands::{
+            audit::handle_audit_command,
+            command_utils::{
+                create_client_for_test, create_message_for_test, create_session_for_test,
+            },
+        },
+        database::{append_audit_record, DatabasePaths},
+        server_errors::ServerError,
+    };
+
+    fn read_response_for_test(reader: &mut TcpStream) -> Response {
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        Response::serialize(String::from_utf8(msg).unwrap()).unwrap()
+    }
+
+    fn create_session_with_audit_for_test() -> (Session, TcpStream, DatabasePaths) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, _db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        let session = create_session_for_test(db_tx);
+        let paths = DatabasePaths::temporary().unwrap();
+        session.config.write().unwrap().audit_file = Some(paths.audit.to_owned());
+        create_client_for_test(&session, addr, "operator".to_string());
+        let (reader, _) = listener.accept().unwrap();
+        (session, reader, paths)
+    }
+
+    #[test]
+    fn test_audit_command_shows_the_newest_records_first() {
+        let (session, mut reader, paths) = create_session_with_audit_for_test();
+        let old = r#"{"time":1,"action":"OPER","actor":"alice","target":"alice","parameters":""}"#;
+        let new =
+            r##"{"time":2,"action":"KICK","actor":"alice","target":"#rust","parameters":"bob"}"##;
+        append_audit_record(old.to_string(), &paths).unwrap();
+        append_audit_record(new.to_string(), &paths).unwrap();
+
+        let message = create_message_for_test(MessageType::Audit, vec![]);
+        assert!(handle_audit_command(message, "operator".to_string(), &session).is_ok());
+
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::CommandResponse {
+                response: CommandResponse::AuditEntry { time: 2, action, target, parameters, .. }
+            } if action == "KICK" && target == "#rust" && parameters == "bob"
+        ));
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::CommandResponse {
+                response: CommandResponse::AuditEntry { time: 1, action, parameters, .. }
+            } if action == "OPER" && parameters.is_empty()
+        ));
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::CommandResponse {
+                response: CommandResponse::EndOfAudit { page: 1, pages: 1 }
+            }
+        ));
+    }
+
+    #[test]
+    fn test_audit_command_with_an_invalid_page() {
+        let (session, mut reader, _paths) = create_session_with_audit_for_test();
+
+        let message = create_message_for_test(MessageType::Audit, vec!["0".to_string()]);
+        let result = handle_audit_command(message, "operator".to_string(), &session);
+
+        assert_eq!(result, Err(ServerError::InvalidParameters));
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::ErrorResponse {
+                response: ErrorResponse::NeedMoreParams { .. }
+            }
+        ));
+    }
+}
@@ -1,21 +1,24 @@
-use super::command_utils::write_lock_clients;
+use crate::locks::{read_lock_config, write_lock_clients};
 use crate::{database::inform_database, server_errors::ServerError, socket::inform_client};
 use model::{
-    message::Message, persistence::PersistenceType, responses::replies::CommandResponse,
-    session::Session,
+    json::ToJson, message::Message, persistence::PersistenceType,
+    responses::replies::CommandResponse, session::Session,
 };
 
 /// Handles the away message, which sets the client as away or not away. If it receives a message, it sets the client as away and sets the away message.
 /// If it receives no message, it sets the client as not away.
 /// Sends the client a command response with the new away status.
+/// Away messages longer than the maximum length of the configuration are truncated.
 pub fn handle_away_command(
     message: Message,
     nickname: String,
     session: &Session,
 ) -> Result<(), ServerError> {
+    let awaylen = read_lock_config(session)?.awaylen;
     if let Some(client) = write_lock_clients(session)?.get_mut(&nickname) {
         if !message.parameters.is_empty() || message.trailing.is_some() {
-            client.away_message = Some(get_away_message(message));
+            let away_message = get_away_message(message).chars().take(awaylen).collect();
+            client.away_message = Some(away_message);
             let response = CommandResponse::NowAway.to_string();
             inform_client(session, &nickname, &response)?;
             println!("{} is now away", nickname);
@@ -28,7 +31,7 @@ pub fn handle_away_command(
         }
         inform_database(
             PersistenceType::ClientUpdate(nickname.to_owned()),
-            client.to_string(),
+            client.to_json(),
             session,
         )?;
     }
@@ -53,3 +56,95 @@ fn get_away_message(message: Message) -> String {
     }
     msg
 }
+
+#[cfg(test)]
+mod away_tests {
+    use model::consts::MAX_MSG_SIZE;
+    use std::{
+        io::Read,
+        net::{TcpListener, TcpStream},
+    };
+
+    use model::{
+        message::MessageType,
+        persistence::PersistenceType,
+        responses::{replies::CommandResponse, response::Response},
+    };
+
+    use crate::{
+        commands::{
+            away::handle_away_command,
+            command_utils::{
+                create_client_for_test, create_message_for_test, create_session_for_test,
+            },
+        },
+        database::{handle_database, DatabasePaths},
+        locks::{read_lock_clients, write_lock_config},
+    };
+
+    fn read_response_for_test(reader: &mut TcpStream) -> Response {
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        Response::serialize(String::from_utf8(msg).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_away_message_is_set_and_cleared() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr, "nickname".to_string());
+        let (mut reader, _) = listener.accept().unwrap();
+
+        let mut message = create_message_for_test(MessageType::Away, vec![]);
+        message.trailing = Some("gone for lunch".to_string());
+        assert!(handle_away_command(message, "nickname".to_string(), &session).is_ok());
+        assert_eq!(
+            read_lock_clients(&session).unwrap()["nickname"].away_message,
+            Some("gone for lunch".to_string())
+        );
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::CommandResponse {
+                response: CommandResponse::NowAway
+            }
+        ));
+
+        let mut message = create_message_for_test(MessageType::Away, vec![]);
+        message.trailing = None;
+        assert!(handle_away_command(message, "nickname".to_string(), &session).is_ok());
+        assert_eq!(
+            read_lock_clients(&session).unwrap()["nickname"].away_message,
+            None
+        );
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::CommandResponse {
+                response: CommandResponse::UnAway
+            }
+        ));
+    }
+
+    #[test]
+    fn test_away_message_longer_than_the_limit_is_truncated() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr, "nickname".to_string());
+        let (_reader, _) = listener.accept().unwrap();
+        write_lock_config(&session).unwrap().awaylen = 4;
+
+        let mut message = create_message_for_test(MessageType::Away, vec![]);
+        message.trailing = Some("gone for lunch".to_string());
+        assert!(handle_away_command(message, "nickname".to_string(), &session).is_ok());
+        assert_eq!(
+            read_lock_clients(&session).unwrap()["nickname"].away_message,
+            Some("gone".to_string())
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use crate::{
+    commands::command_utils::get_current_timestamp, locks::read_lock_channels,
+    server_errors::ServerError, socket::inform_client,
+};
+use model::{
+    message::Message,
+    responses::{errors::ErrorResponse, replies::CommandResponse},
+    session::Session,
+};
+
+static TOP_TALKERS: usize = 5;
+static HOURS_PER_DAY: u64 = 24;
+
+/// Handles the CHANSTATS command, which shows the statistics of the messages of a channel: when
+/// it was created, the amount of messages sent to it, in total, in the last hour and in the last
+/// day, and its most active users.
+/// The statistics are counted by this server, and the amounts of the last hours start again
+/// when the server is restarted.
+/// # Arguments
+/// * `message` - The message sent by the client.
+/// * `nickname` - The nickname of the client that sent the message.
+/// * `session` - The session of the current server.
+/// # Errors
+/// ServerError::ChannelNotFound if the channel doesn't exist or the client can't see it.
+///
+/// For each error a message is sent to the client to inform them of the error.
+pub fn handle_chanstats_command(
+    message: Message,
+    nickname: String,
+    session: &Session,
+) -> Result<(), ServerError> {
+    let channel_name = message.parameters[0].to_owned();
+    let channels = read_lock_channels(session)?;
+    let stats = match channels.get(&channel_name) {
+        Some(channel) if channel.is_visible_to(&nickname) => channel.stats.clone(),
+        _ => {
+            drop(channels);
+            let response = ErrorResponse::NoSuchChannel {
+                channel: channel_name,
+            }
+            .to_string();
+            inform_client(session, &nickname, &response)?;
+            return Err(ServerError::ChannelNotFound);
+        }
+    };
+    drop(channels);
+
+    let now = get_current_timestamp();
+    let response = CommandResponse::ChannelStats {
+        channel: channel_name.to_owned(),
+        created: stats.created,
+        messages: stats.messages,
+        last_hour: stats.messages_in_last_hours(now, 1),
+        last_day: stats.messages_in_last_hours(now, HOURS_PER_DAY),
+    }
+    .to_string();
+    inform_client(session, &nickname, &response)?;
+    for (talker, messages) in stats.top_talkers(TOP_TALKERS) {
+        let response = CommandResponse::ChannelTalker {
+            channel: channel_name.to_owned(),
+            nickname: talker,
+            messages,
+        }
+        .to_string();
+        inform_client(session, &nickname, &response)?;
+    }
+    let response = CommandResponse::EndOfChannelStats {
+        channel: channel_name,
+    }
+    .to_string();
+    inform_client(session, &nickname, &response)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod chanstats_tests {
+    use model::consts::MAX_MSG_SIZE;
+    use std::{
+        io::Read,
+        net::{TcpListener, TcpStream},
+    };
+
+    use model::{
+        channel::Channel,
+        channel_stats::ChannelStats,
+        channelflag::ChannelFlag,
+        message::MessageType,
+        persistence::PersistenceType,
+        responses::{errors::ErrorResponse, replies::CommandResponse, response::Response},
+        session::Session,
+    };
+
+    use crate::{
+        commands::{
+            chanstats::handle_chanstats_command,
+            command_utils::{
+                create_client_for_test, create_message_for_test, create_session_for_test,
+                get_current_timestamp,
+            },
+        },
+        locks::write_lock_channels,
+        server_errors::ServerError,
+    };
+
+    fn read_response_for_test(reader: &mut TcpStream) -> Response {
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        Response::serialize(String::from_utf8(msg).unwrap()).unwrap()
+    }
+
+    fn create_session_with_channel_for_test(channel: Channel) -> (Session, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, _db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr, "nickname".to_string());
+        let (reader, _) = listener.accept().unwrap();
+        write_lock_channels(&session)
+            .unwrap()
+            .insert(channel.name.to_owned(), channel);
+        (session, reader)
+    }
+
+    #[test]
+    fn test_chanstats_shows_the_volume_and_the_top_talkers() {
+        let now = get_current_timestamp();
+        let mut channel = Channel::new("#rust".to_string(), "".to_string(), vec![]);
+        channel.stats = ChannelStats::new(100);
+        channel.stats.record_message("alice", now);
+        channel.stats.record_message("bob", now);
+        channel.stats.record_message("alice", now);
+        let (session, mut reader) = create_session_with_channel_for_test(channel);
+
+        let message = create_message_for_test(MessageType::ChanStats, vec!["#rust".to_string()]);
+        assert!(handle_chanstats_command(message, "nickname".to_string(), &session).is_ok());
+
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::CommandResponse {
+                response: CommandResponse::ChannelStats {
+                    created: 100,
+                    messages: 3,
+                    last_hour: 3,
+                    last_day: 3,
+                    ..
+                }
+            }
+        ));
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::CommandResponse {
+                response: CommandResponse::ChannelTalker { nickname, messages: 2, .. }
+            } if nickname == "alice"
+        ));
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::CommandResponse {
+                response: CommandResponse::ChannelTalker { nickname, messages: 1, .. }
+            } if nickname == "bob"
+        ));
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::CommandResponse {
+                response: CommandResponse::EndOfChannelStats { channel }
+            } if channel == "#rust"
+        ));
+    }
+
+    #[test]
+    fn test_chanstats_of_a_secret_channel_of_other_users() {
+        let mut channel = Channel::new("#secret".to_string(), "".to_string(), vec![]);
+        channel.modes.push(ChannelFlag::Secret);
+        let (session, mut reader) = create_session_with_channel_for_test(channel);
+
+        let message = create_message_for_test(MessageType::ChanStats, vec!["#secret".to_string()]);
+        let result = handle_chanstats_command(message, "nickname".to_string(), &session);
+
+        assert_eq!(result, Err(ServerError::ChannelNotFound));
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::ErrorResponse {
+                response: ErrorResponse::NoSuchChannel { channel }
+            } if channel == "#secret"
+        ));
+    }
+}
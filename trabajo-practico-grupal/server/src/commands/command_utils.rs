@@ -1,107 +1,42 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    io::Read,
     net::{TcpListener, TcpStream},
-    sync::{mpsc::Sender, Arc, Mutex},
+    sync::{mpsc::Sender, Arc, Mutex, RwLock},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use model::{
+    channel::Channel,
+    channel_registry::ChannelRegistry,
     client::Client,
+    client_registry::ClientRegistry,
+    client_registry::ClientsReadGuard,
+    command_metrics::CommandMetrics,
+    config::ServerConfig,
+    consts::MAX_MSG_SIZE,
+    events::EventBus,
+    gc_stats::GcStats,
+    json::ToJson,
     message::{Message, MessageType},
-    network::Network,
+    network::{Network, RemoteClient},
     persistence::PersistenceType,
+    plugins::Plugins,
+    prefix::Prefix,
+    responses::{errors::ErrorResponse, replies::CommandResponse, response::Response},
     session::Session,
+    socket_registry::SocketRegistry,
+    userflag::UserFlag,
 };
 
 use crate::{
-    message_handler::handle_client_message, server_errors::ServerError, socket::inform_network,
+    database::inform_database,
+    locks::{lock_sockets, read_lock_clients, read_lock_config, read_lock_network_clients},
+    message_handler::handle_client_message,
+    server_errors::ServerError,
+    socket::{inform_client, inform_network},
 };
 
-/// Returns a channel hashmap write lock for the session in case of success, and a ServerError::LockError in case of
-/// failure.
-pub fn write_lock_channels(
-    session: &Session,
-) -> Result<
-    std::sync::RwLockWriteGuard<std::collections::HashMap<String, model::channel::Channel>>,
-    ServerError,
-> {
-    let channels_lock = match session.channels.as_ref().write() {
-        Ok(c) => c,
-        Err(_) => {
-            println!("Error locking channels");
-            return Err(ServerError::LockError);
-        }
-    };
-    Ok(channels_lock)
-}
-
-/// Returns a channel hashmap read lock for the session in case of success, and a ServerError::LockError in case of
-/// failure.
-pub fn read_lock_channels(
-    session: &Session,
-) -> Result<
-    std::sync::RwLockReadGuard<std::collections::HashMap<String, model::channel::Channel>>,
-    ServerError,
-> {
-    let channels_lock = match session.channels.as_ref().read() {
-        Ok(c) => c,
-        Err(_) => {
-            println!("Error locking channels");
-            return Err(ServerError::LockError);
-        }
-    };
-    Ok(channels_lock)
-}
-
-/// Returns a client hashmap read lock for the session in case of success, and a ServerError::LockError in case of
-/// failure.
-pub fn read_lock_clients(
-    session: &Session,
-) -> Result<
-    std::sync::RwLockReadGuard<std::collections::HashMap<String, model::client::Client>>,
-    ServerError,
-> {
-    let clients_lock = match session.clients.as_ref().read() {
-        Ok(c) => c,
-        Err(_) => {
-            println!("Error locking clients");
-            return Err(ServerError::LockError);
-        }
-    };
-    Ok(clients_lock)
-}
-
-/// Returns a client hashmap write lock for the session in case of success, and a ServerError::LockError in case of
-/// failure
-pub fn write_lock_clients(
-    session: &Session,
-) -> Result<
-    std::sync::RwLockWriteGuard<std::collections::HashMap<String, model::client::Client>>,
-    ServerError,
-> {
-    let clients_lock = match session.clients.as_ref().write() {
-        Ok(c) => c,
-        Err(_) => {
-            println!("Error locking clients");
-            return Err(ServerError::LockError);
-        }
-    };
-    Ok(clients_lock)
-}
-
-/// Returns a mutex lock of the session sockets in case of success, and a ServerError::LockError in case of failure
-pub fn lock_sockets(
-    session: &Session,
-) -> Result<std::sync::MutexGuard<std::collections::HashMap<String, Arc<TcpStream>>>, ServerError> {
-    let sockets_lock = match session.sockets.as_ref().lock() {
-        Ok(s) => s,
-        Err(_) => {
-            println!("Error locking sockets");
-            return Err(ServerError::LockError);
-        }
-    };
-    Ok(sockets_lock)
-}
-
 pub fn create_client_for_test(session: &Session, addr: String, nickname: String) -> Client {
     let client = Client::from_connection(
         nickname,
@@ -113,11 +48,7 @@ pub fn create_client_for_test(session: &Session, addr: String, nickname: String)
         true,
     );
 
-    session
-        .clients
-        .write()
-        .unwrap()
-        .insert(client.clone().nickname, client.clone());
+    session.clients.insert(client.clone());
 
     let client_stream = Arc::new(TcpStream::connect(addr).unwrap());
     let mut sockets = match lock_sockets(session) {
@@ -132,14 +63,19 @@ pub fn create_client_for_test(session: &Session, addr: String, nickname: String)
 }
 
 pub fn create_session_for_test(tx: Sender<(PersistenceType, String)>) -> Session {
-    let clients = Arc::new(std::sync::RwLock::new(std::collections::HashMap::new()));
-    let sockets = Arc::new(Mutex::new(HashMap::new()));
-    let channels = Arc::new(std::sync::RwLock::new(HashMap::new()));
     Session {
-        clients,
-        sockets,
-        channels,
+        clients: ClientRegistry::default(),
+        sockets: SocketRegistry::default(),
+        connections: Arc::new(Mutex::new(HashMap::new())),
+        rejected_connections: Arc::new(Mutex::new(HashMap::new())),
+        channels: ChannelRegistry::default(),
         database_sender: tx,
+        config: Arc::new(RwLock::new(ServerConfig::default())),
+        events: EventBus::default(),
+        plugins: Plugins::default(),
+        who_queries: Arc::new(Mutex::new(HashMap::new())),
+        command_metrics: CommandMetrics::default(),
+        gc_stats: GcStats::default(),
     }
 }
 
@@ -149,21 +85,274 @@ pub fn create_server_for_test(addr: String) -> TcpListener {
 
 pub fn create_message_for_test(command: MessageType, parameters: Vec<String>) -> Message {
     Message::new(
-        Some("".to_string()),
+        Some(Prefix::nick("")),
         command,
         parameters,
         Some("".to_string()),
     )
 }
 
+/// Reads the next response sent to a client of the tests. The responses of our wire format are
+/// padded to the maximum size of a message, so a whole frame is read before parsing it.
+pub fn read_response_for_test(reader: &mut impl Read) -> Response {
+    let mut buf = vec![0u8; MAX_MSG_SIZE];
+    reader.read_exact(&mut buf).unwrap();
+    let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+    Response::serialize(String::from_utf8(msg).unwrap()).unwrap()
+}
+
+/// Returns true if the user has the invisible mode set, whether it is connected
+/// to this server or to another server of the network.
+/// # Arguments
+/// * `nickname` - The nickname of the user.
+/// * `clients` - The clients connected to this server.
+/// * `network_clients` - The clients of the network.
+pub fn is_invisible(
+    nickname: &str,
+    clients: &ClientsReadGuard,
+    network_clients: &HashMap<String, RemoteClient>,
+) -> bool {
+    if let Some(client) = clients.get(nickname) {
+        return client.modes.contains(&UserFlag::Invisible);
+    }
+    match network_clients.get(nickname) {
+        Some(network_client) => network_client.is_invisible(),
+        None => false,
+    }
+}
+
+/// Returns the nicknames of the users that share at least one channel with the given user.
+/// # Arguments
+/// * `nickname` - The nickname of the user.
+/// * `channels` - The channels of the server.
+pub fn get_channel_peers(nickname: &str, channels: &HashMap<String, Channel>) -> HashSet<String> {
+    let mut peers = HashSet::new();
+    for channel in channels.values() {
+        if channel.users.iter().any(|u| u == nickname) {
+            peers.extend(channel.users.iter().cloned());
+        }
+    }
+    peers
+}
+
+/// Returns the current time in seconds since the epoch.
+pub fn get_current_timestamp() -> u64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs(),
+        Err(_) => 0,
+    }
+}
+
+/// Updates the time of the last command sent by a client, used to know how long it has been idle.
+/// If the client was marked as away for being idle, it is no longer away.
+/// # Arguments
+/// * `nickname` - The nickname of the client.
+/// * `session` - The session of the current server.
+pub fn update_last_activity(nickname: &str, session: &Session) -> Result<(), ServerError> {
+    let mut entry = session.clients.lock_client(nickname);
+    let mut back = false;
+    if let Some(client) = entry.get_mut() {
+        client.last_activity = get_current_timestamp();
+        if client.auto_away {
+            client.auto_away = false;
+            client.away_message = None;
+            inform_database(
+                PersistenceType::ClientUpdate(nickname.to_owned()),
+                client.to_json(),
+                session,
+            )?;
+            back = true;
+        }
+    }
+    drop(entry);
+    if back {
+        inform_client(
+            session,
+            &nickname.to_string(),
+            &CommandResponse::UnAway.to_string(),
+        )?;
+    }
+    Ok(())
+}
+
+/// Checks that the message has at least the given number of parameters.
+/// # Arguments
+/// * `message` - The message sent by the client.
+/// * `count` - The minimum number of parameters of the command.
+/// * `command` - The name of the command, sent back to the client in the error.
+/// * `nickname` - The nickname of the client that sent the message.
+/// * `session` - The session of the current server.
+/// # Errors
+/// ServerError::InvalidParameters if the message has fewer parameters, after sending the client
+/// the error ErrorResponse::NeedMoreParams.
+pub fn require_params(
+    message: &Message,
+    count: usize,
+    command: &str,
+    nickname: &String,
+    session: &Session,
+) -> Result<(), ServerError> {
+    if message.parameters.len() >= count {
+        return Ok(());
+    }
+    let response = ErrorResponse::NeedMoreParams {
+        command: command.to_string(),
+    }
+    .to_string();
+    inform_client(session, nickname, &response)?;
+    Err(ServerError::InvalidParameters)
+}
+
+/// Checks that the channel name starts with one of the channel types of the server and is not
+/// longer than its maximum length.
+/// # Arguments
+/// * `channel` - The name of the channel.
+/// * `nickname` - The nickname of the client that sent the message.
+/// * `session` - The session of the current server.
+/// # Errors
+/// ServerError::ChannelMustStartWithHashOrAmpersand if the name is not valid, after sending the
+/// client the error ErrorResponse::NoSuchChannel.
+pub fn require_channel_name(
+    channel: &str,
+    nickname: &String,
+    session: &Session,
+) -> Result<(), ServerError> {
+    if read_lock_config(session)?.is_valid_channel_name(channel) {
+        return Ok(());
+    }
+    let response = ErrorResponse::NoSuchChannel {
+        channel: channel.to_string(),
+    }
+    .to_string();
+    inform_client(session, nickname, &response)?;
+    Err(ServerError::ChannelMustStartWithHashOrAmpersand)
+}
+
+/// Returns true if a message of a client of this server has more targets than the server allows.
+/// The messages of the clients of other servers were already checked by their server.
+/// # Arguments
+/// * `targets` - The targets of the message separated by commas, as sent by the client.
+/// * `nickname` - The nickname of the client that sent the message.
+/// * `session` - The session of the current server.
+pub fn exceeds_max_targets(
+    targets: &str,
+    nickname: &str,
+    session: &Session,
+) -> Result<bool, ServerError> {
+    if !session.clients.contains(nickname) {
+        return Ok(false);
+    }
+    let count = targets.split(',').count();
+    Ok(!read_lock_config(session)?.allows_targets(count))
+}
+
+/// Checks that a message of a client of this server doesn't have more targets than the server
+/// allows, so that no target receives it if it has too many.
+/// # Arguments
+/// * `targets` - The targets of the message separated by commas, as sent by the client.
+/// * `nickname` - The nickname of the client that sent the message.
+/// * `session` - The session of the current server.
+/// # Errors
+/// ServerError::TooManyTargets if the message has too many targets, after sending the client
+/// the error ErrorResponse::TooManyTargets.
+pub fn require_max_targets(
+    targets: &str,
+    nickname: &String,
+    session: &Session,
+) -> Result<(), ServerError> {
+    if !exceeds_max_targets(targets, nickname, session)? {
+        return Ok(());
+    }
+    let response = ErrorResponse::TooManyTargets {
+        target: targets.to_string(),
+    }
+    .to_string();
+    inform_client(session, nickname, &response)?;
+    Err(ServerError::TooManyTargets)
+}
+
+/// Checks that the sender of a message is registered in the network, either as a client
+/// connected to this server or as a client of another server.
+/// # Arguments
+/// * `nickname` - The nickname of the sender of the message.
+/// * `session` - The session of the current server.
+/// * `network` - The struct that contains the information of the network.
+/// # Errors
+/// ServerError::ClientMustRegisterOrAuthenticate if the sender is not registered, after sending
+/// the client the error ErrorResponse::NotRegistered.
+pub fn require_registered(
+    nickname: &String,
+    session: &Session,
+    network: &Network,
+) -> Result<(), ServerError> {
+    let registered = if session.clients.contains(nickname) {
+        session.clients.is_connected(nickname)
+    } else {
+        read_lock_network_clients(network)?.contains_key(nickname)
+    };
+    if registered {
+        return Ok(());
+    }
+    inform_client(session, nickname, &ErrorResponse::NotRegistered.to_string())?;
+    Err(ServerError::ClientMustRegisterOrAuthenticate)
+}
+
+/// The results of a command sent to several targets, like the channels of a JOIN or the
+/// receivers of a PRIVMSG. Every target is processed even if another one fails, and the
+/// client is informed of the error of each target as it happens.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct TargetResults {
+    pub results: Vec<(String, Result<(), ServerError>)>,
+}
+
+impl TargetResults {
+    pub fn new() -> TargetResults {
+        TargetResults::default()
+    }
+
+    /// Saves the result of a target.
+    pub fn push(&mut self, target: &str, result: Result<(), ServerError>) {
+        self.results.push((target.to_string(), result));
+    }
+
+    /// Returns the targets that were processed successfully.
+    pub fn succeeded(&self) -> Vec<&String> {
+        self.results
+            .iter()
+            .filter(|(_, result)| result.is_ok())
+            .map(|(target, _)| target)
+            .collect()
+    }
+
+    /// Returns the targets that failed, with their errors.
+    pub fn failed(&self) -> Vec<(&String, &ServerError)> {
+        self.results
+            .iter()
+            .filter_map(|(target, result)| match result {
+                Ok(_) => None,
+                Err(error) => Some((target, error)),
+            })
+            .collect()
+    }
+
+    /// Returns the error of the first target that failed, if any.
+    pub fn into_result(self) -> Result<(), ServerError> {
+        for (_, result) in self.results {
+            result?;
+        }
+        Ok(())
+    }
+}
+
 pub fn fetch_info(
     session: &Session,
     network: &Network,
     server_name: &String,
 ) -> Result<(), ServerError> {
     let clients_lock = read_lock_clients(session)?;
-    let nicknames = clients_lock.keys().collect::<Vec<_>>();
-    for nick in nicknames.clone() {
+    let nicknames = clients_lock.keys().cloned().collect::<Vec<_>>();
+    drop(clients_lock);
+    for nick in nicknames.iter() {
         let msg_names = Message::new(None, MessageType::Names, vec![], None);
         let msg_list = Message::new(None, MessageType::List, vec![], None);
         match handle_client_message(msg_names, nick.to_string(), session, network, server_name) {
@@ -175,7 +364,7 @@ pub fn fetch_info(
             Err(e) => println!("Error handling message: {:?}", e),
         }
     }
-    let network_clients = network.clients.read()?;
+    let network_clients = read_lock_network_clients(network)?;
     for net_nick in network_clients.keys() {
         if nicknames.contains(&net_nick) {
             continue;
@@ -186,6 +375,137 @@ pub fn fetch_info(
         inform_network(network, server_name, &message)?;
     }
     drop(network_clients);
-    drop(clients_lock);
     Ok(())
 }
+
+#[cfg(test)]
+mod command_utils_tests {
+    use std::{
+        collections::HashMap,
+        net::TcpListener,
+        sync::{Arc, RwLock},
+    };
+
+    use model::{
+        link_queues::LinkQueues,
+        message::MessageType,
+        network::{Network, RemoteClient},
+        persistence::PersistenceType,
+        responses::{errors::ErrorResponse, response::Response},
+        server::Server,
+    };
+
+    use super::{
+        create_client_for_test, create_message_for_test, create_session_for_test,
+        read_response_for_test, require_channel_name, require_params, require_registered,
+    };
+    use crate::{
+        database::{handle_database, DatabasePaths},
+        server_errors::ServerError,
+    };
+
+    fn create_network_for_test() -> Network {
+        Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        }
+    }
+
+    #[test]
+    fn test_require_params_sends_need_more_params() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr, "nickname".to_string());
+        let (mut reader, _) = listener.accept().unwrap();
+        let nickname = "nickname".to_string();
+        let message = create_message_for_test(MessageType::Invite, vec!["other".to_string()]);
+
+        assert!(require_params(&message, 1, "INVITE", &nickname, &session).is_ok());
+        let result = require_params(&message, 2, "INVITE", &nickname, &session);
+
+        assert_eq!(result, Err(ServerError::InvalidParameters));
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::ErrorResponse {
+                response: ErrorResponse::NeedMoreParams { command }
+            } if command == "INVITE"
+        ));
+    }
+
+    #[test]
+    fn test_require_channel_name_sends_no_such_channel() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr, "nickname".to_string());
+        let (mut reader, _) = listener.accept().unwrap();
+        let nickname = "nickname".to_string();
+
+        assert!(require_channel_name("#channel", &nickname, &session).is_ok());
+        assert!(require_channel_name("&channel", &nickname, &session).is_ok());
+        let result = require_channel_name("channel", &nickname, &session);
+
+        assert_eq!(
+            result,
+            Err(ServerError::ChannelMustStartWithHashOrAmpersand)
+        );
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::ErrorResponse {
+                response: ErrorResponse::NoSuchChannel { channel }
+            } if channel == "channel"
+        ));
+    }
+
+    #[test]
+    fn test_require_registered_accepts_local_and_network_clients() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr.clone(), "nickname".to_string());
+        create_client_for_test(&session, addr, "ghost".to_string());
+        let _ = listener.accept().unwrap();
+        let (mut reader, _) = listener.accept().unwrap();
+        let network = create_network_for_test();
+        network
+            .clients
+            .write()
+            .unwrap()
+            .insert("remote".to_string(), RemoteClient::new(1));
+        session
+            .clients
+            .update("ghost", |client| client.connected = false);
+
+        assert!(require_registered(&"nickname".to_string(), &session, &network).is_ok());
+        assert!(require_registered(&"remote".to_string(), &session, &network).is_ok());
+        assert_eq!(
+            require_registered(&"unknown".to_string(), &session, &network),
+            Err(ServerError::ClientMustRegisterOrAuthenticate)
+        );
+        let result = require_registered(&"ghost".to_string(), &session, &network);
+
+        assert_eq!(result, Err(ServerError::ClientMustRegisterOrAuthenticate));
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::ErrorResponse {
+                response: ErrorResponse::NotRegistered
+            }
+        ));
+    }
+}
@@ -12,7 +12,7 @@ use crate::{
     socket::{inform_client, inform_network, write_socket},
 };
 
-use super::command_utils::read_lock_clients;
+use crate::locks::read_lock_clients;
 
 // DCC command structure
 // :client_who_request DCC command_type client_requested client_ip client_port
@@ -50,7 +50,10 @@ pub fn handle_dcc_command(
     //     _ => {}
     // }
 
-    if dcc_message.command == DccMessageType::Chat {
+    if matches!(
+        dcc_message.command,
+        DccMessageType::Chat | DccMessageType::SChat | DccMessageType::Relay
+    ) {
         let client_requested = dcc_message.parameters[0].to_owned();
         if let Some(client) = local_clients.get(&client_requested) {
             if client.connected {
@@ -59,7 +62,13 @@ pub fn handle_dcc_command(
                     &client_requested,
                     &DccMessage::serialize(dcc_message)?,
                 )?;
-            } else {
+            } else if dcc_message.command == DccMessageType::Relay {
+                // the relay is cancelled, so the client who sent it doesn't wait for an answer
+                if let Some((sender, cancel)) = relay_cancel_message(&dcc_message) {
+                    inform_client(session, &sender, &cancel)?;
+                }
+            } else if dcc_message.passive_token().is_none() {
+                // tells the client who sent the offer, unless it is passive, since it is not listening
                 let response = format!("DCC CLOSE {} NotConnected", client_requested);
                 let ip = dcc_message.parameters[1].to_owned();
                 let port = dcc_message.parameters[2].to_owned();
@@ -76,3 +85,40 @@ pub fn handle_dcc_command(
 
     Ok(())
 }
+
+/// Returns the client who sent a relayed file, and the message that cancels it because the requested
+/// client is not connected.
+/// Returns None if the message has no sender or file.
+/// # Arguments
+/// * `dcc_message` - The relay message sent to the requested client.
+fn relay_cancel_message(dcc_message: &DccMessage) -> Option<(String, String)> {
+    let sender = dcc_message.prefix.clone()?;
+    let requested_client = dcc_message.parameters.first()?;
+    let file_name = dcc_message.parameters.get(2)?;
+    let cancel = format!(":{requested_client} DCC RELAY {sender} CANCEL {file_name}");
+    Some((sender, cancel))
+}
+
+#[cfg(test)]
+mod dcc_tests {
+    use model::dcc::DccMessage;
+
+    use super::relay_cancel_message;
+
+    #[test]
+    fn test_relays_to_clients_not_connected_are_cancelled() {
+        let offer =
+            DccMessage::deserialize(":alice DCC RELAY bob OFFER photo.jpg 100 ab12".to_owned())
+                .unwrap();
+        assert_eq!(
+            relay_cancel_message(&offer),
+            Some((
+                "alice".to_owned(),
+                ":bob DCC RELAY alice CANCEL photo.jpg".to_owned()
+            ))
+        );
+
+        let without_sender = DccMessage::deserialize("DCC RELAY bob OFFER a 1".to_owned()).unwrap();
+        assert_eq!(relay_cancel_message(&without_sender), None);
+    }
+}
@@ -0,0 +1,247 @@
+use crate::{
+    commands::command_utils::get_current_timestamp, database::inform_database,
+    locks::write_lock_channels, server_errors::ServerError, socket::inform_client,
+};
+use model::{
+    filter::{FilterAction, FilterRule},
+    json::ToJson,
+    message::Message,
+    persistence::PersistenceType,
+    responses::{errors::ErrorResponse, replies::CommandResponse},
+    session::Session,
+};
+
+/// Handles the FILTER command, which manages the rules of the content filter of a channel,
+/// applied to its messages while it has the `+G` mode. With only the channel it lists the rules,
+/// with `+<pattern> [CENSOR|BLOCK]` or `<pattern> [CENSOR|BLOCK]` it adds a rule, censoring by
+/// default, and with `-<pattern>` it removes it. Adding a pattern that is already a rule changes
+/// its action.
+/// Only the operators of the channel can use it. The rules are saved with the channel in the
+/// database, and they only apply to the messages sent through this server.
+/// # Errors
+/// ServerError::ChannelNotFound if the channel doesn't exist.
+/// ServerError::UserNotOperator if the client is not an operator of the channel.
+/// ServerError::InvalidParameters if the pattern is empty or has a `,`, or the action is unknown.
+///
+/// For each error a message is sent to the client to inform them of the error.
+pub fn handle_filter_command(
+    message: Message,
+    nickname: String,
+    session: &Session,
+) -> Result<(), ServerError> {
+    let channel_name = message.parameters[0].to_owned();
+    let mut channels = write_lock_channels(session)?;
+    let channel = match channels.get_mut(&channel_name) {
+        Some(channel) => channel,
+        None => {
+            drop(channels);
+            let response = ErrorResponse::NoSuchChannel {
+                channel: channel_name,
+            }
+            .to_string();
+            inform_client(session, &nickname, &response)?;
+            return Err(ServerError::ChannelNotFound);
+        }
+    };
+    if !channel.operators.contains(&nickname) {
+        drop(channels);
+        let response = ErrorResponse::ChanOPrivsNeeded {
+            channel: channel_name,
+        }
+        .to_string();
+        inform_client(session, &nickname, &response)?;
+        return Err(ServerError::UserNotOperator);
+    }
+
+    if let Some(parameter) = message.parameters.get(1) {
+        let (adding, pattern) = match parameter.strip_prefix('-') {
+            Some(pattern) => (false, pattern),
+            None => (true, parameter.strip_prefix('+').unwrap_or(parameter)),
+        };
+        let action = match message.parameters.get(2) {
+            Some(name) => FilterAction::parse(name),
+            None => Some(FilterAction::Censor),
+        };
+        let action = match action {
+            Some(action) if !pattern.is_empty() && !pattern.contains(',') => action,
+            _ => {
+                drop(channels);
+                let response = ErrorResponse::NeedMoreParams {
+                    command: "FILTER".to_string(),
+                }
+                .to_string();
+                inform_client(session, &nickname, &response)?;
+                return Err(ServerError::InvalidParameters);
+            }
+        };
+        let pattern = pattern.to_lowercase();
+        channel.filters.retain(|rule| rule.pattern != pattern);
+        if adding {
+            println!(
+                "{:?} added the filter {:?} to {:?}",
+                nickname, pattern, channel_name
+            );
+            let rule = FilterRule::new(
+                &pattern,
+                action,
+                nickname.to_owned(),
+                get_current_timestamp(),
+            );
+            channel.filters.push(rule);
+        }
+        inform_database(
+            PersistenceType::ChannelUpdate(channel.name.to_owned()),
+            channel.to_json(),
+            session,
+        )?;
+    }
+
+    let rules = channel.filters.clone();
+    drop(channels);
+    for rule in rules {
+        let response = CommandResponse::FilterList {
+            channel: channel_name.to_owned(),
+            pattern: rule.pattern,
+            action: rule.action.name().to_string(),
+        }
+        .to_string();
+        inform_client(session, &nickname, &response)?;
+    }
+    let response = CommandResponse::EndOfFilterList {
+        channel: channel_name,
+    }
+    .to_string();
+    inform_client(session, &nickname, &response)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod filter_tests {
+    use model::consts::MAX_MSG_SIZE;
+    use std::{
+        io::Read,
+        net::{TcpListener, TcpStream},
+    };
+
+    use model::{
+        channel::Channel,
+        filter::FilterAction,
+        message::MessageType,
+        persistence::PersistenceType,
+        responses::{errors::ErrorResponse, replies::CommandResponse, response::Response},
+        session::Session,
+    };
+
+    use crate::{
+        commands::{
+            command_utils::{
+                create_client_for_test, create_message_for_test, create_session_for_test,
+            },
+            filter::handle_filter_command,
+        },
+        database::{handle_database, DatabasePaths},
+        locks::{read_lock_channels, write_lock_channels},
+        server_errors::ServerError,
+    };
+
+    fn read_response_for_test(reader: &mut TcpStream) -> Response {
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        Response::serialize(String::from_utf8(msg).unwrap()).unwrap()
+    }
+
+    fn create_session_with_channel_for_test() -> (Session, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr, "nickname".to_string());
+        let (reader, _) = listener.accept().unwrap();
+        let mut channel = Channel::new(
+            "&rust".to_string(),
+            "".to_string(),
+            vec!["nickname".to_string()],
+        );
+        channel.operators.push("nickname".to_string());
+        write_lock_channels(&session)
+            .unwrap()
+            .insert(channel.name.to_owned(), channel);
+        (session, reader)
+    }
+
+    fn send_filter_for_test(session: &Session, parameters: &[&str]) -> Result<(), ServerError> {
+        let parameters = parameters.iter().map(|p| p.to_string()).collect();
+        let message = create_message_for_test(MessageType::Filter, parameters);
+        handle_filter_command(message, "nickname".to_string(), session)
+    }
+
+    #[test]
+    fn test_filter_adds_lists_and_removes_rules() {
+        let (session, mut reader) = create_session_with_channel_for_test();
+
+        assert!(send_filter_for_test(&session, &["&rust", "+Spam*", "block"]).is_ok());
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::CommandResponse {
+                response: CommandResponse::FilterList { channel, pattern, action }
+            } if channel == "&rust" && pattern == "spam*" && action == "block"
+        ));
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::CommandResponse {
+                response: CommandResponse::EndOfFilterList { channel }
+            } if channel == "&rust"
+        ));
+        let channels = read_lock_channels(&session).unwrap();
+        let rules = &channels["&rust"].filters;
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].action, FilterAction::Block);
+        assert_eq!(rules[0].set_by, "nickname");
+        drop(channels);
+
+        assert!(send_filter_for_test(&session, &["&rust", "-SPAM*"]).is_ok());
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::CommandResponse {
+                response: CommandResponse::EndOfFilterList { .. }
+            }
+        ));
+        assert!(read_lock_channels(&session).unwrap()["&rust"]
+            .filters
+            .is_empty());
+    }
+
+    #[test]
+    fn test_filter_requires_an_operator_and_a_known_action() {
+        let (session, mut reader) = create_session_with_channel_for_test();
+
+        let result = send_filter_for_test(&session, &["&rust", "spam", "delete"]);
+        assert_eq!(result, Err(ServerError::InvalidParameters));
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::ErrorResponse {
+                response: ErrorResponse::NeedMoreParams { .. }
+            }
+        ));
+
+        write_lock_channels(&session)
+            .unwrap()
+            .get_mut("&rust")
+            .unwrap()
+            .operators
+            .clear();
+        let result = send_filter_for_test(&session, &["&rust", "spam"]);
+        assert_eq!(result, Err(ServerError::UserNotOperator));
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::ErrorResponse {
+                response: ErrorResponse::ChanOPrivsNeeded { channel }
+            } if channel == "&rust"
+        ));
+        assert!(read_lock_channels(&session).unwrap()["&rust"]
+            .filters
+            .is_empty());
+    }
+}
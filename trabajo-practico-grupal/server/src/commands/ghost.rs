@@ -0,0 +1,259 @@
+use std::net::Shutdown;
+
+use crate::locks::write_lock_clients;
+use crate::{password::verify_password, server_errors::ServerError, socket::inform_client};
+use model::{
+    message::Message,
+    responses::{errors::ErrorResponse, replies::CommandResponse},
+    session::Session,
+};
+
+/// Handles the GHOST command sent by a registered client.
+/// It disconnects the stale session that holds a nickname, if the password given is the one of the nickname.
+/// # Arguments
+/// * `message` - The message sent by the client, with the nickname of the ghost and its password.
+/// * `nickname` - The nickname of the client that sent the message.
+/// * `session` - The session of the current server.
+/// # Errors
+/// * ServerError::InvalidParameters - If the nickname or the password are missing.
+/// * ServerError::InvalidPassword - If the password is not the one of the nickname.
+/// * ServerError::ClientNotFound - If there is no client with the nickname.
+/// * ServerError::ClientNotConnected - If the client with the nickname is not connected.
+///
+/// For each error it sends an error response to the client.
+pub fn handle_ghost_command(
+    message: Message,
+    nickname: String,
+    session: &Session,
+) -> Result<(), ServerError> {
+    if message.parameters.len() < 2 {
+        let response = ErrorResponse::NeedMoreParams {
+            command: "GHOST".to_string(),
+        }
+        .to_string();
+        inform_client(session, &nickname, &response)?;
+        return Err(ServerError::InvalidParameters);
+    }
+    let ghost = message.parameters[0].to_owned();
+    let password = message.parameters[1].to_owned();
+
+    match drop_ghost(&ghost, &password, session) {
+        Ok(_) => {
+            let response = CommandResponse::Ghost { nickname: ghost }.to_string();
+            inform_client(session, &nickname, &response)?;
+            Ok(())
+        }
+        Err(ServerError::InvalidPassword) => {
+            let response = ErrorResponse::PasswordMismatch.to_string();
+            inform_client(session, &nickname, &response)?;
+            Err(ServerError::InvalidPassword)
+        }
+        Err(ServerError::ClientNotFound) | Err(ServerError::ClientNotConnected) => {
+            let response = ErrorResponse::NoSuchNick {
+                nickname: ghost.to_owned(),
+            }
+            .to_string();
+            inform_client(session, &nickname, &response)?;
+            Err(ServerError::ClientNotConnected)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Drops the session of a connected client, so its nickname can be taken back.
+/// The socket of the session is shut down, which ends the thread that was reading from it.
+/// # Arguments
+/// * `nickname` - The nickname held by the stale session.
+/// * `password` - The password of the nickname.
+/// * `session` - The session of the current server.
+/// # Errors
+/// * ServerError::ClientNotFound - If there is no client with the nickname.
+/// * ServerError::InvalidPassword - If the client has no password or it is not the one given.
+/// * ServerError::ClientNotConnected - If the client is not connected.
+pub fn drop_ghost(nickname: &str, password: &str, session: &Session) -> Result<(), ServerError> {
+    let mut clients = write_lock_clients(session)?;
+    let client = match clients.get_mut(nickname) {
+        Some(client) => client,
+        None => return Err(ServerError::ClientNotFound),
+    };
+    if !matches!(&client.password, Some(saved) if verify_password(saved, password)) {
+        return Err(ServerError::InvalidPassword);
+    }
+    if !client.connected {
+        return Err(ServerError::ClientNotConnected);
+    }
+    client.connected = false;
+    drop(clients);
+
+    if let Some(socket) = session.sockets.unregister(nickname) {
+        if socket.shutdown(Shutdown::Both).is_err() {
+            println!("Socket of ghost {} was already closed", nickname);
+        }
+    }
+    println!("Ghost {} disconnected", nickname);
+    Ok(())
+}
+
+#[cfg(test)]
+mod ghost_tests {
+    use model::consts::MAX_MSG_SIZE;
+    use std::{
+        collections::HashMap,
+        io::Read,
+        net::TcpListener,
+        sync::{Arc, RwLock},
+    };
+
+    use model::{
+        link_queues::LinkQueues,
+        message::MessageType,
+        network::Network,
+        persistence::PersistenceType,
+        responses::{errors::ErrorResponse, replies::CommandResponse, response::Response},
+        server::Server,
+    };
+
+    use crate::locks::{lock_sockets, read_lock_clients, write_lock_clients};
+    use crate::{
+        commands::{
+            command_utils::{
+                create_client_for_test, create_message_for_test, create_session_for_test,
+            },
+            ghost::handle_ghost_command,
+        },
+        database::{handle_database, DatabasePaths},
+        registration::handle_registration,
+        server_errors::ServerError,
+    };
+
+    fn create_network_for_test() -> Network {
+        Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        }
+    }
+
+    #[test]
+    fn test_ghost_command_drops_stale_session() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr.clone(), "ghost".to_string());
+        let (mut ghost_reader, _) = listener.accept().unwrap();
+        create_client_for_test(&session, addr, "owner".to_string());
+        let (mut owner_reader, _) = listener.accept().unwrap();
+        write_lock_clients(&session)
+            .unwrap()
+            .get_mut("ghost")
+            .unwrap()
+            .password = Some("secret".to_string());
+
+        let message = create_message_for_test(
+            MessageType::Ghost,
+            vec!["ghost".to_string(), "secret".to_string()],
+        );
+        let result = handle_ghost_command(message, "owner".to_string(), &session);
+
+        assert!(result.is_ok());
+        assert!(!read_lock_clients(&session).unwrap()["ghost"].connected);
+        assert!(!lock_sockets(&session).unwrap().contains_key("ghost"));
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        assert_eq!(ghost_reader.read(&mut buf).unwrap(), 0);
+
+        owner_reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        let response = Response::serialize(String::from_utf8(msg).unwrap()).unwrap();
+        match response {
+            Response::CommandResponse {
+                response: CommandResponse::Ghost { nickname },
+            } => assert_eq!(nickname, "ghost"),
+            _ => panic!("expected a ghost response"),
+        }
+    }
+
+    #[test]
+    fn test_ghost_command_with_wrong_password() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr.clone(), "ghost".to_string());
+        let (_ghost_reader, _) = listener.accept().unwrap();
+        create_client_for_test(&session, addr, "owner".to_string());
+        let (mut owner_reader, _) = listener.accept().unwrap();
+        write_lock_clients(&session)
+            .unwrap()
+            .get_mut("ghost")
+            .unwrap()
+            .password = Some("secret".to_string());
+
+        let message = create_message_for_test(
+            MessageType::Ghost,
+            vec!["ghost".to_string(), "wrong".to_string()],
+        );
+        let result = handle_ghost_command(message, "owner".to_string(), &session);
+
+        assert_eq!(Err(ServerError::InvalidPassword), result);
+        assert!(read_lock_clients(&session).unwrap()["ghost"].connected);
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        owner_reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        let response = Response::serialize(String::from_utf8(msg).unwrap()).unwrap();
+        assert!(matches!(
+            response,
+            Response::ErrorResponse {
+                response: ErrorResponse::PasswordMismatch
+            }
+        ));
+    }
+
+    #[test]
+    fn test_reclaim_command_takes_back_the_nickname() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let network = create_network_for_test();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr, "ghost".to_string());
+        let (_ghost_reader, _) = listener.accept().unwrap();
+        write_lock_clients(&session)
+            .unwrap()
+            .get_mut("ghost")
+            .unwrap()
+            .password = Some("secret".to_string());
+
+        let mut nickname = None;
+        let mut user_parameters = None;
+        let mut password = None;
+        let message = create_message_for_test(
+            MessageType::Reclaim,
+            vec!["ghost".to_string(), "secret".to_string()],
+        );
+        let result = handle_registration(
+            message,
+            &mut nickname,
+            &mut user_parameters,
+            &mut password,
+            &session,
+            &network,
+        );
+
+        let client = result.unwrap().unwrap();
+        assert_eq!(client.nickname, "ghost");
+        assert_eq!(nickname, Some("ghost".to_string()));
+        assert!(!lock_sockets(&session).unwrap().contains_key("ghost"));
+    }
+}
@@ -0,0 +1,173 @@
+use super::registry::registry;
+use crate::{server_errors::ServerError, socket::inform_client};
+use model::{
+    message::Message,
+    responses::{errors::ErrorResponse, replies::CommandResponse},
+    session::Session,
+};
+
+/// Handles the HELP command, with the usage of the commands of the registry.
+/// Without parameters it lists the commands with their descriptions, and with the name of
+/// a command it sends its syntax and description. Both end with the EndOfHelp response.
+/// # Arguments
+/// * `message` - The message sent by the client, with the name of a command as its optional parameter.
+/// * `nickname` - The nickname of the client that sent the message.
+/// * `session` - The session of the current server.
+pub fn handle_help_command(
+    message: Message,
+    nickname: String,
+    session: &Session,
+) -> Result<(), ServerError> {
+    let topic = match message.parameters.first() {
+        Some(topic) => topic.to_uppercase(),
+        None => "*".to_string(),
+    };
+
+    let mut texts = vec![];
+    if topic == "*" {
+        for command in registry().commands() {
+            texts.push(format!("{} - {}", command.name(), command.description));
+        }
+    } else {
+        match registry().find(&topic) {
+            Some(command) => {
+                texts.push(format!("Syntax: {}", command.syntax));
+                texts.push(command.description.to_string());
+            }
+            None => {
+                let response = ErrorResponse::HelpNotFound { topic }.to_string();
+                inform_client(session, &nickname, &response)?;
+                return Err(ServerError::InvalidParameters);
+            }
+        }
+    }
+
+    for text in texts {
+        let response = CommandResponse::HelpText {
+            topic: topic.to_owned(),
+            text,
+        }
+        .to_string();
+        inform_client(session, &nickname, &response)?;
+    }
+    let response = CommandResponse::EndOfHelp { topic }.to_string();
+    inform_client(session, &nickname, &response)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod help_tests {
+    use model::consts::MAX_MSG_SIZE;
+    use std::{
+        io::Read,
+        net::{TcpListener, TcpStream},
+    };
+
+    use model::{
+        message::MessageType,
+        persistence::PersistenceType,
+        responses::{errors::ErrorResponse, replies::CommandResponse, response::Response},
+    };
+
+    use crate::{
+        commands::{
+            command_utils::{
+                create_client_for_test, create_message_for_test, create_session_for_test,
+            },
+            help::handle_help_command,
+            registry::registry,
+        },
+        database::{handle_database, DatabasePaths},
+        server_errors::ServerError,
+    };
+
+    fn read_response_for_test(reader: &mut TcpStream) -> Response {
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        Response::serialize(String::from_utf8(msg).unwrap()).unwrap()
+    }
+
+    fn send_help_for_test(parameters: Vec<String>) -> (Result<(), ServerError>, Vec<Response>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr, "nickname".to_string());
+        let (mut reader, _) = listener.accept().unwrap();
+
+        let message = create_message_for_test(MessageType::Help, parameters);
+        let result = handle_help_command(message, "nickname".to_string(), &session);
+
+        let mut responses = vec![];
+        loop {
+            let response = read_response_for_test(&mut reader);
+            let end = !matches!(
+                response,
+                Response::CommandResponse {
+                    response: CommandResponse::HelpText { .. }
+                }
+            );
+            responses.push(response);
+            if end {
+                break;
+            }
+        }
+        (result, responses)
+    }
+
+    #[test]
+    fn test_help_command_lists_every_command() {
+        let (result, responses) = send_help_for_test(vec![]);
+
+        assert!(result.is_ok());
+        let commands = registry().commands().len();
+        assert_eq!(responses.len(), commands + 1);
+        match &responses[0] {
+            Response::CommandResponse {
+                response: CommandResponse::HelpText { topic, text },
+            } => {
+                assert_eq!(topic, "*");
+                assert!(text.starts_with("PASS - "));
+            }
+            _ => panic!("expected a help text"),
+        }
+        assert!(matches!(
+            &responses[commands],
+            Response::CommandResponse {
+                response: CommandResponse::EndOfHelp { topic }
+            } if topic == "*"
+        ));
+    }
+
+    #[test]
+    fn test_help_command_with_a_command_sends_its_syntax() {
+        let (result, responses) = send_help_for_test(vec!["join".to_string()]);
+
+        assert!(result.is_ok());
+        assert_eq!(responses.len(), 3);
+        match &responses[0] {
+            Response::CommandResponse {
+                response: CommandResponse::HelpText { topic, text },
+            } => {
+                assert_eq!(topic, "JOIN");
+                assert_eq!(text, "Syntax: JOIN <channel>{,<channel>} [<key>{,<key>}]");
+            }
+            _ => panic!("expected a help text"),
+        }
+    }
+
+    #[test]
+    fn test_help_command_with_an_unknown_command() {
+        let (result, responses) = send_help_for_test(vec!["unknown".to_string()]);
+
+        assert_eq!(result, Err(ServerError::InvalidParameters));
+        assert!(matches!(
+            &responses[0],
+            Response::ErrorResponse {
+                response: ErrorResponse::HelpNotFound { topic }
+            } if topic == "UNKNOWN"
+        ));
+    }
+}
@@ -1,5 +1,7 @@
 use model::{
     channelflag::ChannelFlag,
+    consts::NETWORK_CHANNEL_PREFIX,
+    json::ToJson,
     message::Message,
     network::Network,
     persistence::PersistenceType,
@@ -7,17 +9,21 @@ use model::{
     session::Session,
 };
 
+use super::command_utils::{
+    get_current_timestamp, require_channel_name, require_params, require_registered,
+};
 use crate::{
     database::inform_database,
     server_errors::ServerError,
     socket::{inform_client, inform_network},
 };
 
-use super::command_utils::{read_lock_clients, write_lock_channels};
+use crate::locks::{read_lock_network_clients, write_lock_channels};
 
 /// Handles the invite message, which invites a client to a channel.
 /// #Errors
-/// ServerError::InvalidParameters if the message has no parameters or the parameters are invalid.
+/// ServerError::ClientMustRegisterOrAuthenticate if the sender is not registered in the network.
+/// ServerError::InvalidParameters if the message has less than two parameters.
 /// ServerError::NoSuchChannel if the channel does not exist.
 /// Server Error::ChannelMustStartWithHashOrAmpersand if the channel does not start with a # or &.
 /// ServerError::UserAlreadyInChannel if the user is already in the channel.
@@ -25,6 +31,8 @@ use super::command_utils::{read_lock_clients, write_lock_channels};
 /// ServerError::NoSuchUser if the user does not exist.
 /// ServerError::ClientNotFound if the client is not found.
 /// ServerError::UserIsBanned if the user is banned from the channel.
+///
+/// If the user silenced the client that sent the invitation, it is dropped.
 pub fn handle_invite_command(
     message: Message,
     nickname: String,
@@ -32,17 +40,9 @@ pub fn handle_invite_command(
     network: &Network,
     server_name: &String,
 ) -> Result<(), ServerError> {
-    if message.parameters.is_empty() || message.parameters.len() < 2 {
-        return Err(ServerError::InvalidParameters);
-    }
-    if !message.parameters[1].starts_with('&') && !message.parameters[1].starts_with('#') {
-        let error_response = ErrorResponse::NoSuchChannel {
-            channel: message.parameters[1].clone(),
-        }
-        .to_string();
-        inform_client(session, &nickname, error_response.as_str())?;
-        return Err(ServerError::ChannelMustStartWithHashOrAmpersand);
-    }
+    require_registered(&nickname, session, network)?;
+    require_params(&message, 2, "INVITE", &nickname, session)?;
+    require_channel_name(&message.parameters[1], &nickname, session)?;
 
     let user_to_invite = message.parameters[0].to_owned();
     let channel_name = message.parameters[1].to_owned();
@@ -58,7 +58,7 @@ pub fn handle_invite_command(
                 inform_client(session, &nickname, response.to_string().as_str())?;
                 return Err(ServerError::ChannelIsInviteOnly);
             }
-            if channel.banned_users.contains(&user_to_invite) {
+            if channel.is_banned(&user_to_invite, get_current_timestamp()) {
                 let response = (ErrorResponse::BannedFromChannel {
                     channel: channel.name.to_string(),
                 })
@@ -93,9 +93,12 @@ pub fn handle_invite_command(
                     return Err(ServerError::ChannelIsFull);
                 }
             }
-            match read_lock_clients(session)?.get(&user_to_invite) {
+            match session.clients.get(&user_to_invite) {
                 Some(c) => {
-                    if let Some(away_msg) = c.away_message.to_owned() {
+                    if c.is_silencing(&nickname) {
+                        return Ok(());
+                    }
+                    if let Some(away_msg) = c.away_message {
                         let response = CommandResponse::Away {
                             nickname: user_to_invite.to_string(),
                             message: away_msg.to_string(),
@@ -105,10 +108,10 @@ pub fn handle_invite_command(
                         println!("{} is away: {}", user_to_invite, away_msg);
                         return Ok(());
                     }
-                    channel.users.push(user_to_invite.to_owned());
+                    channel.add_member(&user_to_invite);
                     inform_database(
                         PersistenceType::ChannelUpdate(channel.name.to_owned()),
-                        channel.to_string(),
+                        channel.to_json(),
                         session,
                     )?;
                     let response = CommandResponse::Inviting {
@@ -124,13 +127,13 @@ pub fn handle_invite_command(
                 }
 
                 None => {
-                    if channel.name.starts_with('#') {
-                        let network_clients = network.clients.read()?;
+                    if channel.name.starts_with(NETWORK_CHANNEL_PREFIX) {
+                        let network_clients = read_lock_network_clients(network)?;
                         if network_clients.get(&user_to_invite).is_some() {
-                            channel.users.push(user_to_invite.to_owned());
+                            channel.add_member(&user_to_invite);
                             inform_database(
                                 PersistenceType::ChannelUpdate(channel.name.to_owned()),
-                                channel.to_string(),
+                                channel.to_json(),
                                 session,
                             )?;
                             let response = CommandResponse::Inviting {
@@ -145,7 +148,7 @@ pub fn handle_invite_command(
                             println!("{} joined {}", user_to_invite, channel_name);
 
                             let mut msg = message;
-                            msg.prefix = Some(nickname);
+                            msg.set_nick_prefix(&nickname);
                             let msg = Message::deserialize(msg)?;
                             inform_network(network, server_name, &msg)?;
                         }
@@ -173,6 +176,7 @@ pub fn handle_invite_command(
 
 #[cfg(test)]
 mod invite_tests {
+    use model::consts::MAX_MSG_SIZE;
     use std::{
         collections::HashMap,
         io::Read,
@@ -181,19 +185,22 @@ mod invite_tests {
         vec,
     };
 
+    use crate::locks::{read_lock_channels, write_lock_channels, write_lock_clients};
     use crate::{
         commands::command_utils::{
             create_client_for_test, create_message_for_test, create_session_for_test,
-            read_lock_channels, write_lock_channels, write_lock_clients,
+            get_current_timestamp,
         },
-        database::handle_database,
+        database::{handle_database, DatabasePaths},
         server_errors::ServerError,
     };
 
     use super::handle_invite_command;
     use model::{
+        ban::Ban,
         channel::Channel,
         channelflag::ChannelFlag,
+        link_queues::LinkQueues,
         message::MessageType,
         network::Network,
         persistence::PersistenceType,
@@ -217,16 +224,17 @@ mod invite_tests {
         }));
 
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
 
         let client = create_client_for_test(
@@ -245,11 +253,7 @@ mod invite_tests {
         let mut channel = Channel::new("#channel_test".to_string(), "".to_string(), vec![]);
         channel.modes = vec![ChannelFlag::InviteOnly];
         channel.users = vec![client.nickname.clone()];
-        session
-            .channels
-            .write()
-            .unwrap()
-            .insert(channel.clone().name, channel.clone());
+        session.channels.insert(channel.clone());
 
         let message = create_message_for_test(
             MessageType::Invite,
@@ -266,7 +270,7 @@ mod invite_tests {
 
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -309,16 +313,17 @@ mod invite_tests {
         }));
 
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -329,11 +334,7 @@ mod invite_tests {
         channel.modes = vec![ChannelFlag::InviteOnly];
         channel.users = vec![client.nickname.clone()];
         channel.operators = vec![client.nickname.clone()];
-        session
-            .channels
-            .write()
-            .unwrap()
-            .insert(channel.clone().name, channel.clone());
+        session.channels.insert(channel.clone());
 
         let message = create_message_for_test(
             MessageType::Invite,
@@ -348,7 +349,7 @@ mod invite_tests {
             &"test".to_string(),
         );
         let (mut reader, _addr) = listener.accept().unwrap();
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -376,7 +377,7 @@ mod invite_tests {
 
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -428,27 +429,24 @@ mod invite_tests {
         }));
 
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
 
         let mut channel = Channel::new("&channel_test".to_string(), "".to_string(), vec![]);
         channel.users = vec![client.nickname.clone()];
-        session
-            .channels
-            .write()
-            .unwrap()
-            .insert(channel.name.to_string(), channel.clone());
+        session.channels.insert(channel.clone());
 
         let message = create_message_for_test(
             MessageType::Invite,
@@ -469,7 +467,7 @@ mod invite_tests {
 
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -514,16 +512,17 @@ mod invite_tests {
         }));
 
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -531,11 +530,7 @@ mod invite_tests {
             create_client_for_test(&session, address_port.to_string(), "nickname2".to_string());
 
         let channel = Channel::new("#channel_test".to_string(), "".to_string(), vec![]);
-        session
-            .channels
-            .write()
-            .unwrap()
-            .insert(channel.name.to_string(), channel.clone());
+        session.channels.insert(channel.clone());
 
         let message = create_message_for_test(
             MessageType::Invite,
@@ -551,7 +546,7 @@ mod invite_tests {
         );
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -598,16 +593,17 @@ mod invite_tests {
         }));
 
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -628,7 +624,7 @@ mod invite_tests {
         );
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -668,16 +664,17 @@ mod invite_tests {
         }));
 
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -686,11 +683,7 @@ mod invite_tests {
 
         let mut channel = Channel::new("#channel_test".to_string(), "".to_string(), vec![]);
         channel.users = vec![client.nickname.to_string()];
-        session
-            .channels
-            .write()
-            .unwrap()
-            .insert(channel.name.to_string(), channel.clone());
+        session.channels.insert(channel.clone());
 
         let mut channels_lock = write_lock_channels(&session).unwrap();
         channels_lock.insert(channel.name.clone(), channel.clone());
@@ -709,7 +702,7 @@ mod invite_tests {
             &"test".to_string(),
         );
         let (mut reader, _addr) = listener.accept().unwrap();
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -737,7 +730,7 @@ mod invite_tests {
 
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -789,16 +782,17 @@ mod invite_tests {
         }));
 
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -818,7 +812,7 @@ mod invite_tests {
         );
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -861,16 +855,17 @@ mod invite_tests {
         }));
 
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -879,12 +874,12 @@ mod invite_tests {
 
         let mut channel = Channel::new("#channel_test".to_string(), "".to_string(), vec![]);
         channel.users = vec![client.nickname.to_string()];
-        channel.banned_users.push(client2.nickname.to_string());
-        session
-            .channels
-            .write()
-            .unwrap()
-            .insert(channel.name.to_string(), channel.clone());
+        channel.banned_users.push(Ban::new(
+            client2.nickname.to_string(),
+            client.nickname.to_string(),
+            0,
+        ));
+        session.channels.insert(channel.clone());
 
         let mut channels_lock = write_lock_channels(&session).unwrap();
         channels_lock.insert(channel.name.clone(), channel.clone());
@@ -904,7 +899,7 @@ mod invite_tests {
         );
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -932,7 +927,7 @@ mod invite_tests {
         assert!(!channel.users.contains(&client2.nickname.to_string()));
         assert!(channel.users.contains(&client.nickname.to_string()));
         assert!(!channel.operators.contains(&client2.nickname.to_string()));
-        assert!(channel.banned_users.contains(&client2.nickname.to_string()));
+        assert!(channel.is_banned(&client2.nickname, get_current_timestamp()));
         drop(channels_lock);
         assert_eq!(Err(ServerError::UserIsBanned), result);
     }
@@ -952,16 +947,17 @@ mod invite_tests {
         }));
 
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -970,11 +966,7 @@ mod invite_tests {
 
         let mut channel = Channel::new("#channel_test".to_string(), "".to_string(), vec![]);
         channel.users = vec![client.nickname.to_string(), client2.nickname.to_string()];
-        session
-            .channels
-            .write()
-            .unwrap()
-            .insert(channel.name.to_string(), channel.clone());
+        session.channels.insert(channel.clone());
 
         let mut channels_lock = write_lock_channels(&session).unwrap();
         channels_lock.insert(channel.name.clone(), channel.clone());
@@ -994,7 +986,7 @@ mod invite_tests {
         );
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -1043,16 +1035,17 @@ mod invite_tests {
         }));
 
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -1062,11 +1055,7 @@ mod invite_tests {
         let mut channel = Channel::new("#channel_test".to_string(), "".to_string(), vec![]);
         channel.users = vec![client.nickname.to_string()];
         channel.limit = Some(1);
-        session
-            .channels
-            .write()
-            .unwrap()
-            .insert(channel.name.to_string(), channel.clone());
+        session.channels.insert(channel.clone());
 
         let mut channels_lock = write_lock_channels(&session).unwrap();
         channels_lock.insert(channel.name.clone(), channel.clone());
@@ -1086,7 +1075,7 @@ mod invite_tests {
         );
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -1134,16 +1123,17 @@ mod invite_tests {
         }));
 
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -1158,11 +1148,7 @@ mod invite_tests {
 
         let mut channel = Channel::new("#channel_test".to_string(), "".to_string(), vec![]);
         channel.users = vec![client.nickname.to_string()];
-        session
-            .channels
-            .write()
-            .unwrap()
-            .insert(channel.name.to_string(), channel.clone());
+        session.channels.insert(channel.clone());
 
         let mut channels_lock = write_lock_channels(&session).unwrap();
         channels_lock.insert(channel.name.clone(), channel.clone());
@@ -1182,7 +1168,7 @@ mod invite_tests {
         );
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -1,34 +1,70 @@
-use super::command_utils::write_lock_channels;
+use super::command_utils::{
+    get_current_timestamp, require_channel_name, require_max_targets, require_params,
+    require_registered, TargetResults,
+};
+use crate::locks::{
+    read_lock_channels, read_lock_clients, read_lock_config, read_lock_server, write_lock_channels,
+};
 use crate::{
     database::inform_database,
     server_errors::ServerError,
-    socket::{inform_client, inform_network},
+    socket::{inform_client, inform_clients_shared, inform_network},
 };
 use model::{
     channel::Channel,
+    channel_stats::ChannelStats,
     channelflag::ChannelFlag,
+    config::ChannelCreation,
+    consts::NETWORK_CHANNEL_PREFIX,
+    events::ServerEvent,
+    json::ToJson,
     message::Message,
     network::Network,
     persistence::PersistenceType,
-    responses::{errors::ErrorResponse, replies::CommandResponse},
+    responses::{
+        errors::ErrorResponse, message::MessageResponse, replies::CommandResponse,
+        writer::SharedMessage,
+    },
     session::Session,
 };
+use std::collections::HashMap;
 
 /// Handles the join command, which joins a client to a channel. If the channel does not exist, it creates it.
 /// # Errors
+/// ServerError::ClientMustRegisterOrAuthenticate if the sender is not registered in the network.
 /// ServerError::InvalidParameters if the message does not have the correct number of parameters.
-/// ServerError::ChannelMustStartWithHashOrAmpersand if the channel name is invalid.
+///
+/// Every channel is processed even if joining another one fails, and these errors are saved for
+/// each channel in the results returned:
+/// ServerError::ChannelMustStartWithHashOrAmpersand if the channel name doesn't start with one of the
+/// channel types of the server or is longer than its maximum length.
 /// ServerError::UserAlreadyInChannel if the user is already in the channel.
 /// ServerError::ChannelIsFull if the channel has a user limmit and is already full.
 /// ServerError::ChannelIsInviteOnly if the channel is invite only and the user is not invited.
 /// ServerError::ChannelIsBanned if the user is banned from the channel.
 /// ServerError::ChannelIsModerated if the channel is moderated and the user is not a channel operator.
-/// ServerError::ChannelIsSecret if the channel is secret and the user is not a channel operator.
-/// ServerError::ChannelIsPrivate if the channel is private and the user is not a channel operator.
 /// ServerError::IncorrectPassword if the channel is password protected and the password is incorrect.
+/// ServerError::ChannelCreationDenied if the channel does not exist and the channel creation policy
+/// of the server doesn't allow the user to create it.
+/// ServerError::TooManyChannels if the user already is a member of the maximum amount of channels
+/// of the server.
+///
+/// ServerError::TooManyTargets is returned, without joining any channel, if the message has more
+/// channels than the server allows.
+///
+/// If the channel is full or invite only and has a forward channel (`+f`), the user joins the
+/// forward channel instead, and its result is saved under the name of the forward channel.
+/// The user is informed of the forwarding before the topic of the channel joined.
+///
+/// The keys of the second parameter are paired with the channels by their position, so the
+/// channels without a key are listed first.
 ///
 /// For each error a message is sent to the client to infomr them of the error.
-/// In case of success, a message is sent to the client to know the updated status of the server.
+/// In case of success, a message is sent to the client to know the updated status of the server,
+/// and the other members of the channel are informed that the client joined.
+/// Only a failed write to the client stops the command, the failed writes to the other members
+/// and to the network are logged.
+/// Private and secret channels can be joined, they are only hidden from the listing commands.
 ///
 pub fn handle_join_command(
     message: Message,
@@ -36,131 +72,256 @@ pub fn handle_join_command(
     session: &Session,
     network: &Network,
     server_name: &String,
-) -> Result<(), ServerError> {
-    if message.parameters.is_empty() {
-        let error_response = (ErrorResponse::NeedMoreParams {
-            command: "JOIN".to_string(),
-        })
-        .to_string();
-        inform_client(session, nickname, error_response.as_str())?;
-        return Err(ServerError::InvalidParameters);
-    }
-    if !message.parameters[0].starts_with('&') && !message.parameters[0].starts_with('#') {
-        let error_response = (ErrorResponse::NoSuchChannel {
-            channel: message.parameters[0].clone(),
-        })
-        .to_string();
-        inform_client(session, nickname, error_response.as_str())?;
-        return Err(ServerError::ChannelMustStartWithHashOrAmpersand);
-    }
+) -> Result<TargetResults, ServerError> {
+    require_registered(nickname, session, network)?;
+    require_params(&message, 1, "JOIN", nickname, session)?;
+    require_max_targets(&message.parameters[0], nickname, session)?;
     let channels_name = message.parameters[0].to_owned();
     let channels_name = channels_name
         .split(',')
         .into_iter()
         .map(|a| a.trim())
         .collect::<Vec<_>>();
-    let mut channels_lock = write_lock_channels(session)?;
-    for name in channels_name {
-        match channels_lock.get_mut(name) {
-            Some(channel) => {
-                if channel.users.contains(nickname) {
-                    return Err(ServerError::UserAlreadyInChannel);
-                }
-                if channel.modes.contains(&ChannelFlag::InviteOnly) {
-                    let error_response = (ErrorResponse::InviteOnlyChannel {
-                        channel: name.to_string(),
-                    })
-                    .to_string();
-                    inform_client(session, nickname, error_response.as_str())?;
-                    return Err(ServerError::ChannelIsInviteOnly);
-                }
-                if channel.modes.contains(&ChannelFlag::Secret) {
-                    return Err(ServerError::ChannelIsSecret);
-                }
-                if channel.banned_users.contains(nickname) {
-                    let error_response = (ErrorResponse::BannedFromChannel {
+    let keys = match message.parameters.get(1) {
+        Some(keys) => keys.split(',').map(|key| key.trim()).collect::<Vec<_>>(),
+        None => vec![],
+    };
+    let can_create = can_create_channels(nickname, session, network)?;
+    let max_channels = max_channels_of(nickname, session)?;
+    let mut results = TargetResults::new();
+    for (position, name) in channels_name.into_iter().enumerate() {
+        let key = keys.get(position).copied().filter(|key| !key.is_empty());
+        if let Err(error) = require_channel_name(name, nickname, session) {
+            results.push(name, Err(error));
+            continue;
+        }
+        let mut channels_lock = write_lock_channels(session)?;
+        let mut joined = join_channel(
+            &mut channels_lock,
+            name,
+            nickname,
+            key,
+            (can_create, max_channels),
+            session,
+        )
+        .map(|topic| (name.to_string(), topic));
+        if let Err((_, ServerError::ChannelIsFull | ServerError::ChannelIsInviteOnly)) = joined {
+            if let Some(forwarded) =
+                forward_join(&mut channels_lock, name, nickname, max_channels, session)
+            {
+                joined = Ok(forwarded);
+            }
+        }
+        drop(channels_lock);
+        match joined {
+            Ok((channel, topic)) => {
+                let mut message = message.to_owned();
+                message.parameters.truncate(1);
+                if channel != name {
+                    let response = ErrorResponse::LinkChannel {
                         channel: name.to_string(),
-                    })
-                    .to_string();
-                    inform_client(session, nickname, error_response.as_str())?;
-                    return Err(ServerError::UserIsBanned);
-                }
-                if let Some(limit) = channel.limit {
-                    if channel.users.len() >= (limit as usize) {
-                        let error_response = (ErrorResponse::ChannelIsFull {
-                            channel: name.to_string(),
-                        })
-                        .to_string();
-                        inform_client(session, nickname, error_response.as_str())?;
-                        return Err(ServerError::ChannelIsFull);
-                    }
+                        target: channel.to_owned(),
+                    };
+                    inform_client(session, nickname, response.to_string().as_str())?;
+                } else if let Some(key) = key {
+                    message.parameters.push(key.to_string());
                 }
-                if channel.password.is_some() {
-                    if message.parameters.len() > 1 {
-                        if let Some(password) = &channel.password {
-                            if password != &message.parameters[1] {
-                                let error_response = (ErrorResponse::BadChannelKey {
-                                    channel: name.to_string(),
-                                })
-                                .to_string();
-                                inform_client(session, nickname, error_response.as_str())?;
-                                return Err(ServerError::IncorrectPassword);
-                            }
-                        }
-                    } else {
-                        let error_response = ErrorResponse::BadChannelKey {
-                            channel: name.to_string(),
-                        }
-                        .to_string();
-                        inform_client(session, nickname, error_response.as_str())?;
-                        return Err(ServerError::MustInsertPassword);
-                    }
-                }
-                channel.users.push(nickname.to_owned());
-                inform_database(
-                    PersistenceType::ChannelUpdate(channel.name.to_owned()),
-                    channel.to_string(),
-                    session,
-                )?;
-
                 let response = CommandResponse::Topic {
-                    channel: channel.name.to_string(),
-                    topic: channel.topic.to_string(),
+                    channel: channel.to_owned(),
+                    topic,
                 };
                 inform_client(session, nickname, response.to_string().as_str())?;
-                println!("Channel joined: {}", channel.name);
-                inform_network_about_join(
-                    name,
-                    message.to_owned(),
-                    nickname,
-                    network,
-                    server_name,
-                )?;
+                if let Err(e) =
+                    inform_network_about_join(&channel, message, nickname, network, server_name)
+                {
+                    println!("Error informing the network about the join: {:?}", e);
+                }
+                inform_channel_about_join(&channel, nickname, session)?;
+                results.push(&channel, Ok(()));
+                session.publish(ServerEvent::ChannelJoined {
+                    channel,
+                    nickname: nickname.to_owned(),
+                });
             }
-            None => {
-                let mut channel =
-                    Channel::new(name.to_string(), "".to_string(), vec![nickname.to_owned()]);
-                channel.operators.push(nickname.to_owned());
-                println!("Channel created: {}", channel.name);
-                inform_database(PersistenceType::ChannelSave, channel.to_string(), session)?;
-                channels_lock.insert(name.to_string(), channel);
-                let response = CommandResponse::Topic {
-                    channel: name.to_string(),
-                    topic: "".to_string(),
-                };
-                inform_client(session, nickname, response.to_string().as_str())?;
-                inform_network_about_join(
-                    name,
-                    message.to_owned(),
-                    nickname,
-                    network,
-                    server_name,
-                )?;
+            Err((error_response, error)) => {
+                if let Some(error_response) = error_response {
+                    inform_client(session, nickname, error_response.as_str())?;
+                }
+                results.push(name, Err(error));
             }
         }
     }
-    drop(channels_lock);
-    Ok(())
+    Ok(results)
+}
+
+/// Returns true if the channel creation policy of the server allows the client to create channels.
+/// The clients of other servers can always create them, as their server already applied its policy.
+fn can_create_channels(
+    nickname: &String,
+    session: &Session,
+    network: &Network,
+) -> Result<bool, ServerError> {
+    let policy = read_lock_config(session)?.channel_creation;
+    let clients = read_lock_clients(session)?;
+    let client = match clients.get(nickname) {
+        Some(client) => client,
+        None => return Ok(true),
+    };
+    Ok(match policy {
+        ChannelCreation::Anyone => true,
+        ChannelCreation::Registered => client.password.is_some(),
+        ChannelCreation::Operators => read_lock_server(network)?.operators.contains(nickname),
+    })
+}
+
+/// Returns the maximum amount of channels the client can be a member of, or none if it has no limit.
+/// The clients of other servers have no limit, as their server already applied its own.
+fn max_channels_of(nickname: &str, session: &Session) -> Result<Option<usize>, ServerError> {
+    if !session.clients.contains(nickname) {
+        return Ok(None);
+    }
+    Ok(match read_lock_config(session)?.max_channels {
+        0 => None,
+        max_channels => Some(max_channels),
+    })
+}
+
+/// Joins the client to a channel, creating it if it does not exist and the client can create it.
+/// It only updates the channels and the database, so it can be called while holding the lock
+/// of the channels, leaving the messages to the client and the network for after it is released.
+/// The limits are whether the client can create channels, and the maximum amount of channels it
+/// can be a member of.
+/// Returns the topic of the channel joined.
+/// # Errors
+/// It returns the error, along with the error response for the client if there is one.
+fn join_channel(
+    channels: &mut HashMap<String, Channel>,
+    name: &str,
+    nickname: &String,
+    key: Option<&str>,
+    limits: (bool, Option<usize>),
+    session: &Session,
+) -> Result<String, (Option<String>, ServerError)> {
+    let (can_create, max_channels) = limits;
+    let is_member = channels.get(name).is_some_and(|c| c.has_member(nickname));
+    if let Some(max_channels) = max_channels.filter(|_| !is_member) {
+        if channels.values().filter(|c| c.has_member(nickname)).count() >= max_channels {
+            let error_response = (ErrorResponse::TooManyChannels {
+                channel: name.to_string(),
+            })
+            .to_string();
+            return Err((Some(error_response), ServerError::TooManyChannels));
+        }
+    }
+    let channel = match channels.get_mut(name) {
+        Some(channel) => channel,
+        None if !can_create => {
+            let error_response = (ErrorResponse::ChannelCreationDenied {
+                channel: name.to_string(),
+            })
+            .to_string();
+            return Err((Some(error_response), ServerError::ChannelCreationDenied));
+        }
+        None => {
+            let mut channel =
+                Channel::new(name.to_string(), "".to_string(), vec![nickname.to_owned()]);
+            channel.operators.push(nickname.to_owned());
+            channel.stats = ChannelStats::new(get_current_timestamp());
+            println!("Channel created: {}", channel.name);
+            inform_database(PersistenceType::ChannelSave, channel.to_json(), session)
+                .map_err(|e| (None, e))?;
+            channels.insert(name.to_string(), channel);
+            return Ok("".to_string());
+        }
+    };
+    if channel.users.contains(nickname) {
+        return Err((None, ServerError::UserAlreadyInChannel));
+    }
+    if channel.modes.contains(&ChannelFlag::InviteOnly) {
+        let error_response = (ErrorResponse::InviteOnlyChannel {
+            channel: name.to_string(),
+        })
+        .to_string();
+        return Err((Some(error_response), ServerError::ChannelIsInviteOnly));
+    }
+    if channel.is_banned(nickname, get_current_timestamp()) {
+        let error_response = (ErrorResponse::BannedFromChannel {
+            channel: name.to_string(),
+        })
+        .to_string();
+        return Err((Some(error_response), ServerError::UserIsBanned));
+    }
+    if let Some(limit) = channel.limit {
+        if channel.users.len() >= (limit as usize) {
+            let error_response = (ErrorResponse::ChannelIsFull {
+                channel: name.to_string(),
+            })
+            .to_string();
+            return Err((Some(error_response), ServerError::ChannelIsFull));
+        }
+    }
+    if let Some(password) = &channel.password {
+        let error_response = (ErrorResponse::BadChannelKey {
+            channel: name.to_string(),
+        })
+        .to_string();
+        match key {
+            Some(key) if key != password.as_str() => {
+                return Err((Some(error_response), ServerError::IncorrectPassword));
+            }
+            None => return Err((Some(error_response), ServerError::MustInsertPassword)),
+            _ => (),
+        }
+    }
+    channel.add_member(nickname);
+    inform_database(
+        PersistenceType::ChannelUpdate(channel.name.to_owned()),
+        channel.to_json(),
+        session,
+    )
+    .map_err(|e| (None, e))?;
+    println!("Channel joined: {}", channel.name);
+    Ok(channel.topic.to_string())
+}
+
+/// Joins the client to the forward channel of a channel it couldn't join, if it has one.
+/// The forward channel must already exist, and the client is only forwarded once, so a failed
+/// join to it leaves the client with the error of the original channel.
+/// Returns the name and the topic of the channel joined.
+fn forward_join(
+    channels: &mut HashMap<String, Channel>,
+    name: &str,
+    nickname: &String,
+    max_channels: Option<usize>,
+    session: &Session,
+) -> Option<(String, String)> {
+    let target = channels.get(name)?.forward.to_owned()?;
+    let limits = (false, max_channels);
+    let topic = join_channel(channels, &target, nickname, None, limits, session).ok()?;
+    println!("{} forwarded from {} to {}", nickname, name, target);
+    Some((target, topic))
+}
+
+/// Informs the members of the channel connected to this server, except the one that joined,
+/// that a user joined the channel. The failed writes to a member are logged and don't stop the
+/// others.
+fn inform_channel_about_join(
+    channel_name: &str,
+    nickname: &String,
+    session: &Session,
+) -> Result<(), ServerError> {
+    let users = match read_lock_channels(session)?.get(channel_name) {
+        Some(channel) => channel.users.to_owned(),
+        None => return Ok(()),
+    };
+    let response = MessageResponse::JoinMsg {
+        channel: channel_name.to_string(),
+        nickname: nickname.to_owned(),
+    }
+    .to_string();
+    let message = SharedMessage::new(response.into());
+    let receivers = users.iter().filter(|user| *user != nickname);
+    inform_clients_shared(session, receivers.map(String::as_str), &message)
 }
 
 fn inform_network_about_join(
@@ -170,9 +331,9 @@ fn inform_network_about_join(
     network: &Network,
     server_name: &String,
 ) -> Result<(), ServerError> {
-    if channel_name.starts_with('#') {
+    if channel_name.starts_with(NETWORK_CHANNEL_PREFIX) {
         let mut msg = message;
-        msg.prefix = Some(nickname.to_owned());
+        msg.set_nick_prefix(nickname);
         msg.parameters[0] = channel_name.to_string();
         let msg = Message::deserialize(msg)?;
         inform_network(network, server_name, &msg)?;
@@ -183,28 +344,51 @@ fn inform_network_about_join(
 
 #[cfg(test)]
 mod join_tests {
+    use model::consts::MAX_MSG_SIZE;
     use std::collections::HashMap;
-    use std::io::Read;
-    use std::net::TcpListener;
+    use std::io::{Read, Write};
+    use std::net::{Shutdown, TcpListener, TcpStream};
     use std::sync::{Arc, RwLock};
+    use std::time::{Duration, Instant};
     use std::vec;
 
     use crate::commands::command_utils::{
-        create_client_for_test, create_message_for_test, create_session_for_test,
-        read_lock_channels, write_lock_channels,
+        create_client_for_test, create_message_for_test, create_session_for_test, TargetResults,
     };
     use crate::commands::join::handle_join_command;
-    use crate::database::handle_database;
+    use crate::database::{handle_database, DatabasePaths};
+    use crate::locks::{read_lock_channels, write_lock_channels, write_lock_clients};
     use crate::server_errors::ServerError;
+    use model::ban::Ban;
     use model::channel::Channel;
+    use model::channel_stats::ChannelStats;
     use model::channelflag::ChannelFlag;
+    use model::config::ChannelCreation;
+    use model::link_queues::LinkQueues;
     use model::message::MessageType;
     use model::network::Network;
     use model::persistence::PersistenceType;
     use model::responses::errors::ErrorResponse;
+    use model::responses::message::MessageResponse;
     use model::responses::replies::CommandResponse;
     use model::responses::response::Response;
     use model::server::Server;
+    use model::{
+        events::{ServerEvent, Subscriber},
+        session::Session,
+    };
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct EventRecorder {
+        events: Mutex<Vec<ServerEvent>>,
+    }
+
+    impl Subscriber for EventRecorder {
+        fn notify(&self, _session: &Session, event: &ServerEvent) {
+            self.events.lock().unwrap().push(event.to_owned());
+        }
+    }
 
     #[test]
     fn test_command_join_existing_channel_doesnt_make_user_operator() {
@@ -221,16 +405,17 @@ mod join_tests {
         }));
 
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -263,10 +448,11 @@ mod join_tests {
             &session,
             &network,
             &"test".to_string(),
-        );
+        )
+        .and_then(TargetResults::into_result);
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -318,16 +504,17 @@ mod join_tests {
         }));
 
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -345,10 +532,11 @@ mod join_tests {
             &session,
             &network,
             &"test".to_string(),
-        );
+        )
+        .and_then(TargetResults::into_result);
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -395,16 +583,17 @@ mod join_tests {
         }));
 
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -421,10 +610,11 @@ mod join_tests {
             &session,
             &network,
             &"test".to_string(),
-        );
+        )
+        .and_then(TargetResults::into_result);
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -471,16 +661,17 @@ mod join_tests {
         }));
 
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -501,10 +692,11 @@ mod join_tests {
             &session,
             &network,
             &"test".to_string(),
-        );
+        )
+        .and_then(TargetResults::into_result);
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -528,6 +720,104 @@ mod join_tests {
         assert_eq!(result, Err(ServerError::InvalidParameters));
     }
 
+    #[test]
+    fn test_command_join_joins_every_valid_channel_of_the_list() {
+        let listener = TcpListener::bind("127.0.0.1:0".to_string()).unwrap();
+        let address_port = listener.local_addr().unwrap().to_string();
+        let network = Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        };
+
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, address_port, "nickname".to_string());
+        let (_reader, _addr) = listener.accept().unwrap();
+
+        let message = create_message_for_test(MessageType::Join, vec!["#a,bad,#c".to_string()]);
+        let results = handle_join_command(
+            message,
+            &"nickname".to_string(),
+            &session,
+            &network,
+            &"test".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(results.succeeded(), vec!["#a", "#c"]);
+        assert_eq!(
+            results.failed(),
+            vec![(
+                &"bad".to_string(),
+                &ServerError::ChannelMustStartWithHashOrAmpersand
+            )]
+        );
+        let channels_lock = read_lock_channels(&session).unwrap();
+        assert!(channels_lock.contains_key("#a"));
+        assert!(channels_lock.contains_key("#c"));
+        drop(channels_lock);
+        assert_eq!(
+            results.into_result(),
+            Err(ServerError::ChannelMustStartWithHashOrAmpersand)
+        );
+    }
+
+    #[test]
+    fn test_command_join_publishes_the_channels_joined() {
+        let listener = TcpListener::bind("127.0.0.1:0".to_string()).unwrap();
+        let address_port = listener.local_addr().unwrap().to_string();
+        let network = Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        };
+
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        let recorder = Arc::new(EventRecorder::default());
+        session.events.subscribe(recorder.clone());
+        create_client_for_test(&session, address_port, "nickname".to_string());
+        let (_reader, _addr) = listener.accept().unwrap();
+
+        let message = create_message_for_test(MessageType::Join, vec!["#a,bad,#c".to_string()]);
+        handle_join_command(
+            message,
+            &"nickname".to_string(),
+            &session,
+            &network,
+            &"test".to_string(),
+        )
+        .unwrap();
+
+        let joined = |channel: &str| ServerEvent::ChannelJoined {
+            channel: channel.to_string(),
+            nickname: "nickname".to_string(),
+        };
+        assert_eq!(
+            *recorder.events.lock().unwrap(),
+            vec![joined("#a"), joined("#c")]
+        );
+    }
+
     #[test]
     fn test_command_join_invalid_channel_name() {
         let listener = TcpListener::bind("127.0.0.1:0".to_string()).unwrap();
@@ -543,16 +833,17 @@ mod join_tests {
         }));
 
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -566,10 +857,11 @@ mod join_tests {
             &session,
             &network,
             &"test".to_string(),
-        );
+        )
+        .and_then(TargetResults::into_result);
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -615,16 +907,17 @@ mod join_tests {
         }));
 
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -641,10 +934,11 @@ mod join_tests {
             &session,
             &network,
             &"test".to_string(),
-        );
+        )
+        .and_then(TargetResults::into_result);
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
 
         assert!(result.is_ok());
@@ -657,11 +951,12 @@ mod join_tests {
                 &network,
                 &"test".to_string()
             )
+            .and_then(TargetResults::into_result)
         );
     }
 
     #[test]
-    fn test_command_join_cant_join_secret_channel() {
+    fn test_command_join_can_join_secret_channel() {
         let listener = TcpListener::bind("127.0.0.1:0".to_string()).unwrap();
         let port = listener.local_addr().unwrap().port();
         let address_port = format!("127.0.0.1:{}", port.to_string());
@@ -675,16 +970,17 @@ mod join_tests {
         }));
 
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -702,6 +998,9 @@ mod join_tests {
                 password: None,
                 limit: None,
                 moderators: vec![],
+                forward: None,
+                filters: vec![],
+                stats: ChannelStats::default(),
             },
         );
         drop(channels_write_lock);
@@ -714,14 +1013,15 @@ mod join_tests {
             &session,
             &network,
             &"test".to_string(),
-        );
+        )
+        .and_then(TargetResults::into_result);
         drop(listener);
 
         let channels_lock = read_lock_channels(&session).unwrap();
         let channel = channels_lock.get("#secret").unwrap();
-        assert!(!channel.users.contains(&client.nickname.to_string()));
+        assert!(channel.users.contains(&client.nickname.to_string()));
         drop(channels_lock);
-        assert_eq!(Err(ServerError::ChannelIsSecret), result);
+        assert!(result.is_ok());
     }
 
     #[test]
@@ -739,16 +1039,17 @@ mod join_tests {
         }));
 
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -768,6 +1069,9 @@ mod join_tests {
                 password: None,
                 limit: None,
                 moderators: vec![],
+                forward: None,
+                filters: vec![],
+                stats: ChannelStats::default(),
             },
         );
         drop(channels_lock);
@@ -778,10 +1082,11 @@ mod join_tests {
             &session,
             &network,
             &"test".to_string(),
-        );
+        )
+        .and_then(TargetResults::into_result);
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -825,16 +1130,17 @@ mod join_tests {
         }));
 
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -854,6 +1160,9 @@ mod join_tests {
                 password: None,
                 limit: Some(1),
                 moderators: vec![],
+                forward: None,
+                filters: vec![],
+                stats: ChannelStats::default(),
             },
         );
         drop(channels_lock);
@@ -864,10 +1173,11 @@ mod join_tests {
             &session,
             &network,
             &"test".to_string(),
-        );
+        )
+        .and_then(TargetResults::into_result);
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -910,16 +1220,17 @@ mod join_tests {
             children: HashMap::new(),
         }));
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -935,10 +1246,17 @@ mod join_tests {
                 operators: vec!["user1".to_string()],
                 topic: "".to_string(),
                 modes: vec![],
-                banned_users: vec![client.nickname.to_string()],
+                banned_users: vec![Ban::new(
+                    client.nickname.to_string(),
+                    "user1".to_string(),
+                    0,
+                )],
                 password: None,
                 limit: None,
                 moderators: vec![],
+                forward: None,
+                filters: vec![],
+                stats: ChannelStats::default(),
             },
         );
         drop(channels_lock);
@@ -949,10 +1267,11 @@ mod join_tests {
             &session,
             &network,
             &"test".to_string(),
-        );
+        )
+        .and_then(TargetResults::into_result);
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -996,16 +1315,17 @@ mod join_tests {
         }));
 
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -1025,6 +1345,9 @@ mod join_tests {
                 password: Some("123".to_string()),
                 limit: None,
                 moderators: vec![],
+                forward: None,
+                filters: vec![],
+                stats: ChannelStats::default(),
             },
         );
         drop(channels_lock);
@@ -1035,10 +1358,11 @@ mod join_tests {
             &session,
             &network,
             &"test".to_string(),
-        );
+        )
+        .and_then(TargetResults::into_result);
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -1082,16 +1406,17 @@ mod join_tests {
         }));
 
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -1114,6 +1439,9 @@ mod join_tests {
                 password: Some("123".to_string()),
                 limit: None,
                 moderators: vec![],
+                forward: None,
+                filters: vec![],
+                stats: ChannelStats::default(),
             },
         );
         drop(channels_lock);
@@ -1124,10 +1452,11 @@ mod join_tests {
             &session,
             &network,
             &"test".to_string(),
-        );
+        )
+        .and_then(TargetResults::into_result);
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -1171,16 +1500,17 @@ mod join_tests {
         }));
 
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -1203,6 +1533,9 @@ mod join_tests {
                 password: Some("123".to_string()),
                 limit: None,
                 moderators: vec![],
+                forward: None,
+                filters: vec![],
+                stats: ChannelStats::default(),
             },
         );
         drop(channels_lock);
@@ -1213,10 +1546,11 @@ mod join_tests {
             &session,
             &network,
             &"test".to_string(),
-        );
+        )
+        .and_then(TargetResults::into_result);
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -1245,4 +1579,470 @@ mod join_tests {
         drop(channels_lock);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_command_join_does_not_wait_for_a_stalled_peer() {
+        let listener = TcpListener::bind("127.0.0.1:0".to_string()).unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let stalled = Arc::new(TcpStream::connect(&addr).unwrap());
+        let (_stalled_reader, _addr) = listener.accept().unwrap();
+        stalled.set_nonblocking(true).unwrap();
+        let filler = [1u8; 65536];
+        while (&*stalled).write(&filler).is_ok() {}
+        stalled.set_nonblocking(false).unwrap();
+
+        let mut children = HashMap::new();
+        children.insert("peer".to_string(), stalled);
+        let network = Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children,
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        };
+
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr, "nickname".to_string());
+        let (_reader, _addr) = listener.accept().unwrap();
+
+        let join_session = session.clone();
+        let join = std::thread::spawn(move || {
+            let message =
+                create_message_for_test(MessageType::Join, vec!["#channel_test".to_string()]);
+            handle_join_command(
+                message,
+                &"nickname".to_string(),
+                &join_session,
+                &network,
+                &"test".to_string(),
+            )
+            .and_then(TargetResults::into_result)
+        });
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !join.is_finished() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(join.is_finished());
+        assert!(join.join().unwrap().is_ok());
+        assert!(session.channels.contains("#channel_test"));
+    }
+
+    fn create_network_for_test() -> Network {
+        Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        }
+    }
+
+    #[test]
+    fn test_command_join_cannot_create_channel_if_the_policy_only_allows_operators() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        session.config.write().unwrap().channel_creation = ChannelCreation::Operators;
+        create_client_for_test(&session, addr, "nickname".to_string());
+        let (mut reader, _addr) = listener.accept().unwrap();
+
+        let message = create_message_for_test(MessageType::Join, vec!["#channel_test".to_string()]);
+        let result = handle_join_command(
+            message,
+            &"nickname".to_string(),
+            &session,
+            &create_network_for_test(),
+            &"test".to_string(),
+        )
+        .and_then(TargetResults::into_result);
+
+        assert_eq!(result, Err(ServerError::ChannelCreationDenied));
+        assert!(!read_lock_channels(&session)
+            .unwrap()
+            .contains_key("#channel_test"));
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        match Response::serialize(String::from_utf8(msg).unwrap()).unwrap() {
+            Response::ErrorResponse {
+                response: ErrorResponse::ChannelCreationDenied { channel },
+            } => assert_eq!(channel, "#channel_test"),
+            _ => panic!("Expected the channel creation to be denied"),
+        }
+    }
+
+    #[test]
+    fn test_command_join_cannot_join_more_channels_than_the_limit() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        session.config.write().unwrap().max_channels = 2;
+        create_client_for_test(&session, addr, "nickname".to_string());
+        let (mut reader, _addr) = listener.accept().unwrap();
+
+        let message = create_message_for_test(MessageType::Join, vec!["#a,#b,#a,#c".to_string()]);
+        let result = handle_join_command(
+            message,
+            &"nickname".to_string(),
+            &session,
+            &create_network_for_test(),
+            &"test".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(result.into_result(), Err(ServerError::UserAlreadyInChannel));
+        assert!(session.channels.is_member("#b", "nickname"));
+        assert!(!session.channels.contains("#c"));
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        let too_many = loop {
+            reader.read_exact(&mut buf).unwrap();
+            let msg = buf
+                .iter()
+                .take_while(|&&x| x != 0)
+                .copied()
+                .collect::<Vec<_>>();
+            if let Some(Response::ErrorResponse { response }) =
+                Response::serialize(String::from_utf8(msg).unwrap())
+            {
+                break response;
+            }
+        };
+        assert!(matches!(
+            too_many,
+            ErrorResponse::TooManyChannels { channel } if channel == "#c"
+        ));
+    }
+
+    #[test]
+    fn test_command_join_with_more_channels_than_the_targets_allowed_joins_none() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        session.config.write().unwrap().max_targets = 2;
+        create_client_for_test(&session, addr, "nickname".to_string());
+        let (_reader, _addr) = listener.accept().unwrap();
+
+        let message = create_message_for_test(MessageType::Join, vec!["#a,#b,#c".to_string()]);
+        let result = handle_join_command(
+            message,
+            &"nickname".to_string(),
+            &session,
+            &create_network_for_test(),
+            &"test".to_string(),
+        );
+
+        assert!(matches!(result, Err(ServerError::TooManyTargets)));
+        assert!(!session.channels.contains("#a"));
+    }
+
+    #[test]
+    fn test_command_join_creates_channel_if_the_client_is_allowed_by_the_policy() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        session.config.write().unwrap().channel_creation = ChannelCreation::Registered;
+        create_client_for_test(&session, addr.to_owned(), "registered".to_string());
+        create_client_for_test(&session, addr, "operator".to_string());
+        let (_registered_reader, _) = listener.accept().unwrap();
+        let (_operator_reader, _) = listener.accept().unwrap();
+        let mut clients = write_lock_clients(&session).unwrap();
+        clients.get_mut("registered").unwrap().password = Some("password".to_string());
+        drop(clients);
+        let network = create_network_for_test();
+        network
+            .server
+            .write()
+            .unwrap()
+            .operators
+            .push("operator".to_string());
+
+        let message = create_message_for_test(MessageType::Join, vec!["#registered".to_string()]);
+        let registered = handle_join_command(
+            message,
+            &"registered".to_string(),
+            &session,
+            &network,
+            &"test".to_string(),
+        )
+        .and_then(TargetResults::into_result);
+        session.config.write().unwrap().channel_creation = ChannelCreation::Operators;
+        let message = create_message_for_test(MessageType::Join, vec!["#operator".to_string()]);
+        let operator = handle_join_command(
+            message,
+            &"operator".to_string(),
+            &session,
+            &network,
+            &"test".to_string(),
+        )
+        .and_then(TargetResults::into_result);
+
+        assert!(registered.is_ok());
+        assert!(operator.is_ok());
+        let channels = read_lock_channels(&session).unwrap();
+        assert!(channels.contains_key("#registered"));
+        assert!(channels.contains_key("#operator"));
+    }
+
+    #[test]
+    fn test_command_join_full_channel_forwards_to_its_forward_channel() {
+        let listener = TcpListener::bind("127.0.0.1:0".to_string()).unwrap();
+        let address_port = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, address_port, "nickname".to_string());
+        let (mut reader, _) = listener.accept().unwrap();
+        let network = create_network_for_test();
+
+        let mut channels_lock = write_lock_channels(&session).unwrap();
+        let mut full = Channel::new(
+            "#full".to_string(),
+            "".to_string(),
+            vec!["user1".to_string()],
+        );
+        full.limit = Some(1);
+        full.forward = Some("#overflow".to_string());
+        channels_lock.insert("#full".to_string(), full);
+        let overflow = Channel::new(
+            "#overflow".to_string(),
+            "".to_string(),
+            vec!["user1".to_string()],
+        );
+        channels_lock.insert("#overflow".to_string(), overflow);
+        drop(channels_lock);
+
+        let message = create_message_for_test(MessageType::Join, vec!["#full".to_string()]);
+        let results = handle_join_command(
+            message,
+            &"nickname".to_string(),
+            &session,
+            &network,
+            &"test".to_string(),
+        )
+        .unwrap();
+
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        match Response::serialize(String::from_utf8(msg).unwrap()).unwrap() {
+            Response::ErrorResponse {
+                response: ErrorResponse::LinkChannel { channel, target },
+            } => {
+                assert_eq!(channel, "#full");
+                assert_eq!(target, "#overflow");
+            }
+            _ => panic!("expected a forwarding notice"),
+        }
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        match Response::serialize(String::from_utf8(msg).unwrap()).unwrap() {
+            Response::CommandResponse {
+                response: CommandResponse::Topic { channel, .. },
+            } => assert_eq!(channel, "#overflow"),
+            _ => panic!("expected the topic of the forward channel"),
+        }
+        assert_eq!(results.succeeded(), vec!["#overflow"]);
+        let channels = read_lock_channels(&session).unwrap();
+        assert!(!channels["#full"].users.contains(&"nickname".to_string()));
+        assert!(channels["#overflow"]
+            .users
+            .contains(&"nickname".to_string()));
+    }
+
+    #[test]
+    fn test_command_join_informs_the_other_members_of_the_channel() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let network = Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        };
+
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        let member = create_client_for_test(&session, addr.to_owned(), "member".to_string());
+        let (mut member_reader, _addr) = listener.accept().unwrap();
+        let joining = create_client_for_test(&session, addr, "joining".to_string());
+        let (_joining_reader, _addr) = listener.accept().unwrap();
+
+        let channel = Channel::new(
+            "#channel_test".to_string(),
+            "".to_string(),
+            vec![member.nickname.to_string()],
+        );
+        write_lock_channels(&session)
+            .unwrap()
+            .insert(channel.name.clone(), channel.clone());
+
+        let message = create_message_for_test(MessageType::Join, vec![channel.name.to_string()]);
+        let result = handle_join_command(
+            message,
+            &joining.nickname,
+            &session,
+            &network,
+            &"test".to_string(),
+        )
+        .and_then(TargetResults::into_result);
+        assert!(result.is_ok());
+
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        member_reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        match Response::serialize(String::from_utf8(msg).unwrap()).unwrap() {
+            Response::MessageResponse {
+                response: MessageResponse::JoinMsg { channel, nickname },
+            } => {
+                assert_eq!(channel, "#channel_test");
+                assert_eq!(nickname, "joining");
+            }
+            _ => panic!("Expected a join notification"),
+        }
+    }
+
+    #[test]
+    fn test_command_join_expired_ban_does_not_stop_the_user_before_it_is_removed() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr, "nickname".to_string());
+        let (_reader, _addr) = listener.accept().unwrap();
+        let mut channel = Channel::new("#channel_test".to_string(), "".to_string(), vec![]);
+        let mut ban = Ban::new("nickname".to_string(), "operator".to_string(), 100);
+        ban.expires_at = Some(200);
+        channel.banned_users.push(ban);
+        write_lock_channels(&session)
+            .unwrap()
+            .insert(channel.name.clone(), channel);
+
+        let message = create_message_for_test(MessageType::Join, vec!["#channel_test".to_string()]);
+        let result = handle_join_command(
+            message,
+            &"nickname".to_string(),
+            &session,
+            &create_network_for_test(),
+            &"test".to_string(),
+        )
+        .and_then(TargetResults::into_result);
+
+        assert_eq!(result, Ok(()));
+        assert!(session.channels.is_member("#channel_test", "nickname"));
+    }
+
+    #[test]
+    fn test_command_join_pairs_each_key_with_its_channel() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr, "nickname".to_string());
+        let (_reader, _addr) = listener.accept().unwrap();
+        for (name, password) in [("#a", "first"), ("#b", "second")] {
+            let mut channel = Channel::new(name.to_string(), "".to_string(), vec![]);
+            channel.password = Some(password.to_string());
+            write_lock_channels(&session)
+                .unwrap()
+                .insert(name.to_string(), channel);
+        }
+
+        let message = create_message_for_test(
+            MessageType::Join,
+            vec!["#a,#b,#c".to_string(), "first,second".to_string()],
+        );
+        let results = handle_join_command(
+            message,
+            &"nickname".to_string(),
+            &session,
+            &create_network_for_test(),
+            &"test".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(results.succeeded(), vec!["#a", "#b", "#c"]);
+        assert!(session.channels.is_member("#b", "nickname"));
+    }
+
+    #[test]
+    fn test_command_join_is_not_stopped_by_a_member_that_cannot_be_written() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr.to_owned(), "dead".to_string());
+        let (_dead_reader, _addr) = listener.accept().unwrap();
+        create_client_for_test(&session, addr, "joining".to_string());
+        let (mut joining_reader, _addr) = listener.accept().unwrap();
+        session
+            .sockets
+            .socket("dead")
+            .unwrap()
+            .shutdown(Shutdown::Write)
+            .unwrap();
+        let channel = Channel::new(
+            "#channel_test".to_string(),
+            "".to_string(),
+            vec!["dead".to_string()],
+        );
+        write_lock_channels(&session)
+            .unwrap()
+            .insert(channel.name.clone(), channel);
+
+        let message = create_message_for_test(MessageType::Join, vec!["#channel_test".to_string()]);
+        let result = handle_join_command(
+            message,
+            &"joining".to_string(),
+            &session,
+            &create_network_for_test(),
+            &"test".to_string(),
+        )
+        .and_then(TargetResults::into_result);
+
+        assert_eq!(result, Ok(()));
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        joining_reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        match Response::serialize(String::from_utf8(msg).unwrap()).unwrap() {
+            Response::CommandResponse {
+                response: CommandResponse::Topic { channel, .. },
+            } => assert_eq!(channel, "#channel_test"),
+            _ => panic!("Expected the topic of the channel"),
+        }
+    }
 }
@@ -1,10 +1,15 @@
-use super::command_utils::write_lock_channels;
+use crate::locks::{read_lock_channels, write_lock_channels};
 use crate::{
+    commands::mode::inform_channel_about_mode,
     database::inform_database,
     server_errors::ServerError,
     socket::{inform_client, inform_network},
 };
 use model::{
+    channel::Channel,
+    consts::NETWORK_CHANNEL_PREFIX,
+    events::ServerEvent,
+    json::ToJson,
     message::Message,
     network::Network,
     persistence::PersistenceType,
@@ -28,81 +33,135 @@ pub fn handle_kick_command(
         inform_client(session, &nickname, &response)?;
         return Err(ServerError::InvalidParameters);
     };
-    let mut channels_lock = write_lock_channels(session)?;
+    let channel_name = message.parameters[0].to_owned();
     let user_to_kick = message.parameters[1].to_owned();
 
-    match channels_lock.get_mut(&message.parameters[0]) {
-        Some(channel) => {
-            if !channel.operators.contains(&nickname) {
-                let response = (ErrorResponse::ChanOPrivsNeeded {
-                    channel: channel.name.clone(),
-                })
-                .to_string();
-                inform_client(session, &nickname, response.as_str())?;
-                return Err(ServerError::UserNotOperator);
-            }
-            let mut user_eliminated = false;
-            for (i, user) in channel.users.iter().enumerate() {
-                if user == &user_to_kick {
-                    channel.users.remove(i);
-                    user_eliminated = true;
-                    break;
-                }
-            }
-            if channel.name.starts_with('#') && !user_eliminated {
-                let network_cliens = network.clients.read()?;
-                for (i, (user, _)) in network_cliens.iter().enumerate() {
-                    if user == &user_to_kick {
-                        channel.users.remove(i);
-                        user_eliminated = true;
-                        break;
-                    }
-                }
-                drop(network_cliens);
-            }
-            if !user_eliminated {
-                return Err(ServerError::UserNotInChannel);
-            }
-            inform_database(
-                PersistenceType::ChannelUpdate(channel.name.to_owned()),
-                channel.to_string(),
-                session,
-            )?;
-            println!("Client {} kicked from {}", &user_to_kick, channel.name);
-            let mut msg = format!("{} kicked you from {}", nickname, channel.name);
-            if message.parameters.len() > 2 {
-                msg = message.parameters[2..].to_owned().join(" ");
-            }
-            let response = MessageResponse::KickMsg { message: msg }.to_string();
-            inform_client(session, &user_to_kick, response.as_str())?;
-            if channel.name.starts_with('#') {
-                let mut msg = message.clone();
-                msg.prefix = Some(nickname);
-                let msg = Message::deserialize(msg)?;
-                inform_network(network, server_name, &msg)?;
-            }
+    let is_operator = read_lock_channels(session)?
+        .get(&channel_name)
+        .map(|channel| channel.operators.contains(&nickname));
+    match is_operator {
+        Some(true) => (),
+        Some(false) => {
+            let response = (ErrorResponse::ChanOPrivsNeeded {
+                channel: channel_name,
+            })
+            .to_string();
+            inform_client(session, &nickname, response.as_str())?;
+            return Err(ServerError::UserNotOperator);
         }
         None => {
             let response = ErrorResponse::NoSuchChannel {
-                channel: message.parameters[0].to_owned(),
+                channel: channel_name,
             }
             .to_string();
             inform_client(session, &nickname, &response)?;
             return Err(ServerError::ChannelNotFound);
         }
     }
-    drop(channels_lock);
+
+    let (channel, successor) = remove_from_channel(&channel_name, &user_to_kick, session)?;
+    println!("Client {} kicked from {}", &user_to_kick, channel.name);
+    let mut msg = format!("{} kicked you from {}", nickname, channel.name);
+    if message.parameters.len() > 2 {
+        msg = message.parameters[2..].to_owned().join(" ");
+    }
+    inform_kick(&channel, &user_to_kick, msg, successor, session)?;
+    if channel.name.starts_with(NETWORK_CHANNEL_PREFIX) {
+        let mut msg = message.clone();
+        msg.set_nick_prefix(&nickname);
+        let msg = Message::deserialize(msg)?;
+        inform_network(network, server_name, &msg)?;
+    }
+
+    session.publish(ServerEvent::ClientKicked {
+        channel: channel_name,
+        nickname: user_to_kick,
+        kicked_by: nickname,
+    });
+    Ok(())
+}
+
+/// Removes the user from the channel and persists the change. When no operator is left, the
+/// member that has been in the channel the longest becomes one. The change is made under the
+/// lock of the channels, which is released before returning, so the clients are informed
+/// without holding it.
+/// Returns the channel as it was left, with the nickname of its new operator if it has one.
+/// # Arguments
+/// * `channel_name` - The channel to remove the user from.
+/// * `nickname` - The nickname of the user.
+/// * `session` - The session of the current server.
+/// # Errors
+/// * ServerError::ChannelNotFound - If the channel doesn't exist.
+/// * ServerError::UserNotInChannel - If the user is not a member of the channel.
+pub fn remove_from_channel(
+    channel_name: &str,
+    nickname: &str,
+    session: &Session,
+) -> Result<(Channel, Option<String>), ServerError> {
+    let mut channels_lock = write_lock_channels(session)?;
+    let channel = match channels_lock.get_mut(channel_name) {
+        Some(channel) => channel,
+        None => return Err(ServerError::ChannelNotFound),
+    };
+    if !channel.remove_member(nickname) {
+        return Err(ServerError::UserNotInChannel);
+    }
+    let successor = channel.transfer_operator(|_| true);
+    if let Some(successor) = &successor {
+        println!(
+            "{:?} is now an operator of {:?} after {:?} left",
+            successor, &channel.name, nickname
+        );
+    }
+    inform_database(
+        PersistenceType::ChannelUpdate(channel.name.to_owned()),
+        channel.to_json(),
+        session,
+    )?;
+    Ok((channel.clone(), successor))
+}
+
+/// Informs the user kicked from the channel with the message, and the members left that it
+/// left the channel and which member became operator, if one did.
+/// # Arguments
+/// * `channel` - The channel as it was left, from `remove_from_channel`.
+/// * `nickname` - The nickname of the user kicked.
+/// * `message` - The message shown to the user kicked.
+/// * `successor` - The new operator of the channel, if it has one.
+/// * `session` - The session of the current server.
+pub fn inform_kick(
+    channel: &Channel,
+    nickname: &str,
+    message: String,
+    successor: Option<String>,
+    session: &Session,
+) -> Result<(), ServerError> {
+    let response = MessageResponse::KickMsg { message }.to_string();
+    inform_client(session, &nickname.to_string(), &response)?;
+    let response = MessageResponse::PartMsg {
+        channel: channel.name.to_owned(),
+        nickname: nickname.to_owned(),
+    }
+    .to_string();
+    for user in channel.users.iter() {
+        inform_client(session, user, &response)?;
+    }
+    if let Some(successor) = successor {
+        inform_channel_about_mode(channel, format!("+o {}", successor), nickname, session)?;
+    }
     Ok(())
 }
 
 #[cfg(test)]
 mod kick_tests {
+    use model::consts::MAX_MSG_SIZE;
     use std::collections::HashMap;
     use std::io::Read;
     use std::net::TcpListener;
     use std::sync::{Arc, RwLock};
 
     use model::channel::Channel;
+    use model::link_queues::LinkQueues;
     use model::message::MessageType;
     use model::network::Network;
     use model::persistence::PersistenceType;
@@ -112,10 +171,10 @@ mod kick_tests {
 
     use crate::commands::command_utils::{
         create_client_for_test, create_message_for_test, create_session_for_test,
-        read_lock_channels, write_lock_channels,
     };
     use crate::commands::kick::handle_kick_command;
-    use crate::database::handle_database;
+    use crate::database::{handle_database, DatabasePaths};
+    use crate::locks::{read_lock_channels, write_lock_channels};
     use crate::server_errors::ServerError;
 
     #[test]
@@ -133,16 +192,17 @@ mod kick_tests {
         }));
 
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let operator =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -199,16 +259,17 @@ mod kick_tests {
         }));
 
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let operator =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -232,7 +293,7 @@ mod kick_tests {
         );
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -276,16 +337,17 @@ mod kick_tests {
         }));
 
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let not_operator =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -320,7 +382,7 @@ mod kick_tests {
         );
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -368,16 +430,17 @@ mod kick_tests {
         }));
 
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let operator =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -433,16 +496,17 @@ mod kick_tests {
             children: HashMap::new(),
         }));
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
 
         let network = Network {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let operator =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -474,7 +538,7 @@ mod kick_tests {
         );
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
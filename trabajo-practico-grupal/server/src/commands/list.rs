@@ -1,11 +1,10 @@
-use super::command_utils::write_lock_channels;
+use crate::locks::write_lock_channels;
 use crate::{
     server_errors::ServerError,
     socket::{inform_client, inform_server},
 };
 use model::{
-    channelflag::ChannelFlag, message::Message, network::Network,
-    responses::replies::CommandResponse, session::Session,
+    message::Message, network::Network, responses::replies::CommandResponse, session::Session,
 };
 
 /// Function that handles the list command.
@@ -73,11 +72,8 @@ fn save_channels_and_topics(
     network: &Network,
     server_name: Option<String>,
 ) -> Result<(), ServerError> {
-    if channel.modes.contains(&ChannelFlag::Private)
-        && !channel.modes.contains(&ChannelFlag::Secret)
-        && !channel.users.contains(nickname)
-    {
-        println!("Channel: {:?} is Private", channel.name);
+    if server_name.is_none() && !channel.is_visible_to(nickname) {
+        println!("Channel: {:?} is not visible", channel.name);
     } else {
         let response = CommandResponse::List {
             channel: channel.name.clone(),
@@ -96,6 +92,7 @@ fn save_channels_and_topics(
 
 #[cfg(test)]
 mod list_tests {
+    use model::consts::MAX_MSG_SIZE;
     use std::collections::HashMap;
     use std::io::Read;
     use std::net::TcpListener;
@@ -103,6 +100,7 @@ mod list_tests {
 
     use model::channel::Channel;
     use model::channelflag::ChannelFlag;
+    use model::link_queues::LinkQueues;
     use model::message::MessageType;
     use model::network::Network;
     use model::persistence::PersistenceType;
@@ -112,11 +110,12 @@ mod list_tests {
 
     use crate::commands::command_utils::{
         create_client_for_test, create_message_for_test, create_session_for_test,
-        write_lock_channels,
+        read_response_for_test,
     };
+    use crate::locks::write_lock_channels;
 
     use crate::commands::list::handle_list_command;
-    use crate::database::handle_database;
+    use crate::database::{handle_database, DatabasePaths};
     use crate::server_errors::ServerError;
 
     #[test]
@@ -125,7 +124,7 @@ mod list_tests {
         let port = listener.local_addr().unwrap().port();
         let address_port = format!("127.0.0.1:{}", port.to_string());
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: port.to_string(),
@@ -139,10 +138,11 @@ mod list_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -162,7 +162,7 @@ mod list_tests {
         let port = listener.local_addr().unwrap().port();
         let address_port = format!("127.0.0.1:{}", port.to_string());
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: port.to_string(),
@@ -176,10 +176,11 @@ mod list_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client1 =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -207,7 +208,7 @@ mod list_tests {
         let result =
             handle_list_command(message, &"nickname".to_string(), &session, &network, None);
         let (mut reader, _addr) = listener.accept().unwrap();
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -232,7 +233,7 @@ mod list_tests {
             }
         }
 
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -260,7 +261,7 @@ mod list_tests {
             }
         }
 
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -290,7 +291,7 @@ mod list_tests {
             }
         }
 
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -321,7 +322,7 @@ mod list_tests {
         let port = listener.local_addr().unwrap().port();
         let address_port = format!("127.0.0.1:{}", port.to_string());
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: port.to_string(),
@@ -335,10 +336,11 @@ mod list_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client1 =
             create_client_for_test(&session, address_port.to_string(), "nickname".to_string());
@@ -364,7 +366,7 @@ mod list_tests {
         let result =
             handle_list_command(message, &"nickname".to_string(), &session, &network, None);
         let (mut reader, _addr) = listener.accept().unwrap();
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -386,7 +388,7 @@ mod list_tests {
             }
         }
 
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -409,7 +411,7 @@ mod list_tests {
             }
         }
 
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -439,7 +441,7 @@ mod list_tests {
     fn test_list_command_multiple_channels() {
         let listener = TcpListener::bind("127.0.0.1:8112".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8112".to_string(),
@@ -453,10 +455,11 @@ mod list_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client1 = create_client_for_test(
             &session,
@@ -493,7 +496,7 @@ mod list_tests {
         let result =
             handle_list_command(message, &"nickname".to_string(), &session, &network, None);
         let (mut reader, _addr) = listener.accept().unwrap();
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -518,7 +521,7 @@ mod list_tests {
             }
         }
 
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
 
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
@@ -547,7 +550,7 @@ mod list_tests {
             }
         }
 
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -577,7 +580,7 @@ mod list_tests {
             }
         }
 
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -606,7 +609,7 @@ mod list_tests {
     fn test_list_command_private_channel() {
         let listener = TcpListener::bind("127.0.0.1:8113".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8113".to_string(),
@@ -620,10 +623,11 @@ mod list_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client1 = create_client_for_test(
             &session,
@@ -652,7 +656,7 @@ mod list_tests {
             None,
         );
         let (mut reader, _addr) = listener.accept().unwrap();
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -674,7 +678,7 @@ mod list_tests {
             }
         }
 
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -699,4 +703,90 @@ mod list_tests {
         drop(listener);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_list_command_secret_channel_only_visible_to_members() {
+        let listener = TcpListener::bind("127.0.0.1:0".to_string()).unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
+        let arc_server = Arc::new(RwLock::new(Server {
+            ip: "0.0.0.0".to_string(),
+            port: "0".to_string(),
+            name: "test".to_string(),
+            operators: vec![],
+            father: None,
+            children: HashMap::new(),
+        }));
+
+        let network = Network {
+            server: arc_server,
+            servers: arc_servers,
+            clients: arc_network_clients,
+            links: LinkQueues::default(),
+        };
+
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        let member = create_client_for_test(&session, addr.to_owned(), "member".to_string());
+        let outsider = create_client_for_test(&session, addr, "outsider".to_string());
+        let (mut member_reader, _addr) = listener.accept().unwrap();
+        let (mut outsider_reader, _addr) = listener.accept().unwrap();
+
+        let mut channel = Channel::new(
+            "#channel_test".to_string(),
+            "test_topic".to_string(),
+            vec![member.nickname.to_string()],
+        );
+        channel.modes.push(ChannelFlag::Secret);
+
+        let mut channels_lock = write_lock_channels(&session).unwrap();
+        channels_lock.insert(channel.name.clone(), channel.clone());
+        drop(channels_lock);
+
+        let message = create_message_for_test(MessageType::List, vec![]);
+        let result = handle_list_command(
+            message,
+            &outsider.nickname.to_string(),
+            &session,
+            &network,
+            None,
+        );
+        assert!(result.is_ok());
+
+        let responses = (0..2)
+            .map(|_| read_response_for_test(&mut outsider_reader))
+            .collect::<Vec<_>>();
+        match &responses[1] {
+            Response::CommandResponse {
+                response: CommandResponse::ListEnd,
+            } => (),
+            _ => panic!("Expected the end of the list"),
+        }
+
+        let message = create_message_for_test(MessageType::List, vec![]);
+        let result = handle_list_command(
+            message,
+            &member.nickname.to_string(),
+            &session,
+            &network,
+            None,
+        );
+        assert!(result.is_ok());
+
+        let responses = (0..3)
+            .map(|_| read_response_for_test(&mut member_reader))
+            .collect::<Vec<_>>();
+        match &responses[1] {
+            Response::CommandResponse {
+                response: CommandResponse::List { channel: c, topic },
+            } => {
+                assert_eq!(*c, channel.name);
+                assert_eq!(*topic, channel.topic);
+            }
+            _ => panic!("Expected the secret channel in the list"),
+        }
+        drop(listener);
+    }
 }
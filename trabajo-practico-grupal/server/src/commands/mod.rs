@@ -1,6 +1,11 @@
+pub mod audit;
 pub mod away;
+pub mod chanstats;
 pub mod command_utils;
 pub mod dcc;
+pub mod filter;
+pub mod ghost;
+pub mod help;
 pub mod invite;
 pub mod join;
 pub mod kick;
@@ -8,14 +13,21 @@ pub mod list;
 pub mod mode;
 pub mod names;
 pub mod nick;
+pub mod nickserv;
 pub mod oper;
 pub mod part;
 pub mod pass;
+pub mod ping;
+pub mod preferences;
 pub mod privmsg;
 pub mod quit;
+pub mod registry;
+pub mod rehash;
 pub mod server;
 pub mod server_commands_handler;
+pub mod silence;
 pub mod squit;
+pub mod stats;
 pub mod topic;
 pub mod user;
 pub mod who;
@@ -1,17 +1,29 @@
-use super::command_utils::{write_lock_channels, write_lock_clients};
+use super::command_utils::{
+    get_current_timestamp, require_channel_name, require_params, require_registered,
+};
+use crate::locks::{
+    write_lock_channels, write_lock_clients, write_lock_network_clients, write_lock_server,
+};
 use crate::{
     database::inform_database,
     server_errors::ServerError,
-    socket::{inform_client, inform_network},
+    socket::{inform_client, inform_clients_shared, inform_network},
 };
 use model::{
+    ban::Ban,
     channel::Channel,
     channelflag::ChannelFlag,
     client::Client,
+    consts::{is_channel_name, NETWORK_CHANNEL_PREFIX},
+    events::ServerEvent,
+    json::ToJson,
     message::Message,
     network::Network,
     persistence::PersistenceType,
-    responses::{errors::ErrorResponse, replies::CommandResponse},
+    responses::{
+        errors::ErrorResponse, message::MessageResponse, replies::CommandResponse,
+        writer::SharedMessage,
+    },
     session::Session,
     userflag::UserFlag,
 };
@@ -28,31 +40,44 @@ pub fn handle_mode_command(
     network: &Network,
     server_name: &String,
 ) -> Result<(), ServerError> {
-    if message.parameters.is_empty() {
-        let response = (ErrorResponse::NeedMoreParams {
-            command: "MODE".to_string(),
-        })
-        .to_string();
-        inform_client(session, &nickname, &response)?;
-        return Err(ServerError::InvalidParameters);
-    }
-    if message.parameters[0].starts_with('&') || message.parameters[0].starts_with('#') {
-        handle_channel_mode_command(message, session, network, nickname, server_name)?;
+    require_registered(&nickname, session, network)?;
+    require_params(&message, 1, "MODE", &nickname, session)?;
+    let parameters = message.parameters.to_owned();
+    if is_channel_name(&parameters[0]) {
+        handle_channel_mode_command(message, session, network, nickname.to_owned(), server_name)?;
     } else {
-        handle_user_mode_command(message, session, nickname, network)?;
+        handle_user_mode_command(message, session, nickname.to_owned(), network, server_name)?;
+    }
+    if is_mode_change(&parameters) {
+        session.publish(ServerEvent::ModeChanged {
+            target: parameters[0].to_owned(),
+            modes: parameters[1..].join(" "),
+            set_by: nickname,
+        });
     }
     Ok(())
 }
 
+/// Returns true if the parameters of the MODE command change modes, instead of only asking
+/// for the modes of the target or for the ban list of a channel.
+fn is_mode_change(parameters: &[String]) -> bool {
+    match parameters.get(1) {
+        Some(flags) => parameters.len() > 2 || flags.trim_start_matches(['+', '-']) != "b",
+        None => false,
+    }
+}
+
 /// Handles the MODE command when the first parameter is a nick.
 /// If the user is not found, it returns an error.
 /// If the user is found, but it doesn't match with the sender, it returns an error.
 /// If it receives a mode and the nick matches the sender's nick, it will set the user mode.
+/// After the flags are set, the new user modes are propagated to the network.
 fn handle_user_mode_command(
     message: Message,
     session: &Session,
     nickname: String,
     network: &Network,
+    server_name: &String,
 ) -> Result<(), ServerError> {
     if message.parameters[0] != nickname {
         let response = ErrorResponse::UsersDontMatch.to_string();
@@ -92,6 +117,7 @@ fn handle_user_mode_command(
                 return Err(ServerError::InvalidFlags);
             }
             handle_user_flags(flags, c, action, session, network)?;
+            inform_network_about_user_modes(c, network, server_name)?;
         }
         _ => {
             let response = (ErrorResponse::NeedMoreParams {
@@ -105,6 +131,46 @@ fn handle_user_mode_command(
     Ok(())
 }
 
+/// Updates the network record of the client with its current modes and informs
+/// the other servers of the network about them.
+/// # Arguments
+/// * `client` - The client whose modes changed.
+/// * `network` - The struct that contains information about the network.
+/// * `server_name` - The name of the server to exclude from the message.
+pub fn inform_network_about_user_modes(
+    client: &Client,
+    network: &Network,
+    server_name: &String,
+) -> Result<(), ServerError> {
+    let mut network_clients = write_lock_network_clients(network)?;
+    if let Some(network_client) = network_clients.get_mut(&client.nickname) {
+        network_client.modes = client.modes.to_owned();
+    }
+    drop(network_clients);
+
+    let msg = format!(
+        ":{} MODE {} {}",
+        client.nickname,
+        client.nickname,
+        get_user_modes_string(&client.modes)
+    );
+    inform_network(network, server_name, &msg)?;
+    Ok(())
+}
+
+/// Returns the modes of a user in the `+<flags>` format, with `-` when no flags are set.
+pub fn get_user_modes_string(modes: &[UserFlag]) -> String {
+    let flags = modes
+        .iter()
+        .filter(|f| **f != UserFlag::Other)
+        .map(UserFlag::to_string)
+        .collect::<String>();
+    if flags.is_empty() {
+        return "-".to_string();
+    }
+    format!("+{}", flags)
+}
+
 ///Returns the HashMap with the user modes.
 fn get_user_modes_hash(client: Client) -> HashMap<String, String> {
     let mut hash_modes = HashMap::new();
@@ -139,6 +205,13 @@ fn get_user_modes_hash(client: Client) -> HashMap<String, String> {
                     hash_modes.insert(flag.to_string(), "-".to_string());
                 }
             }
+            UserFlag::DeliveryReceipts => {
+                if modes.contains(&flag) {
+                    hash_modes.insert(flag.to_string(), "+".to_string());
+                } else {
+                    hash_modes.insert(flag.to_string(), "-".to_string());
+                }
+            }
             UserFlag::Other => {
                 continue;
             }
@@ -168,6 +241,9 @@ fn handle_user_flags(
             UserFlag::Wallops => {
                 set_user_flag(client, action, UserFlag::Wallops, session)?;
             }
+            UserFlag::DeliveryReceipts => {
+                set_user_flag(client, action, UserFlag::DeliveryReceipts, session)?;
+            }
             UserFlag::Operator => {
                 handle_operator_flag(client, action, session, network)?;
             }
@@ -196,12 +272,7 @@ fn handle_operator_flag(
         return Err(ServerError::InvalidFlags);
     }
 
-    let mut server_lock = match network.server.as_ref().write() {
-        Ok(server_lock) => server_lock,
-        Err(_) => {
-            return Err(ServerError::LockError);
-        }
-    };
+    let mut server_lock = write_lock_server(network)?;
 
     if !server_lock.operators.contains(&client.nickname) {
         println!("{:?} is already not an operator", &client.nickname);
@@ -234,7 +305,7 @@ fn set_user_flag(
                 client.modes.push(flag);
                 inform_database(
                     PersistenceType::ClientUpdate(client.nickname.to_owned()),
-                    client.to_string(),
+                    client.to_json(),
                     session,
                 )?;
             }
@@ -248,7 +319,7 @@ fn set_user_flag(
                         client.modes.remove(i);
                         inform_database(
                             PersistenceType::ClientUpdate(client.nickname.to_owned()),
-                            client.to_string(),
+                            client.to_json(),
                             session,
                         )?;
                         break;
@@ -293,8 +364,10 @@ fn handle_channel_mode_command(
     };
     if message.parameters.len() == 1 {
         let hash_modes = get_channel_modes_hash(channel);
+        let show_key = channel.users.contains(&nickname);
         let response = (CommandResponse::ChannelMode {
             channel: channel.name.clone(),
+            mode_string: channel.get_mode_string(show_key),
             modes: hash_modes,
         })
         .to_string();
@@ -388,6 +461,20 @@ pub fn get_channel_modes_hash(channel: &Channel) -> HashMap<String, String> {
                     hash_modes.insert(flag.to_string(), "-".to_string());
                 }
             }
+            ChannelFlag::Filtered => {
+                if modes.contains(&flag) {
+                    hash_modes.insert(flag.to_string(), "+".to_string());
+                } else {
+                    hash_modes.insert(flag.to_string(), "-".to_string());
+                }
+            }
+            ChannelFlag::Permanent => {
+                if modes.contains(&flag) {
+                    hash_modes.insert(flag.to_string(), "+".to_string());
+                } else {
+                    hash_modes.insert(flag.to_string(), "-".to_string());
+                }
+            }
             ChannelFlag::ChannelOperator => {
                 hash_modes.insert(flag.to_string(), channel.operators.join(","));
             }
@@ -399,7 +486,12 @@ pub fn get_channel_modes_hash(channel: &Channel) -> HashMap<String, String> {
                 }
             }
             ChannelFlag::Ban => {
-                hash_modes.insert(flag.to_string(), channel.banned_users.join(","));
+                let bans = channel
+                    .banned_users
+                    .iter()
+                    .map(|ban| ban.to_string())
+                    .collect::<Vec<_>>();
+                hash_modes.insert(flag.to_string(), bans.join(","));
             }
             ChannelFlag::ChannelKey => {
                 hash_modes.insert(flag.to_string(), "".to_string());
@@ -407,6 +499,10 @@ pub fn get_channel_modes_hash(channel: &Channel) -> HashMap<String, String> {
             ChannelFlag::SpeakInModeratedChannel => {
                 hash_modes.insert(flag.to_string(), channel.moderators.join(","));
             }
+            ChannelFlag::Forward => {
+                let forward = channel.forward.to_owned().unwrap_or_default();
+                hash_modes.insert(flag.to_string(), forward);
+            }
             ChannelFlag::Other => {
                 continue;
             }
@@ -438,6 +534,8 @@ fn handle_channel_flags(
                     nickname,
                     server_name,
                 )?;
+                let modes = format!("{}{}", flag_info.0, f);
+                inform_channel_about_mode(channel, modes, nickname, session)?;
             }
             ChannelFlag::Secret => {
                 set_channel_flag(
@@ -449,6 +547,8 @@ fn handle_channel_flags(
                     nickname,
                     server_name,
                 )?;
+                let modes = format!("{}{}", flag_info.0, f);
+                inform_channel_about_mode(channel, modes, nickname, session)?;
             }
             ChannelFlag::InviteOnly => {
                 set_channel_flag(
@@ -460,6 +560,8 @@ fn handle_channel_flags(
                     nickname,
                     server_name,
                 )?;
+                let modes = format!("{}{}", flag_info.0, f);
+                inform_channel_about_mode(channel, modes, nickname, session)?;
             }
             ChannelFlag::ModeratedChannel => {
                 set_channel_flag(
@@ -471,6 +573,8 @@ fn handle_channel_flags(
                     nickname,
                     server_name,
                 )?;
+                let modes = format!("{}{}", flag_info.0, f);
+                inform_channel_about_mode(channel, modes, nickname, session)?;
             }
             ChannelFlag::TopicSettableOnlyOperators => {
                 set_channel_flag(
@@ -482,6 +586,8 @@ fn handle_channel_flags(
                     nickname,
                     server_name,
                 )?;
+                let modes = format!("{}{}", flag_info.0, f);
+                inform_channel_about_mode(channel, modes, nickname, session)?;
             }
             ChannelFlag::NoMessageFromOutside => {
                 set_channel_flag(
@@ -493,6 +599,34 @@ fn handle_channel_flags(
                     nickname,
                     server_name,
                 )?;
+                let modes = format!("{}{}", flag_info.0, f);
+                inform_channel_about_mode(channel, modes, nickname, session)?;
+            }
+            ChannelFlag::Filtered => {
+                set_channel_flag(
+                    channel,
+                    (flag_info.0, ChannelFlag::Filtered),
+                    message,
+                    session,
+                    network,
+                    nickname,
+                    server_name,
+                )?;
+                let modes = format!("{}{}", flag_info.0, f);
+                inform_channel_about_mode(channel, modes, nickname, session)?;
+            }
+            ChannelFlag::Permanent => {
+                set_channel_flag(
+                    channel,
+                    (flag_info.0, ChannelFlag::Permanent),
+                    message,
+                    session,
+                    network,
+                    nickname,
+                    server_name,
+                )?;
+                let modes = format!("{}{}", flag_info.0, f);
+                inform_channel_about_mode(channel, modes, nickname, session)?;
             }
             ChannelFlag::ChannelOperator => {
                 handle_channel_operator_flag(
@@ -549,6 +683,17 @@ fn handle_channel_flags(
                     server_name,
                 )?;
             }
+            ChannelFlag::Forward => {
+                handle_forward_flag(
+                    channel,
+                    flag_info.0,
+                    message,
+                    session,
+                    network,
+                    nickname,
+                    server_name,
+                )?;
+            }
             ChannelFlag::Other => {
                 let response = (ErrorResponse::UnknownMode { character: f }).to_string();
                 inform_client(session, nickname, response.as_str())?;
@@ -578,7 +723,7 @@ fn set_channel_flag(
                 channel.modes.push(flag_info.1);
                 inform_database(
                     PersistenceType::ChannelUpdate(channel.name.to_owned()),
-                    channel.to_string(),
+                    channel.to_json(),
                     session,
                 )?;
             }
@@ -592,7 +737,7 @@ fn set_channel_flag(
                         channel.modes.remove(i);
                         inform_database(
                             PersistenceType::ChannelUpdate(channel.name.to_owned()),
-                            channel.to_string(),
+                            channel.to_json(),
                             session,
                         )?;
                         break;
@@ -607,9 +752,9 @@ fn set_channel_flag(
             return Err(ServerError::InvalidFlags);
         }
     }
-    if channel.name.starts_with('#') {
+    if channel.name.starts_with(NETWORK_CHANNEL_PREFIX) {
         let mut msg = message.clone();
-        msg.prefix = Some(nickname.to_owned());
+        msg.set_nick_prefix(nickname);
         let msg = Message::deserialize(msg)?;
         inform_network(network, server_name, &msg)?;
     }
@@ -644,7 +789,7 @@ fn handle_channel_operator_flag(
                 channel.operators.push(nick.to_string());
                 inform_database(
                     PersistenceType::ChannelUpdate(channel.name.to_owned()),
-                    channel.to_string(),
+                    channel.to_json(),
                     session,
                 )?;
             }
@@ -664,7 +809,7 @@ fn handle_channel_operator_flag(
                         channel.operators.remove(i);
                         inform_database(
                             PersistenceType::ChannelUpdate(channel.name.to_owned()),
-                            channel.to_string(),
+                            channel.to_json(),
                             session,
                         )?;
                         break;
@@ -679,9 +824,11 @@ fn handle_channel_operator_flag(
             return Err(ServerError::InvalidFlags);
         }
     }
-    if channel.name.starts_with('#') {
+    let modes = format!("{}o {}", action, nick);
+    inform_channel_about_mode(channel, modes, nickname, session)?;
+    if channel.name.starts_with(NETWORK_CHANNEL_PREFIX) {
         let mut msg = message.clone();
-        msg.prefix = Some(nickname.to_owned());
+        msg.set_nick_prefix(nickname);
         let msg = Message::deserialize(msg)?;
         inform_network(network, server_name, &msg)?;
     }
@@ -715,7 +862,7 @@ fn handle_key_flag(
             channel.password = Some(key.to_string());
             inform_database(
                 PersistenceType::ChannelUpdate(channel.name.to_owned()),
-                channel.to_string(),
+                channel.to_json(),
                 session,
             )?;
         }
@@ -736,7 +883,7 @@ fn handle_key_flag(
                 channel.password = None;
                 inform_database(
                     PersistenceType::ChannelUpdate(channel.name.to_owned()),
-                    channel.to_string(),
+                    channel.to_json(),
                     session,
                 )?;
             }
@@ -747,9 +894,14 @@ fn handle_key_flag(
             return Err(ServerError::InvalidFlags);
         }
     }
-    if channel.name.starts_with('#') {
+    let modes = match action {
+        '+' => format!("+k {}", key),
+        _ => "-k".to_string(),
+    };
+    inform_channel_about_mode(channel, modes, &nickname, session)?;
+    if channel.name.starts_with(NETWORK_CHANNEL_PREFIX) {
         let mut msg = message.clone();
-        msg.prefix = Some(nickname.to_owned());
+        msg.set_nick_prefix(&nickname);
         let msg = Message::deserialize(msg)?;
         inform_network(network, server_name, &msg)?;
     }
@@ -765,7 +917,7 @@ fn handle_ban_flag(
     nickname: &String,
     server_name: &String,
 ) -> Result<(), ServerError> {
-    if message.parameters.len() > 3 {
+    if message.parameters.len() > 4 {
         return Err(ServerError::InvalidParameters);
     }
     if message.parameters.len() == 2 {
@@ -783,36 +935,49 @@ fn handle_ban_flag(
         inform_client(session, nickname, response.as_str())?;
     } else {
         let user = &message.parameters[2];
+        let duration = match message.parameters.get(3) {
+            Some(duration) => match duration.parse::<u64>() {
+                Ok(duration) => Some(duration),
+                Err(_) => {
+                    let response = ErrorResponse::NeedMoreParams {
+                        command: "MODE".to_string(),
+                    }
+                    .to_string();
+                    inform_client(session, nickname, response.as_str())?;
+                    return Err(ServerError::InvalidParameters);
+                }
+            },
+            None => None,
+        };
         match action {
             '+' => {
-                if channel.banned_users.contains(user) {
+                let now = get_current_timestamp();
+                if channel.is_banned(user, now) {
                     println!("{:?} is already banned from {:?}", user, &channel.name);
                 } else {
                     println!("{:?} is now banned from {:?}", user, &channel.name);
-                    channel.banned_users.push(user.to_string());
-                    for (i, nick) in channel.users.iter().enumerate() {
-                        if nick == user {
-                            channel.users.remove(i);
-                            inform_database(
-                                PersistenceType::ChannelUpdate(channel.name.to_owned()),
-                                channel.to_string(),
-                                session,
-                            )?;
-                            break;
-                        }
-                    }
+                    let mut ban = Ban::new(user.to_string(), nickname.to_string(), now);
+                    ban.expires_at = duration.map(|duration| now + duration);
+                    channel.banned_users.retain(|ban| ban.nickname != *user);
+                    channel.banned_users.push(ban);
+                    channel.users.retain(|nick| nick != user);
+                    inform_database(
+                        PersistenceType::ChannelUpdate(channel.name.to_owned()),
+                        channel.to_json(),
+                        session,
+                    )?;
                 }
             }
             '-' => {
-                if !channel.banned_users.contains(user) {
+                if !channel.is_banned(user, get_current_timestamp()) {
                     println!("{:?} is already not banned from {:?}", user, &channel.name);
                 } else {
-                    for (i, nick) in channel.banned_users.iter().enumerate() {
-                        if nick == user {
+                    for (i, ban) in channel.banned_users.iter().enumerate() {
+                        if ban.nickname == *user {
                             channel.banned_users.remove(i);
                             inform_database(
                                 PersistenceType::ChannelUpdate(channel.name.to_owned()),
-                                channel.to_string(),
+                                channel.to_json(),
                                 session,
                             )?;
                             break;
@@ -827,9 +992,14 @@ fn handle_ban_flag(
                 return Err(ServerError::InvalidFlags);
             }
         }
-        if channel.name.starts_with('#') {
+        let modes = match duration {
+            Some(duration) if action == '+' => format!("+b {} {}", user, duration),
+            _ => format!("{}b {}", action, user),
+        };
+        inform_channel_about_mode(channel, modes, nickname, session)?;
+        if channel.name.starts_with(NETWORK_CHANNEL_PREFIX) {
             let mut msg = message.clone();
-            msg.prefix = Some(nickname.to_owned());
+            msg.set_nick_prefix(nickname);
             let msg = Message::deserialize(msg)?;
             inform_network(network, server_name, &msg)?;
         }
@@ -850,6 +1020,7 @@ fn handle_user_limit_flag(
     if message.parameters.len() == 2 && action == '-' {
         channel.limit = None;
         println!("{:?} has no user limit", &channel.name);
+        inform_channel_about_mode(channel, "-l".to_string(), nickname, session)?;
     } else if message.parameters.len() == 3 && action == '+' {
         channel.limit = match message.parameters[2].parse::<i32>() {
             Ok(limit) => Some(limit),
@@ -859,13 +1030,15 @@ fn handle_user_limit_flag(
         };
         inform_database(
             PersistenceType::ChannelUpdate(channel.name.to_owned()),
-            channel.to_string(),
+            channel.to_json(),
             session,
         )?;
         println!("Limit is now set to {:?}", message.parameters[2]);
-        if channel.name.starts_with('#') {
+        let modes = format!("+l {}", message.parameters[2]);
+        inform_channel_about_mode(channel, modes, nickname, session)?;
+        if channel.name.starts_with(NETWORK_CHANNEL_PREFIX) {
             let mut msg = message.clone();
-            msg.prefix = Some(nickname.to_owned());
+            msg.set_nick_prefix(nickname);
             let msg = Message::deserialize(msg)?;
             inform_network(network, server_name, &msg)?;
         }
@@ -880,6 +1053,60 @@ fn handle_user_limit_flag(
     Ok(())
 }
 
+/// Sets or removes the channel the users are forwarded to when they can't join the channel
+/// because it is full or invite only.
+/// # Errors
+/// ServerError::InvalidParameters if the forward channel is missing, is not a valid channel name
+/// or is the channel itself.
+fn handle_forward_flag(
+    channel: &mut Channel,
+    action: char,
+    message: &Message,
+    session: &Session,
+    network: &Network,
+    nickname: &String,
+    server_name: &String,
+) -> Result<(), ServerError> {
+    let modes = if message.parameters.len() == 2 && action == '-' {
+        if channel.forward.is_none() {
+            println!("{:?} already has no forward channel", &channel.name);
+            return Ok(());
+        }
+        channel.forward = None;
+        "-f".to_string()
+    } else if message.parameters.len() == 3 && action == '+' {
+        let target = &message.parameters[2];
+        require_channel_name(target, nickname, session)
+            .map_err(|_| ServerError::InvalidParameters)?;
+        if *target == channel.name {
+            return Err(ServerError::InvalidParameters);
+        }
+        channel.forward = Some(target.to_owned());
+        println!("{:?} now forwards to {:?}", &channel.name, target);
+        format!("+f {}", target)
+    } else {
+        let response = (ErrorResponse::NeedMoreParams {
+            command: "MODE".to_string(),
+        })
+        .to_string();
+        inform_client(session, nickname, response.as_str())?;
+        return Err(ServerError::InvalidParameters);
+    };
+    inform_database(
+        PersistenceType::ChannelUpdate(channel.name.to_owned()),
+        channel.to_json(),
+        session,
+    )?;
+    inform_channel_about_mode(channel, modes, nickname, session)?;
+    if channel.name.starts_with(NETWORK_CHANNEL_PREFIX) {
+        let mut msg = message.clone();
+        msg.set_nick_prefix(nickname);
+        let msg = Message::deserialize(msg)?;
+        inform_network(network, server_name, &msg)?;
+    }
+    Ok(())
+}
+
 fn handle_speak_in_moderated_channel_flag(
     channel: &mut Channel,
     action: char,
@@ -903,7 +1130,7 @@ fn handle_speak_in_moderated_channel_flag(
                 channel.moderators.push(user.to_string());
                 inform_database(
                     PersistenceType::ChannelUpdate(channel.name.to_owned()),
-                    channel.to_string(),
+                    channel.to_json(),
                     session,
                 )?;
             }
@@ -921,7 +1148,7 @@ fn handle_speak_in_moderated_channel_flag(
                         channel.moderators.remove(i);
                         inform_database(
                             PersistenceType::ChannelUpdate(channel.name.to_owned()),
-                            channel.to_string(),
+                            channel.to_json(),
                             session,
                         )?;
                         break;
@@ -936,11 +1163,505 @@ fn handle_speak_in_moderated_channel_flag(
             return Err(ServerError::InvalidFlags);
         }
     }
-    if channel.name.starts_with('#') {
+    let modes = format!("{}v {}", action, user);
+    inform_channel_about_mode(channel, modes, nickname, session)?;
+    let msg = match action {
+        '+' => format!("{} has given you voice in {}", nickname, channel.name),
+        _ => format!("{} has taken your voice in {}", nickname, channel.name),
+    };
+    let response = MessageResponse::VoiceMsg { message: msg }.to_string();
+    inform_client(session, user, &response)?;
+    if channel.name.starts_with(NETWORK_CHANNEL_PREFIX) {
         let mut msg = message.clone();
-        msg.prefix = Some(nickname.to_owned());
+        msg.set_nick_prefix(nickname);
         let msg = Message::deserialize(msg)?;
         inform_network(network, server_name, &msg)?;
     }
     Ok(())
 }
+
+/// Gives the operator privileges of the channel to the member that has been in it the longest,
+/// when the last operator present leaves. The change is persisted and the members are informed.
+/// # Arguments
+/// * `channel` - The channel the operator left.
+/// * `nickname` - The nickname of the user who left the channel.
+/// * `session` - The session of the current server.
+/// * `is_present` - Returns true if the member is still present in the channel.
+pub fn transfer_channel_operator<F: Fn(&str) -> bool>(
+    channel: &mut Channel,
+    nickname: &str,
+    session: &Session,
+    is_present: F,
+) -> Result<Option<String>, ServerError> {
+    let successor = match channel.transfer_operator(is_present) {
+        Some(successor) => successor,
+        None => return Ok(None),
+    };
+    println!(
+        "{:?} is now an operator of {:?} after {:?} left",
+        successor, &channel.name, nickname
+    );
+    inform_database(
+        PersistenceType::ChannelUpdate(channel.name.to_owned()),
+        channel.to_json(),
+        session,
+    )?;
+    let modes = format!("+o {}", successor);
+    inform_channel_about_mode(channel, modes, nickname, session)?;
+    Ok(Some(successor))
+}
+
+/// Informs the members of the channel about a change of its modes, with the `MODE` notification
+/// of the channel, like `+l 10` or `-k`. The failed writes to a member are logged and don't
+/// stop the others.
+/// # Arguments
+/// * `channel` - The channel whose modes changed.
+/// * `modes` - The modes changed, with their parameters.
+/// * `nickname` - The nickname of the client who changed the modes.
+/// * `session` - The session of the current server.
+pub fn inform_channel_about_mode(
+    channel: &Channel,
+    modes: String,
+    nickname: &str,
+    session: &Session,
+) -> Result<(), ServerError> {
+    let response = MessageResponse::ChannelModeMsg {
+        channel: channel.name.to_owned(),
+        sender: nickname.to_owned(),
+        modes,
+    }
+    .to_string();
+    let message = SharedMessage::new(response.into());
+    inform_clients_shared(session, channel.users.iter().map(String::as_str), &message)
+}
+
+#[cfg(test)]
+mod mode_tests {
+    use model::consts::MAX_MSG_SIZE;
+    use std::collections::HashMap;
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::sync::{Arc, RwLock};
+
+    use crate::commands::command_utils::{
+        create_client_for_test, create_message_for_test, create_session_for_test,
+        get_current_timestamp, read_response_for_test,
+    };
+    use crate::commands::mode::handle_mode_command;
+    use crate::database::{handle_database, DatabasePaths};
+    use crate::locks::{read_lock_channels, write_lock_channels};
+    use crate::server_errors::ServerError;
+    use model::channel::Channel;
+    use model::channelflag::ChannelFlag;
+    use model::link_queues::LinkQueues;
+    use model::message::MessageType;
+    use model::network::Network;
+    use model::persistence::PersistenceType;
+    use model::responses::message::MessageResponse;
+    use model::responses::replies::CommandResponse;
+    use model::responses::response::Response;
+    use model::server::Server;
+
+    fn read_channel_mode_string(reader: &mut std::net::TcpStream) -> String {
+        match read_response_for_test(reader) {
+            Response::CommandResponse {
+                response:
+                    CommandResponse::ChannelMode {
+                        channel: _,
+                        mode_string,
+                        modes: _,
+                    },
+            } => mode_string,
+            _ => panic!("Expected a channel mode response"),
+        }
+    }
+
+    #[test]
+    fn test_mode_command_channel_mode_string() {
+        let listener = TcpListener::bind("127.0.0.1:0".to_string()).unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
+        let arc_server = Arc::new(RwLock::new(Server {
+            ip: "0.0.0.0".to_string(),
+            port: "0".to_string(),
+            name: "test".to_string(),
+            operators: vec![],
+            father: None,
+            children: HashMap::new(),
+        }));
+
+        let network = Network {
+            server: arc_server,
+            servers: arc_servers,
+            clients: arc_network_clients,
+            links: LinkQueues::default(),
+        };
+
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        let member = create_client_for_test(&session, addr.to_owned(), "member".to_string());
+        let outsider = create_client_for_test(&session, addr, "outsider".to_string());
+        let (mut member_reader, _addr) = listener.accept().unwrap();
+        let (mut outsider_reader, _addr) = listener.accept().unwrap();
+
+        let mut channel = Channel::new(
+            "#channel_test".to_string(),
+            "".to_string(),
+            vec![member.nickname.to_string()],
+        );
+        channel.modes.push(ChannelFlag::TopicSettableOnlyOperators);
+        channel.modes.push(ChannelFlag::NoMessageFromOutside);
+        channel.password = Some("key".to_string());
+        channel.limit = Some(10);
+
+        let mut channels_lock = write_lock_channels(&session).unwrap();
+        channels_lock.insert(channel.name.clone(), channel.clone());
+        drop(channels_lock);
+
+        let message = create_message_for_test(MessageType::Mode, vec![channel.name.to_string()]);
+        let result = handle_mode_command(
+            message,
+            member.nickname.to_string(),
+            &session,
+            &network,
+            &"test".to_string(),
+        );
+        assert!(result.is_ok());
+        assert_eq!(
+            read_channel_mode_string(&mut member_reader),
+            "+ntkl key 10".to_string()
+        );
+
+        let message = create_message_for_test(MessageType::Mode, vec![channel.name.to_string()]);
+        let result = handle_mode_command(
+            message,
+            outsider.nickname.to_string(),
+            &session,
+            &network,
+            &"test".to_string(),
+        );
+        assert!(result.is_ok());
+        assert_eq!(
+            read_channel_mode_string(&mut outsider_reader),
+            "+ntkl * 10".to_string()
+        );
+    }
+
+    #[test]
+    fn test_mode_command_notifies_channel_members() {
+        let listener = TcpListener::bind("127.0.0.1:0".to_string()).unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let network = Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        };
+
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        let operator = create_client_for_test(&session, addr.to_owned(), "operator".to_string());
+        let member = create_client_for_test(&session, addr, "member".to_string());
+        let (_operator_reader, _addr) = listener.accept().unwrap();
+        let (mut member_reader, _addr) = listener.accept().unwrap();
+
+        let mut channel = Channel::new(
+            "#channel_test".to_string(),
+            "".to_string(),
+            vec![operator.nickname.to_string(), member.nickname.to_string()],
+        );
+        channel.operators.push(operator.nickname.to_string());
+        write_lock_channels(&session)
+            .unwrap()
+            .insert(channel.name.clone(), channel.clone());
+
+        let message = create_message_for_test(
+            MessageType::Mode,
+            vec![channel.name.to_string(), "+l".to_string(), "10".to_string()],
+        );
+        let result = handle_mode_command(
+            message,
+            operator.nickname.to_string(),
+            &session,
+            &network,
+            &"test".to_string(),
+        );
+        assert!(result.is_ok());
+
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        member_reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        match Response::serialize(String::from_utf8(msg).unwrap()).unwrap() {
+            Response::MessageResponse {
+                response:
+                    MessageResponse::ChannelModeMsg {
+                        channel: name,
+                        sender,
+                        modes,
+                    },
+            } => {
+                assert_eq!(name, channel.name);
+                assert_eq!(sender, "operator");
+                assert_eq!(modes, "+l 10");
+            }
+            _ => panic!("Expected a channel mode notification"),
+        }
+    }
+
+    #[test]
+    fn test_mode_command_sets_the_forward_channel() {
+        let listener = TcpListener::bind("127.0.0.1:0".to_string()).unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let network = Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        };
+
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        let operator = create_client_for_test(&session, addr, "operator".to_string());
+        let (_operator_reader, _addr) = listener.accept().unwrap();
+
+        let mut channel = Channel::new(
+            "#channel_test".to_string(),
+            "".to_string(),
+            vec![operator.nickname.to_string()],
+        );
+        channel.operators.push(operator.nickname.to_string());
+        write_lock_channels(&session)
+            .unwrap()
+            .insert(channel.name.clone(), channel.clone());
+
+        let message = create_message_for_test(
+            MessageType::Mode,
+            vec![
+                channel.name.to_string(),
+                "+f".to_string(),
+                "#overflow".to_string(),
+            ],
+        );
+        let result = handle_mode_command(
+            message,
+            operator.nickname.to_string(),
+            &session,
+            &network,
+            &"test".to_string(),
+        );
+        assert!(result.is_ok());
+        let forward = read_lock_channels(&session).unwrap()[&channel.name]
+            .forward
+            .to_owned();
+        assert_eq!(forward, Some("#overflow".to_string()));
+
+        let message = create_message_for_test(
+            MessageType::Mode,
+            vec![
+                channel.name.to_string(),
+                "+f".to_string(),
+                channel.name.to_string(),
+            ],
+        );
+        let result = handle_mode_command(
+            message,
+            operator.nickname.to_string(),
+            &session,
+            &network,
+            &"test".to_string(),
+        );
+        assert_eq!(result, Err(ServerError::InvalidParameters));
+
+        let message = create_message_for_test(
+            MessageType::Mode,
+            vec![channel.name.to_string(), "-f".to_string()],
+        );
+        let result = handle_mode_command(
+            message,
+            operator.nickname.to_string(),
+            &session,
+            &network,
+            &"test".to_string(),
+        );
+        assert!(result.is_ok());
+        let channels = read_lock_channels(&session).unwrap();
+        assert_eq!(channels[&channel.name].forward, None);
+    }
+
+    #[test]
+    fn test_mode_command_voice_notifies_the_user() {
+        let listener = TcpListener::bind("127.0.0.1:0".to_string()).unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let network = Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        };
+
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        let operator = create_client_for_test(&session, addr.to_owned(), "operator".to_string());
+        let member = create_client_for_test(&session, addr, "member".to_string());
+        let (_operator_reader, _addr) = listener.accept().unwrap();
+        let (mut member_reader, _addr) = listener.accept().unwrap();
+
+        let mut channel = Channel::new(
+            "#channel_test".to_string(),
+            "".to_string(),
+            vec![operator.nickname.to_string(), member.nickname.to_string()],
+        );
+        channel.operators.push(operator.nickname.to_string());
+        write_lock_channels(&session)
+            .unwrap()
+            .insert(channel.name.clone(), channel.clone());
+
+        let message = create_message_for_test(
+            MessageType::Mode,
+            vec![
+                channel.name.to_string(),
+                "+v".to_string(),
+                "member".to_string(),
+            ],
+        );
+        let result = handle_mode_command(
+            message,
+            operator.nickname.to_string(),
+            &session,
+            &network,
+            &"test".to_string(),
+        );
+        assert!(result.is_ok());
+        assert!(write_lock_channels(&session).unwrap()[&channel.name]
+            .moderators
+            .contains(&"member".to_string()));
+
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        member_reader.read_exact(&mut buf).unwrap();
+        member_reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        match Response::serialize(String::from_utf8(msg).unwrap()).unwrap() {
+            Response::MessageResponse {
+                response: MessageResponse::VoiceMsg { message },
+            } => assert_eq!(message, "operator has given you voice in #channel_test"),
+            _ => panic!("Expected a voice notification"),
+        }
+    }
+
+    #[test]
+    fn test_mode_command_ban_list_with_metadata() {
+        let listener = TcpListener::bind("127.0.0.1:0".to_string()).unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let network = Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        };
+
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        let operator = create_client_for_test(&session, addr.to_owned(), "operator".to_string());
+        let (mut operator_reader, _addr) = listener.accept().unwrap();
+
+        let mut channel = Channel::new(
+            "#channel_test".to_string(),
+            "".to_string(),
+            vec![operator.nickname.to_string(), "member".to_string()],
+        );
+        channel.operators.push(operator.nickname.to_string());
+        write_lock_channels(&session)
+            .unwrap()
+            .insert(channel.name.clone(), channel.clone());
+
+        let before = get_current_timestamp();
+        let message = create_message_for_test(
+            MessageType::Mode,
+            vec![
+                channel.name.to_string(),
+                "+b".to_string(),
+                "member".to_string(),
+            ],
+        );
+        let result = handle_mode_command(
+            message,
+            operator.nickname.to_string(),
+            &session,
+            &network,
+            &"test".to_string(),
+        );
+        assert!(result.is_ok());
+        let channels = write_lock_channels(&session).unwrap();
+        assert!(!channels[&channel.name]
+            .users
+            .contains(&"member".to_string()));
+        drop(channels);
+
+        let message = create_message_for_test(
+            MessageType::Mode,
+            vec![channel.name.to_string(), "+b".to_string()],
+        );
+        let result = handle_mode_command(
+            message,
+            operator.nickname.to_string(),
+            &session,
+            &network,
+            &"test".to_string(),
+        );
+        assert!(result.is_ok());
+
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        operator_reader.read_exact(&mut buf).unwrap();
+        operator_reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        match Response::serialize(String::from_utf8(msg).unwrap()).unwrap() {
+            Response::CommandResponse {
+                response:
+                    CommandResponse::BanList {
+                        channel: _,
+                        ban_list,
+                    },
+            } => {
+                assert_eq!(ban_list.len(), 1);
+                assert_eq!(ban_list[0].nickname, "member");
+                assert_eq!(ban_list[0].set_by, "operator");
+                assert!(ban_list[0].set_at >= before);
+            }
+            _ => panic!("Expected the ban list"),
+        }
+    }
+}
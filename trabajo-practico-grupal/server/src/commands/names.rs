@@ -1,16 +1,20 @@
-use super::{
-    command_utils::{read_lock_channels, read_lock_clients},
-    mode::get_channel_modes_hash,
-};
+use super::{command_utils::is_invisible, mode::get_channel_modes_hash};
+use crate::locks::{read_lock_channels_and_clients, read_lock_network_clients};
 use crate::{
     server_errors::ServerError,
     socket::{inform_client, inform_server},
 };
 use model::{
-    channel::Channel, channelflag::ChannelFlag, message::Message, network::Network,
-    responses::replies::CommandResponse, session::Session,
+    channel::Channel,
+    client_registry::ClientsReadGuard,
+    consts::{NETWORK_CHANNEL_PREFIX, VOICE_FLAG},
+    message::Message,
+    network::{Network, RemoteClient},
+    responses::replies::CommandResponse,
+    session::Session,
+    userflag::UserFlag,
 };
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 /// Function that handles the NAMES command received from a client/server.
 /// # Arguments
@@ -26,17 +30,23 @@ pub fn handle_names_command(
     network: &Network,
     server_name: Option<String>,
 ) -> Result<(), ServerError> {
-    let channels_lock = read_lock_channels(session)?;
+    let (channels_lock, clients_lock) = read_lock_channels_and_clients(session)?;
+    let network_clients = read_lock_network_clients(network)?;
     let mut channel_users: String = String::new();
     let mut response;
     if message.parameters.is_empty() {
         let mut visible_users: Vec<String> = Vec::new();
         let mut not_visible_users: Vec<String> = Vec::new();
         for channel in channels_lock.values() {
-            if channel.users.contains(&nickname) || !channel.modes.contains(&ChannelFlag::Secret) {
+            if server_name.is_some() || channel.is_visible_to(&nickname) {
+                let names = if server_name.is_some() {
+                    channel.users.clone()
+                } else {
+                    get_visible_names(&nickname, channel, &clients_lock, &network_clients)
+                };
                 response = (CommandResponse::Names {
                     channel: channel.name.clone(),
-                    names: channel.users.clone(),
+                    names: names.clone(),
                 })
                 .to_string();
                 if let Some(name) = server_name.to_owned() {
@@ -45,32 +55,28 @@ pub fn handle_names_command(
                     inform_client(session, &nickname, &response.to_string())?;
                 }
 
-                let chans_users_str = format!(
-                    "{},{:?};",
-                    channel.name.to_owned(),
-                    channel.users.to_owned()
-                );
+                let chans_users_str =
+                    format!("{},{:?};", channel.name.to_owned(), names.to_owned());
                 channel_users.push_str(&chans_users_str);
                 let mut users_aux: Vec<String> = channel.users.clone();
                 visible_users.append(&mut users_aux);
             }
         }
         let users: HashSet<String> = HashSet::from_iter(visible_users);
-        let clients_lock = read_lock_clients(session)?;
         for client in clients_lock.values() {
-            if !users.contains(&client.nickname) {
+            if !users.contains(&client.nickname) && !client.modes.contains(&UserFlag::Invisible) {
                 not_visible_users.push(client.nickname.clone());
             }
         }
-        let network_clients = network.clients.as_ref().read()?;
-        for nick in network_clients.keys().clone() {
-            if !users.contains(nick) {
+        for (nick, network_client) in network_clients.iter() {
+            if !users.contains(nick)
+                && !clients_lock.contains_key(nick)
+                && !network_client.is_invisible()
+            {
                 not_visible_users.push(nick.clone());
             }
         }
-        drop(network_clients);
 
-        drop(clients_lock);
         response = (CommandResponse::Names {
             channel: "*".to_string(),
             names: not_visible_users,
@@ -87,20 +93,17 @@ pub fn handle_names_command(
             .collect::<Vec<_>>();
         for name in channels_name {
             if let Some(channel) = channels_lock.get(name) {
-                if channel.users.contains(&nickname)
-                    || !channel.modes.contains(&ChannelFlag::Secret)
-                {
+                if channel.is_visible_to(&nickname) {
+                    let names =
+                        get_visible_names(&nickname, channel, &clients_lock, &network_clients);
                     response = (CommandResponse::Names {
                         channel: channel.name.clone(),
-                        names: channel.users.clone(),
+                        names: names.clone(),
                     })
                     .to_string();
                     inform_client(session, &nickname, response.as_str())?;
-                    let chans_users_str = format!(
-                        "{},{:?};",
-                        channel.name.to_owned(),
-                        channel.users.to_owned()
-                    );
+                    let chans_users_str =
+                        format!("{},{:?};", channel.name.to_owned(), names.to_owned());
                     channel_users.push_str(&chans_users_str);
                 }
             }
@@ -108,20 +111,46 @@ pub fn handle_names_command(
         response = CommandResponse::EndNames.to_string();
         inform_client(session, &nickname, response.as_str())?;
     }
+    drop(network_clients);
+    drop(clients_lock);
     drop(channels_lock);
     Ok(())
 }
 
+/// Returns the users of the channel that the given user can see, with a `+` before the voiced users.
+/// Members of the channel see every user, the rest don't see the users with the invisible mode set.
+fn get_visible_names(
+    nickname: &String,
+    channel: &Channel,
+    clients: &ClientsReadGuard,
+    network_clients: &HashMap<String, RemoteClient>,
+) -> Vec<String> {
+    let is_member = channel.users.contains(nickname);
+    channel
+        .users
+        .iter()
+        .filter(|u| is_member || !is_invisible(u, clients, network_clients))
+        .map(|u| {
+            if channel.moderators.contains(u) {
+                format!("{}{}", VOICE_FLAG, u)
+            } else {
+                u.to_owned()
+            }
+        })
+        .collect()
+}
+
 fn inform_server_about_channel(
     network: &Network,
     server_name: &String,
     channel: &Channel,
     response: &str,
 ) -> Result<(), ServerError> {
-    if channel.name.starts_with('#') {
+    if channel.name.starts_with(NETWORK_CHANNEL_PREFIX) {
         inform_server(network, server_name, response)?;
         let mode_response = CommandResponse::ChannelMode {
             channel: channel.name.to_owned(),
+            mode_string: channel.get_mode_string(false),
             modes: get_channel_modes_hash(channel),
         }
         .to_string();
@@ -133,18 +162,20 @@ fn inform_server_about_channel(
 
 #[cfg(test)]
 mod names_tests {
+    use crate::locks::write_lock_channels;
     use crate::{
         commands::command_utils::{
             create_client_for_test, create_message_for_test, create_session_for_test,
-            write_lock_channels,
+            read_response_for_test,
         },
-        database::handle_database,
+        database::{handle_database, DatabasePaths},
     };
+    use model::consts::MAX_MSG_SIZE;
 
     use super::*;
     use model::{
-        channel::Channel, message::MessageType, persistence::PersistenceType,
-        responses::response::Response, server::Server,
+        channel::Channel, channelflag::ChannelFlag, link_queues::LinkQueues, message::MessageType,
+        persistence::PersistenceType, responses::response::Response, server::Server,
     };
     use std::{
         collections::HashMap,
@@ -157,7 +188,7 @@ mod names_tests {
     fn test_names_command_multiple_cases() {
         let listener = TcpListener::bind("127.0.0.1:8114".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8114".to_string(),
@@ -171,10 +202,11 @@ mod names_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client = create_client_for_test(
             &session,
@@ -225,7 +257,7 @@ mod names_tests {
             None,
         );
         let (mut reader, _addr) = listener.accept().unwrap();
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -248,7 +280,7 @@ mod names_tests {
             }
         }
 
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -271,7 +303,7 @@ mod names_tests {
             }
         }
 
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -294,7 +326,7 @@ mod names_tests {
             }
         }
 
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -323,7 +355,7 @@ mod names_tests {
     fn test_handle_names_command_no_channel() {
         let listener = TcpListener::bind("127.0.0.1:8115".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8115".to_string(),
@@ -337,10 +369,11 @@ mod names_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client = create_client_for_test(
             &session,
@@ -363,7 +396,7 @@ mod names_tests {
             None,
         );
         let (mut reader, _addr) = listener.accept().unwrap();
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -387,7 +420,7 @@ mod names_tests {
             }
         }
 
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -416,7 +449,7 @@ mod names_tests {
     fn test_handle_names_command_one_channel() {
         let listener = TcpListener::bind("127.0.0.1:8116".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8116".to_string(),
@@ -430,10 +463,11 @@ mod names_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client = create_client_for_test(
             &session,
@@ -469,7 +503,7 @@ mod names_tests {
             None,
         );
         let (mut reader, _addr) = listener.accept().unwrap();
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -492,7 +526,7 @@ mod names_tests {
             }
         }
 
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -521,7 +555,7 @@ mod names_tests {
     fn test_handle_names_command_secret_channel() {
         let listener = TcpListener::bind("127.0.0.1:8117".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8117".to_string(),
@@ -535,10 +569,11 @@ mod names_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client = create_client_for_test(
             &session,
@@ -577,7 +612,7 @@ mod names_tests {
             None,
         );
         let (mut reader, _addr) = listener.accept().unwrap();
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -600,7 +635,7 @@ mod names_tests {
             }
         }
 
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -623,4 +658,148 @@ mod names_tests {
 
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_handle_names_command_invisible_users() {
+        let listener = TcpListener::bind("127.0.0.1:0".to_string()).unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let mut hash_network_clients = HashMap::new();
+        let mut remote_invisible = RemoteClient::new(1);
+        remote_invisible.modes.push(UserFlag::Invisible);
+        hash_network_clients.insert("remote".to_string(), RemoteClient::new(1));
+        hash_network_clients.insert("remote_invisible".to_string(), remote_invisible);
+        let arc_network_clients = Arc::new(RwLock::new(hash_network_clients));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
+        let arc_server = Arc::new(RwLock::new(Server {
+            ip: "0.0.0.0".to_string(),
+            port: "0".to_string(),
+            name: "test".to_string(),
+            operators: vec![],
+            father: None,
+            children: HashMap::new(),
+        }));
+
+        let network = Network {
+            server: arc_server,
+            servers: arc_servers,
+            clients: arc_network_clients,
+            links: LinkQueues::default(),
+        };
+
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        let client = create_client_for_test(&session, addr.to_owned(), "nickname".to_string());
+        let client2 = create_client_for_test(&session, addr, "nickname2".to_string());
+        session.clients.update(&client2.nickname, |client| {
+            client.modes.push(UserFlag::Invisible)
+        });
+
+        let mut channel = Channel::new("#channel_test".to_string(), "".to_string(), vec![]);
+        channel.users.push(client2.nickname.to_string());
+        channel.users.push("remote".to_string());
+        channel.users.push("remote_invisible".to_string());
+
+        let mut channels_lock = write_lock_channels(&session).unwrap();
+        channels_lock.insert(channel.name.clone(), channel.clone());
+        drop(channels_lock);
+
+        let message = create_message_for_test(MessageType::Names, vec![channel.name.to_string()]);
+
+        let result = handle_names_command(
+            message,
+            client.nickname.to_string(),
+            &session,
+            &network,
+            None,
+        );
+        let (mut reader, _addr) = listener.accept().unwrap();
+        match read_response_for_test(&mut reader) {
+            Response::CommandResponse {
+                response: CommandResponse::Names { channel: c, names },
+            } => {
+                assert_eq!(c, channel.name.to_string());
+                assert_eq!(names, vec!["remote".to_string()]);
+            }
+            _ => panic!("Expected the names of the channel"),
+        }
+
+        let message = create_message_for_test(MessageType::Names, vec![channel.name.to_string()]);
+
+        let result2 = handle_names_command(
+            message,
+            client2.nickname.to_string(),
+            &session,
+            &network,
+            None,
+        );
+        let (mut reader, _addr) = listener.accept().unwrap();
+        match read_response_for_test(&mut reader) {
+            Response::CommandResponse {
+                response: CommandResponse::Names { channel: c, names },
+            } => {
+                assert_eq!(c, channel.name.to_string());
+                assert_eq!(names, channel.users);
+            }
+            _ => panic!("Expected the names of the channel"),
+        }
+
+        assert!(result.is_ok());
+        assert!(result2.is_ok());
+    }
+
+    #[test]
+    fn test_handle_names_command_voiced_users() {
+        let listener = TcpListener::bind("127.0.0.1:0".to_string()).unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let network = Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        };
+
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        let client = create_client_for_test(&session, addr, "nickname".to_string());
+        let (mut reader, _addr) = listener.accept().unwrap();
+
+        let mut channel = Channel::new(
+            "#channel_test".to_string(),
+            "".to_string(),
+            vec![client.nickname.to_string(), "voiced".to_string()],
+        );
+        channel.moderators.push("voiced".to_string());
+        write_lock_channels(&session)
+            .unwrap()
+            .insert(channel.name.clone(), channel.clone());
+
+        let message = create_message_for_test(MessageType::Names, vec![channel.name.to_string()]);
+        let result = handle_names_command(
+            message,
+            client.nickname.to_string(),
+            &session,
+            &network,
+            None,
+        );
+
+        assert!(result.is_ok());
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        match Response::serialize(String::from_utf8(msg).unwrap()).unwrap() {
+            Response::CommandResponse {
+                response: CommandResponse::Names { channel: _, names },
+            } => assert_eq!(names, vec!["nickname".to_string(), "+voiced".to_string()]),
+            _ => panic!("Expected the names of the channel"),
+        }
+    }
 }
@@ -1,8 +1,18 @@
-use crate::server_errors::ServerError;
+use crate::{
+    commands::nickserv::is_nickserv,
+    locks::{
+        read_lock_clients, read_lock_config, read_lock_network_clients, read_lock_server,
+        read_lock_servers,
+    },
+    password::verify_password,
+    server_errors::ServerError,
+};
 use model::{client::Client, message::Message, network::Network, session::Session};
 
 /// Functions that handles the NICK command sent by a client.
 /// If nickname already in use, it checks the password if exists.
+/// Nicknames longer than the maximum length of the server are rejected,
+/// as well as the reserved ones, the names of the servers of the network and the one of NickServ.
 /// # Arguments
 /// * `message` - The message sent by the client.
 /// * `user_parameters` - The username, realname, servername y hostname.
@@ -22,69 +32,71 @@ pub fn handle_nick_command(
         return Err(ServerError::InvalidParameters);
     }
     println!("NICK {}", message.parameters[0]);
+    let config = read_lock_config(session)?;
+    let valid = config.is_valid_nickname(&message.parameters[0])
+        && !config.is_reserved_nickname(&message.parameters[0])
+        && !is_nickserv(&message.parameters[0]);
+    drop(config);
+    if !valid || is_server_name(&message.parameters[0], network)? {
+        *nickname = None;
+        return Err(ServerError::ErroneusNickname);
+    }
     *nickname = Option::Some(message.parameters[0].to_owned());
     let nick = message.parameters[0].to_owned();
 
-    match session.clients.read() {
-        Ok(clients) => {
-            if let Some(c) = clients.get(&nick) {
-                if user_parameters.is_some() {
-                    *user_parameters = None;
-                    *nickname = None;
-                    *password = None;
-                    return Err(ServerError::NicknameInUse(nick.clone()));
-                }
-                if let Some(pass) = c.password.to_owned() {
-                    if let Some(p) = password.to_owned() {
-                        if pass == p {
-                            let vec = vec![
-                                c.username.to_owned(),
-                                c.hostname.to_owned(),
-                                c.servername.to_owned(),
-                                c.realname.to_owned(),
-                            ];
-                            if c.connected {
-                                return Err(ServerError::NicknameInUse(nick.clone()));
-                            }
-                            *user_parameters = Option::Some(vec);
-                            return Ok(Some(c.to_owned()));
-                        } else {
-                            *nickname = None;
-                            *password = None;
-                            return Err(ServerError::InvalidPassword);
-                        }
-                    } else {
-                        *nickname = None;
-                        *password = None;
-                        return Err(ServerError::InvalidPassword);
-                    }
-                } else {
+    let clients = read_lock_clients(session)?;
+    if let Some(c) = clients.get(&nick) {
+        if user_parameters.is_some() {
+            *user_parameters = None;
+            *nickname = None;
+            *password = None;
+            return Err(ServerError::NicknameInUse(nick.clone()));
+        }
+        if let Some(pass) = c.password.to_owned() {
+            if let Some(p) = password.to_owned() {
+                if verify_password(&pass, &p) {
                     let vec = vec![
                         c.username.to_owned(),
                         c.hostname.to_owned(),
                         c.servername.to_owned(),
                         c.realname.to_owned(),
                     ];
+                    if c.connected {
+                        return Err(ServerError::NicknameInUse(nick.clone()));
+                    }
                     *user_parameters = Option::Some(vec);
-                    return Ok(Option::Some(c.to_owned()));
-                }
-            } else {
-                let network_clients = network.clients.read()?;
-                if network_clients.get(&nick).is_some() {
-                    *user_parameters = None;
+                    return Ok(Some(c.to_owned()));
+                } else {
                     *nickname = None;
                     *password = None;
-                    return Err(ServerError::NicknameInUse(nick.clone()));
+                    return Err(ServerError::InvalidPassword);
                 }
-                drop(network_clients);
+            } else {
+                *nickname = None;
+                *password = None;
+                return Err(ServerError::InvalidPassword);
             }
-            drop(clients);
+        } else {
+            let vec = vec![
+                c.username.to_owned(),
+                c.hostname.to_owned(),
+                c.servername.to_owned(),
+                c.realname.to_owned(),
+            ];
+            *user_parameters = Option::Some(vec);
+            return Ok(Option::Some(c.to_owned()));
         }
-        Err(_) => {
-            println!("Error reading clients");
-            return Err(ServerError::LockError);
+    } else {
+        let network_clients = read_lock_network_clients(network)?;
+        if network_clients.get(&nick).is_some() {
+            *user_parameters = None;
+            *nickname = None;
+            *password = None;
+            return Err(ServerError::NicknameInUse(nick.clone()));
         }
-    };
+        drop(network_clients);
+    }
+    drop(clients);
 
     match user_parameters {
         Some(user_params) => {
@@ -103,6 +115,17 @@ pub fn handle_nick_command(
     }
 }
 
+/// Returns true if the nickname is the name of a server of the network, ignoring the case.
+fn is_server_name(nickname: &str, network: &Network) -> Result<bool, ServerError> {
+    let nickname = nickname.to_lowercase();
+    let servers = read_lock_servers(network)?;
+    if servers.keys().any(|name| name.to_lowercase() == nickname) {
+        return Ok(true);
+    }
+    drop(servers);
+    Ok(read_lock_server(network)?.name.to_lowercase() == nickname)
+}
+
 #[cfg(test)]
 mod nick_tests {
 
@@ -113,25 +136,32 @@ mod nick_tests {
     };
 
     use model::{
-        message::MessageType, network::Network, persistence::PersistenceType, server::Server,
+        client::Client,
+        link_queues::LinkQueues,
+        message::MessageType,
+        network::{LinkedServer, Network},
+        persistence::PersistenceType,
+        server::Server,
+        session::Session,
     };
 
+    use crate::locks::read_lock_clients;
     use crate::{
         commands::{
             command_utils::{
                 create_client_for_test, create_message_for_test, create_session_for_test,
-                read_lock_clients,
             },
             nick::handle_nick_command,
         },
-        database::handle_database,
+        database::{handle_database, DatabasePaths},
+        server_errors::ServerError,
     };
 
     #[test]
     fn test_command_nick_with_username() {
         let listener = TcpListener::bind("127.0.0.1:8144".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8144".to_string(),
@@ -145,10 +175,11 @@ mod nick_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let mut nickname = Option::None;
         let mut user_parameters = Option::Some(vec![
@@ -179,11 +210,49 @@ mod nick_tests {
         drop(listener);
     }
 
+    #[test]
+    fn test_nick_command_longer_than_nicklen() {
+        let network = Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        };
+
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        session.config.write().unwrap().nicklen = 5;
+        let mut nickname = Option::None;
+        let mut user_parameters = Option::None;
+        let mut password = Option::None;
+
+        let msg = create_message_for_test(MessageType::Nick, vec!["nickname".to_string()]);
+        let result = handle_nick_command(
+            msg,
+            &mut nickname,
+            &mut user_parameters,
+            &mut password,
+            &session,
+            &network,
+        );
+
+        assert!(matches!(result, Err(ServerError::ErroneusNickname)));
+        assert!(nickname.is_none());
+    }
+
     #[test]
     fn test_nick_command_with_existing_nickname() {
         let listener = TcpListener::bind("127.0.0.1:8145".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8145".to_string(),
@@ -197,10 +266,11 @@ mod nick_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client = create_client_for_test(
             &session,
@@ -235,7 +305,7 @@ mod nick_tests {
     fn test_command_nick_invalid_parameters() {
         let listener = TcpListener::bind("127.0.0.1:8146".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8146".to_string(),
@@ -249,10 +319,11 @@ mod nick_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let msg = create_message_for_test(
             MessageType::Nick,
@@ -279,4 +350,87 @@ mod nick_tests {
         drop(listener);
         assert!(result.is_err());
     }
+
+    fn create_network_for_test() -> Network {
+        Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        }
+    }
+
+    fn send_nick_for_test(
+        nick: &str,
+        session: &Session,
+        network: &Network,
+    ) -> (Result<Option<Client>, ServerError>, Option<String>) {
+        let mut nickname = Option::None;
+        let mut user_parameters = Option::Some(vec![
+            "username".to_string(),
+            "hostname".to_string(),
+            "servername".to_string(),
+            "realname".to_string(),
+        ]);
+        let mut password = Option::None;
+        let msg = create_message_for_test(MessageType::Nick, vec![nick.to_string()]);
+        let result = handle_nick_command(
+            msg,
+            &mut nickname,
+            &mut user_parameters,
+            &mut password,
+            session,
+            network,
+        );
+        (result, nickname)
+    }
+
+    #[test]
+    fn test_nick_command_with_reserved_nicknames() {
+        let network = create_network_for_test();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        session
+            .config
+            .write()
+            .unwrap()
+            .set("RESERVEDNICKS", "root, admin*,op?");
+
+        for nick in ["root", "ROOT", "admin", "Admin42", "op1"] {
+            let (result, nickname) = send_nick_for_test(nick, &session, &network);
+            assert!(matches!(result, Err(ServerError::ErroneusNickname)));
+            assert!(nickname.is_none());
+        }
+        for nick in ["rooted", "myadmin", "op", "op12"] {
+            let (result, _) = send_nick_for_test(nick, &session, &network);
+            assert!(result.is_ok());
+        }
+    }
+
+    #[test]
+    fn test_nick_command_with_the_name_of_a_server() {
+        let network = create_network_for_test();
+        network
+            .servers
+            .write()
+            .unwrap()
+            .insert("child".to_string(), LinkedServer::new(1));
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+
+        let (current, _) = send_nick_for_test("Test", &session, &network);
+        let (child, _) = send_nick_for_test("child", &session, &network);
+
+        assert!(matches!(current, Err(ServerError::ErroneusNickname)));
+        assert!(matches!(child, Err(ServerError::ErroneusNickname)));
+    }
 }
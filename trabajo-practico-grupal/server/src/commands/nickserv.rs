@@ -0,0 +1,373 @@
+use crate::{
+    database::inform_database,
+    locks::{read_lock_clients, write_lock_clients},
+    password::{hash_password, verify_password},
+    server_errors::ServerError,
+    socket::inform_client,
+};
+use model::{
+    json::ToJson, message::Message, persistence::PersistenceType,
+    responses::message::MessageResponse, session::Session,
+};
+
+/// The nickname of the service, that no client can take.
+pub static NICKSERV: &str = "NickServ";
+
+/// Returns true if the receiver of a message is the service, ignoring the case.
+pub fn is_nickserv(receiver: &str) -> bool {
+    receiver.eq_ignore_ascii_case(NICKSERV)
+}
+
+/// Handles a PRIVMSG sent to NickServ, the service that registers the nicknames.
+/// The text of the message is the command of the service and its parameters:
+/// * `REGISTER <password> <email>` - Registers the nickname of the client with a password.
+/// * `IDENTIFY <password>` - Checks that the client owns its nickname.
+/// * `DROP <password>` - Removes the registration of the nickname.
+///
+/// Once a nickname is registered, a client can only take it by sending its password with PASS.
+/// The service answers every command with a private message.
+/// # Arguments
+/// * `message` - The PRIVMSG sent by the client.
+/// * `nickname` - The nickname of the client that sent the message.
+/// * `session` - The session of the current server.
+pub fn handle_nickserv_message(
+    message: &Message,
+    nickname: &String,
+    session: &Session,
+) -> Result<(), ServerError> {
+    let mut text = message.parameters[1..].to_vec();
+    if let Some(trailing) = &message.trailing {
+        text.extend(trailing.split_whitespace().map(str::to_string));
+    }
+    let command = match text.first() {
+        Some(command) => command.to_uppercase(),
+        None => String::new(),
+    };
+
+    match (command.as_str(), text.len()) {
+        ("REGISTER", 3) => register(nickname, &text[1], &text[2], session),
+        ("IDENTIFY", 2) => identify(nickname, &text[1], session),
+        ("DROP", 2) => drop_registration(nickname, &text[1], session),
+        ("REGISTER", _) => reply_error(
+            session,
+            nickname,
+            "Syntax: REGISTER <password> <email>",
+            ServerError::InvalidParameters,
+        ),
+        ("IDENTIFY", _) => reply_error(
+            session,
+            nickname,
+            "Syntax: IDENTIFY <password>",
+            ServerError::InvalidParameters,
+        ),
+        ("DROP", _) => reply_error(
+            session,
+            nickname,
+            "Syntax: DROP <password>",
+            ServerError::InvalidParameters,
+        ),
+        _ => reply_error(
+            session,
+            nickname,
+            "Unknown command, use REGISTER, IDENTIFY or DROP",
+            ServerError::InvalidCommand,
+        ),
+    }
+}
+
+/// Registers the nickname with the hash of the password and the email, and saves it in the database.
+fn register(
+    nickname: &String,
+    password: &str,
+    email: &str,
+    session: &Session,
+) -> Result<(), ServerError> {
+    if !email.contains('@') || email.contains(';') || password.contains(';') {
+        return reply_error(
+            session,
+            nickname,
+            "The email must contain '@', and neither the email nor the password can contain ';'",
+            ServerError::InvalidParameters,
+        );
+    }
+    let mut clients = write_lock_clients(session)?;
+    let client = match clients.get_mut(nickname) {
+        Some(client) => client,
+        None => return Err(ServerError::ClientNotFound),
+    };
+    if client.password.is_some() {
+        drop(clients);
+        let text = format!("Nickname {} is already registered", nickname);
+        return reply_error(
+            session,
+            nickname,
+            &text,
+            ServerError::NicknameAlreadyRegistered,
+        );
+    }
+    client.password = Some(hash_password(password));
+    client.email = Some(email.to_string());
+    inform_database(
+        PersistenceType::ClientUpdate(nickname.to_owned()),
+        client.to_json(),
+        session,
+    )?;
+    drop(clients);
+
+    let text = format!("Nickname {} registered with the email {}", nickname, email);
+    reply(session, nickname, &text)
+}
+
+/// Checks that the password is the one of the nickname.
+fn identify(nickname: &String, password: &str, session: &Session) -> Result<(), ServerError> {
+    let clients = read_lock_clients(session)?;
+    let saved = clients.get(nickname).and_then(|c| c.password.to_owned());
+    drop(clients);
+    match saved {
+        Some(saved) if verify_password(&saved, password) => {
+            let text = format!("You are now identified for {}", nickname);
+            reply(session, nickname, &text)
+        }
+        Some(_) => {
+            let text = format!("Invalid password for {}", nickname);
+            reply_error(session, nickname, &text, ServerError::InvalidPassword)
+        }
+        None => {
+            let text = format!("Nickname {} is not registered", nickname);
+            reply_error(session, nickname, &text, ServerError::NicknameNotRegistered)
+        }
+    }
+}
+
+/// Removes the password and the email of the nickname, and saves it in the database.
+fn drop_registration(
+    nickname: &String,
+    password: &str,
+    session: &Session,
+) -> Result<(), ServerError> {
+    let mut clients = write_lock_clients(session)?;
+    let client = match clients.get_mut(nickname) {
+        Some(client) => client,
+        None => return Err(ServerError::ClientNotFound),
+    };
+    let error = match &client.password {
+        Some(saved) if verify_password(saved, password) => None,
+        Some(_) => Some((
+            format!("Invalid password for {}", nickname),
+            ServerError::InvalidPassword,
+        )),
+        None => Some((
+            format!("Nickname {} is not registered", nickname),
+            ServerError::NicknameNotRegistered,
+        )),
+    };
+    if let Some((text, error)) = error {
+        drop(clients);
+        return reply_error(session, nickname, &text, error);
+    }
+    client.password = None;
+    client.email = None;
+    inform_database(
+        PersistenceType::ClientUpdate(nickname.to_owned()),
+        client.to_json(),
+        session,
+    )?;
+    drop(clients);
+
+    let text = format!("Nickname {} has been dropped", nickname);
+    reply(session, nickname, &text)
+}
+
+/// Sends a private message from the service to the client.
+fn reply(session: &Session, nickname: &String, text: &str) -> Result<(), ServerError> {
+    let response = MessageResponse::UserPrivMsg {
+        sender: NICKSERV.to_string(),
+        message: text.to_string(),
+    }
+    .to_string();
+    inform_client(session, nickname, &response)
+}
+
+/// Sends a private message from the service to the client, and returns the error received.
+fn reply_error(
+    session: &Session,
+    nickname: &String,
+    text: &str,
+    error: ServerError,
+) -> Result<(), ServerError> {
+    reply(session, nickname, text)?;
+    Err(error)
+}
+
+#[cfg(test)]
+mod nickserv_tests {
+    use model::consts::MAX_MSG_SIZE;
+    use std::{
+        collections::HashMap,
+        io::Read,
+        net::{TcpListener, TcpStream},
+        sync::{Arc, RwLock},
+    };
+
+    use model::{
+        json::ToJson,
+        link_queues::LinkQueues,
+        message::{Message, MessageType},
+        network::Network,
+        persistence::PersistenceType,
+        responses::{message::MessageResponse, response::Response},
+        server::Server,
+        session::Session,
+    };
+
+    use crate::{
+        commands::{
+            command_utils::{create_client_for_test, create_session_for_test},
+            nick::handle_nick_command,
+            nickserv::handle_nickserv_message,
+        },
+        database::{handle_database, DatabasePaths},
+        locks::{read_lock_clients, write_lock_clients},
+        server_errors::ServerError,
+    };
+
+    fn create_nickserv_session_for_test(listener: &TcpListener) -> (Session, TcpStream) {
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr, "nickname".to_string());
+        let (reader, _) = listener.accept().unwrap();
+        (session, reader)
+    }
+
+    fn send_to_nickserv_for_test(text: &str, session: &Session) -> Result<(), ServerError> {
+        let message = Message::new(
+            None,
+            MessageType::Privmsg,
+            vec!["NickServ".to_string()],
+            Some(text.to_string()),
+        );
+        handle_nickserv_message(&message, &"nickname".to_string(), session)
+    }
+
+    fn read_reply_for_test(reader: &mut TcpStream) -> String {
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        match Response::serialize(String::from_utf8(msg).unwrap()).unwrap() {
+            Response::MessageResponse {
+                response: MessageResponse::UserPrivMsg { sender, message },
+            } => {
+                assert_eq!(sender, "NickServ");
+                message
+            }
+            _ => panic!("expected a private message of NickServ"),
+        }
+    }
+
+    #[test]
+    fn test_register_saves_the_hash_of_the_password_and_the_email() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (session, mut reader) = create_nickserv_session_for_test(&listener);
+
+        let result = send_to_nickserv_for_test("REGISTER secret nick@mail.com", &session);
+
+        assert!(result.is_ok());
+        let clients = read_lock_clients(&session).unwrap();
+        let password = clients["nickname"].password.to_owned().unwrap();
+        assert!(password.starts_with("pbkdf2-sha256$"));
+        assert!(!password.contains("secret"));
+        assert_eq!(clients["nickname"].email, Some("nick@mail.com".to_string()));
+        assert!(clients["nickname"]
+            .to_json()
+            .contains(r#""email":"nick@mail.com""#));
+        drop(clients);
+        assert!(read_reply_for_test(&mut reader).contains("registered"));
+
+        let again = send_to_nickserv_for_test("REGISTER other nick@mail.com", &session);
+        assert_eq!(again, Err(ServerError::NicknameAlreadyRegistered));
+        assert!(read_reply_for_test(&mut reader).contains("already registered"));
+    }
+
+    #[test]
+    fn test_identify_checks_the_password_of_the_nickname() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (session, mut reader) = create_nickserv_session_for_test(&listener);
+
+        let unregistered = send_to_nickserv_for_test("IDENTIFY secret", &session);
+        read_reply_for_test(&mut reader);
+        send_to_nickserv_for_test("REGISTER secret nick@mail.com", &session).unwrap();
+        read_reply_for_test(&mut reader);
+        let wrong = send_to_nickserv_for_test("IDENTIFY other", &session);
+        read_reply_for_test(&mut reader);
+        let right = send_to_nickserv_for_test("identify secret", &session);
+
+        assert_eq!(unregistered, Err(ServerError::NicknameNotRegistered));
+        assert_eq!(wrong, Err(ServerError::InvalidPassword));
+        assert!(right.is_ok());
+        assert!(read_reply_for_test(&mut reader).contains("identified"));
+    }
+
+    #[test]
+    fn test_drop_removes_the_registration() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (session, mut reader) = create_nickserv_session_for_test(&listener);
+        send_to_nickserv_for_test("REGISTER secret nick@mail.com", &session).unwrap();
+        read_reply_for_test(&mut reader);
+
+        let wrong = send_to_nickserv_for_test("DROP other", &session);
+        read_reply_for_test(&mut reader);
+        let right = send_to_nickserv_for_test("DROP secret", &session);
+
+        assert_eq!(wrong, Err(ServerError::InvalidPassword));
+        assert!(right.is_ok());
+        let clients = read_lock_clients(&session).unwrap();
+        assert!(clients["nickname"].password.is_none());
+        assert!(clients["nickname"].email.is_none());
+    }
+
+    #[test]
+    fn test_registered_nickname_can_only_be_taken_with_its_password() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (session, mut reader) = create_nickserv_session_for_test(&listener);
+        send_to_nickserv_for_test("REGISTER secret nick@mail.com", &session).unwrap();
+        read_reply_for_test(&mut reader);
+        write_lock_clients(&session)
+            .unwrap()
+            .get_mut("nickname")
+            .unwrap()
+            .connected = false;
+        let network = Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        };
+
+        let mut results = vec![];
+        for pass in [None, Some("other"), Some("secret")] {
+            let message = Message::new(None, MessageType::Nick, vec!["nickname".to_string()], None);
+            let mut password = pass.map(str::to_string);
+            results.push(handle_nick_command(
+                message,
+                &mut None,
+                &mut None,
+                &mut password,
+                &session,
+                &network,
+            ));
+        }
+
+        assert!(matches!(results[0], Err(ServerError::InvalidPassword)));
+        assert!(matches!(results[1], Err(ServerError::InvalidPassword)));
+        assert!(matches!(results[2], Ok(Some(_))));
+    }
+}
@@ -1,7 +1,8 @@
 use std::io::{BufRead, BufReader};
 
-use crate::{server_errors::ServerError, socket::inform_client};
+use crate::{locks::write_lock_server, server_errors::ServerError, socket::inform_client};
 use model::{
+    events::ServerEvent,
     message::Message,
     network::Network,
     responses::{errors::ErrorResponse, replies::CommandResponse},
@@ -44,17 +45,17 @@ pub fn handle_oper_command(
                 };
                 let credentials = line.split(';').into_iter().collect::<Vec<&str>>();
                 if credentials[0] == nick && credentials[1] == pass {
-                    let mut server_lock = match network.server.as_ref().write() {
-                        Ok(server_lock) => server_lock,
-                        Err(_) => return Err(ServerError::LockError),
-                    };
+                    let mut server_lock = write_lock_server(network)?;
                     if !server_lock.operators.contains(&nickname) {
                         server_lock.operators.push(nickname.clone());
                     }
-                    let response = CommandResponse::YouAreOperator.to_string();
-                    inform_client(session, &nickname, response.as_str())?;
                     println!("Operator added: {:?}", server_lock);
                     drop(server_lock);
+                    let response = CommandResponse::YouAreOperator.to_string();
+                    inform_client(session, &nickname, response.as_str())?;
+                    session.publish(ServerEvent::OperatorGranted {
+                        nickname: nickname.to_owned(),
+                    });
                     found = true;
                     break;
                 }
@@ -76,6 +77,7 @@ pub fn handle_oper_command(
 
 #[cfg(test)]
 mod oper_tests {
+    use model::consts::MAX_MSG_SIZE;
     use std::collections::HashMap;
     use std::io::Read;
     use std::net::TcpListener;
@@ -86,8 +88,9 @@ mod oper_tests {
         create_client_for_test, create_message_for_test, create_session_for_test,
     };
     use crate::commands::oper::handle_oper_command;
-    use crate::database::handle_database;
+    use crate::database::{handle_database, DatabasePaths};
     use crate::server_errors::ServerError;
+    use model::link_queues::LinkQueues;
     use model::message::MessageType;
     use model::network::Network;
     use model::persistence::PersistenceType;
@@ -99,7 +102,7 @@ mod oper_tests {
     pub fn test_command_oper_invalid_parameters() {
         let listener = TcpListener::bind("127.0.0.1:8118".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8118".to_string(),
@@ -113,10 +116,11 @@ mod oper_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client = create_client_for_test(
             &session,
@@ -128,7 +132,7 @@ mod oper_tests {
         let result = handle_oper_command(message, client.nickname.to_string(), &session, &network);
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -4,62 +4,58 @@ use crate::{
     socket::{inform_client, inform_network},
 };
 use model::{
-    message::Message, network::Network, persistence::PersistenceType,
-    responses::errors::ErrorResponse, session::Session,
+    consts::NETWORK_CHANNEL_PREFIX,
+    events::ServerEvent,
+    json::ToJson,
+    message::Message,
+    network::Network,
+    persistence::PersistenceType,
+    responses::{errors::ErrorResponse, message::MessageResponse},
+    session::Session,
 };
 
-use super::command_utils::write_lock_channels;
+use super::command_utils::{require_params, TargetResults};
+use super::mode::transfer_channel_operator;
+use crate::locks::write_lock_channels;
 
 /// Handles the PART command received from a client/server
-/// If channel is empty, it is removed from the database
+/// If channel is empty, it is removed from the database, unless it is permanent
 /// # Arguments
 /// * `session` - The session of the current server
 /// * `network` - The network the client is connected to
 /// * `message` - The message received from the client/server
 /// * `nickname` - The nickname of the client
 /// * `server_name` - The name of the server
+///
+/// Without a part message, the part message of the preferences of the client is sent.
+/// The members of the channel connected to this server are informed that the client left.
+/// Every channel is left even if leaving another one fails. The client is informed of the
+/// channels it is not on or that don't exist, and the result of each channel is returned.
 pub fn handle_part_command(
-    message: Message,
+    mut message: Message,
     nickname: String,
     session: &Session,
     network: &Network,
     server_name: &String,
-) -> Result<(), ServerError> {
-    if message.parameters.is_empty() {
-        let response = ErrorResponse::NeedMoreParams {
-            command: "PART".to_string(),
+) -> Result<TargetResults, ServerError> {
+    require_params(&message, 1, "PART", &nickname, session)?;
+    if message.trailing.is_none() {
+        if let Some(client) = session.clients.get(&nickname) {
+            message.trailing = client.preferences.part_message;
         }
-        .to_string();
-        inform_client(session, &nickname, &response)?;
-        return Err(ServerError::InvalidParameters);
     }
     let channels_name = message.parameters[0]
         .split(',')
         .map(|a| a.trim())
         .collect::<Vec<_>>();
 
+    let mut results = TargetResults::new();
     for channel_name in channels_name {
         let mut channels = write_lock_channels(session)?;
         if let Some(channel) = channels.get_mut(channel_name) {
-            let mut user_eliminated = false;
-            for (i, user) in channel.users.iter().enumerate() {
-                if *user == nickname {
-                    channel.users.remove(i);
-                    println!("Channel left: {:?}", channel);
-                    user_eliminated = true;
-                    break;
-                }
-            }
-            if channel_name.starts_with('#') && !user_eliminated {
-                let network_cliens = network.clients.read()?;
-                for (i, (user, _)) in network_cliens.iter().enumerate() {
-                    if *user == nickname {
-                        channel.users.remove(i);
-                        user_eliminated = true;
-                        break;
-                    }
-                }
-                drop(network_cliens);
+            let user_eliminated = channel.remove_member(&nickname);
+            if user_eliminated {
+                println!("Channel left: {:?}", channel);
             }
             if !user_eliminated {
                 let response = ErrorResponse::NotOnChannel {
@@ -67,68 +63,89 @@ pub fn handle_part_command(
                 }
                 .to_string();
                 inform_client(session, &nickname, &response)?;
+                results.push(channel_name, Err(ServerError::ClientNotOnChannel));
                 continue;
             }
-            if channel.users.is_empty() {
+            if channel.users.is_empty() && !channel.is_permanent() {
                 inform_database(
                     PersistenceType::ChannelDelete(channel.name.to_owned()),
-                    channel.to_string(),
+                    channel.to_json(),
                     session,
                 )?;
                 channels.remove(channel_name);
             } else {
                 inform_database(
                     PersistenceType::ChannelUpdate(channel.name.to_owned()),
-                    channel.to_string(),
+                    channel.to_json(),
                     session,
                 )?;
+                transfer_channel_operator(channel, &nickname, session, |_| true)?;
+                let response = MessageResponse::PartMsg {
+                    channel: channel_name.to_string(),
+                    nickname: nickname.to_owned(),
+                }
+                .to_string();
+                for user in channel.users.iter() {
+                    inform_client(session, user, &response)?;
+                }
             }
-            if channel_name.starts_with('#') {
+            if channel_name.starts_with(NETWORK_CHANNEL_PREFIX) {
                 let mut msg = message.clone();
-                msg.prefix = Some(nickname.clone());
+                msg.set_nick_prefix(&nickname);
                 let msg = Message::deserialize(msg)?;
                 inform_network(network, server_name, &msg)?;
             }
+            results.push(channel_name, Ok(()));
         } else {
             let response = ErrorResponse::NoSuchChannel {
                 channel: channel_name.to_string(),
             }
             .to_string();
             inform_client(session, &nickname, &response)?;
+            results.push(channel_name, Err(ServerError::ChannelNotFound));
         }
         drop(channels);
     }
-    Ok(())
+    for channel in results.succeeded() {
+        session.publish(ServerEvent::ChannelParted {
+            channel: channel.to_owned(),
+            nickname: nickname.to_owned(),
+        });
+    }
+    Ok(results)
 }
 
 #[cfg(test)]
 mod part_tests {
+    use model::consts::MAX_MSG_SIZE;
     use std::collections::HashMap;
     use std::io::Read;
     use std::net::TcpListener;
     use std::sync::{Arc, RwLock};
 
     use model::channel::Channel;
+    use model::link_queues::LinkQueues;
     use model::message::MessageType;
     use model::network::Network;
     use model::persistence::PersistenceType;
     use model::responses::errors::ErrorResponse;
+    use model::responses::message::MessageResponse;
     use model::responses::response::Response;
     use model::server::Server;
 
     use crate::commands::command_utils::{
-        create_client_for_test, create_message_for_test, create_session_for_test,
-        read_lock_channels, write_lock_channels,
+        create_client_for_test, create_message_for_test, create_session_for_test, TargetResults,
     };
     use crate::commands::part::handle_part_command;
-    use crate::database::handle_database;
+    use crate::database::{handle_database, DatabasePaths};
+    use crate::locks::{read_lock_channels, write_lock_channels};
     use crate::server_errors::ServerError;
 
     #[test]
     fn test_command_part_client_leaves_the_channel() {
         let listener = TcpListener::bind("127.0.0.1:8120".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8120".to_string(),
@@ -142,10 +159,11 @@ mod part_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client = create_client_for_test(
             &session,
@@ -177,7 +195,8 @@ mod part_tests {
             &session,
             &network,
             &"test".to_string(),
-        );
+        )
+        .and_then(TargetResults::into_result);
         assert!(result.is_ok());
 
         let channels_lock = read_lock_channels(&session).unwrap();
@@ -191,7 +210,7 @@ mod part_tests {
     fn test_command_part_with_one_client_deletes_the_channel() {
         let listener = TcpListener::bind("127.0.0.1:8121".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8121".to_string(),
@@ -205,10 +224,11 @@ mod part_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client = create_client_for_test(
             &session,
@@ -233,7 +253,8 @@ mod part_tests {
             &session,
             &network,
             &"test".to_string(),
-        );
+        )
+        .and_then(TargetResults::into_result);
         assert!(result.is_ok());
 
         let channels_lock = read_lock_channels(&session).unwrap();
@@ -246,7 +267,7 @@ mod part_tests {
     pub fn test_command_part_invalid_channel_name() {
         let listener = TcpListener::bind("127.0.0.1:8121".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8121".to_string(),
@@ -260,10 +281,11 @@ mod part_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client = create_client_for_test(
             &session,
@@ -280,10 +302,11 @@ mod part_tests {
             &session,
             &network,
             &"test".to_string(),
-        );
-        assert!(result.is_ok());
+        )
+        .and_then(TargetResults::into_result);
+        assert_eq!(result, Err(ServerError::ChannelNotFound));
         let (mut reader, _addr) = listener.accept().unwrap();
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -314,7 +337,7 @@ mod part_tests {
     pub fn test_command_part_invalid_parameters() {
         let listener = TcpListener::bind("127.0.0.1:8122".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8122".to_string(),
@@ -328,10 +351,11 @@ mod part_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client = create_client_for_test(
             &session,
@@ -346,9 +370,10 @@ mod part_tests {
             &session,
             &network,
             &"test".to_string(),
-        );
+        )
+        .and_then(TargetResults::into_result);
         let (mut reader, _addr) = listener.accept().unwrap();
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -377,7 +402,7 @@ mod part_tests {
     fn test_command_part_not_on_channel() {
         let listener = TcpListener::bind("127.0.0.1:8123".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8123".to_string(),
@@ -391,10 +416,11 @@ mod part_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client = create_client_for_test(
             &session,
@@ -421,11 +447,12 @@ mod part_tests {
             &session,
             &network,
             &"test".to_string(),
-        );
-        assert!(result.is_ok());
+        )
+        .and_then(TargetResults::into_result);
+        assert_eq!(result, Err(ServerError::ClientNotOnChannel));
 
         let (mut reader, _addr) = listener.accept().unwrap();
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -447,4 +474,74 @@ mod part_tests {
             }
         }
     }
+
+    #[test]
+    fn test_command_part_of_the_last_operator_transfers_the_privileges() {
+        let listener = TcpListener::bind("127.0.0.1:0".to_string()).unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let network = Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        };
+
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        let operator = create_client_for_test(&session, addr.to_owned(), "operator".to_string());
+        let oldest = create_client_for_test(&session, addr.to_owned(), "oldest".to_string());
+        let newest = create_client_for_test(&session, addr, "newest".to_string());
+        let (_operator_reader, _addr) = listener.accept().unwrap();
+        let (mut oldest_reader, _addr) = listener.accept().unwrap();
+        let (_newest_reader, _addr) = listener.accept().unwrap();
+
+        let mut channel = Channel::new(
+            "#channel_test".to_string(),
+            "".to_string(),
+            vec![
+                operator.nickname.to_string(),
+                oldest.nickname.to_string(),
+                newest.nickname.to_string(),
+            ],
+        );
+        channel.operators.push(operator.nickname.to_string());
+        write_lock_channels(&session)
+            .unwrap()
+            .insert(channel.name.clone(), channel.clone());
+
+        let message = create_message_for_test(MessageType::Part, vec![channel.name.to_string()]);
+        let result = handle_part_command(
+            message,
+            operator.nickname.to_string(),
+            &session,
+            &network,
+            &"test".to_string(),
+        )
+        .and_then(TargetResults::into_result);
+        assert!(result.is_ok());
+
+        let channels_lock = read_lock_channels(&session).unwrap();
+        let operators = channels_lock[&channel.name].operators.clone();
+        drop(channels_lock);
+        assert!(operators.contains(&oldest.nickname));
+        assert!(!operators.contains(&newest.nickname));
+
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        oldest_reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        match Response::serialize(String::from_utf8(msg).unwrap()).unwrap() {
+            Response::MessageResponse {
+                response: MessageResponse::ChannelModeMsg { modes, .. },
+            } => assert_eq!(modes, "+o oldest"),
+            _ => panic!("Expected a channel mode notification"),
+        }
+    }
 }
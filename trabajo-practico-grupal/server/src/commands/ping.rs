@@ -0,0 +1,78 @@
+use crate::{server_errors::ServerError, socket::inform_client};
+use model::{message::Message, responses::replies::CommandResponse, session::Session};
+
+/// Handles the PING command of a client, answering it with a PONG that has the same token,
+/// so the client can measure the lag of its connection.
+/// # Arguments
+/// * `message` - The message sent by the client, with the token as its first parameter.
+/// * `nickname` - The nickname of the client that sent the message.
+/// * `session` - The session of the current server.
+/// * `server_name` - The name of the current server.
+pub fn handle_ping_command(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    server_name: &str,
+) -> Result<(), ServerError> {
+    let token = match message.parameters.first() {
+        Some(token) => token.to_owned(),
+        None => return Err(ServerError::InvalidParameters),
+    };
+    let response = CommandResponse::Pong {
+        server: server_name.to_string(),
+        token,
+    }
+    .to_string();
+    inform_client(session, &nickname, &response)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod ping_tests {
+    use model::consts::MAX_MSG_SIZE;
+    use std::{io::Read, net::TcpListener};
+
+    use model::{
+        message::MessageType,
+        persistence::PersistenceType,
+        responses::{replies::CommandResponse, response::Response},
+    };
+
+    use crate::{
+        commands::{
+            command_utils::{
+                create_client_for_test, create_message_for_test, create_session_for_test,
+            },
+            ping::handle_ping_command,
+        },
+        database::{handle_database, DatabasePaths},
+    };
+
+    #[test]
+    fn test_ping_is_answered_with_a_pong_with_the_same_token() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr, "nickname".to_string());
+        let (mut reader, _) = listener.accept().unwrap();
+
+        let message = create_message_for_test(MessageType::Ping, vec!["lag-7".to_string()]);
+        let result = handle_ping_command(message, "nickname".to_string(), &session, "test");
+
+        assert!(result.is_ok());
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        match Response::serialize(String::from_utf8(msg).unwrap()).unwrap() {
+            Response::CommandResponse {
+                response: CommandResponse::Pong { server, token },
+            } => {
+                assert_eq!(server, "test");
+                assert_eq!(token, "lag-7");
+            }
+            _ => panic!("Expected a pong"),
+        }
+    }
+}
@@ -0,0 +1,324 @@
+use super::command_utils::require_channel_name;
+use crate::locks::{read_lock_config, write_lock_clients};
+use crate::{database::inform_database, server_errors::ServerError, socket::inform_client};
+use model::{
+    json::ToJson,
+    message::Message,
+    persistence::PersistenceType,
+    preferences::Preference,
+    responses::{errors::ErrorResponse, replies::CommandResponse},
+    session::Session,
+};
+
+/// Handles the SETPREF command, which saves a preference of the client with its record in the
+/// database. The value is the rest of the message, and without a value the preference is removed.
+/// The channels of the auto-join list are separated by commas, and the away message is
+/// truncated to the maximum length of the configuration.
+/// The new value of the preference is sent back to the client.
+/// # Errors
+/// ServerError::InvalidParameters if the preference doesn't exist or the value has a `;`.
+/// ServerError::ChannelMustStartWithHashOrAmpersand if a channel of the auto-join list is not valid.
+///
+/// For each error a message is sent to the client to inform them of the error.
+pub fn handle_setpref_command(
+    message: Message,
+    nickname: String,
+    session: &Session,
+) -> Result<(), ServerError> {
+    let preference = message
+        .parameters
+        .first()
+        .and_then(|name| Preference::from_name(name));
+    let mut words = message
+        .parameters
+        .iter()
+        .skip(1)
+        .cloned()
+        .collect::<Vec<_>>();
+    words.extend(message.trailing.to_owned());
+    let mut value = words.join(" ");
+    let preference = match preference {
+        Some(preference) if !value.contains(';') => preference,
+        _ => {
+            let response = ErrorResponse::NeedMoreParams {
+                command: "SETPREF".to_string(),
+            }
+            .to_string();
+            inform_client(session, &nickname, &response)?;
+            return Err(ServerError::InvalidParameters);
+        }
+    };
+    match preference {
+        Preference::AutoJoin => require_channel_list(&value, &nickname, session)?,
+        Preference::Away => {
+            let awaylen = read_lock_config(session)?.awaylen;
+            value = value.chars().take(awaylen).collect();
+        }
+        Preference::PartMessage | Preference::QuitMessage => (),
+    }
+
+    let mut clients = write_lock_clients(session)?;
+    let client = match clients.get_mut(&nickname) {
+        Some(client) => client,
+        None => return Err(ServerError::ClientNotFound),
+    };
+    client.preferences.set(&preference, &value);
+    inform_database(
+        PersistenceType::ClientUpdate(nickname.to_owned()),
+        client.to_json(),
+        session,
+    )?;
+    let value = client.preferences.get(&preference);
+    drop(clients);
+
+    let response = CommandResponse::Preference {
+        name: preference.name().to_string(),
+        value,
+    }
+    .to_string();
+    inform_client(session, &nickname, &response)?;
+    Ok(())
+}
+
+/// Handles the SETAUTOJOIN command, which manages the channels the client joins after registering.
+/// With `+<channel>{,<channel>}` or `<channel>{,<channel>}` it adds the channels to the list and with
+/// `-<channel>{,<channel>}` it removes them. Without parameters the list is left as it is.
+/// The list is saved with the preferences of the client and sent back as the AUTOJOIN preference.
+/// # Errors
+/// ServerError::ChannelMustStartWithHashOrAmpersand if a channel added is not valid.
+///
+/// For each error a message is sent to the client to inform them of the error.
+pub fn handle_setautojoin_command(
+    message: Message,
+    nickname: String,
+    session: &Session,
+) -> Result<(), ServerError> {
+    let change = match message.parameters.first() {
+        Some(parameter) => match parameter.strip_prefix('-') {
+            Some(channels) => Some((false, channels.to_string())),
+            None => {
+                let channels = parameter.strip_prefix('+').unwrap_or(parameter);
+                require_channel_list(channels, &nickname, session)?;
+                Some((true, channels.to_string()))
+            }
+        },
+        None => None,
+    };
+
+    let mut clients = write_lock_clients(session)?;
+    let client = match clients.get_mut(&nickname) {
+        Some(client) => client,
+        None => return Err(ServerError::ClientNotFound),
+    };
+    if let Some((adding, channels)) = change {
+        let autojoin = &mut client.preferences.autojoin;
+        for channel in channels.split(',').filter(|channel| !channel.is_empty()) {
+            if adding && !autojoin.iter().any(|c| c == channel) {
+                autojoin.push(channel.to_string());
+            } else if !adding {
+                autojoin.retain(|c| c != channel);
+            }
+        }
+        inform_database(
+            PersistenceType::ClientUpdate(nickname.to_owned()),
+            client.to_json(),
+            session,
+        )?;
+    }
+    let value = client.preferences.get(&Preference::AutoJoin);
+    drop(clients);
+
+    let response = CommandResponse::Preference {
+        name: Preference::AutoJoin.name().to_string(),
+        value,
+    }
+    .to_string();
+    inform_client(session, &nickname, &response)?;
+    Ok(())
+}
+
+/// Handles the PREFS command, which lists every preference of the client with its value,
+/// followed by the EndOfPreferences response. The preferences not set have an empty value.
+pub fn handle_prefs_command(nickname: String, session: &Session) -> Result<(), ServerError> {
+    let preferences = match session.clients.get(&nickname) {
+        Some(client) => client.preferences,
+        None => return Err(ServerError::ClientNotFound),
+    };
+    for preference in Preference::iter() {
+        let response = CommandResponse::Preference {
+            name: preference.name().to_string(),
+            value: preferences.get(&preference),
+        }
+        .to_string();
+        inform_client(session, &nickname, &response)?;
+    }
+    let response = CommandResponse::EndOfPreferences.to_string();
+    inform_client(session, &nickname, &response)?;
+    Ok(())
+}
+
+/// Checks that every channel of a list separated by commas is a valid channel name.
+/// # Errors
+/// ServerError::ChannelMustStartWithHashOrAmpersand if a channel is not valid, after sending
+/// the NoSuchChannel response to the client.
+fn require_channel_list(
+    channels: &str,
+    nickname: &String,
+    session: &Session,
+) -> Result<(), ServerError> {
+    for channel in channels.split(',').filter(|channel| !channel.is_empty()) {
+        require_channel_name(channel, nickname, session)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod preferences_tests {
+    use model::consts::MAX_MSG_SIZE;
+    use std::{
+        io::Read,
+        net::{TcpListener, TcpStream},
+    };
+
+    use model::{
+        message::MessageType,
+        persistence::PersistenceType,
+        responses::{errors::ErrorResponse, replies::CommandResponse, response::Response},
+        session::Session,
+    };
+
+    use crate::{
+        commands::{
+            command_utils::{
+                create_client_for_test, create_message_for_test, create_session_for_test,
+            },
+            preferences::{
+                handle_prefs_command, handle_setautojoin_command, handle_setpref_command,
+            },
+        },
+        database::{handle_database, DatabasePaths},
+        locks::read_lock_clients,
+        server_errors::ServerError,
+    };
+
+    fn read_response_for_test(reader: &mut TcpStream) -> Response {
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        Response::serialize(String::from_utf8(msg).unwrap()).unwrap()
+    }
+
+    fn create_session_with_client_for_test() -> (Session, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr, "nickname".to_string());
+        let (reader, _) = listener.accept().unwrap();
+        (session, reader)
+    }
+
+    fn send_setpref_for_test(
+        session: &Session,
+        parameters: Vec<String>,
+        trailing: Option<&str>,
+    ) -> Result<(), ServerError> {
+        let mut message = create_message_for_test(MessageType::SetPref, parameters);
+        message.trailing = trailing.map(|t| t.to_string());
+        handle_setpref_command(message, "nickname".to_string(), session)
+    }
+
+    #[test]
+    fn test_setpref_saves_the_preference_and_prefs_lists_it() {
+        let (session, mut reader) = create_session_with_client_for_test();
+
+        let result =
+            send_setpref_for_test(&session, vec!["partmsg".to_string()], Some("see you later"));
+        assert!(result.is_ok());
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::CommandResponse {
+                response: CommandResponse::Preference { name, value }
+            } if name == "PARTMSG" && value == "see you later"
+        ));
+        let clients = read_lock_clients(&session).unwrap();
+        let preferences = &clients["nickname"].preferences;
+        assert_eq!(preferences.part_message, Some("see you later".to_string()));
+        drop(clients);
+
+        assert!(handle_prefs_command("nickname".to_string(), &session).is_ok());
+        let mut listed = vec![];
+        for _ in 0..4 {
+            match read_response_for_test(&mut reader) {
+                Response::CommandResponse {
+                    response: CommandResponse::Preference { name, value },
+                } => listed.push((name, value)),
+                _ => panic!("expected a preference"),
+            }
+        }
+        assert_eq!(
+            listed[0],
+            ("PARTMSG".to_string(), "see you later".to_string())
+        );
+        assert_eq!(listed[2], ("AUTOJOIN".to_string(), "".to_string()));
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::CommandResponse {
+                response: CommandResponse::EndOfPreferences
+            }
+        ));
+    }
+
+    #[test]
+    fn test_setpref_rejects_unknown_preferences_and_invalid_channels() {
+        let (session, mut reader) = create_session_with_client_for_test();
+
+        let result = send_setpref_for_test(&session, vec!["unknown".to_string()], Some("value"));
+        assert_eq!(result, Err(ServerError::InvalidParameters));
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::ErrorResponse {
+                response: ErrorResponse::NeedMoreParams { command }
+            } if command == "SETPREF"
+        ));
+
+        let result = send_setpref_for_test(
+            &session,
+            vec!["autojoin".to_string(), "#channel,channel".to_string()],
+            None,
+        );
+        assert_eq!(
+            result,
+            Err(ServerError::ChannelMustStartWithHashOrAmpersand)
+        );
+        let clients = read_lock_clients(&session).unwrap();
+        assert!(clients["nickname"].preferences.autojoin.is_empty());
+    }
+
+    #[test]
+    fn test_setautojoin_adds_and_removes_channels() {
+        let (session, mut reader) = create_session_with_client_for_test();
+
+        let message =
+            create_message_for_test(MessageType::SetAutoJoin, vec!["+#a,#b,#a".to_string()]);
+        assert!(handle_setautojoin_command(message, "nickname".to_string(), &session).is_ok());
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::CommandResponse {
+                response: CommandResponse::Preference { name, value }
+            } if name == "AUTOJOIN" && value == "#a,#b"
+        ));
+
+        let message = create_message_for_test(MessageType::SetAutoJoin, vec!["-#a".to_string()]);
+        assert!(handle_setautojoin_command(message, "nickname".to_string(), &session).is_ok());
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::CommandResponse {
+                response: CommandResponse::Preference { value, .. }
+            } if value == "#b"
+        ));
+        let clients = read_lock_clients(&session).unwrap();
+        assert_eq!(clients["nickname"].preferences.autojoin, vec!["#b"]);
+    }
+}
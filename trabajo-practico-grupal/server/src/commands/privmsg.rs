@@ -1,17 +1,30 @@
-use super::{
-    command_utils::{lock_sockets, read_lock_channels, read_lock_clients},
-    server_commands_handler::handle_server_privmsg_command,
+use super::command_utils::{
+    exceeds_max_targets, get_current_timestamp, require_max_targets, require_params,
+    require_registered, TargetResults,
+};
+use super::nickserv::{handle_nickserv_message, is_nickserv};
+use super::server_commands_handler::handle_server_privmsg_command;
+use crate::locks::{
+    read_lock_channels, read_lock_clients, read_lock_network_clients, read_lock_server,
+    read_lock_servers,
 };
 use crate::{
     server_errors::ServerError,
-    socket::{inform_client, inform_network, write_socket},
+    socket::{inform_client, inform_clients_shared, inform_network},
 };
 use model::{
     channelflag::ChannelFlag,
-    message::Message,
+    client::Client,
+    consts::{operators_channel_name, LOCAL_CHANNEL_PREFIX, NETWORK_CHANNEL_PREFIX, OPERATOR_FLAG},
+    events::ServerEvent,
+    message::{Message, MessageType},
     network::Network,
-    responses::{errors::ErrorResponse, message::MessageResponse, replies::CommandResponse},
+    responses::{
+        errors::ErrorResponse, message::MessageResponse, replies::CommandResponse,
+        writer::SharedMessage,
+    },
     session::Session,
+    userflag::UserFlag,
 };
 
 /// Function to handle the PRIVMSG command from a client/server
@@ -21,63 +34,147 @@ use model::{
 /// * `session` - The session of the current server
 /// * `network` - The struct that contains the network information
 /// * `server_name` - The name of the server
+///
+/// The message is sent to every receiver even if sending it to another one fails,
+/// and the result of each receiver is returned.
+/// A message with more receivers than the server allows is not sent to any of them, and
+/// ServerError::TooManyTargets is returned.
 pub fn handle_privmsg_command(
     message: Message,
     nickname: &String,
     session: &Session,
     network: &Network,
     server_name: &String,
+) -> Result<TargetResults, ServerError> {
+    require_registered(nickname, session, network)?;
+    let count = if message.trailing.is_some() { 1 } else { 2 };
+    require_params(&message, count, "PRIVMSG", nickname, session)?;
+    require_max_targets(&message.parameters[0], nickname, session)?;
+    Ok(send_to_receivers(
+        &message,
+        nickname,
+        session,
+        network,
+        server_name,
+    ))
+}
+
+/// Function to handle the NOTICE command from a client/server
+/// The notice is sent like a PRIVMSG, but no reply is ever sent back automatically:
+/// the sender is not informed of errors, away messages or delivery receipts, and a notice
+/// without receivers or text, or with more receivers than the server allows, is dropped.
+/// The text, like a CTCP reply, is relayed untouched.
+/// # Arguments
+/// * `message` - The message received from the client/server
+/// * `nickname` - The nickname of the client
+/// * `session` - The session of the current server
+/// * `network` - The struct that contains the network information
+/// * `server_name` - The name of the server
+pub fn handle_notice_command(
+    message: Message,
+    nickname: &String,
+    session: &Session,
+    network: &Network,
+    server_name: &String,
 ) -> Result<(), ServerError> {
-    if message.parameters.len() < 2 && message.trailing.is_none()
-        || message.parameters.is_empty() && message.trailing.is_some()
+    require_registered(nickname, session, network)?;
+    let count = if message.trailing.is_some() { 1 } else { 2 };
+    if message.parameters.len() < count
+        || exceeds_max_targets(&message.parameters[0], nickname, session)?
     {
-        let response = ErrorResponse::NeedMoreParams {
-            command: "PRIVMSG".to_string(),
-        }
-        .to_string();
-        inform_client(session, nickname, &response)?;
-        return Err(ServerError::InvalidParameters);
+        return Ok(());
     }
+    send_to_receivers(&message, nickname, session, network, server_name);
+    Ok(())
+}
 
+/// Sends a PRIVMSG or NOTICE to each of its receivers, returning the result of each one.
+/// A NOTICE is never handled by NickServ.
+/// The messages sent are published as events, except the ones to NickServ, since they have
+/// the passwords of the clients.
+fn send_to_receivers(
+    message: &Message,
+    nickname: &String,
+    session: &Session,
+    network: &Network,
+    server_name: &String,
+) -> TargetResults {
+    let notice = message.command == MessageType::Notice;
     let receivers = message.parameters[0]
         .split(',')
         .map(|a| a.trim())
         .collect::<Vec<_>>();
+    let text = match (&message.trailing, message.parameters.get(1)) {
+        (Some(text), _) | (None, Some(text)) => text.to_owned(),
+        (None, None) => "".to_string(),
+    };
+    let mut results = TargetResults::new();
     for receiver in receivers {
         if receiver == nickname {
             continue;
         }
 
-        if receiver.starts_with('&') {
-            msg_to_local_channel(receiver, nickname, session, &message)?;
-        } else if receiver.starts_with('#') {
-            msg_to_distributed_channel(
+        if is_nickserv(receiver) {
+            if notice {
+                continue;
+            }
+            results.push(
                 receiver,
+                handle_nickserv_message(message, nickname, session),
+            );
+            continue;
+        }
+        let (channel_receiver, only_operators) = match operators_channel_name(receiver) {
+            Some(channel) => (channel, true),
+            None => (receiver, false),
+        };
+        let result = if channel_receiver.starts_with(LOCAL_CHANNEL_PREFIX) {
+            msg_to_local_channel(channel_receiver, nickname, session, message, only_operators)
+        } else if channel_receiver.starts_with(NETWORK_CHANNEL_PREFIX) {
+            msg_to_distributed_channel(
+                channel_receiver,
                 nickname,
                 session,
                 network,
-                &message,
+                message,
                 server_name,
-            )?;
+                only_operators,
+            )
+        } else if is_client_address(receiver, session, network) {
+            msg_to_client_address(receiver, nickname, session, network, message, server_name)
         } else {
-            msg_to_client(receiver, nickname, session, network, &message, server_name)?;
+            msg_to_client(receiver, nickname, session, network, message, server_name)
+        };
+        if result.is_ok() {
+            session.publish(ServerEvent::MessageSent {
+                sender: nickname.to_owned(),
+                target: receiver.to_string(),
+                message: text.to_owned(),
+            });
         }
+        results.push(receiver, result);
     }
-    Ok(())
+    results
 }
 
 /// Function that sends a PRIVMSG to a local channel
+/// For a NOTICE the sender is not informed when it can't send to the channel.
+/// A message sent to `@<channel>` is only delivered to the operators of the channel, with that
+/// target, and the sender needs the same permissions as to send it to the whole channel.
 /// # Arguments
 /// * `chan_receiver` - The receiver channel that receives the message
 /// * `nickname` - The nickname of the client
 /// * `session` - The session of the current server
 /// * `message` - The message received from the client
+/// * `only_operators` - If the message is only for the operators of the channel
 fn msg_to_local_channel(
     chan_receiver: &str,
     nickname: &String,
     session: &Session,
     message: &Message,
+    only_operators: bool,
 ) -> Result<(), ServerError> {
+    let notice = message.command == MessageType::Notice;
     if let Some(channel) = read_lock_channels(session)?.get(chan_receiver) {
         if !channel.users.contains(nickname)
             && channel.modes.contains(&ChannelFlag::NoMessageFromOutside)
@@ -86,15 +183,19 @@ fn msg_to_local_channel(
                 channel: chan_receiver.to_string(),
             })
             .to_string();
-            inform_client(session, nickname, &response)?;
+            if !notice {
+                inform_client(session, nickname, &response)?;
+            }
             return Err(ServerError::UserNotInChannel);
         }
-        if channel.banned_users.contains(nickname) {
+        if channel.is_banned(nickname, get_current_timestamp()) {
             let response = (ErrorResponse::CannotSendToChannel {
                 channel: chan_receiver.to_string(),
             })
             .to_string();
-            inform_client(session, nickname, &response)?;
+            if !notice {
+                inform_client(session, nickname, &response)?;
+            }
             return Err(ServerError::UserNotInChannel);
         }
         if !channel.moderators.contains(nickname)
@@ -104,24 +205,27 @@ fn msg_to_local_channel(
                 channel: chan_receiver.to_string(),
             })
             .to_string();
-            inform_client(session, nickname, &response)?;
-            return Err(ServerError::ChannelIsModerated);
-        }
-        for user in channel.users.iter() {
-            if let Some(c) = read_lock_clients(session)?.get(user) {
-                if c.connected && c.nickname != *nickname {
-                    if let Some(socket) = lock_sockets(session)?.get(&c.nickname) {
-                        let msg = match message.prefix.to_owned() {
-                            Some(prefix) => {
-                                prepare_chan_msg(message, &prefix, &chan_receiver.to_string())
-                            }
-                            None => prepare_chan_msg(message, nickname, &chan_receiver.to_string()),
-                        };
-                        write_socket(socket.clone(), msg.as_str())?;
-                    }
-                }
+            if !notice {
+                inform_client(session, nickname, &response)?;
             }
+            return Err(ServerError::ChannelIsModerated);
         }
+        let target = if only_operators {
+            format!("{}{}", OPERATOR_FLAG, chan_receiver)
+        } else {
+            chan_receiver.to_string()
+        };
+        let sender = message.prefix_name().unwrap_or_else(|| nickname.to_owned());
+        let msg = SharedMessage::new(prepare_chan_msg(message, &sender, &target).into());
+        let clients = read_lock_clients(session)?;
+        let receivers = channel
+            .users
+            .iter()
+            .filter(|user| !only_operators || channel.operators.contains(user))
+            .filter_map(|user| clients.get(user))
+            .filter(|c| c.connected && c.nickname != *nickname && !c.is_silencing(nickname))
+            .map(|c| c.nickname.as_str());
+        inform_clients_shared(session, receivers, &msg)?;
     }
     Ok(())
 }
@@ -134,6 +238,8 @@ fn msg_to_local_channel(
 /// * `network` - The struct that contains the network information
 /// * `message` - The message received from the client/server
 /// * `server_name` - The name of the server
+/// * `only_operators` - If the message is only for the operators of the channel, which is relayed
+///   to the other servers with the `@<channel>` target too
 fn msg_to_distributed_channel(
     chan_receiver: &str,
     nickname: &String,
@@ -141,11 +247,16 @@ fn msg_to_distributed_channel(
     network: &Network,
     message: &Message,
     server_name: &String,
+    only_operators: bool,
 ) -> Result<(), ServerError> {
-    msg_to_local_channel(chan_receiver, nickname, session, message)?;
+    msg_to_local_channel(chan_receiver, nickname, session, message, only_operators)?;
     let mut msg = message.clone();
-    msg.prefix = Some(nickname.to_string());
-    msg.parameters[0] = chan_receiver.to_string();
+    msg.set_nick_prefix(nickname);
+    msg.parameters[0] = if only_operators {
+        format!("{}{}", OPERATOR_FLAG, chan_receiver)
+    } else {
+        chan_receiver.to_string()
+    };
     let msg = Message::deserialize(msg)?;
     inform_network(network, server_name, &msg)?;
 
@@ -153,6 +264,13 @@ fn msg_to_distributed_channel(
 }
 
 /// Function that sends a PRIVMSG to a client
+/// If the receiver is away, the sender is also informed of its away message.
+/// If the receiver silenced the sender, the message is dropped.
+/// If the sender has the delivery receipts mode set and the receiver is a client of this server,
+/// the sender is informed whether the message was delivered. A silenced sender gets the same
+/// receipt, so it doesn't know it was silenced.
+/// A NOTICE is delivered without the away message or the delivery receipt, and it is dropped
+/// if the receiver doesn't exist in the network.
 /// # Arguments
 /// * `receiver` - The receiver client that receives the message
 /// * `nickname` - The nickname of the client that sended the message
@@ -168,63 +286,205 @@ fn msg_to_client(
     message: &Message,
     server_name: &String,
 ) -> Result<(), ServerError> {
+    let notice = message.command == MessageType::Notice;
     let local_clients = read_lock_clients(session)?;
+    let wants_receipt = match local_clients.get(nickname) {
+        Some(sender) => !notice && sender.modes.contains(&UserFlag::DeliveryReceipts),
+        None => false,
+    };
     if let Some(c) = local_clients.get(receiver) {
-        if let Some(away_msg) = c.away_message.to_owned() {
-            if local_clients.get(nickname).is_some() {
-                let response = (CommandResponse::Away {
-                    nickname: receiver.to_string(),
-                    message: away_msg,
-                })
-                .to_string();
-                inform_client(session, nickname, &response)?;
-            } else {
-                let msg = format!(":{} AWAY {} :{}", nickname, receiver, away_msg);
-                let server_lock = network.server.as_ref().read()?;
-                let current_server_name = server_lock.name.clone();
-                drop(server_lock);
-                inform_network(network, &current_server_name, &msg)?;
-            }
-            return Ok(());
-        } else if c.connected {
-            if let Some(socket) = lock_sockets(session)?.get(receiver) {
-                let msg = match message.prefix.to_owned() {
+        if !c.is_silencing(nickname) {
+            if c.connected {
+                let msg = match message.prefix_name() {
                     Some(prefix) => prepare_msg(message, &prefix),
                     None => prepare_msg(message, nickname),
                 };
-                write_socket(socket.clone(), msg.as_str())?;
+                inform_client(session, &receiver.to_string(), msg.as_str())?;
+            }
+            if let Some(away_msg) = c.away_message.to_owned().filter(|_| !notice) {
+                if local_clients.get(nickname).is_some() {
+                    let response = (CommandResponse::Away {
+                        nickname: receiver.to_string(),
+                        message: away_msg,
+                    })
+                    .to_string();
+                    inform_client(session, nickname, &response)?;
+                } else {
+                    let msg = format!(":{} AWAY {} :{}", nickname, receiver, away_msg);
+                    let server_lock = read_lock_server(network)?;
+                    let current_server_name = server_lock.name.clone();
+                    drop(server_lock);
+                    inform_network(network, &current_server_name, &msg)?;
+                }
             }
         }
+        if wants_receipt {
+            let response = (CommandResponse::DeliveryReceipt {
+                nickname: receiver.to_string(),
+                status: get_delivery_status(c),
+            })
+            .to_string();
+            inform_client(session, nickname, &response)?;
+        }
     } else {
         let mut msg = message.clone();
-        msg.prefix = Some(nickname.to_string());
+        msg.set_nick_prefix(nickname);
         msg.parameters[0] = receiver.to_string();
         handle_server_privmsg_command(msg, nickname, server_name, session, network)?;
     }
     Ok(())
 }
 
+/// Returns true if the receiver addresses a client by its server or by its user and host, like
+/// `nick@server`, `user%host` or `user%host@server`, instead of by its nickname. A nickname
+/// known in the network is never an address, even if it has `@` or `%`.
+fn is_client_address(receiver: &str, session: &Session, network: &Network) -> bool {
+    if !receiver.contains(['@', '%']) {
+        return false;
+    }
+    let local = match read_lock_clients(session) {
+        Ok(clients) => clients.contains_key(receiver),
+        Err(_) => false,
+    };
+    let remote = match read_lock_network_clients(network) {
+        Ok(clients) => clients.contains_key(receiver),
+        Err(_) => false,
+    };
+    !local && !remote
+}
+
+/// Function that sends a PRIVMSG to a client addressed by its server or by its user and host.
+/// A client addressed by `user%host` must be a client of the server that handles the address,
+/// and it must be the only client with that username and hostname, ignoring the case.
+/// If the address has another server of the network, the message is relayed through the network
+/// until it reaches that server, which delivers it to its own client.
+/// A NOTICE is dropped without informing the sender if the address doesn't match any client.
+/// # Arguments
+/// * `receiver` - The address of the client, like `nick@server` or `user%host`
+/// * `nickname` - The nickname of the client that sent the message
+/// * `session` - The session of the current server
+/// * `network` - The struct that contains the network information
+/// * `message` - The message received from the client/server
+/// * `server_name` - The name of the server the message was received from
+fn msg_to_client_address(
+    receiver: &str,
+    nickname: &String,
+    session: &Session,
+    network: &Network,
+    message: &Message,
+    server_name: &String,
+) -> Result<(), ServerError> {
+    let notice = message.command == MessageType::Notice;
+    let (local_part, server) = match receiver.rsplit_once('@') {
+        Some((local_part, server)) => (local_part, Some(server)),
+        None => (receiver, None),
+    };
+    let current_server_name = read_lock_server(network)?.name.to_owned();
+    if let Some(server) = server.filter(|s| !s.eq_ignore_ascii_case(&current_server_name)) {
+        let known = read_lock_servers(network)?
+            .keys()
+            .any(|known| known.eq_ignore_ascii_case(server));
+        if !known {
+            if !notice {
+                let response = ErrorResponse::NoSuchServer {
+                    servername: server.to_string(),
+                }
+                .to_string();
+                inform_client(session, nickname, &response)?;
+            }
+            return Err(ServerError::ServerNotFound);
+        }
+        let mut msg = message.clone();
+        msg.set_nick_prefix(nickname);
+        msg.parameters[0] = receiver.to_string();
+        let msg = Message::deserialize(msg)?;
+        return inform_network(network, server_name, &msg);
+    }
+
+    match find_addressed_client(local_part, session)? {
+        Some(client) => msg_to_client(&client, nickname, session, network, message, server_name),
+        None => {
+            if !notice {
+                let response = ErrorResponse::NoSuchNick {
+                    nickname: receiver.to_string(),
+                }
+                .to_string();
+                inform_client(session, nickname, &response)?;
+            }
+            Err(ServerError::ClientNotFound)
+        }
+    }
+}
+
+/// Returns the nickname of the client of this server addressed by its nickname or by its
+/// `user%host`, if there is exactly one.
+fn find_addressed_client(
+    local_part: &str,
+    session: &Session,
+) -> Result<Option<String>, ServerError> {
+    let clients = read_lock_clients(session)?;
+    let (username, hostname) = match local_part.split_once('%') {
+        Some(user_and_host) => user_and_host,
+        None => {
+            return Ok(clients
+                .get(local_part)
+                .map(|client| client.nickname.to_owned()))
+        }
+    };
+    let mut matching = clients.values().filter(|client| {
+        client.username.eq_ignore_ascii_case(username)
+            && client.hostname.eq_ignore_ascii_case(hostname)
+    });
+    match (matching.next(), matching.next()) {
+        (Some(client), None) => Ok(Some(client.nickname.to_owned())),
+        _ => Ok(None),
+    }
+}
+
+/// Returns the status of the delivery receipt of a private message sent to the client:
+/// `offline` if it is not connected, `away` if it is away and `delivered` otherwise.
+fn get_delivery_status(client: &Client) -> String {
+    if !client.connected {
+        "offline".to_string()
+    } else if client.away_message.is_some() {
+        "away".to_string()
+    } else {
+        "delivered".to_string()
+    }
+}
+
+/// Returns the text of the message, the parameters after the receivers followed by the trailing.
+/// # Arguments
+/// * `message` - The message received from the client
+fn message_text(message: &Message) -> String {
+    let mut words = message
+        .parameters
+        .get(1..)
+        .unwrap_or_default()
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    if let Some(trailing) = &message.trailing {
+        words.push(trailing);
+    }
+    words.join(" ")
+}
+
 /// Function that parses the message from client to client
+/// A NOTICE is sent as a UserNotice instead of a UserPrivMsg.
 /// # Arguments
 /// * `message` - The message received from the client
 /// * `nickname` - The nickname of the client that sent the message
 fn prepare_msg(message: &Message, nick: &String) -> String {
-    let msg;
-    if message.parameters.len() > 1 {
-        if let Some(trailing) = message.trailing.to_owned() {
-            let mut params = message.parameters[1..].to_owned();
-            params.push(trailing);
-            msg = params.join(" ");
-        } else {
-            let params = message.parameters[1..].to_owned();
-            msg = params.join(" ");
+    let msg = message_text(message);
+
+    if message.command == MessageType::Notice {
+        return MessageResponse::UserNotice {
+            sender: nick.to_owned(),
+            message: msg,
         }
-    } else if let Some(traling) = message.trailing.to_owned() {
-        msg = traling;
-    } else {
-        msg = "".to_owned();
+        .to_string();
     }
-
     MessageResponse::UserPrivMsg {
         sender: nick.to_owned(),
         message: msg,
@@ -233,27 +493,22 @@ fn prepare_msg(message: &Message, nick: &String) -> String {
 }
 
 /// Function that parses the message from client to channel
+/// A NOTICE is sent as a ChannelNotice instead of a ChannelPrivMsg.
 /// # Arguments
 /// * `message` - The message received from the client
 /// * `nick` - The nickname of the client that sent the message
 /// * `chan` - The channel that receives the message
 fn prepare_chan_msg(message: &Message, nick: &String, chan: &String) -> String {
-    let msg;
-    if message.parameters.len() > 1 {
-        if let Some(trailing) = message.trailing.to_owned() {
-            let mut params = message.parameters[1..].to_owned();
-            params.push(trailing);
-            msg = params.join(" ");
-        } else {
-            let params = message.parameters[1..].to_owned();
-            msg = params.join(" ");
+    let msg = message_text(message);
+
+    if message.command == MessageType::Notice {
+        return MessageResponse::ChannelNotice {
+            channel: chan.to_owned(),
+            sender: nick.to_owned(),
+            message: msg,
         }
-    } else if let Some(trailing) = message.trailing.to_owned() {
-        msg = trailing;
-    } else {
-        msg = "".to_owned();
+        .to_string();
     }
-
     MessageResponse::ChannelPrivMsg {
         channel: chan.to_owned(),
         sender: nick.to_owned(),
@@ -267,30 +522,37 @@ fn prepare_chan_msg(message: &Message, nick: &String, chan: &String) -> String {
 mod privmsg_tests {
     use model::channel::Channel;
     use model::channelflag::ChannelFlag;
+    use model::consts::MAX_MSG_SIZE;
+    use model::link_queues::LinkQueues;
     use model::message::MessageType;
-    use model::network::Network;
+    use model::network::{LinkedServer, Network};
     use model::persistence::PersistenceType;
     use model::responses::errors::ErrorResponse;
+    use model::responses::message::MessageResponse;
+    use model::responses::numeric::WireFormat;
+    use model::responses::replies::CommandResponse;
     use model::responses::response::Response;
     use model::server::Server;
+    use model::session::Session;
+    use model::userflag::UserFlag;
     use std::collections::HashMap;
     use std::io::Read;
-    use std::net::TcpListener;
+    use std::net::{TcpListener, TcpStream};
     use std::sync::{Arc, RwLock};
 
     use crate::commands::command_utils::{
-        create_client_for_test, create_message_for_test, create_session_for_test,
-        write_lock_channels,
+        create_client_for_test, create_message_for_test, create_session_for_test, TargetResults,
     };
-    use crate::commands::privmsg::handle_privmsg_command;
-    use crate::database::handle_database;
+    use crate::commands::privmsg::{handle_notice_command, handle_privmsg_command};
+    use crate::database::{handle_database, DatabasePaths};
+    use crate::locks::write_lock_channels;
     use crate::server_errors::ServerError;
 
     #[test]
     fn test_privmsg_to_user() {
         let listener = TcpListener::bind("127.0.0.1:8124".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8124".to_string(),
@@ -304,10 +566,11 @@ mod privmsg_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, "127.0.0.1:8124".to_string(), "sender".to_string());
@@ -335,7 +598,8 @@ mod privmsg_tests {
             &session,
             &network,
             &"test".to_string(),
-        );
+        )
+        .and_then(TargetResults::into_result);
 
         assert!(result.is_ok());
 
@@ -346,7 +610,7 @@ mod privmsg_tests {
     fn test_privmsg_to_channel() {
         let listener = TcpListener::bind("127.0.0.1:8125".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8125".to_string(),
@@ -360,10 +624,11 @@ mod privmsg_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, "127.0.0.1:8125".to_string(), "sender".to_string());
@@ -393,7 +658,8 @@ mod privmsg_tests {
             &session,
             &network,
             &"test".to_string(),
-        );
+        )
+        .and_then(TargetResults::into_result);
         assert!(result.is_ok());
 
         drop(listener);
@@ -403,7 +669,7 @@ mod privmsg_tests {
     fn test_privmsg_invlaid_parameters() {
         let listener = TcpListener::bind("127.0.0.1:8126".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8126".to_string(),
@@ -417,10 +683,11 @@ mod privmsg_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, "127.0.0.1:8126".to_string(), "sender".to_string());
@@ -439,10 +706,11 @@ mod privmsg_tests {
             &session,
             &network,
             &"test".to_string(),
-        );
+        )
+        .and_then(TargetResults::into_result);
 
         let (mut reader, _addr) = listener.accept().unwrap();
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -472,7 +740,7 @@ mod privmsg_tests {
     fn test_privmsg_invlaid_parameters_no_trailing() {
         let listener = TcpListener::bind("127.0.0.1:8127".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8127".to_string(),
@@ -486,10 +754,11 @@ mod privmsg_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, "127.0.0.1:8127".to_string(), "sender".to_string());
@@ -509,10 +778,11 @@ mod privmsg_tests {
             &session,
             &network,
             &"test".to_string(),
-        );
+        )
+        .and_then(TargetResults::into_result);
 
         let (mut reader, _addr) = listener.accept().unwrap();
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -542,7 +812,7 @@ mod privmsg_tests {
     fn test_privmsg_send_message_to_client_and_channel() {
         let listener = TcpListener::bind("127.0.0.1:8128".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8128".to_string(),
@@ -556,10 +826,11 @@ mod privmsg_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, "127.0.0.1:8128".to_string(), "sender".to_string());
@@ -598,7 +869,8 @@ mod privmsg_tests {
             &session,
             &network,
             &"test".to_string(),
-        );
+        )
+        .and_then(TargetResults::into_result);
         assert!(result.is_ok());
 
         drop(listener);
@@ -608,7 +880,7 @@ mod privmsg_tests {
     fn test_privmsg_cant_send_message_to_moderated_channel() {
         let listener = TcpListener::bind("127.0.0.1:8129".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8129".to_string(),
@@ -622,10 +894,11 @@ mod privmsg_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, "127.0.0.1:8129".to_string(), "sender".to_string());
@@ -658,10 +931,11 @@ mod privmsg_tests {
             &session,
             &network,
             &"test".to_string(),
-        );
+        )
+        .and_then(TargetResults::into_result);
 
         let (mut reader, _addr) = listener.accept().unwrap();
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -691,7 +965,7 @@ mod privmsg_tests {
     fn test_privmsg_cant_send_message_to_no_message_from_outside_channel() {
         let listener = TcpListener::bind("127.0.0.1:8130".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8130".to_string(),
@@ -705,10 +979,11 @@ mod privmsg_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, "127.0.0.1:8130".to_string(), "sender".to_string());
@@ -739,10 +1014,11 @@ mod privmsg_tests {
             &session,
             &network,
             &"test".to_string(),
-        );
+        )
+        .and_then(TargetResults::into_result);
 
         let (mut reader, _addr) = listener.accept().unwrap();
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -767,4 +1043,536 @@ mod privmsg_tests {
         drop(listener);
         assert_eq!(Err(ServerError::UserNotInChannel), result);
     }
+
+    #[test]
+    fn test_privmsg_to_user_with_numeric_format() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let network = Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        };
+
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        let client = create_client_for_test(&session, addr.clone(), "sender".to_string());
+        let (mut sender_reader, _) = listener.accept().unwrap();
+        let client2 = create_client_for_test(&session, addr, "receiver".to_string());
+        let (mut receiver_reader, _) = listener.accept().unwrap();
+        session
+            .sockets
+            .set_wire_format(&client.nickname, WireFormat::Numeric);
+        session
+            .sockets
+            .set_wire_format(&client2.nickname, WireFormat::Numeric);
+
+        let mut message =
+            create_message_for_test(MessageType::Privmsg, vec!["receiver".to_string()]);
+        message.prefix = None;
+        message.trailing = Some("hello".to_string());
+        let result = handle_privmsg_command(
+            message,
+            &client.nickname,
+            &session,
+            &network,
+            &"test".to_string(),
+        )
+        .and_then(TargetResults::into_result);
+        assert!(result.is_ok());
+
+        let expected = ":sender PRIVMSG receiver :hello\r\n";
+        let mut buf = vec![0u8; expected.len()];
+        receiver_reader.read_exact(&mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), expected);
+
+        let mut message = create_message_for_test(MessageType::Privmsg, vec![]);
+        message.trailing = None;
+        let result = handle_privmsg_command(
+            message,
+            &client.nickname,
+            &session,
+            &network,
+            &"test".to_string(),
+        )
+        .and_then(TargetResults::into_result);
+        assert_eq!(Err(ServerError::InvalidParameters), result);
+
+        let expected = "461 sender PRIVMSG :Not enough parameters\r\n";
+        let mut buf = vec![0u8; expected.len()];
+        sender_reader.read_exact(&mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_privmsg_to_away_user_is_delivered_and_returns_the_away_message() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let network = Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        };
+
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        let client = create_client_for_test(&session, addr.clone(), "sender".to_string());
+        let (mut sender_reader, _) = listener.accept().unwrap();
+        let client2 = create_client_for_test(&session, addr, "receiver".to_string());
+        let (mut receiver_reader, _) = listener.accept().unwrap();
+        session
+            .sockets
+            .set_wire_format(&client.nickname, WireFormat::Numeric);
+        session
+            .sockets
+            .set_wire_format(&client2.nickname, WireFormat::Numeric);
+        session.clients.update("receiver", |client| {
+            client.away_message = Some("gone".to_string())
+        });
+
+        let mut message =
+            create_message_for_test(MessageType::Privmsg, vec!["receiver".to_string()]);
+        message.prefix = None;
+        message.trailing = Some("hello".to_string());
+        let result = handle_privmsg_command(
+            message,
+            &client.nickname,
+            &session,
+            &network,
+            &"test".to_string(),
+        )
+        .and_then(TargetResults::into_result);
+        assert!(result.is_ok());
+
+        let expected = ":sender PRIVMSG receiver :hello\r\n";
+        let mut buf = vec![0u8; expected.len()];
+        receiver_reader.read_exact(&mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), expected);
+
+        let expected = "301 sender receiver :gone\r\n";
+        let mut buf = vec![0u8; expected.len()];
+        sender_reader.read_exact(&mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_notice_relays_ctcp_payloads_untouched_without_replying_to_the_sender() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let network = Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        };
+
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        let client = create_client_for_test(&session, addr.clone(), "sender".to_string());
+        let (mut sender_reader, _) = listener.accept().unwrap();
+        let client2 = create_client_for_test(&session, addr, "receiver".to_string());
+        let (mut receiver_reader, _) = listener.accept().unwrap();
+        session
+            .sockets
+            .set_wire_format(&client.nickname, WireFormat::Numeric);
+        session
+            .sockets
+            .set_wire_format(&client2.nickname, WireFormat::Numeric);
+        session.clients.update("receiver", |client| {
+            client.away_message = Some("gone".to_string())
+        });
+
+        for (command, text) in [
+            (MessageType::Notice, "\x01VERSION taller-irc 0.1.0\x01"),
+            (MessageType::Notice, "missing"),
+            (MessageType::Privmsg, "\x01ACTION waves\x01"),
+        ] {
+            let receiver = if text == "missing" {
+                "nobody"
+            } else {
+                "receiver"
+            };
+            let mut message = create_message_for_test(command.clone(), vec![receiver.to_string()]);
+            message.prefix = None;
+            message.trailing = Some(text.to_string());
+            let result = match command {
+                MessageType::Notice => handle_notice_command(
+                    message,
+                    &client.nickname,
+                    &session,
+                    &network,
+                    &"test".to_string(),
+                ),
+                _ => handle_privmsg_command(
+                    message,
+                    &client.nickname,
+                    &session,
+                    &network,
+                    &"test".to_string(),
+                )
+                .and_then(TargetResults::into_result),
+            };
+            assert!(result.is_ok());
+        }
+
+        let expected = ":sender NOTICE receiver :\x01VERSION taller-irc 0.1.0\x01\r\n:sender PRIVMSG receiver :\x01ACTION waves\x01\r\n";
+        let mut buf = vec![0u8; expected.len()];
+        receiver_reader.read_exact(&mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), expected);
+
+        // the first reply the sender gets is the away message of the PRIVMSG
+        let expected = "301 sender receiver :gone\r\n";
+        let mut buf = vec![0u8; expected.len()];
+        sender_reader.read_exact(&mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_privmsg_from_a_silenced_sender_is_dropped() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let network = Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        };
+
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr.clone(), "sender".to_string());
+        let (_sender_reader, _) = listener.accept().unwrap();
+        create_client_for_test(&session, addr.clone(), "other".to_string());
+        let (_other_reader, _) = listener.accept().unwrap();
+        create_client_for_test(&session, addr, "receiver".to_string());
+        let (mut receiver_reader, _) = listener.accept().unwrap();
+        session
+            .sockets
+            .set_wire_format("receiver", WireFormat::Numeric);
+        session.clients.update("receiver", |client| {
+            client.silenced = vec!["send*".to_string()]
+        });
+
+        for sender in ["sender", "other"] {
+            let mut message =
+                create_message_for_test(MessageType::Privmsg, vec!["receiver".to_string()]);
+            message.prefix = None;
+            message.trailing = Some("hello".to_string());
+            let result = handle_privmsg_command(
+                message,
+                &sender.to_string(),
+                &session,
+                &network,
+                &"test".to_string(),
+            )
+            .and_then(TargetResults::into_result);
+            assert!(result.is_ok());
+        }
+
+        let expected = ":other PRIVMSG receiver :hello\r\n";
+        let mut buf = vec![0u8; expected.len()];
+        receiver_reader.read_exact(&mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_privmsg_sends_a_delivery_receipt_to_senders_with_the_mode_set() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let network = Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        };
+
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr.clone(), "sender".to_string());
+        let (mut sender_reader, _) = listener.accept().unwrap();
+        create_client_for_test(&session, addr.clone(), "receiver".to_string());
+        let (_receiver_reader, _) = listener.accept().unwrap();
+        create_client_for_test(&session, addr, "offline".to_string());
+        let (_offline_reader, _) = listener.accept().unwrap();
+        session.clients.update("sender", |sender| {
+            sender.modes.push(UserFlag::DeliveryReceipts)
+        });
+        session
+            .clients
+            .update("offline", |offline| offline.connected = false);
+
+        let mut message =
+            create_message_for_test(MessageType::Privmsg, vec!["receiver,offline".to_string()]);
+        message.prefix = None;
+        message.trailing = Some("hello".to_string());
+        let result = handle_privmsg_command(
+            message,
+            &"sender".to_string(),
+            &session,
+            &network,
+            &"test".to_string(),
+        )
+        .and_then(TargetResults::into_result);
+        assert!(result.is_ok());
+
+        for (receiver, expected) in [("receiver", "delivered"), ("offline", "offline")] {
+            let mut buf = vec![0u8; MAX_MSG_SIZE];
+            sender_reader.read_exact(&mut buf).unwrap();
+            let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+            match Response::serialize(String::from_utf8(msg).unwrap()).unwrap() {
+                Response::CommandResponse {
+                    response: CommandResponse::DeliveryReceipt { nickname, status },
+                } => {
+                    assert_eq!(nickname, receiver);
+                    assert_eq!(status, expected);
+                }
+                _ => panic!("expected a delivery receipt"),
+            }
+        }
+    }
+
+    fn create_network_for_test() -> Network {
+        Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        }
+    }
+
+    fn read_response_for_test(reader: &mut impl Read) -> Response {
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        Response::serialize(String::from_utf8(msg).unwrap()).unwrap()
+    }
+
+    fn send_privmsg_for_test(
+        session: &Session,
+        network: &Network,
+        receiver: &str,
+    ) -> Result<(), ServerError> {
+        let mut message = create_message_for_test(MessageType::Privmsg, vec![receiver.to_string()]);
+        message.prefix = None;
+        message.trailing = Some("hello".to_string());
+        handle_privmsg_command(
+            message,
+            &"sender".to_string(),
+            session,
+            network,
+            &"test".to_string(),
+        )
+        .and_then(TargetResults::into_result)
+    }
+
+    #[test]
+    fn test_privmsg_to_a_user_and_host_of_this_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let network = create_network_for_test();
+        let (db_tx, _db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr.clone(), "sender".to_string());
+        let (mut sender_reader, _) = listener.accept().unwrap();
+        create_client_for_test(&session, addr, "receiver".to_string());
+        let (mut receiver_reader, _) = listener.accept().unwrap();
+        session.clients.update("receiver", |receiver| {
+            receiver.username = "ferris".to_string();
+            receiver.hostname = "rust.org".to_string();
+        });
+
+        assert!(send_privmsg_for_test(&session, &network, "Ferris%RUST.org@test").is_ok());
+        assert!(send_privmsg_for_test(&session, &network, "receiver@TEST").is_ok());
+        for _ in 0..2 {
+            assert!(matches!(
+                read_response_for_test(&mut receiver_reader),
+                Response::MessageResponse {
+                    response: MessageResponse::UserPrivMsg { sender, message }
+                } if sender == "sender" && message == "hello"
+            ));
+        }
+
+        let result = send_privmsg_for_test(&session, &network, "ferris%elsewhere");
+        assert_eq!(result, Err(ServerError::ClientNotFound));
+        assert!(matches!(
+            read_response_for_test(&mut sender_reader),
+            Response::ErrorResponse {
+                response: ErrorResponse::NoSuchNick { nickname }
+            } if nickname == "ferris%elsewhere"
+        ));
+    }
+
+    #[test]
+    fn test_privmsg_with_more_receivers_than_allowed_is_not_sent_to_any() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let network = create_network_for_test();
+        let (db_tx, _db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        let session = create_session_for_test(db_tx);
+        session.config.write().unwrap().max_targets = 2;
+        create_client_for_test(&session, addr.clone(), "sender".to_string());
+        let (mut sender_reader, _) = listener.accept().unwrap();
+        create_client_for_test(&session, addr, "receiver".to_string());
+        let (mut receiver_reader, _) = listener.accept().unwrap();
+
+        let result = send_privmsg_for_test(&session, &network, "receiver,sender,receiver");
+        assert_eq!(result, Err(ServerError::TooManyTargets));
+        assert!(matches!(
+            read_response_for_test(&mut sender_reader),
+            Response::ErrorResponse {
+                response: ErrorResponse::TooManyTargets { target }
+            } if target == "receiver,sender,receiver"
+        ));
+
+        assert!(send_privmsg_for_test(&session, &network, "receiver,sender").is_ok());
+        assert!(matches!(
+            read_response_for_test(&mut receiver_reader),
+            Response::MessageResponse {
+                response: MessageResponse::UserPrivMsg { message, .. }
+            } if message == "hello"
+        ));
+    }
+
+    #[test]
+    fn test_privmsg_to_a_client_of_another_server_is_relayed() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let network = create_network_for_test();
+        let child = Arc::new(TcpStream::connect(&addr).unwrap());
+        let (mut child_reader, _) = listener.accept().unwrap();
+        network
+            .server
+            .write()
+            .unwrap()
+            .children
+            .insert("other".to_string(), child);
+        network
+            .servers
+            .write()
+            .unwrap()
+            .insert("other".to_string(), LinkedServer::new(1));
+        let (db_tx, _db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr, "sender".to_string());
+        let (mut sender_reader, _) = listener.accept().unwrap();
+
+        assert!(send_privmsg_for_test(&session, &network, "bob@other").is_ok());
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        child_reader.read_exact(&mut buf).unwrap();
+        let relayed = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        assert!(String::from_utf8(relayed)
+            .unwrap()
+            .starts_with(":sender PRIVMSG bob@other :hello"));
+
+        let result = send_privmsg_for_test(&session, &network, "bob@nowhere");
+        assert_eq!(result, Err(ServerError::ServerNotFound));
+        assert!(matches!(
+            read_response_for_test(&mut sender_reader),
+            Response::ErrorResponse {
+                response: ErrorResponse::NoSuchServer { servername }
+            } if servername == "nowhere"
+        ));
+    }
+
+    #[test]
+    fn test_privmsg_to_the_operators_of_a_channel() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let network = create_network_for_test();
+        let child = Arc::new(TcpStream::connect(&addr).unwrap());
+        let (mut child_reader, _) = listener.accept().unwrap();
+        network
+            .server
+            .write()
+            .unwrap()
+            .children
+            .insert("other".to_string(), child);
+        let (db_tx, _db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr.clone(), "sender".to_string());
+        let (_sender_reader, _) = listener.accept().unwrap();
+        create_client_for_test(&session, addr.clone(), "operator".to_string());
+        let (mut operator_reader, _) = listener.accept().unwrap();
+        create_client_for_test(&session, addr, "member".to_string());
+        let (mut member_reader, _) = listener.accept().unwrap();
+        let users = vec![
+            "sender".to_string(),
+            "operator".to_string(),
+            "member".to_string(),
+        ];
+        let mut channel = Channel::new("#rust".to_string(), "".to_string(), users);
+        channel.operators.push("operator".to_string());
+        write_lock_channels(&session)
+            .unwrap()
+            .insert(channel.name.to_owned(), channel);
+
+        assert!(send_privmsg_for_test(&session, &network, "@#rust").is_ok());
+
+        assert!(matches!(
+            read_response_for_test(&mut operator_reader),
+            Response::MessageResponse {
+                response: MessageResponse::ChannelPrivMsg { channel, sender, .. }
+            } if channel == "@#rust" && sender == "sender"
+        ));
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        child_reader.read_exact(&mut buf).unwrap();
+        let relayed = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        assert!(String::from_utf8(relayed)
+            .unwrap()
+            .starts_with(":sender PRIVMSG @#rust :hello"));
+        member_reader
+            .set_read_timeout(Some(std::time::Duration::from_millis(100)))
+            .unwrap();
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        assert!(member_reader.read_exact(&mut buf).is_err());
+    }
 }
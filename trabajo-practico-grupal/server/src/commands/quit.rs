@@ -1,14 +1,29 @@
-use crate::server_errors::ServerError;
-use model::{message::Message, session::Session};
-use std::net::Shutdown;
+use crate::{
+    server_errors::ServerError,
+    socket::{inform_client, inform_network},
+};
+use model::{
+    consts::NETWORK_CHANNEL_PREFIX, events::ServerEvent, message::Message, network::Network,
+    responses::message::MessageResponse, session::Session,
+};
+use std::{collections::HashSet, net::Shutdown};
 
-use super::command_utils::lock_sockets;
+use super::mode::transfer_channel_operator;
+use crate::locks::{
+    read_lock_channels, read_lock_clients, read_lock_network_clients, write_lock_channels,
+};
 
 /// Handles the quit command, closing the connection with the client.
+/// If the client was the last operator present in a channel, the operator privileges are
+/// given to the member that has been in the channel the longest.
+/// Without a quit message, the quit message of the preferences of the client is used.
+/// The members of the channels of the client connected to this server are informed that it quit.
 /// # Arguments
 /// * `message` - The message received from the client
 /// * `nickname` - The nickname of the client
 /// * `session` - The session of the server
+/// * `network` - The network the client is connected to
+/// * `server_name` - The name of the server
 ///
 /// Returns a Result with a ServerError if an error occurs.
 ///
@@ -16,15 +31,182 @@ pub fn handle_quit_command(
     message: Message,
     nickname: String,
     session: &Session,
+    network: &Network,
+    server_name: &String,
 ) -> Result<(), ServerError> {
-    if message.parameters.is_empty() {
-        println!("QUIT {}", nickname);
-    } else {
-        println!("QUIT {}", message.parameters[0]);
+    let reason = match (message.parameters.first(), message.trailing.as_ref()) {
+        (Some(reason), _) | (None, Some(reason)) => Some(reason.to_owned()),
+        (None, None) => match session.clients.get(&nickname) {
+            Some(client) => client.preferences.quit_message,
+            None => None,
+        },
+    };
+    match &reason {
+        Some(reason) => println!("QUIT {}: {}", nickname, reason),
+        None => println!("QUIT {}", nickname),
     }
 
-    if let Some(socket) = lock_sockets(session)?.get(&nickname) {
+    inform_channels_about_quit(&nickname, reason.to_owned().unwrap_or_default(), session)?;
+    transfer_operators_of_quitting_client(&nickname, session, network, server_name)?;
+
+    if let Some(socket) = session.sockets.socket(&nickname) {
         socket.shutdown(Shutdown::Both)?;
     }
+    session.publish(ServerEvent::ClientQuit { nickname, reason });
+    Ok(())
+}
+
+/// Informs the clients that share a channel with the quitting client that it quit, once each.
+fn inform_channels_about_quit(
+    nickname: &String,
+    message: String,
+    session: &Session,
+) -> Result<(), ServerError> {
+    let channels = read_lock_channels(session)?;
+    let members = channels
+        .values()
+        .filter(|channel| channel.users.contains(nickname))
+        .flat_map(|channel| channel.users.iter())
+        .filter(|user| *user != nickname)
+        .map(|user| user.to_owned())
+        .collect::<HashSet<_>>();
+    drop(channels);
+
+    let response = MessageResponse::QuitMsg {
+        nickname: nickname.to_owned(),
+        message,
+    }
+    .to_string();
+    for member in members.iter() {
+        inform_client(session, member, &response)?;
+    }
+    Ok(())
+}
+
+/// Gives the operator privileges of the channels the client was the last operator present of
+/// to other members, and informs the network about the new operators.
+fn transfer_operators_of_quitting_client(
+    nickname: &String,
+    session: &Session,
+    network: &Network,
+    server_name: &String,
+) -> Result<(), ServerError> {
+    let clients = read_lock_clients(session)?;
+    let mut present = clients
+        .values()
+        .filter(|c| c.connected && c.nickname != *nickname)
+        .map(|c| c.nickname.to_owned())
+        .collect::<HashSet<_>>();
+    let network_clients = read_lock_network_clients(network)?;
+    for nick in network_clients.keys() {
+        if !clients.contains_key(nick) {
+            present.insert(nick.to_owned());
+        }
+    }
+    drop(network_clients);
+    drop(clients);
+
+    let mut channels = write_lock_channels(session)?;
+    for channel in channels.values_mut() {
+        if !channel.users.contains(nickname) {
+            continue;
+        }
+        if let Some(successor) =
+            transfer_channel_operator(channel, nickname, session, |u| present.contains(u))?
+        {
+            if channel.name.starts_with(NETWORK_CHANNEL_PREFIX) {
+                let msg = format!(":{} MODE {} +o {}", nickname, channel.name, successor);
+                inform_network(network, server_name, &msg)?;
+            }
+        }
+    }
+    drop(channels);
     Ok(())
 }
+
+#[cfg(test)]
+mod quit_tests {
+    use std::{
+        collections::HashMap,
+        net::TcpListener,
+        sync::{Arc, RwLock},
+    };
+
+    use model::{
+        channel::Channel, link_queues::LinkQueues, message::MessageType, network::Network,
+        persistence::PersistenceType, server::Server,
+    };
+
+    use crate::locks::{read_lock_channels, write_lock_channels, write_lock_clients};
+    use crate::{
+        commands::{
+            command_utils::{
+                create_client_for_test, create_message_for_test, create_session_for_test,
+            },
+            quit::handle_quit_command,
+        },
+        database::{handle_database, DatabasePaths},
+    };
+
+    #[test]
+    fn test_quit_of_the_last_operator_transfers_the_privileges_to_a_connected_member() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let network = Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        };
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr.to_owned(), "operator".to_string());
+        create_client_for_test(&session, addr.to_owned(), "away".to_string());
+        create_client_for_test(&session, addr, "member".to_string());
+        let (_operator_reader, _) = listener.accept().unwrap();
+        let (_away_reader, _) = listener.accept().unwrap();
+        let (_member_reader, _) = listener.accept().unwrap();
+        write_lock_clients(&session)
+            .unwrap()
+            .get_mut("away")
+            .unwrap()
+            .connected = false;
+
+        let mut channel = Channel::new(
+            "#channel_test".to_string(),
+            "".to_string(),
+            vec![
+                "operator".to_string(),
+                "away".to_string(),
+                "member".to_string(),
+            ],
+        );
+        channel.operators.push("operator".to_string());
+        write_lock_channels(&session)
+            .unwrap()
+            .insert(channel.name.clone(), channel);
+
+        let message = create_message_for_test(MessageType::Quit, vec![]);
+        let result = handle_quit_command(
+            message,
+            "operator".to_string(),
+            &session,
+            &network,
+            &"test".to_string(),
+        );
+
+        assert!(result.is_ok());
+        let channels = read_lock_channels(&session).unwrap();
+        let operators = &channels["#channel_test"].operators;
+        assert!(operators.contains(&"member".to_string()));
+        assert!(!operators.contains(&"away".to_string()));
+    }
+}
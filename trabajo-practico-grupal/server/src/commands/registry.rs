@@ -0,0 +1,677 @@
+use std::sync::OnceLock;
+
+use super::{
+    audit::handle_audit_command,
+    away::handle_away_command,
+    chanstats::handle_chanstats_command,
+    command_utils::fetch_info,
+    dcc::handle_dcc_command,
+    filter::handle_filter_command,
+    ghost::handle_ghost_command,
+    help::handle_help_command,
+    invite::handle_invite_command,
+    join::handle_join_command,
+    kick::handle_kick_command,
+    list::handle_list_command,
+    mode::handle_mode_command,
+    names::handle_names_command,
+    oper::handle_oper_command,
+    part::handle_part_command,
+    ping::handle_ping_command,
+    preferences::{handle_prefs_command, handle_setautojoin_command, handle_setpref_command},
+    privmsg::{handle_notice_command, handle_privmsg_command},
+    quit::handle_quit_command,
+    rehash::handle_rehash_command,
+    silence::handle_silence_command,
+    stats::handle_stats_command,
+    topic::handle_topic_command,
+    who::handle_who_command,
+    whois::handle_whois_command,
+};
+use crate::server_errors::ServerError;
+use model::{
+    message::{Message, MessageType},
+    network::Network,
+    session::Session,
+};
+
+static REGISTRY: OnceLock<CommandRegistry> = OnceLock::new();
+
+/// Handles a command sent by a registered client.
+/// Every function with the signature of the handlers of the registry implements it.
+pub trait CommandHandler: Send + Sync {
+    /// Handles the message sent by the client with the nickname received.
+    // the handlers receive the name of the server as a &String, so the trait does too
+    #[allow(clippy::ptr_arg)]
+    fn handle(
+        &self,
+        message: Message,
+        nickname: String,
+        session: &Session,
+        network: &Network,
+        server_name: &String,
+    ) -> Result<(), ServerError>;
+}
+
+impl<F> CommandHandler for F
+where
+    F: Fn(Message, String, &Session, &Network, &String) -> Result<(), ServerError> + Send + Sync,
+{
+    fn handle(
+        &self,
+        message: Message,
+        nickname: String,
+        session: &Session,
+        network: &Network,
+        server_name: &String,
+    ) -> Result<(), ServerError> {
+        self(message, nickname, session, network, server_name)
+    }
+}
+
+/// The clients that can send a command.
+/// # Variants
+/// * `Anyone`: Every registered client.
+/// * `ServerOperator`: Only the operators of the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Privilege {
+    Anyone,
+    ServerOperator,
+}
+
+/// A command of the registry.
+/// # Fields
+/// * `command` - The type of the message of the command.
+/// * `syntax` - The parameters of the command, like `JOIN <channel>{,<channel>} [<key>{,<key>}]`.
+/// * `description` - What the command does, in a single line.
+/// * `parameters` - The minimum amount of parameters of the command, without the trailing.
+/// * `privilege` - The clients that can send the command.
+/// * `handler` - The handler of the command, or None if it can only be sent while registering.
+pub struct Command {
+    pub command: MessageType,
+    pub syntax: &'static str,
+    pub description: &'static str,
+    pub parameters: usize,
+    pub privilege: Privilege,
+    pub handler: Option<Box<dyn CommandHandler>>,
+}
+
+impl Command {
+    /// Returns the name of the command, like `PRIVMSG`.
+    pub fn name(&self) -> String {
+        MessageType::message_type_to_string(self.command.clone()).unwrap_or_default()
+    }
+
+    fn with_privilege(mut self, privilege: Privilege) -> Command {
+        self.privilege = privilege;
+        self
+    }
+}
+
+/// The commands the clients can send, in the order they are listed by HELP.
+pub struct CommandRegistry {
+    commands: Vec<Command>,
+}
+
+impl CommandRegistry {
+    /// Returns the commands of the registry.
+    pub fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    /// Returns the command of the type received, if it is in the registry.
+    pub fn get(&self, command: &MessageType) -> Option<&Command> {
+        self.commands.iter().find(|c| c.command == *command)
+    }
+
+    /// Returns the command with the name received, ignoring the case.
+    pub fn find(&self, name: &str) -> Option<&Command> {
+        self.commands
+            .iter()
+            .find(|c| c.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Returns the registry of the commands, that is created the first time it is used.
+pub fn registry() -> &'static CommandRegistry {
+    REGISTRY.get_or_init(create_registry)
+}
+
+fn command(
+    command: MessageType,
+    syntax: &'static str,
+    description: &'static str,
+    parameters: usize,
+    handler: Option<Box<dyn CommandHandler>>,
+) -> Command {
+    Command {
+        command,
+        syntax,
+        description,
+        parameters,
+        privilege: Privilege::Anyone,
+        handler,
+    }
+}
+
+fn create_registry() -> CommandRegistry {
+    let commands = vec![
+        command(
+            MessageType::Pass,
+            "PASS <password>",
+            "Sets the password of the connection, sent before NICK and USER.",
+            1,
+            None,
+        ),
+        command(
+            MessageType::Nick,
+            "NICK <nickname>",
+            "Sets the nickname of the connection while registering.",
+            1,
+            None,
+        ),
+        command(
+            MessageType::User,
+            "USER <username> <hostname> <servername> :<realname>",
+            "Sets the username and the real name of the connection while registering.",
+            3,
+            None,
+        ),
+        command(
+            MessageType::Ghost,
+            "GHOST <nickname> <password>",
+            "Disconnects a stale session that holds your nickname.",
+            2,
+            Some(Box::new(ghost)),
+        ),
+        command(
+            MessageType::Reclaim,
+            "RECLAIM <nickname> <password>",
+            "Disconnects a stale session that holds your nickname and takes it back.",
+            2,
+            None,
+        ),
+        command(
+            MessageType::Privmsg,
+            "PRIVMSG <receiver>{,<receiver>} :<text>",
+            "Sends a message to clients or channels, or a command to NickServ.",
+            1,
+            Some(Box::new(privmsg)),
+        ),
+        command(
+            MessageType::Notice,
+            "NOTICE <receiver>{,<receiver>} :<text>",
+            "Sends a message to clients or channels that is never answered automatically.",
+            0,
+            Some(Box::new(notice)),
+        ),
+        command(
+            MessageType::Join,
+            "JOIN <channel>{,<channel>} [<key>{,<key>}]",
+            "Joins the channels, creating the ones that don't exist.",
+            1,
+            Some(Box::new(join)),
+        ),
+        command(
+            MessageType::Part,
+            "PART <channel>{,<channel>}",
+            "Leaves the channels.",
+            1,
+            Some(Box::new(part)),
+        ),
+        command(
+            MessageType::Quit,
+            "QUIT [:<message>]",
+            "Disconnects from the server.",
+            0,
+            Some(Box::new(quit)),
+        ),
+        command(
+            MessageType::Kick,
+            "KICK <channel> <nickname> [:<comment>]",
+            "Removes a client from a channel, if you are an operator of the channel.",
+            2,
+            Some(Box::new(kick)),
+        ),
+        command(
+            MessageType::Names,
+            "NAMES [<channel>{,<channel>}]",
+            "Lists the clients of the channels, or of every visible channel.",
+            0,
+            Some(Box::new(names)),
+        ),
+        command(
+            MessageType::Topic,
+            "TOPIC <channel> [:<topic>]",
+            "Shows or changes the topic of a channel.",
+            1,
+            Some(Box::new(topic)),
+        ),
+        command(
+            MessageType::List,
+            "LIST [<channel>{,<channel>}]",
+            "Lists the channels and their topics.",
+            0,
+            Some(Box::new(list)),
+        ),
+        command(
+            MessageType::Mode,
+            "MODE <channel|nickname> [{+|-}<modes> [<parameters>]]",
+            "Shows or changes the modes of a channel or of your client.",
+            1,
+            Some(Box::new(mode)),
+        ),
+        command(
+            MessageType::Filter,
+            "FILTER <channel> [+|-<pattern> [CENSOR|BLOCK]]",
+            "Lists, adds or removes the rules of the filter of a channel, if you are one of its operators.",
+            1,
+            Some(Box::new(filter)),
+        ),
+        command(
+            MessageType::ChanStats,
+            "CHANSTATS <channel>",
+            "Shows the amount of messages of a channel, its most active users and when it was created.",
+            1,
+            Some(Box::new(chanstats)),
+        ),
+        command(
+            MessageType::Oper,
+            "OPER <user> <password>",
+            "Makes you an operator of the server.",
+            2,
+            Some(Box::new(oper)),
+        ),
+        command(
+            MessageType::Invite,
+            "INVITE <nickname> <channel>",
+            "Invites a client to a channel.",
+            2,
+            Some(Box::new(invite)),
+        ),
+        command(
+            MessageType::Who,
+            "WHO [<name>]",
+            "Lists the clients that match the name, or the members of a channel.",
+            0,
+            Some(Box::new(who)),
+        ),
+        command(
+            MessageType::WhoIs,
+            "WHOIS <nickname>{,<nickname>}",
+            "Shows the information of the clients.",
+            1,
+            Some(Box::new(whois)),
+        ),
+        command(
+            MessageType::Away,
+            "AWAY [:<message>]",
+            "Marks you as away with the message, or as back without it.",
+            0,
+            Some(Box::new(away)),
+        ),
+        command(
+            MessageType::Silence,
+            "SILENCE [+|-<mask>]",
+            "Lists the masks you silenced, or adds or removes one to stop receiving their messages.",
+            0,
+            Some(Box::new(silence)),
+        ),
+        command(
+            MessageType::SetPref,
+            "SETPREF <PARTMSG|QUITMSG|AUTOJOIN|AWAY> [:<value>]",
+            "Saves a preference applied when you register, or removes it without a value.",
+            1,
+            Some(Box::new(setpref)),
+        ),
+        command(
+            MessageType::Prefs,
+            "PREFS",
+            "Lists your saved preferences.",
+            0,
+            Some(Box::new(prefs)),
+        ),
+        command(
+            MessageType::SetAutoJoin,
+            "SETAUTOJOIN [+|-<channel>{,<channel>}]",
+            "Shows the channels you join when you register, or adds or removes some of them.",
+            0,
+            Some(Box::new(setautojoin)),
+        ),
+        command(
+            MessageType::Dcc,
+            "DCC <CHAT|SCHAT|MSG|SEND|SSEND|RESUME|ACCEPT|STOP|CLOSE> <nickname> [<parameters>]",
+            "Starts a direct connection with a client to chat or send files.",
+            0,
+            Some(Box::new(dcc)),
+        ),
+        command(
+            MessageType::Stats,
+            "STATS [l|u|c|s]",
+            "Shows the connected clients, the uptime, the connections of each IP or the server links.",
+            0,
+            Some(Box::new(stats)),
+        ),
+        command(
+            MessageType::Rehash,
+            "REHASH",
+            "Loads again the configuration file, if you are an operator of the server.",
+            0,
+            Some(Box::new(rehash)),
+        )
+        .with_privilege(Privilege::ServerOperator),
+        command(
+            MessageType::Audit,
+            "AUDIT [<page>]",
+            "Shows a page of the actions of the operators, the newest first, if you are an operator of the server.",
+            0,
+            Some(Box::new(audit)),
+        )
+        .with_privilege(Privilege::ServerOperator),
+        command(
+            MessageType::Ping,
+            "PING <token>",
+            "Answers with a PONG with the same token, to measure the lag of the connection.",
+            1,
+            Some(Box::new(ping)),
+        ),
+        command(
+            MessageType::Help,
+            "HELP [<command>]",
+            "Lists the commands, or shows the syntax of a command.",
+            0,
+            Some(Box::new(help)),
+        ),
+    ];
+    CommandRegistry { commands }
+}
+
+fn quit(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    network: &Network,
+    server_name: &String,
+) -> Result<(), ServerError> {
+    handle_quit_command(message, nickname, session, network, server_name)?;
+    fetch_info(session, network, server_name)
+}
+
+fn privmsg(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    network: &Network,
+    server_name: &String,
+) -> Result<(), ServerError> {
+    handle_privmsg_command(message, &nickname, session, network, server_name)?.into_result()
+}
+
+fn notice(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    network: &Network,
+    server_name: &String,
+) -> Result<(), ServerError> {
+    handle_notice_command(message, &nickname, session, network, server_name)
+}
+
+fn join(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    network: &Network,
+    server_name: &String,
+) -> Result<(), ServerError> {
+    let results = handle_join_command(message, &nickname, session, network, server_name)?;
+    fetch_info(session, network, server_name)?;
+    results.into_result()
+}
+
+fn part(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    network: &Network,
+    server_name: &String,
+) -> Result<(), ServerError> {
+    let results = handle_part_command(message, nickname, session, network, server_name)?;
+    fetch_info(session, network, server_name)?;
+    results.into_result()
+}
+
+fn kick(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    network: &Network,
+    server_name: &String,
+) -> Result<(), ServerError> {
+    handle_kick_command(message, nickname, session, network, server_name)?;
+    fetch_info(session, network, server_name)
+}
+
+fn names(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    network: &Network,
+    _server_name: &String,
+) -> Result<(), ServerError> {
+    handle_names_command(message, nickname, session, network, None)
+}
+
+fn topic(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    network: &Network,
+    server_name: &String,
+) -> Result<(), ServerError> {
+    handle_topic_command(message, nickname, session, network, server_name)?;
+    fetch_info(session, network, server_name)
+}
+
+fn list(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    network: &Network,
+    _server_name: &String,
+) -> Result<(), ServerError> {
+    handle_list_command(message, &nickname, session, network, None)
+}
+
+fn mode(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    network: &Network,
+    server_name: &String,
+) -> Result<(), ServerError> {
+    handle_mode_command(message, nickname, session, network, server_name)
+}
+
+fn oper(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    network: &Network,
+    _server_name: &String,
+) -> Result<(), ServerError> {
+    handle_oper_command(message, nickname, session, network)
+}
+
+fn invite(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    network: &Network,
+    server_name: &String,
+) -> Result<(), ServerError> {
+    handle_invite_command(message, nickname, session, network, server_name)?;
+    fetch_info(session, network, server_name)
+}
+
+fn who(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    network: &Network,
+    _server_name: &String,
+) -> Result<(), ServerError> {
+    handle_who_command(message, nickname, session, network, None)
+}
+
+fn whois(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    network: &Network,
+    _server_name: &String,
+) -> Result<(), ServerError> {
+    handle_whois_command(message, nickname, session, network)
+}
+
+fn away(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    _network: &Network,
+    _server_name: &String,
+) -> Result<(), ServerError> {
+    handle_away_command(message, nickname, session)
+}
+
+fn dcc(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    network: &Network,
+    server_name: &String,
+) -> Result<(), ServerError> {
+    handle_dcc_command(message, nickname, session, network, server_name)
+}
+
+fn stats(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    network: &Network,
+    _server_name: &String,
+) -> Result<(), ServerError> {
+    handle_stats_command(message, nickname, session, network)
+}
+
+fn ghost(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    _network: &Network,
+    _server_name: &String,
+) -> Result<(), ServerError> {
+    handle_ghost_command(message, nickname, session)
+}
+
+fn rehash(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    _network: &Network,
+    _server_name: &String,
+) -> Result<(), ServerError> {
+    handle_rehash_command(message, nickname, session)
+}
+
+fn audit(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    _network: &Network,
+    _server_name: &String,
+) -> Result<(), ServerError> {
+    handle_audit_command(message, nickname, session)
+}
+
+fn help(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    _network: &Network,
+    _server_name: &String,
+) -> Result<(), ServerError> {
+    handle_help_command(message, nickname, session)
+}
+
+// the handlers of the registry receive the name of the server as a &String
+#[allow(clippy::ptr_arg)]
+fn ping(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    _network: &Network,
+    server_name: &String,
+) -> Result<(), ServerError> {
+    handle_ping_command(message, nickname, session, server_name)
+}
+
+fn silence(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    _network: &Network,
+    _server_name: &String,
+) -> Result<(), ServerError> {
+    handle_silence_command(message, nickname, session)
+}
+
+fn filter(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    _network: &Network,
+    _server_name: &String,
+) -> Result<(), ServerError> {
+    handle_filter_command(message, nickname, session)
+}
+
+fn chanstats(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    _network: &Network,
+    _server_name: &String,
+) -> Result<(), ServerError> {
+    handle_chanstats_command(message, nickname, session)
+}
+
+fn setpref(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    _network: &Network,
+    _server_name: &String,
+) -> Result<(), ServerError> {
+    handle_setpref_command(message, nickname, session)
+}
+
+fn prefs(
+    _message: Message,
+    nickname: String,
+    session: &Session,
+    _network: &Network,
+    _server_name: &String,
+) -> Result<(), ServerError> {
+    handle_prefs_command(nickname, session)
+}
+
+fn setautojoin(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    _network: &Network,
+    _server_name: &String,
+) -> Result<(), ServerError> {
+    handle_setautojoin_command(message, nickname, session)
+}
@@ -0,0 +1,161 @@
+use crate::{
+    load::reload_config,
+    locks::{read_lock_config, write_lock_config},
+    server_errors::ServerError,
+    socket::inform_client,
+};
+use model::{message::Message, responses::replies::CommandResponse, session::Session};
+
+/// Handles the REHASH command, which loads again the configuration file of the server.
+/// Only the operators of the server can send it, which is checked by the registry of the commands.
+/// # Arguments
+/// * `message` - The message sent by the client.
+/// * `nickname` - The nickname of the client that sent the message.
+/// * `session` - The session of the current server.
+pub fn handle_rehash_command(
+    _message: Message,
+    nickname: String,
+    session: &Session,
+) -> Result<(), ServerError> {
+    let current = read_lock_config(session)?;
+    let config = reload_config(&current)?;
+    drop(current);
+    let file = match &config.file {
+        Some(file) => file.display().to_string(),
+        None => "*".to_string(),
+    };
+    *write_lock_config(session)? = config;
+    println!("Configuration reloaded from {} by {}", file, nickname);
+
+    let response = CommandResponse::Rehashing { file }.to_string();
+    inform_client(session, &nickname, &response)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod rehash_tests {
+    use model::consts::MAX_MSG_SIZE;
+    use std::{
+        collections::HashMap,
+        io::Read,
+        net::TcpListener,
+        sync::{Arc, RwLock},
+    };
+
+    use model::{
+        link_queues::LinkQueues,
+        message::MessageType,
+        network::Network,
+        persistence::PersistenceType,
+        responses::{errors::ErrorResponse, replies::CommandResponse, response::Response},
+        server::Server,
+    };
+
+    use crate::{
+        commands::command_utils::{
+            create_client_for_test, create_message_for_test, create_session_for_test,
+        },
+        database::{handle_database, DatabasePaths},
+        load::load_config,
+        message_handler::handle_client_message,
+        server_errors::ServerError,
+    };
+
+    fn create_network_for_test() -> Network {
+        Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        }
+    }
+
+    fn read_response_for_test(reader: &mut impl Read) -> Response {
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        Response::serialize(String::from_utf8(msg).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_rehash_command_reloads_the_reserved_nicknames() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let paths = DatabasePaths::temporary().unwrap();
+        std::fs::write(&paths.config, "RESERVEDNICKS;root\n").unwrap();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, paths.clone());
+        let session = create_session_for_test(db_tx);
+        *session.config.write().unwrap() = load_config(&paths).unwrap();
+        let started = session.config.read().unwrap().started;
+        create_client_for_test(&session, addr, "operator".to_string());
+        let (mut reader, _) = listener.accept().unwrap();
+        let network = create_network_for_test();
+        network
+            .server
+            .write()
+            .unwrap()
+            .operators
+            .push("operator".to_string());
+
+        std::fs::write(&paths.config, "RESERVEDNICKS;root,admin*\n").unwrap();
+        let message = create_message_for_test(MessageType::Rehash, vec![]);
+        let result = handle_client_message(
+            message,
+            "operator".to_string(),
+            &session,
+            &network,
+            &"test".to_string(),
+        );
+
+        assert!(result.is_ok());
+        let config = session.config.read().unwrap();
+        assert!(config.is_reserved_nickname("Administrator"));
+        assert_eq!(config.started, started);
+        drop(config);
+        match read_response_for_test(&mut reader) {
+            Response::CommandResponse {
+                response: CommandResponse::Rehashing { file },
+            } => assert_eq!(file, paths.config.display().to_string()),
+            _ => panic!("expected a rehashing response"),
+        }
+    }
+
+    #[test]
+    fn test_rehash_command_from_a_client_that_is_not_operator() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        session.config.write().unwrap().nicklen = 5;
+        create_client_for_test(&session, addr, "nickname".to_string());
+        let (mut reader, _) = listener.accept().unwrap();
+        let network = create_network_for_test();
+
+        let message = create_message_for_test(MessageType::Rehash, vec![]);
+        let result = handle_client_message(
+            message,
+            "nickname".to_string(),
+            &session,
+            &network,
+            &"test".to_string(),
+        );
+
+        assert_eq!(result, Err(ServerError::UserNotOperator));
+        assert_eq!(session.config.read().unwrap().nicklen, 5);
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::ErrorResponse {
+                response: ErrorResponse::NoPrivileges
+            }
+        ));
+    }
+}
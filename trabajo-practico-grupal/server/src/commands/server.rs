@@ -1,5 +1,9 @@
-use crate::{server_errors::ServerError, socket::write_socket};
-use model::{message::Message, network::Network};
+use crate::locks::{write_lock_server, write_lock_servers};
+use crate::{server_errors::ServerError, socket::inform_network};
+use model::{
+    message::Message,
+    network::{LinkedServer, Network},
+};
 
 /// Function that handles the SERVER command received from another server.
 /// It inserts the new server into the network struct and informs the network
@@ -17,7 +21,7 @@ pub fn handle_server_command(
         return Err(ServerError::InvalidParameters);
     }
 
-    let from = match message.prefix.to_owned() {
+    let from = match message.prefix_name() {
         Some(p) => p,
         None => "".to_string(),
     };
@@ -28,7 +32,7 @@ pub fn handle_server_command(
         return Err(ServerError::ServerAlreadyRegistered);
     }
 
-    let mut servers_lock = network.servers.as_ref().write()?;
+    let mut servers_lock = write_lock_servers(network)?;
     if servers_lock.get(&new_server_name).is_some() {
         drop(servers_lock);
         return Err(ServerError::ServerAlreadyRegistered);
@@ -37,29 +41,18 @@ pub fn handle_server_command(
     println!("Received from server: {}", from);
     println!("New server entered the IRC Network: {}", new_server_name);
 
-    let server_lock = network.server.as_ref().write()?;
+    let server_lock = write_lock_server(network)?;
 
     let mut msg = message;
     msg.parameters[1] = (hopcount + 1).to_string();
     msg.trailing = Some(server_lock.name.to_owned());
     let buff = Message::deserialize(msg.to_owned())?;
 
-    if let Some((father_name, father_socket)) = server_lock.father.to_owned() {
-        if father_name != from {
-            write_socket(father_socket, &buff)?;
-        }
-    }
-
-    for (child_name, child_socket) in server_lock.children.clone().into_iter() {
-        if *child_name != from {
-            write_socket(child_socket, &buff)?;
-        }
-    }
-
-    servers_lock.insert(new_server_name, hopcount);
+    servers_lock.insert(new_server_name, LinkedServer::new(hopcount));
 
     drop(server_lock);
     drop(servers_lock);
 
+    inform_network(network, &from, &buff)?;
     Ok(())
 }
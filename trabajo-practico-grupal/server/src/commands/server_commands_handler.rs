@@ -1,21 +1,29 @@
 use std::{collections::HashMap, net::TcpStream, sync::Arc, thread, time::Duration};
 
 use model::{
+    ban::Ban,
     channel::Channel,
+    channel_stats::ChannelStats,
     channelflag::ChannelFlag,
     dcc::{DccMessage, DccMessageType},
-    message::Message,
-    network::Network,
+    message::{Message, MessageType},
+    network::{LinkedServer, Network, RemoteClient},
+    prefix::Prefix,
     responses::{errors::ErrorResponse, replies::CommandResponse},
     session::Session,
+    userflag::UserFlag,
 };
 
 use crate::{
+    commands::{command_utils::get_current_timestamp, who::add_who_answer},
     server_errors::ServerError,
-    socket::{inform_client, inform_network, write_socket},
+    socket::{inform_client, inform_client_link, inform_network, write_socket},
 };
 
-use super::command_utils::{read_lock_clients, write_lock_channels};
+use crate::locks::{
+    read_lock_clients, read_lock_network_clients, read_lock_server, write_lock_channels,
+    write_lock_network_clients, write_lock_servers,
+};
 
 /// Function that handles the command `NICK` received from a connected server.
 /// # Arguments
@@ -33,13 +41,13 @@ pub fn handle_server_nick_command(
     let hopcount = message.parameters[1].parse::<u8>().unwrap_or(0);
     println!("New client connected to network: {}", nickname);
     // add new client to network info
-    let mut clients_lock = network.clients.as_ref().write()?;
-    clients_lock.insert(nickname, hopcount);
+    let mut clients_lock = write_lock_network_clients(network)?;
+    clients_lock.insert(nickname, RemoteClient::new(hopcount).with_link(name));
     drop(clients_lock);
 
     // inform father and children about new client
     let mut msg = message;
-    msg.prefix = Some(name.to_owned());
+    msg.prefix = Some(Prefix::ServerName(name.to_owned()));
     msg.parameters[1] = (hopcount + 1).to_string();
     let msg = Message::deserialize(msg)?;
 
@@ -47,7 +55,76 @@ pub fn handle_server_nick_command(
     Ok(())
 }
 
+/// Function that handles the command `MODE` of a user received from a connected server.
+/// It updates the modes of the client in the network and informs the rest of the network.
+/// # Arguments
+/// * `message` - The message struct that contains the message received from the server.
+/// * `name` - The name of the server that sent the message.
+/// * `network` - The struct that contains information about the network.
+pub fn handle_server_user_mode_command(
+    message: Message,
+    name: &String,
+    network: &Network,
+) -> Result<(), ServerError> {
+    if message.parameters.len() < 2 {
+        return Err(ServerError::InvalidParameters);
+    }
+    let nickname = message.parameters[0].to_owned();
+    let modes = message.parameters[1]
+        .trim_start_matches('+')
+        .chars()
+        .map(UserFlag::match_flag)
+        .filter(|f| *f != UserFlag::Other)
+        .collect::<Vec<UserFlag>>();
+
+    let mut clients_lock = write_lock_network_clients(network)?;
+    if let Some(client) = clients_lock.get_mut(&nickname) {
+        println!("Modes of network client {} updated: {:?}", nickname, modes);
+        client.modes = modes;
+    }
+    drop(clients_lock);
+
+    let msg = Message::deserialize(message)?;
+    inform_network(network, name, &msg)?;
+    Ok(())
+}
+
+/// Function that handles the command `USER` received from a connected server, which follows
+/// the NICK of a new client with its user information, like `:alice USER alice host server :Alice`.
+/// It updates the client in the network and informs the rest of the network.
+/// # Arguments
+/// * `message` - The message struct that contains the message received from the server.
+/// * `name` - The name of the server that sent the message.
+/// * `network` - The struct that contains information about the network.
+/// # Errors
+/// ServerError::InvalidParameters if the message doesn't have the nickname, the username,
+/// the hostname and the servername.
+pub fn handle_server_user_command(
+    message: Message,
+    name: &String,
+    network: &Network,
+) -> Result<(), ServerError> {
+    let nickname = match message.prefix_name() {
+        Some(nickname) if message.parameters.len() >= 3 => nickname,
+        _ => return Err(ServerError::InvalidParameters),
+    };
+
+    let mut clients_lock = write_lock_network_clients(network)?;
+    if let Some(client) = clients_lock.get_mut(&nickname) {
+        client.username = message.parameters[0].to_owned();
+        client.hostname = message.parameters[1].to_owned();
+        client.servername = message.parameters[2].to_owned();
+        client.realname = message.trailing.to_owned().unwrap_or_default();
+    }
+    drop(clients_lock);
+
+    let msg = Message::deserialize(message)?;
+    inform_network(network, name, &msg)?;
+    Ok(())
+}
+
 /// Function that handles the command `PRIVMSG` received from a server.
+/// It is also used for the command `NOTICE`, which doesn't inform the sender of a missing receiver.
 /// # Arguments
 /// * `message` - The message struct that contains the message received from the server.
 /// * `server_name` - The name of the server that sent the message.
@@ -62,22 +139,11 @@ pub fn handle_server_privmsg_command(
 ) -> Result<(), ServerError> {
     let receiver = message.parameters[0].to_owned();
 
-    let clients = network.clients.as_ref().read()?;
+    let clients = read_lock_network_clients(network)?;
     if clients.get(&receiver).is_some() {
-        let server_lock = network.server.as_ref().write()?;
-        if let Some((father_name, father_socket)) = server_lock.father.to_owned() {
-            if *server_name != father_name {
-                let buff = Message::deserialize(message.to_owned())?;
-                write_socket(father_socket, &buff)?;
-            }
-        }
-        for (child_server_name, child_socket) in server_lock.children.clone().into_iter() {
-            if *server_name != child_server_name {
-                let buff = Message::deserialize(message.to_owned())?;
-                write_socket(child_socket, &buff)?;
-            }
-        }
-    } else {
+        let buff = Message::deserialize(message.to_owned())?;
+        inform_network(network, server_name, &buff)?;
+    } else if message.command != MessageType::Notice {
         let response = ErrorResponse::NoSuchNick { nickname: receiver }.to_string();
         inform_client(session, nickname, &response)?;
     }
@@ -85,6 +151,38 @@ pub fn handle_server_privmsg_command(
     Ok(())
 }
 
+/// Function that handles the command `REPLY` received from a server, which carries a response
+/// to a client of the network, like `:<server> REPLY <nickname> :<response>`.
+/// The response is sent to the client if it is connected to this server, adding it to its WHO
+/// query if it is part of one, or relayed towards the client otherwise.
+/// # Arguments
+/// * `message` - The message struct that contains the message received from the server.
+/// * `session` - The session of the current server.
+/// * `network` - The struct that contains information about the network.
+/// # Errors
+/// ServerError::InvalidParameters if the message doesn't have the nickname and the response.
+pub fn handle_server_reply_command(
+    message: Message,
+    session: &Session,
+    network: &Network,
+) -> Result<(), ServerError> {
+    let (nickname, response) = match (message.parameters.first(), &message.trailing) {
+        (Some(nickname), Some(response)) => (nickname.to_owned(), response.to_owned()),
+        _ => return Err(ServerError::InvalidParameters),
+    };
+    let is_local = read_lock_clients(session)?.contains_key(&nickname);
+    if is_local {
+        let server_name = message.prefix_name().unwrap_or_default();
+        if !add_who_answer(session, &nickname, &server_name, &response)? {
+            inform_client(session, &nickname, &response)?;
+        }
+    } else {
+        let msg = Message::deserialize(message)?;
+        inform_client_link(network, &nickname, &msg)?;
+    }
+    Ok(())
+}
+
 /// Function that handles the command `AWAY` received from a server.
 /// # Arguments
 /// * `message` - The message struct that contains the message received from the server.
@@ -97,7 +195,7 @@ pub fn handle_server_away_command(
     session: &Session,
     network: &Network,
 ) -> Result<(), ServerError> {
-    let sender = match message.prefix.to_owned() {
+    let sender = match message.prefix_name() {
         Some(p) => p,
         None => "".to_owned(),
     };
@@ -156,7 +254,10 @@ pub fn handle_server_dcc_command(
     //     _ => {}
     // }
 
-    if dcc_message.command == DccMessageType::Chat {
+    if matches!(
+        dcc_message.command,
+        DccMessageType::Chat | DccMessageType::SChat | DccMessageType::Relay
+    ) {
         if let Some(client) = local_clients.get(&requested_client) {
             if client.connected {
                 inform_client(
@@ -164,7 +265,11 @@ pub fn handle_server_dcc_command(
                     &requested_client,
                     &DccMessage::serialize(dcc_message)?,
                 )?;
-            } else {
+            } else if dcc_message.command != DccMessageType::Relay
+                && dcc_message.passive_token().is_none()
+            {
+                // tells the client who sent the offer, unless it is passive, since it is not listening,
+                // or relayed, since it has no address and its relay expires
                 let response = format!("DCC CLOSE {} NotConnected", requested_client);
                 let ip = dcc_message.parameters[1].to_owned();
                 let port = dcc_message.parameters[2].to_owned();
@@ -195,12 +300,14 @@ pub fn handle_server_who_reply(
     network: &Network,
 ) -> Result<(), ServerError> {
     let local_clients = read_lock_clients(session)?;
-    let mut network_clients = network.clients.as_ref().write()?;
+    let mut network_clients = write_lock_network_clients(network)?;
 
     for user in users.clone() {
         if !local_clients.contains_key(&user) {
             println!("New network client: {}", user);
-            network_clients.insert(user, 1);
+            network_clients
+                .entry(user)
+                .or_insert_with(|| RemoteClient::new(1).with_link(server_name));
         }
     }
 
@@ -237,9 +344,19 @@ pub fn handle_server_names_reply(
             password: None,
             limit: None,
             moderators: vec![],
+            forward: None,
+            filters: vec![],
+            stats: ChannelStats::new(get_current_timestamp()),
         };
         println!("New distributed channel: {}", channel_name);
         channels.insert(channel_name.to_owned(), channel);
+    } else if let Some(channel) = channels.get_mut(&channel_name) {
+        // the names of a crowded channel arrive in several replies
+        for user in channel_users.iter() {
+            if !channel.users.contains(user) {
+                channel.users.push(user.to_owned());
+            }
+        }
     }
     drop(channels);
 
@@ -350,6 +467,20 @@ fn set_modes(channel: &mut Channel, modes: HashMap<String, String>) {
                     }
                 }
             }
+            ChannelFlag::Filtered => {
+                if let Some(mode) = modes.get(&flag.to_string()) {
+                    if *mode == "+" {
+                        channel.modes.push(flag);
+                    }
+                }
+            }
+            ChannelFlag::Permanent => {
+                if let Some(mode) = modes.get(&flag.to_string()) {
+                    if *mode == "+" {
+                        channel.modes.push(flag);
+                    }
+                }
+            }
             ChannelFlag::ChannelOperator => {
                 if let Some(operators) = modes.get(&flag.to_string()) {
                     let opers = operators
@@ -367,16 +498,7 @@ fn set_modes(channel: &mut Channel, modes: HashMap<String, String>) {
             }
             ChannelFlag::Ban => {
                 if let Some(banned_users) = modes.get(&flag.to_string()) {
-                    let bans = banned_users
-                        .split(',')
-                        .filter_map(|s| {
-                            if !s.is_empty() {
-                                Some(s.to_string())
-                            } else {
-                                None
-                            }
-                        })
-                        .collect();
+                    let bans = banned_users.split(',').filter_map(Ban::serialize).collect();
                     channel.banned_users = bans;
                 }
             }
@@ -396,6 +518,15 @@ fn set_modes(channel: &mut Channel, modes: HashMap<String, String>) {
                     }
                 }
             }
+            ChannelFlag::Forward => {
+                if let Some(forward) = modes.get(&flag.to_string()) {
+                    if !forward.is_empty() {
+                        channel.forward = Some(forward.to_string());
+                    } else {
+                        channel.forward = None
+                    }
+                }
+            }
             ChannelFlag::SpeakInModeratedChannel => {
                 if let Some(moderators) = modes.get(&flag.to_string()) {
                     let mods = moderators
@@ -426,11 +557,11 @@ pub fn handle_server_server_reply(
     _session: &Session,
     network: &Network,
 ) -> Result<(), ServerError> {
-    let mut servers = network.servers.as_ref().write()?;
-    let server_lock = network.server.as_ref().read()?;
+    let mut servers = write_lock_servers(network)?;
+    let server_lock = read_lock_server(network)?;
     for (server, hops) in hash_servers {
         if !servers.contains_key(&server) && server != *server_lock.name {
-            servers.insert(server, hops + 1);
+            servers.insert(server, LinkedServer::new(hops + 1));
         }
     }
     drop(servers);
@@ -0,0 +1,186 @@
+use crate::locks::{read_lock_config, write_lock_clients};
+use crate::{database::inform_database, server_errors::ServerError, socket::inform_client};
+use model::{
+    json::ToJson,
+    message::Message,
+    persistence::PersistenceType,
+    responses::{errors::ErrorResponse, replies::CommandResponse},
+    session::Session,
+};
+
+/// Handles the SILENCE command, which manages the nickname masks whose messages and invitations
+/// are not delivered to the client. Without parameters it lists the masks, with `+<mask>` or
+/// `<mask>` it adds the mask and with `-<mask>` it removes it.
+/// The list is saved with the client in the database and sent back to the client after each change.
+/// # Errors
+/// ServerError::InvalidParameters if the mask is empty or has a `;` or a `,`.
+/// ServerError::SilenceListFull if the list already has the maximum amount of masks of the configuration.
+///
+/// For each error a message is sent to the client to inform them of the error.
+pub fn handle_silence_command(
+    message: Message,
+    nickname: String,
+    session: &Session,
+) -> Result<(), ServerError> {
+    let silence_limit = read_lock_config(session)?.silence_limit;
+    let mut clients = write_lock_clients(session)?;
+    let client = match clients.get_mut(&nickname) {
+        Some(client) => client,
+        None => return Err(ServerError::ClientNotFound),
+    };
+
+    if let Some(parameter) = message.parameters.first() {
+        let (adding, mask) = match parameter.strip_prefix('-') {
+            Some(mask) => (false, mask),
+            None => (true, parameter.strip_prefix('+').unwrap_or(parameter)),
+        };
+        if mask.is_empty() || mask.contains(';') || mask.contains(',') {
+            drop(clients);
+            let response = ErrorResponse::NeedMoreParams {
+                command: "SILENCE".to_string(),
+            }
+            .to_string();
+            inform_client(session, &nickname, &response)?;
+            return Err(ServerError::InvalidParameters);
+        }
+        let mask = mask.to_lowercase();
+        if adding && !client.silenced.contains(&mask) {
+            if client.silenced.len() >= silence_limit {
+                drop(clients);
+                let response = ErrorResponse::SilenceListFull { mask }.to_string();
+                inform_client(session, &nickname, &response)?;
+                return Err(ServerError::SilenceListFull);
+            }
+            client.silenced.push(mask);
+        } else if !adding {
+            client.silenced.retain(|silenced| *silenced != mask);
+        }
+        inform_database(
+            PersistenceType::ClientUpdate(nickname.to_owned()),
+            client.to_json(),
+            session,
+        )?;
+    }
+
+    let masks = client.silenced.clone();
+    drop(clients);
+    for mask in masks {
+        let response = CommandResponse::SilenceList { mask }.to_string();
+        inform_client(session, &nickname, &response)?;
+    }
+    let response = CommandResponse::EndOfSilenceList.to_string();
+    inform_client(session, &nickname, &response)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod silence_tests {
+    use model::consts::MAX_MSG_SIZE;
+    use std::{
+        io::Read,
+        net::{TcpListener, TcpStream},
+    };
+
+    use model::{
+        message::MessageType,
+        persistence::PersistenceType,
+        responses::{errors::ErrorResponse, replies::CommandResponse, response::Response},
+        session::Session,
+    };
+
+    use crate::{
+        commands::{
+            command_utils::{
+                create_client_for_test, create_message_for_test, create_session_for_test,
+            },
+            silence::handle_silence_command,
+        },
+        database::{handle_database, DatabasePaths},
+        locks::{read_lock_clients, write_lock_config},
+        server_errors::ServerError,
+    };
+
+    fn read_response_for_test(reader: &mut TcpStream) -> Response {
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        Response::serialize(String::from_utf8(msg).unwrap()).unwrap()
+    }
+
+    fn create_session_with_client_for_test() -> (Session, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr, "nickname".to_string());
+        let (reader, _) = listener.accept().unwrap();
+        (session, reader)
+    }
+
+    fn send_silence_for_test(
+        session: &Session,
+        parameters: Vec<String>,
+    ) -> Result<(), ServerError> {
+        let message = create_message_for_test(MessageType::Silence, parameters);
+        handle_silence_command(message, "nickname".to_string(), session)
+    }
+
+    #[test]
+    fn test_silence_adds_and_removes_masks() {
+        let (session, mut reader) = create_session_with_client_for_test();
+
+        assert!(send_silence_for_test(&session, vec!["+Spam*".to_string()]).is_ok());
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::CommandResponse {
+                response: CommandResponse::SilenceList { mask }
+            } if mask == "spam*"
+        ));
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::CommandResponse {
+                response: CommandResponse::EndOfSilenceList
+            }
+        ));
+        let clients = read_lock_clients(&session).unwrap();
+        assert_eq!(clients["nickname"].silenced, vec!["spam*".to_string()]);
+        assert!(clients["nickname"].is_silencing("SpamBot"));
+        assert!(!clients["nickname"].is_silencing("friend"));
+        drop(clients);
+
+        assert!(send_silence_for_test(&session, vec!["-spam*".to_string()]).is_ok());
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::CommandResponse {
+                response: CommandResponse::EndOfSilenceList
+            }
+        ));
+        assert!(read_lock_clients(&session).unwrap()["nickname"]
+            .silenced
+            .is_empty());
+    }
+
+    #[test]
+    fn test_silence_list_cannot_exceed_the_limit() {
+        let (session, mut reader) = create_session_with_client_for_test();
+        write_lock_config(&session).unwrap().silence_limit = 1;
+
+        assert!(send_silence_for_test(&session, vec!["first".to_string()]).is_ok());
+        read_response_for_test(&mut reader);
+        read_response_for_test(&mut reader);
+        let result = send_silence_for_test(&session, vec!["second".to_string()]);
+
+        assert_eq!(result, Err(ServerError::SilenceListFull));
+        assert!(matches!(
+            read_response_for_test(&mut reader),
+            Response::ErrorResponse {
+                response: ErrorResponse::SilenceListFull { mask }
+            } if mask == "second"
+        ));
+        assert_eq!(
+            read_lock_clients(&session).unwrap()["nickname"].silenced,
+            vec!["first".to_string()]
+        );
+    }
+}
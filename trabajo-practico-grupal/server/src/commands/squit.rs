@@ -1,4 +1,4 @@
-use crate::server_errors::ServerError;
+use crate::{locks::write_lock_servers_and_server, server_errors::ServerError};
 use model::{message::Message, network::Network};
 use std::net::Shutdown;
 
@@ -12,8 +12,7 @@ pub fn handle_squit_command(
     name: &String,
     network: &Network,
 ) -> Result<(), ServerError> {
-    let mut server_lock = network.server.as_ref().write()?;
-    let mut servers_lock = network.servers.as_ref().write()?;
+    let (mut servers_lock, mut server_lock) = write_lock_servers_and_server(network)?;
     if let Some(child_socket) = server_lock.children.get(name) {
         child_socket.as_ref().shutdown(Shutdown::Both)?;
         server_lock.children.remove(name);
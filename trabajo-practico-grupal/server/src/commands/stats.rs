@@ -0,0 +1,464 @@
+use super::command_utils::get_current_timestamp;
+use crate::locks::{
+    lock_connections, read_lock_channels, read_lock_clients, read_lock_config, read_lock_servers,
+};
+use crate::{server_errors::ServerError, socket::inform_client};
+use model::{
+    json::ToJson, message::Message, network::Network, responses::replies::CommandResponse,
+    session::Session,
+};
+
+/// Handles the STATS command, which sends the client information about the server.
+/// The queries supported are:
+/// * `l` - The connected clients, with the seconds they have been idle and the time they connected.
+/// * `u` - The time the server has been running.
+/// * `c` - The amount of connections open from each IP.
+/// * `s` - The servers of the network, with their hopcount and the latency of the links with
+///   the servers linked to this one.
+/// * `m` - The commands handled by the server, with the amount of times each one was handled and
+///   the mean, 95th percentile and maximum of the time its handler took, in microseconds.
+/// * `g` - The amount of clients and channels of the server with the bytes their records use,
+///   and the totals of the garbage collection.
+///
+/// Any other query only receives the EndOfStats response.
+/// # Arguments
+/// * `message` - The message sent by the client, with the query as its first parameter.
+/// * `nickname` - The nickname of the client that sent the message.
+/// * `session` - The session of the current server.
+/// * `network` - The struct that contains the information of the network.
+pub fn handle_stats_command(
+    message: Message,
+    nickname: String,
+    session: &Session,
+    network: &Network,
+) -> Result<(), ServerError> {
+    let query = match message.parameters.first() {
+        Some(query) => query.to_owned(),
+        None => "*".to_string(),
+    };
+    let now = get_current_timestamp();
+
+    match query.as_str() {
+        "l" => {
+            let clients = read_lock_clients(session)?;
+            let mut responses = vec![];
+            for client in clients.values().filter(|c| c.connected) {
+                responses.push(
+                    CommandResponse::StatsLinkInfo {
+                        nickname: client.nickname.to_owned(),
+                        idle: client.get_idle_time(now),
+                        signon: client.signon,
+                    }
+                    .to_string(),
+                );
+            }
+            drop(clients);
+            for response in responses {
+                inform_client(session, &nickname, &response)?;
+            }
+        }
+        "u" => {
+            let started = read_lock_config(session)?.started;
+            let response = CommandResponse::StatsUptime {
+                seconds: now.saturating_sub(started),
+            }
+            .to_string();
+            inform_client(session, &nickname, &response)?;
+        }
+        "c" => {
+            let connections = lock_connections(session)?;
+            let responses = connections
+                .iter()
+                .map(|(ip, connections)| {
+                    CommandResponse::StatsConnections {
+                        ip: ip.to_owned(),
+                        connections: *connections,
+                    }
+                    .to_string()
+                })
+                .collect::<Vec<_>>();
+            drop(connections);
+            for response in responses {
+                inform_client(session, &nickname, &response)?;
+            }
+        }
+        "s" => {
+            let mut servers = read_lock_servers(network)?
+                .iter()
+                .map(|(name, server)| (name.to_owned(), server.hopcount, server.latency))
+                .collect::<Vec<_>>();
+            servers.sort();
+            for (server, hopcount, latency) in servers {
+                let response = CommandResponse::StatsServerLink {
+                    server,
+                    hopcount,
+                    latency,
+                }
+                .to_string();
+                inform_client(session, &nickname, &response)?;
+            }
+        }
+        "m" => {
+            for (command, histogram) in session.command_metrics.snapshot() {
+                let response = CommandResponse::StatsCommandLatency {
+                    command,
+                    count: histogram.count,
+                    mean: histogram.mean(),
+                    p95: histogram.percentile(95),
+                    max: histogram.max,
+                }
+                .to_string();
+                inform_client(session, &nickname, &response)?;
+            }
+        }
+        "g" => {
+            let channels = read_lock_channels(session)?;
+            let channel_bytes = channels
+                .values()
+                .map(|channel| channel.to_json().len())
+                .sum();
+            let channels_amount = channels.len();
+            let clients = read_lock_clients(session)?;
+            let client_bytes = clients.values().map(|client| client.to_json().len()).sum();
+            let memory = CommandResponse::StatsMemory {
+                clients: clients.len(),
+                client_bytes,
+                channels: channels_amount,
+                channel_bytes,
+            }
+            .to_string();
+            drop(clients);
+            drop(channels);
+            let totals = session.gc_stats.totals();
+            let garbage = CommandResponse::StatsGarbage {
+                passes: totals.passes,
+                channels_removed: totals.channels_removed,
+                clients_archived: totals.clients_archived,
+                last_pass: totals.last_pass,
+            }
+            .to_string();
+            inform_client(session, &nickname, &memory)?;
+            inform_client(session, &nickname, &garbage)?;
+        }
+        _ => (),
+    }
+
+    let response = CommandResponse::EndOfStats { query }.to_string();
+    inform_client(session, &nickname, &response)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod stats_tests {
+    use model::consts::MAX_MSG_SIZE;
+    use std::{
+        collections::HashMap,
+        io::Read,
+        net::TcpListener,
+        sync::{Arc, RwLock},
+        time::Duration,
+    };
+
+    use model::{
+        link_queues::LinkQueues,
+        message::MessageType,
+        network::{LinkedServer, Network},
+        persistence::PersistenceType,
+        responses::{replies::CommandResponse, response::Response},
+        server::Server,
+    };
+
+    use crate::locks::{lock_connections, write_lock_clients};
+    use crate::{
+        commands::{
+            command_utils::{
+                create_client_for_test, create_message_for_test, create_session_for_test,
+                get_current_timestamp,
+            },
+            stats::handle_stats_command,
+        },
+        database::{handle_database, DatabasePaths},
+    };
+
+    fn read_response(reader: &mut std::net::TcpStream) -> Response {
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        Response::serialize(String::from_utf8(msg).unwrap()).unwrap()
+    }
+
+    fn create_network_for_test() -> Network {
+        Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        }
+    }
+
+    #[test]
+    fn test_stats_command_lists_idle_clients() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr, "client".to_string());
+        let (mut reader, _) = listener.accept().unwrap();
+        let now = get_current_timestamp();
+        let mut clients = write_lock_clients(&session).unwrap();
+        let client = clients.get_mut("client").unwrap();
+        client.signon = now - 100;
+        client.last_activity = now - 60;
+        drop(clients);
+
+        let message = create_message_for_test(MessageType::Stats, vec!["l".to_string()]);
+        let result = handle_stats_command(
+            message,
+            "client".to_string(),
+            &session,
+            &create_network_for_test(),
+        );
+
+        assert!(result.is_ok());
+        match read_response(&mut reader) {
+            Response::CommandResponse {
+                response:
+                    CommandResponse::StatsLinkInfo {
+                        nickname,
+                        idle,
+                        signon,
+                    },
+            } => {
+                assert_eq!(nickname, "client");
+                assert!(idle >= 60);
+                assert_eq!(signon, now - 100);
+            }
+            _ => panic!("expected the information of the client"),
+        }
+        match read_response(&mut reader) {
+            Response::CommandResponse {
+                response: CommandResponse::EndOfStats { query },
+            } => assert_eq!(query, "l"),
+            _ => panic!("expected the end of the stats"),
+        }
+    }
+
+    #[test]
+    fn test_stats_command_uptime() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr, "client".to_string());
+        let (mut reader, _) = listener.accept().unwrap();
+        session.config.write().unwrap().started = get_current_timestamp() - 3661;
+
+        let message = create_message_for_test(MessageType::Stats, vec!["u".to_string()]);
+        let result = handle_stats_command(
+            message,
+            "client".to_string(),
+            &session,
+            &create_network_for_test(),
+        );
+
+        assert!(result.is_ok());
+        match read_response(&mut reader) {
+            Response::CommandResponse {
+                response: CommandResponse::StatsUptime { seconds },
+            } => assert!((3661..3671).contains(&seconds)),
+            _ => panic!("expected the uptime of the server"),
+        }
+    }
+
+    #[test]
+    fn test_stats_command_lists_connections_per_ip() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr, "client".to_string());
+        let (mut reader, _) = listener.accept().unwrap();
+        lock_connections(&session)
+            .unwrap()
+            .insert("10.0.0.1".to_string(), 3);
+
+        let message = create_message_for_test(MessageType::Stats, vec!["c".to_string()]);
+        let result = handle_stats_command(
+            message,
+            "client".to_string(),
+            &session,
+            &create_network_for_test(),
+        );
+
+        assert!(result.is_ok());
+        match read_response(&mut reader) {
+            Response::CommandResponse {
+                response: CommandResponse::StatsConnections { ip, connections },
+            } => {
+                assert_eq!(ip, "10.0.0.1");
+                assert_eq!(connections, 3);
+            }
+            _ => panic!("expected the connections of the IP"),
+        }
+    }
+
+    #[test]
+    fn test_stats_command_lists_the_latency_of_the_links() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr, "client".to_string());
+        let (mut reader, _) = listener.accept().unwrap();
+        let network = create_network_for_test();
+        let mut linked = LinkedServer::new(1);
+        linked.latency = Some(25);
+        let mut servers = network.servers.write().unwrap();
+        servers.insert("child".to_string(), linked);
+        servers.insert("grandchild".to_string(), LinkedServer::new(2));
+        drop(servers);
+
+        let message = create_message_for_test(MessageType::Stats, vec!["s".to_string()]);
+        let result = handle_stats_command(message, "client".to_string(), &session, &network);
+
+        assert!(result.is_ok());
+        let mut links = vec![];
+        for _ in 0..2 {
+            match read_response(&mut reader) {
+                Response::CommandResponse {
+                    response:
+                        CommandResponse::StatsServerLink {
+                            server,
+                            hopcount,
+                            latency,
+                        },
+                } => links.push((server, hopcount, latency)),
+                _ => panic!("expected the links of the server"),
+            }
+        }
+        assert_eq!(
+            links,
+            vec![
+                ("child".to_string(), 1, Some(25)),
+                ("grandchild".to_string(), 2, None)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stats_command_lists_the_processing_time_of_the_commands() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr, "client".to_string());
+        let (mut reader, _) = listener.accept().unwrap();
+        session
+            .command_metrics
+            .record("JOIN", Duration::from_micros(300));
+        session
+            .command_metrics
+            .record("JOIN", Duration::from_micros(900));
+
+        let message = create_message_for_test(MessageType::Stats, vec!["m".to_string()]);
+        let result = handle_stats_command(
+            message,
+            "client".to_string(),
+            &session,
+            &create_network_for_test(),
+        );
+
+        assert!(result.is_ok());
+        match read_response(&mut reader) {
+            Response::CommandResponse {
+                response:
+                    CommandResponse::StatsCommandLatency {
+                        command,
+                        count,
+                        mean,
+                        p95,
+                        max,
+                    },
+            } => {
+                assert_eq!(command, "JOIN");
+                assert_eq!(count, 2);
+                assert_eq!(mean, 600);
+                assert_eq!(p95, 900);
+                assert_eq!(max, 900);
+            }
+            _ => panic!("expected the processing time of JOIN"),
+        }
+        match read_response(&mut reader) {
+            Response::CommandResponse {
+                response: CommandResponse::EndOfStats { query },
+            } => assert_eq!(query, "m"),
+            _ => panic!("expected the end of the stats"),
+        }
+    }
+
+    #[test]
+    fn test_stats_command_reports_the_memory_and_the_garbage_collection() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr, "client".to_string());
+        let (mut reader, _) = listener.accept().unwrap();
+        session.gc_stats.record_pass(2, 1, 500);
+
+        let message = create_message_for_test(MessageType::Stats, vec!["g".to_string()]);
+        let result = handle_stats_command(
+            message,
+            "client".to_string(),
+            &session,
+            &create_network_for_test(),
+        );
+
+        assert!(result.is_ok());
+        match read_response(&mut reader) {
+            Response::CommandResponse {
+                response:
+                    CommandResponse::StatsMemory {
+                        clients,
+                        client_bytes,
+                        channels,
+                        channel_bytes,
+                    },
+            } => {
+                assert_eq!(clients, 1);
+                assert!(client_bytes > 0);
+                assert_eq!(channels, 0);
+                assert_eq!(channel_bytes, 0);
+            }
+            _ => panic!("expected the memory of the server"),
+        }
+        match read_response(&mut reader) {
+            Response::CommandResponse {
+                response:
+                    CommandResponse::StatsGarbage {
+                        passes,
+                        channels_removed,
+                        clients_archived,
+                        last_pass,
+                    },
+            } => assert_eq!(
+                (passes, channels_removed, clients_archived, last_pass),
+                (1, 2, 1, 500)
+            ),
+            _ => panic!("expected the totals of the garbage collection"),
+        }
+    }
+}
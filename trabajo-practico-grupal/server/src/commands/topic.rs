@@ -1,5 +1,6 @@
 use model::{
-    channel::Channel, channelflag::ChannelFlag, message::Message, network::Network,
+    channel::Channel, channelflag::ChannelFlag, consts::NETWORK_CHANNEL_PREFIX,
+    events::ServerEvent, json::ToJson, message::Message, network::Network,
     persistence::PersistenceType, responses::errors::ErrorResponse,
     responses::replies::CommandResponse, session::Session,
 };
@@ -11,7 +12,7 @@ use crate::{
     socket::{inform_client, inform_network},
 };
 
-use super::command_utils::write_lock_channels;
+use crate::locks::write_lock_channels;
 
 /// Function that handles the topic command.
 /// If one paramater is received in the message, then it returs
@@ -30,6 +31,7 @@ pub fn handle_topic_command(
     server_name: &String,
 ) -> Result<(), ServerError> {
     let mut channels_lock = write_lock_channels(session)?;
+    let mut changed = None;
 
     match message.parameters.len() {
         0 => {
@@ -42,14 +44,14 @@ pub fn handle_topic_command(
         }
         1 => {
             if message.trailing.is_some() {
-                set_topic(
+                changed = Some(set_topic(
                     &mut channels_lock,
                     &nickname,
                     message,
                     session,
                     network,
                     server_name,
-                )?;
+                )?);
             } else {
                 match channels_lock.get(&message.parameters[0]) {
                     Some(channel) => {
@@ -69,17 +71,24 @@ pub fn handle_topic_command(
             }
         }
         _ => {
-            set_topic(
+            changed = Some(set_topic(
                 &mut channels_lock,
                 &nickname,
                 message,
                 session,
                 network,
                 server_name,
-            )?;
+            )?);
         }
     }
     drop(channels_lock);
+    if let Some((channel, topic)) = changed {
+        session.publish(ServerEvent::TopicChanged {
+            channel,
+            topic,
+            set_by: nickname,
+        });
+    }
     Ok(())
 }
 
@@ -91,6 +100,8 @@ pub fn handle_topic_command(
 /// * `session` - The session of the user that sent the message.
 /// * `network` - The struct that contains information about the network.
 /// * `server_name` - The name of the current server.
+///
+/// Returns the name of the channel and its new topic.
 fn set_topic(
     channels_lock: &mut RwLockWriteGuard<HashMap<String, Channel>>,
     nickname: &String,
@@ -98,7 +109,7 @@ fn set_topic(
     session: &Session,
     network: &Network,
     server_name: &String,
-) -> Result<(), ServerError> {
+) -> Result<(String, String), ServerError> {
     match channels_lock.get_mut(&message.parameters[0]) {
         Some(channel) => {
             if !channel.users.contains(nickname) {
@@ -124,7 +135,7 @@ fn set_topic(
             channel.topic = get_topic(message.to_owned());
             inform_database(
                 PersistenceType::ChannelUpdate(channel.name.to_owned()),
-                channel.to_string(),
+                channel.to_json(),
                 session,
             )?;
             println!(
@@ -137,19 +148,19 @@ fn set_topic(
             })
             .to_string();
             inform_client(session, nickname, response.as_str())?;
-            if channel.name.starts_with('#') {
+            if channel.name.starts_with(NETWORK_CHANNEL_PREFIX) {
                 let mut msg = message.to_owned();
-                msg.prefix = Some(nickname.to_owned());
+                msg.set_nick_prefix(nickname);
                 let msg = Message::deserialize(msg)?;
                 inform_network(network, server_name, &msg)?;
             }
+            Ok((channel.name.to_owned(), channel.topic.to_owned()))
         }
         None => {
             // drop(channels_lock);
-            return Err(ServerError::ChannelNotFound);
+            Err(ServerError::ChannelNotFound)
         }
     }
-    Ok(())
 }
 
 /// Functions that parses the topic checking the
@@ -173,6 +184,7 @@ fn get_topic(message: Message) -> String {
 
 #[cfg(test)]
 mod topic_tests {
+    use model::consts::MAX_MSG_SIZE;
     use std::collections::HashMap;
     use std::io::Read;
     use std::net::TcpListener;
@@ -181,13 +193,14 @@ mod topic_tests {
 
     use crate::commands::command_utils::{
         create_client_for_test, create_message_for_test, create_session_for_test,
-        read_lock_channels, write_lock_channels,
     };
     use crate::commands::topic::handle_topic_command;
-    use crate::database::handle_database;
+    use crate::database::{handle_database, DatabasePaths};
+    use crate::locks::{read_lock_channels, write_lock_channels};
     use crate::server_errors::ServerError;
     use model::channel::Channel;
     use model::channelflag::ChannelFlag;
+    use model::link_queues::LinkQueues;
     use model::message::MessageType;
     use model::network::Network;
     use model::persistence::PersistenceType;
@@ -200,7 +213,7 @@ mod topic_tests {
     pub fn test_command_topic_changes_channel_topic() {
         let listener = TcpListener::bind("127.0.0.1:8140".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8140".to_string(),
@@ -214,10 +227,11 @@ mod topic_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, "127.0.0.1:8140".to_string(), "client".to_string());
@@ -243,7 +257,7 @@ mod topic_tests {
         );
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -278,7 +292,7 @@ mod topic_tests {
     fn test_command_topic_only_settable_by_operators() {
         let listener = TcpListener::bind("127.0.0.1:8141".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8141".to_string(),
@@ -292,10 +306,11 @@ mod topic_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, "127.0.0.1:8141".to_string(), "client".to_string());
@@ -321,7 +336,7 @@ mod topic_tests {
         );
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -355,7 +370,7 @@ mod topic_tests {
     pub fn test_command_topic_cant_change_topic_of_non_existing_channel() {
         let listener = TcpListener::bind("127.0.0.1:8142".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8142".to_string(),
@@ -369,10 +384,11 @@ mod topic_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, "127.0.0.1:8142".to_string(), "client".to_string());
@@ -396,7 +412,7 @@ mod topic_tests {
     pub fn test_command_topic_invalid_parameters() {
         let listener = TcpListener::bind("127.0.0.1:8143".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8143".to_string(),
@@ -410,10 +426,11 @@ mod topic_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, "127.0.0.1:8143".to_string(), "client".to_string());
@@ -429,7 +446,7 @@ mod topic_tests {
         );
         let (mut reader, _addr) = listener.accept().unwrap();
         drop(listener);
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -1,21 +1,37 @@
-use super::command_utils::{read_lock_channels, read_lock_clients};
+use super::{
+    command_utils::{get_channel_peers, is_invisible, require_registered},
+    mode::get_user_modes_string,
+};
+use crate::locks::{
+    lock_who_queries, read_lock_channels_and_clients, read_lock_network_clients, read_lock_server,
+    read_lock_servers,
+};
 use crate::{
     server_errors::ServerError,
-    socket::{inform_client, inform_server},
+    socket::{inform_client, inform_network, inform_remote_client, inform_server},
 };
 use model::{
-    channelflag::ChannelFlag,
+    client_registry::ClientsReadGuard,
+    consts::is_channel_name,
     message::Message,
-    network::Network,
-    responses::{errors::ErrorResponse, replies::CommandResponse},
+    network::{Network, RemoteClient},
+    responses::{errors::ErrorResponse, replies::CommandResponse, response::Response},
     session::Session,
     userflag::UserFlag,
+    who_query::WhoQuery,
+};
+use std::{
+    collections::HashSet,
+    thread,
+    time::{Duration, Instant},
 };
-use std::collections::HashSet;
 
+static WHO_QUERY_TIMEOUT: Duration = Duration::from_secs(5);
 /// Returns a list of all users on the server if there are not any parameters, or a list of all users on the server
 /// matching the given parameters.
+/// Users with the invisible mode set are only listed to the users that share a channel with them.
 /// # Errors
+/// * `ServerError::ClientMustRegisterOrAuthenticate`: If the client that sent the command is not registered. It will send the client a response with the error ErrorResponse::NotRegistered.
 /// * `ServerError::InvalidParameters`: If the command is not followed by enough parameters. It will send the client a response with the error ErrorResponse::NeedMoreParams.
 /// * `ServerError::ChannelNotFound`: If the channel that was requested does not exist. It will send the client a response with the error ErrorResponse::NoSuchChannel.
 pub fn handle_who_command(
@@ -25,6 +41,9 @@ pub fn handle_who_command(
     network: &Network,
     server_name: Option<String>,
 ) -> Result<(), ServerError> {
+    if server_name.is_none() {
+        require_registered(&nickname, session, network)?;
+    }
     if message.parameters.len() > 1 {
         let response = (ErrorResponse::NeedMoreParams {
             command: "WHO".to_string(),
@@ -34,64 +53,110 @@ pub fn handle_who_command(
         return Err(ServerError::InvalidParameters);
     }
     let mut clients_to_display: Vec<String> = vec![];
-    if message.parameters.is_empty() {
-        let mut visible_users: Vec<String> = Vec::new();
-        let channels_lock = read_lock_channels(session)?;
-        for channel in channels_lock.values() {
-            if channel.users.contains(&nickname) {
-                let mut users_aux: Vec<String> = channel.users.clone();
-                visible_users.append(&mut users_aux);
+    let mut forward_query = false;
+    let (channels_lock, clients_lock) = read_lock_channels_and_clients(session)?;
+    let network_clients = read_lock_network_clients(network)?;
+    let visible_users = get_channel_peers(&nickname, &channels_lock);
+    if server_name.is_some() {
+        clients_to_display = clients_lock.keys().cloned().collect();
+        for c in network_clients.keys() {
+            if !clients_to_display.contains(c) {
+                clients_to_display.push(c.to_owned());
             }
         }
-        drop(channels_lock);
-        let visible_users: HashSet<String> = HashSet::from_iter(visible_users);
-        let clients_lock = read_lock_clients(session)?;
+    } else if message.parameters.is_empty() {
         for c in clients_lock.values() {
             if !visible_users.contains(&c.nickname) && !c.modes.contains(&UserFlag::Invisible) {
                 clients_to_display.push(c.nickname.clone());
             }
         }
-        drop(clients_lock);
-    } else if message.parameters[0].starts_with('&') || message.parameters[0].starts_with('#') {
-        let channels_lock = read_lock_channels(session)?;
+        for (n, c) in network_clients.iter() {
+            if !clients_lock.contains_key(n) && !visible_users.contains(n) && !c.is_invisible() {
+                clients_to_display.push(n.to_owned());
+            }
+        }
+    } else if is_channel_name(&message.parameters[0]) {
         let channel_name = message.parameters[0].to_string();
         match channels_lock.get(&channel_name) {
             Some(channel) => {
-                if channel.users.contains(&nickname)
-                    || !channel.modes.contains(&ChannelFlag::Private)
-                {
+                if channel.users.contains(&nickname) {
                     println!("Channel: {}", channel.name);
                     clients_to_display = channel.users.clone();
+                } else if channel.is_visible_to(&nickname) {
+                    println!("Channel: {}", channel.name);
+                    clients_to_display = channel
+                        .users
+                        .iter()
+                        .filter(|u| !is_invisible(u, &clients_lock, &network_clients))
+                        .cloned()
+                        .collect();
                 }
             }
             None => {
+                drop(network_clients);
+                drop(clients_lock);
+                drop(channels_lock);
+                let response = (ErrorResponse::NoSuchChannel {
+                    channel: channel_name,
+                })
+                .to_string();
+                inform_client(session, &nickname, &response)?;
                 return Err(ServerError::ChannelNotFound);
             }
         }
-        drop(channels_lock);
     } else {
-        let clients_lock = read_lock_clients(session)?;
-        for (n, c) in clients_lock.iter() {
-            if *n == message.parameters[0]
-                || c.username == message.parameters[0]
-                || c.hostname == message.parameters[0]
-                || c.servername == message.parameters[0]
-                || c.realname == message.parameters[0]
+        clients_to_display = get_matching_clients(
+            &message.parameters[0],
+            &nickname,
+            &clients_lock,
+            &visible_users,
+        );
+        for (n, c) in network_clients.iter() {
+            if (*n == message.parameters[0] || c.matches_user(&message.parameters[0]))
+                && !clients_lock.contains_key(n)
+                && (visible_users.contains(n) || !c.is_invisible())
             {
-                clients_to_display.push(c.nickname.clone());
+                clients_to_display.push(n.to_owned());
             }
         }
-        drop(clients_lock);
+        forward_query = server_name.is_none();
     }
 
-    let network_clients = network.clients.as_ref().read()?;
-    for c in network_clients.keys().clone() {
-        if !clients_to_display.contains(c) {
-            clients_to_display.push(c.to_owned());
+    // the user information and the modes of the users are sent to the server after the reply,
+    // so it knows who they are and who is invisible
+    let mut sync_messages = vec![];
+    if server_name.is_some() {
+        for c in clients_to_display.iter() {
+            let network_client = match clients_lock.get(c) {
+                Some(client) => RemoteClient::from_client(client),
+                None => match network_clients.get(c) {
+                    Some(network_client) => network_client.to_owned(),
+                    None => continue,
+                },
+            };
+            if !network_client.username.is_empty() {
+                sync_messages.push(network_client.user_message(c));
+            }
+            let modes = network_client.modes;
+            if !modes.is_empty() {
+                sync_messages.push(format!(
+                    ":{} MODE {} {}",
+                    c,
+                    c,
+                    get_user_modes_string(&modes)
+                ));
+            }
         }
     }
     drop(network_clients);
+    drop(clients_lock);
+    drop(channels_lock);
     println!("Matching users: {:?}", clients_to_display);
+    if forward_query
+        && forward_who_query(&message, &nickname, &clients_to_display, session, network)?
+    {
+        return Ok(());
+    }
 
     let response = (CommandResponse::WhoReply {
         users: clients_to_display,
@@ -106,6 +171,9 @@ pub fn handle_who_command(
     let response = CommandResponse::EndOfWho.to_string();
     if let Some(name) = server_name {
         inform_server(network, &name, &response)?;
+        for msg in sync_messages {
+            inform_server(network, &name, &msg)?;
+        }
     } else {
         inform_client(session, &nickname, &response)?;
     }
@@ -113,35 +181,206 @@ pub fn handle_who_command(
     Ok(())
 }
 
+/// Returns the clients of this server whose nickname, username, hostname, servername or realname
+/// is the mask, leaving out the invisible ones that don't share a channel with the client that asks.
+fn get_matching_clients(
+    mask: &str,
+    nickname: &str,
+    clients: &ClientsReadGuard,
+    visible_users: &HashSet<String>,
+) -> Vec<String> {
+    clients
+        .iter()
+        .filter(|(n, c)| {
+            (*n == mask
+                || c.username == mask
+                || c.hostname == mask
+                || c.servername == mask
+                || c.realname == mask)
+                && (*n == nickname
+                    || visible_users.contains(*n)
+                    || !c.modes.contains(&UserFlag::Invisible))
+        })
+        .map(|(_, c)| c.nickname.clone())
+        .collect()
+}
+
+/// Forwards the WHO query of a client to the other servers of the network, which know the
+/// username, hostname and realname of their own clients, and saves it with the users that
+/// matched in this server until every server answers or the time to answer runs out.
+/// Returns false if there are no other servers to ask.
+fn forward_who_query(
+    message: &Message,
+    nickname: &str,
+    users: &[String],
+    session: &Session,
+    network: &Network,
+) -> Result<bool, ServerError> {
+    let servers = read_lock_servers(network)?
+        .keys()
+        .cloned()
+        .collect::<HashSet<_>>();
+    if servers.is_empty() {
+        return Ok(false);
+    }
+    let query = WhoQuery::new(users.to_vec(), servers);
+    let started = query.started;
+    lock_who_queries(session)?.insert(nickname.to_string(), query);
+
+    let mut msg = message.to_owned();
+    msg.set_nick_prefix(nickname);
+    let msg = Message::deserialize(msg)?;
+    inform_network(network, &String::new(), &msg)?;
+
+    let session = session.clone();
+    let nickname = nickname.to_string();
+    thread::spawn(move || {
+        thread::sleep(WHO_QUERY_TIMEOUT);
+        if let Err(e) = answer_who_query(&session, &nickname, started) {
+            println!("Error answering WHO query of {}: {:?}", nickname, e);
+        }
+    });
+    Ok(true)
+}
+
+/// Adds the answer of another server to the WHO query of a client of this server, answering the
+/// client once every server answered. The answer of each server is a WhoReply followed by an
+/// EndOfWho.
+/// Returns false if the response is not part of a query of the client.
+/// # Arguments
+/// * `session` - The session of the current server.
+/// * `nickname` - The nickname of the client that sent the query.
+/// * `server_name` - The name of the server that answered.
+/// * `response` - The response of the server.
+pub fn add_who_answer(
+    session: &Session,
+    nickname: &str,
+    server_name: &str,
+    response: &str,
+) -> Result<bool, ServerError> {
+    let mut queries = lock_who_queries(session)?;
+    let query = match queries.get_mut(nickname) {
+        Some(query) => query,
+        None => return Ok(false),
+    };
+    let complete = match Response::serialize(response.to_string()) {
+        Some(Response::CommandResponse {
+            response: CommandResponse::WhoReply { users },
+        }) => {
+            query.add_users(users);
+            false
+        }
+        Some(Response::CommandResponse {
+            response: CommandResponse::EndOfWho,
+        }) => query.answered_by(server_name),
+        _ => return Ok(false),
+    };
+    let started = query.started;
+    drop(queries);
+    if complete {
+        answer_who_query(session, nickname, started)?;
+    }
+    Ok(true)
+}
+
+/// Sends the users that matched the WHO query of a client and forgets the query, if it is still
+/// the one started at the time received.
+fn answer_who_query(
+    session: &Session,
+    nickname: &str,
+    started: Instant,
+) -> Result<(), ServerError> {
+    let mut queries = lock_who_queries(session)?;
+    let users = match queries.get(nickname) {
+        Some(query) if query.started == started => query.users.to_owned(),
+        _ => return Ok(()),
+    };
+    queries.remove(nickname);
+    drop(queries);
+
+    let nickname = nickname.to_string();
+    let response = CommandResponse::WhoReply { users }.to_string();
+    inform_client(session, &nickname, &response)?;
+    let response = CommandResponse::EndOfWho.to_string();
+    inform_client(session, &nickname, &response)?;
+    Ok(())
+}
+
+/// Handles a WHO query that another server forwarded for one of the clients of the network, like
+/// `:<nickname> WHO <mask>`. The query is forwarded to the rest of the network and answered with
+/// the clients of this server that match it and that the client can see, in a WhoReply and an
+/// EndOfWho sent back towards the client.
+/// # Arguments
+/// * `message` - The message received.
+/// * `server_name` - The name of the server that sent the message.
+/// * `session` - The session of the current server.
+/// * `network` - The struct that contains the information of the network.
+/// # Errors
+/// ServerError::InvalidParameters if the query doesn't have a mask.
+pub fn handle_remote_who_query(
+    message: Message,
+    server_name: &String,
+    session: &Session,
+    network: &Network,
+) -> Result<(), ServerError> {
+    let nickname = message.prefix_name().unwrap_or_default();
+    let mask = match message.parameters.first() {
+        Some(mask) => mask.to_owned(),
+        None => return Err(ServerError::InvalidParameters),
+    };
+    let msg = Message::deserialize(message)?;
+    inform_network(network, server_name, &msg)?;
+
+    let (channels_lock, clients_lock) = read_lock_channels_and_clients(session)?;
+    let visible_users = get_channel_peers(&nickname, &channels_lock);
+    let users = get_matching_clients(&mask, &nickname, &clients_lock, &visible_users);
+    drop(clients_lock);
+    drop(channels_lock);
+
+    let current = read_lock_server(network)?.name.to_owned();
+    let response = CommandResponse::WhoReply { users }.to_string();
+    inform_remote_client(network, &current, &nickname, &response)?;
+    let response = CommandResponse::EndOfWho.to_string();
+    inform_remote_client(network, &current, &nickname, &response)?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod who_tests {
+    use model::consts::MAX_MSG_SIZE;
     use std::collections::HashMap;
     use std::io::Read;
     use std::net::TcpListener;
     use std::sync::{Arc, RwLock};
 
     use model::channel::Channel;
+    use model::channelflag::ChannelFlag;
+    use model::link_queues::LinkQueues;
     use model::message::MessageType;
-    use model::network::Network;
+    use model::network::{Network, RemoteClient};
     use model::persistence::PersistenceType;
     use model::responses::errors::ErrorResponse;
     use model::responses::replies::CommandResponse;
     use model::responses::response::Response;
     use model::server::Server;
+    use model::userflag::UserFlag;
+    use model::who_query::WhoQuery;
+    use std::collections::HashSet;
 
     use crate::commands::command_utils::{
         create_client_for_test, create_message_for_test, create_session_for_test,
-        write_lock_channels,
+        read_response_for_test,
     };
-    use crate::commands::who::handle_who_command;
-    use crate::database::handle_database;
+    use crate::commands::who::{add_who_answer, handle_who_command};
+    use crate::database::{handle_database, DatabasePaths};
+    use crate::locks::{lock_who_queries, write_lock_channels};
     use crate::server_errors::ServerError;
 
     #[test]
     fn test_who_command_invalid_parameters() {
         let listener = TcpListener::bind("127.0.0.1:8132".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8132".to_string(),
@@ -155,10 +394,11 @@ mod who_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, "127.0.0.1:8132".to_string(), "sender".to_string());
@@ -171,7 +411,7 @@ mod who_tests {
         let result = handle_who_command(message, client.nickname, &session, &network, None);
 
         let (mut reader, _addr) = listener.accept().unwrap();
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -201,7 +441,7 @@ mod who_tests {
     fn test_who_command_no_parameters() {
         let listener = TcpListener::bind("127.0.0.1:8133".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8133".to_string(),
@@ -215,10 +455,11 @@ mod who_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, "127.0.0.1:8133".to_string(), "client".to_string());
@@ -246,7 +487,7 @@ mod who_tests {
 
         let result = handle_who_command(message, client.nickname, &session, &network, None);
         let (mut reader, _addr) = listener.accept().unwrap();
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -268,7 +509,7 @@ mod who_tests {
             }
         }
 
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -298,7 +539,7 @@ mod who_tests {
     fn test_who_command_existing_channel() {
         let listener = TcpListener::bind("127.0.0.1:8134".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8134".to_string(),
@@ -312,10 +553,11 @@ mod who_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, "127.0.0.1:8134".to_string(), "client".to_string());
@@ -350,7 +592,7 @@ mod who_tests {
             None,
         );
         let (mut reader, _addr) = listener.accept().unwrap();
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -374,7 +616,7 @@ mod who_tests {
             }
         }
 
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -404,7 +646,7 @@ mod who_tests {
     fn test_who_command_client_nickname() {
         let listener = TcpListener::bind("127.0.0.1:8135".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8135".to_string(),
@@ -418,10 +660,11 @@ mod who_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, "127.0.0.1:8135".to_string(), "client".to_string());
@@ -441,7 +684,7 @@ mod who_tests {
             None,
         );
         let (mut reader, _addr) = listener.accept().unwrap();
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -463,7 +706,7 @@ mod who_tests {
             }
         }
 
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -493,7 +736,7 @@ mod who_tests {
     fn test_who_command_client_username() {
         let listener = TcpListener::bind("127.0.0.1:8136".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8136".to_string(),
@@ -507,10 +750,11 @@ mod who_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, "127.0.0.1:8136".to_string(), "client".to_string());
@@ -525,7 +769,7 @@ mod who_tests {
             None,
         );
         let (mut reader, _addr) = listener.accept().unwrap();
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -547,7 +791,7 @@ mod who_tests {
             }
         }
 
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -577,7 +821,7 @@ mod who_tests {
     fn test_who_command_client_hostname() {
         let listener = TcpListener::bind("127.0.0.1:8137".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8137".to_string(),
@@ -591,10 +835,11 @@ mod who_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, "127.0.0.1:8137".to_string(), "client".to_string());
@@ -609,7 +854,7 @@ mod who_tests {
             None,
         );
         let (mut reader, _addr) = listener.accept().unwrap();
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -631,7 +876,7 @@ mod who_tests {
             }
         }
 
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -661,7 +906,7 @@ mod who_tests {
     fn test_who_command_client_servername() {
         let listener = TcpListener::bind("127.0.0.1:8138".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8138".to_string(),
@@ -675,10 +920,11 @@ mod who_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, "127.0.0.1:8138".to_string(), "client".to_string());
@@ -694,7 +940,7 @@ mod who_tests {
             None,
         );
         let (mut reader, _addr) = listener.accept().unwrap();
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -716,7 +962,7 @@ mod who_tests {
             }
         }
 
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -746,7 +992,7 @@ mod who_tests {
     fn test_who_command_client_realname() {
         let listener = TcpListener::bind("127.0.0.1:8139".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8139".to_string(),
@@ -760,10 +1006,11 @@ mod who_tests {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         let client =
             create_client_for_test(&session, "127.0.0.1:8139".to_string(), "client".to_string());
@@ -778,7 +1025,7 @@ mod who_tests {
             None,
         );
         let (mut reader, _addr) = listener.accept().unwrap();
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -800,7 +1047,7 @@ mod who_tests {
             }
         }
 
-        let mut buf = vec![0u8; 510];
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
         reader.read(&mut buf).unwrap();
         let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
         let response = String::from_utf8(msg).unwrap();
@@ -825,4 +1072,202 @@ mod who_tests {
         drop(listener);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_who_command_invisible_users() {
+        let listener = TcpListener::bind("127.0.0.1:0".to_string()).unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let mut hash_network_clients = HashMap::new();
+        let mut remote_invisible = RemoteClient::new(1);
+        remote_invisible.modes.push(UserFlag::Invisible);
+        hash_network_clients.insert("remote".to_string(), RemoteClient::new(1));
+        hash_network_clients.insert("remote_invisible".to_string(), remote_invisible);
+        let arc_network_clients = Arc::new(RwLock::new(hash_network_clients));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
+        let arc_server = Arc::new(RwLock::new(Server {
+            ip: "0.0.0.0".to_string(),
+            port: "0".to_string(),
+            name: "test".to_string(),
+            operators: vec![],
+            father: None,
+            children: HashMap::new(),
+        }));
+
+        let network = Network {
+            server: arc_server,
+            servers: arc_servers,
+            clients: arc_network_clients,
+            links: LinkQueues::default(),
+        };
+
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        let client = create_client_for_test(&session, addr.to_owned(), "client".to_string());
+        let client2 = create_client_for_test(&session, addr.to_owned(), "client2".to_string());
+        let client3 = create_client_for_test(&session, addr, "client3".to_string());
+        let (mut reader, _addr) = listener.accept().unwrap();
+        let (mut reader2, _addr) = listener.accept().unwrap();
+        let (mut reader3, _addr) = listener.accept().unwrap();
+        session.clients.update(&client2.nickname, |client| {
+            client.modes.push(UserFlag::Invisible)
+        });
+
+        let mut channel = Channel::new("#channel_test".to_string(), "".to_string(), vec![]);
+        channel.users.push(client.nickname.clone());
+        channel.users.push(client2.nickname.clone());
+        channel.users.push("remote_invisible".to_string());
+
+        let mut channels_lock = write_lock_channels(&session).unwrap();
+        channels_lock.insert(channel.name.clone(), channel.clone());
+        drop(channels_lock);
+
+        let message = create_message_for_test(MessageType::Who, vec![]);
+        let result = handle_who_command(message, client.nickname, &session, &network, None);
+        match read_response_for_test(&mut reader) {
+            Response::CommandResponse {
+                response: CommandResponse::WhoReply { users },
+            } => {
+                assert_eq!(users, ["client3".to_string(), "remote".to_string()]);
+            }
+            _ => panic!("Expected the reply of WHO"),
+        }
+        assert!(result.is_ok());
+
+        let message = create_message_for_test(MessageType::Who, vec![channel.name.to_string()]);
+        let result = handle_who_command(message, client3.nickname, &session, &network, None);
+        match read_response_for_test(&mut reader3) {
+            Response::CommandResponse {
+                response: CommandResponse::WhoReply { users },
+            } => {
+                assert_eq!(users, ["client".to_string()]);
+            }
+            _ => panic!("Expected the reply of WHO"),
+        }
+        assert!(result.is_ok());
+
+        let message = create_message_for_test(MessageType::Who, vec![channel.name.to_string()]);
+        let result = handle_who_command(message, client2.nickname, &session, &network, None);
+        match read_response_for_test(&mut reader2) {
+            Response::CommandResponse {
+                response: CommandResponse::WhoReply { users },
+            } => {
+                assert_eq!(users, channel.users);
+            }
+            _ => panic!("Expected the reply of WHO"),
+        }
+
+        drop(listener);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_who_command_secret_channel_only_visible_to_members() {
+        let listener = TcpListener::bind("127.0.0.1:0".to_string()).unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
+        let arc_server = Arc::new(RwLock::new(Server {
+            ip: "0.0.0.0".to_string(),
+            port: "0".to_string(),
+            name: "test".to_string(),
+            operators: vec![],
+            father: None,
+            children: HashMap::new(),
+        }));
+
+        let network = Network {
+            server: arc_server,
+            servers: arc_servers,
+            clients: arc_network_clients,
+            links: LinkQueues::default(),
+        };
+
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        let member = create_client_for_test(&session, addr.to_owned(), "member".to_string());
+        let outsider = create_client_for_test(&session, addr, "outsider".to_string());
+        let (mut member_reader, _addr) = listener.accept().unwrap();
+        let (mut outsider_reader, _addr) = listener.accept().unwrap();
+
+        let mut channel = Channel::new("#secret".to_string(), "".to_string(), vec![]);
+        channel.users.push(member.nickname.clone());
+        channel.modes.push(ChannelFlag::Secret);
+
+        let mut channels_lock = write_lock_channels(&session).unwrap();
+        channels_lock.insert(channel.name.clone(), channel.clone());
+        drop(channels_lock);
+
+        let message = create_message_for_test(MessageType::Who, vec![channel.name.to_string()]);
+        let result = handle_who_command(message, outsider.nickname, &session, &network, None);
+        match read_response_for_test(&mut outsider_reader) {
+            Response::CommandResponse {
+                response: CommandResponse::WhoReply { users },
+            } => {
+                assert!(users.is_empty());
+            }
+            _ => panic!("Expected the reply of WHO"),
+        }
+        assert!(result.is_ok());
+
+        let message = create_message_for_test(MessageType::Who, vec![channel.name.to_string()]);
+        let result = handle_who_command(message, member.nickname, &session, &network, None);
+        match read_response_for_test(&mut member_reader) {
+            Response::CommandResponse {
+                response: CommandResponse::WhoReply { users },
+            } => {
+                assert_eq!(users, channel.users);
+            }
+            _ => panic!("Expected the reply of WHO"),
+        }
+
+        drop(listener);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_who_query_is_answered_once_every_server_answered() {
+        let (tx, _rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        let session = create_session_for_test(tx);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        create_client_for_test(&session, addr, "alice".to_string());
+        let (mut reader, _) = listener.accept().unwrap();
+        let servers = HashSet::from(["child1".to_string(), "child2".to_string()]);
+        lock_who_queries(&session).unwrap().insert(
+            "alice".to_string(),
+            WhoQuery::new(vec!["bob".to_string()], servers),
+        );
+        let reply = CommandResponse::WhoReply {
+            users: vec!["carol".to_string()],
+        }
+        .to_string();
+        let end = CommandResponse::EndOfWho.to_string();
+        let welcome = CommandResponse::Welcome {
+            nickname: "alice".to_string(),
+            username: "alice".to_string(),
+            hostname: "host".to_string(),
+        }
+        .to_string();
+
+        assert!(add_who_answer(&session, "alice", "child1", &reply).unwrap());
+        assert!(add_who_answer(&session, "alice", "child1", &end).unwrap());
+        assert!(!add_who_answer(&session, "alice", "child2", &welcome).unwrap());
+        assert!(lock_who_queries(&session).unwrap().contains_key("alice"));
+        assert!(add_who_answer(&session, "alice", "child2", &end).unwrap());
+
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        let response = Response::serialize(String::from_utf8(msg).unwrap()).unwrap();
+        assert!(matches!(
+            response,
+            Response::CommandResponse {
+                response: CommandResponse::WhoReply { users }
+            } if users == vec!["bob".to_string(), "carol".to_string()]
+        ));
+        assert!(!lock_who_queries(&session).unwrap().contains_key("alice"));
+        assert!(!add_who_answer(&session, "alice", "child2", &end).unwrap());
+    }
 }
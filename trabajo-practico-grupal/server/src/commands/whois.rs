@@ -1,32 +1,41 @@
 use std::collections::HashMap;
 
 use crate::{
-    commands::command_utils::read_lock_channels, server_errors::ServerError, socket::inform_client,
+    locks::{read_lock_channels, read_lock_network_clients, read_lock_server},
+    server_errors::ServerError,
+    socket::{inform_client, inform_client_link, inform_remote_client},
 };
 use model::{
     channelflag::ChannelFlag,
+    consts::{OPERATOR_FLAG, VOICE_FLAG},
     message::Message,
+    network::Network,
     responses::{errors::ErrorResponse, replies::CommandResponse},
     session::Session,
 };
 
-use super::command_utils::read_lock_clients;
+use super::command_utils::get_current_timestamp;
+use crate::locks::read_lock_clients;
 
 /// Handles the WHOIS command.
 /// # Arguments
 /// * `session`: The session of the client that sent the command.
 /// * `message`: The message that contains the command.
 /// * `nickname`: The nickname of the client that sent the command.
+/// * `network`: The struct that contains the information of the network.
 ///
 /// # Errors
 /// * `ErrorResponse::NeedMoreParams`: If the command is not followed by enough parameters. It will send the client a response with the error ErrorResponse::NeedMoreParams.
 /// * `ErrorResponse::NoSuchNick`: If the nickname of the client that sent the command is not registered. It will send the client a response with the error ErrorResponse::NoSuchNick.
 ///
-/// Sends the client a command response with the information of the client that was requested. Sends the command responses WhoIsChannels and WhoIsUser, and EndOfWhoIs
+/// Sends the client a command response with the information of the client that was requested. Sends the command responses WhoIsUser, WhoIsChannels, WhoIsIdle and EndOfWhoIs
+/// Private and secret channels are only listed in WhoIsChannels if the client that sent the command is a member of them.
+/// The queries about the clients of other servers are forwarded towards their servers, which send the responses back.
 pub fn handle_whois_command(
     message: Message,
     nickname: String,
     session: &Session,
+    network: &Network,
 ) -> Result<(), ServerError> {
     if message.parameters.is_empty() {
         let response = ErrorResponse::NeedMoreParams {
@@ -42,77 +51,160 @@ pub fn handle_whois_command(
         .map(|n| n.trim())
         .collect::<Vec<_>>();
     for nick in nicknames {
-        let clients_lock = read_lock_clients(session)?;
-        match clients_lock.get(nick) {
-            Some(c) => {
-                if !c.connected {
-                    println!("Client {} disconnected", nick);
-                    continue;
-                }
-                println!("\nNickname: {}", c.nickname);
-                println!(
-                    "Username: {}, Hostname: {}, Servername: {}, Realname: {}",
-                    c.username, c.hostname, c.servername, c.realname
-                );
-                let response = (CommandResponse::WhoIsUser {
-                    nickname: c.nickname.to_owned(),
-                    username: c.username.to_owned(),
-                    hostname: c.hostname.to_owned(),
-                    servername: c.servername.to_owned(),
-                    realname: c.realname.to_owned(),
-                })
-                .to_string();
+        if let Some(responses) = get_whois_responses(nick, &nickname, session)? {
+            for response in responses {
                 inform_client(session, &nickname, response.as_str())?;
+            }
+            continue;
+        }
+        let query = format!(":{} WHOIS {}", nickname, nick);
+        if !inform_client_link(network, nick, &query)? {
+            println!("Client {} not found", nick);
+            let response = ErrorResponse::NoSuchNick {
+                nickname: nick.to_string(),
+            }
+            .to_string();
+            inform_client(session, &nickname, &response)?;
+            return Err(ServerError::ClientNotFound);
+        }
+    }
 
-                print!("Channels: ");
-                let mut channels_hash = HashMap::new();
-                let channels_lock = read_lock_channels(session)?;
-                for channel in channels_lock.values() {
-                    if channel.users.contains(&c.nickname) {
-                        print!("{}", channel.name);
-                        if channel.operators.contains(&c.nickname.clone())
-                            && channel.modes.contains(&ChannelFlag::ModeratedChannel)
-                            && channel.moderators.contains(&c.nickname.clone())
-                        {
-                            print!("[operator]");
-                            print!("+");
-                            channels_hash.insert(channel.name.to_string(), "@+".to_string());
-                        } else if channel.operators.contains(&c.nickname.clone()) {
-                            channels_hash.insert(channel.name.to_string(), "@".to_string());
-                        } else if channel.modes.contains(&ChannelFlag::ModeratedChannel)
-                            && channel.moderators.contains(&c.nickname.clone())
-                        {
-                            channels_hash.insert(channel.name.to_string(), "+".to_string());
-                        } else {
-                            channels_hash.insert(channel.name.to_string(), "".to_string());
-                        }
-                        print!(" ");
-                    }
-                }
-                drop(channels_lock);
+    Ok(())
+}
 
-                if !channels_hash.is_empty() {
-                    let response = (CommandResponse::WhoIsChannels {
-                        nickname: c.nickname.to_owned(),
-                        channels: channels_hash,
-                    })
-                    .to_string();
-                    inform_client(session, &nickname, response.as_str())?;
-                }
+/// Handles a WHOIS query that another server forwarded for one of the clients of the network,
+/// like `:<nickname> WHOIS <target>`. If the target is a client of this server the responses are
+/// sent back towards the client that asked, otherwise the query is forwarded towards the server
+/// of the target.
+/// # Arguments
+/// * `message` - The message received.
+/// * `server_name` - The name of the server that sent the message.
+/// * `session` - The session of the current server.
+/// * `network` - The struct that contains the information of the network.
+/// # Errors
+/// ServerError::InvalidParameters if the query doesn't have a target.
+pub fn handle_remote_whois_query(
+    message: Message,
+    server_name: &str,
+    session: &Session,
+    network: &Network,
+) -> Result<(), ServerError> {
+    let nickname = message.prefix_name().unwrap_or_default();
+    let target = match message.parameters.first() {
+        Some(target) => target.to_owned(),
+        None => return Err(ServerError::InvalidParameters),
+    };
+    let responses = match get_whois_responses(&target, &nickname, session)? {
+        Some(responses) => responses,
+        None => {
+            // the query is never sent back to the server it came from
+            let msg = Message::deserialize(message)?;
+            if !is_link_of(network, &target, server_name)?
+                && inform_client_link(network, &target, &msg)?
+            {
+                return Ok(());
+            }
+            vec![ErrorResponse::NoSuchNick { nickname: target }.to_string()]
+        }
+    };
+    let current = read_lock_server(network)?.name.to_owned();
+    for response in responses {
+        inform_remote_client(network, &current, &nickname, &response)?;
+    }
+    Ok(())
+}
 
-                let response = CommandResponse::EndOfWhoIs.to_string();
-                inform_client(session, &nickname, response.as_str())?;
+/// Returns true if the client is reached through the linked server received.
+fn is_link_of(network: &Network, nickname: &str, server_name: &str) -> Result<bool, ServerError> {
+    Ok(read_lock_network_clients(network)?
+        .get(nickname)
+        .and_then(|network_client| network_client.link.as_deref())
+        == Some(server_name))
+}
 
-                println!("\n");
-            }
-            None => {
-                println!("Client {} not found", nick);
-                return Err(ServerError::ClientNotFound);
+/// Returns the responses of WHOIS about a client of this server for the client that asks, none
+/// if it is not a client of this server. A client that is disconnected has no responses.
+/// # Arguments
+/// * `nick` - The nickname of the client asked about.
+/// * `nickname` - The nickname of the client that asks.
+/// * `session` - The session of the current server.
+fn get_whois_responses(
+    nick: &str,
+    nickname: &str,
+    session: &Session,
+) -> Result<Option<Vec<String>>, ServerError> {
+    let clients_lock = read_lock_clients(session)?;
+    let c = match clients_lock.get(nick) {
+        Some(c) => c,
+        None => return Ok(None),
+    };
+    if !c.connected {
+        println!("Client {} disconnected", nick);
+        return Ok(Some(vec![]));
+    }
+    println!("\nNickname: {}", c.nickname);
+    println!(
+        "Username: {}, Hostname: {}, Servername: {}, Realname: {}",
+        c.username, c.hostname, c.servername, c.realname
+    );
+    let mut responses = vec![(CommandResponse::WhoIsUser {
+        nickname: c.nickname.to_owned(),
+        username: c.username.to_owned(),
+        hostname: c.hostname.to_owned(),
+        servername: c.servername.to_owned(),
+        realname: c.realname.to_owned(),
+    })
+    .to_string()];
+
+    print!("Channels: ");
+    let mut channels_hash = HashMap::new();
+    let channels_lock = read_lock_channels(session)?;
+    for channel in channels_lock.values() {
+        if channel.users.contains(&c.nickname) && channel.is_visible_to(nickname) {
+            print!("{}", channel.name);
+            if channel.operators.contains(&c.nickname.clone())
+                && channel.modes.contains(&ChannelFlag::ModeratedChannel)
+                && channel.moderators.contains(&c.nickname.clone())
+            {
+                print!("[operator]");
+                print!("+");
+                channels_hash.insert(
+                    channel.name.to_string(),
+                    format!("{}{}", OPERATOR_FLAG, VOICE_FLAG),
+                );
+            } else if channel.operators.contains(&c.nickname.clone()) {
+                channels_hash.insert(channel.name.to_string(), OPERATOR_FLAG.to_string());
+            } else if channel.modes.contains(&ChannelFlag::ModeratedChannel)
+                && channel.moderators.contains(&c.nickname.clone())
+            {
+                channels_hash.insert(channel.name.to_string(), VOICE_FLAG.to_string());
+            } else {
+                channels_hash.insert(channel.name.to_string(), "".to_string());
             }
-        };
+            print!(" ");
+        }
+    }
+    drop(channels_lock);
 
-        drop(clients_lock);
+    if !channels_hash.is_empty() {
+        responses.push(
+            (CommandResponse::WhoIsChannels {
+                nickname: c.nickname.to_owned(),
+                channels: channels_hash,
+            })
+            .to_string(),
+        );
     }
 
-    Ok(())
+    responses.push(
+        (CommandResponse::WhoIsIdle {
+            nickname: c.nickname.to_owned(),
+            idle: c.get_idle_time(get_current_timestamp()),
+            signon: c.signon,
+        })
+        .to_string(),
+    );
+    responses.push(CommandResponse::EndOfWhoIs.to_string());
+    println!("\n");
+    Ok(Some(responses))
 }
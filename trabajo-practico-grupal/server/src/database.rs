@@ -1,11 +1,77 @@
-use std::{io::Write, sync::mpsc::Receiver};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::Receiver,
+    },
+};
 
 use model::{persistence::PersistenceType, session::Session};
+use serde_json::Value;
 
 use crate::server_errors::ServerError;
 
-static CLIENTS_PATH: &str = "server/rsc/clients.txt";
-static CHANNELS_PATH: &str = "server/rsc/channels.txt";
+static DEFAULT_DIRECTORY: &str = "server/rsc";
+static CLIENTS_FILE: &str = "clients.txt";
+static CHANNELS_FILE: &str = "channels.txt";
+static CONFIG_FILE: &str = "config.txt";
+static AUDIT_FILE: &str = "audit.txt";
+static ARCHIVE_FILE: &str = "archive.txt";
+static TEMPORARY_DIRECTORIES: AtomicUsize = AtomicUsize::new(0);
+
+/// Struct with the location of the files where the server keeps its state,
+/// so servers running at the same time do not share them.
+/// # Fields
+/// * `clients` - The file of the clients.
+/// * `channels` - The file of the channels.
+/// * `config` - The file of the configuration of the server.
+/// * `audit` - The file of the actions of the operators, which is only appended to.
+/// * `archive` - The file of the clients removed after being disconnected for too long, which is only appended to.
+#[derive(Debug, Clone)]
+pub struct DatabasePaths {
+    pub clients: PathBuf,
+    pub channels: PathBuf,
+    pub config: PathBuf,
+    pub audit: PathBuf,
+    pub archive: PathBuf,
+}
+
+impl DatabasePaths {
+    /// Returns the paths of the files inside the directory received.
+    pub fn new(directory: &Path) -> DatabasePaths {
+        DatabasePaths {
+            clients: directory.join(CLIENTS_FILE),
+            channels: directory.join(CHANNELS_FILE),
+            config: directory.join(CONFIG_FILE),
+            audit: directory.join(AUDIT_FILE),
+            archive: directory.join(ARCHIVE_FILE),
+        }
+    }
+
+    /// Creates a new directory inside the temporary directory of the system,
+    /// with empty files of clients and channels, and returns its paths.
+    /// Each call returns a different directory, so every test has its own database.
+    pub fn temporary() -> Result<DatabasePaths, ServerError> {
+        let directory = std::env::temp_dir().join(format!(
+            "irc-server-{}-{}",
+            std::process::id(),
+            TEMPORARY_DIRECTORIES.fetch_add(1, Ordering::SeqCst)
+        ));
+        std::fs::create_dir_all(&directory)?;
+        let paths = DatabasePaths::new(&directory);
+        std::fs::File::create(&paths.clients)?;
+        std::fs::File::create(&paths.channels)?;
+        Ok(paths)
+    }
+}
+
+impl Default for DatabasePaths {
+    /// Returns the paths of the files of the repository, used when no directory is given.
+    fn default() -> Self {
+        DatabasePaths::new(Path::new(DEFAULT_DIRECTORY))
+    }
+}
 
 /// Function that sendes the action and the data to
 /// be done by the database
@@ -26,10 +92,11 @@ pub fn inform_database(
 /// Function that receives the action and data to be done by the database
 /// # Arguments
 /// * `rx` - The receiver of the database
-pub fn handle_database(rx: Receiver<(PersistenceType, String)>) {
+/// * `paths` - The files where the database saves the data
+pub fn handle_database(rx: Receiver<(PersistenceType, String)>, paths: DatabasePaths) {
     std::thread::spawn(move || {
         while let Ok((persistence_type, data)) = rx.recv() {
-            match handle_persistence(persistence_type, data) {
+            match handle_persistence(persistence_type, data, &paths) {
                 Ok(_) => (),
                 Err(e) => println!("Error handling persistence: {:?}", e),
             }
@@ -41,14 +108,21 @@ pub fn handle_database(rx: Receiver<(PersistenceType, String)>) {
 /// # Arguments
 /// * `persistence_type` - The action to be done by the database
 /// * `data` - The data to be saved by the database
-fn handle_persistence(persystence_type: PersistenceType, data: String) -> Result<(), ServerError> {
+/// * `paths` - The files where the database saves the data
+fn handle_persistence(
+    persystence_type: PersistenceType,
+    data: String,
+    paths: &DatabasePaths,
+) -> Result<(), ServerError> {
     match persystence_type {
-        PersistenceType::ClientSave => persist_client(data)?,
-        PersistenceType::ClientUpdate(id) => update_client(id, data)?,
-        PersistenceType::ClientDelete(id) => delete_client(id)?,
-        PersistenceType::ChannelSave => persist_channel(data)?,
-        PersistenceType::ChannelUpdate(id) => update_channel(id, data)?,
-        PersistenceType::ChannelDelete(id) => delete_channel(id)?,
+        PersistenceType::ClientSave => persist_client(data, paths)?,
+        PersistenceType::ClientUpdate(id) => update_client(id, data, paths)?,
+        PersistenceType::ClientDelete(id) => delete_client(id, paths)?,
+        PersistenceType::ClientArchive(id) => archive_client(id, data, paths)?,
+        PersistenceType::ChannelSave => persist_channel(data, paths)?,
+        PersistenceType::ChannelUpdate(id) => update_channel(id, data, paths)?,
+        PersistenceType::ChannelDelete(id) => delete_channel(id, paths)?,
+        PersistenceType::AuditRecord => append_audit_record(data, paths)?,
     }
     Ok(())
 }
@@ -56,12 +130,13 @@ fn handle_persistence(persystence_type: PersistenceType, data: String) -> Result
 /// Function that saves a new client to the database
 /// # Arguments
 /// * `data` - The data to be saved by the database
-pub fn persist_client(data: String) -> Result<(), ServerError> {
+/// * `paths` - The files of the database
+pub fn persist_client(data: String, paths: &DatabasePaths) -> Result<(), ServerError> {
     let mut file = std::fs::OpenOptions::new()
         .create(false)
         .write(true)
         .append(true)
-        .open(CLIENTS_PATH)?;
+        .open(&paths.clients)?;
     file.write_all(data.as_bytes())?;
     file.write_all("\n".as_bytes())?;
     Ok(())
@@ -71,8 +146,9 @@ pub fn persist_client(data: String) -> Result<(), ServerError> {
 /// # Arguments
 /// * `id` - The nickname that identifies the client to be updated
 /// * `data` - The data to be saved by the database
-pub fn update_client(id: String, data: String) -> Result<(), ServerError> {
-    let clients_str = std::fs::read_to_string(CLIENTS_PATH)?;
+/// * `paths` - The files of the database
+pub fn update_client(id: String, data: String, paths: &DatabasePaths) -> Result<(), ServerError> {
+    let clients_str = std::fs::read_to_string(&paths.clients)?;
     let mut clients = clients_str
         .split('\n')
         .map(|x| x.to_string())
@@ -81,16 +157,16 @@ pub fn update_client(id: String, data: String) -> Result<(), ServerError> {
         clients.pop();
     }
     for client in &mut clients {
-        if client.split(';').collect::<Vec<_>>()[0] == id {
+        if record_id(client, "nickname") == id {
             *client = data;
             break;
         }
     }
-    std::fs::remove_file(CLIENTS_PATH)?;
+    std::fs::remove_file(&paths.clients)?;
     let mut file = std::fs::OpenOptions::new()
         .create_new(true)
         .write(true)
-        .open(CLIENTS_PATH)?;
+        .open(&paths.clients)?;
     file.write_all(clients.join("\n").as_bytes())?;
     file.write_all("\n".as_bytes())?;
     Ok(())
@@ -99,37 +175,55 @@ pub fn update_client(id: String, data: String) -> Result<(), ServerError> {
 /// Function that deletes a client from the database
 /// # Arguments
 /// * `id` - The nickname that identifies the client to be deleted
-pub fn delete_client(id: String) -> Result<(), ServerError> {
-    let clients_str = std::fs::read_to_string(CLIENTS_PATH)?;
+/// * `paths` - The files of the database
+pub fn delete_client(id: String, paths: &DatabasePaths) -> Result<(), ServerError> {
+    let clients_str = std::fs::read_to_string(&paths.clients)?;
     let mut clients = clients_str.split('\n').collect::<Vec<_>>();
     if clients.len() > 1 {
         clients.pop();
     }
     for i in 0..clients.len() {
-        if clients[i].split(';').collect::<Vec<_>>()[0] == id {
+        if record_id(clients[i], "nickname") == id {
             clients.remove(i);
             break;
         }
     }
-    std::fs::remove_file(CLIENTS_PATH)?;
+    std::fs::remove_file(&paths.clients)?;
     let mut file = std::fs::OpenOptions::new()
         .create_new(true)
         .write(true)
-        .open(CLIENTS_PATH)?;
+        .open(&paths.clients)?;
     file.write_all(clients.join("\n").as_bytes())?;
     file.write_all("\n".as_bytes())?;
     Ok(())
 }
 
+/// Function that appends a client to the archive, creating the file if it doesn't exist,
+/// and deletes it from the database
+/// # Arguments
+/// * `id` - The nickname that identifies the client to be archived
+/// * `data` - The record of the client
+/// * `paths` - The files of the database
+pub fn archive_client(id: String, data: String, paths: &DatabasePaths) -> Result<(), ServerError> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&paths.archive)?;
+    file.write_all(data.as_bytes())?;
+    file.write_all("\n".as_bytes())?;
+    delete_client(id, paths)
+}
+
 /// Function that saves a new channel to the database
 /// # Arguments
 /// * `data` - The data to be saved by the database
-pub fn persist_channel(data: String) -> Result<(), ServerError> {
+/// * `paths` - The files of the database
+pub fn persist_channel(data: String, paths: &DatabasePaths) -> Result<(), ServerError> {
     let mut file = std::fs::OpenOptions::new()
         .create(false)
         .write(true)
         .append(true)
-        .open(CHANNELS_PATH)?;
+        .open(&paths.channels)?;
     file.write_all(data.as_bytes())?;
     file.write_all("\n".as_bytes())?;
     Ok(())
@@ -139,8 +233,9 @@ pub fn persist_channel(data: String) -> Result<(), ServerError> {
 /// # Arguments
 /// * `id` - The name that identifies the channel to be updated
 /// * `data` - The data to be saved by the database
-pub fn update_channel(id: String, data: String) -> Result<(), ServerError> {
-    let channels_str = std::fs::read_to_string(CHANNELS_PATH)?;
+/// * `paths` - The files of the database
+pub fn update_channel(id: String, data: String, paths: &DatabasePaths) -> Result<(), ServerError> {
+    let channels_str = std::fs::read_to_string(&paths.channels)?;
     let mut channels = channels_str
         .split('\n')
         .map(|x| x.to_string())
@@ -149,16 +244,16 @@ pub fn update_channel(id: String, data: String) -> Result<(), ServerError> {
         channels.pop();
     }
     for channel in &mut channels {
-        if channel.split(';').collect::<Vec<_>>()[0] == id {
+        if record_id(channel, "name") == id {
             *channel = data;
             break;
         }
     }
-    std::fs::remove_file(CHANNELS_PATH)?;
+    std::fs::remove_file(&paths.channels)?;
     let mut file = std::fs::OpenOptions::new()
         .create_new(true)
         .write(true)
-        .open(CHANNELS_PATH)?;
+        .open(&paths.channels)?;
     file.write_all(channels.join("\n").as_bytes())?;
     file.write_all("\n".as_bytes())?;
     Ok(())
@@ -167,24 +262,140 @@ pub fn update_channel(id: String, data: String) -> Result<(), ServerError> {
 /// Function that deletes a channel from the database
 /// # Arguments
 /// * `id` - The name that identifies the channel to be deleted
-pub fn delete_channel(id: String) -> Result<(), ServerError> {
-    let channels_str = std::fs::read_to_string(CHANNELS_PATH)?;
+/// * `paths` - The files of the database
+pub fn delete_channel(id: String, paths: &DatabasePaths) -> Result<(), ServerError> {
+    let channels_str = std::fs::read_to_string(&paths.channels)?;
     let mut channels = channels_str.split('\n').collect::<Vec<_>>();
     if channels.len() > 1 {
         channels.pop();
     }
     for i in 0..channels.len() {
-        if channels[i].split(';').collect::<Vec<_>>()[0] == id {
+        if record_id(channels[i], "name") == id {
             channels.remove(i);
             break;
         }
     }
-    std::fs::remove_file(CHANNELS_PATH)?;
+    std::fs::remove_file(&paths.channels)?;
     let mut file = std::fs::OpenOptions::new()
         .create_new(true)
         .write(true)
-        .open(CHANNELS_PATH)?;
+        .open(&paths.channels)?;
     file.write_all(channels.join("\n").as_bytes())?;
     file.write_all("\n".as_bytes())?;
     Ok(())
 }
+
+/// Function that appends a record to the audit log, creating the file if it doesn't exist.
+/// The records already written are never changed.
+/// # Arguments
+/// * `data` - The record to be appended
+/// * `paths` - The files of the database
+pub fn append_audit_record(data: String, paths: &DatabasePaths) -> Result<(), ServerError> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&paths.audit)?;
+    file.write_all(data.as_bytes())?;
+    file.write_all("\n".as_bytes())?;
+    Ok(())
+}
+
+/// Returns the field that identifies the record of a line of the database.
+/// The records not migrated to JSON yet are identified by their first field.
+/// # Arguments
+/// * `record` - The line of the record.
+/// * `key` - The field that identifies the records, like the nickname of the clients.
+fn record_id(record: &str, key: &str) -> String {
+    match serde_json::from_str::<Value>(record) {
+        Ok(json) => json
+            .get(key)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        Err(_) => record.split(';').next().unwrap_or_default().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod database_tests {
+    use super::{
+        append_audit_record, archive_client, delete_channel, delete_client, persist_channel,
+        persist_client, update_channel, update_client, DatabasePaths,
+    };
+
+    #[test]
+    fn test_temporary_paths_are_different_and_start_empty() {
+        let first = DatabasePaths::temporary().unwrap();
+        let second = DatabasePaths::temporary().unwrap();
+
+        assert_ne!(first.clients, second.clients);
+        assert_eq!(std::fs::read_to_string(&first.clients).unwrap(), "");
+        assert_eq!(std::fs::read_to_string(&second.channels).unwrap(), "");
+    }
+
+    #[test]
+    fn test_clients_are_saved_updated_and_deleted_in_the_paths_received() {
+        let paths = DatabasePaths::temporary().unwrap();
+
+        persist_client("alice;a".to_string(), &paths).unwrap();
+        persist_client("bob;b".to_string(), &paths).unwrap();
+        update_client("alice".to_string(), "alice;c".to_string(), &paths).unwrap();
+        delete_client("bob".to_string(), &paths).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&paths.clients).unwrap(),
+            "alice;c\n"
+        );
+    }
+
+    #[test]
+    fn test_json_and_legacy_records_are_identified_by_their_name() {
+        let paths = DatabasePaths::temporary().unwrap();
+
+        persist_channel("#old;topic;alice;;;alice;;;".to_string(), &paths).unwrap();
+        persist_channel(r##"{"name":"#rust","topic":"a"}"##.to_string(), &paths).unwrap();
+        persist_channel(r##"{"name":"#go","topic":"b"}"##.to_string(), &paths).unwrap();
+        update_channel(
+            "#rust".to_string(),
+            r##"{"name":"#rust","topic":"c"}"##.to_string(),
+            &paths,
+        )
+        .unwrap();
+        delete_channel("#old".to_string(), &paths).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&paths.channels).unwrap(),
+            "{\"name\":\"#rust\",\"topic\":\"c\"}\n{\"name\":\"#go\",\"topic\":\"b\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_audit_records_are_appended_to_a_new_file() {
+        let paths = DatabasePaths::temporary().unwrap();
+        assert!(!paths.audit.exists());
+
+        append_audit_record("first".to_string(), &paths).unwrap();
+        append_audit_record("second".to_string(), &paths).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&paths.audit).unwrap(),
+            "first\nsecond\n"
+        );
+    }
+
+    #[test]
+    fn test_archived_clients_are_moved_out_of_the_database() {
+        let paths = DatabasePaths::temporary().unwrap();
+        assert!(!paths.archive.exists());
+
+        persist_client("alice;a".to_string(), &paths).unwrap();
+        persist_client("bob;b".to_string(), &paths).unwrap();
+        archive_client("bob".to_string(), "bob;b".to_string(), &paths).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&paths.clients).unwrap(),
+            "alice;a\n"
+        );
+        assert_eq!(std::fs::read_to_string(&paths.archive).unwrap(), "bob;b\n");
+    }
+}
@@ -0,0 +1,267 @@
+use std::{thread, time::Duration};
+
+use model::{
+    consts::NETWORK_CHANNEL_PREFIX,
+    json::ToJson,
+    message::{Message, MessageType},
+    network::Network,
+    persistence::PersistenceType,
+    responses::message::MessageResponse,
+    session::Session,
+};
+
+use crate::locks::{read_lock_config, write_lock_channels_and_clients};
+use crate::{
+    commands::{command_utils::get_current_timestamp, mode::transfer_channel_operator},
+    database::inform_database,
+    server_errors::ServerError,
+    socket::{inform_client, inform_network},
+};
+
+static GC_SWEEP_INTERVAL: u64 = 3600;
+static SECONDS_PER_DAY: u64 = 86400;
+static ARCHIVED_PART_MESSAGE: &str = "Archived after being disconnected for too long";
+
+/// Function that periodically removes the empty channels and archives the clients that have
+/// been disconnected for too long.
+/// # Arguments
+/// * `session` - The session of the current server.
+/// * `network` - The network of the current server.
+pub fn handle_garbage_collection(session: Session, network: Network) {
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(GC_SWEEP_INTERVAL));
+        if let Err(e) = collect_garbage(&session, &network, get_current_timestamp()) {
+            println!("Error collecting garbage: {:?}", e);
+        }
+    });
+}
+
+/// Archives the clients of this server disconnected for the retention days of the
+/// configuration, and then removes the empty channels that are not permanent. The pass is
+/// counted in the stats of the session, and the amounts of channels removed and clients
+/// archived are returned.
+/// The archived clients leave their channels: the members of this server are informed, and
+/// so is the network when the channel is distributed.
+/// # Arguments
+/// * `session` - The session of the current server.
+/// * `network` - The network of the current server.
+/// * `now` - The current time, in seconds since the epoch.
+pub fn collect_garbage(
+    session: &Session,
+    network: &Network,
+    now: u64,
+) -> Result<(usize, usize), ServerError> {
+    let retention = read_lock_config(session)?.client_retention_days * SECONDS_PER_DAY;
+
+    let (mut channels, mut clients) = write_lock_channels_and_clients(session)?;
+    let archived = clients
+        .values()
+        .filter(|client| {
+            retention > 0
+                && !client.connected
+                && now.saturating_sub(client.last_activity) >= retention
+        })
+        .map(|client| client.nickname.to_owned())
+        .collect::<Vec<_>>();
+    for nickname in archived.iter() {
+        if let Some(client) = clients.remove(nickname) {
+            println!("Client {} archived", nickname);
+            inform_database(
+                PersistenceType::ClientArchive(nickname.to_owned()),
+                client.to_json(),
+                session,
+            )?;
+        }
+    }
+    drop(clients);
+
+    let mut parts = vec![];
+    for channel in channels.values_mut() {
+        for nickname in archived.iter() {
+            if !channel.remove_member(nickname) {
+                continue;
+            }
+            channel.operators.retain(|operator| operator != nickname);
+            channel.moderators.retain(|moderator| moderator != nickname);
+            inform_database(
+                PersistenceType::ChannelUpdate(channel.name.to_owned()),
+                channel.to_json(),
+                session,
+            )?;
+            let response = MessageResponse::PartMsg {
+                channel: channel.name.to_owned(),
+                nickname: nickname.to_owned(),
+            }
+            .to_string();
+            for user in channel.users.iter() {
+                inform_client(session, user, &response)?;
+            }
+            transfer_channel_operator(channel, nickname, session, |_| true)?;
+            if channel.name.starts_with(NETWORK_CHANNEL_PREFIX) {
+                parts.push((channel.name.to_owned(), nickname.to_owned()));
+            }
+        }
+    }
+    let empty = channels
+        .values()
+        .filter(|channel| channel.users.is_empty() && !channel.is_permanent())
+        .map(|channel| channel.name.to_owned())
+        .collect::<Vec<_>>();
+    for name in empty.iter() {
+        if let Some(channel) = channels.remove(name) {
+            println!("Empty channel {} removed", name);
+            inform_database(
+                PersistenceType::ChannelDelete(name.to_owned()),
+                channel.to_json(),
+                session,
+            )?;
+        }
+    }
+    drop(channels);
+
+    for (channel, nickname) in parts {
+        let mut message = Message::new(
+            None,
+            MessageType::Part,
+            vec![channel],
+            Some(ARCHIVED_PART_MESSAGE.to_string()),
+        );
+        message.set_nick_prefix(&nickname);
+        inform_network(network, &String::new(), &Message::deserialize(message)?)?;
+    }
+
+    session
+        .gc_stats
+        .record_pass(empty.len(), archived.len(), now);
+    Ok((empty.len(), archived.len()))
+}
+
+#[cfg(test)]
+mod gc_tests {
+    use model::consts::MAX_MSG_SIZE;
+    use std::{
+        collections::HashMap,
+        io::Read,
+        net::TcpListener,
+        sync::{Arc, RwLock},
+    };
+
+    use model::{
+        channel::Channel,
+        channelflag::ChannelFlag,
+        link_queues::LinkQueues,
+        network::Network,
+        persistence::PersistenceType,
+        responses::{message::MessageResponse, response::Response},
+        server::Server,
+    };
+
+    use crate::locks::{read_lock_channels, write_lock_channels, write_lock_config};
+    use crate::{
+        commands::command_utils::{create_client_for_test, create_session_for_test},
+        database::{handle_database, DatabasePaths},
+        gc::collect_garbage,
+    };
+
+    static DAY: u64 = 86400;
+
+    fn create_network_for_test() -> Network {
+        Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "127.0.0.1".to_string(),
+                port: "8080".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        }
+    }
+
+    fn insert_channel_for_test(
+        session: &model::session::Session,
+        name: &str,
+        users: &[&str],
+        modes: Vec<ChannelFlag>,
+    ) {
+        let users = users.iter().map(|user| user.to_string()).collect();
+        let mut channel = Channel::new(name.to_string(), "".to_string(), users);
+        channel.modes = modes;
+        write_lock_channels(session)
+            .unwrap()
+            .insert(name.to_string(), channel);
+    }
+
+    #[test]
+    fn test_clients_disconnected_for_too_long_are_archived_and_leave_their_channels() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr.to_owned(), "member".to_string());
+        let (mut reader, _) = listener.accept().unwrap();
+        let now = 40 * DAY;
+        for (nickname, last_activity) in [("gone", 0), ("recent", now - DAY)] {
+            create_client_for_test(&session, addr.to_owned(), nickname.to_string());
+            session.clients.update(nickname, |client| {
+                client.connected = false;
+                client.last_activity = last_activity;
+            });
+        }
+        insert_channel_for_test(&session, "#shared", &["member", "gone"], vec![]);
+        insert_channel_for_test(&session, "&solo", &["gone"], vec![]);
+        insert_channel_for_test(&session, "&kept", &["gone"], vec![ChannelFlag::Permanent]);
+
+        let result = collect_garbage(&session, &create_network_for_test(), now);
+
+        assert_eq!(result, Ok((1, 1)));
+        assert!(!session.clients.contains("gone"));
+        assert!(session.clients.contains("recent"));
+        let channels = read_lock_channels(&session).unwrap();
+        assert_eq!(channels["#shared"].users, vec!["member"]);
+        assert!(!channels.contains_key("&solo"));
+        assert!(channels["&kept"].users.is_empty());
+        drop(channels);
+        assert_eq!(session.gc_stats.totals().clients_archived, 1);
+
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        match Response::serialize(String::from_utf8(msg).unwrap()).unwrap() {
+            Response::MessageResponse {
+                response: MessageResponse::PartMsg { channel, nickname },
+            } => {
+                assert_eq!(channel, "#shared");
+                assert_eq!(nickname, "gone");
+            }
+            _ => panic!("Expected the part of the archived client"),
+        }
+    }
+
+    #[test]
+    fn test_clients_are_kept_without_retention_days() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr, "gone".to_string());
+        session.clients.update("gone", |client| {
+            client.connected = false;
+            client.last_activity = 0;
+        });
+        write_lock_config(&session).unwrap().client_retention_days = 0;
+        insert_channel_for_test(&session, "&empty", &[], vec![]);
+
+        let result = collect_garbage(&session, &create_network_for_test(), 400 * DAY);
+
+        assert_eq!(result, Ok((1, 0)));
+        assert!(session.clients.contains("gone"));
+        assert!(!session.channels.contains("&empty"));
+        assert_eq!(session.gc_stats.totals().passes, 1);
+    }
+}
@@ -0,0 +1,103 @@
+use std::{
+    net::TcpStream,
+    time::{Duration, Instant},
+};
+
+use crate::server_errors::ServerError;
+
+/// The time a new connection has to register before it is dropped.
+pub static HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(30);
+/// The messages that can't be parsed or are refused a new connection may send before it is dropped.
+pub static MAX_HANDSHAKE_ATTEMPTS: usize = 3;
+
+/// Struct that bounds the registration of a new connection, from its first message until the
+/// client is registered. The connection has a deadline for all of its messages, instead of a
+/// read timeout for each one, and a number of messages it can get wrong.
+/// # Fields
+/// * `deadline` - The moment the connection is dropped if it is not registered yet.
+/// * `attempts` - The messages that can't be parsed or were refused so far.
+/// * `max_attempts` - The messages that can be wrong before the connection is dropped.
+#[derive(Debug, Clone)]
+pub struct Handshake {
+    deadline: Instant,
+    attempts: usize,
+    max_attempts: usize,
+}
+
+impl Handshake {
+    /// Starts the handshake of a connection that was just accepted.
+    /// # Arguments
+    /// * `timeout` - The time the connection has to register.
+    /// * `max_attempts` - The messages that can be wrong before the connection is dropped.
+    pub fn new(timeout: Duration, max_attempts: usize) -> Handshake {
+        Handshake {
+            deadline: Instant::now() + timeout,
+            attempts: 0,
+            max_attempts,
+        }
+    }
+
+    /// Sets the read timeout of the socket to the time left until the deadline, so that the
+    /// next read can't wait past it.
+    /// # Arguments
+    /// * `socket` - The socket of the connection.
+    /// # Errors
+    /// Returns `ServerError::SocketTimeout` if the deadline already passed.
+    pub fn set_read_timeout(&self, socket: &TcpStream) -> Result<(), ServerError> {
+        let left = self.deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(ServerError::SocketTimeout);
+        }
+        socket.set_read_timeout(Some(left))?;
+        Ok(())
+    }
+
+    /// Counts a message of the connection that can't be parsed or was refused.
+    /// # Errors
+    /// Returns `ServerError::InvalidMessage` once the connection used all of its attempts.
+    pub fn fail(&mut self) -> Result<(), ServerError> {
+        self.attempts += 1;
+        if self.attempts >= self.max_attempts {
+            return Err(ServerError::InvalidMessage);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod handshake_tests {
+    use std::{
+        net::{TcpListener, TcpStream},
+        thread,
+        time::Duration,
+    };
+
+    use super::Handshake;
+    use crate::server_errors::ServerError;
+
+    #[test]
+    fn test_attempts_are_counted_until_the_limit() {
+        let mut handshake = Handshake::new(Duration::from_secs(30), 3);
+
+        assert_eq!(handshake.fail(), Ok(()));
+        assert_eq!(handshake.fail(), Ok(()));
+        assert_eq!(handshake.fail(), Err(ServerError::InvalidMessage));
+    }
+
+    #[test]
+    fn test_read_timeout_is_the_time_left_until_the_deadline() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let socket = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let handshake = Handshake::new(Duration::from_millis(200), 3);
+
+        handshake.set_read_timeout(&socket).unwrap();
+        let timeout = socket.read_timeout().unwrap().unwrap();
+        thread::sleep(Duration::from_millis(250));
+
+        assert!(timeout <= Duration::from_millis(200));
+        assert_eq!(
+            handshake.set_read_timeout(&socket),
+            Err(ServerError::SocketTimeout)
+        );
+    }
+}
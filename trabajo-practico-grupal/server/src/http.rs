@@ -0,0 +1,358 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, Read, Write},
+};
+
+use crate::server_errors::ServerError;
+
+static MAX_HEADER_LINES: usize = 100;
+static MAX_LINE_SIZE: usize = 8192;
+static MAX_BODY_SIZE: usize = 65536;
+
+/// Struct that represents an HTTP request received by the admin interface.
+/// # Fields
+/// * `method` - The method of the request, like `GET`.
+/// * `path` - The path of the request, without its query.
+/// * `query` - The parameters of the query of the path.
+/// * `headers` - The headers of the request, with their names in lowercase.
+/// * `form` - The parameters of the body, when it is a form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    pub query: HashMap<String, String>,
+    pub headers: HashMap<String, String>,
+    pub form: HashMap<String, String>,
+}
+
+impl HttpRequest {
+    /// Reads a request from the connection.
+    /// Returns ServerError::InvalidRequest if it is not a valid HTTP request or it is too long.
+    /// # Arguments
+    /// * `reader` - The connection the request is read from.
+    pub fn read<R: BufRead>(reader: &mut R) -> Result<HttpRequest, ServerError> {
+        let request_line = read_line(reader)?;
+        let mut parts = request_line.split(' ');
+        let (method, target) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(method), Some(target), Some(version)) if version.starts_with("HTTP/") => {
+                (method.to_string(), target)
+            }
+            _ => return Err(ServerError::InvalidRequest),
+        };
+        let (path, query) = match target.split_once('?') {
+            Some((path, query)) => (path.to_string(), parse_form(query)),
+            None => (target.to_string(), HashMap::new()),
+        };
+
+        let mut headers = HashMap::new();
+        loop {
+            let line = read_line(reader)?;
+            if line.is_empty() {
+                break;
+            }
+            if headers.len() >= MAX_HEADER_LINES {
+                return Err(ServerError::InvalidRequest);
+            }
+            match line.split_once(':') {
+                Some((name, value)) => {
+                    headers.insert(name.trim().to_lowercase(), value.trim().to_string())
+                }
+                None => return Err(ServerError::InvalidRequest),
+            };
+        }
+
+        let length = match headers.get("content-length") {
+            Some(length) => length
+                .parse::<usize>()
+                .map_err(|_| ServerError::InvalidRequest)?,
+            None => 0,
+        };
+        if length > MAX_BODY_SIZE {
+            return Err(ServerError::InvalidRequest);
+        }
+        let mut body = vec![0u8; length];
+        reader.read_exact(&mut body)?;
+        let form = match headers.get("content-type") {
+            Some(kind) if kind.starts_with("application/x-www-form-urlencoded") => {
+                parse_form(&String::from_utf8_lossy(&body))
+            }
+            _ => HashMap::new(),
+        };
+
+        Ok(HttpRequest {
+            method,
+            path,
+            query,
+            headers,
+            form,
+        })
+    }
+
+    /// Returns the parameter of the form of the request, or of its query if the form doesn't have it.
+    /// # Arguments
+    /// * `name` - The name of the parameter.
+    pub fn param(&self, name: &str) -> Option<&String> {
+        self.form.get(name).or_else(|| self.query.get(name))
+    }
+
+    /// Returns the value of a header, by its name in lowercase.
+    /// # Arguments
+    /// * `name` - The name of the header.
+    pub fn header(&self, name: &str) -> Option<&String> {
+        self.headers.get(name)
+    }
+
+    /// Returns the value of a cookie sent in the `Cookie` header of the request.
+    /// # Arguments
+    /// * `name` - The name of the cookie.
+    pub fn cookie(&self, name: &str) -> Option<&str> {
+        self.header("cookie")?
+            .split(';')
+            .filter_map(|pair| pair.trim().split_once('='))
+            .find(|(cookie, _)| *cookie == name)
+            .map(|(_, value)| value)
+    }
+}
+
+/// Struct that represents an HTTP response sent by the admin interface.
+/// # Fields
+/// * `status` - The status code of the response.
+/// * `reason` - The reason phrase of the status.
+/// * `headers` - The headers of the response, besides its length.
+/// * `body` - The body of the response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub reason: &'static str,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl HttpResponse {
+    /// Creates a response with a body of the given type.
+    fn new(status: u16, reason: &'static str, content_type: &str, body: String) -> HttpResponse {
+        HttpResponse {
+            status,
+            reason,
+            headers: vec![("Content-Type".to_string(), content_type.to_string())],
+            body,
+        }
+    }
+
+    /// Creates a successful response with an HTML page.
+    pub fn html(body: String) -> HttpResponse {
+        HttpResponse::new(200, "OK", "text/html; charset=utf-8", body)
+    }
+
+    /// Creates a successful response with a JSON value.
+    pub fn json(body: String) -> HttpResponse {
+        HttpResponse::new(200, "OK", "application/json", body)
+    }
+
+    /// Creates a response that sends the browser to another page after a form is submitted.
+    /// # Arguments
+    /// * `location` - The page the browser is sent to.
+    pub fn redirect(location: &str) -> HttpResponse {
+        let mut response = HttpResponse::new(303, "See Other", "text/plain", "".to_string());
+        response
+            .headers
+            .push(("Location".to_string(), location.to_string()));
+        response
+    }
+
+    /// Creates an error response, with its reason as the body.
+    /// # Arguments
+    /// * `status` - The status code of the error.
+    /// * `reason` - The reason phrase of the status.
+    pub fn error(status: u16, reason: &'static str) -> HttpResponse {
+        HttpResponse::new(status, reason, "text/plain", format!("{}\n", reason))
+    }
+
+    /// Writes the response to the connection, closing it after the body.
+    /// # Arguments
+    /// * `out` - The connection to write to.
+    pub fn write<W: Write>(&self, out: &mut W) -> Result<(), ServerError> {
+        let mut response = format!("HTTP/1.1 {} {}\r\n", self.status, self.reason);
+        for (name, value) in self.headers.iter() {
+            response.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        response.push_str(&format!(
+            "Content-Length: {}\r\nConnection: close\r\n\r\n",
+            self.body.len()
+        ));
+        response.push_str(&self.body);
+        out.write_all(response.as_bytes())?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// Reads a line of the head of the request, without its line ending.
+fn read_line<R: BufRead>(reader: &mut R) -> Result<String, ServerError> {
+    let mut line = vec![];
+    reader
+        .take(MAX_LINE_SIZE as u64)
+        .read_until(b'\n', &mut line)?;
+    if !line.ends_with(b"\n") {
+        return Err(ServerError::InvalidRequest);
+    }
+    let line = String::from_utf8(line).map_err(|_| ServerError::InvalidRequest)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Returns the parameters of a query or a form, like `channel=%23rust&nickname=bob`.
+/// # Arguments
+/// * `text` - The encoded parameters.
+pub fn parse_form(text: &str) -> HashMap<String, String> {
+    text.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((name, value)) => (percent_decode(name), percent_decode(value)),
+            None => (percent_decode(pair), "".to_string()),
+        })
+        .collect()
+}
+
+/// Decodes the `+` and the `%XX` sequences of a parameter of a form.
+/// The invalid sequences are kept as they are.
+/// # Arguments
+/// * `text` - The encoded text.
+pub fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => match hex_value(bytes[i + 1], bytes[i + 2]) {
+                Some(byte) => {
+                    decoded.push(byte);
+                    i += 2;
+                }
+                None => decoded.push(b'%'),
+            },
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Returns the byte written by two hexadecimal digits, if they are.
+fn hex_value(high: u8, low: u8) -> Option<u8> {
+    let high = (high as char).to_digit(16)?;
+    let low = (low as char).to_digit(16)?;
+    Some((high * 16 + low) as u8)
+}
+
+/// Encodes the text so that it can be a parameter of a query.
+/// # Arguments
+/// * `text` - The text to encode.
+pub fn percent_encode(text: &str) -> String {
+    let mut encoded = String::new();
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Escapes the characters of the text that have a meaning in HTML, so that the names and
+/// messages of the users are shown as text.
+/// # Arguments
+/// * `text` - The text to escape.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod http_tests {
+    use std::io::BufReader;
+
+    use super::{escape_html, percent_decode, HttpRequest, HttpResponse};
+    use crate::server_errors::ServerError;
+
+    #[test]
+    fn test_request_is_read_with_its_query_headers_and_form() {
+        let body = "channel=%23rust&nickname=bob+smith";
+        let text = format!(
+            "POST /kick?token=abc HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let request = HttpRequest::read(&mut BufReader::new(text.as_bytes())).unwrap();
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/kick");
+        assert_eq!(request.header("host"), Some(&"localhost".to_string()));
+        assert_eq!(request.param("token"), Some(&"abc".to_string()));
+        assert_eq!(request.param("channel"), Some(&"#rust".to_string()));
+        assert_eq!(request.param("nickname"), Some(&"bob smith".to_string()));
+    }
+
+    #[test]
+    fn test_cookies_are_read_by_their_name() {
+        let text = "GET / HTTP/1.1\r\nCookie: theme=dark; admin_token=a=b\r\n\r\n";
+        let request = HttpRequest::read(&mut BufReader::new(text.as_bytes())).unwrap();
+
+        assert_eq!(request.cookie("theme"), Some("dark"));
+        assert_eq!(request.cookie("admin_token"), Some("a=b"));
+        assert_eq!(request.cookie("token"), None);
+    }
+
+    #[test]
+    fn test_invalid_requests_are_rejected() {
+        for text in [
+            "GET /\r\n\r\n",
+            "GET / HTTP/1.1\r\nHost\r\n\r\n",
+            "GET / HTTP/1.1\r\nContent-Length: 999999\r\n\r\n",
+            "GET / HTTP/1.1\r\nHost: localhost",
+        ] {
+            let result = HttpRequest::read(&mut BufReader::new(text.as_bytes()));
+            assert_eq!(result, Err(ServerError::InvalidRequest));
+        }
+    }
+
+    #[test]
+    fn test_invalid_escapes_are_kept_when_decoding() {
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%41"), "%zzA");
+        assert_eq!(percent_decode("%C3%B1"), "ñ");
+    }
+
+    #[test]
+    fn test_html_is_escaped() {
+        assert_eq!(
+            escape_html("<b onclick=\"x\">&'"),
+            "&lt;b onclick=&quot;x&quot;&gt;&amp;&#39;"
+        );
+    }
+
+    #[test]
+    fn test_response_is_written_with_its_length() {
+        let mut out = vec![];
+        HttpResponse::error(404, "Not Found")
+            .write(&mut out)
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "HTTP/1.1 404 Not Found\r\nContent-Type: text/plain\r\nContent-Length: 10\r\nConnection: close\r\n\r\nNot Found\n"
+        );
+    }
+}
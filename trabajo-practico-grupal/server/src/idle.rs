@@ -0,0 +1,272 @@
+use std::{thread, time::Duration};
+
+use model::{
+    json::ToJson,
+    message::{Message, MessageType},
+    network::Network,
+    persistence::PersistenceType,
+    responses::{replies::CommandResponse, response::Response},
+    session::Session,
+};
+
+use crate::locks::{read_lock_config, write_lock_clients};
+use crate::{
+    commands::{command_utils::get_current_timestamp, quit::handle_quit_command},
+    database::inform_database,
+    server_errors::ServerError,
+    socket::inform_client_response,
+};
+
+static IDLE_SWEEP_INTERVAL: u64 = 30;
+static SECONDS_PER_MINUTE: u64 = 60;
+static AUTO_AWAY_MESSAGE: &str = "Away after being idle";
+static IDLE_QUIT_MESSAGE: &str = "Idle for too long";
+
+/// Function that periodically applies the idle policies of the configuration to the clients.
+/// # Arguments
+/// * `session` - The session of the current server.
+/// * `network` - The network of the current server.
+/// * `server_name` - The name of the current server.
+pub fn handle_idle_clients(session: Session, network: Network, server_name: String) {
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(IDLE_SWEEP_INTERVAL));
+        match apply_idle_policies(&session, &network, &server_name, get_current_timestamp()) {
+            Ok(_) => (),
+            Err(e) => println!("Error applying the idle policies: {:?}", e),
+        }
+    });
+}
+
+/// Marks as away the connected clients that have been idle for the auto away minutes
+/// of the configuration, and disconnects the ones idle for the idle timeout minutes.
+/// A policy with 0 minutes is disabled. Clients that are already away keep their message,
+/// and the ones marked by this policy are no longer away when they send a command.
+/// # Arguments
+/// * `session` - The session of the current server.
+/// * `network` - The network of the current server.
+/// * `server_name` - The name of the current server.
+/// * `now` - The current time, in seconds since the epoch.
+pub fn apply_idle_policies(
+    session: &Session,
+    network: &Network,
+    server_name: &String,
+    now: u64,
+) -> Result<(), ServerError> {
+    let config = read_lock_config(session)?;
+    let auto_away = config.auto_away_minutes * SECONDS_PER_MINUTE;
+    let idle_timeout = config.idle_timeout_minutes * SECONDS_PER_MINUTE;
+    drop(config);
+
+    let mut away = vec![];
+    let mut idle = vec![];
+    let mut clients = write_lock_clients(session)?;
+    for client in clients.values_mut().filter(|c| c.connected) {
+        let idle_time = client.get_idle_time(now);
+        if idle_timeout > 0 && idle_time >= idle_timeout {
+            idle.push(client.nickname.to_owned());
+        } else if auto_away > 0 && idle_time >= auto_away && client.away_message.is_none() {
+            client.away_message = Some(AUTO_AWAY_MESSAGE.to_string());
+            client.auto_away = true;
+            inform_database(
+                PersistenceType::ClientUpdate(client.nickname.to_owned()),
+                client.to_json(),
+                session,
+            )?;
+            away.push(client.nickname.to_owned());
+        }
+    }
+    drop(clients);
+
+    for nickname in away {
+        println!("{} is now away after being idle", nickname);
+        let response = Response::CommandResponse {
+            response: CommandResponse::NowAway,
+        };
+        inform_client_response(session, &nickname, &response)?;
+    }
+    for nickname in idle {
+        println!("Disconnecting {} after being idle", nickname);
+        let message = Message::new(
+            None,
+            MessageType::Quit,
+            vec![],
+            Some(IDLE_QUIT_MESSAGE.to_string()),
+        );
+        handle_quit_command(message, nickname, session, network, server_name)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod idle_tests {
+    use model::consts::MAX_MSG_SIZE;
+    use std::{
+        collections::HashMap,
+        io::Read,
+        net::{TcpListener, TcpStream},
+        sync::{Arc, RwLock},
+    };
+
+    use model::{
+        link_queues::LinkQueues,
+        network::Network,
+        persistence::PersistenceType,
+        responses::{replies::CommandResponse, response::Response},
+        server::Server,
+        session::Session,
+    };
+
+    use crate::locks::{read_lock_clients, write_lock_clients};
+    use crate::{
+        commands::command_utils::{
+            create_client_for_test, create_session_for_test, update_last_activity,
+        },
+        database::{handle_database, DatabasePaths},
+        idle::apply_idle_policies,
+    };
+
+    fn create_network_for_test() -> Network {
+        Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "127.0.0.1".to_string(),
+                port: "8080".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        }
+    }
+
+    fn create_idle_session_for_test(
+        listener: &TcpListener,
+        idle: &[(&str, u64)],
+    ) -> (Session, Vec<TcpStream>) {
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        let mut readers = vec![];
+        for (nickname, last_activity) in idle {
+            create_client_for_test(&session, addr.to_owned(), nickname.to_string());
+            readers.push(listener.accept().unwrap().0);
+            let mut clients = write_lock_clients(&session).unwrap();
+            clients.get_mut(nickname).unwrap().last_activity = *last_activity;
+        }
+        (session, readers)
+    }
+
+    fn read_response(reader: &mut TcpStream) -> Response {
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        Response::serialize(String::from_utf8(msg).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_clients_idle_for_the_auto_away_minutes_are_marked_as_away() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (session, mut readers) =
+            create_idle_session_for_test(&listener, &[("idle", 1000), ("active", 2700)]);
+        session.config.write().unwrap().auto_away_minutes = 30;
+
+        let result = apply_idle_policies(
+            &session,
+            &create_network_for_test(),
+            &"test".to_string(),
+            1000 + 30 * 60,
+        );
+
+        assert!(result.is_ok());
+        let clients = read_lock_clients(&session).unwrap();
+        assert!(clients["idle"].auto_away);
+        assert!(clients["idle"].away_message.is_some());
+        assert!(!clients["active"].auto_away);
+        assert!(clients["active"].away_message.is_none());
+        drop(clients);
+        match read_response(&mut readers[0]) {
+            Response::CommandResponse {
+                response: CommandResponse::NowAway,
+            } => (),
+            _ => panic!("Expected the client to be marked as away"),
+        }
+    }
+
+    #[test]
+    fn test_away_message_of_the_client_is_kept_when_it_is_idle() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (session, _readers) = create_idle_session_for_test(&listener, &[("away", 0)]);
+        let mut clients = write_lock_clients(&session).unwrap();
+        clients.get_mut("away").unwrap().away_message = Some("lunch".to_string());
+        drop(clients);
+
+        let result = apply_idle_policies(
+            &session,
+            &create_network_for_test(),
+            &"test".to_string(),
+            100000,
+        );
+
+        assert!(result.is_ok());
+        let clients = read_lock_clients(&session).unwrap();
+        assert!(!clients["away"].auto_away);
+        assert_eq!(clients["away"].away_message, Some("lunch".to_string()));
+    }
+
+    #[test]
+    fn test_client_marked_as_away_for_being_idle_is_back_when_it_sends_a_command() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (session, mut readers) = create_idle_session_for_test(&listener, &[("idle", 0)]);
+        apply_idle_policies(
+            &session,
+            &create_network_for_test(),
+            &"test".to_string(),
+            100000,
+        )
+        .unwrap();
+        read_response(&mut readers[0]);
+
+        let result = update_last_activity("idle", &session);
+
+        assert!(result.is_ok());
+        let clients = read_lock_clients(&session).unwrap();
+        assert!(!clients["idle"].auto_away);
+        assert!(clients["idle"].away_message.is_none());
+        drop(clients);
+        match read_response(&mut readers[0]) {
+            Response::CommandResponse {
+                response: CommandResponse::UnAway,
+            } => (),
+            _ => panic!("Expected the client to be no longer away"),
+        }
+    }
+
+    #[test]
+    fn test_clients_idle_for_the_idle_timeout_are_disconnected() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (session, mut readers) =
+            create_idle_session_for_test(&listener, &[("idle", 1000), ("active", 4000)]);
+        let mut config = session.config.write().unwrap();
+        config.auto_away_minutes = 0;
+        config.idle_timeout_minutes = 60;
+        drop(config);
+
+        let result = apply_idle_policies(
+            &session,
+            &create_network_for_test(),
+            &"test".to_string(),
+            1000 + 60 * 60,
+        );
+
+        assert!(result.is_ok());
+        let mut buf = [0u8; 1];
+        assert_eq!(readers[0].read(&mut buf).unwrap(), 0);
+        readers[1]
+            .set_read_timeout(Some(std::time::Duration::from_millis(100)))
+            .unwrap();
+        assert!(readers[1].read(&mut buf).is_err());
+    }
+}
@@ -1,9 +1,25 @@
+pub mod admin;
+pub mod audit;
+pub mod ban_expiry;
+pub mod channel_stats;
+pub mod channel_sync;
 pub mod client_handler;
 pub mod commands;
 pub mod database;
+pub mod gc;
+pub mod handshake;
+pub mod http;
+pub mod idle;
 pub mod load;
+pub mod locks;
 pub mod message_handler;
+pub mod password;
+pub mod plugins;
 pub mod registration;
+pub mod run;
 pub mod server_errors;
 pub mod server_handler;
+pub mod server_notices;
+pub mod server_ping;
 pub mod socket;
+pub mod websocket;
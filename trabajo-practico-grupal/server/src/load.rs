@@ -1,138 +1,350 @@
-use crate::server_errors::ServerError;
-use model::{channel::Channel, channelflag::ChannelFlag, client::Client, userflag::UserFlag};
+use crate::{
+    commands::command_utils::get_current_timestamp, database::DatabasePaths,
+    server_errors::ServerError,
+};
+use model::{
+    ban::Ban, channel::Channel, channel_stats::ChannelStats, channelflag::ChannelFlag,
+    client::Client, config::ServerConfig, json::ToJson, network::RemoteClient,
+    preferences::Preferences, userflag::UserFlag,
+};
+use serde::{de::DeserializeOwned, Serialize};
 use std::{collections::HashMap, path::Path};
 
-static CLIENTS_PATH: &str = "server/rsc/clients.txt";
-static CHANNELS_PATH: &str = "server/rsc/channels.txt";
+static SECONDS_PER_DAY: u64 = 86400;
 
-/// Function that loads the clients from the file of the database
-pub fn load_clients() -> Result<HashMap<String, Client>, ServerError> {
-    let mut hash = HashMap::new();
-    if Path::new(CLIENTS_PATH).exists() {
-        let clients_str = std::fs::read_to_string(CLIENTS_PATH)?;
-        let mut clients = clients_str.split('\n').collect::<Vec<_>>();
-        clients.pop();
-        for client in clients {
-            let client = client.split(';').collect::<Vec<_>>();
-            let nickname = client[0].to_string();
-            let username = client[1].to_string();
-            let hostname = client[2].to_string();
-            let servername = client[3].to_string();
-            let realname = client[4].to_string();
-            let mut password = None;
-            if !client[5].is_empty() {
-                password = Some(client[5].to_string());
-            }
-            let mut away_message = None;
-            if !client[6].is_empty() {
-                away_message = Some(client[6].to_string());
-            }
-            let mut modes = vec![];
-            for mode in client[7].split(',') {
-                let m = mode.chars().next();
-                if let Some(f) = m {
-                    modes.push(UserFlag::match_flag(f));
-                }
-            }
+/// Function that loads the configuration of the server from its file.
+/// Each line of the file has the name of an option and its value separated by `;`,
+/// and the options that are not in the file keep their default value.
+/// # Arguments
+/// * `paths` - The files of the database of the server.
+pub fn load_config(paths: &DatabasePaths) -> Result<ServerConfig, ServerError> {
+    let mut config = read_config_file(&paths.config)?;
+    config.started = get_current_timestamp();
+    config.created = get_date(config.started);
+    config.audit_file = Some(paths.audit.to_owned());
+    Ok(config)
+}
+
+/// Function that loads again the configuration of the server from the file it was loaded from,
+/// keeping the time the server was started and its audit log.
+/// # Arguments
+/// * `config` - The current configuration of the server.
+pub fn reload_config(config: &ServerConfig) -> Result<ServerConfig, ServerError> {
+    let mut reloaded = match &config.file {
+        Some(file) => read_config_file(file)?,
+        None => ServerConfig::default(),
+    };
+    reloaded.started = config.started;
+    reloaded.created = config.created.to_owned();
+    reloaded.audit_file = config.audit_file.to_owned();
+    Ok(reloaded)
+}
 
-            let new_client = Client {
-                nickname: nickname.to_owned(),
-                username,
-                hostname,
-                servername,
-                realname,
-                password,
-                connected: false,
-                away_message,
-                modes,
-            };
-            hash.insert(nickname.to_owned(), new_client);
-            println!("Client loaded: {}", nickname);
+/// Reads the options of the configuration file, if it exists.
+fn read_config_file(file: &Path) -> Result<ServerConfig, ServerError> {
+    let mut config = ServerConfig::default();
+    if file.exists() {
+        let config_str = std::fs::read_to_string(file)?;
+        for line in config_str.lines() {
+            if let Some((key, value)) = line.split_once(';') {
+                config.set(key.trim(), value.trim());
+            }
         }
     }
+    config.file = Some(file.to_path_buf());
+    Ok(config)
+}
+
+/// Returns the date in UTC of the given time, with the format `YYYY-MM-DD HH:MM:SS UTC`.
+/// # Arguments
+/// * `seconds` - The time in seconds since the epoch.
+fn get_date(seconds: u64) -> String {
+    let days = (seconds / SECONDS_PER_DAY) as i64;
+    let time = seconds % SECONDS_PER_DAY;
+
+    // converts the days since the epoch to a civil date
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        time / 3600,
+        (time % 3600) / 60,
+        time % 60
+    )
+}
+
+/// Function that loads the clients from the file of the database.
+/// A file saved in the format used before the records were written as JSON is migrated.
+/// # Arguments
+/// * `paths` - The files of the database of the server.
+pub fn load_clients(paths: &DatabasePaths) -> Result<HashMap<String, Client>, ServerError> {
+    let mut hash = HashMap::new();
+    for client in read_records(&paths.clients, parse_legacy_client)? {
+        println!("Client loaded: {}", client.nickname);
+        hash.insert(client.nickname.to_owned(), client);
+    }
     Ok(hash)
 }
 
+/// Reads a client saved in the format used before the records were written as JSON, with its
+/// fields separated by `;`. Returns None if the record doesn't have the fields of a client.
+fn parse_legacy_client(record: &str) -> Option<Client> {
+    let client = record.split(';').collect::<Vec<_>>();
+    if client.len() < 8 {
+        return None;
+    }
+    let nickname = client[0].to_string();
+    let username = client[1].to_string();
+    let hostname = client[2].to_string();
+    let servername = client[3].to_string();
+    let realname = client[4].to_string();
+    let mut password = None;
+    if !client[5].is_empty() {
+        password = Some(client[5].to_string());
+    }
+    let mut away_message = None;
+    if !client[6].is_empty() {
+        away_message = Some(client[6].to_string());
+    }
+    let mut email = None;
+    if let Some(e) = client.get(8).filter(|e| !e.is_empty()) {
+        email = Some(e.to_string());
+    }
+    let silenced = match client.get(9) {
+        Some(masks) => masks
+            .split(',')
+            .filter(|mask| !mask.is_empty())
+            .map(|mask| mask.to_string())
+            .collect(),
+        None => vec![],
+    };
+    let preferences = Preferences::from_fields(client.get(10..).unwrap_or_default());
+    let mut modes = vec![];
+    for mode in client[7].split(',') {
+        let m = mode.chars().next();
+        if let Some(f) = m {
+            modes.push(UserFlag::match_flag(f));
+        }
+    }
+
+    Some(Client {
+        nickname,
+        username,
+        hostname,
+        servername,
+        realname,
+        password,
+        email,
+        connected: false,
+        away_message,
+        auto_away: false,
+        modes,
+        signon: 0,
+        last_activity: 0,
+        silenced,
+        preferences,
+    })
+}
+
 /// Function that loads network clients from the loaded clients
 /// # Arguments
 /// * `hash_clients` - The clients loaded from the database
-pub fn load_network_clients(hash_clients: &HashMap<String, Client>) -> HashMap<String, u8> {
+pub fn load_network_clients(
+    hash_clients: &HashMap<String, Client>,
+) -> HashMap<String, RemoteClient> {
     let mut hash = HashMap::new();
-    for client in hash_clients.keys() {
-        hash.insert(client.to_owned(), 0);
+    for (nickname, client) in hash_clients {
+        hash.insert(nickname.to_owned(), RemoteClient::from_client(client));
     }
     hash
 }
 
-/// Function that loads the channels from the file of the database
-pub fn load_channels() -> Result<HashMap<String, Channel>, ServerError> {
+/// Function that loads the channels from the file of the database.
+/// A file saved in the format used before the records were written as JSON is migrated.
+/// # Arguments
+/// * `paths` - The files of the database of the server.
+pub fn load_channels(paths: &DatabasePaths) -> Result<HashMap<String, Channel>, ServerError> {
     let mut hash = HashMap::new();
-    if Path::new(CHANNELS_PATH).exists() {
-        let channels_str = std::fs::read_to_string(CHANNELS_PATH)?;
-        let mut channels = channels_str.split('\n').collect::<Vec<_>>();
-        channels.pop();
-        for channel in channels {
-            let channel = channel.split(';').collect::<Vec<_>>();
-            if channel.len() != 9 {
+    for channel in read_records(&paths.channels, parse_legacy_channel)? {
+        println!("Channel loaded: {}", channel.name);
+        hash.insert(channel.name.to_owned(), channel);
+    }
+    Ok(hash)
+}
+
+/// Reads a channel saved in the format used before the records were written as JSON, with its
+/// fields separated by `;`. Returns None if the record doesn't have the fields of a channel.
+fn parse_legacy_channel(record: &str) -> Option<Channel> {
+    let channel = record.split(';').collect::<Vec<_>>();
+    if channel.len() != 9 && channel.len() != 10 {
+        return None;
+    }
+    let name = channel[0].to_string();
+    let topic = channel[1].to_string();
+    let users = channel[2]
+        .split(',')
+        .map(|u| u.to_string())
+        .collect::<Vec<_>>();
+    let mut password = None;
+    if !channel[3].is_empty() {
+        password = Some(channel[3].to_string());
+    }
+    let banned_users = channel[4]
+        .split(',')
+        .filter_map(Ban::serialize)
+        .collect::<Vec<Ban>>();
+    let operators = channel[5]
+        .split(',')
+        .map(|u| u.to_string())
+        .collect::<Vec<_>>();
+    let mut modes = vec![];
+    for mode in channel[6].split(',') {
+        let m = mode.chars().next();
+        if let Some(f) = m {
+            modes.push(ChannelFlag::match_flag(f));
+        }
+    }
+    let mut limit = None;
+    if !channel[7].is_empty() {
+        limit = channel[7].parse::<i32>().ok();
+    }
+    let moderators = channel[8]
+        .split(',')
+        .map(|u| u.to_string())
+        .collect::<Vec<_>>();
+    let forward = channel
+        .get(9)
+        .filter(|forward| !forward.is_empty())
+        .map(|forward| forward.to_string());
+
+    Some(Channel {
+        name,
+        topic,
+        password,
+        users,
+        operators,
+        modes,
+        banned_users,
+        limit,
+        moderators,
+        forward,
+        filters: vec![],
+        stats: ChannelStats::default(),
+    })
+}
+
+/// Reads the records of a file of the database, one for each line.
+/// The lines that are not JSON are read with the parser of the old format. If there is any,
+/// the file is written again with those records as JSON, so that the old files are migrated
+/// the first time they are loaded. The records that can't be read are skipped and kept as
+/// they are.
+/// # Arguments
+/// * `file` - The file of the records.
+/// * `parse_legacy` - The parser of the records saved in the old format.
+fn read_records<T: DeserializeOwned + Serialize>(
+    file: &Path,
+    parse_legacy: fn(&str) -> Option<T>,
+) -> Result<Vec<T>, ServerError> {
+    let mut records = vec![];
+    if !file.exists() {
+        return Ok(records);
+    }
+    let mut lines = vec![];
+    let mut migrated = false;
+    for line in std::fs::read_to_string(file)?.lines() {
+        if line.is_empty() {
+            continue;
+        }
+        if !line.starts_with('{') {
+            if let Some(record) = parse_legacy(line) {
+                lines.push(record.to_json());
+                records.push(record);
+                migrated = true;
                 continue;
             }
-            let name = channel[0].to_string();
-            let topic = channel[1].to_string();
-            let users = channel[2]
-                .split(',')
-                .map(|u| u.to_string())
-                .collect::<Vec<_>>();
-            let mut password = None;
-            if !channel[3].is_empty() {
-                password = Some(channel[3].to_string());
-            }
-            let banned_users = channel[4]
-                .split(',')
-                .filter_map(|u| {
-                    if !u.is_empty() {
-                        Some(u.to_string())
-                    } else {
-                        None
-                    }
-                })
-                .collect::<Vec<String>>();
-            let operators = channel[5]
-                .split(',')
-                .map(|u| u.to_string())
-                .collect::<Vec<_>>();
-            let mut modes = vec![];
-            for mode in channel[6].split(',') {
-                let m = mode.chars().next();
-                if let Some(f) = m {
-                    modes.push(ChannelFlag::match_flag(f));
-                }
-            }
-            let mut limit = None;
-            if !channel[7].is_empty() {
-                limit = match channel[7].parse::<i32>() {
-                    Ok(l) => Some(l),
-                    Err(_) => None,
-                }
-            }
-            let moderators = channel[8]
-                .split(',')
-                .map(|u| u.to_string())
-                .collect::<Vec<_>>();
-
-            let new_channel = Channel {
-                name: name.to_owned(),
-                topic,
-                password,
-                users,
-                operators,
-                modes,
-                banned_users,
-                limit,
-                moderators,
-            };
-            hash.insert(name.to_owned(), new_channel);
-            println!("Channel loaded: {}", name);
         }
+        match serde_json::from_str::<T>(line) {
+            Ok(record) => records.push(record),
+            Err(e) => println!("Discarding record: {:?}", e),
+        }
+        lines.push(line.to_string());
+    }
+    if migrated {
+        let mut data = lines.join("\n");
+        data.push('\n');
+        std::fs::write(file, data)?;
+        println!("Database file migrated to JSON: {}", file.display());
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod load_tests {
+    use model::{json::ToJson, userflag::UserFlag};
+
+    use super::{load_channels, load_clients};
+    use crate::database::DatabasePaths;
+
+    #[test]
+    fn test_legacy_files_are_migrated_to_json_on_load() {
+        let paths = DatabasePaths::temporary().unwrap();
+        std::fs::write(
+            &paths.clients,
+            "alice;al;host;srv;Alice A;secret;;i,o;a@b.c;bob*;bye;;#rust,#go;\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &paths.channels,
+            "#rust;hi;alice,bob;;carol:alice:10:;alice;t,n;5;;#overflow\n",
+        )
+        .unwrap();
+
+        let clients = load_clients(&paths).unwrap();
+        let channels = load_channels(&paths).unwrap();
+        let alice = &clients["alice"];
+        assert_eq!(alice.password, Some("secret".to_string()));
+        assert_eq!(alice.modes, vec![UserFlag::Invisible, UserFlag::Operator]);
+        assert_eq!(alice.preferences.autojoin, vec!["#rust", "#go"]);
+        let rust = &channels["#rust"];
+        assert_eq!(rust.users, vec!["alice", "bob"]);
+        assert_eq!(rust.banned_users[0].set_at, 10);
+        assert_eq!(rust.forward, Some("#overflow".to_string()));
+
+        assert_eq!(
+            std::fs::read_to_string(&paths.clients).unwrap(),
+            format!("{}\n", alice.to_json())
+        );
+        assert_eq!(
+            std::fs::read_to_string(&paths.channels).unwrap(),
+            format!("{}\n", rust.to_json())
+        );
+        let reloaded = load_clients(&paths).unwrap();
+        assert_eq!(reloaded["alice"].to_json(), alice.to_json());
+        assert_eq!(
+            load_channels(&paths).unwrap()["#rust"].to_json(),
+            rust.to_json()
+        );
+    }
+
+    #[test]
+    fn test_invalid_records_are_skipped_and_kept() {
+        let paths = DatabasePaths::temporary().unwrap();
+        let contents = "{\"nickname\":\"alice\"\nbob;b\n";
+        std::fs::write(&paths.clients, contents).unwrap();
+
+        assert!(load_clients(&paths).unwrap().is_empty());
+        assert_eq!(std::fs::read_to_string(&paths.clients).unwrap(), contents);
     }
-    Ok(hash)
 }
@@ -0,0 +1,314 @@
+use std::{
+    collections::HashMap,
+    net::TcpStream,
+    sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use model::{
+    channel::Channel,
+    client_registry::{ClientsReadGuard, ClientsWriteGuard},
+    config::ServerConfig,
+    locks,
+    network::{LinkedServer, Network, RemoteClient},
+    responses::numeric::WireFormat,
+    server::Server,
+    session::Session,
+    who_query::WhoQuery,
+};
+
+use crate::server_errors::ServerError;
+
+// The locks of the session and the network must always be acquired in this order:
+//
+// 1. `session.config`
+// 2. `session.channels`
+// 3. the shards of `session.clients`, in order
+// 4. `network.clients`
+// 5. the sockets of `session.sockets`
+// 6. the wire formats of `session.sockets`
+// 7. `session.connections`
+// 8. `session.rejected_connections`
+// 9. `session.who_queries`
+// 10. `network.servers`
+// 11. `network.server`
+// 12. the queues of `network.links`
+// 13. the histograms of `session.command_metrics`
+// 14. the totals of `session.gc_stats`
+//
+// A thread holding one of them may only acquire the locks that come after it.
+// The clients are locked either one shard at a time, through the methods of the
+// registry, or all of them with `read_lock_clients` and `write_lock_clients`, but a
+// thread never holds two shards that it locked on its own.
+// Locks held at the same time must be released before acquiring an earlier one,
+// so two handlers can never wait for each other. The pairs taken together by many
+// handlers have helpers that acquire them in this order, like
+// `read_lock_channels_and_clients`.
+//
+// The events of the session are published without holding any of them, since the
+// subscribers may acquire any lock.
+
+/// The read locks of the channels and of every client of the server.
+pub type ChannelsAndClientsReadLocks<'a> = (
+    RwLockReadGuard<'a, HashMap<String, Channel>>,
+    ClientsReadGuard<'a>,
+);
+
+/// The write locks of the channels and of every client of the server.
+pub type ChannelsAndClientsWriteLocks<'a> = (
+    RwLockWriteGuard<'a, HashMap<String, Channel>>,
+    ClientsWriteGuard<'a>,
+);
+
+/// The write locks of the servers of the network and of the current server.
+pub type ServersLocks<'a> = (
+    RwLockWriteGuard<'a, HashMap<String, LinkedServer>>,
+    RwLockWriteGuard<'a, Server>,
+);
+
+// A lock is poisoned when a thread panics while holding it, and it is recovered by the
+// helpers of the model, so the locks of the server never fail.
+
+fn read_lock<'a, T>(
+    lock: &'a RwLock<T>,
+    name: &str,
+) -> Result<RwLockReadGuard<'a, T>, ServerError> {
+    Ok(locks::read_lock(lock, name))
+}
+
+fn write_lock<'a, T>(
+    lock: &'a RwLock<T>,
+    name: &str,
+) -> Result<RwLockWriteGuard<'a, T>, ServerError> {
+    Ok(locks::write_lock(lock, name))
+}
+
+fn lock<'a, T>(lock: &'a Mutex<T>, name: &str) -> Result<MutexGuard<'a, T>, ServerError> {
+    Ok(locks::lock(lock, name))
+}
+
+/// Locks the configuration of the server for reading.
+pub fn read_lock_config(
+    session: &Session,
+) -> Result<RwLockReadGuard<'_, ServerConfig>, ServerError> {
+    read_lock(&session.config, "config")
+}
+
+/// Locks the configuration of the server for writing.
+pub fn write_lock_config(
+    session: &Session,
+) -> Result<RwLockWriteGuard<'_, ServerConfig>, ServerError> {
+    write_lock(&session.config, "config")
+}
+
+/// Locks the channels of the server for writing.
+pub fn write_lock_channels(
+    session: &Session,
+) -> Result<RwLockWriteGuard<'_, HashMap<String, Channel>>, ServerError> {
+    write_lock(session.channels.as_lock(), "channels")
+}
+
+/// Locks the channels of the server for reading.
+pub fn read_lock_channels(
+    session: &Session,
+) -> Result<RwLockReadGuard<'_, HashMap<String, Channel>>, ServerError> {
+    read_lock(session.channels.as_lock(), "channels")
+}
+
+/// Locks every client of the server for reading.
+pub fn read_lock_clients(session: &Session) -> Result<ClientsReadGuard<'_>, ServerError> {
+    Ok(session.clients.read_all())
+}
+
+/// Locks every client of the server for writing.
+pub fn write_lock_clients(session: &Session) -> Result<ClientsWriteGuard<'_>, ServerError> {
+    Ok(session.clients.write_all())
+}
+
+/// Locks the channels and every client of the server for reading, in that order.
+pub fn read_lock_channels_and_clients(
+    session: &Session,
+) -> Result<ChannelsAndClientsReadLocks<'_>, ServerError> {
+    let channels = read_lock_channels(session)?;
+    let clients = read_lock_clients(session)?;
+    Ok((channels, clients))
+}
+
+/// Locks the channels and every client of the server for writing, in that order.
+pub fn write_lock_channels_and_clients(
+    session: &Session,
+) -> Result<ChannelsAndClientsWriteLocks<'_>, ServerError> {
+    let channels = write_lock_channels(session)?;
+    let clients = write_lock_clients(session)?;
+    Ok((channels, clients))
+}
+
+/// Locks the clients of the network for reading.
+pub fn read_lock_network_clients(
+    network: &Network,
+) -> Result<RwLockReadGuard<'_, HashMap<String, RemoteClient>>, ServerError> {
+    read_lock(&network.clients, "network clients")
+}
+
+/// Locks the clients of the network for writing.
+pub fn write_lock_network_clients(
+    network: &Network,
+) -> Result<RwLockWriteGuard<'_, HashMap<String, RemoteClient>>, ServerError> {
+    write_lock(&network.clients, "network clients")
+}
+
+/// Locks the sockets of the clients of the server.
+pub fn lock_sockets(
+    session: &Session,
+) -> Result<MutexGuard<'_, HashMap<String, Arc<TcpStream>>>, ServerError> {
+    lock(session.sockets.as_sockets_lock(), "sockets")
+}
+
+/// Locks the format used by the connection of each client.
+pub fn lock_wire_formats(
+    session: &Session,
+) -> Result<MutexGuard<'_, HashMap<String, WireFormat>>, ServerError> {
+    lock(session.sockets.as_wire_formats_lock(), "wire formats")
+}
+
+/// Locks the amount of open connections of each IP.
+pub fn lock_connections(
+    session: &Session,
+) -> Result<MutexGuard<'_, HashMap<String, usize>>, ServerError> {
+    lock(&session.connections, "connections")
+}
+
+/// Locks the amount of connections rejected for each reason.
+pub fn lock_rejected_connections(
+    session: &Session,
+) -> Result<MutexGuard<'_, HashMap<String, usize>>, ServerError> {
+    lock(&session.rejected_connections, "rejected connections")
+}
+
+/// Locks the WHO queries of the clients that wait for the answers of the other servers.
+pub fn lock_who_queries(
+    session: &Session,
+) -> Result<MutexGuard<'_, HashMap<String, WhoQuery>>, ServerError> {
+    lock(&session.who_queries, "WHO queries")
+}
+
+/// Locks the servers of the network, with their hopcount and latency, for reading.
+pub fn read_lock_servers(
+    network: &Network,
+) -> Result<RwLockReadGuard<'_, HashMap<String, LinkedServer>>, ServerError> {
+    read_lock(&network.servers, "servers")
+}
+
+/// Locks the servers of the network, with their hopcount and latency, for writing.
+pub fn write_lock_servers(
+    network: &Network,
+) -> Result<RwLockWriteGuard<'_, HashMap<String, LinkedServer>>, ServerError> {
+    write_lock(&network.servers, "servers")
+}
+
+/// Locks the current server for reading.
+pub fn read_lock_server(network: &Network) -> Result<RwLockReadGuard<'_, Server>, ServerError> {
+    read_lock(&network.server, "server")
+}
+
+/// Locks the current server for writing.
+pub fn write_lock_server(network: &Network) -> Result<RwLockWriteGuard<'_, Server>, ServerError> {
+    write_lock(&network.server, "server")
+}
+
+/// Locks the servers of the network and the current server for writing, in that order.
+pub fn write_lock_servers_and_server(network: &Network) -> Result<ServersLocks<'_>, ServerError> {
+    let servers = write_lock_servers(network)?;
+    let server = write_lock_server(network)?;
+    Ok((servers, server))
+}
+
+#[cfg(test)]
+mod locks_tests {
+    use model::consts::MAX_MSG_SIZE;
+    use std::{
+        collections::HashMap,
+        io::Read,
+        net::TcpListener,
+        sync::{Arc, RwLock},
+        thread,
+    };
+
+    use model::{
+        link_queues::LinkQueues,
+        message::MessageType,
+        network::Network,
+        persistence::PersistenceType,
+        responses::{replies::CommandResponse, response::Response},
+        server::Server,
+    };
+
+    use crate::{
+        commands::{
+            command_utils::{
+                create_client_for_test, create_message_for_test, create_session_for_test,
+                TargetResults,
+            },
+            join::handle_join_command,
+        },
+        database::{handle_database, DatabasePaths},
+        locks::{read_lock_channels, write_lock_channels},
+    };
+
+    #[test]
+    fn test_commands_succeed_after_a_handler_panics_holding_a_lock() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr, "client".to_string());
+        let (mut reader, _) = listener.accept().unwrap();
+        let network = Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "127.0.0.1".to_string(),
+                port: "8080".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        };
+
+        let panicking = session.clone();
+        let result = thread::spawn(move || {
+            let _channels = write_lock_channels(&panicking).unwrap();
+            panic!("handler panicked while holding the channels");
+        })
+        .join();
+        assert!(result.is_err());
+        assert!(session.channels.as_lock().is_poisoned());
+
+        let message = create_message_for_test(MessageType::Join, vec!["#channel_test".to_string()]);
+        let result = handle_join_command(
+            message,
+            &"client".to_string(),
+            &session,
+            &network,
+            &"test".to_string(),
+        )
+        .and_then(TargetResults::into_result);
+
+        assert!(result.is_ok());
+        assert!(!session.channels.as_lock().is_poisoned());
+        assert!(read_lock_channels(&session)
+            .unwrap()
+            .contains_key("#channel_test"));
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        match Response::serialize(String::from_utf8(msg).unwrap()).unwrap() {
+            Response::CommandResponse {
+                response: CommandResponse::Topic { .. },
+            } => (),
+            _ => panic!("Expected the topic of the channel joined"),
+        }
+    }
+}
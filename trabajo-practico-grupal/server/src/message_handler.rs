@@ -1,23 +1,24 @@
+use std::time::Instant;
+
 use crate::{
-    commands::{
-        away::handle_away_command, command_utils::fetch_info, dcc::handle_dcc_command,
-        invite::handle_invite_command, join::handle_join_command, kick::handle_kick_command,
-        list::handle_list_command, mode::handle_mode_command, names::handle_names_command,
-        oper::handle_oper_command, part::handle_part_command, privmsg::handle_privmsg_command,
-        quit::handle_quit_command, topic::handle_topic_command, who::handle_who_command,
-        whois::handle_whois_command,
-    },
+    commands::registry::{registry, Privilege},
+    locks::read_lock_server,
     server_errors::ServerError,
+    socket::inform_client,
 };
 use model::{
-    message::{Message, MessageType},
-    network::Network,
-    session::Session,
+    message::Message, network::Network, responses::errors::ErrorResponse, session::Session,
 };
 
 /// This function is called when a client is registered and authenticated
-/// It will check the message type and call the appropriate function to handle the message
-/// If the message type is not handled, it will return an error.
+/// It looks for the command in the registry, checks that the client can send it and that
+/// it has enough parameters, and calls its handler. The time the handler takes is recorded in
+/// the histogram of the command, whether it succeeds or not.
+/// # Errors
+/// * ServerError::InvalidCommand - If the command is not in the registry.
+/// * ServerError::ClientAlreadyRegistered - If the command can only be sent while registering.
+/// * ServerError::UserNotOperator - If the command is only for operators and the client is not one.
+/// * ServerError::InvalidParameters - If the message doesn't have enough parameters.
 pub fn handle_client_message(
     message: Message,
     nickname: String,
@@ -25,63 +26,165 @@ pub fn handle_client_message(
     network: &Network,
     server_name: &String,
 ) -> Result<(), ServerError> {
-    match message.command {
-        MessageType::Quit => {
-            handle_quit_command(message, nickname, session)?;
-            fetch_info(session, network, server_name)?;
-        }
-        MessageType::Privmsg => {
-            handle_privmsg_command(message, &nickname, session, network, server_name)?;
-        }
-        MessageType::Join => {
-            handle_join_command(message, &nickname, session, network, server_name)?;
-            fetch_info(session, network, server_name)?;
-        }
-        MessageType::Part => {
-            handle_part_command(message, nickname, session, network, server_name)?;
-            fetch_info(session, network, server_name)?;
-        }
-        MessageType::Kick => {
-            handle_kick_command(message, nickname, session, network, server_name)?;
-            fetch_info(session, network, server_name)?;
-        }
-        MessageType::Names => {
-            handle_names_command(message, nickname, session, network, None)?;
-        }
-        MessageType::Topic => {
-            handle_topic_command(message, nickname, session, network, server_name)?;
-            fetch_info(session, network, server_name)?;
-        }
-        MessageType::List => {
-            handle_list_command(message, &nickname, session, network, None)?;
-        }
-        MessageType::Mode => {
-            handle_mode_command(message, nickname, session, network, server_name)?;
-        }
-        MessageType::Oper => {
-            handle_oper_command(message, nickname, session, network)?;
-        }
-        MessageType::Invite => {
-            handle_invite_command(message, nickname, session, network, server_name)?;
-            fetch_info(session, network, server_name)?;
-        }
-        MessageType::Who => {
-            handle_who_command(message, nickname, session, network, None)?;
+    let command = match registry().get(&message.command) {
+        Some(command) => command,
+        None => return Err(ServerError::InvalidCommand),
+    };
+    let handler = match &command.handler {
+        Some(handler) => handler,
+        None => return Err(ServerError::ClientAlreadyRegistered),
+    };
+
+    if command.privilege == Privilege::ServerOperator
+        && !read_lock_server(network)?.operators.contains(&nickname)
+    {
+        let response = ErrorResponse::NoPrivileges.to_string();
+        inform_client(session, &nickname, &response)?;
+        return Err(ServerError::UserNotOperator);
+    }
+    if message.parameters.len() < command.parameters {
+        let response = ErrorResponse::NeedMoreParams {
+            command: command.name(),
         }
-        MessageType::WhoIs => {
-            handle_whois_command(message, nickname, session)?;
+        .to_string();
+        inform_client(session, &nickname, &response)?;
+        return Err(ServerError::InvalidParameters);
+    }
+
+    let started = Instant::now();
+    let result = handler.handle(message, nickname, session, network, server_name);
+    session
+        .command_metrics
+        .record(&command.name(), started.elapsed());
+    result
+}
+
+#[cfg(test)]
+mod message_handler_tests {
+    use model::consts::MAX_MSG_SIZE;
+    use std::{
+        collections::HashMap,
+        io::Read,
+        net::TcpListener,
+        sync::{Arc, RwLock},
+    };
+
+    use model::{
+        link_queues::LinkQueues,
+        message::MessageType,
+        network::Network,
+        persistence::PersistenceType,
+        responses::{errors::ErrorResponse, response::Response},
+        server::Server,
+    };
+
+    use crate::{
+        commands::command_utils::{
+            create_client_for_test, create_message_for_test, create_session_for_test,
+        },
+        database::{handle_database, DatabasePaths},
+        message_handler::handle_client_message,
+        server_errors::ServerError,
+    };
+
+    fn create_network_for_test() -> Network {
+        Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
         }
-        MessageType::Away => {
-            handle_away_command(message, nickname, session)?;
+    }
+
+    #[test]
+    fn test_command_without_enough_parameters_is_not_handled() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr, "nickname".to_string());
+        let (mut reader, _) = listener.accept().unwrap();
+        let network = create_network_for_test();
+
+        let message = create_message_for_test(MessageType::Kick, vec!["#channel".to_string()]);
+        let result = handle_client_message(
+            message,
+            "nickname".to_string(),
+            &session,
+            &network,
+            &"test".to_string(),
+        );
+
+        assert_eq!(result, Err(ServerError::InvalidParameters));
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        match Response::serialize(String::from_utf8(msg).unwrap()).unwrap() {
+            Response::ErrorResponse {
+                response: ErrorResponse::NeedMoreParams { command },
+            } => assert_eq!(command, "KICK"),
+            _ => panic!("expected a need more params error"),
         }
-        MessageType::Dcc => {
-            handle_dcc_command(message, nickname, session, network, server_name)?;
+    }
+
+    #[test]
+    fn test_registration_and_server_commands_are_rejected() {
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        let network = create_network_for_test();
+
+        let mut results = vec![];
+        for command in [MessageType::Nick, MessageType::Reclaim, MessageType::Squit] {
+            let message = create_message_for_test(command, vec!["nickname".to_string()]);
+            results.push(handle_client_message(
+                message,
+                "nickname".to_string(),
+                &session,
+                &network,
+                &"test".to_string(),
+            ));
         }
-        MessageType::Nick => return Err(ServerError::ClientAlreadyRegistered),
-        MessageType::Pass => return Err(ServerError::ClientAlreadyRegistered),
-        MessageType::User => return Err(ServerError::ClientAlreadyRegistered),
-        _ => return Err(ServerError::InvalidCommand),
+
+        assert_eq!(results[0], Err(ServerError::ClientAlreadyRegistered));
+        assert_eq!(results[1], Err(ServerError::ClientAlreadyRegistered));
+        assert_eq!(results[2], Err(ServerError::InvalidCommand));
     }
 
-    Ok(())
+    #[test]
+    fn test_time_of_the_handled_commands_is_recorded() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        create_client_for_test(&session, addr, "nickname".to_string());
+        let (_reader, _) = listener.accept().unwrap();
+        let network = create_network_for_test();
+
+        for parameters in [vec!["#channel"], vec!["#channel", "other"]] {
+            let parameters = parameters.into_iter().map(str::to_string).collect();
+            let message = create_message_for_test(MessageType::Kick, parameters);
+            let _ = handle_client_message(
+                message,
+                "nickname".to_string(),
+                &session,
+                &network,
+                &"test".to_string(),
+            );
+        }
+
+        let snapshot = session.command_metrics.snapshot();
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!(snapshot[0].0, "KICK");
+        assert_eq!(snapshot[0].1.count, 1);
+    }
 }
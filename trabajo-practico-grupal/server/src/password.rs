@@ -0,0 +1,110 @@
+use model::crypto::{from_hex, pbkdf2_sha256, random_bytes, sha256, to_hex};
+
+static HASH_PREFIX: &str = "pbkdf2-sha256";
+static LEGACY_HASH_PREFIX: &str = "sha256";
+/// The iterations of the hashes saved from now on. The hashes keep the iterations they were
+/// saved with, so raising them doesn't break the passwords saved before.
+static HASH_ITERATIONS: u32 = 100_000;
+
+/// Returns the hash of the password to save it in the database, with the format
+/// `pbkdf2-sha256$<iterations>$<salt>$<digest>`. The salt is random, so two clients with the
+/// same password don't get the same hash.
+/// # Arguments
+/// * `password` - The password in plain text.
+pub fn hash_password(password: &str) -> String {
+    let salt = random_bytes::<16>();
+    hash_with(HASH_ITERATIONS, &salt, password)
+}
+
+/// Returns true if the password is the one saved.
+/// The hashes saved with a single SHA-256 and the passwords saved before they were hashed are
+/// still verified.
+/// # Arguments
+/// * `saved` - The password saved for the client, hashed or not.
+/// * `password` - The password in plain text sent by the client.
+pub fn verify_password(saved: &str, password: &str) -> bool {
+    let parts = saved.split('$').collect::<Vec<_>>();
+    match parts[..] {
+        [prefix, iterations, salt, _] if prefix == HASH_PREFIX => {
+            match (iterations.parse::<u32>(), from_hex(salt)) {
+                (Ok(iterations), Some(salt)) => {
+                    hashes_match(&hash_with(iterations, &salt, password), saved)
+                }
+                _ => false,
+            }
+        }
+        [prefix, salt, _] if prefix == LEGACY_HASH_PREFIX => {
+            let digest = sha256(format!("{}{}", salt, password).as_bytes());
+            let hash = format!("{}${}${}", LEGACY_HASH_PREFIX, salt, to_hex(&digest));
+            hashes_match(&hash, saved)
+        }
+        _ => saved == password,
+    }
+}
+
+fn hash_with(iterations: u32, salt: &[u8], password: &str) -> String {
+    let digest = pbkdf2_sha256(password.as_bytes(), salt, iterations);
+    format!(
+        "{}${}${}${}",
+        HASH_PREFIX,
+        iterations,
+        to_hex(salt),
+        to_hex(&digest)
+    )
+}
+
+/// Compares the hashes reading every byte, so that the time it takes doesn't tell how much of
+/// the hash was right.
+fn hashes_match(hash: &str, saved: &str) -> bool {
+    hash.len() == saved.len()
+        && hash
+            .bytes()
+            .zip(saved.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod password_tests {
+    use model::crypto::{sha256, to_hex};
+
+    use super::{hash_password, hash_with, verify_password};
+
+    #[test]
+    fn test_hashed_password_is_verified() {
+        let hash = hash_password("secret");
+
+        assert!(hash.starts_with("pbkdf2-sha256$100000$"));
+        assert!(!hash.contains("secret"));
+        assert_ne!(hash, hash_password("secret"));
+        assert!(verify_password(&hash, "secret"));
+        assert!(!verify_password(&hash, "other"));
+    }
+
+    #[test]
+    fn test_hash_is_verified_with_the_iterations_it_was_saved_with() {
+        let hash = hash_with(10, b"salt", "secret");
+
+        assert!(hash.starts_with("pbkdf2-sha256$10$73616c74$"));
+        assert!(verify_password(&hash, "secret"));
+        assert!(!verify_password(
+            &hash.replacen("$10$", "$11$", 1),
+            "secret"
+        ));
+    }
+
+    #[test]
+    fn test_hash_of_a_single_sha256_is_verified() {
+        let digest = to_hex(&sha256(b"0123456789abcdefsecret"));
+        let hash = format!("sha256$0123456789abcdef${}", digest);
+
+        assert!(verify_password(&hash, "secret"));
+        assert!(!verify_password(&hash, "other"));
+    }
+
+    #[test]
+    fn test_password_saved_as_plain_text_is_verified() {
+        assert!(verify_password("secret", "secret"));
+        assert!(!verify_password("secret", "other"));
+    }
+}
@@ -0,0 +1,127 @@
+use model::{
+    events::ServerEvent, json::ToJson, mask::matches_mask, persistence::PersistenceType,
+    plugins::Plugin, session::Session,
+};
+
+use crate::{
+    commands::mode::inform_channel_about_mode, database::inform_database,
+    locks::write_lock_channels, server_errors::ServerError,
+};
+
+/// Plugin that makes operators of a channel the clients whose nickname matches one of its
+/// masks, when they join it on this server. The other servers of the network don't learn
+/// about the new operators.
+/// # Fields
+/// * `server_name` - The name of the current server, that sets the mode.
+/// * `rules` - The channels and the masks of the nicknames, like `("#rust", "ferris*")`.
+pub struct AutoOp {
+    server_name: String,
+    rules: Vec<(String, String)>,
+}
+
+impl AutoOp {
+    /// Creates the plugin.
+    /// # Arguments
+    /// * `server_name` - The name of the current server.
+    /// * `rules` - The channels and the masks of the nicknames to make operators of them.
+    pub fn new(server_name: &str, rules: Vec<(String, String)>) -> AutoOp {
+        AutoOp {
+            server_name: server_name.to_string(),
+            rules,
+        }
+    }
+
+    /// Returns true if the nickname must be an operator of the channel.
+    fn applies_to(&self, channel: &str, nickname: &str) -> bool {
+        self.rules.iter().any(|(rule_channel, mask)| {
+            rule_channel.eq_ignore_ascii_case(channel) && matches_mask(mask, nickname)
+        })
+    }
+
+    /// Makes the client an operator of the channel, if it is not one yet.
+    /// Returns true if the client became an operator.
+    fn give_operator(
+        &self,
+        session: &Session,
+        channel_name: &str,
+        nickname: &str,
+    ) -> Result<bool, ServerError> {
+        let mut channels = write_lock_channels(session)?;
+        let channel = match channels.get_mut(channel_name) {
+            Some(channel) => channel,
+            None => return Err(ServerError::ChannelNotFound),
+        };
+        if channel
+            .operators
+            .iter()
+            .any(|operator| operator == nickname)
+        {
+            return Ok(false);
+        }
+        channel.operators.push(nickname.to_string());
+        inform_database(
+            PersistenceType::ChannelUpdate(channel.name.to_owned()),
+            channel.to_json(),
+            session,
+        )?;
+        let modes = format!("+o {}", nickname);
+        inform_channel_about_mode(channel, modes, &self.server_name, session)?;
+        Ok(true)
+    }
+}
+
+impl Plugin for AutoOp {
+    fn name(&self) -> &str {
+        "autoop"
+    }
+
+    fn on_join(&self, session: &Session, channel: &str, nickname: &str) {
+        if !self.applies_to(channel, nickname) {
+            return;
+        }
+        match self.give_operator(session, channel, nickname) {
+            Ok(true) => session.publish(ServerEvent::ModeChanged {
+                target: channel.to_string(),
+                modes: format!("+o {}", nickname),
+                set_by: self.server_name.to_owned(),
+            }),
+            Ok(false) => (),
+            Err(e) => println!("Error giving operator to {}: {:?}", nickname, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod auto_op_tests {
+    use model::{channel::Channel, persistence::PersistenceType, plugins::Plugin};
+
+    use super::AutoOp;
+    use crate::{
+        commands::command_utils::create_session_for_test,
+        database::{handle_database, DatabasePaths},
+        locks::{read_lock_channels, write_lock_channels},
+    };
+
+    #[test]
+    fn test_matching_clients_become_operators_when_they_join() {
+        let (tx, rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(tx);
+        let channel = Channel::new(
+            "&rust".to_string(),
+            "".to_string(),
+            vec!["ferris".to_string(), "bob".to_string()],
+        );
+        write_lock_channels(&session)
+            .unwrap()
+            .insert(channel.name.to_owned(), channel);
+        let plugin = AutoOp::new("test", vec![("&rust".to_string(), "fer*".to_string())]);
+
+        plugin.on_join(&session, "&rust", "ferris");
+        plugin.on_join(&session, "&rust", "bob");
+        plugin.on_join(&session, "&rust", "ferris");
+
+        let channels = read_lock_channels(&session).unwrap();
+        assert_eq!(channels.get("&rust").unwrap().operators, vec!["ferris"]);
+    }
+}
@@ -0,0 +1,139 @@
+use model::{
+    channelflag::ChannelFlag,
+    consts::{is_channel_name, operators_channel_name},
+    filter::{apply_filters, FilterOutcome},
+    message::{Message, MessageType},
+    plugins::{HookAction, Plugin},
+    session::Session,
+};
+
+use crate::locks::read_lock_channels;
+
+/// Plugin that applies the content filter of the channels with the `+G` mode to the PRIVMSG
+/// and the NOTICE sent to them or to their operators. A message sent to several receivers is
+/// censored for all of them, and it is not delivered to any of them if a channel blocks it.
+/// It is always loaded, since the filter is a mode of the channels.
+pub struct ChannelFilter;
+
+impl Plugin for ChannelFilter {
+    fn name(&self) -> &str {
+        "channelfilter"
+    }
+
+    fn on_pre_command(&self, session: &Session, _nickname: &str, message: &Message) -> HookAction {
+        if message.command != MessageType::Privmsg && message.command != MessageType::Notice {
+            return HookAction::Allow;
+        }
+        let receivers = match message.parameters.first() {
+            Some(receivers) => receivers.to_owned(),
+            None => return HookAction::Allow,
+        };
+        let mut filtered = message.clone();
+        let text = match (filtered.trailing.as_mut(), filtered.parameters.get_mut(1)) {
+            (Some(text), _) | (None, Some(text)) => text,
+            (None, None) => return HookAction::Allow,
+        };
+        let channels = match read_lock_channels(session) {
+            Ok(channels) => channels,
+            Err(e) => {
+                println!("Error filtering a message: {:?}", e);
+                return HookAction::Allow;
+            }
+        };
+
+        let mut changed = false;
+        for receiver in receivers.split(',').map(|r| r.trim()) {
+            let receiver = operators_channel_name(receiver).unwrap_or(receiver);
+            if !is_channel_name(receiver) {
+                continue;
+            }
+            let channel = match channels.get(receiver) {
+                Some(channel) if channel.modes.contains(&ChannelFlag::Filtered) => channel,
+                _ => continue,
+            };
+            match apply_filters(&channel.filters, text) {
+                FilterOutcome::Clean => (),
+                FilterOutcome::Censored(censored) => {
+                    *text = censored;
+                    changed = true;
+                }
+                FilterOutcome::Blocked(_) => {
+                    return HookAction::Deny(format!(
+                        "Your message to {} was blocked by the filter of the channel",
+                        channel.name
+                    ));
+                }
+            }
+        }
+        if changed {
+            HookAction::Rewrite(filtered)
+        } else {
+            HookAction::Allow
+        }
+    }
+}
+
+#[cfg(test)]
+mod channel_filter_tests {
+    use model::{
+        channel::Channel,
+        channelflag::ChannelFlag,
+        filter::{FilterAction, FilterRule},
+        message::MessageType,
+        persistence::PersistenceType,
+        plugins::{HookAction, Plugin},
+        session::Session,
+    };
+
+    use super::ChannelFilter;
+    use crate::{
+        commands::command_utils::{create_message_for_test, create_session_for_test},
+        locks::write_lock_channels,
+    };
+
+    fn add_channel_for_test(session: &Session, name: &str, filtered: bool) {
+        let mut channel = Channel::new(name.to_string(), "".to_string(), vec![]);
+        channel.filters = vec![
+            FilterRule::new("darn*", FilterAction::Censor, "op".to_string(), 0),
+            FilterRule::new("spam", FilterAction::Block, "op".to_string(), 0),
+        ];
+        if filtered {
+            channel.modes.push(ChannelFlag::Filtered);
+        }
+        write_lock_channels(session)
+            .unwrap()
+            .insert(name.to_string(), channel);
+    }
+
+    #[test]
+    fn test_messages_are_filtered_only_in_channels_with_the_mode() {
+        let (tx, _rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        let session = create_session_for_test(tx);
+        add_channel_for_test(&session, "&filtered", true);
+        add_channel_for_test(&session, "&open", false);
+        let send = |receivers: &str, text: &str| {
+            let mut message =
+                create_message_for_test(MessageType::Privmsg, vec![receivers.to_string()]);
+            message.trailing = Some(text.to_string());
+            ChannelFilter.on_pre_command(&session, "alice", &message)
+        };
+
+        let censored = send("&open,&filtered", "darned thing");
+        let blocked = send("&filtered", "buy spam");
+        let open = send("&open", "buy spam");
+
+        match censored {
+            HookAction::Rewrite(message) => {
+                assert_eq!(message.trailing, Some("****** thing".to_string()))
+            }
+            other => panic!("{:?} is not a rewrite", other),
+        }
+        assert_eq!(
+            blocked,
+            HookAction::Deny(
+                "Your message to &filtered was blocked by the filter of the channel".to_string()
+            )
+        );
+        assert_eq!(open, HookAction::Allow);
+    }
+}
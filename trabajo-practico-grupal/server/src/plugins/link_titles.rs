@@ -0,0 +1,421 @@
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs},
+    sync::{
+        mpsc::{sync_channel, Receiver, SyncSender, TrySendError},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use model::{
+    consts::is_channel_name, plugins::Plugin, responses::message::MessageResponse, session::Session,
+};
+
+use crate::{locks::read_lock_channels, server_errors::ServerError, socket::inform_client};
+
+static FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+static CHANNEL_INTERVAL: Duration = Duration::from_secs(10);
+static QUEUE_CAPACITY: usize = 16;
+static MAX_PAGE_SIZE: usize = 32768;
+static MAX_TITLE_LENGTH: usize = 120;
+static HTTP_SCHEME: &str = "http://";
+
+/// A link whose title is sent to a channel.
+/// # Fields
+/// * `session` - The session of the current server.
+/// * `channel` - The name of the channel the link was sent to.
+/// * `url` - The link.
+struct TitleRequest {
+    session: Session,
+    channel: String,
+    url: String,
+}
+
+/// Plugin that looks for the first link of the messages sent to the channels, and sends the
+/// title of its page to the members of the channel connected to this server, as a notice of the
+/// server. The pages are fetched one at a time by a single thread, and only the `http://` links
+/// to public addresses are followed, since the server doesn't speak TLS and shouldn't be used to
+/// reach the machines of its own network. Each channel gets at most one title every
+/// `CHANNEL_INTERVAL`, and the links are dropped while the queue of the thread is full.
+/// # Fields
+/// * `requests` - The queue of the thread that fetches the pages.
+/// * `last_links` - When each channel last had a link fetched.
+pub struct LinkTitles {
+    requests: SyncSender<TitleRequest>,
+    last_links: Mutex<HashMap<String, Instant>>,
+}
+
+impl LinkTitles {
+    /// Creates the plugin and starts the thread that fetches the pages.
+    /// # Arguments
+    /// * `server_name` - The name of the current server.
+    pub fn new(server_name: &str) -> LinkTitles {
+        let (requests, receiver) = sync_channel::<TitleRequest>(QUEUE_CAPACITY);
+        let server_name = server_name.to_string();
+        thread::spawn(move || fetch_titles(receiver, &server_name));
+        LinkTitles {
+            requests,
+            last_links: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns true if the channel can have a link fetched, saving the time if it can. The
+    /// channels whose interval already passed are forgotten.
+    /// # Arguments
+    /// * `channel` - The name of the channel.
+    /// * `now` - The current time.
+    fn allow_link(&self, channel: &str, now: Instant) -> bool {
+        let mut last_links = match self.last_links.lock() {
+            Ok(last_links) => last_links,
+            Err(_) => return false,
+        };
+        last_links.retain(|_, last| now.duration_since(*last) < CHANNEL_INTERVAL);
+        if last_links.contains_key(channel) {
+            return false;
+        }
+        last_links.insert(channel.to_string(), now);
+        true
+    }
+}
+
+impl Plugin for LinkTitles {
+    fn name(&self) -> &str {
+        "linktitles"
+    }
+
+    fn on_message(&self, session: &Session, _sender: &str, target: &str, text: &str) {
+        if !is_channel_name(target) {
+            return;
+        }
+        let url = match find_link(text) {
+            Some(url) => url.to_string(),
+            None => return,
+        };
+        if !self.allow_link(target, Instant::now()) {
+            return;
+        }
+        let request = TitleRequest {
+            session: session.clone(),
+            channel: target.to_string(),
+            url,
+        };
+        if let Err(TrySendError::Full(request)) = self.requests.try_send(request) {
+            println!("Too many links to fetch, skipping {}", request.url);
+        }
+    }
+}
+
+/// Fetches the pages of the links of the queue, one at a time, and sends their titles to the
+/// channels. The thread stops when the plugin is dropped.
+/// # Arguments
+/// * `receiver` - The queue of the links.
+/// * `server_name` - The name of the current server, that sends the notices.
+fn fetch_titles(receiver: Receiver<TitleRequest>, server_name: &str) {
+    for request in receiver {
+        let title = match fetch_title(&request.url) {
+            Some(title) => title,
+            None => continue,
+        };
+        if let Err(e) =
+            inform_channel_about_title(&request.session, &request.channel, server_name, &title)
+        {
+            println!("Error sending the title of {}: {:?}", request.url, e);
+        }
+    }
+}
+
+/// Sends the title of a link to the members of the channel connected to this server. The
+/// channels are released before writing, and a failed write doesn't stop the others.
+fn inform_channel_about_title(
+    session: &Session,
+    channel_name: &str,
+    server_name: &str,
+    title: &str,
+) -> Result<(), ServerError> {
+    let (channel, users) = {
+        let channels = read_lock_channels(session)?;
+        match channels.get(channel_name) {
+            Some(channel) => (channel.name.to_owned(), channel.users.clone()),
+            None => return Err(ServerError::ChannelNotFound),
+        }
+    };
+    let response = MessageResponse::ChannelNotice {
+        channel,
+        sender: server_name.to_string(),
+        message: format!("[ {} ]", title),
+    }
+    .to_string();
+    for user in users.iter() {
+        if let Err(e) = inform_client(session, user, &response) {
+            println!("Error sending a link title to {}: {:?}", user, e);
+        }
+    }
+    Ok(())
+}
+
+/// Returns the first `http://` link of the text.
+/// # Arguments
+/// * `text` - The text of a message.
+pub fn find_link(text: &str) -> Option<&str> {
+    text.split_whitespace()
+        .find(|word| word.len() > HTTP_SCHEME.len() && word.starts_with(HTTP_SCHEME))
+}
+
+/// Returns the host, the port and the path of an `http://` link.
+/// # Arguments
+/// * `url` - The link, like `http://example.com:8080/page`.
+pub fn parse_link(url: &str) -> Option<(String, u16, String)> {
+    let rest = url.strip_prefix(HTTP_SCHEME)?;
+    let (authority, path) = match rest.find(['/', '?', '#']) {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    let path = path.split('#').next().unwrap_or("/");
+    let path = if path.starts_with('/') {
+        path.to_string()
+    } else {
+        format!("/{}", path)
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse::<u16>().ok()?),
+        None => (authority, 80),
+    };
+    if host.is_empty() || host.contains('@') {
+        return None;
+    }
+    Some((host.to_string(), port, path))
+}
+
+/// Fetches the page of an `http://` link and returns its title, if it has one. The links whose
+/// host resolves to a loopback, private, link-local or unspecified address are refused.
+/// # Arguments
+/// * `url` - The link of the page.
+pub fn fetch_title(url: &str) -> Option<String> {
+    let (host, port, path) = parse_link(url)?;
+    let address = resolve_public_address(&host, port)?;
+    fetch_title_at(&address, &host, &path)
+}
+
+/// Returns the address of the host, or None if it can't be resolved or one of its addresses is
+/// not public, so a host can't hide a private address behind a public one.
+/// # Arguments
+/// * `host` - The host of the link.
+/// * `port` - The port of the link.
+fn resolve_public_address(host: &str, port: u16) -> Option<SocketAddr> {
+    let addresses = (host, port).to_socket_addrs().ok()?.collect::<Vec<_>>();
+    if let Some(address) = addresses.iter().find(|a| !is_public_address(&a.ip())) {
+        println!(
+            "Refusing to fetch {}, it resolves to {}",
+            host,
+            address.ip()
+        );
+        return None;
+    }
+    addresses.into_iter().next()
+}
+
+/// Returns true if the address can be reached from the internet, which is false for the
+/// loopback, private, link-local, unspecified, broadcast and multicast addresses.
+/// # Arguments
+/// * `ip` - The address.
+pub fn is_public_address(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast())
+        }
+        IpAddr::V6(ip) => {
+            if let Some(mapped) = ip.to_ipv4_mapped() {
+                return is_public_address(&IpAddr::V4(mapped));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || first & 0xfe00 == 0xfc00
+                || first & 0xffc0 == 0xfe80)
+        }
+    }
+}
+
+/// Fetches a page from an address and returns its title, if it has one. Only the first
+/// `MAX_PAGE_SIZE` bytes of the page are read, the whole fetch takes at most `FETCH_TIMEOUT`,
+/// and the redirections are not followed.
+/// # Arguments
+/// * `address` - The address of the host.
+/// * `host` - The host of the link, sent in the request.
+/// * `path` - The path of the page.
+fn fetch_title_at(address: &SocketAddr, host: &str, path: &str) -> Option<String> {
+    let deadline = Instant::now() + FETCH_TIMEOUT;
+    let mut stream = TcpStream::connect_timeout(address, FETCH_TIMEOUT).ok()?;
+    stream.set_write_timeout(Some(FETCH_TIMEOUT)).ok()?;
+    let request = format!(
+        "GET {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: rusty-irc\r\nConnection: close\r\n\r\n",
+        path, host
+    );
+    stream.write_all(request.as_bytes()).ok()?;
+
+    let mut page = vec![];
+    let mut buffer = [0u8; 4096];
+    while page.len() < MAX_PAGE_SIZE {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() || stream.set_read_timeout(Some(remaining)).is_err() {
+            break;
+        }
+        match stream.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => page.extend_from_slice(&buffer[..read.min(MAX_PAGE_SIZE - page.len())]),
+        }
+    }
+    let page = String::from_utf8_lossy(&page);
+    let (head, body) = page.split_once("\r\n\r\n")?;
+    if !head.starts_with("HTTP/") || head.split(' ').nth(1) != Some("200") {
+        return None;
+    }
+    parse_title(body)
+}
+
+/// Returns the text of the `<title>` element of an HTML page, without its extra spaces and with
+/// its entities decoded, cut to a length that fits in a message.
+/// # Arguments
+/// * `html` - The page.
+pub fn parse_title(html: &str) -> Option<String> {
+    let lowercase = html.to_lowercase();
+    if lowercase.len() != html.len() {
+        return None;
+    }
+    let open = lowercase.find("<title")?;
+    let start = open + lowercase[open..].find('>')? + 1;
+    let end = start + lowercase[start..].find("</title")?;
+    let title = unescape_html(&html[start..end])
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if title.is_empty() {
+        return None;
+    }
+    if title.chars().count() > MAX_TITLE_LENGTH {
+        let cut = title.chars().take(MAX_TITLE_LENGTH).collect::<String>();
+        return Some(format!("{}...", cut));
+    }
+    Some(title)
+}
+
+/// Decodes the most common entities of HTML.
+fn unescape_html(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod link_titles_tests {
+    use std::{
+        io::{Read, Write},
+        net::{IpAddr, TcpListener},
+        thread,
+        time::Instant,
+    };
+
+    use super::{
+        fetch_title, fetch_title_at, find_link, is_public_address, parse_link, parse_title,
+        LinkTitles, CHANNEL_INTERVAL,
+    };
+
+    #[test]
+    fn test_links_are_found_and_parsed() {
+        assert_eq!(
+            find_link("look at http://example.com/page?x=1 please"),
+            Some("http://example.com/page?x=1")
+        );
+        assert_eq!(find_link("https://example.com http://"), None);
+        assert_eq!(
+            parse_link("http://example.com:8080/page#top"),
+            Some(("example.com".to_string(), 8080, "/page".to_string()))
+        );
+        assert_eq!(
+            parse_link("http://example.com?q=1"),
+            Some(("example.com".to_string(), 80, "/?q=1".to_string()))
+        );
+        assert_eq!(parse_link("http://user@example.com/"), None);
+        assert_eq!(parse_link("http://example.com:port/"), None);
+    }
+
+    #[test]
+    fn test_title_is_parsed_and_shortened() {
+        assert_eq!(
+            parse_title("<html><head><TITLE lang=\"en\">\n  Rust &amp; IRC\n</TITLE>"),
+            Some("Rust & IRC".to_string())
+        );
+        assert_eq!(parse_title("<html><body>no title</body>"), None);
+        let long = format!("<title>{}</title>", "a".repeat(200));
+        assert_eq!(parse_title(&long).unwrap().len(), 123);
+    }
+
+    #[test]
+    fn test_title_is_fetched_from_the_page() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 1024];
+            let read = stream.read(&mut request).unwrap();
+            stream
+                .write_all(b"HTTP/1.0 200 OK\r\n\r\n<title>The page</title>")
+                .unwrap();
+            String::from_utf8_lossy(&request[..read]).to_string()
+        });
+
+        let title = fetch_title_at(&address, "127.0.0.1", "/page");
+
+        assert_eq!(title, Some("The page".to_string()));
+        assert!(server.join().unwrap().starts_with("GET /page HTTP/1.0\r\n"));
+    }
+
+    #[test]
+    fn test_links_to_the_local_network_are_refused() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        assert_eq!(fetch_title(&format!("http://127.0.0.1:{}/", port)), None);
+        assert_eq!(fetch_title(&format!("http://localhost:{}/", port)), None);
+        for address in [
+            "10.0.0.1",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "fe80::1",
+            "fd00::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_address(&address.parse::<IpAddr>().unwrap()));
+        }
+        assert!(is_public_address(&"93.184.216.34".parse().unwrap()));
+        assert!(is_public_address(&"2606:2800:220:1::".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_each_channel_gets_one_link_per_interval() {
+        let plugin = LinkTitles::new("test");
+        let now = Instant::now();
+
+        assert!(plugin.allow_link("#rust", now));
+        assert!(!plugin.allow_link("#rust", now));
+        assert!(plugin.allow_link("#irc", now));
+        assert!(plugin.allow_link("#rust", now + CHANNEL_INTERVAL));
+    }
+}
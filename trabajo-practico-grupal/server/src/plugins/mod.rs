@@ -0,0 +1,168 @@
+pub mod auto_op;
+pub mod channel_filter;
+pub mod link_titles;
+pub mod word_filter;
+
+use std::sync::Arc;
+
+use model::{config::ServerConfig, plugins::Plugin, session::Session};
+
+use crate::{locks::read_lock_config, server_errors::ServerError};
+
+use self::{
+    auto_op::AutoOp, channel_filter::ChannelFilter, link_titles::LinkTitles,
+    word_filter::WordFilter,
+};
+
+/// The function that creates a plugin from the configuration of the server and its name.
+pub type PluginFactory = fn(&ServerConfig, &str) -> Arc<dyn Plugin>;
+
+/// Struct that holds the plugins that can be loaded, by the names the configuration file uses.
+/// A crate that runs the server can register its own plugins before starting it.
+/// # Fields
+/// * `factories` - The names of the plugins and the functions that create them.
+pub struct PluginFactories {
+    factories: Vec<(String, PluginFactory)>,
+}
+
+impl PluginFactories {
+    /// Returns the factories of the plugins of the server: `wordfilter`, `autoop` and `linktitles`.
+    pub fn builtin() -> PluginFactories {
+        let mut factories = PluginFactories { factories: vec![] };
+        factories.register("wordfilter", |config, _| {
+            Arc::new(WordFilter::new(config.filtered_words.to_owned()))
+        });
+        factories.register("autoop", |config, server_name| {
+            Arc::new(AutoOp::new(server_name, config.auto_ops.to_owned()))
+        });
+        factories.register("linktitles", |_, server_name| {
+            Arc::new(LinkTitles::new(server_name))
+        });
+        factories
+    }
+
+    /// Adds a plugin that can be loaded, replacing the one with the same name.
+    /// # Arguments
+    /// * `name` - The name of the plugin in the configuration file, in lowercase.
+    /// * `factory` - The function that creates the plugin.
+    pub fn register(&mut self, name: &str, factory: PluginFactory) {
+        let name = name.to_lowercase();
+        self.factories.retain(|(registered, _)| *registered != name);
+        self.factories.push((name, factory));
+    }
+
+    /// Registers in the session the filter of the channels, and then the plugins listed in the
+    /// configuration, in the order they are listed. The unknown names are skipped. The plugins
+    /// are only loaded when the server starts.
+    /// # Arguments
+    /// * `session` - The session of the current server.
+    /// * `server_name` - The name of the current server.
+    pub fn load(&self, session: &Session, server_name: &str) -> Result<(), ServerError> {
+        let config = read_lock_config(session)?.clone();
+        session.plugins.register(Arc::new(ChannelFilter));
+        for name in config.plugins.iter() {
+            match self
+                .factories
+                .iter()
+                .find(|(registered, _)| registered == name)
+            {
+                Some((_, factory)) => {
+                    println!("Loading plugin {}", name);
+                    session.plugins.register(factory(&config, server_name));
+                }
+                None => println!("Unknown plugin {}", name),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod plugins_tests {
+    use std::sync::Arc;
+
+    use model::{
+        message::{Message, MessageType},
+        persistence::PersistenceType,
+        plugins::{HookAction, Plugin},
+        session::Session,
+    };
+
+    use super::{word_filter::WordFilter, PluginFactories};
+    use crate::{
+        commands::command_utils::{create_message_for_test, create_session_for_test},
+        locks::write_lock_config,
+    };
+
+    struct DenyJoins;
+
+    impl Plugin for DenyJoins {
+        fn name(&self) -> &str {
+            "denyjoins"
+        }
+
+        fn on_pre_command(
+            &self,
+            _session: &Session,
+            _nickname: &str,
+            message: &Message,
+        ) -> HookAction {
+            match message.command {
+                MessageType::Join => HookAction::Deny("No joins".to_string()),
+                _ => HookAction::Allow,
+            }
+        }
+    }
+
+    #[test]
+    fn test_plugins_see_the_rewrites_of_the_previous_ones_and_can_deny() {
+        let (tx, _rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        let session = create_session_for_test(tx);
+        session
+            .plugins
+            .register(Arc::new(WordFilter::new(vec!["darn".to_string()])));
+        session
+            .plugins
+            .register(Arc::new(WordFilter::new(vec!["heck".to_string()])));
+        session.plugins.register(Arc::new(DenyJoins));
+        let mut privmsg = create_message_for_test(MessageType::Privmsg, vec!["bob".to_string()]);
+        privmsg.trailing = Some("darn heck".to_string());
+        let join = create_message_for_test(MessageType::Join, vec!["&rust".to_string()]);
+        let part = create_message_for_test(MessageType::Part, vec!["&rust".to_string()]);
+
+        let mut expected = privmsg.clone();
+        expected.trailing = Some("**** ****".to_string());
+        assert_eq!(
+            session.plugins.pre_command(&session, "alice", &privmsg),
+            HookAction::Rewrite(expected)
+        );
+        assert_eq!(
+            session.plugins.pre_command(&session, "alice", &join),
+            HookAction::Deny("No joins".to_string())
+        );
+        assert_eq!(
+            session.plugins.pre_command(&session, "alice", &part),
+            HookAction::Allow
+        );
+    }
+
+    #[test]
+    fn test_plugins_listed_in_the_configuration_are_loaded() {
+        let (tx, _rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        let session = create_session_for_test(tx);
+        write_lock_config(&session).unwrap().plugins = vec![
+            "denyjoins".to_string(),
+            "unknown".to_string(),
+            "wordfilter".to_string(),
+        ];
+        let mut factories = PluginFactories::builtin();
+        factories.register("DenyJoins", |_, _| Arc::new(DenyJoins));
+
+        factories.load(&session, "test").unwrap();
+
+        assert_eq!(
+            session.plugins.names(),
+            vec!["channelfilter", "denyjoins", "wordfilter"]
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use model::{
+    message::{Message, MessageType},
+    plugins::{HookAction, Plugin},
+    session::Session,
+};
+
+use crate::commands::nickserv::is_nickserv;
+
+static CENSOR: &str = "*";
+
+/// Plugin that replaces the filtered words of the PRIVMSG and the NOTICE sent by the clients
+/// with asterisks, ignoring the case. The messages sent to NickServ are kept as they are, since
+/// they carry passwords.
+/// # Fields
+/// * `words` - The filtered words, in lowercase.
+pub struct WordFilter {
+    words: Vec<String>,
+}
+
+impl WordFilter {
+    /// Creates the plugin.
+    /// # Arguments
+    /// * `words` - The words to filter.
+    pub fn new(words: Vec<String>) -> WordFilter {
+        WordFilter {
+            words: words
+                .iter()
+                .map(|word| word.to_lowercase())
+                .filter(|word| !word.is_empty())
+                .collect(),
+        }
+    }
+
+    /// Returns the text with every filtered word replaced by asterisks, keeping the rest of it.
+    /// # Arguments
+    /// * `text` - The text of a message.
+    pub fn censor(&self, text: &str) -> String {
+        let mut censored = text.to_string();
+        for word in self.words.iter() {
+            censored = censor_word(&censored, word);
+        }
+        censored
+    }
+}
+
+impl Plugin for WordFilter {
+    fn name(&self) -> &str {
+        "wordfilter"
+    }
+
+    fn on_pre_command(&self, _session: &Session, _nickname: &str, message: &Message) -> HookAction {
+        if message.command != MessageType::Privmsg && message.command != MessageType::Notice {
+            return HookAction::Allow;
+        }
+        match message.parameters.first() {
+            Some(receivers) if !receivers.split(',').any(|r| is_nickserv(r.trim())) => (),
+            _ => return HookAction::Allow,
+        }
+
+        let mut filtered = message.clone();
+        let text = match (filtered.trailing.as_mut(), filtered.parameters.get_mut(1)) {
+            (Some(text), _) | (None, Some(text)) => text,
+            (None, None) => return HookAction::Allow,
+        };
+        let censored = self.censor(text);
+        if censored == *text {
+            return HookAction::Allow;
+        }
+        *text = censored;
+        HookAction::Rewrite(filtered)
+    }
+}
+
+/// Replaces every appearance of the word in the text, ignoring the case.
+fn censor_word(text: &str, word: &str) -> String {
+    let lowercase = text.to_lowercase();
+    if lowercase.len() != text.len() {
+        return text.to_string();
+    }
+    let mut censored = String::with_capacity(text.len());
+    let mut start = 0;
+    while let Some(found) = lowercase[start..].find(word) {
+        let found = start + found;
+        censored.push_str(&text[start..found]);
+        let end = found + word.len();
+        censored.push_str(&CENSOR.repeat(text[found..end].chars().count()));
+        start = end;
+    }
+    censored.push_str(&text[start..]);
+    censored
+}
+
+#[cfg(test)]
+mod word_filter_tests {
+    use model::{
+        message::MessageType,
+        persistence::PersistenceType,
+        plugins::{HookAction, Plugin},
+    };
+
+    use super::WordFilter;
+    use crate::commands::command_utils::{create_message_for_test, create_session_for_test};
+
+    #[test]
+    fn test_filtered_words_are_censored_ignoring_the_case() {
+        let filter = WordFilter::new(vec!["darn".to_string(), "heck".to_string()]);
+
+        assert_eq!(
+            filter.censor("Darn it, what the HECK"),
+            "**** it, what the ****"
+        );
+        assert_eq!(filter.censor("nothing to see"), "nothing to see");
+    }
+
+    #[test]
+    fn test_privmsg_is_rewritten_except_to_nickserv() {
+        let (tx, _rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        let session = create_session_for_test(tx);
+        let filter = WordFilter::new(vec!["darn".to_string()]);
+        let mut message = create_message_for_test(MessageType::Privmsg, vec!["#rust".to_string()]);
+        message.trailing = Some("darn it".to_string());
+        let mut to_nickserv = message.clone();
+        to_nickserv.parameters = vec!["NickServ".to_string()];
+
+        let mut expected = message.clone();
+        expected.trailing = Some("**** it".to_string());
+        assert_eq!(
+            filter.on_pre_command(&session, "alice", &message),
+            HookAction::Rewrite(expected)
+        );
+        assert_eq!(
+            filter.on_pre_command(&session, "alice", &to_nickserv),
+            HookAction::Allow
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::commands::ghost::drop_ghost;
 use crate::commands::nick::handle_nick_command;
 use crate::commands::pass::handle_pass_command;
 use crate::commands::user::handle_user_command;
@@ -15,6 +16,8 @@ use model::session::Session;
 /// * ServerError::ClientMustRegisterOrAuthenticate if the client is not registered or authenticated
 ///
 /// Returns a client if the registration is successful, if it is not completed yet, it will return None.
+/// A client that lost its connection can send GHOST to drop its stale session, or RECLAIM to drop it
+/// and take back the nickname.
 pub fn handle_registration(
     message: Message,
     nickname: &mut Option<String>,
@@ -38,8 +41,59 @@ pub fn handle_registration(
         MessageType::User => {
             return handle_user_command(message, nickname, user_parameters, password)
         }
+        MessageType::Ghost => {
+            if message.parameters.len() < 2 {
+                return Err(ServerError::InvalidParameters);
+            }
+            drop_ghost(&message.parameters[0], &message.parameters[1], session)?;
+        }
+        MessageType::Reclaim => {
+            return handle_reclaim_command(
+                message,
+                nickname,
+                user_parameters,
+                password,
+                session,
+                network,
+            )
+        }
         MessageType::Quit => println!("Unregistered client left the server"),
         _ => return Err(ServerError::ClientMustRegisterOrAuthenticate),
     }
     Ok(Option::None)
 }
+
+/// Handles the RECLAIM command, which drops the stale session that holds a nickname and
+/// takes the nickname back, as if a NICK with the password of the nickname was received.
+/// # Errors
+/// * ServerError::InvalidParameters if the nickname or the password are missing.
+/// * ServerError::InvalidPassword if the password is not the one of the nickname.
+fn handle_reclaim_command(
+    message: Message,
+    nickname: &mut Option<String>,
+    user_parameters: &mut Option<Vec<String>>,
+    password: &mut Option<String>,
+    session: &Session,
+    network: &Network,
+) -> Result<Option<Client>, ServerError> {
+    if message.parameters.len() < 2 {
+        return Err(ServerError::InvalidParameters);
+    }
+    let nick = message.parameters[0].to_owned();
+    let pass = message.parameters[1].to_owned();
+    match drop_ghost(&nick, &pass, session) {
+        Ok(_) | Err(ServerError::ClientNotConnected) => (),
+        Err(e) => return Err(e),
+    }
+
+    *password = Some(pass);
+    let nick_message = Message::new(None, MessageType::Nick, vec![nick], None);
+    handle_nick_command(
+        nick_message,
+        nickname,
+        user_parameters,
+        password,
+        session,
+        network,
+    )
+}
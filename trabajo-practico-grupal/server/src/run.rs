@@ -0,0 +1,348 @@
+use model::{
+    channel_registry::ChannelRegistry,
+    client_registry::ClientRegistry,
+    command_metrics::CommandMetrics,
+    events::EventBus,
+    gc_stats::GcStats,
+    link_queues::LinkQueues,
+    message::{Message, MessageType},
+    network::{LinkedServer, Network},
+    persistence::PersistenceType,
+    plugins::Plugins,
+    responses::{errors::ErrorResponse, numeric::WireFormat},
+    server::Server,
+    session::Session,
+    socket_registry::SocketRegistry,
+};
+use std::{
+    collections::HashMap,
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
+};
+
+use crate::{
+    admin::handle_admin,
+    audit::AuditLog,
+    ban_expiry::handle_ban_expiry,
+    channel_stats::MessageCounter,
+    channel_sync::handle_channel_sync,
+    client_handler::handle_client,
+    database::{handle_database, DatabasePaths},
+    gc::handle_garbage_collection,
+    handshake::{Handshake, HANDSHAKE_TIMEOUT, MAX_HANDSHAKE_ATTEMPTS},
+    idle::handle_idle_clients,
+    load::{load_channels, load_clients, load_config, load_network_clients},
+    locks::{
+        lock_connections, lock_rejected_connections, read_lock_config,
+        write_lock_servers_and_server,
+    },
+    plugins::PluginFactories,
+    server_errors::ServerError,
+    server_handler::{handle_father_comunication, handle_server, read_from_stdin},
+    server_notices::ServerNotices,
+    server_ping::handle_server_ping,
+    socket::{detect_wire_format, read_socket_as, write_socket_as},
+    websocket::handle_websocket,
+};
+
+static REFUSED_CONNECTION_TIMEOUT: Duration = Duration::from_secs(1);
+pub static REJECTED_TOO_MANY_CONNECTIONS: &str = "too many connections";
+pub static REJECTED_NO_MESSAGE: &str = "no first message";
+pub static REJECTED_UNPARSEABLE_MESSAGE: &str = "unparseable first message";
+pub static REJECTED_REGISTRATION_TIMEOUT: &str = "registration timed out";
+pub static REJECTED_FAILED_REGISTRATION: &str = "failed registration";
+
+/// Function that runs the server and handles the clients/servers connections
+/// # Arguments
+/// * `server` - the struct of the server.
+/// * `paths` - the files where the server loads and saves its state.
+pub fn server_run(server: Server, paths: DatabasePaths) -> Result<(), ServerError> {
+    server_run_with_plugins(server, paths, &PluginFactories::builtin())
+}
+
+/// Function that runs the server like `server_run`, with the plugins the configuration lists
+/// taken from the factories received, so that other crates can add their own plugins.
+/// # Arguments
+/// * `server` - the struct of the server.
+/// * `paths` - the files where the server loads and saves its state.
+/// * `factories` - the plugins that the configuration can load.
+pub fn server_run_with_plugins(
+    server: Server,
+    paths: DatabasePaths,
+    factories: &PluginFactories,
+) -> Result<(), ServerError> {
+    let address = format!("{}:{}", server.ip, server.port);
+    let listener = TcpListener::bind(address.to_owned())?;
+    println!("Listening on {}", address);
+
+    //uncomment to test multiserver in the same repository
+    // from here
+    let mut hash_clients = HashMap::new();
+    let mut hash_network_clients = HashMap::new();
+    let mut hash_channels = HashMap::new();
+    let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+    if server.father.is_none() {
+        hash_clients = load_clients(&paths)?;
+        hash_network_clients = load_network_clients(&hash_clients);
+        hash_channels = load_channels(&paths)?;
+        handle_database(db_rx, paths.clone());
+    }
+    // to here
+
+    //  uncomment to test multiserver in different repositories
+    /*
+    // from here
+    let hash_clients = load_clients(&paths)?;
+    let hash_network_clients = load_network_clients(&hash_clients);
+    let hash_channels = load_channels(&paths)?;
+
+    let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+    handle_database(db_rx, paths.clone());
+    // to here
+    */
+
+    let session = Session {
+        clients: ClientRegistry::new(hash_clients),
+        sockets: SocketRegistry::default(),
+        connections: Arc::new(Mutex::new(HashMap::new())),
+        rejected_connections: Arc::new(Mutex::new(HashMap::new())),
+        channels: ChannelRegistry::new(hash_channels),
+        database_sender: db_tx,
+        config: Arc::new(RwLock::new(load_config(&paths)?)),
+        events: EventBus::default(),
+        plugins: Plugins::default(),
+        who_queries: Arc::new(Mutex::new(HashMap::new())),
+        command_metrics: CommandMetrics::default(),
+        gc_stats: GcStats::default(),
+    };
+    factories.load(&session, &server.name)?;
+    let network = Network {
+        server: Arc::new(RwLock::new(server)),
+        servers: Arc::new(RwLock::new(HashMap::new())),
+        clients: Arc::new(RwLock::new(hash_network_clients)),
+        links: LinkQueues::default(),
+    };
+
+    server_listen(listener, session, network)
+}
+
+/// Function that starts the tasks of the server and accepts the connections of the listener,
+/// using the session and the network received as the state of the server.
+/// It only returns when the listener fails.
+/// # Arguments
+/// * `listener` - The listener bound to the address of the server.
+/// * `session` - The session of the server.
+/// * `network` - The network of the server, with the struct of the server itself.
+pub fn server_listen(
+    listener: TcpListener,
+    session: Session,
+    network: Network,
+) -> Result<(), ServerError> {
+    let (mut servers_lock, server_lock) = write_lock_servers_and_server(&network)?;
+    let server_name = server_lock.name.clone();
+
+    session
+        .events
+        .subscribe(Arc::new(ServerNotices::new(&server_name)));
+    session.events.subscribe(Arc::new(AuditLog));
+    session.events.subscribe(Arc::new(MessageCounter));
+    session.events.subscribe(Arc::new(session.plugins.clone()));
+    handle_ban_expiry(session.clone(), server_name.clone());
+    handle_idle_clients(session.clone(), network.clone(), server_name.clone());
+    handle_channel_sync(session.clone(), network.clone());
+    handle_server_ping(session.clone(), network.clone());
+    handle_garbage_collection(session.clone(), network.clone());
+
+    if let Some((father_name, father_socket)) = server_lock.father.to_owned() {
+        servers_lock.insert(father_name.to_owned(), LinkedServer::new(1));
+        drop(servers_lock);
+        drop(server_lock);
+        handle_father_comunication(session.clone(), network.clone(), father_name, father_socket)?;
+    } else {
+        drop(servers_lock);
+        drop(server_lock);
+        read_from_stdin(None, &session, &network);
+    }
+    if let Err(e) = handle_admin(session.clone(), network.clone(), server_name.clone()) {
+        println!("Error starting the admin interface: {:?}", e);
+    }
+    if let Err(e) = handle_websocket(session.clone(), network.clone(), server_name.clone()) {
+        println!("Error starting the WebSocket gateway: {:?}", e);
+    }
+
+    for stream in listener.incoming() {
+        let arc_socket = Arc::new(stream?);
+        let ip = match arc_socket.peer_addr() {
+            Ok(address) => address.ip().to_string(),
+            Err(e) => {
+                println!("Error reading the address of a connection: {:?}", e);
+                continue;
+            }
+        };
+        let accepted = open_connection(&session, &ip)?;
+        let session_clone = session.clone();
+        let network_clone = network.clone();
+        let sn = server_name.clone();
+        std::thread::spawn(move || {
+            if !accepted {
+                println!("Refusing connection from {}: too many connections", ip);
+                count_rejected_connection(&session_clone, REJECTED_TOO_MANY_CONNECTIONS);
+                if let Err(e) = refuse_connection(arc_socket, &ip) {
+                    println!("Error refusing connection: {:?}", e);
+                }
+                return;
+            }
+            match handle_connection(arc_socket, session_clone.clone(), network_clone, &sn) {
+                Ok(_) => (),
+                Err(e) => println!("Error handling connection: {:?}", e),
+            }
+            if let Err(e) = close_connection(&session_clone, &ip) {
+                println!("Error closing connection: {:?}", e);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// Counts a new connection from the IP received if it doesn't exceed
+/// the connections per IP allowed by the configuration.
+/// Returns true if the connection was counted and must be handled.
+/// # Arguments
+/// * `session` - The session of the current server.
+/// * `ip` - The IP the connection comes from.
+pub fn open_connection(session: &Session, ip: &str) -> Result<bool, ServerError> {
+    let limit = read_lock_config(session)?.connections_per_ip;
+    let mut connections = lock_connections(session)?;
+    let open = connections.get(ip).copied().unwrap_or(0);
+    if open >= limit {
+        return Ok(false);
+    }
+    connections.insert(ip.to_string(), open + 1);
+    Ok(true)
+}
+
+/// Stops counting a connection from the IP received, once it is closed.
+/// # Arguments
+/// * `session` - The session of the current server.
+/// * `ip` - The IP the connection comes from.
+pub fn close_connection(session: &Session, ip: &str) -> Result<(), ServerError> {
+    let mut connections = lock_connections(session)?;
+    match connections.get(ip).copied() {
+        Some(open) if open > 1 => {
+            connections.insert(ip.to_string(), open - 1);
+        }
+        _ => {
+            connections.remove(ip);
+        }
+    }
+    Ok(())
+}
+
+/// Sends the error of too many connections to a connection that is not handled,
+/// in the wire format it uses. If the connection sends nothing for a while,
+/// the error is sent in the format of our client.
+/// # Arguments
+/// * `arc_socket` - The socket of the connection refused.
+/// * `ip` - The IP the connection comes from.
+fn refuse_connection(arc_socket: Arc<TcpStream>, ip: &str) -> Result<(), ServerError> {
+    arc_socket.set_read_timeout(Some(REFUSED_CONNECTION_TIMEOUT))?;
+    let format = detect_wire_format(arc_socket.clone()).unwrap_or(WireFormat::Custom);
+    let response = ErrorResponse::TooManyConnections {
+        host: ip.to_string(),
+    }
+    .to_string();
+    write_socket_as(arc_socket, &response, format, "*")
+}
+
+/// Counts a connection rejected for the reason received in the metrics of the server.
+/// # Arguments
+/// * `session` - The session of the current server.
+/// * `reason` - Why the connection was rejected.
+pub fn count_rejected_connection(session: &Session, reason: &str) {
+    match lock_rejected_connections(session) {
+        Ok(mut rejected) => *rejected.entry(reason.to_string()).or_insert(0) += 1,
+        Err(e) => println!("Error counting rejected connection: {:?}", e),
+    }
+}
+
+/// Function that matches the message to decide if it
+/// handles a server or a client connection.
+/// The wire format of the connection is detected from its first message.
+/// A connection that sends nothing or only messages that can't be parsed is dropped,
+/// and counted in the rejected connections. The handshake that bounds the first message
+/// goes on until the client registers, and a server gets the read timeout of the
+/// configuration once it sends its first message.
+/// # Arguments
+/// * `arc_socket` - Reference of new connection socket.
+/// * `session` - The session of the current server.
+/// * `network` - The network of the current server.
+/// * `server_name` - The name of the current server.
+pub fn handle_connection(
+    arc_socket: Arc<TcpStream>,
+    session: Session,
+    network: Network,
+    server_name: &String,
+) -> Result<(), ServerError> {
+    let config = read_lock_config(&session)?;
+    let (read_timeout, write_timeout) = (config.read_timeout(), config.write_timeout());
+    drop(config);
+    arc_socket.set_write_timeout(write_timeout)?;
+    let mut handshake = Handshake::new(HANDSHAKE_TIMEOUT, MAX_HANDSHAKE_ATTEMPTS);
+    let (message, format) = match read_first_message(arc_socket.clone(), &mut handshake) {
+        Ok(first) => first,
+        Err(e) => {
+            let reason = match e {
+                ServerError::InvalidMessage => REJECTED_UNPARSEABLE_MESSAGE,
+                _ => REJECTED_NO_MESSAGE,
+            };
+            println!("Dropping new connection: {}", reason);
+            count_rejected_connection(&session, reason);
+            return Err(e);
+        }
+    };
+    if message.command == MessageType::Server {
+        arc_socket.set_read_timeout(read_timeout)?;
+        handle_server(arc_socket, message, session, network)?;
+    } else {
+        let connection = (arc_socket, format, handshake);
+        handle_client(connection, message, session, network, server_name)?;
+    }
+
+    Ok(())
+}
+
+/// Reads the first message of a new connection, with the wire format it uses.
+/// The messages that can't be parsed are answered with the error of unknown command, and the
+/// ones that are not valid UTF-8 with the error of invalid encoding, until the handshake runs
+/// out of attempts.
+/// # Arguments
+/// * `arc_socket` - The socket of the new connection.
+/// * `handshake` - The deadline and the attempts of the connection.
+/// # Errors
+/// Returns `ServerError::InvalidMessage` if none of the messages can be parsed, or the error
+/// of the socket if the connection closes or sends nothing before the deadline.
+fn read_first_message(
+    arc_socket: Arc<TcpStream>,
+    handshake: &mut Handshake,
+) -> Result<(Message, WireFormat), ServerError> {
+    handshake.set_read_timeout(&arc_socket)?;
+    let format = detect_wire_format(arc_socket.clone())?;
+    loop {
+        handshake.set_read_timeout(&arc_socket)?;
+        let response = match read_socket_as(arc_socket.clone(), format) {
+            Ok(message_str) => match Message::serialize(message_str) {
+                Ok(message) => return Ok((message, format)),
+                Err(_) => ErrorResponse::UnknownCommand {
+                    command: "".to_string(), // como se el comando si me tiro error el serialize?
+                }
+                .to_string(),
+            },
+            Err(ServerError::InvalidEncoding) => ErrorResponse::InvalidEncoding.to_string(),
+            Err(e) => return Err(e),
+        };
+        write_socket_as(arc_socket.clone(), &response, format, "*")?;
+        handshake.fail()?;
+    }
+}
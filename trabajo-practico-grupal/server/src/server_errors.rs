@@ -6,7 +6,7 @@ use std::{
     },
 };
 
-use model::{dcc::DccMessageError, message::MessageError};
+use model::{dcc::DccMessageError, link_queues::LinkQueueError, message::MessageError};
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum ServerError {
@@ -15,6 +15,7 @@ pub enum ServerError {
     InvalidCommand,
     InvalidArgs,
     InvalidMessage,
+    InvalidEncoding,
     InvalidParameters,
     InvalidPort,
     ClientAlreadyRegistered,
@@ -36,8 +37,9 @@ pub enum ServerError {
     LockError,
     CannotWriteSocket,
     CannotReadFromSocket,
+    SocketTimeout,
+    LinkQueueFull,
     ChannelIsInviteOnly,
-    ChannelIsSecret,
     MustInsertPassword,
     CannotRemoveLastOperator,
     InvalidFlags,
@@ -62,6 +64,14 @@ pub enum ServerError {
     InvalidPassword,
     NotOnChannel,
     ErroneusNickname,
+    SpoofedPrefix,
+    ChannelCreationDenied,
+    NicknameAlreadyRegistered,
+    NicknameNotRegistered,
+    SilenceListFull,
+    TooManyChannels,
+    TooManyTargets,
+    InvalidRequest,
     Other,
 }
 
@@ -71,6 +81,8 @@ impl From<MessageError> for ServerError {
             MessageError::EmptyCommand => ServerError::EmptyCommand,
             MessageError::EmptyMessage => ServerError::EmptyMessage,
             MessageError::InvalidCommand => ServerError::InvalidCommand,
+            MessageError::InvalidPrefix => ServerError::InvalidMessage,
+            MessageError::InvalidEncoding => ServerError::InvalidEncoding,
         }
     }
 }
@@ -86,10 +98,20 @@ impl From<DccMessageError> for ServerError {
     }
 }
 
+impl From<LinkQueueError> for ServerError {
+    fn from(error: LinkQueueError) -> Self {
+        match error {
+            LinkQueueError::Full => ServerError::LinkQueueFull,
+            LinkQueueError::Closed => ServerError::CannotWriteSocket,
+        }
+    }
+}
+
 impl From<Error> for ServerError {
     fn from(e: Error) -> Self {
         match e.kind() {
             ErrorKind::InvalidInput => ServerError::InvalidPort,
+            ErrorKind::WouldBlock | ErrorKind::TimedOut => ServerError::SocketTimeout,
             _ => ServerError::Other,
         }
     }
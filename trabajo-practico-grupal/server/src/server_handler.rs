@@ -1,15 +1,26 @@
-use std::{net::TcpStream, sync::Arc};
+use std::{
+    net::{Shutdown, TcpStream},
+    sync::Arc,
+};
 
 use model::{
+    consts::is_channel_name,
+    events::ServerEvent,
     message::{Message, MessageType},
-    network::Network,
+    network::{LinkedServer, Network},
+    prefix::Prefix,
     responses::{replies::CommandResponse, response::Response},
     session::Session,
 };
 
+use crate::locks::{
+    read_lock_channels, read_lock_clients, read_lock_network_clients, read_lock_servers,
+    write_lock_network_clients, write_lock_server, write_lock_servers,
+    write_lock_servers_and_server,
+};
 use crate::{
+    channel_sync::{handle_chansum_command, handle_chansync_command},
     commands::{
-        command_utils::{read_lock_channels, read_lock_clients},
         invite::handle_invite_command,
         join::handle_join_command,
         kick::handle_kick_command,
@@ -17,21 +28,27 @@ use crate::{
         mode::handle_mode_command,
         names::handle_names_command,
         part::handle_part_command,
-        privmsg::handle_privmsg_command,
+        privmsg::{handle_notice_command, handle_privmsg_command},
         server::handle_server_command,
         server_commands_handler::{
             handle_mode_server_reply, handle_server_away_command, handle_server_dcc_command,
             handle_server_list_reply, handle_server_names_reply, handle_server_nick_command,
-            handle_server_server_reply, handle_server_who_reply,
+            handle_server_reply_command, handle_server_server_reply, handle_server_user_command,
+            handle_server_user_mode_command, handle_server_who_reply,
         },
         squit::handle_squit_command,
         topic::handle_topic_command,
-        who::handle_who_command,
+        who::{handle_remote_who_query, handle_who_command},
+        whois::handle_remote_whois_query,
     },
     server_errors::ServerError,
-    socket::{read_socket, write_socket},
+    server_ping::{handle_server_ping_command, handle_server_pong_command},
+    socket::{read_link_socket, write_link, write_socket},
 };
 
+/// The reason published when the link with a server is closed.
+static LINK_CLOSED_REASON: &str = "Link closed";
+
 /// Function that handles the server connection.
 /// If the credentials are right ir registers the client and
 /// proceeds to handle the messages.
@@ -49,7 +66,7 @@ pub fn handle_server(
     let mut name = None;
     register_server(message, &mut name, arc_socket.clone(), &network)?;
     if let Some(n) = name {
-        while let Ok(msg_str) = read_socket(arc_socket.clone()) {
+        while let Ok(msg_str) = read_link_socket(arc_socket.clone()) {
             match Message::serialize(msg_str.to_owned()) {
                 Ok(msg) => {
                     match handle_server_message(msg, &n, &session, &network) {
@@ -70,20 +87,53 @@ pub fn handle_server(
                 }
             };
         }
-        disconnect_server(&network, &n)?;
+        split_from_server(&n, LINK_CLOSED_REASON, &session, &network)?;
     }
     Ok(())
 }
 
-/// Function that deletes a server from the network struct
-/// when it disconnects.
+/// Splits the network at the link with a server that was lost: the link is closed, and the
+/// server and the clients reached through it are forgotten. A link that was already split
+/// is ignored, so the reader of the link and the one that dropped it can both split it.
 /// # Arguments
+/// * `server_name` - The name of the linked server.
+/// * `reason` - Why the link was lost, published with the quit of the server.
+/// * `session` - The session of the current server.
 /// * `network` - The struct that contains the information of the network.
-/// * `server_name` - The name of the server that disconnected.
-fn disconnect_server(network: &Network, server_name: &String) -> Result<(), ServerError> {
-    let mut servers_lock = network.servers.write()?;
-    servers_lock.remove(server_name);
-    drop(servers_lock);
+pub fn split_from_server(
+    server_name: &str,
+    reason: &str,
+    session: &Session,
+    network: &Network,
+) -> Result<(), ServerError> {
+    write_lock_network_clients(network)?
+        .retain(|_, client| client.link.as_deref() != Some(server_name));
+
+    let (mut servers, mut server) = write_lock_servers_and_server(network)?;
+    servers.remove(server_name);
+    let socket = match server.father.take() {
+        Some((father_name, socket)) if father_name == server_name => Some(socket),
+        father => {
+            server.father = father;
+            server.children.remove(server_name)
+        }
+    };
+    drop(server);
+    drop(servers);
+    network.links.remove(server_name);
+
+    let socket = match socket {
+        Some(socket) => socket,
+        None => return Ok(()),
+    };
+    if let Err(e) = socket.shutdown(Shutdown::Both) {
+        println!("Error closing the link with {}: {:?}", server_name, e);
+    }
+    println!("Link with {} is dead: {}", server_name, reason);
+    session.publish(ServerEvent::ServerQuit {
+        server: server_name.to_string(),
+        reason: Some(reason.to_string()),
+    });
     Ok(())
 }
 
@@ -104,29 +154,56 @@ fn handle_server_message(
             handle_server_command(message, name, network)?;
         }
         MessageType::Squit => {
+            let reason = message.trailing.to_owned();
             handle_squit_command(message, name, network)?;
+            session.publish(ServerEvent::ServerQuit {
+                server: name.to_owned(),
+                reason,
+            });
         }
         MessageType::Privmsg => {
-            let nickname = match message.prefix.to_owned() {
+            let nickname = match message.prefix_name() {
                 Some(p) => p,
                 None => "".to_owned(),
             };
-            handle_privmsg_command(message, &nickname, session, network, name)?;
+            handle_privmsg_command(message, &nickname, session, network, name)?.into_result()?;
+        }
+        MessageType::Notice => {
+            let nickname = match message.prefix_name() {
+                Some(p) => p,
+                None => "".to_owned(),
+            };
+            handle_notice_command(message, &nickname, session, network, name)?;
         }
         MessageType::Nick => {
             handle_server_nick_command(message, name, session, network)?;
         }
+        MessageType::User => {
+            handle_server_user_command(message, name, network)?;
+        }
         MessageType::Who => {
-            handle_who_command(
-                message,
-                "".to_owned(),
-                session,
-                network,
-                Some(name.to_owned()),
-            )?;
+            // the queries of the clients have their nickname as prefix, unlike the WHO sent to
+            // learn the clients of the network when the servers are linked
+            if message.prefix_name().filter(|p| !p.is_empty()).is_some() {
+                handle_remote_who_query(message, name, session, network)?;
+            } else {
+                handle_who_command(
+                    message,
+                    "".to_owned(),
+                    session,
+                    network,
+                    Some(name.to_owned()),
+                )?;
+            }
+        }
+        MessageType::WhoIs => {
+            handle_remote_whois_query(message, name, session, network)?;
+        }
+        MessageType::Reply => {
+            handle_server_reply_command(message, session, network)?;
         }
         MessageType::List => {
-            if let Some(prx) = message.prefix.to_owned() {
+            if let Some(prx) = message.prefix_name() {
                 let clients = read_lock_clients(session)?;
                 if clients.get(&prx).is_some() {
                     drop(clients);
@@ -145,7 +222,7 @@ fn handle_server_message(
             }
         }
         MessageType::Names => {
-            if let Some(prx) = message.prefix.to_owned() {
+            if let Some(prx) = message.prefix_name() {
                 let clients = read_lock_clients(session)?;
                 if clients.get(&prx).is_some() {
                     drop(clients);
@@ -164,46 +241,50 @@ fn handle_server_message(
             }
         }
         MessageType::Join => {
-            let nickname = match message.prefix.to_owned() {
+            let nickname = match message.prefix_name() {
                 Some(p) => p,
                 None => "".to_owned(),
             };
-            handle_join_command(message, &nickname, session, network, name)?;
+            handle_join_command(message, &nickname, session, network, name)?.into_result()?;
         }
         MessageType::Invite => {
-            let nickname = match message.prefix.to_owned() {
+            let nickname = match message.prefix_name() {
                 Some(p) => p,
                 None => "".to_owned(),
             };
             handle_invite_command(message, nickname, session, network, name)?;
         }
         MessageType::Kick => {
-            let nickname = match message.prefix.to_owned() {
+            let nickname = match message.prefix_name() {
                 Some(p) => p,
                 None => "".to_owned(),
             };
             handle_kick_command(message, nickname, session, network, name)?;
         }
         MessageType::Part => {
-            let nickname = match message.prefix.to_owned() {
+            let nickname = match message.prefix_name() {
                 Some(p) => p,
                 None => "".to_owned(),
             };
-            handle_part_command(message, nickname, session, network, name)?;
+            handle_part_command(message, nickname, session, network, name)?.into_result()?;
         }
         MessageType::Topic => {
-            let nickname = match message.prefix.to_owned() {
+            let nickname = match message.prefix_name() {
                 Some(p) => p,
                 None => "".to_owned(),
             };
             handle_topic_command(message, nickname, session, network, name)?;
         }
         MessageType::Mode => {
-            let nickname = match message.prefix.to_owned() {
+            let nickname = match message.prefix_name() {
                 Some(p) => p,
                 None => "".to_owned(),
             };
-            handle_mode_command(message, nickname, session, network, name)?;
+            if message.parameters.is_empty() || is_channel_name(&message.parameters[0]) {
+                handle_mode_command(message, nickname, session, network, name)?;
+            } else {
+                handle_server_user_mode_command(message, name, network)?;
+            }
         }
         MessageType::Away => {
             handle_server_away_command(message, name, session, network)?;
@@ -211,6 +292,18 @@ fn handle_server_message(
         MessageType::Dcc => {
             handle_server_dcc_command(message, name, session, network)?;
         }
+        MessageType::ChanSum => {
+            handle_chansum_command(message, name, session, network)?;
+        }
+        MessageType::ChanSync => {
+            handle_chansync_command(message, name, session, network)?;
+        }
+        MessageType::Ping => {
+            handle_server_ping_command(name, network)?;
+        }
+        MessageType::Pong => {
+            handle_server_pong_command(name, network)?;
+        }
         _ => {}
     }
     Ok(())
@@ -237,7 +330,11 @@ fn handle_server_response(
             CommandResponse::Names { channel, names } => {
                 handle_server_names_reply(channel, names, session, network, name)?;
             }
-            CommandResponse::ChannelMode { channel, modes } => {
+            CommandResponse::ChannelMode {
+                channel,
+                mode_string: _,
+                modes,
+            } => {
                 handle_mode_server_reply(channel, modes, session, network)?;
             }
             CommandResponse::List { channel, topic } => {
@@ -275,13 +372,13 @@ fn register_server(
         None => String::new(),
     };
 
-    let mut servers_lock = network.servers.as_ref().write()?;
+    let mut servers_lock = write_lock_servers(network)?;
     if servers_lock.get(&child_name).is_some() {
         drop(servers_lock);
         return Err(ServerError::ServerAlreadyRegistered);
     }
 
-    let mut server_lock = network.server.as_ref().write()?;
+    let mut server_lock = write_lock_server(network)?;
     if server_lock.children.get(&child_name).is_some() {
         return Err(ServerError::ServerAlreadyRegistered);
     } else {
@@ -291,28 +388,31 @@ fn register_server(
         server_lock
             .children
             .insert(child_name.to_owned(), arc_socket);
-        servers_lock.insert(child_name.to_owned(), hopcount);
+        servers_lock.insert(child_name.to_owned(), LinkedServer::new(hopcount));
 
         let mut msg = message;
-        msg.prefix = Some(server_lock.name.to_owned());
+        msg.prefix = Some(Prefix::ServerName(server_lock.name.to_owned()));
         msg.parameters[1] = (hopcount + 1).to_string();
         let buff = Message::deserialize(msg.to_owned())?;
 
-        if let Some((_father_name, father_socket)) = server_lock.father.to_owned() {
-            write_socket(father_socket, &buff)?;
+        if let Some((father_name, father_socket)) = server_lock.father.as_ref() {
+            // A link that overflows is split by its own reader.
+            let _ = write_link(network, father_name, father_socket, &buff);
         }
-        for (child_server_name, child_socket) in server_lock.children.clone().into_iter() {
+        for (child_server_name, child_socket) in server_lock.children.iter() {
             if *child_server_name != child_name {
-                write_socket(child_socket, &buff)?;
+                let _ = write_link(network, child_server_name, child_socket, &buff);
             } else {
                 let response = CommandResponse::Server {
-                    servers: servers_lock.clone(),
+                    servers: servers_lock
+                        .iter()
+                        .map(|(name, server)| (name.to_owned(), server.hopcount))
+                        .collect(),
                 }
                 .to_string();
-                write_socket(child_socket.clone(), &response)?;
-                write_socket(child_socket.clone(), "WHO")?;
-                write_socket(child_socket.clone(), "NAMES")?;
-                write_socket(child_socket, "LIST")?;
+                for message in [response.as_str(), "WHO", "NAMES", "LIST"] {
+                    write_link(network, &child_name, child_socket, message)?;
+                }
             }
         }
     }
@@ -342,18 +442,10 @@ pub fn read_from_stdin(
         if father_socket.is_none() {
             first_command = false;
         }
-        let mut fetched = false;
         loop {
-            if father_socket.is_some() && !first_command && !fetched {
-                if let Some(socket) = father_socket.to_owned() {
-                    fetched = true;
-                    if write_socket(socket.clone(), "WHO").is_ok() {};
-                    if write_socket(socket.clone(), "NAMES").is_ok() {};
-                    if write_socket(socket.clone(), "LIST").is_ok() {};
-                }
-            }
             let mut buff = String::new();
             match stdin.read_line(&mut buff) {
+                Ok(0) => break,
                 Ok(_) => {
                     if buff.starts_with("INFO") {
                         print_server_info(&session_clone, &network_clone);
@@ -364,7 +456,7 @@ pub fn read_from_stdin(
                             first_command = false;
                         }
                         if let Some(socket) = father_socket.to_owned() {
-                            if write_socket(socket.clone(), &buff).is_ok() {}
+                            if register_in_father(socket, &buff).is_ok() {}
                         }
                     } else if first_command && !buff.starts_with("SERVER") {
                         println!("You must register to the server with 'SERVER' command");
@@ -381,6 +473,19 @@ pub fn read_from_stdin(
     });
 }
 
+/// Function that registers the current server in its father with the SERVER message received,
+/// and then asks the father for the clients and channels of the network.
+/// # Arguments
+/// * `father_socket` - The socket of the father server.
+/// * `message` - The SERVER message, like `SERVER name 1 :info`.
+pub fn register_in_father(father_socket: Arc<TcpStream>, message: &str) -> Result<(), ServerError> {
+    write_socket(father_socket.clone(), message)?;
+    write_socket(father_socket.clone(), "WHO")?;
+    write_socket(father_socket.clone(), "NAMES")?;
+    write_socket(father_socket, "LIST")?;
+    Ok(())
+}
+
 /// Function thar prints the actual server
 /// information in console.
 /// # Arguments
@@ -397,12 +502,12 @@ fn print_server_info(session: &Session, network: &Network) {
         println!("{:?}", channels);
     }
 
-    if let Ok(clients) = network.clients.as_ref().read() {
+    if let Ok(clients) = read_lock_network_clients(network) {
         println!("Clients:");
         println!("{:?}", clients);
     }
 
-    if let Ok(servers) = network.servers.as_ref().read() {
+    if let Ok(servers) = read_lock_servers(network) {
         println!("Servers:");
         println!("{:?}", servers);
     }
@@ -422,7 +527,7 @@ pub fn handle_father_comunication(
 ) -> Result<(), ServerError> {
     read_from_stdin(Some(father_socket.clone()), &session, &network);
     std::thread::spawn(move || {
-        while let Ok(msg) = read_socket(father_socket.clone()) {
+        while let Ok(msg) = read_link_socket(father_socket.clone()) {
             match Message::serialize(msg.to_owned()) {
                 Ok(m) => {
                     match handle_server_message(m, &father_name, &session, &network) {
@@ -441,6 +546,9 @@ pub fn handle_father_comunication(
                 },
             };
         }
+        if let Err(e) = split_from_server(&father_name, LINK_CLOSED_REASON, &session, &network) {
+            println!("Error splitting from the father server {:?}", e);
+        }
     });
     Ok(())
 }
@@ -0,0 +1,159 @@
+use model::{
+    events::{ServerEvent, Subscriber},
+    responses::message::MessageResponse,
+    session::Session,
+    userflag::UserFlag,
+};
+
+use crate::{locks::read_lock_clients, server_errors::ServerError, socket::inform_client};
+
+/// Subscriber that sends the server notices, about the clients that connect to this server and
+/// leave it, to the clients of this server with the `+s` mode.
+/// # Fields
+/// * `server_name` - The name of this server, the sender of the notices.
+pub struct ServerNotices {
+    server_name: String,
+}
+
+impl ServerNotices {
+    /// Creates the subscriber of the server notices.
+    /// # Arguments
+    /// * `server_name` - The name of this server.
+    pub fn new(server_name: &str) -> ServerNotices {
+        ServerNotices {
+            server_name: server_name.to_owned(),
+        }
+    }
+}
+
+impl Subscriber for ServerNotices {
+    fn notify(&self, session: &Session, event: &ServerEvent) {
+        let (nickname, text) = match event {
+            ServerEvent::ClientRegistered {
+                nickname,
+                username,
+                hostname,
+            } => (
+                nickname,
+                format!(
+                    "Client connecting: {} ({}@{})",
+                    nickname, username, hostname
+                ),
+            ),
+            ServerEvent::ClientDisconnected { nickname } => {
+                (nickname, format!("Client exiting: {}", nickname))
+            }
+            _ => return,
+        };
+        if let Err(e) = send_server_notice(session, &self.server_name, nickname, &text) {
+            println!("Error sending server notice: {:?}", e);
+        }
+    }
+}
+
+/// Sends the notice to the connected clients with the `+s` mode, except the client it is about.
+/// # Arguments
+/// * `session` - The session of the current server.
+/// * `server_name` - The name of the current server.
+/// * `subject` - The nickname of the client the notice is about.
+/// * `text` - The text of the notice.
+fn send_server_notice(
+    session: &Session,
+    server_name: &str,
+    subject: &str,
+    text: &str,
+) -> Result<(), ServerError> {
+    let clients = read_lock_clients(session)?;
+    let receivers = clients
+        .values()
+        .filter(|client| client.connected && client.nickname != subject)
+        .filter(|client| client.modes.contains(&UserFlag::ServerNotice))
+        .map(|client| client.nickname.to_owned())
+        .collect::<Vec<_>>();
+    drop(clients);
+
+    let response = MessageResponse::UserNotice {
+        sender: server_name.to_owned(),
+        message: text.to_owned(),
+    }
+    .to_string();
+    for receiver in receivers {
+        if let Err(e) = inform_client(session, &receiver, &response) {
+            println!("Error sending server notice to {}: {:?}", receiver, e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod server_notices_tests {
+    use model::consts::MAX_MSG_SIZE;
+    use std::{io::Read, net::TcpListener, sync::Arc, time::Duration};
+
+    use model::{
+        events::ServerEvent,
+        persistence::PersistenceType,
+        responses::{message::MessageResponse, response::Response},
+        userflag::UserFlag,
+    };
+
+    use super::ServerNotices;
+    use crate::{
+        commands::command_utils::{create_client_for_test, create_session_for_test},
+        database::{handle_database, DatabasePaths},
+        locks::write_lock_clients,
+    };
+
+    #[test]
+    fn test_clients_with_server_notices_are_told_about_connections() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        session
+            .events
+            .subscribe(Arc::new(ServerNotices::new("test")));
+        create_client_for_test(&session, addr.to_owned(), "oper".to_string());
+        let (mut oper, _) = listener.accept().unwrap();
+        create_client_for_test(&session, addr, "other".to_string());
+        let (mut other, _) = listener.accept().unwrap();
+        let mut clients = write_lock_clients(&session).unwrap();
+        let oper_client = clients.get_mut("oper").unwrap();
+        oper_client.modes.push(UserFlag::ServerNotice);
+        drop(clients);
+
+        session.publish(ServerEvent::ClientRegistered {
+            nickname: "alice".to_string(),
+            username: "al".to_string(),
+            hostname: "host".to_string(),
+        });
+        session.publish(ServerEvent::ChannelJoined {
+            channel: "#rust".to_string(),
+            nickname: "alice".to_string(),
+        });
+        session.publish(ServerEvent::ClientDisconnected {
+            nickname: "alice".to_string(),
+        });
+
+        for expected in [
+            "Client connecting: alice (al@host)",
+            "Client exiting: alice",
+        ] {
+            let mut buf = vec![0u8; MAX_MSG_SIZE];
+            oper.read_exact(&mut buf).unwrap();
+            let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+            let response = Response::serialize(String::from_utf8(msg).unwrap());
+            assert!(matches!(
+                response,
+                Some(Response::MessageResponse {
+                    response: MessageResponse::UserNotice { sender, message }
+                }) if sender == "test" && message == expected
+            ));
+        }
+        other
+            .set_read_timeout(Some(Duration::from_millis(100)))
+            .unwrap();
+        assert!(other.read(&mut [0u8; MAX_MSG_SIZE]).is_err());
+    }
+}
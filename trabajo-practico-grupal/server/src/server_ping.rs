@@ -0,0 +1,185 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use model::{network::Network, session::Session};
+
+use crate::{
+    locks::{read_lock_server, write_lock_servers},
+    server_errors::ServerError,
+    server_handler::split_from_server,
+    socket::inform_server,
+};
+
+static PING_INTERVAL: u64 = 30;
+/// The PINGs in a row a linked server may leave unanswered before its link is declared dead.
+static MAX_MISSED_PONGS: u8 = 3;
+static PING_TIMEOUT_REASON: &str = "Ping timeout";
+
+/// Function that periodically sends a PING to the servers linked to this one, measuring the
+/// latency of each link and dropping the links that stopped answering.
+/// # Arguments
+/// * `session` - The session of the current server.
+/// * `network` - The struct that contains the information of the network.
+pub fn handle_server_ping(session: Session, network: Network) {
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(PING_INTERVAL));
+        if let Err(e) = ping_linked_servers(&session, &network, Instant::now()) {
+            println!("Error pinging the linked servers: {:?}", e);
+        }
+    });
+}
+
+/// Sends `PING <server>` to the father and the children of this server.
+/// The links that left the last pings unanswered are split from the network instead.
+/// # Arguments
+/// * `session` - The session of the current server.
+/// * `network` - The struct that contains the information of the network.
+/// * `now` - When the pings are sent.
+pub fn ping_linked_servers(
+    session: &Session,
+    network: &Network,
+    now: Instant,
+) -> Result<(), ServerError> {
+    let mut servers = write_lock_servers(network)?;
+    let server = read_lock_server(network)?;
+    let message = format!("PING {}", server.name);
+    let mut links = server.children.keys().cloned().collect::<Vec<_>>();
+    if let Some((father_name, _)) = server.father.as_ref() {
+        links.push(father_name.to_owned());
+    }
+    drop(server);
+
+    let mut alive = vec![];
+    let mut dead = vec![];
+    for link in links {
+        match servers.get_mut(&link).map(|linked| linked.record_ping(now)) {
+            Some(missed) if missed >= MAX_MISSED_PONGS => dead.push(link),
+            Some(_) => alive.push(link),
+            None => (),
+        }
+    }
+    drop(servers);
+
+    for link in alive {
+        if let Err(e) = inform_server(network, &link, &message) {
+            println!("Error pinging server {}: {:?}", link, e);
+        }
+    }
+    for link in dead {
+        split_from_server(&link, PING_TIMEOUT_REASON, session, network)?;
+    }
+    Ok(())
+}
+
+/// Handles the PING message of a linked server, answering it with `PONG <server>`.
+/// # Arguments
+/// * `server_name` - The name of the server that sent the message.
+/// * `network` - The struct that contains the information of the network.
+pub fn handle_server_ping_command(server_name: &str, network: &Network) -> Result<(), ServerError> {
+    let message = format!("PONG {}", read_lock_server(network)?.name);
+    inform_server(network, &server_name.to_string(), &message)
+}
+
+/// Handles the PONG message of a linked server, saving the latency of its link.
+/// # Arguments
+/// * `server_name` - The name of the server that sent the message.
+/// * `network` - The struct that contains the information of the network.
+pub fn handle_server_pong_command(server_name: &str, network: &Network) -> Result<(), ServerError> {
+    if let Some(linked) = write_lock_servers(network)?.get_mut(server_name) {
+        linked.record_pong(Instant::now());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod server_ping_tests {
+    use std::{
+        collections::HashMap,
+        io::Read,
+        net::{TcpListener, TcpStream},
+        sync::{Arc, RwLock},
+        time::Instant,
+    };
+
+    use model::{
+        consts::MAX_MSG_SIZE,
+        link_queues::LinkQueues,
+        message::Message,
+        network::{LinkedServer, Network, RemoteClient},
+        persistence::PersistenceType,
+        server::Server,
+    };
+
+    use super::{handle_server_pong_command, ping_linked_servers};
+    use crate::commands::command_utils::create_session_for_test;
+
+    fn read_message_for_test(reader: &mut TcpStream) -> String {
+        let mut buf = vec![0u8; MAX_MSG_SIZE];
+        reader.read_exact(&mut buf).unwrap();
+        let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+        String::from_utf8(msg).unwrap()
+    }
+
+    /// Creates a main server with the child `child`, which reached the client `bob`,
+    /// and returns the end where the child reads.
+    fn create_father_for_test() -> (Network, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let child_socket = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (child_reader, _) = listener.accept().unwrap();
+        let network = Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "father".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::from([("child".to_string(), Arc::new(child_socket))]),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::from([(
+                "child".to_string(),
+                LinkedServer::new(1),
+            )]))),
+            clients: Arc::new(RwLock::new(HashMap::from([(
+                "bob".to_string(),
+                RemoteClient::new(1).with_link("child"),
+            )]))),
+            links: LinkQueues::default(),
+        };
+        (network, child_reader)
+    }
+
+    #[test]
+    fn test_pong_of_the_child_saves_the_latency_of_the_link() {
+        let (tx, _rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        let session = create_session_for_test(tx);
+        let (network, mut child_reader) = create_father_for_test();
+
+        ping_linked_servers(&session, &network, Instant::now()).unwrap();
+        let ping = Message::serialize(read_message_for_test(&mut child_reader)).unwrap();
+        handle_server_pong_command("child", &network).unwrap();
+
+        assert_eq!(ping.parameters, vec!["father".to_string()]);
+        let servers = network.servers.read().unwrap();
+        assert!(servers["child"].latency.is_some());
+        assert_eq!(servers["child"].missed_pongs, 0);
+    }
+
+    #[test]
+    fn test_child_that_misses_the_pongs_is_split_from_the_network() {
+        let (tx, _rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        let session = create_session_for_test(tx);
+        let (network, _child_reader) = create_father_for_test();
+
+        for _ in 0..3 {
+            ping_linked_servers(&session, &network, Instant::now()).unwrap();
+            assert!(network.servers.read().unwrap().contains_key("child"));
+        }
+        ping_linked_servers(&session, &network, Instant::now()).unwrap();
+
+        assert!(!network.servers.read().unwrap().contains_key("child"));
+        assert!(network.server.read().unwrap().children.is_empty());
+        assert!(network.clients.read().unwrap().is_empty());
+    }
+}
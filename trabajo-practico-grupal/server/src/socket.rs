@@ -1,36 +1,167 @@
-use model::{network::Network, session::Session};
+use model::{
+    consts::{CRLF, MAX_MSG_SIZE},
+    network::Network,
+    responses::{
+        numeric::WireFormat,
+        response::Response,
+        writer::{ResponseWriter, SharedMessage},
+    },
+    sanitize::sanitize,
+    session::Session,
+};
 use std::{
-    io::{Read, Write},
+    io::{ErrorKind, Read, Write},
     net::TcpStream,
     sync::Arc,
 };
 
-use crate::commands::command_utils::lock_sockets;
+use crate::locks::{lock_sockets, read_lock_network_clients, write_lock_server};
 use crate::server_errors::ServerError;
 
-//static CRLF: &str = "\r\n";
-const MAX_MSG_SIZE: usize = 510;
-
 /// Function that writes the socket received.
 /// # Arguments
 /// * `arc_socket` - The socket to write to.
 /// * `message` - The message to write.
 pub fn write_socket(arc_socket: Arc<TcpStream>, message: &str) -> Result<(), ServerError> {
-    let mut msg = message.to_owned().into_bytes();
-    msg.resize(MAX_MSG_SIZE, 0);
-    arc_socket.as_ref().write_all(&msg)?;
-    Ok(())
+    write_socket_as(arc_socket, message, WireFormat::Custom, "*")
 }
 
 /// Function that reads the socket received. It returs
-/// the message read in a String.
+/// the message read in a String, without its control characters.
 /// # Arguments
 /// * `arc_socket` - The socket to read from.
+/// # Errors
+/// Returns `ServerError::InvalidEncoding` if the message is not valid UTF-8. The message is
+/// consumed, so the connection can keep reading the next ones.
 pub fn read_socket(arc_socket: Arc<TcpStream>) -> Result<String, ServerError> {
     let mut buff = [0u8; MAX_MSG_SIZE];
     arc_socket.as_ref().read_exact(&mut buff)?;
     let msg = buff.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
-    Ok(String::from_utf8_lossy(&msg).to_string())
+    Ok(sanitize(&msg)?)
+}
+
+/// Function that reads the socket of a link with another server, discarding the messages
+/// that are not valid UTF-8, since there is no client to answer with an error.
+/// # Arguments
+/// * `arc_socket` - The socket of the link.
+pub fn read_link_socket(arc_socket: Arc<TcpStream>) -> Result<String, ServerError> {
+    loop {
+        match read_socket(arc_socket.clone()) {
+            Err(ServerError::InvalidEncoding) => println!("Discarding message of a link"),
+            result => return result,
+        }
+    }
+}
+
+/// Function that detects the format used by a new connection, peeking the first bytes it sent.
+/// Standard irc clients send lines terminated in `\r\n`, while our client sends zero padded frames.
+/// # Arguments
+/// * `arc_socket` - The socket of the new connection.
+pub fn detect_wire_format(arc_socket: Arc<TcpStream>) -> Result<WireFormat, ServerError> {
+    let mut buff = [0u8; MAX_MSG_SIZE];
+    let read = arc_socket.as_ref().peek(&mut buff)?;
+    for byte in buff[..read].iter() {
+        match byte {
+            0 => return Ok(WireFormat::Custom),
+            b'\n' => return Ok(WireFormat::Numeric),
+            _ => (),
+        }
+    }
+    Ok(WireFormat::Custom)
+}
+
+/// Function that reads a line terminated in `\r\n` from the socket received.
+/// It returns the line read without the terminator nor its control characters.
+/// The lines longer than the RFC allows are discarded up to their terminator, so their
+/// tail is never read as another command, and the next line is read instead.
+/// # Arguments
+/// * `arc_socket` - The socket to read from.
+/// # Errors
+/// Returns `ServerError::InvalidEncoding` if the line is not valid UTF-8. The line is
+/// consumed, so the connection can keep reading the next ones.
+pub fn read_line_socket(arc_socket: Arc<TcpStream>) -> Result<String, ServerError> {
+    let mut line = vec![];
+    let mut too_long = false;
+    let mut byte = [0u8; 1];
+    loop {
+        arc_socket.as_ref().read_exact(&mut byte)?;
+        if byte[0] == b'\n' {
+            if !too_long {
+                return Ok(sanitize(&line)?);
+            }
+            println!("Discarding a line longer than {} bytes", MAX_MSG_SIZE);
+            too_long = false;
+        } else if line.len() < MAX_MSG_SIZE + CRLF.len() {
+            line.push(byte[0]);
+            continue;
+        } else {
+            too_long = true;
+        }
+        line.clear();
+    }
+}
+
+/// Function that reads the socket received according to the format of the connection.
+/// # Arguments
+/// * `arc_socket` - The socket to read from.
+/// * `format` - The format used by the connection.
+pub fn read_socket_as(
+    arc_socket: Arc<TcpStream>,
+    format: WireFormat,
+) -> Result<String, ServerError> {
+    match format {
+        WireFormat::Custom => read_socket(arc_socket),
+        WireFormat::Numeric => read_line_socket(arc_socket),
+    }
+}
+
+/// Function that writes the socket received according to the format of the connection.
+/// The responses sent to a `Numeric` connection are encoded as the numeric replies of the RFC,
+/// and the lines longer than the RFC allows are split.
+/// # Arguments
+/// * `arc_socket` - The socket to write to.
+/// * `message` - The message to write.
+/// * `format` - The format used by the connection.
+/// * `nickname` - The nickname of the client of the connection, `*` if it is not registered yet.
+pub fn write_socket_as(
+    arc_socket: Arc<TcpStream>,
+    message: &str,
+    format: WireFormat,
+    nickname: &str,
+) -> Result<(), ServerError> {
+    let writer = ResponseWriter::new(nickname, format);
+    write_all_to_socket(&arc_socket, &writer.encode(&writer.message_lines(message)))
+}
+
+/// Function that writes all the bytes to the socket received, looping on the partial writes
+/// of a busy connection. The writes interrupted by a signal are retried.
+/// # Arguments
+/// * `socket` - The socket to write to.
+/// * `bytes` - The bytes to write.
+/// # Errors
+/// Returns `ServerError::SocketTimeout` if the socket is not writable before its write timeout.
+/// The bytes may be half sent by then, so the caller should drop the connection.
+pub fn write_all_to_socket(mut socket: &TcpStream, bytes: &[u8]) -> Result<(), ServerError> {
+    let mut written = 0;
+    while written < bytes.len() {
+        match socket.write(&bytes[written..]) {
+            Ok(0) => return Err(ServerError::CannotWriteSocket),
+            Ok(n) => written += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => (),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
+
+/// Function that returns the writer of the messages sent to the client in session.
+/// Clients whose format is not known yet use the `Custom` format.
+/// # Arguments
+/// * `session` - The session of the client.
+/// * `nickname` - The nickname of the client.
+fn client_writer(session: &Session, nickname: &str) -> ResponseWriter {
+    let format = session.sockets.wire_format(nickname);
+    ResponseWriter::new(nickname, format)
 }
 
 /// Function that sends a message to the client socket in session.
@@ -45,7 +176,83 @@ pub fn inform_client(
 ) -> Result<(), ServerError> {
     let sockets = lock_sockets(session)?;
     if let Some(socket) = sockets.get(nickname) {
-        write_socket(socket.clone(), message)?
+        let writer = client_writer(session, nickname);
+        write_all_to_socket(socket, &writer.encode(&writer.message_lines(message)))?;
+    }
+    Ok(())
+}
+
+/// Function that sends a message shared by many clients in session, like the members of a
+/// channel. The sockets are locked once to take the connections of every receiver, and released
+/// before writing, so a slow receiver doesn't block the other writes of the server. The message
+/// is encoded once for each wire format instead of once for each client. A failed write to a
+/// receiver, like a dead connection not closed yet, is logged and doesn't stop the others.
+/// # Arguments
+/// * `session` - The session to send the message to.
+/// * `nicknames` - The nicknames of the clients.
+/// * `message` - The message to send.
+pub fn inform_clients_shared<'a, I: IntoIterator<Item = &'a str>>(
+    session: &Session,
+    nicknames: I,
+    message: &SharedMessage,
+) -> Result<(), ServerError> {
+    let receivers = {
+        let sockets = lock_sockets(session)?;
+        nicknames
+            .into_iter()
+            .filter_map(|nickname| Some((nickname, sockets.get(nickname)?.clone())))
+            .collect::<Vec<_>>()
+    };
+    for (nickname, socket) in receivers {
+        let format = session.sockets.wire_format(nickname);
+        if let Err(e) = write_all_to_socket(&socket, message.bytes(format)) {
+            println!("Error sending a message to {}: {:?}", nickname, e);
+        }
+    }
+    Ok(())
+}
+
+/// Function that sends a response to the client socket in session, encoded in the format
+/// of its connection.
+/// # Arguments
+/// * `session` - The session to send the response to.
+/// * `nickname` - The nickname of the client.
+/// * `response` - The response to send.
+pub fn inform_client_response(
+    session: &Session,
+    nickname: &str,
+    response: &Response,
+) -> Result<(), ServerError> {
+    let sockets = lock_sockets(session)?;
+    if let Some(socket) = sockets.get(nickname) {
+        let writer = client_writer(session, nickname);
+        write_all_to_socket(socket, &writer.encode(&writer.response_lines(response)))?;
+    }
+    Ok(())
+}
+
+/// Function that queues a message to a server linked to this one, to be written by the writer
+/// of the link. A handler never blocks on a slow link: when the queue of the link is full, the
+/// link is dropped, and its reader splits the server from the network.
+/// # Arguments
+/// * `network` - The network of the current server.
+/// * `server_name` - The name of the linked server.
+/// * `socket` - The socket of the link.
+/// * `message` - The message to send.
+/// # Errors
+/// Returns `ServerError::LinkQueueFull` if the link was dropped because its queue was full,
+/// and `ServerError::CannotWriteSocket` if its socket had already failed.
+pub fn write_link(
+    network: &Network,
+    server_name: &str,
+    socket: &Arc<TcpStream>,
+    message: &str,
+) -> Result<(), ServerError> {
+    let writer = ResponseWriter::new("*", WireFormat::Custom);
+    let bytes = writer.encode(&writer.message_lines(message));
+    if let Err(e) = network.links.send(server_name, socket, bytes) {
+        println!("Dropping the link with {}: {:?}", server_name, e);
+        return Err(e.into());
     }
     Ok(())
 }
@@ -60,25 +267,21 @@ pub fn inform_server(
     servername: &String,
     message: &str,
 ) -> Result<(), ServerError> {
-    let server_lock = network.server.as_ref().write()?;
-    if let Some((father_name, father_socket)) = server_lock.father.to_owned() {
-        if father_name == *servername {
-            write_socket(father_socket, message)?;
-            drop(server_lock);
-            return Ok(());
+    let server_lock = write_lock_server(network)?;
+    if let Some((father_name, father_socket)) = server_lock.father.as_ref() {
+        if father_name == servername {
+            return write_link(network, father_name, father_socket, message);
         }
     }
-    for (child_name, child_socket) in server_lock.children.clone() {
-        if child_name == *servername {
-            write_socket(child_socket, message)?;
-            break;
-        }
+    if let Some(child_socket) = server_lock.children.get(servername) {
+        write_link(network, servername, child_socket, message)?;
     }
     drop(server_lock);
     Ok(())
 }
 
 /// Function that sends a message to the servers in the network.
+/// Every link is informed even if informing another one fails.
 /// # Arguments
 /// * `network` - The network to send the message to.
 /// * `server_name` - The server to exclude from the message.
@@ -88,18 +291,140 @@ pub fn inform_network(
     server_name: &String,
     message: &str,
 ) -> Result<(), ServerError> {
-    let server_lock = network.server.as_ref().write()?;
-    if let Some((father_name, father_socket)) = server_lock.father.to_owned() {
-        if father_name != *server_name {
-            write_socket(father_socket, message)?;
+    let server_lock = write_lock_server(network)?;
+    let mut result = Ok(());
+    if let Some((father_name, father_socket)) = server_lock.father.as_ref() {
+        if father_name != server_name {
+            result = result.and(write_link(network, father_name, father_socket, message));
         }
     }
 
-    for (child_name, child_socket) in server_lock.children.clone() {
-        if child_name != *server_name {
-            write_socket(child_socket.clone(), message)?;
+    for (child_name, child_socket) in server_lock.children.iter() {
+        if child_name != server_name {
+            result = result.and(write_link(network, child_name, child_socket, message));
         }
     }
     drop(server_lock);
+    result
+}
+
+/// Function that sends a message to the linked server through which a client of another server
+/// is reached. Returns false if the client is not known to be reached through a linked server.
+/// # Arguments
+/// * `network` - The struct that contains the information of the network.
+/// * `nickname` - The nickname of the client.
+/// * `message` - The message to send.
+pub fn inform_client_link(
+    network: &Network,
+    nickname: &str,
+    message: &str,
+) -> Result<bool, ServerError> {
+    let link = match read_lock_network_clients(network)?.get(nickname) {
+        Some(network_client) => network_client.link.to_owned(),
+        None => None,
+    };
+    match link {
+        Some(link) => {
+            inform_server(network, &link, message)?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Function that sends a response to a client of another server, in a message like
+/// `:<server> REPLY <nickname> :<response>` that the servers relay towards the client.
+/// # Arguments
+/// * `network` - The struct that contains the information of the network.
+/// * `server_name` - The name of the server that sends the response.
+/// * `nickname` - The nickname of the client.
+/// * `response` - The response to send.
+pub fn inform_remote_client(
+    network: &Network,
+    server_name: &str,
+    nickname: &str,
+    response: &str,
+) -> Result<(), ServerError> {
+    let message = format!(":{} REPLY {} :{}", server_name, nickname, response);
+    if !inform_client_link(network, nickname, &message)? {
+        println!("Dropping reply to unreachable client {}", nickname);
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod socket_tests {
+    use std::{
+        net::{TcpListener, TcpStream},
+        sync::Arc,
+        time::Duration,
+    };
+
+    use model::consts::MAX_MSG_SIZE;
+
+    use super::{read_line_socket, read_socket, write_all_to_socket};
+    use crate::server_errors::ServerError;
+
+    fn connect_pair_for_test() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        (client, server)
+    }
+
+    #[test]
+    fn test_read_that_times_out_is_a_timeout_error() {
+        let (_client, server) = connect_pair_for_test();
+        server
+            .set_read_timeout(Some(Duration::from_millis(50)))
+            .unwrap();
+
+        let result = read_socket(Arc::new(server));
+
+        assert_eq!(result, Err(ServerError::SocketTimeout));
+    }
+
+    #[test]
+    fn test_message_that_is_not_valid_utf8_is_an_error_and_the_next_one_is_read() {
+        let (client, server) = connect_pair_for_test();
+        let server = Arc::new(server);
+        for message in [&b"PRIVMSG #rust :caf\xe9"[..], b"PRIVMSG #rust :hi"] {
+            let mut frame = message.to_vec();
+            frame.resize(MAX_MSG_SIZE, 0);
+            write_all_to_socket(&client, &frame).unwrap();
+        }
+
+        assert_eq!(
+            read_socket(server.clone()),
+            Err(ServerError::InvalidEncoding)
+        );
+        assert_eq!(read_socket(server), Ok("PRIVMSG #rust :hi".to_string()));
+    }
+
+    #[test]
+    fn test_line_longer_than_the_limit_is_discarded_up_to_its_terminator() {
+        let (client, server) = connect_pair_for_test();
+        let mut long_line = format!("PRIVMSG #rust :{}", "a".repeat(576));
+        long_line.push_str("QUIT :x\r\n");
+        assert_eq!(long_line.len(), 600);
+        write_all_to_socket(&client, long_line.as_bytes()).unwrap();
+        write_all_to_socket(&client, b"PRIVMSG #rust :hi\r\n").unwrap();
+
+        assert_eq!(
+            read_line_socket(Arc::new(server)),
+            Ok("PRIVMSG #rust :hi".to_string())
+        );
+    }
+
+    #[test]
+    fn test_write_to_a_connection_that_never_reads_is_a_timeout_error() {
+        let (_client, server) = connect_pair_for_test();
+        server
+            .set_write_timeout(Some(Duration::from_millis(50)))
+            .unwrap();
+
+        let result = write_all_to_socket(&server, &vec![0u8; 64 * 1024 * 1024]);
+
+        assert_eq!(result, Err(ServerError::SocketTimeout));
+    }
+}
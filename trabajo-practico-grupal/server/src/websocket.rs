@@ -0,0 +1,532 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use model::{
+    consts::CRLF,
+    crypto::{sha1, to_base64},
+    network::Network,
+    sanitize::sanitize,
+    session::Session,
+};
+
+use crate::{
+    http::{HttpRequest, HttpResponse},
+    locks::{read_lock_config, read_lock_server},
+    run::{
+        close_connection, count_rejected_connection, handle_connection, open_connection,
+        REJECTED_TOO_MANY_CONNECTIONS,
+    },
+    server_errors::ServerError,
+};
+
+static WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+static BRIDGE_ADDRESS: &str = "127.0.0.1:0";
+static MAX_FRAME_SIZE: usize = 16384;
+static CLOSE_NORMAL: u16 = 1000;
+static CLOSE_PROTOCOL_ERROR: u16 = 1002;
+static CLOSE_UNSUPPORTED_DATA: u16 = 1003;
+static CLOSE_TOO_BIG: u16 = 1009;
+
+/// Enum that represents the opcodes of the frames of a WebSocket.
+/// * Continuation -> The next piece of a fragmented message
+/// * Text -> A message of UTF-8 text, which carries lines of the RFC
+/// * Binary -> A binary message, which is not supported
+/// * Close -> The closing of the connection, with its code
+/// * Ping -> A ping, answered with a pong with the same payload
+/// * Pong -> The answer to a ping
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opcode {
+    Continuation,
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong,
+}
+
+impl Opcode {
+    /// Returns the opcode of the bits of a frame, if it is known.
+    fn from_bits(bits: u8) -> Option<Opcode> {
+        match bits {
+            0x0 => Some(Opcode::Continuation),
+            0x1 => Some(Opcode::Text),
+            0x2 => Some(Opcode::Binary),
+            0x8 => Some(Opcode::Close),
+            0x9 => Some(Opcode::Ping),
+            0xA => Some(Opcode::Pong),
+            _ => None,
+        }
+    }
+
+    /// Returns the bits of the opcode in a frame.
+    fn bits(&self) -> u8 {
+        match self {
+            Opcode::Continuation => 0x0,
+            Opcode::Text => 0x1,
+            Opcode::Binary => 0x2,
+            Opcode::Close => 0x8,
+            Opcode::Ping => 0x9,
+            Opcode::Pong => 0xA,
+        }
+    }
+
+    /// Returns true for the frames that control the connection, which can't be fragmented.
+    fn is_control(&self) -> bool {
+        matches!(self, Opcode::Close | Opcode::Ping | Opcode::Pong)
+    }
+}
+
+/// Struct that represents a frame of a WebSocket, with its payload already unmasked.
+/// # Fields
+/// * `fin` - True if it is the last piece of its message.
+/// * `opcode` - The kind of the frame.
+/// * `payload` - The data of the frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub fin: bool,
+    pub opcode: Opcode,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    /// Reads a frame sent by a browser. The frames sent by the browsers must be masked.
+    /// # Arguments
+    /// * `reader` - The connection the frame is read from.
+    pub fn read<R: Read>(reader: &mut R) -> Result<Frame, FrameError> {
+        let mut head = [0u8; 2];
+        reader.read_exact(&mut head)?;
+        let fin = head[0] & 0x80 != 0;
+        let opcode = match Opcode::from_bits(head[0] & 0x0F) {
+            Some(opcode) if head[0] & 0x70 == 0 => opcode,
+            _ => return Err(FrameError::Protocol),
+        };
+        if head[1] & 0x80 == 0 {
+            return Err(FrameError::Protocol);
+        }
+        let length = match head[1] & 0x7F {
+            126 => {
+                let mut length = [0u8; 2];
+                reader.read_exact(&mut length)?;
+                u16::from_be_bytes(length) as usize
+            }
+            127 => {
+                let mut length = [0u8; 8];
+                reader.read_exact(&mut length)?;
+                u64::from_be_bytes(length) as usize
+            }
+            length => length as usize,
+        };
+        if opcode.is_control() && (!fin || length > 125) {
+            return Err(FrameError::Protocol);
+        }
+        if length > MAX_FRAME_SIZE {
+            return Err(FrameError::TooBig);
+        }
+        let mut mask = [0u8; 4];
+        reader.read_exact(&mut mask)?;
+        let mut payload = vec![0u8; length];
+        reader.read_exact(&mut payload)?;
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+        Ok(Frame {
+            fin,
+            opcode,
+            payload,
+        })
+    }
+
+    /// Writes a frame sent by the server, which is not masked.
+    /// # Arguments
+    /// * `writer` - The connection to write to.
+    /// * `opcode` - The kind of the frame.
+    /// * `payload` - The data of the frame.
+    pub fn write<W: Write>(
+        writer: &mut W,
+        opcode: Opcode,
+        payload: &[u8],
+    ) -> Result<(), ServerError> {
+        let mut frame = vec![0x80 | opcode.bits()];
+        match payload.len() {
+            length if length < 126 => frame.push(length as u8),
+            length if length <= u16::MAX as usize => {
+                frame.push(126);
+                frame.extend_from_slice(&(length as u16).to_be_bytes());
+            }
+            length => {
+                frame.push(127);
+                frame.extend_from_slice(&(length as u64).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(payload);
+        writer.write_all(&frame)?;
+        Ok(())
+    }
+}
+
+/// Enum that represents the reasons a frame can't be read.
+/// * Closed -> The connection was closed or failed
+/// * Protocol -> The frame doesn't follow the protocol, like a frame that is not masked
+/// * TooBig -> The frame is longer than the server accepts
+#[derive(Debug, PartialEq, Eq)]
+pub enum FrameError {
+    Closed,
+    Protocol,
+    TooBig,
+}
+
+impl From<std::io::Error> for FrameError {
+    fn from(_: std::io::Error) -> Self {
+        FrameError::Closed
+    }
+}
+
+/// Function that starts the WebSocket gateway of the server, if the configuration sets its port.
+/// The gateway listens on the IP of the server.
+/// # Arguments
+/// * `session` - The session of the current server.
+/// * `network` - The network of the current server.
+/// * `server_name` - The name of the current server.
+pub fn handle_websocket(
+    session: Session,
+    network: Network,
+    server_name: String,
+) -> Result<(), ServerError> {
+    let port = read_lock_config(&session)?.websocket_port;
+    if port == 0 {
+        return Ok(());
+    }
+    let ip = read_lock_server(&network)?.ip.to_owned();
+    let listener = TcpListener::bind(format!("{}:{}", ip, port))?;
+    println!("WebSocket gateway listening on {}:{}", ip, port);
+    let bridge = TcpListener::bind(BRIDGE_ADDRESS)?;
+    thread::spawn(move || websocket_listen(listener, bridge, session, network, server_name));
+    Ok(())
+}
+
+/// Function that accepts the connections of the browsers. Each WebSocket is handled as the
+/// connection of a client, through a pair of sockets connected on the loopback: the server
+/// handles one end like any other connection that sends lines of the RFC, and the gateway
+/// relays the frames of the browser to the other end.
+/// # Arguments
+/// * `listener` - The listener bound to the port of the gateway.
+/// * `bridge` - The listener on the loopback that the pairs of sockets are connected through.
+/// * `session` - The session of the current server.
+/// * `network` - The network of the current server.
+/// * `server_name` - The name of the current server.
+pub fn websocket_listen(
+    listener: TcpListener,
+    bridge: TcpListener,
+    session: Session,
+    network: Network,
+    server_name: String,
+) {
+    let bridge = Arc::new(Mutex::new(bridge));
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                println!("Error accepting WebSocket connection: {:?}", e);
+                continue;
+            }
+        };
+        let bridge = bridge.clone();
+        let session = session.clone();
+        let network = network.clone();
+        let server_name = server_name.clone();
+        thread::spawn(move || {
+            if let Err(e) =
+                handle_websocket_connection(stream, &bridge, session, network, &server_name)
+            {
+                println!("Error handling WebSocket connection: {:?}", e);
+            }
+        });
+    }
+}
+
+/// Upgrades the connection of a browser to a WebSocket and relays its frames to the server,
+/// until either side closes. The connection counts as a connection from the IP of the browser.
+fn handle_websocket_connection(
+    stream: TcpStream,
+    bridge: &Mutex<TcpListener>,
+    session: Session,
+    network: Network,
+    server_name: &str,
+) -> Result<(), ServerError> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let request = match HttpRequest::read(&mut reader) {
+        Ok(request) => request,
+        Err(e) => {
+            HttpResponse::error(400, "Bad Request").write(&mut &stream)?;
+            return Err(e);
+        }
+    };
+    let key = match handshake_key(&request) {
+        Some(key) => key.to_owned(),
+        None => {
+            let mut response = HttpResponse::error(426, "Upgrade Required");
+            response
+                .headers
+                .push(("Upgrade".to_string(), "websocket".to_string()));
+            return response.write(&mut &stream);
+        }
+    };
+
+    let ip = stream.peer_addr()?.ip().to_string();
+    if !open_connection(&session, &ip)? {
+        println!(
+            "Refusing WebSocket connection from {}: too many connections",
+            ip
+        );
+        count_rejected_connection(&session, REJECTED_TOO_MANY_CONNECTIONS);
+        return HttpResponse::error(503, "Service Unavailable").write(&mut &stream);
+    }
+    let result = bridge_websocket(
+        &stream,
+        &mut reader,
+        &key,
+        bridge,
+        &session,
+        network,
+        server_name,
+    );
+    let _ = stream.shutdown(Shutdown::Both);
+    close_connection(&session, &ip)?;
+    result
+}
+
+/// Sends the handshake and relays the frames of the browser to a new connection of the server,
+/// and the lines the server sends back as text frames.
+fn bridge_websocket(
+    stream: &TcpStream,
+    reader: &mut BufReader<TcpStream>,
+    key: &str,
+    bridge: &Mutex<TcpListener>,
+    session: &Session,
+    network: Network,
+    server_name: &str,
+) -> Result<(), ServerError> {
+    let (gateway_end, server_end) = connect_pair(bridge)?;
+    write_handshake(&mut &*stream, &accept_key(key))?;
+    let writer = Arc::new(Mutex::new(stream.try_clone()?));
+
+    let server_end = Arc::new(server_end);
+    let server_thread = {
+        let session = session.clone();
+        let server_name = server_name.to_owned();
+        thread::spawn(move || {
+            if let Err(e) = handle_connection(server_end.clone(), session, network, &server_name) {
+                println!("Error handling WebSocket client: {:?}", e);
+            }
+            let _ = server_end.shutdown(Shutdown::Both);
+        })
+    };
+    let browser_thread = {
+        let gateway_end = gateway_end.try_clone()?;
+        let writer = writer.clone();
+        thread::spawn(move || relay_to_browser(gateway_end, &writer))
+    };
+
+    let code = relay_to_server(reader, &gateway_end, &writer);
+    let _ = gateway_end.shutdown(Shutdown::Both);
+    let _ = server_thread.join();
+    let _ = browser_thread.join();
+    send_close(&writer, code)
+}
+
+/// Returns the key of the handshake if the request asks to upgrade to a WebSocket of the
+/// version supported.
+fn handshake_key(request: &HttpRequest) -> Option<&String> {
+    let upgrade = request.header("upgrade")?;
+    let version = request.header("sec-websocket-version")?;
+    if request.method != "GET" || !upgrade.eq_ignore_ascii_case("websocket") || version != "13" {
+        return None;
+    }
+    request.header("sec-websocket-key")
+}
+
+/// Returns the key that proves to the browser that the server understood its handshake.
+/// # Arguments
+/// * `key` - The key sent by the browser.
+pub fn accept_key(key: &str) -> String {
+    to_base64(&sha1(
+        format!("{}{}", key.trim(), WEBSOCKET_GUID).as_bytes(),
+    ))
+}
+
+/// Writes the response that accepts the upgrade to a WebSocket.
+fn write_handshake<W: Write>(writer: &mut W, accept: &str) -> Result<(), ServerError> {
+    let response = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept
+    );
+    writer.write_all(response.as_bytes())?;
+    Ok(())
+}
+
+/// Returns a pair of sockets connected to each other through the bridge, the end of the
+/// gateway first. The connections of other processes to the bridge are discarded.
+fn connect_pair(bridge: &Mutex<TcpListener>) -> Result<(TcpStream, TcpStream), ServerError> {
+    let bridge = bridge.lock().map_err(|_| ServerError::PoisonedLock)?;
+    let gateway_end = TcpStream::connect(bridge.local_addr()?)?;
+    loop {
+        let (server_end, address) = bridge.accept()?;
+        if address == gateway_end.local_addr()? {
+            return Ok((gateway_end, server_end));
+        }
+    }
+}
+
+/// Relays the text frames of the browser to the server, a line of the RFC for each line of
+/// the frames, and answers the pings. Returns the code the WebSocket is closed with.
+fn relay_to_server<R: Read>(
+    reader: &mut R,
+    mut gateway_end: &TcpStream,
+    writer: &Mutex<TcpStream>,
+) -> u16 {
+    let mut message: Option<Vec<u8>> = None;
+    loop {
+        let frame = match Frame::read(reader) {
+            Ok(frame) => frame,
+            Err(FrameError::Closed) => return CLOSE_NORMAL,
+            Err(FrameError::Protocol) => return CLOSE_PROTOCOL_ERROR,
+            Err(FrameError::TooBig) => return CLOSE_TOO_BIG,
+        };
+        let data = match (frame.opcode, message.as_mut()) {
+            (Opcode::Close, _) => return CLOSE_NORMAL,
+            (Opcode::Ping, _) => {
+                if write_frame(writer, Opcode::Pong, &frame.payload).is_err() {
+                    return CLOSE_NORMAL;
+                }
+                continue;
+            }
+            (Opcode::Pong, _) => continue,
+            (Opcode::Binary, _) => return CLOSE_UNSUPPORTED_DATA,
+            (Opcode::Text, None) => message.insert(frame.payload),
+            (Opcode::Continuation, Some(data)) => {
+                data.extend_from_slice(&frame.payload);
+                data
+            }
+            (Opcode::Text, Some(_)) | (Opcode::Continuation, None) => return CLOSE_PROTOCOL_ERROR,
+        };
+        if data.len() > MAX_FRAME_SIZE {
+            return CLOSE_TOO_BIG;
+        }
+        if !frame.fin {
+            continue;
+        }
+        let text = match message.take().map(String::from_utf8) {
+            Some(Ok(text)) => text,
+            _ => return CLOSE_UNSUPPORTED_DATA,
+        };
+        for line in text.lines().filter(|line| !line.is_empty()) {
+            let line = format!("{}{}", line, CRLF);
+            if gateway_end.write_all(line.as_bytes()).is_err() {
+                return CLOSE_NORMAL;
+            }
+        }
+    }
+}
+
+/// Relays the lines the server sends to the browser, a text frame for each line, until the
+/// server closes the connection.
+fn relay_to_browser(gateway_end: TcpStream, writer: &Mutex<TcpStream>) {
+    let mut reader = BufReader::new(gateway_end);
+    loop {
+        let mut line = vec![];
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => (),
+        }
+        let end = line.len()
+            - line
+                .iter()
+                .rev()
+                .take_while(|b| **b == b'\n' || **b == b'\r')
+                .count();
+        let line = match sanitize(&line[..end]) {
+            Ok(line) => line,
+            Err(e) => {
+                println!("Discarding line to a WebSocket: {:?}", e);
+                continue;
+            }
+        };
+        if write_frame(writer, Opcode::Text, line.as_bytes()).is_err() {
+            break;
+        }
+    }
+    if let Ok(stream) = writer.lock() {
+        let _ = stream.shutdown(Shutdown::Read);
+    }
+}
+
+/// Writes a frame to the browser, without mixing it with the frames written by other threads.
+fn write_frame(
+    writer: &Mutex<TcpStream>,
+    opcode: Opcode,
+    payload: &[u8],
+) -> Result<(), ServerError> {
+    let mut stream = writer.lock().map_err(|_| ServerError::PoisonedLock)?;
+    Frame::write(&mut *stream, opcode, payload)
+}
+
+/// Sends the close frame, with its code, to the browser. The browser may be gone already.
+fn send_close(writer: &Mutex<TcpStream>, code: u16) -> Result<(), ServerError> {
+    let _ = write_frame(writer, Opcode::Close, &code.to_be_bytes());
+    Ok(())
+}
+
+#[cfg(test)]
+mod websocket_tests {
+    use super::{accept_key, Frame, FrameError, Opcode};
+
+    #[test]
+    fn test_accept_key_matches_the_rfc() {
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn test_masked_frames_are_read_and_frames_are_written_unmasked() {
+        let masked = [
+            0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58,
+        ];
+        let frame = Frame::read(&mut &masked[..]).unwrap();
+        assert_eq!(
+            frame,
+            Frame {
+                fin: true,
+                opcode: Opcode::Text,
+                payload: b"Hello".to_vec(),
+            }
+        );
+
+        let mut written = vec![];
+        Frame::write(&mut written, Opcode::Text, b"Hello").unwrap();
+        assert_eq!(written, [0x81, 0x05, b'H', b'e', b'l', b'l', b'o']);
+        let mut long = vec![];
+        Frame::write(&mut long, Opcode::Text, &[b'a'; 300]).unwrap();
+        assert_eq!(long[..4], [0x81, 126, 0x01, 0x2c]);
+    }
+
+    #[test]
+    fn test_frames_that_break_the_protocol_are_rejected() {
+        let unmasked = [0x81, 0x05, b'H', b'e', b'l', b'l', b'o'];
+        let fragmented_ping = [0x09, 0x80, 0, 0, 0, 0];
+        let too_big = [0x81, 0xFF, 0, 0, 0, 0, 0, 1, 0, 0];
+
+        assert_eq!(Frame::read(&mut &unmasked[..]), Err(FrameError::Protocol));
+        assert_eq!(
+            Frame::read(&mut &fragmented_ping[..]),
+            Err(FrameError::Protocol)
+        );
+        assert_eq!(Frame::read(&mut &too_big[..]), Err(FrameError::TooBig));
+        assert_eq!(Frame::read(&mut &[0x81][..]), Err(FrameError::Closed));
+    }
+}
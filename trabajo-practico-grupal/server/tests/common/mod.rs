@@ -0,0 +1,291 @@
+#![allow(dead_code)]
+
+use std::{
+    collections::HashMap,
+    io::Write,
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex, RwLock},
+    thread,
+    time::{Duration, Instant},
+};
+
+use model::{
+    channel_registry::ChannelRegistry,
+    client_registry::ClientRegistry,
+    command_metrics::CommandMetrics,
+    config::ServerConfig,
+    consts::MAX_MSG_SIZE,
+    events::EventBus,
+    gc_stats::GcStats,
+    link_queues::LinkQueues,
+    network::Network,
+    persistence::PersistenceType,
+    plugins::Plugins,
+    responses::{replies::CommandResponse, response::Response},
+    server::Server,
+    session::Session,
+    socket_registry::SocketRegistry,
+};
+use server::{
+    database::{handle_database, DatabasePaths},
+    plugins::PluginFactories,
+    run::server_listen,
+    server_handler::register_in_father,
+    socket::{read_socket, write_socket},
+};
+
+static RESPONSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A server running in a thread of the test, listening on an ephemeral port of localhost.
+/// It saves its database in a temporary directory of its own.
+/// # Fields
+/// * `name` - The name of the server.
+/// * `port` - The port the server is listening on.
+/// * `session` - The session of the server, to inspect its state from the test.
+/// * `network` - The network of the server, to inspect its state from the test.
+/// * `paths` - The files of the database of the server.
+pub struct TestServer {
+    pub name: String,
+    pub port: u16,
+    pub session: Session,
+    pub network: Network,
+    pub paths: DatabasePaths,
+}
+
+impl TestServer {
+    /// Starts a main server with the name received.
+    pub fn start(name: &str) -> TestServer {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port().to_string();
+        let server = Server::new_main_server("127.0.0.1".to_string(), port, name.to_string());
+        TestServer::launch(listener, server)
+    }
+
+    /// Starts a server with the name received as a child of the father received,
+    /// and waits until the father registers it.
+    pub fn start_child(name: &str, father: &TestServer) -> TestServer {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port().to_string();
+        let father_socket = Arc::new(TcpStream::connect(father.address()).unwrap());
+        let server = Server::new_child_server(
+            "127.0.0.1".to_string(),
+            port,
+            name.to_string(),
+            Some((father.name.clone(), father_socket.clone())),
+        );
+        let child = TestServer::launch(listener, server);
+
+        let message = format!("SERVER {} 1 :{} test server", name, name);
+        register_in_father(father_socket, &message).unwrap();
+        wait_until(&format!("{} to register {}", father.name, name), || {
+            father.network.servers.read().unwrap().contains_key(name)
+        });
+        child
+    }
+
+    fn launch(listener: TcpListener, server: Server) -> TestServer {
+        let name = server.name.clone();
+        let port = listener.local_addr().unwrap().port();
+        let paths = DatabasePaths::temporary().unwrap();
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, paths.clone());
+
+        let session = Session {
+            clients: ClientRegistry::default(),
+            sockets: SocketRegistry::default(),
+            connections: Arc::new(Mutex::new(HashMap::new())),
+            rejected_connections: Arc::new(Mutex::new(HashMap::new())),
+            channels: ChannelRegistry::default(),
+            database_sender: db_tx,
+            config: Arc::new(RwLock::new(ServerConfig::default())),
+            events: EventBus::default(),
+            plugins: Plugins::default(),
+            who_queries: Arc::new(Mutex::new(HashMap::new())),
+            command_metrics: CommandMetrics::default(),
+            gc_stats: GcStats::default(),
+        };
+        PluginFactories::builtin().load(&session, &name).unwrap();
+        let network = Network {
+            server: Arc::new(RwLock::new(server)),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        };
+
+        let session_clone = session.clone();
+        let network_clone = network.clone();
+        thread::spawn(move || server_listen(listener, session_clone, network_clone));
+
+        TestServer {
+            name,
+            port,
+            session,
+            network,
+            paths,
+        }
+    }
+
+    /// Returns the address the clients connect to.
+    pub fn address(&self) -> String {
+        format!("127.0.0.1:{}", self.port)
+    }
+
+    /// Connects a new client and registers it with the nickname received.
+    pub fn connect(&self, nickname: &str) -> TestClient {
+        let mut client = TestClient::connect(self, nickname);
+        client.register();
+        client
+    }
+}
+
+/// A father server with its children, all of them running in the test.
+/// # Fields
+/// * `father` - The main server of the network, named `father`.
+/// * `children` - The children of the father, named `child1`, `child2` and so on.
+pub struct TestNetwork {
+    pub father: TestServer,
+    pub children: Vec<TestServer>,
+}
+
+impl TestNetwork {
+    /// Starts a father with the amount of children received, registering them in order.
+    pub fn start(children: usize) -> TestNetwork {
+        let father = TestServer::start("father");
+        let children = (1..=children)
+            .map(|i| TestServer::start_child(&format!("child{}", i), &father))
+            .collect();
+        TestNetwork { father, children }
+    }
+}
+
+/// Waits until the condition holds, as the changes take a while to reach the other servers.
+/// It panics if the condition does not hold before the timeout.
+pub fn wait_until<F: Fn() -> bool>(description: &str, condition: F) {
+    let deadline = Instant::now() + RESPONSE_TIMEOUT;
+    while !condition() {
+        if Instant::now() > deadline {
+            panic!("Timed out waiting for {}", description);
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// A client connected to a test server, that sends commands as our client does
+/// and reads the responses the server sends back.
+/// # Fields
+/// * `nickname` - The nickname the client registers with.
+/// * `socket` - The connection with the server.
+pub struct TestClient {
+    pub nickname: String,
+    socket: Arc<TcpStream>,
+}
+
+impl TestClient {
+    /// Connects to the server without registering.
+    pub fn connect(server: &TestServer, nickname: &str) -> TestClient {
+        let socket = TcpStream::connect(server.address()).unwrap();
+        socket.set_read_timeout(Some(RESPONSE_TIMEOUT)).unwrap();
+        TestClient {
+            nickname: nickname.to_string(),
+            socket: Arc::new(socket),
+        }
+    }
+
+    /// Registers the client with its nickname, in the order our client does.
+    /// It waits until the server sends the names of the channels after the welcome,
+    /// which happens once the connection is saved and the client can receive messages.
+    pub fn register(&mut self) {
+        let nickname = self.nickname.clone();
+        self.send_all(&[
+            &format!("USER {} host server :{}", nickname, nickname),
+            &format!("NICK {}", nickname),
+        ]);
+        self.expect("the welcome message", |response| {
+            matches!(
+                response,
+                Response::CommandResponse {
+                    response: CommandResponse::Welcome { .. }
+                }
+            )
+        });
+        self.expect("the names of the channels", |response| {
+            matches!(
+                response,
+                Response::CommandResponse {
+                    response: CommandResponse::EndNames
+                }
+            )
+        });
+    }
+
+    /// Sends a command to the server, like `JOIN #channel`.
+    pub fn send(&self, command: &str) {
+        write_socket(self.socket.clone(), command).unwrap();
+    }
+
+    /// Sends the bytes to the server as one message, padded as our client does, even if they
+    /// are not valid UTF-8.
+    pub fn send_bytes(&self, bytes: &[u8]) {
+        let mut frame = bytes.to_vec();
+        frame.resize(MAX_MSG_SIZE, 0);
+        self.socket.as_ref().write_all(&frame).unwrap();
+    }
+
+    /// Sends the commands to the server, in order.
+    pub fn send_all(&self, commands: &[&str]) {
+        for command in commands {
+            self.send(command);
+        }
+    }
+
+    /// Returns the next response sent by the server,
+    /// or None if it sends nothing before the timeout.
+    pub fn read_response(&self) -> Option<Response> {
+        loop {
+            let msg = read_socket(self.socket.clone()).ok()?;
+            if let Some(response) = Response::serialize(msg) {
+                return Some(response);
+            }
+        }
+    }
+
+    /// Reads responses until one of them matches the predicate, and returns it.
+    /// The responses read before it are skipped.
+    /// It panics if no response matches before the timeout.
+    pub fn expect<F: Fn(&Response) -> bool>(&self, description: &str, predicate: F) -> Response {
+        let deadline = Instant::now() + RESPONSE_TIMEOUT;
+        let mut skipped = vec![];
+        while Instant::now() < deadline {
+            match self.read_response() {
+                Some(response) if predicate(&response) => return response,
+                Some(response) => skipped.push(response.to_string()),
+                None => break,
+            }
+        }
+        panic!(
+            "{} expected {}, but received {:?}",
+            self.nickname, description, skipped
+        );
+    }
+
+    /// Reads responses for the time received and panics if one of them matches the predicate.
+    pub fn expect_none<F: Fn(&Response) -> bool>(
+        &self,
+        description: &str,
+        within: Duration,
+        predicate: F,
+    ) {
+        self.socket.set_read_timeout(Some(within)).unwrap();
+        while let Some(response) = self.read_response() {
+            if predicate(&response) {
+                panic!(
+                    "{} did not expect {}, but received {}",
+                    self.nickname, description, response
+                );
+            }
+        }
+        self.socket
+            .set_read_timeout(Some(RESPONSE_TIMEOUT))
+            .unwrap();
+    }
+}
@@ -0,0 +1,443 @@
+mod common;
+
+#[cfg(test)]
+mod end_to_end_test {
+    use std::{
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+        thread,
+        time::Duration,
+    };
+
+    use model::responses::{
+        errors::ErrorResponse, message::MessageResponse, replies::CommandResponse,
+        response::Response,
+    };
+
+    use server::websocket::websocket_listen;
+
+    use crate::common::{wait_until, TestClient, TestServer};
+
+    fn is_topic_of(response: &Response, name: &str) -> bool {
+        matches!(
+            response,
+            Response::CommandResponse {
+                response: CommandResponse::Topic { channel, .. }
+            } if channel == name
+        )
+    }
+
+    fn join(client: &TestClient, channel: &str) {
+        client.send(&format!("JOIN {}", channel));
+        client.expect(&format!("the topic of {}", channel), |r| {
+            is_topic_of(r, channel)
+        });
+    }
+
+    #[test]
+    fn test_client_registers_and_receives_the_welcome() {
+        let server = TestServer::start("test");
+        let client = server.connect("alice");
+
+        assert_eq!(client.nickname, "alice");
+        assert!(server.session.clients.contains("alice"));
+        assert!(server.network.clients.read().unwrap().contains_key("alice"));
+    }
+
+    #[test]
+    fn test_servers_save_their_clients_in_their_own_database() {
+        let first = TestServer::start("first");
+        let second = TestServer::start("second");
+        first.connect("alice");
+        second.connect("bob");
+
+        wait_until("the clients to be saved", || {
+            let first_clients = std::fs::read_to_string(&first.paths.clients).unwrap();
+            let second_clients = std::fs::read_to_string(&second.paths.clients).unwrap();
+            first_clients.starts_with(r#"{"nickname":"alice","#)
+                && second_clients.starts_with(r#"{"nickname":"bob","#)
+        });
+        let first_clients = std::fs::read_to_string(&first.paths.clients).unwrap();
+        assert!(!first_clients.contains(r#""nickname":"bob""#));
+    }
+
+    #[test]
+    fn test_client_sends_privmsg_to_other_client() {
+        let server = TestServer::start("test");
+        let alice = server.connect("alice");
+        let bob = server.connect("bob");
+
+        alice.send("PRIVMSG bob :hello bob");
+
+        bob.expect("the message of alice", |r| {
+            matches!(
+                r,
+                Response::MessageResponse {
+                    response: MessageResponse::UserPrivMsg { sender, message }
+                } if sender == "alice" && message == "hello bob"
+            )
+        });
+    }
+
+    #[test]
+    fn test_two_clients_join_and_one_is_kicked() {
+        let server = TestServer::start("test");
+        let alice = server.connect("alice");
+        let bob = server.connect("bob");
+        join(&alice, "#rust");
+        join(&bob, "#rust");
+
+        alice.send("KICK #rust bob");
+
+        bob.expect("the kick notification", |r| {
+            matches!(
+                r,
+                Response::MessageResponse {
+                    response: MessageResponse::KickMsg { .. }
+                }
+            )
+        });
+        assert!(!server.session.channels.is_member("#rust", "bob"));
+        alice.send("PRIVMSG #rust :bob is gone");
+        bob.expect_none("the messages of #rust", Duration::from_millis(300), |r| {
+            matches!(
+                r,
+                Response::MessageResponse {
+                    response: MessageResponse::ChannelPrivMsg { .. }
+                }
+            )
+        });
+    }
+
+    #[test]
+    fn test_member_that_is_not_operator_cannot_kick() {
+        let server = TestServer::start("test");
+        let alice = server.connect("alice");
+        let bob = server.connect("bob");
+        join(&alice, "#rust");
+        join(&bob, "#rust");
+
+        bob.send("KICK #rust alice");
+
+        bob.expect("the lack of privileges", |r| {
+            matches!(
+                r,
+                Response::ErrorResponse {
+                    response: ErrorResponse::ChanOPrivsNeeded { .. }
+                }
+            )
+        });
+        assert!(server.session.channels.is_member("#rust", "alice"));
+    }
+
+    #[test]
+    fn test_privmsg_with_the_prefix_of_other_client_is_discarded() {
+        let server = TestServer::start("test");
+        let alice = server.connect("alice");
+        let bob = server.connect("bob");
+        server.connect("carol");
+
+        alice.send(":carol PRIVMSG bob :this is carol");
+        alice.send(":alice PRIVMSG bob :this is alice");
+
+        let response = bob.expect("the message of alice", |r| {
+            matches!(
+                r,
+                Response::MessageResponse {
+                    response: MessageResponse::UserPrivMsg { .. }
+                }
+            )
+        });
+        match response {
+            Response::MessageResponse {
+                response: MessageResponse::UserPrivMsg { sender, message },
+            } => {
+                assert_eq!(sender, "alice");
+                assert_eq!(message, "this is alice");
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_channel_privmsg_with_the_prefix_of_other_client_is_discarded() {
+        let server = TestServer::start("test");
+        let alice = server.connect("alice");
+        let bob = server.connect("bob");
+        join(&alice, "#rust");
+        join(&bob, "#rust");
+
+        alice.send(":bob PRIVMSG #rust :this is bob");
+
+        bob.expect_none(
+            "the message with the prefix of bob",
+            Duration::from_millis(300),
+            |r| {
+                matches!(
+                    r,
+                    Response::MessageResponse {
+                        response: MessageResponse::ChannelPrivMsg { .. }
+                    }
+                )
+            },
+        );
+    }
+
+    #[test]
+    fn test_connections_beyond_the_limit_per_ip_are_refused() {
+        let server = TestServer::start("test");
+        server.session.config.write().unwrap().connections_per_ip = 2;
+        let alice = server.connect("alice");
+        let _bob = server.connect("bob");
+
+        let refused = TestClient::connect(&server, "carol");
+        refused.send("USER carol host server :carol");
+        refused.expect("the error of too many connections", |r| {
+            matches!(
+                r,
+                Response::ErrorResponse {
+                    response: ErrorResponse::TooManyConnections { .. }
+                }
+            )
+        });
+        assert_eq!(server.session.connections.lock().unwrap()["127.0.0.1"], 2);
+
+        drop(alice);
+        wait_until("the connection of alice to be closed", || {
+            server.session.connections.lock().unwrap()["127.0.0.1"] == 1
+        });
+        server.connect("carol");
+    }
+
+    #[test]
+    fn test_message_that_is_not_valid_utf8_is_answered_with_an_error() {
+        let server = TestServer::start("test");
+        let alice = server.connect("alice");
+
+        alice.send_bytes(b"PRIVMSG bob :caf\xe9");
+        alice.expect("the error of invalid encoding", |r| {
+            matches!(
+                r,
+                Response::ErrorResponse {
+                    response: ErrorResponse::InvalidEncoding
+                }
+            )
+        });
+
+        alice.send("PING still-connected");
+        alice.expect("the pong after the invalid message", |r| {
+            matches!(
+                r,
+                Response::CommandResponse {
+                    response: CommandResponse::Pong { .. }
+                }
+            )
+        });
+    }
+
+    #[test]
+    fn test_connection_that_only_sends_unparseable_messages_is_dropped() {
+        let server = TestServer::start("test");
+        let scanner = TestClient::connect(&server, "scanner");
+
+        for _ in 0..3 {
+            scanner.send("GET / HTTP/1.1");
+            scanner.expect("the error of unknown command", |r| {
+                matches!(
+                    r,
+                    Response::ErrorResponse {
+                        response: ErrorResponse::UnknownCommand { .. }
+                    }
+                )
+            });
+        }
+
+        wait_until("the connection of the scanner to be closed", || {
+            server.session.connections.lock().unwrap().is_empty()
+        });
+        assert!(scanner.read_response().is_none());
+        assert_eq!(
+            server.session.rejected_connections.lock().unwrap()["unparseable first message"],
+            1
+        );
+    }
+
+    #[test]
+    fn test_connection_that_never_finishes_its_registration_is_dropped() {
+        let server = TestServer::start("test");
+        let scanner = TestClient::connect(&server, "scanner");
+
+        scanner.send("NICK scanner");
+        for _ in 0..3 {
+            scanner.send("GET / HTTP/1.1");
+        }
+
+        wait_until("the failed registration to be counted", || {
+            server
+                .session
+                .rejected_connections
+                .lock()
+                .unwrap()
+                .contains_key("failed registration")
+        });
+        wait_until("the connection of the scanner to be closed", || {
+            server.session.connections.lock().unwrap().is_empty()
+        });
+        assert!(!server.session.clients.contains("scanner"));
+    }
+
+    #[test]
+    fn test_filtered_channel_censors_and_blocks_messages() {
+        let server = TestServer::start("test");
+        let alice = server.connect("alice");
+        let bob = server.connect("bob");
+        join(&alice, "#rust");
+        join(&bob, "#rust");
+
+        alice.send("MODE #rust +G");
+        alice.send("FILTER #rust +darn*");
+        alice.send("FILTER #rust +spam BLOCK");
+        alice.expect("the rule of spam in the filter list", |r| {
+            matches!(
+                r,
+                Response::CommandResponse {
+                    response: CommandResponse::FilterList { pattern, .. }
+                } if pattern == "spam"
+            )
+        });
+        bob.send("PRIVMSG #rust :buy spam");
+        bob.expect("the notice of the blocked message", |r| {
+            matches!(
+                r,
+                Response::MessageResponse {
+                    response: MessageResponse::UserNotice { message, .. }
+                } if message.contains("blocked by the filter")
+            )
+        });
+        bob.send("PRIVMSG #rust :darn it");
+
+        let received = alice.expect("the censored message", |r| {
+            matches!(
+                r,
+                Response::MessageResponse {
+                    response: MessageResponse::ChannelPrivMsg { .. }
+                }
+            )
+        });
+        assert!(matches!(
+            received,
+            Response::MessageResponse {
+                response: MessageResponse::ChannelPrivMsg { message, .. }
+            } if message == "**** it"
+        ));
+    }
+
+    fn register_with_password(server: &TestServer, commands: &[&str]) -> TestClient {
+        let client = TestClient::connect(server, "alice");
+        client.send_all(commands);
+        client.expect("the welcome message", |r| {
+            matches!(
+                r,
+                Response::CommandResponse {
+                    response: CommandResponse::Welcome { .. }
+                }
+            )
+        });
+        client
+    }
+
+    #[test]
+    fn test_client_joins_its_auto_join_channels_when_it_registers_again() {
+        let server = TestServer::start("test");
+        let alice = register_with_password(
+            &server,
+            &["PASS secret", "USER alice host server :alice", "NICK alice"],
+        );
+        alice.send("SETAUTOJOIN +#home,#work");
+        alice.expect("the auto-join list", |r| {
+            matches!(
+                r,
+                Response::CommandResponse {
+                    response: CommandResponse::Preference { name, value }
+                } if name == "AUTOJOIN" && value == "#home,#work"
+            )
+        });
+
+        drop(alice);
+        wait_until("alice to be disconnected", || {
+            !server.session.clients.is_connected("alice")
+        });
+        let alice = register_with_password(&server, &["PASS secret", "NICK alice"]);
+
+        alice.expect("the topic of #home", |r| is_topic_of(r, "#home"));
+        alice.expect("the topic of #work", |r| is_topic_of(r, "#work"));
+        assert!(server.session.channels.is_member("#home", "alice"));
+    }
+
+    fn send_websocket_text(socket: &mut TcpStream, text: &str) {
+        let mask = [1u8, 2, 3, 4];
+        let mut frame = vec![0x81, 0x80 | text.len() as u8];
+        frame.extend_from_slice(&mask);
+        frame.extend(text.bytes().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        socket.write_all(&frame).unwrap();
+    }
+
+    fn read_websocket_text(socket: &mut TcpStream) -> String {
+        let mut head = [0u8; 2];
+        socket.read_exact(&mut head).unwrap();
+        assert_eq!(head[0], 0x81);
+        let length = match head[1] {
+            126 => {
+                let mut length = [0u8; 2];
+                socket.read_exact(&mut length).unwrap();
+                u16::from_be_bytes(length) as usize
+            }
+            length => length as usize,
+        };
+        let mut payload = vec![0u8; length];
+        socket.read_exact(&mut payload).unwrap();
+        String::from_utf8(payload).unwrap()
+    }
+
+    #[test]
+    fn test_browser_registers_through_the_websocket_gateway() {
+        let server = TestServer::start("test");
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let bridge = TcpListener::bind("127.0.0.1:0").unwrap();
+        let session = server.session.clone();
+        let network = server.network.clone();
+        thread::spawn(move || {
+            websocket_listen(listener, bridge, session, network, "test".to_string())
+        });
+
+        let mut socket = TcpStream::connect(address).unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .unwrap();
+        socket
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n")
+            .unwrap();
+        let mut handshake = vec![];
+        while !handshake.ends_with(b"\r\n\r\n") {
+            let mut byte = [0u8; 1];
+            socket.read_exact(&mut byte).unwrap();
+            handshake.push(byte[0]);
+        }
+        let handshake = String::from_utf8(handshake).unwrap();
+        assert!(handshake.starts_with("HTTP/1.1 101 "));
+        assert!(handshake.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+
+        send_websocket_text(&mut socket, "USER alice host server :alice\r\nNICK alice");
+        let welcome = read_websocket_text(&mut socket);
+        assert!(welcome.starts_with("001 alice "));
+        while !read_websocket_text(&mut socket).starts_with("366 ") {}
+        assert!(server.session.clients.is_connected("alice"));
+
+        drop(socket);
+        wait_until("alice to be disconnected", || {
+            !server.session.clients.is_connected("alice")
+        });
+        assert!(server.session.connections.lock().unwrap().is_empty());
+    }
+}
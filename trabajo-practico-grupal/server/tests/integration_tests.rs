@@ -1,13 +1,26 @@
 #[cfg(test)]
 mod integration_test {
+    use model::channel_registry::ChannelRegistry;
+    use model::client_registry::ClientRegistry;
+    use model::command_metrics::CommandMetrics;
+    use model::config::ServerConfig;
+    use model::consts::MAX_MSG_SIZE;
+    use model::events::EventBus;
+    use model::gc_stats::GcStats;
+    use model::link_queues::LinkQueues;
     use model::network::Network;
     use model::persistence::PersistenceType;
+    use model::plugins::Plugins;
+    use model::responses::numeric::WireFormat;
+    use model::responses::{replies::CommandResponse, response::Response};
     use model::session::Session;
+    use model::socket_registry::SocketRegistry;
     use model::{message::Message, server::Server};
-    use server::database::handle_database;
+    use server::database::{handle_database, DatabasePaths};
     use server::server_errors::ServerError;
     use server::{client_handler::register_client, message_handler::handle_client_message};
     use std::collections::HashMap;
+    use std::io::Read;
     use std::sync::mpsc::Sender;
     use std::sync::{Arc, Mutex};
     use std::{
@@ -16,14 +29,19 @@ mod integration_test {
     };
 
     fn create_session_for_test(tx: Sender<(PersistenceType, String)>) -> Session {
-        let clients = Arc::new(std::sync::RwLock::new(std::collections::HashMap::new()));
-        let sockets = Arc::new(Mutex::new(HashMap::new()));
-        let channels = Arc::new(std::sync::RwLock::new(HashMap::new()));
         Session {
-            clients,
-            sockets,
-            channels,
+            clients: ClientRegistry::default(),
+            sockets: SocketRegistry::default(),
+            connections: Arc::new(Mutex::new(HashMap::new())),
+            rejected_connections: Arc::new(Mutex::new(HashMap::new())),
+            channels: ChannelRegistry::default(),
             database_sender: tx,
+            config: Arc::new(RwLock::new(ServerConfig::default())),
+            events: EventBus::default(),
+            plugins: Plugins::default(),
+            who_queries: Arc::new(Mutex::new(HashMap::new())),
+            command_metrics: CommandMetrics::default(),
+            gc_stats: GcStats::default(),
         }
     }
 
@@ -31,7 +49,7 @@ mod integration_test {
     fn test_user_sends_msg_to_other_user() {
         let listener = TcpListener::bind("127.0.0.1:8200".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8200".to_string(),
@@ -45,10 +63,11 @@ mod integration_test {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
         //let client = create_client_for_test(&session, "127.0.0.1:8200".to_string(), "nickname".to_string());
         //let client2 = create_client_for_test(&session, "127.0.0.1:8200".to_string(), "nickname2".to_string());
@@ -69,7 +88,7 @@ mod integration_test {
             message_user_receiver,
             (&mut nickname, &mut user_parameters),
             &mut password,
-            client_stream_receiver.clone(),
+            (client_stream_receiver.clone(), WireFormat::Custom),
             &session,
             &network,
             &"test".to_string(),
@@ -79,7 +98,7 @@ mod integration_test {
             message_nick_receiver,
             (&mut nickname, &mut user_parameters),
             &mut password,
-            client_stream_receiver.clone(),
+            (client_stream_receiver.clone(), WireFormat::Custom),
             &session,
             &network,
             &"test".to_string(),
@@ -94,7 +113,7 @@ mod integration_test {
             message_user_sender,
             (&mut nickname, &mut user_parameters),
             &mut password,
-            client_stream_sender.clone(),
+            (client_stream_sender.clone(), WireFormat::Custom),
             &session,
             &network,
             &"test".to_string(),
@@ -104,14 +123,14 @@ mod integration_test {
             message_nick_sender,
             (&mut nickname, &mut user_parameters),
             &mut password,
-            client_stream_sender.clone(),
+            (client_stream_sender.clone(), WireFormat::Custom),
             &session,
             &network,
             &"test".to_string(),
         )
         .is_ok());
 
-        let lock_clients = session.clients.as_ref().write().unwrap();
+        let lock_clients = session.clients.write_all();
 
         assert!(lock_clients.contains_key("sender"));
         assert!(lock_clients.contains_key("receiver"));
@@ -141,7 +160,7 @@ mod integration_test {
     fn test_user_leaves_and_rejoins_channel_but_is_no_longer_operator() {
         let listener = TcpListener::bind("127.0.0.1:8201".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8201".to_string(),
@@ -155,10 +174,11 @@ mod integration_test {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
 
         let mut nickname: Option<String> = Option::None;
@@ -179,7 +199,7 @@ mod integration_test {
             &mut nickname,
             &mut user_parameters,
             &mut password,
-            client1_stream.clone(),
+            (client1_stream.clone(), WireFormat::Custom),
             &session,
             &network,
             &"test".to_string(),
@@ -190,7 +210,7 @@ mod integration_test {
             &mut nickname,
             &mut user_parameters,
             &mut password,
-            client1_stream.clone(),
+            (client1_stream.clone(), WireFormat::Custom),
             &session,
             &network,
             &"test".to_string(),
@@ -207,7 +227,7 @@ mod integration_test {
             &mut nickname,
             &mut user_parameters,
             &mut password,
-            client2_stream.clone(),
+            (client2_stream.clone(), WireFormat::Custom),
             &session,
             &network,
             &"test".to_string(),
@@ -218,7 +238,7 @@ mod integration_test {
             &mut nickname,
             &mut user_parameters,
             &mut password,
-            client2_stream.clone(),
+            (client2_stream.clone(), WireFormat::Custom),
             &session,
             &network,
             &"test".to_string(),
@@ -226,7 +246,7 @@ mod integration_test {
         )
         .is_ok());
 
-        let lock_clients = session.clients.as_ref().write().unwrap();
+        let lock_clients = session.clients.write_all();
 
         assert!(lock_clients.contains_key("operator"));
         assert!(lock_clients.contains_key("user"));
@@ -258,7 +278,7 @@ mod integration_test {
 
         assert!(result.is_ok());
 
-        let lock_channels = session.channels.as_ref().read().unwrap();
+        let lock_channels = session.channels.as_lock().read().unwrap();
         assert!(lock_channels.contains_key("#test"));
         assert!(lock_channels
         .get("#test")
@@ -344,7 +364,7 @@ mod integration_test {
     fn test_user_banned_after_message() {
         let listener = TcpListener::bind("127.0.0.1:8201".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8201".to_string(),
@@ -358,10 +378,11 @@ mod integration_test {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
 
         let mut nickname: Option<String> = Option::None;
@@ -379,7 +400,7 @@ mod integration_test {
             message_user1,
             (&mut nickname, &mut user_parameters),
             &mut password,
-            client1_stream.clone(),
+            (client1_stream.clone(), WireFormat::Custom),
             &session,
             &network,
             &"test".to_string(),
@@ -389,7 +410,7 @@ mod integration_test {
             message_nick1,
             (&mut nickname, &mut user_parameters),
             &mut password,
-            client1_stream.clone(),
+            (client1_stream.clone(), WireFormat::Custom),
             &session,
             &network,
             &"test".to_string(),
@@ -404,7 +425,7 @@ mod integration_test {
             message_user2,
             (&mut nickname, &mut user_parameters),
             &mut password,
-            client2_stream.clone(),
+            (client2_stream.clone(), WireFormat::Custom),
             &session,
             &network,
             &"test".to_string(),
@@ -414,14 +435,14 @@ mod integration_test {
             message_nick2,
             (&mut nickname, &mut user_parameters),
             &mut password,
-            client2_stream.clone(),
+            (client2_stream.clone(), WireFormat::Custom),
             &session,
             &network,
             &"test".to_string(),
         )
         .is_ok());
 
-        let lock_clients = session.clients.as_ref().write().unwrap();
+        let lock_clients = session.clients.write_all();
         assert!(lock_clients.contains_key("user1"));
         assert!(lock_clients.contains_key("user2"));
 
@@ -486,7 +507,7 @@ mod integration_test {
     fn test_users_join_channel_and_send_messages() {
         let listener = TcpListener::bind("127.0.0.1:8202".to_string()).unwrap();
         let arc_network_clients = Arc::new(RwLock::new(HashMap::new()));
-        let arc_servers = Arc::new(RwLock::new(HashMap::<String, u8>::new()));
+        let arc_servers = Arc::new(RwLock::new(HashMap::new()));
         let arc_server = Arc::new(RwLock::new(Server {
             ip: "0.0.0.0".to_string(),
             port: "8202".to_string(),
@@ -500,10 +521,11 @@ mod integration_test {
             server: arc_server,
             servers: arc_servers,
             clients: arc_network_clients,
+            links: LinkQueues::default(),
         };
 
         let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
-        handle_database(db_rx);
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
         let session = create_session_for_test(db_tx);
 
         let mut nickname: Option<String> = Option::None;
@@ -527,7 +549,7 @@ mod integration_test {
             message_user_receiver,
             (&mut nickname, &mut user_parameters),
             &mut password,
-            client_stream_receiver.clone(),
+            (client_stream_receiver.clone(), WireFormat::Custom),
             &session,
             &network,
             &"test".to_string(),
@@ -537,7 +559,7 @@ mod integration_test {
             message_nick_receiver,
             (&mut nickname, &mut user_parameters),
             &mut password,
-            client_stream_receiver.clone(),
+            (client_stream_receiver.clone(), WireFormat::Custom),
             &session,
             &network,
             &"test".to_string(),
@@ -551,7 +573,7 @@ mod integration_test {
             message_user_sender,
             (&mut nickname, &mut user_parameters),
             &mut password,
-            client_stream_sender.clone(),
+            (client_stream_sender.clone(), WireFormat::Custom),
             &session,
             &network,
             &"test".to_string(),
@@ -561,14 +583,14 @@ mod integration_test {
             message_nick_sender,
             (&mut nickname, &mut user_parameters),
             &mut password,
-            client_stream_sender.clone(),
+            (client_stream_sender.clone(), WireFormat::Custom),
             &session,
             &network,
             &"test".to_string(),
         )
         .is_ok());
 
-        let lock_clients = session.clients.as_ref().write().unwrap();
+        let lock_clients = session.clients.write_all();
 
         assert!(lock_clients.contains_key("receiver"));
         assert!(lock_clients.contains_key("sender"));
@@ -596,7 +618,7 @@ mod integration_test {
         assert!(result_join_sender.is_ok());
         assert!(result_join_receiver.is_ok());
 
-        let lock_channels = session.channels.as_ref().read().unwrap();
+        let lock_channels = session.channels.as_lock().read().unwrap();
         assert!(lock_channels.contains_key("#test"));
         assert!(lock_channels
             .get("#test")
@@ -645,4 +667,106 @@ mod integration_test {
         drop(client_stream_receiver);
         drop(listener);
     }
+
+    #[test]
+    fn test_user_receives_welcome_burst_after_registration() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let network = Network {
+            server: Arc::new(RwLock::new(Server {
+                ip: "0.0.0.0".to_string(),
+                port: "0".to_string(),
+                name: "test".to_string(),
+                operators: vec![],
+                father: None,
+                children: HashMap::new(),
+            })),
+            servers: Arc::new(RwLock::new(HashMap::new())),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            links: LinkQueues::default(),
+        };
+
+        let (db_tx, db_rx) = std::sync::mpsc::channel::<(PersistenceType, String)>();
+        handle_database(db_rx, DatabasePaths::temporary().unwrap());
+        let session = create_session_for_test(db_tx);
+        let client_stream = Arc::new(TcpStream::connect(addr).unwrap());
+        let (mut reader, _) = listener.accept().unwrap();
+
+        let message_user = Message::serialize("USER welcome a a a".to_string()).unwrap();
+        let message_nick = Message::serialize("NICK welcome".to_string()).unwrap();
+        let mut nickname: Option<String> = Option::None;
+        let mut user_parameters = Option::None;
+        let mut password = Option::None;
+        for message in [message_user, message_nick] {
+            assert!(register_client(
+                message,
+                (&mut nickname, &mut user_parameters),
+                &mut password,
+                (client_stream.clone(), WireFormat::Custom),
+                &session,
+                &network,
+                &"test".to_string(),
+            )
+            .is_ok());
+        }
+
+        let mut responses = vec![];
+        for _ in 0..5 {
+            let mut buf = vec![0u8; MAX_MSG_SIZE];
+            reader.read_exact(&mut buf).unwrap();
+            let msg = buf.into_iter().take_while(|&x| x != 0).collect::<Vec<_>>();
+            let response = String::from_utf8(msg).unwrap();
+            responses.push(Response::serialize(response).unwrap());
+        }
+
+        match &responses[0] {
+            Response::CommandResponse {
+                response: CommandResponse::Welcome { nickname, .. },
+            } => assert_eq!(nickname, "welcome"),
+            _ => panic!("expected RPL_WELCOME"),
+        }
+        match &responses[1] {
+            Response::CommandResponse {
+                response: CommandResponse::YourHost { servername, .. },
+            } => assert_eq!(servername, "test"),
+            _ => panic!("expected RPL_YOURHOST"),
+        }
+        assert!(matches!(
+            &responses[2],
+            Response::CommandResponse {
+                response: CommandResponse::Created { .. }
+            }
+        ));
+        match &responses[3] {
+            Response::CommandResponse {
+                response:
+                    CommandResponse::MyInfo {
+                        user_modes,
+                        channel_modes,
+                        ..
+                    },
+            } => {
+                assert!(user_modes.contains('i'));
+                assert!(channel_modes.contains('k'));
+            }
+            _ => panic!("expected RPL_MYINFO"),
+        }
+        match &responses[4] {
+            Response::CommandResponse {
+                response: CommandResponse::ISupport { tokens },
+            } => assert_eq!(
+                tokens,
+                &vec![
+                    "NICKLEN=9".to_string(),
+                    "CHANNELLEN=50".to_string(),
+                    "CHANTYPES=#&".to_string(),
+                    "CHANLIMIT=#&:20".to_string(),
+                    "MAXTARGETS=10".to_string(),
+                    "AWAYLEN=200".to_string(),
+                    "SILENCE=15".to_string()
+                ]
+            ),
+            _ => panic!("expected RPL_ISUPPORT"),
+        }
+    }
 }
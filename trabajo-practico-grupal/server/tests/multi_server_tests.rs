@@ -0,0 +1,233 @@
+mod common;
+
+#[cfg(test)]
+mod multi_server_test {
+    use model::responses::{
+        message::MessageResponse, replies::CommandResponse, response::Response,
+    };
+
+    use crate::common::{wait_until, TestClient, TestNetwork, TestServer};
+
+    #[test]
+    fn test_children_are_registered_in_the_father() {
+        let network = TestNetwork::start(2);
+
+        let servers = network.father.network.servers.read().unwrap();
+        assert_eq!(servers.get("child1").map(|server| server.hopcount), Some(1));
+        assert_eq!(servers.get("child2").map(|server| server.hopcount), Some(1));
+        drop(servers);
+        let father = network.father.network.server.read().unwrap();
+        assert!(father.children.contains_key("child1"));
+        assert!(father.children.contains_key("child2"));
+    }
+
+    #[test]
+    fn test_children_learn_about_their_siblings() {
+        let network = TestNetwork::start(2);
+        let child1 = &network.children[0];
+
+        wait_until("child1 to know about child2", || {
+            child1
+                .network
+                .servers
+                .read()
+                .unwrap()
+                .contains_key("child2")
+        });
+        assert!(child1
+            .network
+            .servers
+            .read()
+            .unwrap()
+            .contains_key("father"));
+    }
+
+    #[test]
+    fn test_client_of_a_child_is_known_by_the_network() {
+        let network = TestNetwork::start(2);
+        let _alice = network.children[0].connect("alice");
+
+        wait_until("the father to know alice", || {
+            network
+                .father
+                .network
+                .clients
+                .read()
+                .unwrap()
+                .contains_key("alice")
+        });
+        wait_until("child2 to know alice", || {
+            network.children[1]
+                .network
+                .clients
+                .read()
+                .unwrap()
+                .contains_key("alice")
+        });
+    }
+
+    #[test]
+    fn test_privmsg_reaches_a_client_of_another_server() {
+        let network = TestNetwork::start(1);
+        let alice = network.father.connect("alice");
+        let bob = network.children[0].connect("bob");
+        wait_until("the father to know bob", || {
+            network
+                .father
+                .network
+                .clients
+                .read()
+                .unwrap()
+                .contains_key("bob")
+        });
+
+        alice.send("PRIVMSG bob :hello from the father");
+
+        bob.expect("the message of alice", |r| {
+            matches!(
+                r,
+                Response::MessageResponse {
+                    response: MessageResponse::UserPrivMsg { sender, message }
+                } if sender == "alice" && message == "hello from the father"
+            )
+        });
+    }
+
+    #[test]
+    fn test_distributed_channel_is_shared_by_the_servers() {
+        let network = TestNetwork::start(1);
+        let alice = network.father.connect("alice");
+        let bob = network.children[0].connect("bob");
+
+        alice.send("JOIN #rust");
+        alice.expect("the topic of #rust", |r| {
+            matches!(
+                r,
+                Response::CommandResponse {
+                    response: CommandResponse::Topic { channel, .. }
+                } if channel == "#rust"
+            )
+        });
+        wait_until("the child to know #rust", || {
+            network.children[0].session.channels.contains("#rust")
+        });
+        bob.send("JOIN #rust");
+        wait_until("the father to see bob in #rust", || {
+            network.father.session.channels.is_member("#rust", "bob")
+        });
+
+        bob.send("PRIVMSG #rust :hello channel");
+
+        alice.expect("the message of bob in #rust", |r| {
+            matches!(
+                r,
+                Response::MessageResponse {
+                    response: MessageResponse::ChannelPrivMsg { channel, sender, message }
+                } if channel == "#rust" && sender == "bob" && message == "hello channel"
+            )
+        });
+    }
+
+    #[test]
+    fn test_whois_of_a_client_of_another_server() {
+        let network = TestNetwork::start(2);
+        let alice = network.children[0].connect("alice");
+        let _bob = network.children[1].connect("bob");
+        wait_until("child1 to know bob", || {
+            network.children[0]
+                .network
+                .clients
+                .read()
+                .unwrap()
+                .contains_key("bob")
+        });
+
+        alice.send("WHOIS bob");
+
+        alice.expect("the user of bob", |r| {
+            matches!(
+                r,
+                Response::CommandResponse {
+                    response: CommandResponse::WhoIsUser { nickname, username, .. }
+                } if nickname == "bob" && username == "bob"
+            )
+        });
+        alice.expect("the end of the WHOIS", |r| {
+            matches!(
+                r,
+                Response::CommandResponse {
+                    response: CommandResponse::EndOfWhoIs
+                }
+            )
+        });
+    }
+
+    #[test]
+    fn test_who_matches_the_username_of_a_client_of_another_server() {
+        let network = TestNetwork::start(1);
+        let alice = network.father.connect("alice");
+        let carol = TestClient::connect(&network.children[0], "carol");
+        carol.send_all(&["USER cuser host server :Carol", "NICK carol"]);
+        carol.expect("the welcome message", |r| {
+            matches!(
+                r,
+                Response::CommandResponse {
+                    response: CommandResponse::Welcome { .. }
+                }
+            )
+        });
+        wait_until("the father to know carol", || {
+            network
+                .father
+                .network
+                .clients
+                .read()
+                .unwrap()
+                .contains_key("carol")
+        });
+
+        alice.send("WHO cuser");
+
+        alice.expect("carol in the WHO reply", |r| {
+            matches!(
+                r,
+                Response::CommandResponse {
+                    response: CommandResponse::WhoReply { users }
+                } if users == &vec!["carol".to_string()]
+            )
+        });
+        alice.expect("the end of the WHO", |r| {
+            matches!(
+                r,
+                Response::CommandResponse {
+                    response: CommandResponse::EndOfWho
+                }
+            )
+        });
+    }
+
+    #[test]
+    fn test_the_user_information_of_the_clients_reaches_the_other_servers() {
+        let father = TestServer::start("father");
+        let _alice = father.connect("alice");
+        let child = TestServer::start_child("child1", &father);
+        let _bob = child.connect("bob");
+
+        let knows = |server: &TestServer, nickname: &str| {
+            server
+                .network
+                .clients
+                .read()
+                .unwrap()
+                .get(nickname)
+                .map(|client| (client.username.to_owned(), client.realname.to_owned()))
+                == Some((nickname.to_string(), nickname.to_string()))
+        };
+        wait_until("the child to know the user of alice", || {
+            knows(&child, "alice")
+        });
+        wait_until("the father to know the user of bob", || {
+            knows(&father, "bob")
+        });
+    }
+}